@@ -13,7 +13,13 @@ pub const SLOTS_PER_HOUR: u64 = SLOTS_PER_MINUTE * 60;
 pub const SLOTS_PER_DAY: u64 = SLOTS_PER_HOUR * 24;
 pub const SLOTS_PER_YEAR: u64 = SLOTS_PER_DAY * 365;
 
-pub const PROGRAM_VERSION: u8 = 1;
+/// Largest growth a single interest accrual applies to the debt, the third order
+/// approximation of the compounded rate drifts too far from the exact value beyond it
+pub const MAX_COMPOUNDED_INTEREST_PER_ACCRUAL: Fraction = fraction!(2);
+
+/// Reserves of any other version fail the lending checks, refreshes and price pushes until the
+/// market owner runs `migrate_reserve` on them, every deployed version 1 reserve included
+pub const PROGRAM_VERSION: u8 = 2;
 
 pub const FULL_BPS: u16 = 10_000;
 
@@ -28,17 +34,31 @@ pub const LIQUIDATION_CLOSE_VALUE: u64 = 2;
 
 pub const MAX_LIQUIDATABLE_VALUE_AT_ONCE: u64 = 500_000;
 
+pub const MAX_LIQUIDATION_BATCH_OBLIGATIONS: usize = 5;
+
 pub const MIN_AUTODELEVERAGE_BONUS_BPS: u64 = 50;
 
 pub const MAX_OBLIGATION_RESERVES: u64 = 20;
 
 pub const CLOSE_TO_INSOLVENCY_RISKY_LTV: u8 = 95;
 
+pub const ELEVATION_GROUP_NONE: u8 = 0;
+
+pub const MAX_NUM_ELEVATION_GROUPS: u8 = 8;
+
+pub const DEFAULT_MAX_CONFIDENCE_PCT: u64 = 2;
+
+pub const MAX_CONFIDENCE_PCT: u64 = 50;
+
 pub const NULL_PUBKEY: pubkey::Pubkey = solana_program::pubkey::Pubkey::new_from_array([
     11, 193, 238, 216, 208, 116, 241, 195, 55, 212, 76, 22, 75, 202, 40, 216, 76, 206, 27, 169,
     138, 64, 177, 28, 19, 90, 156, 0, 0, 0, 0, 0,
 ]);
 
+pub const UTILIZATION_SNAPSHOTS_LEN: usize = 12;
+
+pub const UTILIZATION_SNAPSHOT_INTERVAL_SLOTS: u64 = 9_000;
+
 pub const LENDING_MARKET_SIZE: usize = 4656;
 pub const RESERVE_SIZE: usize = 8616;
 pub const OBLIGATION_SIZE: usize = 1936;
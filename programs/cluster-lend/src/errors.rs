@@ -152,6 +152,74 @@ pub enum LendingError {
     ReserveVaultBalanceMismatch,
     #[msg("Reserve internal state accounting has been unexpectedly modified")]
     ReserveAccountingMismatch,
+    #[msg("Elevation group is invalid or not configured")]
+    InvalidElevationGroup,
+    #[msg("Reserve does not belong to the obligation elevation group")]
+    InconsistentElevationGroup,
+    #[msg("Emergency mode is not enabled")]
+    EmergencyModeNotEnabled,
+    #[msg("Senior debt must be liquidated before subordinated debt")]
+    SeniorDebtOutstanding,
+    #[msg("Lending market requires a liquidation bond account")]
+    LiquidationBondRequired,
+    #[msg("Liquidation bond account does not match the obligation and liquidator")]
+    InvalidLiquidationBondAccount,
+    #[msg("Liquidation bond challenge window has not elapsed")]
+    LiquidationBondChallengeWindowActive,
+    #[msg("Liquidation bond challenge window has elapsed")]
+    LiquidationBondChallengeWindowElapsed,
+    #[msg("Obligation debt is below the full liquidation threshold, the whole borrow must be liquidated")]
+    FullLiquidationRequired,
+    #[msg("Aggressive mode LTV boost is disabled or above the market bound")]
+    InvalidAggressiveMode,
+    #[msg("Aggressive mode was changed too recently")]
+    AggressiveModeCooldown,
+    #[msg("Autodeleverage target LTV is invalid or not set")]
+    InvalidAutodeleverageTarget,
+    #[msg("Autodeleverage target LTV cannot be reached with the obligation's reserves")]
+    AutodeleverageTargetUnreachable,
+    #[msg("Obligation total value would exceed the market maximum")]
+    ObligationValueCapExceeded,
+    #[msg("Liquidation amount is above the close factor or the market maximum")]
+    LiquidationTooLarge,
+    #[msg("Reserve is already at the current program version")]
+    ReserveAlreadyMigrated,
+    #[msg("Deposits are disabled on this reserve")]
+    ReserveDepositsDisabled,
+    #[msg("Protocol fee destination is not owned by the reserve fee receiver")]
+    InvalidProtocolFeeReceiver,
+    #[msg("Borrow factor override cannot be below 100%")]
+    InvalidBorrowFactorOverride,
+    #[msg("Initial deposit is below the reserve minimum")]
+    InitialDepositTooSmall,
+    #[msg("Deposit would exceed the market global deposit value cap")]
+    GlobalDepositValueExceeded,
+    #[msg("Obligation owner cannot liquidate their own obligation")]
+    SelfLiquidationForbidden,
+    #[msg("Deposits are disabled on this market")]
+    DepositsDisabled,
+    #[msg("Deposits order must be a permutation of the obligation deposit slots")]
+    InvalidDepositsOrder,
+    #[msg("Price too divergent from the fallback oracle price")]
+    PriceTooDivergentFromFallback,
+    #[msg("Reserve is priced by an oracle, its price can't be pushed")]
+    PricePushNotEnabled,
+    #[msg("Price authority is invalid")]
+    InvalidPriceAuthority,
+    #[msg("Collateral cannot be withdrawn before the deposit hold period has elapsed")]
+    DepositHoldPeriodNotElapsed,
+    #[msg("Risk authority can only tighten the reserve or market config")]
+    RiskAuthorityUpdateNotAllowed,
+    #[msg("Borrow would exceed the market isolated debt borrow value cap")]
+    IsolatedDebtBorrowValueExceeded,
+    #[msg("Reserve must be hidden for this operation")]
+    ReserveNotHidden,
+    #[msg("User LTV cap must be below 100%")]
+    InvalidUserLtvCap,
+    #[msg("Reserve routes liquidation fees to insurance but its insurance vault was not provided")]
+    InsuranceVaultRequired,
+    #[msg("Obligation reserve allowlists can only be set while it has no deposits or borrows")]
+    ObligationNotEmpty,
 }
 
 pub type LendingResult<T = ()> = std::result::Result<T, LendingError>;
@@ -0,0 +1,20 @@
+use anchor_lang::prelude::*;
+
+/// Emitted on each interest compounding step that grows a reserve's debt.
+/// A market holds a single reserve per liquidity mint, so the pair identifies the reserve.
+#[event]
+pub struct ProtocolFeeAccruedEvent {
+    pub lending_market: Pubkey,
+    pub liquidity_mint: Pubkey,
+    pub net_new_debt_sf: u128,
+    pub protocol_fee_sf: u128,
+}
+
+/// Emitted for each obligation of a batched liquidation, skipped ones report zero amounts
+#[event]
+pub struct BatchLiquidationResultEvent {
+    pub obligation: Pubkey,
+    pub liquidated: bool,
+    pub repay_amount: u64,
+    pub withdraw_collateral_amount: u64,
+}
@@ -0,0 +1,48 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    errors::LendingError,
+    state::{InitObligationAllowlistParams, LendingMarket, ObligationAllowlist},
+    utils::seeds,
+    xmsg,
+};
+
+pub fn process_allow_obligation_owner(ctx: Context<AllowObligationOwnerCtx>) -> Result<()> {
+    let obligation_allowlist = &mut ctx.accounts.obligation_allowlist.load_init()?;
+    obligation_allowlist.init(InitObligationAllowlistParams {
+        lending_market: ctx.accounts.lending_market.key(),
+        owner: ctx.accounts.allowed_owner.key(),
+        bump: ctx.bumps.obligation_allowlist,
+    });
+
+    xmsg!(
+        "Allowed {} to open obligations",
+        ctx.accounts.allowed_owner.key()
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct AllowObligationOwnerCtx<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        has_one = owner @ LendingError::InvalidMarketOwner,
+    )]
+    pub lending_market: AccountLoader<'info, LendingMarket>,
+
+    /// CHECK: any account can be allowed to own obligations
+    pub allowed_owner: AccountInfo<'info>,
+
+    #[account(init,
+        seeds = [seeds::OBLIGATION_ALLOWLIST, lending_market.key().as_ref(), allowed_owner.key().as_ref()],
+        bump,
+        payer = owner,
+        space = 8 + std::mem::size_of::<ObligationAllowlist>(),
+    )]
+    pub obligation_allowlist: AccountLoader<'info, ObligationAllowlist>,
+
+    pub system_program: Program<'info, System>,
+}
@@ -0,0 +1,50 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    lending_market::lending_operations,
+    state::{LendingMarket, Reserve, ReserveConfigTemplate},
+    xmsg,
+};
+
+pub fn process_apply_reserve_config_template(
+    ctx: Context<ApplyReserveConfigTemplateCtx>,
+) -> Result<()> {
+    let template = &ctx.accounts.reserve_config_template.load()?;
+    let reserve = &mut ctx.accounts.reserve.load_mut()?;
+
+    xmsg!(
+        "Applying reserve config template {:?} to reserve {:?} {}",
+        ctx.accounts.reserve_config_template.key(),
+        ctx.accounts.reserve.key(),
+        reserve.config.token_info.symbol(),
+    );
+
+    let clock = Clock::get()?;
+    lending_operations::refresh_reserve(reserve, &clock, None)?;
+
+    template.apply(&mut reserve.config);
+
+    lending_operations::utils::validate_reserve_config(&reserve.config)?;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ApplyReserveConfigTemplateCtx<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        has_one = owner
+    )]
+    pub lending_market: AccountLoader<'info, LendingMarket>,
+
+    #[account(mut,
+        has_one = lending_market
+    )]
+    pub reserve: AccountLoader<'info, Reserve>,
+
+    #[account(
+        has_one = lending_market
+    )]
+    pub reserve_config_template: AccountLoader<'info, ReserveConfigTemplate>,
+}
@@ -12,13 +12,13 @@ use crate::{
     lending_market::{lending_checks, lending_operations},
     state::{LendingMarket, Reserve},
     utils::{seeds, token_transfer},
-    xmsg, CalculateBorrowResult, LendingAction, Obligation,
+    xmsg, BorrowObligationLiquidityResult, CalculateBorrowResult, LendingAction, Obligation,
 };
 
 pub fn process_borrow_obligation_liquidity(
     ctx: Context<BorrowObligationLiquidityCtx>,
     liquidity_amount: u64,
-) -> Result<()> {
+) -> Result<BorrowObligationLiquidityResult> {
     // check_refresh_ixs!(ctx, borrow_reserve);
     lending_checks::borrow_obligation_liquidity_checks(&ctx)?;
 
@@ -33,12 +33,13 @@ pub fn process_borrow_obligation_liquidity(
 
     let initial_reserve_token_balance =
         token::accessor::amount(&ctx.accounts.reserve_source_liquidity.to_account_info())?;
+    borrow_reserve.reconcile_rebasing_vault_balance(initial_reserve_token_balance)?;
     let initial_reserve_available_liquidity = borrow_reserve.liquidity.available_amount;
 
     let CalculateBorrowResult {
+        borrow_amount_f,
         receive_amount,
         borrow_fee,
-        ..
     } = lending_operations::borrow_obligation_liquidity(
         lending_market,
         borrow_reserve,
@@ -47,10 +48,14 @@ pub fn process_borrow_obligation_liquidity(
         clock,
         ctx.accounts.borrow_reserve.key(),
     )?;
+    lending_operations::update_reserve_isolated_debt_value(lending_market, borrow_reserve, true)?;
 
     xmsg!("pnl: Borrow obligation liquidity {receive_amount} with borrow_fee {borrow_fee}",);
 
-    if borrow_fee > 0 {
+    let (protocol_fee, host_fee) = lending_market
+        .calculate_borrow_fee_split(borrow_fee, ctx.accounts.host_fee_receiver.is_some());
+
+    if protocol_fee > 0 {
         token_transfer::send_origination_fees_transfer(
             ctx.accounts.token_program.to_account_info(),
             ctx.accounts.reserve_source_liquidity.to_account_info(),
@@ -59,10 +64,24 @@ pub fn process_borrow_obligation_liquidity(
                 .to_account_info(),
             ctx.accounts.lending_market_authority.to_account_info(),
             authority_signer_seeds,
-            borrow_fee,
+            protocol_fee,
         )?;
     }
 
+    if let Some(host_fee_receiver) = &ctx.accounts.host_fee_receiver {
+        if host_fee > 0 {
+            xmsg!("Sending host fee {host_fee} to {}", host_fee_receiver.key());
+            token_transfer::send_origination_fees_transfer(
+                ctx.accounts.token_program.to_account_info(),
+                ctx.accounts.reserve_source_liquidity.to_account_info(),
+                host_fee_receiver.to_account_info(),
+                ctx.accounts.lending_market_authority.to_account_info(),
+                authority_signer_seeds,
+                host_fee,
+            )?;
+        }
+    }
+
     token_transfer::borrow_obligation_liquidity_transfer(
         ctx.accounts.token_program.to_account_info(),
         ctx.accounts.reserve_source_liquidity.to_account_info(),
@@ -80,7 +99,11 @@ pub fn process_borrow_obligation_liquidity(
         LendingAction::Subtractive(borrow_fee + receive_amount),
     )?;
 
-    Ok(())
+    Ok(BorrowObligationLiquidityResult {
+        borrow_amount_sf: borrow_amount_f.to_bits(),
+        receive_amount,
+        borrow_fee,
+    })
 }
 
 #[derive(Accounts)]
@@ -128,4 +151,9 @@ pub struct BorrowObligationLiquidityCtx<'info> {
     /// CHECK: instruction_sysvar account
     #[account(address = SysInstructions::id())]
     pub instruction_sysvar_account: AccountInfo<'info>,
+
+    #[account(mut,
+        token::mint = reserve_source_liquidity.mint
+    )]
+    pub host_fee_receiver: Option<Box<Account<'info, TokenAccount>>>,
 }
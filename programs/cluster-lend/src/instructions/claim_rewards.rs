@@ -0,0 +1,85 @@
+use anchor_lang::{prelude::*, Accounts};
+use anchor_spl::token::{Token, TokenAccount};
+
+use crate::{
+    errors::LendingError,
+    gen_signer_seeds,
+    lending_market::lending_operations,
+    state::{LendingMarket, Obligation, Reserve},
+    utils::{seeds, token_transfer, FatAccountLoader},
+    xmsg,
+};
+
+pub fn process_claim_rewards(ctx: Context<ClaimRewardsCtx>) -> Result<()> {
+    let lending_market = ctx.accounts.lending_market.load()?;
+    let lending_market_key = ctx.accounts.lending_market.key();
+    let obligation = &mut ctx.accounts.obligation.load_mut()?;
+    let deposits_count = obligation.deposits_count();
+
+    if ctx.remaining_accounts.iter().len() != deposits_count {
+        xmsg!("expected_remaining_accounts={}", deposits_count);
+        return err!(LendingError::InvalidAccountInput);
+    }
+
+    let reserves_iter = ctx
+        .remaining_accounts
+        .iter()
+        .map(|account_info| FatAccountLoader::<Reserve>::try_from(account_info).unwrap());
+
+    let claim_amount = lending_operations::claim_obligation_rewards(
+        obligation,
+        ctx.accounts.reward_vault.amount,
+        reserves_iter,
+    )?;
+
+    xmsg!("Claiming rewards: {}", claim_amount);
+
+    if claim_amount > 0 {
+        let authority_signer_seeds =
+            gen_signer_seeds!(lending_market_key, lending_market.bump as u8);
+
+        token_transfer::claim_rewards_transfer(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.reward_vault.to_account_info(),
+            ctx.accounts.user_destination_reward.to_account_info(),
+            ctx.accounts.lending_market_authority.to_account_info(),
+            authority_signer_seeds,
+            claim_amount,
+        )?;
+    }
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ClaimRewardsCtx<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(mut,
+        has_one = lending_market,
+        has_one = owner
+    )]
+    pub obligation: AccountLoader<'info, Obligation>,
+
+    pub lending_market: AccountLoader<'info, LendingMarket>,
+
+    /// CHECK: market authority PDA
+    #[account(
+        seeds = [seeds::LENDING_MARKET_AUTH, lending_market.key().as_ref()],
+        bump = lending_market.load()?.bump as u8,
+    )]
+    pub lending_market_authority: AccountInfo<'info>,
+
+    #[account(mut,
+        address = lending_market.load()?.reward_vault,
+        token::authority = lending_market_authority,
+    )]
+    pub reward_vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut,
+        token::mint = reward_vault.mint,
+    )]
+    pub user_destination_reward: Box<Account<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+}
@@ -0,0 +1,37 @@
+use anchor_lang::prelude::*;
+
+use crate::{xmsg, LendingMarket, LiquidationBond};
+
+pub fn process_contest_liquidation_bond(ctx: Context<ContestLiquidationBondCtx>) -> Result<()> {
+    let lending_market = &ctx.accounts.lending_market.load()?;
+    let liquidation_bond = &ctx.accounts.liquidation_bond.load()?;
+    let clock = Clock::get()?;
+
+    liquidation_bond.check_contestable(
+        clock.unix_timestamp,
+        lending_market.liquidation_bond_challenge_window_secs,
+    )?;
+
+    xmsg!(
+        "Forfeiting liquidation bond {} of liquidator {}",
+        liquidation_bond.amount,
+        liquidation_bond.liquidator
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ContestLiquidationBondCtx<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(has_one = owner)]
+    pub lending_market: AccountLoader<'info, LendingMarket>,
+
+    #[account(mut,
+        has_one = lending_market,
+        close = owner,
+    )]
+    pub liquidation_bond: AccountLoader<'info, LiquidationBond>,
+}
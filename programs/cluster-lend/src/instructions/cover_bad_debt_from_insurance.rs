@@ -0,0 +1,96 @@
+use anchor_lang::{prelude::*, Accounts};
+use anchor_spl::token::{self, Token, TokenAccount};
+
+use crate::{
+    errors::LendingError,
+    gen_signer_seeds,
+    lending_market::{
+        cover_bad_debt_from_insurance, post_transfer_vault_balance_liquidity_reserve_checks,
+        refresh_reserve,
+    },
+    state::{LendingAction, LendingMarket, Reserve},
+    utils::{seeds, token_transfer},
+    xmsg,
+};
+
+pub fn process_cover_bad_debt_from_insurance(
+    ctx: Context<CoverBadDebtFromInsuranceCtx>,
+    liquidity_amount: u64,
+) -> Result<()> {
+    let lending_market = ctx.accounts.lending_market.load()?;
+    let reserve = &mut ctx.accounts.reserve.load_mut()?;
+    let clock = &Clock::get()?;
+
+    refresh_reserve(reserve, clock, None)?;
+
+    let initial_reserve_token_balance =
+        token::accessor::amount(&ctx.accounts.reserve_liquidity_supply.to_account_info())?;
+    reserve.reconcile_rebasing_vault_balance(initial_reserve_token_balance)?;
+    let initial_reserve_available_liquidity = reserve.liquidity.available_amount;
+    let cover_amount = cover_bad_debt_from_insurance(
+        reserve,
+        clock,
+        liquidity_amount,
+        ctx.accounts.insurance_vault.amount,
+    )?;
+
+    xmsg!(
+        "pnl: Covering bad debt of reserve {:?} with insurance {}",
+        ctx.accounts.reserve.key(),
+        cover_amount
+    );
+
+    let lending_market_key = ctx.accounts.lending_market.key();
+    let authority_signer_seeds =
+        gen_signer_seeds!(lending_market_key.as_ref(), lending_market.bump as u8);
+
+    token_transfer::cover_bad_debt_from_insurance_transfer(
+        ctx.accounts.token_program.to_account_info(),
+        ctx.accounts.insurance_vault.to_account_info(),
+        ctx.accounts.reserve_liquidity_supply.to_account_info(),
+        ctx.accounts.lending_market_authority.to_account_info(),
+        authority_signer_seeds,
+        cover_amount,
+    )?;
+
+    post_transfer_vault_balance_liquidity_reserve_checks(
+        token::accessor::amount(&ctx.accounts.reserve_liquidity_supply.to_account_info())?,
+        reserve.liquidity.available_amount,
+        initial_reserve_token_balance,
+        initial_reserve_available_liquidity,
+        LendingAction::Additive(cover_amount),
+    )?;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct CoverBadDebtFromInsuranceCtx<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(has_one = owner @ LendingError::InvalidMarketOwner)]
+    pub lending_market: AccountLoader<'info, LendingMarket>,
+
+    #[account(mut,
+        has_one = lending_market
+    )]
+    pub reserve: AccountLoader<'info, Reserve>,
+
+    /// CHECK: market authority PDA
+    #[account(
+        seeds = [seeds::LENDING_MARKET_AUTH, lending_market.key().as_ref()],
+        bump = lending_market.load()?.bump as u8,
+    )]
+    pub lending_market_authority: AccountInfo<'info>,
+
+    #[account(mut, address = reserve.load()?.liquidity.supply_vault)]
+    pub reserve_liquidity_supply: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut,
+        address = reserve.load()?.liquidity.insurance_vault @ LendingError::InvalidAccountInput,
+        token::authority = lending_market_authority,
+    )]
+    pub insurance_vault: Box<Account<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+}
@@ -37,9 +37,11 @@ pub fn process_deposit_liquidity_collateral(
 
     let initial_reserve_token_balance =
         token::accessor::amount(&ctx.accounts.reserve_liquidity_supply.to_account_info())?;
+    reserve.reconcile_rebasing_vault_balance(initial_reserve_token_balance)?;
     let initial_reserve_available_liquidity = reserve.liquidity.available_amount;
     let collateral_amount =
         lending_operations::deposit_reserve_liquidity(reserve, &clock, liquidity_amount)?;
+    lending_operations::update_reserve_deposit_value(lending_market, reserve, true)?;
 
     lending_operations::refresh_reserve(reserve, &clock, None)?;
 
@@ -9,6 +9,7 @@ use crate::{
     errors::LendingError, gen_signer_seeds, lending_market::{
         deposit_reserve_liquidity, lending_checks,
         post_transfer_vault_balance_liquidity_reserve_checks, refresh_reserve,
+        update_reserve_deposit_value,
     }, state::{LendingAction, LendingMarket, Reserve}, utils::{seeds, token_transfer}, xmsg, DepositReserveLiquidityAccounts
 };
 
@@ -42,8 +43,10 @@ pub fn process_deposit_reserve_liquidity(
 
     let initial_reserve_token_balance =
         token::accessor::amount(&ctx.accounts.reserve_liquidity_supply.to_account_info())?;
+    reserve.reconcile_rebasing_vault_balance(initial_reserve_token_balance)?;
     let initial_reserve_available_liquidity = reserve.liquidity.available_amount;
     let collateral_amount = deposit_reserve_liquidity(reserve, &clock, liquidity_amount)?;
+    update_reserve_deposit_value(lending_market, reserve, true)?;
 
     xmsg!(
         "pnl: Depositing in reserve {:?} liquidity {}",
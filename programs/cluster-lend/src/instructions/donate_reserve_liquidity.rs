@@ -0,0 +1,76 @@
+use anchor_lang::{prelude::*, Accounts};
+use anchor_spl::token::{self, Token, TokenAccount};
+
+use crate::{
+    errors::LendingError,
+    lending_market::{
+        donate_reserve_liquidity, post_transfer_vault_balance_liquidity_reserve_checks,
+        refresh_reserve,
+    },
+    state::{LendingAction, LendingMarket, Reserve},
+    utils::token_transfer,
+    xmsg,
+};
+
+pub fn process_donate_reserve_liquidity(
+    ctx: Context<DonateReserveLiquidityCtx>,
+    liquidity_amount: u64,
+) -> Result<()> {
+    let reserve = &mut ctx.accounts.reserve.load_mut()?;
+    let clock = &Clock::get()?;
+
+    refresh_reserve(reserve, clock, None)?;
+
+    let initial_reserve_token_balance =
+        token::accessor::amount(&ctx.accounts.reserve_liquidity_supply.to_account_info())?;
+    reserve.reconcile_rebasing_vault_balance(initial_reserve_token_balance)?;
+    let initial_reserve_available_liquidity = reserve.liquidity.available_amount;
+    donate_reserve_liquidity(reserve, clock, liquidity_amount)?;
+
+    xmsg!(
+        "pnl: Donating to reserve {:?} liquidity {}",
+        ctx.accounts.reserve.key(),
+        liquidity_amount
+    );
+
+    token_transfer::donate_reserve_liquidity_transfer(
+        ctx.accounts.token_program.to_account_info(),
+        ctx.accounts.owner_source_liquidity.to_account_info(),
+        ctx.accounts.reserve_liquidity_supply.to_account_info(),
+        ctx.accounts.owner.to_account_info(),
+        liquidity_amount,
+    )?;
+
+    post_transfer_vault_balance_liquidity_reserve_checks(
+        token::accessor::amount(&ctx.accounts.reserve_liquidity_supply.to_account_info())?,
+        reserve.liquidity.available_amount,
+        initial_reserve_token_balance,
+        initial_reserve_available_liquidity,
+        LendingAction::Additive(liquidity_amount),
+    )?;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct DonateReserveLiquidityCtx<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(has_one = owner @ LendingError::InvalidMarketOwner)]
+    pub lending_market: AccountLoader<'info, LendingMarket>,
+
+    #[account(mut,
+        has_one = lending_market
+    )]
+    pub reserve: AccountLoader<'info, Reserve>,
+
+    #[account(mut, address = reserve.load()?.liquidity.supply_vault)]
+    pub reserve_liquidity_supply: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut,
+        token::mint = reserve_liquidity_supply.mint
+    )]
+    pub owner_source_liquidity: Box<Account<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+}
@@ -21,6 +21,7 @@ pub fn process_flash_borrow_reserve(
 
     let initial_reserve_token_balance =
         token::accessor::amount(&ctx.accounts.reserve_source_liquidity.to_account_info())?;
+    reserve.reconcile_rebasing_vault_balance(initial_reserve_token_balance)?;
     let initial_reserve_available_liquidity = reserve.liquidity.available_amount;
 
     flash_ixs::flash_borrow_checks(&ctx, liquidity_amount)?;
@@ -84,4 +85,6 @@ pub struct FlashBorrowReserveCtx<'info> {
     #[account(address = sysvar::instructions::ID)]
     pub sysvar_info: AccountInfo<'info>,
     pub token_program: Program<'info, Token>,
+
+    pub referrer_token_account: Option<Box<Account<'info, TokenAccount>>>,
 }
@@ -19,15 +19,17 @@ pub fn process_flash_repay_reserve(
 
     let initial_reserve_token_balance =
         token::accessor::amount(&ctx.accounts.reserve_destination_liquidity.to_account_info())?;
+    reserve.reconcile_rebasing_vault_balance(initial_reserve_token_balance)?;
     let initial_reserve_available_liquidity = reserve.liquidity.available_amount;
 
     flash_ixs::flash_repay_checks(&ctx, borrow_instruction_index, liquidity_amount)?;
 
-    let (flash_loan_amount, reserve_origination_fee) =
+    let (flash_loan_amount, reserve_origination_fee, referral_fee) =
         lending_operations::flash_repay_reserve_liquidity(
             reserve,
             liquidity_amount,
             Clock::get()?.slot,
+            ctx.accounts.referrer_token_account.is_some(),
         )?;
 
     token_transfer::repay_obligation_liquidity_transfer(
@@ -50,6 +52,20 @@ pub fn process_flash_repay_reserve(
         )?;
     }
 
+    if referral_fee > 0 {
+        token_transfer::pay_borrowing_fees_transfer(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.user_source_liquidity.to_account_info(),
+            ctx.accounts
+                .referrer_token_account
+                .as_ref()
+                .unwrap()
+                .to_account_info(),
+            ctx.accounts.user_transfer_authority.to_account_info(),
+            referral_fee,
+        )?;
+    }
+
     lending_checks::post_transfer_vault_balance_liquidity_reserve_checks(
         token::accessor::amount(&ctx.accounts.reserve_destination_liquidity.to_account_info())
             .unwrap(),
@@ -97,4 +113,9 @@ pub struct FlashRepayReserveCtx<'info> {
     #[account(address = sysvar::instructions::ID)]
     pub sysvar_info: AccountInfo<'info>,
     pub token_program: Program<'info, Token>,
+
+    #[account(mut,
+        token::mint = reserve.load()?.liquidity.mint_pubkey
+    )]
+    pub referrer_token_account: Option<Box<Account<'info, TokenAccount>>>,
 }
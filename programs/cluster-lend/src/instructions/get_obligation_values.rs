@@ -0,0 +1,19 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    lending_market::lending_operations,
+    state::{GetObligationValuesResult, Obligation},
+};
+
+pub fn process_get_obligation_values(
+    ctx: Context<GetObligationValuesCtx>,
+) -> Result<GetObligationValuesResult> {
+    let obligation = &ctx.accounts.obligation.load()?;
+
+    Ok(lending_operations::get_obligation_values(obligation))
+}
+
+#[derive(Accounts)]
+pub struct GetObligationValuesCtx<'info> {
+    pub obligation: AccountLoader<'info, Obligation>,
+}
@@ -0,0 +1,17 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    lending_market::lending_operations,
+    state::{GetReserveApyResult, Reserve},
+};
+
+pub fn process_get_reserve_apy(ctx: Context<GetReserveApyCtx>) -> Result<GetReserveApyResult> {
+    let reserve = &ctx.accounts.reserve.load()?;
+
+    lending_operations::get_reserve_apy(reserve)
+}
+
+#[derive(Accounts)]
+pub struct GetReserveApyCtx<'info> {
+    pub reserve: AccountLoader<'info, Reserve>,
+}
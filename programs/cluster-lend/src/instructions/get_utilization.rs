@@ -0,0 +1,17 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    lending_market::lending_operations,
+    state::{GetUtilizationResult, Reserve},
+};
+
+pub fn process_get_utilization(ctx: Context<GetUtilizationCtx>) -> Result<GetUtilizationResult> {
+    let reserve = &ctx.accounts.reserve.load()?;
+
+    lending_operations::get_utilization(reserve)
+}
+
+#[derive(Accounts)]
+pub struct GetUtilizationCtx<'info> {
+    pub reserve: AccountLoader<'info, Reserve>,
+}
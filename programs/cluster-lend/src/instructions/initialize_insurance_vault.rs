@@ -0,0 +1,61 @@
+use anchor_lang::{prelude::*, Accounts};
+use anchor_spl::token::{Mint, Token, TokenAccount};
+
+use crate::{
+    errors::LendingError,
+    state::{LendingMarket, Reserve},
+    utils::seeds,
+    xmsg,
+};
+
+pub fn process_initialize_insurance_vault(ctx: Context<InitializeInsuranceVaultCtx>) -> Result<()> {
+    let reserve = &mut ctx.accounts.reserve.load_mut()?;
+    let insurance_vault = ctx.accounts.insurance_vault.key();
+
+    xmsg!(
+        "Initializing insurance vault {} of reserve {}",
+        insurance_vault,
+        reserve.token_symbol()
+    );
+
+    reserve.liquidity.insurance_vault = insurance_vault;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct InitializeInsuranceVaultCtx<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(has_one = owner @ LendingError::InvalidMarketOwner)]
+    pub lending_market: AccountLoader<'info, LendingMarket>,
+
+    #[account(mut,
+        has_one = lending_market
+    )]
+    pub reserve: AccountLoader<'info, Reserve>,
+
+    /// CHECK: market authority PDA
+    #[account(
+        seeds = [seeds::LENDING_MARKET_AUTH, lending_market.key().as_ref()],
+        bump = lending_market.load()?.bump as u8,
+    )]
+    pub lending_market_authority: AccountInfo<'info>,
+
+    #[account(address = reserve.load()?.liquidity.mint_pubkey)]
+    pub reserve_liquidity_mint: Box<Account<'info, Mint>>,
+
+    #[account(init,
+        seeds = [seeds::INSURANCE_VAULT, lending_market.key().as_ref(), reserve_liquidity_mint.key().as_ref()],
+        bump,
+        payer = owner,
+        token::mint = reserve_liquidity_mint,
+        token::authority = lending_market_authority
+    )]
+    pub insurance_vault: Box<Account<'info, TokenAccount>>,
+
+    pub rent: Sysvar<'info, Rent>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
@@ -5,9 +5,11 @@ use crate::{
     constants::OBLIGATION_SIZE,
     errors::LendingError,
     state::{
-        check_obligation_seeds, InitObligationArgs, LendingMarket, Obligation,
-        ObligationCollateral, ObligationLiquidity,
+        InitObligationArgs, LendingMarket, Obligation, ObligationAllowlist, ObligationCollateral,
+        ObligationLiquidity,
     },
+    utils::validate_init_obligation_args,
+    xmsg,
 };
 
 pub fn process_initialize_obligation(
@@ -16,14 +18,29 @@ pub fn process_initialize_obligation(
 ) -> Result<()> {
     let clock = &Clock::get()?;
 
-    require!(args.id == 0, LendingError::InvalidObligationId);
-
-    check_obligation_seeds(
-        args.tag,
+    validate_init_obligation_args(
+        &args,
         &ctx.accounts.seed1_account,
         &ctx.accounts.seed2_account,
-    )
-    .unwrap();
+    )?;
+
+    if ctx.accounts.lending_market.load()?.is_permissioned() {
+        let is_allowed = match &ctx.accounts.obligation_allowlist {
+            Some(obligation_allowlist) => {
+                let obligation_allowlist = obligation_allowlist.load()?;
+                obligation_allowlist.lending_market == ctx.accounts.lending_market.key()
+                    && obligation_allowlist.owner == ctx.accounts.owner.key()
+            }
+            None => false,
+        };
+        if !is_allowed {
+            xmsg!(
+                "Market is permissioned and {} is not allowed to open obligations",
+                ctx.accounts.owner.key()
+            );
+            return err!(LendingError::InvalidObligationOwner);
+        }
+    }
 
     let obligation = &mut ctx.accounts.obligation.load_init()?;
 
@@ -63,6 +80,9 @@ pub struct InitializeObligationCtx<'info> {
     /// CHECK: seed2 account for obligation
     pub seed2_account: AccountInfo<'info>,
 
+    /// Required when the market is permissioned
+    pub obligation_allowlist: Option<AccountLoader<'info, ObligationAllowlist>>,
+
     pub rent: Sysvar<'info, Rent>,
     pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
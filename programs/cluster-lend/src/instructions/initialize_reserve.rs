@@ -5,7 +5,8 @@ use crate::{
     errors::LendingError,
     state::{
         InitReserveParams, LendingMarket, NewReserveCollateralParams, NewReserveLiquidityParams,
-        Reserve, ReserveCollateral, ReserveConfig, ReserveLiquidity, ReserveStatus,
+        Reserve, ReserveCollateral, ReserveConfig, ReserveConfigTemplate, ReserveLiquidity,
+        ReserveStatus,
     },
     utils::seeds,
 };
@@ -14,6 +15,14 @@ pub fn process_initialize_reserve(ctx: Context<InitializeReserveCtx>) -> Result<
     let clock = &Clock::get()?;
     let reserve = &mut ctx.accounts.reserve.load_init()?;
 
+    let mut config = ReserveConfig {
+        status: ReserveStatus::Hidden.into(),
+        ..Default::default()
+    };
+    if let Some(template) = &ctx.accounts.reserve_config_template {
+        template.load()?.apply(&mut config);
+    }
+
     reserve.init(InitReserveParams {
         current_slot: clock.slot,
         lending_market: ctx.accounts.lending_market.key(),
@@ -28,10 +37,7 @@ pub fn process_initialize_reserve(ctx: Context<InitializeReserveCtx>) -> Result<
             mint_pubkey: ctx.accounts.reserve_collateral_mint.key(),
             supply_vault: ctx.accounts.reserve_collateral_supply.key(),
         })),
-        config: Box::new(ReserveConfig {
-            status: ReserveStatus::Hidden.into(),
-            ..Default::default()
-        }),
+        config: Box::new(config),
     });
 
     Ok(())
@@ -85,7 +91,7 @@ pub struct InitializeReserveCtx<'info> {
         seeds = [seeds::RESERVE_COLL_MINT, lending_market.key().as_ref(), reserve_liquidity_mint.key().as_ref()],
         bump,
         payer = owner,
-        mint::decimals = 6,
+        mint::decimals = reserve_liquidity_mint.decimals,
         mint::authority = lending_market_authority
     )]
     pub reserve_collateral_mint: Box<Account<'info, Mint>>,
@@ -99,6 +105,11 @@ pub struct InitializeReserveCtx<'info> {
     )]
     pub reserve_collateral_supply: Box<Account<'info, TokenAccount>>,
 
+    #[account(
+        has_one = lending_market
+    )]
+    pub reserve_config_template: Option<AccountLoader<'info, ReserveConfigTemplate>>,
+
     pub rent: Sysvar<'info, Rent>,
     pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
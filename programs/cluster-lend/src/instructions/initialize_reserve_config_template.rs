@@ -0,0 +1,52 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    errors::LendingError,
+    state::{
+        InitReserveConfigTemplateParams, LendingMarket, ReserveConfigTemplate,
+        ReserveConfigTemplateKind,
+    },
+    utils::seeds,
+    xmsg,
+};
+
+pub fn process_initialize_reserve_config_template(
+    ctx: Context<InitializeReserveConfigTemplateCtx>,
+    kind: u8,
+) -> Result<()> {
+    let kind = ReserveConfigTemplateKind::try_from(kind)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+
+    let template = &mut ctx.accounts.reserve_config_template.load_init()?;
+    template.init(InitReserveConfigTemplateParams {
+        lending_market: ctx.accounts.lending_market.key(),
+        kind,
+        bump: ctx.bumps.reserve_config_template,
+    });
+
+    xmsg!("Initialized {:?} reserve config template", kind);
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(kind: u8)]
+pub struct InitializeReserveConfigTemplateCtx<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        has_one = owner @ LendingError::InvalidMarketOwner,
+    )]
+    pub lending_market: AccountLoader<'info, LendingMarket>,
+
+    #[account(init,
+        seeds = [seeds::RESERVE_CONFIG_TEMPLATE, lending_market.key().as_ref(), &[kind]],
+        bump,
+        payer = owner,
+        space = 8 + std::mem::size_of::<ReserveConfigTemplate>(),
+    )]
+    pub reserve_config_template: AccountLoader<'info, ReserveConfigTemplate>,
+
+    pub system_program: Program<'info, System>,
+}
@@ -1,21 +1,25 @@
 use anchor_lang::{
     prelude::*,
     solana_program::sysvar::{instructions::Instructions as SysInstructions, SysvarId},
+    system_program, Discriminator,
 };
 use anchor_spl::token::{self, Mint, Token, TokenAccount};
 
 use crate::{
-    check_refresh_ixs, gen_signer_seeds,
-    lending_market::{lending_checks, lending_operations},
-    utils::{seeds, token_transfer},
-    xmsg, LendingAction, LendingMarket, LiquidateAndRedeemResult, Obligation,
-    RedeemReserveCollateralAccounts, Reserve,
+    check_refresh_ixs,
+    errors::LendingError,
+    gen_signer_seeds,
+    lending_market::{lending_checks, lending_operations, liquidation_operations},
+    utils::{seeds, token_transfer, FatAccountLoader},
+    xmsg, InitLiquidationBondParams, LendingAction, LendingMarket, LiquidateAndRedeemResult,
+    LiquidationBond, LiquidationMode, Obligation, RedeemReserveCollateralAccounts, Reserve,
 };
 
 pub fn process_liquidate_obligation(
     ctx: Context<LiquidateObligationCtx>,
     liquidity_amount: u64,
     min_acceptable_received_collateral_amount: u64,
+    min_acceptable_received_liquidity_amount: u64,
     max_allowed_ltv_override_percent: u64,
 ) -> Result<()> {
     xmsg!(
@@ -24,141 +28,276 @@ pub fn process_liquidate_obligation(
         max_allowed_ltv_override_percent
     );
 
+    let is_self_liquidation =
+        ctx.accounts.liquidator.key() == ctx.accounts.obligation.load()?.owner;
+
+    if is_self_liquidation && !cfg!(feature = "staging") {
+        xmsg!("Obligation owner cannot liquidate their own obligation outside the staging program");
+        return err!(LendingError::SelfLiquidationForbidden);
+    }
+
+    let max_allowed_ltv_override_pct_opt =
+        if is_self_liquidation && max_allowed_ltv_override_percent > 0 {
+            Some(max_allowed_ltv_override_percent)
+        } else {
+            None
+        };
+
+    liquidate_and_settle(
+        ctx,
+        liquidity_amount,
+        min_acceptable_received_collateral_amount,
+        min_acceptable_received_liquidity_amount,
+        LiquidationMode::Standard {
+            max_allowed_ltv_override_pct_opt,
+        },
+    )
+}
+
+pub fn process_deleverage_obligation_to_target(
+    ctx: Context<LiquidateObligationCtx>,
+    liquidity_amount: u64,
+    min_acceptable_received_collateral_amount: u64,
+) -> Result<()> {
+    xmsg!("DeleverageObligationToTarget amount {}", liquidity_amount);
+
+    liquidate_and_settle(
+        ctx,
+        liquidity_amount,
+        min_acceptable_received_collateral_amount,
+        0,
+        LiquidationMode::ToTargetLtv,
+    )
+}
+
+fn liquidate_and_settle(
+    ctx: Context<LiquidateObligationCtx>,
+    liquidity_amount: u64,
+    min_acceptable_received_collateral_amount: u64,
+    min_acceptable_received_liquidity_amount: u64,
+    mode: LiquidationMode,
+) -> Result<()> {
     check_refresh_ixs!(ctx, withdraw_reserve, repay_reserve);
 
-    lending_checks::liquidate_obligation_checks(&ctx)?;
-    lending_checks::redeem_reserve_collateral_checks(&RedeemReserveCollateralAccounts {
-        user_source_collateral: ctx.accounts.user_destination_collateral.clone(),
-        user_destination_liquidity: ctx.accounts.user_destination_liquidity.clone(),
-        reserve: ctx.accounts.withdraw_reserve.clone(),
-        reserve_collateral_mint: ctx.accounts.withdraw_reserve_collateral_mint.clone(),
-        reserve_liquidity_supply: ctx.accounts.withdraw_reserve_liquidity_supply.clone(),
-        lending_market: ctx.accounts.lending_market.clone(),
-        lending_market_authority: ctx.accounts.lending_market_authority.clone(),
-        owner: ctx.accounts.liquidator.clone(),
-        token_program: ctx.accounts.token_program.clone(),
-    })?;
+    let accounts = ctx.accounts.liquidation_accounts();
+    lending_checks::liquidate_obligation_checks(
+        &accounts,
+        &ctx.accounts.instruction_sysvar_account,
+    )?;
+    lending_checks::redeem_reserve_collateral_checks(
+        &accounts.redeem_reserve_collateral_accounts(),
+    )?;
 
     let lending_market = &ctx.accounts.lending_market.load()?;
     let obligation = &mut ctx.accounts.obligation.load_mut()?;
-    let lending_market_key = ctx.accounts.lending_market.key();
     let clock = &Clock::get()?;
 
-    let max_allowed_ltv_override_pct_opt = if ctx.accounts.liquidator.key() == obligation.owner
-        && max_allowed_ltv_override_percent > 0
+    settle_liquidation(
+        &accounts,
+        lending_market,
+        obligation,
+        clock,
+        liquidity_amount,
+        min_acceptable_received_collateral_amount,
+        min_acceptable_received_liquidity_amount,
+        mode,
+    )?;
+
+    // deleveraging to the target is authorized by the owner, there is nothing to contest
+    let liquidation_bond_lamports = ctx
+        .accounts
+        .repay_reserve
+        .load()?
+        .config
+        .liquidation_bond_lamports;
+    if matches!(mode, LiquidationMode::Standard { .. })
+        && lending_market.is_liquidation_bond_required()
+        && liquidation_bond_lamports > 0
     {
-        if cfg!(feature = "staging") {
-            Some(max_allowed_ltv_override_percent)
-        } else {
-            xmsg!("Warning! Attempting to set an ltv override outside the staging program");
-            None
+        post_liquidation_bond(
+            &ctx,
+            liquidation_bond_lamports,
+            lending_market.liquidation_bond_challenge_window_secs,
+            clock,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Accounts a liquidation moves tokens between, shared by single and batched liquidations
+pub struct LiquidationAccounts<'info> {
+    pub liquidator: Signer<'info>,
+    pub lending_market: AccountLoader<'info, LendingMarket>,
+    pub lending_market_authority: AccountInfo<'info>,
+    pub repay_reserve: AccountLoader<'info, Reserve>,
+    pub repay_reserve_liquidity_supply: Box<Account<'info, TokenAccount>>,
+    pub withdraw_reserve: AccountLoader<'info, Reserve>,
+    pub withdraw_reserve_collateral_mint: Box<Account<'info, Mint>>,
+    pub withdraw_reserve_collateral_supply: Box<Account<'info, TokenAccount>>,
+    pub withdraw_reserve_liquidity_supply: Box<Account<'info, TokenAccount>>,
+    pub withdraw_reserve_liquidity_fee_receiver: Box<Account<'info, TokenAccount>>,
+    pub user_source_liquidity: Box<Account<'info, TokenAccount>>,
+    pub user_destination_collateral: Box<Account<'info, TokenAccount>>,
+    pub user_destination_liquidity: Box<Account<'info, TokenAccount>>,
+    pub token_program: Program<'info, Token>,
+    pub withdraw_reserve_insurance_vault: Option<Box<Account<'info, TokenAccount>>>,
+}
+
+impl<'info> LiquidationAccounts<'info> {
+    pub fn redeem_reserve_collateral_accounts(&self) -> RedeemReserveCollateralAccounts<'info> {
+        RedeemReserveCollateralAccounts {
+            user_source_collateral: self.user_destination_collateral.clone(),
+            user_destination_liquidity: self.user_destination_liquidity.clone(),
+            reserve: self.withdraw_reserve.clone(),
+            reserve_collateral_mint: self.withdraw_reserve_collateral_mint.clone(),
+            reserve_liquidity_supply: self.withdraw_reserve_liquidity_supply.clone(),
+            lending_market: self.lending_market.clone(),
+            lending_market_authority: self.lending_market_authority.clone(),
+            owner: self.liquidator.clone(),
+            token_program: self.token_program.clone(),
         }
-    } else {
-        None
-    };
+    }
+}
 
-    let initial_withdraw_reserve_token_balance = token::accessor::amount(
-        &ctx.accounts
-            .withdraw_reserve_liquidity_supply
-            .to_account_info(),
-    )?;
+#[allow(clippy::too_many_arguments)]
+pub fn settle_liquidation(
+    accounts: &LiquidationAccounts,
+    lending_market: &LendingMarket,
+    obligation: &mut Obligation,
+    clock: &Clock,
+    liquidity_amount: u64,
+    min_acceptable_received_collateral_amount: u64,
+    min_acceptable_received_liquidity_amount: u64,
+    mode: LiquidationMode,
+) -> Result<LiquidateAndRedeemResult> {
+    let lending_market_key = accounts.lending_market.key();
 
-    let initial_repay_reserve_token_balance = token::accessor::amount(
-        &ctx.accounts
-            .repay_reserve_liquidity_supply
-            .to_account_info(),
-    )?;
+    let initial_withdraw_reserve_token_balance =
+        token::accessor::amount(&accounts.withdraw_reserve_liquidity_supply.to_account_info())?;
+
+    let initial_repay_reserve_token_balance =
+        token::accessor::amount(&accounts.repay_reserve_liquidity_supply.to_account_info())?;
+
+    accounts
+        .repay_reserve
+        .load_mut()?
+        .reconcile_rebasing_vault_balance(initial_repay_reserve_token_balance)?;
+    accounts
+        .withdraw_reserve
+        .load_mut()?
+        .reconcile_rebasing_vault_balance(initial_withdraw_reserve_token_balance)?;
 
     let (initial_repay_reserve_available_amount, initial_withdraw_reserve_available_amount) =
         lending_checks::initial_liquidation_reserve_liquidity_available_amount(
-            &ctx.accounts.repay_reserve,
-            &ctx.accounts.withdraw_reserve,
+            &accounts.repay_reserve,
+            &accounts.withdraw_reserve,
         );
 
     let authority_signer_seeds = gen_signer_seeds!(lending_market_key, lending_market.bump as u8);
 
-    let LiquidateAndRedeemResult {
-        repay_amount,
-        withdraw_collateral_amount,
-        withdraw_amount,
-        total_withdraw_liquidity_amount,
-        ..
-    } = lending_operations::liquidate_and_redeem(
+    let result = lending_operations::liquidate_and_redeem(
         lending_market,
-        &ctx.accounts.repay_reserve,
-        &ctx.accounts.withdraw_reserve,
+        &accounts.repay_reserve,
+        &accounts.withdraw_reserve,
         obligation,
         clock,
         liquidity_amount,
         min_acceptable_received_collateral_amount,
-        max_allowed_ltv_override_pct_opt,
+        min_acceptable_received_liquidity_amount,
+        mode,
     )?;
+    let LiquidateAndRedeemResult {
+        repay_amount,
+        withdraw_collateral_amount,
+        withdraw_amount,
+        total_withdraw_liquidity_amount,
+        ..
+    } = result;
 
     token_transfer::repay_obligation_liquidity_transfer(
-        ctx.accounts.token_program.to_account_info(),
-        ctx.accounts.user_source_liquidity.to_account_info(),
-        ctx.accounts
-            .repay_reserve_liquidity_supply
-            .to_account_info(),
-        ctx.accounts.liquidator.to_account_info(),
+        accounts.token_program.to_account_info(),
+        accounts.user_source_liquidity.to_account_info(),
+        accounts.repay_reserve_liquidity_supply.to_account_info(),
+        accounts.liquidator.to_account_info(),
         repay_amount,
     )?;
 
     token_transfer::withdraw_obligation_collateral_transfer(
-        ctx.accounts.token_program.to_account_info(),
-        ctx.accounts.user_destination_collateral.to_account_info(),
-        ctx.accounts
+        accounts.token_program.to_account_info(),
+        accounts.user_destination_collateral.to_account_info(),
+        accounts
             .withdraw_reserve_collateral_supply
             .to_account_info(),
-        ctx.accounts.lending_market_authority.to_account_info(),
+        accounts.lending_market_authority.to_account_info(),
         authority_signer_seeds,
         withdraw_amount,
     )?;
 
     if let Some((withdraw_liquidity_amount, protocol_fee)) = total_withdraw_liquidity_amount {
         token_transfer::redeem_reserve_collateral_transfer(
-            ctx.accounts.token_program.to_account_info(),
-            ctx.accounts
-                .withdraw_reserve_collateral_mint
-                .to_account_info(),
-            ctx.accounts.user_destination_collateral.to_account_info(),
-            ctx.accounts.liquidator.to_account_info(),
-            ctx.accounts
-                .withdraw_reserve_liquidity_supply
-                .to_account_info(),
-            ctx.accounts.user_destination_liquidity.to_account_info(),
-            ctx.accounts.lending_market_authority.to_account_info(),
+            accounts.token_program.to_account_info(),
+            accounts.withdraw_reserve_collateral_mint.to_account_info(),
+            accounts.user_destination_collateral.to_account_info(),
+            accounts.liquidator.to_account_info(),
+            accounts.withdraw_reserve_liquidity_supply.to_account_info(),
+            accounts.user_destination_liquidity.to_account_info(),
+            accounts.lending_market_authority.to_account_info(),
             authority_signer_seeds,
             withdraw_collateral_amount,
             withdraw_liquidity_amount,
         )?;
 
+        let withdraw_reserve = &accounts.withdraw_reserve.load()?;
+
+        // the insurance share stays with the fee vault until the reserve has an insurance vault
+        let insurance_fee = if withdraw_reserve.liquidity.insurance_vault == Pubkey::default() {
+            0
+        } else {
+            liquidation_operations::calculate_insurance_fee(
+                protocol_fee,
+                withdraw_reserve.config.insurance_fee_bps,
+            )
+        };
+
         token::transfer(
             CpiContext::new(
-                ctx.accounts.token_program.to_account_info(),
+                accounts.token_program.to_account_info(),
                 anchor_spl::token::Transfer {
-                    from: ctx.accounts.user_destination_liquidity.to_account_info(),
-                    to: ctx
-                        .accounts
+                    from: accounts.user_destination_liquidity.to_account_info(),
+                    to: accounts
                         .withdraw_reserve_liquidity_fee_receiver
                         .to_account_info(),
-                    authority: ctx.accounts.liquidator.to_account_info(),
+                    authority: accounts.liquidator.to_account_info(),
                 },
             ),
-            protocol_fee,
+            protocol_fee - insurance_fee,
         )?;
-        let withdraw_reserve = &ctx.accounts.withdraw_reserve.load()?;
-
-        let net_withdrawal_amount = if ctx
-            .accounts
-            .withdraw_reserve_liquidity_supply
-            .to_account_info()
-            .key
-            == ctx
-                .accounts
-                .repay_reserve_liquidity_supply
-                .to_account_info()
-                .key
+
+        if insurance_fee > 0 {
+            let Some(insurance_vault) = accounts.withdraw_reserve_insurance_vault.as_ref() else {
+                xmsg!(
+                    "Insurance vault {} must be provided",
+                    withdraw_reserve.liquidity.insurance_vault
+                );
+                return err!(LendingError::InsuranceVaultRequired);
+            };
+            xmsg!("Routing liquidation fee {} to insurance", insurance_fee);
+            token::transfer(
+                CpiContext::new(
+                    accounts.token_program.to_account_info(),
+                    anchor_spl::token::Transfer {
+                        from: accounts.user_destination_liquidity.to_account_info(),
+                        to: insurance_vault.to_account_info(),
+                        authority: accounts.liquidator.to_account_info(),
+                    },
+                ),
+                insurance_fee,
+            )?;
+        }
+
+        let net_withdrawal_amount = if accounts.withdraw_reserve_liquidity_supply.key()
+            == accounts.repay_reserve_liquidity_supply.key()
         {
             withdraw_liquidity_amount - repay_amount
         } else {
@@ -166,39 +305,23 @@ pub fn process_liquidate_obligation(
         };
 
         lending_checks::post_transfer_vault_balance_liquidity_reserve_checks(
-            token::accessor::amount(
-                &ctx.accounts
-                    .withdraw_reserve_liquidity_supply
-                    .to_account_info(),
-            )
-            .unwrap(),
+            token::accessor::amount(&accounts.withdraw_reserve_liquidity_supply.to_account_info())
+                .unwrap(),
             withdraw_reserve.liquidity.available_amount,
             initial_withdraw_reserve_token_balance,
             initial_withdraw_reserve_available_amount,
             LendingAction::Subtractive(net_withdrawal_amount),
         )?;
     }
-    let repay_reserve = &ctx.accounts.repay_reserve.load()?;
+    let repay_reserve = &accounts.repay_reserve.load()?;
 
-    if ctx
-        .accounts
-        .withdraw_reserve_liquidity_supply
-        .to_account_info()
-        .key
-        != ctx
-            .accounts
-            .repay_reserve_liquidity_supply
-            .to_account_info()
-            .key
+    if accounts.withdraw_reserve_liquidity_supply.key()
+        != accounts.repay_reserve_liquidity_supply.key()
         || total_withdraw_liquidity_amount.is_none()
     {
         lending_checks::post_transfer_vault_balance_liquidity_reserve_checks(
-            token::accessor::amount(
-                &ctx.accounts
-                    .repay_reserve_liquidity_supply
-                    .to_account_info(),
-            )
-            .unwrap(),
+            token::accessor::amount(&accounts.repay_reserve_liquidity_supply.to_account_info())
+                .unwrap(),
             repay_reserve.liquidity.available_amount,
             initial_repay_reserve_token_balance,
             initial_repay_reserve_available_amount,
@@ -206,11 +329,131 @@ pub fn process_liquidate_obligation(
         )?;
     }
 
+    Ok(result)
+}
+
+fn post_liquidation_bond(
+    ctx: &Context<LiquidateObligationCtx>,
+    amount: u64,
+    challenge_window_secs: u64,
+    clock: &Clock,
+) -> Result<()> {
+    let (Some(liquidation_bond), Some(system_program)) = (
+        ctx.accounts.liquidation_bond.as_ref(),
+        ctx.accounts.system_program.as_ref(),
+    ) else {
+        xmsg!("Liquidation bond and system program accounts are required");
+        return err!(LendingError::LiquidationBondRequired);
+    };
+
+    let obligation_key = ctx.accounts.obligation.key();
+    let liquidator_key = ctx.accounts.liquidator.key();
+    let (liquidation_bond_key, bump) = Pubkey::find_program_address(
+        &[
+            seeds::LIQUIDATION_BOND,
+            obligation_key.as_ref(),
+            liquidator_key.as_ref(),
+        ],
+        ctx.program_id,
+    );
+    if liquidation_bond.key() != liquidation_bond_key {
+        xmsg!(
+            "Expected liquidation bond {} got {}",
+            liquidation_bond_key,
+            liquidation_bond.key()
+        );
+        return err!(LendingError::InvalidLiquidationBondAccount);
+    }
+
+    if liquidation_bond.data_is_empty() {
+        let space = 8 + std::mem::size_of::<LiquidationBond>();
+        let bond_signer_seeds: &[&[&[u8]]] = &[&[
+            seeds::LIQUIDATION_BOND,
+            obligation_key.as_ref(),
+            liquidator_key.as_ref(),
+            &[bump],
+        ]];
+
+        // create_account fails on an address that already holds lamports, anyone could
+        // block the bond by funding it first, so top up, allocate and assign instead
+        let rent_exempt_top_up = Rent::get()?
+            .minimum_balance(space)
+            .saturating_sub(liquidation_bond.lamports());
+        if rent_exempt_top_up > 0 {
+            system_program::transfer(
+                CpiContext::new(
+                    system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: ctx.accounts.liquidator.to_account_info(),
+                        to: liquidation_bond.to_account_info(),
+                    },
+                ),
+                rent_exempt_top_up,
+            )?;
+        }
+        system_program::allocate(
+            CpiContext::new_with_signer(
+                system_program.to_account_info(),
+                system_program::Allocate {
+                    account_to_allocate: liquidation_bond.to_account_info(),
+                },
+                bond_signer_seeds,
+            ),
+            space as u64,
+        )?;
+        system_program::assign(
+            CpiContext::new_with_signer(
+                system_program.to_account_info(),
+                system_program::Assign {
+                    account_to_assign: liquidation_bond.to_account_info(),
+                },
+                bond_signer_seeds,
+            ),
+            ctx.program_id,
+        )?;
+
+        let bond_loader = FatAccountLoader::<LiquidationBond>::try_from_unchecked(
+            ctx.program_id,
+            liquidation_bond,
+        )?;
+        bond_loader.load_init()?.init(InitLiquidationBondParams {
+            lending_market: ctx.accounts.lending_market.key(),
+            obligation: obligation_key,
+            liquidator: liquidator_key,
+            bump,
+        });
+        liquidation_bond.try_borrow_mut_data()?[..8]
+            .copy_from_slice(&LiquidationBond::discriminator());
+    }
+
+    system_program::transfer(
+        CpiContext::new(
+            system_program.to_account_info(),
+            system_program::Transfer {
+                from: ctx.accounts.liquidator.to_account_info(),
+                to: liquidation_bond.to_account_info(),
+            },
+        ),
+        amount,
+    )?;
+
+    let bond_loader = FatAccountLoader::<LiquidationBond>::try_from(liquidation_bond)?;
+    let bond = &mut bond_loader.load_mut()?;
+    bond.post(amount, clock.unix_timestamp)?;
+
+    xmsg!(
+        "Posted liquidation bond {} total {} refundable from {}",
+        amount,
+        bond.amount,
+        bond.challenge_window_end(challenge_window_secs)
+    );
+
     Ok(())
 }
 
 #[derive(Accounts)]
 pub struct LiquidateObligationCtx<'info> {
+    #[account(mut)]
     pub liquidator: Signer<'info>,
 
     #[account(mut,
@@ -270,4 +513,39 @@ pub struct LiquidateObligationCtx<'info> {
     /// CHECK: instruction_sysvar account
     #[account(address = SysInstructions::id())]
     pub instruction_sysvar_account: AccountInfo<'info>,
+
+    /// CHECK: liquidation bond PDA, checked and created when the market requires bonds
+    #[account(mut)]
+    pub liquidation_bond: Option<AccountInfo<'info>>,
+
+    pub system_program: Option<Program<'info, System>>,
+
+    #[account(mut,
+        address = withdraw_reserve.load()?.liquidity.insurance_vault
+    )]
+    pub withdraw_reserve_insurance_vault: Option<Box<Account<'info, TokenAccount>>>,
+}
+
+impl<'info> LiquidateObligationCtx<'info> {
+    pub fn liquidation_accounts(&self) -> LiquidationAccounts<'info> {
+        LiquidationAccounts {
+            liquidator: self.liquidator.clone(),
+            lending_market: self.lending_market.clone(),
+            lending_market_authority: self.lending_market_authority.clone(),
+            repay_reserve: self.repay_reserve.clone(),
+            repay_reserve_liquidity_supply: self.repay_reserve_liquidity_supply.clone(),
+            withdraw_reserve: self.withdraw_reserve.clone(),
+            withdraw_reserve_collateral_mint: self.withdraw_reserve_collateral_mint.clone(),
+            withdraw_reserve_collateral_supply: self.withdraw_reserve_collateral_supply.clone(),
+            withdraw_reserve_liquidity_supply: self.withdraw_reserve_liquidity_supply.clone(),
+            withdraw_reserve_liquidity_fee_receiver: self
+                .withdraw_reserve_liquidity_fee_receiver
+                .clone(),
+            user_source_liquidity: self.user_source_liquidity.clone(),
+            user_destination_collateral: self.user_destination_collateral.clone(),
+            user_destination_liquidity: self.user_destination_liquidity.clone(),
+            token_program: self.token_program.clone(),
+            withdraw_reserve_insurance_vault: self.withdraw_reserve_insurance_vault.clone(),
+        }
+    }
 }
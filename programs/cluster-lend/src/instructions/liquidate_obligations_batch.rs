@@ -0,0 +1,254 @@
+use anchor_lang::{
+    prelude::*,
+    solana_program::sysvar::{instructions::Instructions as SysInstructions, SysvarId},
+};
+use anchor_spl::token::{Mint, Token, TokenAccount};
+
+use crate::{
+    constants::MAX_LIQUIDATION_BATCH_OBLIGATIONS,
+    errors::LendingError,
+    events::BatchLiquidationResultEvent,
+    lending_market::{lending_checks, lending_operations},
+    settle_liquidation,
+    utils::{check_refresh, seeds, FatAccountLoader},
+    xmsg, LendingMarket, LiquidateAndRedeemResult, LiquidationAccounts, LiquidationMode,
+    Obligation, Reserve,
+};
+
+pub fn process_liquidate_obligations_batch(
+    ctx: Context<LiquidateObligationsBatchCtx>,
+    liquidity_amount: u64,
+    min_acceptable_received_collateral_amount: u64,
+    min_acceptable_received_liquidity_amount: u64,
+) -> Result<()> {
+    let obligation_infos = ctx.remaining_accounts;
+    xmsg!(
+        "LiquidateObligationsBatch amount {} obligations {}",
+        liquidity_amount,
+        obligation_infos.len()
+    );
+
+    if obligation_infos.is_empty() || obligation_infos.len() > MAX_LIQUIDATION_BATCH_OBLIGATIONS {
+        xmsg!(
+            "Batch must hold between 1 and {} obligations",
+            MAX_LIQUIDATION_BATCH_OBLIGATIONS
+        );
+        return err!(LendingError::InvalidAccountInput);
+    }
+
+    let repay_reserve_key = ctx.accounts.repay_reserve.key();
+    let withdraw_reserve_key = ctx.accounts.withdraw_reserve.key();
+    let obligation_keys: Vec<Pubkey> = obligation_infos.iter().map(|info| info.key()).collect();
+    {
+        let repay_reserve = ctx.accounts.repay_reserve.load()?;
+        let withdraw_reserve = ctx.accounts.withdraw_reserve.load()?;
+        check_refresh(
+            &ctx.accounts.instruction_sysvar_account,
+            &[
+                (withdraw_reserve_key, &withdraw_reserve),
+                (repay_reserve_key, &repay_reserve),
+            ],
+            &obligation_keys,
+        )?;
+    }
+
+    let accounts = ctx.accounts.liquidation_accounts();
+    lending_checks::liquidate_obligation_checks(
+        &accounts,
+        &ctx.accounts.instruction_sysvar_account,
+    )?;
+    lending_checks::redeem_reserve_collateral_checks(
+        &accounts.redeem_reserve_collateral_accounts(),
+    )?;
+
+    let lending_market = &ctx.accounts.lending_market.load()?;
+    let clock = &Clock::get()?;
+
+    if lending_market.is_liquidation_bond_required()
+        && ctx
+            .accounts
+            .repay_reserve
+            .load()?
+            .config
+            .liquidation_bond_lamports
+            > 0
+    {
+        xmsg!("Liquidations of this reserve post a bond, liquidate obligations one at a time");
+        return err!(LendingError::LiquidationBondRequired);
+    }
+
+    for (index, obligation_info) in obligation_infos.iter().enumerate() {
+        let obligation_loader = FatAccountLoader::<Obligation>::try_from(obligation_info)?;
+        let obligation = &mut obligation_loader.load_mut()?;
+        if obligation.lending_market != ctx.accounts.lending_market.key() {
+            xmsg!(
+                "Obligation {} belongs to another lending market",
+                obligation_info.key()
+            );
+            return err!(LendingError::InvalidAccountInput);
+        }
+
+        // each liquidation leaves the reserves stale, the prices saved this slot still hold
+        if index > 0 {
+            let repay_reserve = &mut ctx.accounts.repay_reserve.load_mut()?;
+            lending_operations::refresh_reserve(repay_reserve, clock, None)?;
+        }
+        if index > 0 && withdraw_reserve_key != repay_reserve_key {
+            let withdraw_reserve = &mut ctx.accounts.withdraw_reserve.load_mut()?;
+            lending_operations::refresh_reserve(withdraw_reserve, clock, None)?;
+        }
+
+        let skip_reason = if obligation.owner == ctx.accounts.liquidator.key() {
+            Some(error!(LendingError::SelfLiquidationForbidden))
+        } else {
+            let repay_reserve = &ctx.accounts.repay_reserve.load()?;
+            let withdraw_reserve = &ctx.accounts.withdraw_reserve.load()?;
+            lending_operations::simulate_liquidation(
+                lending_market,
+                repay_reserve,
+                withdraw_reserve,
+                obligation,
+                repay_reserve_key,
+                withdraw_reserve_key,
+                clock.slot,
+                liquidity_amount,
+            )
+            .err()
+        };
+
+        if let Some(skip_reason) = skip_reason {
+            xmsg!(
+                "Skipping obligation {}: {}",
+                obligation_info.key(),
+                skip_reason
+            );
+            emit!(BatchLiquidationResultEvent {
+                obligation: obligation_info.key(),
+                liquidated: false,
+                repay_amount: 0,
+                withdraw_collateral_amount: 0,
+            });
+            continue;
+        }
+
+        let LiquidateAndRedeemResult {
+            repay_amount,
+            withdraw_collateral_amount,
+            ..
+        } = settle_liquidation(
+            &accounts,
+            lending_market,
+            obligation,
+            clock,
+            liquidity_amount,
+            min_acceptable_received_collateral_amount,
+            min_acceptable_received_liquidity_amount,
+            LiquidationMode::Standard {
+                max_allowed_ltv_override_pct_opt: None,
+            },
+        )?;
+
+        xmsg!(
+            "Liquidated obligation {} repaying {} for {} collateral",
+            obligation_info.key(),
+            repay_amount,
+            withdraw_collateral_amount
+        );
+        emit!(BatchLiquidationResultEvent {
+            obligation: obligation_info.key(),
+            liquidated: true,
+            repay_amount,
+            withdraw_collateral_amount,
+        });
+    }
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LiquidateObligationsBatchCtx<'info> {
+    #[account(mut)]
+    pub liquidator: Signer<'info>,
+
+    pub lending_market: AccountLoader<'info, LendingMarket>,
+
+    /// CHECK: market authority PDA
+    #[account(
+        seeds = [seeds::LENDING_MARKET_AUTH, lending_market.key().as_ref()],
+        bump = lending_market.load()?.bump as u8,
+    )]
+    pub lending_market_authority: AccountInfo<'info>,
+
+    #[account(mut,
+        has_one = lending_market
+    )]
+    pub repay_reserve: AccountLoader<'info, Reserve>,
+
+    #[account(mut,
+        address = repay_reserve.load()?.liquidity.supply_vault
+    )]
+    pub repay_reserve_liquidity_supply: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut,
+        has_one = lending_market
+    )]
+    pub withdraw_reserve: AccountLoader<'info, Reserve>,
+    #[account(mut,
+        address = withdraw_reserve.load()?.collateral.mint_pubkey
+    )]
+    pub withdraw_reserve_collateral_mint: Box<Account<'info, Mint>>,
+    #[account(mut,
+        address = withdraw_reserve.load()?.collateral.supply_vault
+    )]
+    pub withdraw_reserve_collateral_supply: Box<Account<'info, TokenAccount>>,
+    #[account(mut,
+        address = withdraw_reserve.load()?.liquidity.supply_vault
+    )]
+    pub withdraw_reserve_liquidity_supply: Box<Account<'info, TokenAccount>>,
+    #[account(mut,
+        address = withdraw_reserve.load()?.liquidity.fee_vault
+    )]
+    pub withdraw_reserve_liquidity_fee_receiver: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub user_source_liquidity: Box<Account<'info, TokenAccount>>,
+    #[account(mut)]
+    pub user_destination_collateral: Box<Account<'info, TokenAccount>>,
+    #[account(mut)]
+    pub user_destination_liquidity: Box<Account<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+
+    /// CHECK: instruction_sysvar account
+    #[account(address = SysInstructions::id())]
+    pub instruction_sysvar_account: AccountInfo<'info>,
+
+    #[account(mut,
+        address = withdraw_reserve.load()?.liquidity.insurance_vault
+    )]
+    pub withdraw_reserve_insurance_vault: Option<Box<Account<'info, TokenAccount>>>,
+}
+
+impl<'info> LiquidateObligationsBatchCtx<'info> {
+    pub fn liquidation_accounts(&self) -> LiquidationAccounts<'info> {
+        LiquidationAccounts {
+            liquidator: self.liquidator.clone(),
+            lending_market: self.lending_market.clone(),
+            lending_market_authority: self.lending_market_authority.clone(),
+            repay_reserve: self.repay_reserve.clone(),
+            repay_reserve_liquidity_supply: self.repay_reserve_liquidity_supply.clone(),
+            withdraw_reserve: self.withdraw_reserve.clone(),
+            withdraw_reserve_collateral_mint: self.withdraw_reserve_collateral_mint.clone(),
+            withdraw_reserve_collateral_supply: self.withdraw_reserve_collateral_supply.clone(),
+            withdraw_reserve_liquidity_supply: self.withdraw_reserve_liquidity_supply.clone(),
+            withdraw_reserve_liquidity_fee_receiver: self
+                .withdraw_reserve_liquidity_fee_receiver
+                .clone(),
+            user_source_liquidity: self.user_source_liquidity.clone(),
+            user_destination_collateral: self.user_destination_collateral.clone(),
+            user_destination_liquidity: self.user_destination_liquidity.clone(),
+            token_program: self.token_program.clone(),
+            withdraw_reserve_insurance_vault: self.withdraw_reserve_insurance_vault.clone(),
+        }
+    }
+}
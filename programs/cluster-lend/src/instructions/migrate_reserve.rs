@@ -0,0 +1,46 @@
+use anchor_lang::{prelude::*, Discriminator};
+
+use crate::{
+    errors::LendingError,
+    lending_market::lending_operations,
+    state::{LendingMarket, Reserve},
+    xmsg,
+};
+
+pub fn process_migrate_reserve(ctx: Context<MigrateReserveCtx>) -> Result<()> {
+    let expected_len = Reserve::discriminator().len() + std::mem::size_of::<Reserve>();
+    let data_len = ctx.accounts.reserve.as_ref().data_len();
+    if data_len != expected_len {
+        xmsg!(
+            "Reserve account size {} does not match the current layout size {}",
+            data_len,
+            expected_len
+        );
+        return err!(LendingError::InvalidAccountInput);
+    }
+
+    let reserve = &mut ctx.accounts.reserve.load_mut()?;
+
+    xmsg!(
+        "Migrating reserve {:?} {}",
+        ctx.accounts.reserve.key(),
+        reserve.config.token_info.symbol(),
+    );
+
+    lending_operations::migrate_reserve(reserve)
+}
+
+#[derive(Accounts)]
+pub struct MigrateReserveCtx<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        has_one = owner
+    )]
+    pub lending_market: AccountLoader<'info, LendingMarket>,
+
+    #[account(mut,
+        has_one = lending_market
+    )]
+    pub reserve: AccountLoader<'info, Reserve>,
+}
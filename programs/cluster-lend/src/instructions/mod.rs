@@ -1,41 +1,109 @@
+mod allow_obligation_owner;
+mod apply_reserve_config_template;
 mod borrow_obligation_liquidity;
+mod claim_rewards;
+mod contest_liquidation_bond;
+mod cover_bad_debt_from_insurance;
 mod deposit_liquidity_collateral;
 mod deposit_obligation_collateral;
 mod deposit_reserve_liquidity;
+mod donate_reserve_liquidity;
+mod emergency_redeem_reserve_collateral;
 mod flash_borrow_reserve;
 mod flash_repay_reserve;
+mod get_obligation_values;
+mod get_reserve_apy;
+mod get_utilization;
+mod initialize_insurance_vault;
 mod initialize_market;
 mod initialize_obligation;
 mod initialize_reserve;
+mod initialize_reserve_config_template;
 mod liquidate_obligation;
+mod liquidate_obligations_batch;
+mod migrate_reserve;
+mod push_reserve_price;
+mod reconcile_reserve;
 mod redeem_fees;
 mod redeem_reserve_collateral;
+mod redeem_reserve_collateral_exact_liquidity;
 mod refresh_obligation;
 mod refresh_reserve;
+mod refresh_reserves;
+mod refund_liquidation_bond;
+mod reorder_obligation_deposits;
 mod repay_obligation_liquidity;
+mod repay_obligation_liquidity_with_collateral;
+mod request_elevation_group;
+mod revoke_obligation_owner;
+mod set_aggressive_mode;
+mod set_autodeleverage_target;
+mod set_borrow_factor_override;
+mod set_obligation_reserve_allowlist;
+mod set_reserve_vaults;
+mod set_user_ltv_cap;
+mod simulate_liquidation;
+mod transfer_obligation_collateral;
 mod update_market;
 mod update_market_owner;
+mod update_market_risk_authority;
 mod update_reserve;
+mod update_reserve_config_template;
 mod withdraw_obligation_collateral;
+mod withdraw_obligation_collateral_and_redeem;
 mod withdraw_protocol_fees;
 
+pub use allow_obligation_owner::*;
+pub use apply_reserve_config_template::*;
 pub use borrow_obligation_liquidity::*;
+pub use claim_rewards::*;
+pub use contest_liquidation_bond::*;
+pub use cover_bad_debt_from_insurance::*;
 pub use deposit_liquidity_collateral::*;
 pub use deposit_obligation_collateral::*;
 pub use deposit_reserve_liquidity::*;
+pub use donate_reserve_liquidity::*;
+pub use emergency_redeem_reserve_collateral::*;
 pub use flash_borrow_reserve::*;
 pub use flash_repay_reserve::*;
+pub use get_obligation_values::*;
+pub use get_reserve_apy::*;
+pub use get_utilization::*;
+pub use initialize_insurance_vault::*;
 pub use initialize_market::*;
 pub use initialize_obligation::*;
 pub use initialize_reserve::*;
+pub use initialize_reserve_config_template::*;
 pub use liquidate_obligation::*;
+pub use liquidate_obligations_batch::*;
+pub use migrate_reserve::*;
+pub use push_reserve_price::*;
+pub use reconcile_reserve::*;
 pub use redeem_fees::*;
 pub use redeem_reserve_collateral::*;
+pub use redeem_reserve_collateral_exact_liquidity::*;
 pub use refresh_obligation::*;
 pub use refresh_reserve::*;
+pub use refresh_reserves::*;
+pub use refund_liquidation_bond::*;
+pub use reorder_obligation_deposits::*;
 pub use repay_obligation_liquidity::*;
+pub use repay_obligation_liquidity_with_collateral::*;
+pub use request_elevation_group::*;
+pub use revoke_obligation_owner::*;
+pub use set_aggressive_mode::*;
+pub use set_autodeleverage_target::*;
+pub use set_borrow_factor_override::*;
+pub use set_obligation_reserve_allowlist::*;
+pub use set_reserve_vaults::*;
+pub use set_user_ltv_cap::*;
+pub use simulate_liquidation::*;
+pub use transfer_obligation_collateral::*;
 pub use update_market::*;
 pub use update_market_owner::*;
+pub use update_market_risk_authority::*;
 pub use update_reserve::*;
+pub use update_reserve_config_template::*;
 pub use withdraw_obligation_collateral::*;
+pub use withdraw_obligation_collateral_and_redeem::*;
 pub use withdraw_protocol_fees::*;
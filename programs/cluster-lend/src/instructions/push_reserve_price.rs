@@ -0,0 +1,48 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    constants::PROGRAM_VERSION,
+    errors::LendingError,
+    lending_market::lending_operations,
+    state::{LendingMarket, Reserve},
+    utils::Fraction,
+    xmsg,
+};
+
+pub fn process_push_reserve_price(ctx: Context<PushReservePriceCtx>, price_sf: u128) -> Result<()> {
+    let clock = &Clock::get()?;
+    let reserve = &mut ctx.accounts.reserve.load_mut()?;
+
+    require!(
+        reserve.version == PROGRAM_VERSION as u64,
+        LendingError::ReserveDeprecated
+    );
+
+    let price = Fraction::from_bits(price_sf);
+    lending_operations::push_reserve_price(
+        reserve,
+        ctx.accounts.price_authority.key(),
+        price,
+        clock,
+    )?;
+
+    xmsg!(
+        "Token: {} Pushed price: {}",
+        reserve.config.token_info.symbol(),
+        price
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct PushReservePriceCtx<'info> {
+    pub price_authority: Signer<'info>,
+
+    pub lending_market: AccountLoader<'info, LendingMarket>,
+
+    #[account(mut,
+        has_one = lending_market
+    )]
+    pub reserve: AccountLoader<'info, Reserve>,
+}
@@ -0,0 +1,26 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::TokenAccount;
+
+use crate::{
+    lending_market::lending_operations,
+    state::{ReconcileReserveResult, Reserve},
+};
+
+pub fn process_reconcile_reserve(
+    ctx: Context<ReconcileReserveCtx>,
+) -> Result<ReconcileReserveResult> {
+    let reserve = &ctx.accounts.reserve.load()?;
+
+    Ok(lending_operations::reconcile_reserve(
+        reserve,
+        ctx.accounts.reserve_liquidity_supply.amount,
+    ))
+}
+
+#[derive(Accounts)]
+pub struct ReconcileReserveCtx<'info> {
+    pub reserve: AccountLoader<'info, Reserve>,
+
+    #[account(address = reserve.load()?.liquidity.supply_vault)]
+    pub reserve_liquidity_supply: Box<Account<'info, TokenAccount>>,
+}
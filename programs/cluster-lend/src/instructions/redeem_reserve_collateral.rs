@@ -9,7 +9,7 @@ use crate::{
     gen_signer_seeds,
     lending_market::{
         post_transfer_vault_balance_liquidity_reserve_checks, redeem_reserve_collateral,
-        redeem_reserve_collateral_checks, refresh_reserve,
+        redeem_reserve_collateral_checks, refresh_reserve, update_reserve_deposit_value,
     },
     state::{LendingAction, LendingMarket, RedeemReserveCollateralAccounts, Reserve},
     utils::{seeds, token_transfer}, xmsg,
@@ -41,11 +41,13 @@ pub fn process_redeem_reserve_collateral(
 
     let initial_reserve_token_balance =
         token::accessor::amount(&ctx.accounts.reserve_liquidity_supply.to_account_info())?;
+    reserve.reconcile_rebasing_vault_balance(initial_reserve_token_balance)?;
     let initial_reserve_available_liquidity = reserve.liquidity.available_amount;
 
     refresh_reserve(reserve, &clock, None)?;
     let withdraw_liquidity_amount =
         redeem_reserve_collateral(reserve, collateral_amount, &clock, true)?;
+    update_reserve_deposit_value(lending_market, reserve, false)?;
 
     xmsg!(
         "pnl: Redeeming reserve collateral {}",
@@ -56,7 +58,7 @@ pub fn process_redeem_reserve_collateral(
         ctx.accounts.token_program.to_account_info(),
         ctx.accounts.reserve_collateral_mint.to_account_info(),
         ctx.accounts.user_source_collateral.to_account_info(),
-        ctx.accounts.owner.to_account_info(),
+        ctx.accounts.collateral_authority(),
         ctx.accounts.reserve_liquidity_supply.to_account_info(),
         ctx.accounts.user_destination_liquidity.to_account_info(),
         ctx.accounts.lending_market_authority.clone(),
@@ -117,4 +119,17 @@ pub struct RedeemReserveCollateralCtx<'info> {
     /// CHECK: instruction_sysvar account
     #[account(address = SysInstructions::id())]
     pub instruction_sysvar_account: AccountInfo<'info>,
+
+    /// Burns the source collateral in place of the owner, for collateral held by an account the
+    /// owner doesn't sign for
+    pub user_transfer_authority: Option<Signer<'info>>,
+}
+
+impl<'info> RedeemReserveCollateralCtx<'info> {
+    pub fn collateral_authority(&self) -> AccountInfo<'info> {
+        self.user_transfer_authority
+            .as_ref()
+            .unwrap_or(&self.owner)
+            .to_account_info()
+    }
 }
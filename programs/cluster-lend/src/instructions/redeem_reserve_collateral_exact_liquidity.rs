@@ -0,0 +1,78 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token;
+
+use crate::{
+    gen_signer_seeds,
+    instructions::RedeemReserveCollateralCtx,
+    lending_market::{
+        post_transfer_vault_balance_liquidity_reserve_checks, redeem_reserve_collateral_checks,
+        redeem_reserve_collateral_exact_liquidity, refresh_reserve, update_reserve_deposit_value,
+    },
+    state::{LendingAction, RedeemReserveCollateralAccounts},
+    utils::token_transfer,
+    xmsg,
+};
+
+pub fn process_redeem_reserve_collateral_exact_liquidity(
+    ctx: Context<RedeemReserveCollateralCtx>,
+    liquidity_amount: u64,
+) -> Result<()> {
+    redeem_reserve_collateral_checks(&RedeemReserveCollateralAccounts {
+        user_source_collateral: ctx.accounts.user_source_collateral.clone(),
+        user_destination_liquidity: ctx.accounts.user_destination_liquidity.clone(),
+        reserve: ctx.accounts.reserve.clone(),
+        reserve_collateral_mint: ctx.accounts.reserve_collateral_mint.clone(),
+        reserve_liquidity_supply: ctx.accounts.reserve_liquidity_supply.clone(),
+        lending_market: ctx.accounts.lending_market.clone(),
+        lending_market_authority: ctx.accounts.lending_market_authority.clone(),
+        owner: ctx.accounts.owner.clone(),
+        token_program: ctx.accounts.token_program.clone(),
+    })?;
+
+    let reserve = &mut ctx.accounts.reserve.load_mut()?;
+    let lending_market = &ctx.accounts.lending_market.load()?;
+    let clock = Clock::get()?;
+
+    let lending_market_key = ctx.accounts.lending_market.key();
+    let authority_signer_seeds =
+        gen_signer_seeds!(lending_market_key.as_ref(), lending_market.bump as u8);
+
+    let initial_reserve_token_balance =
+        token::accessor::amount(&ctx.accounts.reserve_liquidity_supply.to_account_info())?;
+    reserve.reconcile_rebasing_vault_balance(initial_reserve_token_balance)?;
+    let initial_reserve_available_liquidity = reserve.liquidity.available_amount;
+
+    refresh_reserve(reserve, &clock, None)?;
+    let collateral_amount =
+        redeem_reserve_collateral_exact_liquidity(reserve, liquidity_amount, &clock)?;
+    update_reserve_deposit_value(lending_market, reserve, false)?;
+
+    xmsg!(
+        "pnl: Redeeming reserve collateral {} for exact liquidity {}",
+        collateral_amount,
+        liquidity_amount
+    );
+
+    token_transfer::redeem_reserve_collateral_transfer(
+        ctx.accounts.token_program.to_account_info(),
+        ctx.accounts.reserve_collateral_mint.to_account_info(),
+        ctx.accounts.user_source_collateral.to_account_info(),
+        ctx.accounts.collateral_authority(),
+        ctx.accounts.reserve_liquidity_supply.to_account_info(),
+        ctx.accounts.user_destination_liquidity.to_account_info(),
+        ctx.accounts.lending_market_authority.clone(),
+        authority_signer_seeds,
+        collateral_amount,
+        liquidity_amount,
+    )?;
+
+    post_transfer_vault_balance_liquidity_reserve_checks(
+        token::accessor::amount(&ctx.accounts.reserve_liquidity_supply.to_account_info()).unwrap(),
+        reserve.liquidity.available_amount,
+        initial_reserve_token_balance,
+        initial_reserve_available_liquidity,
+        LendingAction::Subtractive(liquidity_amount),
+    )?;
+
+    Ok(())
+}
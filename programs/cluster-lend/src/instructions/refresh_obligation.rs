@@ -25,7 +25,21 @@ pub fn process_refresh_obligation(ctx: Context<RefreshObligationCtx>) -> Result<
         .take(reserves_count)
         .map(|account_info| FatAccountLoader::<Reserve>::try_from(account_info).unwrap());
 
-    lending_operations::refresh_obligation(obligation, lending_market, clock.slot, reserves_iter)?;
+    if cfg!(feature = "compact-refresh") {
+        lending_operations::refresh_obligation_compact(
+            obligation,
+            lending_market,
+            clock.slot,
+            reserves_iter,
+        )?;
+    } else {
+        lending_operations::refresh_obligation(
+            obligation,
+            lending_market,
+            clock.slot,
+            reserves_iter,
+        )?;
+    }
 
     Ok(())
 }
@@ -8,31 +8,65 @@ use crate::{
     utils::{constraints, get_price}, xmsg,
 };
 
+/// A saved price younger than the market `price_refresh_trigger_to_max_age_pct` is kept without
+/// loading the oracle
 pub fn process_refresh_reserve(ctx: Context<RefreshReserveCtx>) -> Result<()> {
+    process_refresh_reserve_price(ctx, false)
+}
+
+/// Always loads the oracle, as liquidators would to price at the latest oracle update
+pub fn process_force_refresh_reserve(ctx: Context<RefreshReserveCtx>) -> Result<()> {
+    process_refresh_reserve_price(ctx, true)
+}
+
+fn process_refresh_reserve_price(
+    ctx: Context<RefreshReserveCtx>,
+    force_price_refresh: bool,
+) -> Result<()> {
     let clock = &Clock::get()?;
     let reserve = &mut ctx.accounts.reserve.load_mut()?;
     let lending_market = &ctx.accounts.lending_market.load()?;
 
     constraints::check_remaining_accounts(&ctx)?;
 
+    refresh_reserve_with_oracle(
+        reserve,
+        lending_market,
+        &ctx.accounts.pyth_oracle,
+        &ctx.accounts.fallback_oracle,
+        clock,
+        force_price_refresh,
+    )
+}
+
+pub(crate) fn refresh_reserve_with_oracle(
+    reserve: &mut Reserve,
+    lending_market: &LendingMarket,
+    pyth_oracle: &Option<AccountInfo>,
+    fallback_oracle: &Option<AccountInfo>,
+    clock: &Clock,
+    force_price_refresh: bool,
+) -> Result<()> {
     require!(
         reserve.version == PROGRAM_VERSION as u64,
         LendingError::ReserveDeprecated
     );
 
-    let price_res = if lending_operations::is_price_refresh_needed(
-        reserve,
-        lending_market,
-        clock.unix_timestamp,
-    ) {
+    let price_res = if force_price_refresh
+        || lending_operations::is_price_refresh_needed(
+            reserve,
+            lending_market,
+            clock.unix_timestamp,
+        ) {
         reserve
             .config
             .token_info
-            .validate_token_info_config(&ctx.accounts.pyth_oracle)?;
+            .validate_token_info_config(pyth_oracle, fallback_oracle)?;
 
         get_price(
             &reserve.config.token_info,
-            ctx.accounts.pyth_oracle.as_ref(),
+            pyth_oracle.as_ref(),
+            fallback_oracle.as_ref(),
             clock.unix_timestamp,
         )?
     } else {
@@ -61,4 +95,7 @@ pub struct RefreshReserveCtx<'info> {
     pub lending_market: AccountLoader<'info, LendingMarket>,
 
     pub pyth_oracle: Option<AccountInfo<'info>>,
+
+    /// Required when the reserve has a fallback oracle configured
+    pub fallback_oracle: Option<AccountInfo<'info>>,
 }
@@ -0,0 +1,67 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    errors::LendingError,
+    instructions::refresh_reserve_with_oracle,
+    state::{LendingMarket, Reserve},
+    utils::FatAccountLoader,
+    xmsg,
+};
+
+pub fn process_refresh_reserves(ctx: Context<RefreshReservesCtx>) -> Result<()> {
+    let clock = &Clock::get()?;
+    let lending_market = &ctx.accounts.lending_market.load()?;
+    let lending_market_key = ctx.accounts.lending_market.key();
+
+    if ctx.remaining_accounts.is_empty() || ctx.remaining_accounts.len() % 2 != 0 {
+        xmsg!(
+            "Expected pairs of reserve and price accounts, got {} remaining accounts",
+            ctx.remaining_accounts.len()
+        );
+        return err!(LendingError::InvalidAccountInput);
+    }
+
+    for (index, accounts) in ctx.remaining_accounts.chunks_exact(2).enumerate() {
+        let reserve_info = &accounts[0];
+        let pyth_oracle = if *accounts[1].key == crate::ID {
+            None
+        } else {
+            Some(accounts[1].clone())
+        };
+
+        let reserve_loader = FatAccountLoader::<Reserve>::try_from(reserve_info)?;
+        let reserve = &mut reserve_loader.load_mut()?;
+
+        if reserve.lending_market != lending_market_key {
+            xmsg!(
+                "Reserve {} at index {} does not belong to the lending market",
+                reserve_info.key,
+                index
+            );
+            return err!(LendingError::InvalidAccountInput);
+        }
+
+        if !reserve
+            .config
+            .token_info
+            .check_pyth_acc_matches(&pyth_oracle)
+        {
+            xmsg!(
+                "Price account at index {} does not match the config of reserve {}",
+                index,
+                reserve_info.key
+            );
+            return err!(LendingError::InvalidPythPriceAccount);
+        }
+
+        // reserves with a fallback oracle have to be refreshed one by one with `refresh_reserve`
+        refresh_reserve_with_oracle(reserve, lending_market, &pyth_oracle, &None, clock, false)?;
+    }
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct RefreshReservesCtx<'info> {
+    pub lending_market: AccountLoader<'info, LendingMarket>,
+}
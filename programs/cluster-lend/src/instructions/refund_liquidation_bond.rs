@@ -0,0 +1,37 @@
+use anchor_lang::prelude::*;
+
+use crate::{xmsg, LendingMarket, LiquidationBond};
+
+pub fn process_refund_liquidation_bond(ctx: Context<RefundLiquidationBondCtx>) -> Result<()> {
+    let lending_market = &ctx.accounts.lending_market.load()?;
+    let liquidation_bond = &ctx.accounts.liquidation_bond.load()?;
+    let clock = Clock::get()?;
+
+    liquidation_bond.check_refundable(
+        clock.unix_timestamp,
+        lending_market.liquidation_bond_challenge_window_secs,
+    )?;
+
+    xmsg!(
+        "Refunding liquidation bond {} for obligation {}",
+        liquidation_bond.amount,
+        liquidation_bond.obligation
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct RefundLiquidationBondCtx<'info> {
+    #[account(mut)]
+    pub liquidator: Signer<'info>,
+
+    pub lending_market: AccountLoader<'info, LendingMarket>,
+
+    #[account(mut,
+        has_one = lending_market,
+        has_one = liquidator,
+        close = liquidator,
+    )]
+    pub liquidation_bond: AccountLoader<'info, LiquidationBond>,
+}
@@ -0,0 +1,28 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    lending_market::lending_operations,
+    state::{LendingMarket, Obligation},
+};
+
+pub fn process_reorder_obligation_deposits(
+    ctx: Context<ReorderObligationDepositsCtx>,
+    order: [u8; 8],
+) -> Result<()> {
+    let obligation = &mut ctx.accounts.obligation.load_mut()?;
+
+    lending_operations::reorder_obligation_deposits(obligation, order)
+}
+
+#[derive(Accounts)]
+pub struct ReorderObligationDepositsCtx<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(mut,
+        has_one = lending_market,
+        has_one = owner
+    )]
+    pub obligation: AccountLoader<'info, Obligation>,
+
+    pub lending_market: AccountLoader<'info, LendingMarket>,
+}
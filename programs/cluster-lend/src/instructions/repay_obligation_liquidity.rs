@@ -7,16 +7,17 @@ use anchor_spl::token::{self, Token, TokenAccount};
 
 use crate::{
     check_refresh_ixs,
+    errors::LendingError,
     lending_market::{lending_checks, lending_operations},
     state::{LendingAction, LendingMarket, Reserve},
-    utils::token_transfer,
-    xmsg, Obligation,
+    utils::{token_transfer, Fraction},
+    xmsg, Obligation, RepayObligationLiquidityResult,
 };
 
 pub fn process_repay_obligation_liquidity(
     ctx: Context<RepayObligationLiquidityCtx>,
     liquidity_amount: u64,
-) -> Result<()> {
+) -> Result<RepayObligationLiquidityResult> {
     check_refresh_ixs!(ctx, repay_reserve);
     lending_checks::repay_obligation_liquidity_checks(&ctx)?;
 
@@ -28,21 +29,34 @@ pub fn process_repay_obligation_liquidity(
 
     let initial_reserve_token_balance =
         token::accessor::amount(&ctx.accounts.reserve_destination_liquidity.to_account_info())?;
+    repay_reserve.reconcile_rebasing_vault_balance(initial_reserve_token_balance)?;
     let initial_reserve_available_liquidity = repay_reserve.liquidity.available_amount;
 
+    // the repay fee is paid from the same balance, on top of the repaid amount
+    let available_liquidity_amount = repay_reserve
+        .config
+        .max_repay_amount_before_fees(ctx.accounts.user_source_liquidity.amount)?;
+
     let repay_amount = lending_operations::repay_obligation_liquidity(
         repay_reserve,
         obligation,
         &clock,
         liquidity_amount,
+        available_liquidity_amount,
         ctx.accounts.repay_reserve.key(),
         lending_market,
     )?;
+    lending_operations::update_reserve_isolated_debt_value(lending_market, repay_reserve, false)?;
+
+    let repay_fee = repay_reserve
+        .config
+        .calculate_repay_fees(Fraction::from(repay_amount))?;
 
     xmsg!(
-        "pnl: Repaying obligation liquidity {} liquidity_amount {}",
+        "pnl: Repaying obligation liquidity {} liquidity_amount {} repay_fee {}",
         repay_amount,
-        liquidity_amount
+        liquidity_amount,
+        repay_fee
     );
 
     token_transfer::repay_obligation_liquidity_transfer(
@@ -62,7 +76,31 @@ pub fn process_repay_obligation_liquidity(
         LendingAction::Additive(repay_amount),
     )?;
 
-    Ok(())
+    if repay_fee > 0 {
+        let Some(fee_receiver) = &ctx.accounts.repay_reserve_liquidity_fee_receiver else {
+            xmsg!("Repay fee {} requires the reserve fee vault", repay_fee);
+            return err!(LendingError::InvalidAccountInput);
+        };
+
+        let initial_fee_vault_balance = fee_receiver.amount;
+        token_transfer::repay_obligation_liquidity_transfer(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.user_source_liquidity.to_account_info(),
+            fee_receiver.to_account_info(),
+            ctx.accounts.owner.to_account_info(),
+            repay_fee,
+        )?;
+
+        // the fee stays out of the reserve's available liquidity
+        let fee_vault_balance = token::accessor::amount(&fee_receiver.to_account_info())?;
+        require_eq!(
+            initial_fee_vault_balance + repay_fee,
+            fee_vault_balance,
+            LendingError::ReserveVaultBalanceMismatch
+        );
+    }
+
+    Ok(RepayObligationLiquidityResult { repay_amount })
 }
 
 #[derive(Accounts)]
@@ -97,4 +135,10 @@ pub struct RepayObligationLiquidityCtx<'info> {
     /// CHECK: instruction_sysvar account
     #[account(address = SysInstructions::id())]
     pub instruction_sysvar_account: AccountInfo<'info>,
+
+    /// Required when the reserve charges a repay fee
+    #[account(mut,
+        address = repay_reserve.load()?.liquidity.fee_vault
+    )]
+    pub repay_reserve_liquidity_fee_receiver: Option<Box<Account<'info, TokenAccount>>>,
 }
@@ -0,0 +1,117 @@
+use anchor_lang::{
+    prelude::*,
+    solana_program::sysvar::{instructions::Instructions as SysInstructions, SysvarId},
+    Accounts,
+};
+use anchor_spl::token::{self, Mint, Token, TokenAccount};
+
+use crate::{
+    check_refresh_ixs, gen_signer_seeds,
+    lending_market::{lending_checks, lending_operations},
+    state::{LendingAction, LendingMarket, Reserve},
+    utils::{seeds, token_transfer},
+    xmsg, Obligation,
+};
+
+pub fn process_repay_obligation_liquidity_with_collateral(
+    ctx: Context<RepayObligationLiquidityWithCollateralCtx>,
+    collateral_amount: u64,
+) -> Result<()> {
+    check_refresh_ixs!(ctx, reserve);
+
+    let clock = Clock::get()?;
+
+    let reserve = &mut ctx.accounts.reserve.load_mut()?;
+    let obligation = &mut ctx.accounts.obligation.load_mut()?;
+    let lending_market = &ctx.accounts.lending_market.load()?;
+
+    let lending_market_key = ctx.accounts.lending_market.key();
+    let authority_signer_seeds =
+        gen_signer_seeds!(lending_market_key.as_ref(), lending_market.bump as u8);
+
+    let initial_reserve_token_balance =
+        token::accessor::amount(&ctx.accounts.reserve_liquidity_supply.to_account_info())?;
+    reserve.reconcile_rebasing_vault_balance(initial_reserve_token_balance)?;
+    let initial_reserve_available_liquidity = reserve.liquidity.available_amount;
+
+    let (burn_collateral_amount, repay_amount) =
+        lending_operations::repay_obligation_liquidity_with_collateral(
+            reserve,
+            obligation,
+            &clock,
+            collateral_amount,
+            ctx.accounts.reserve.key(),
+            lending_market,
+        )?;
+
+    xmsg!(
+        "pnl: Repaying obligation liquidity {} with collateral {}",
+        repay_amount,
+        burn_collateral_amount
+    );
+
+    token_transfer::repay_obligation_liquidity_with_collateral_transfer(
+        ctx.accounts.token_program.to_account_info(),
+        ctx.accounts.reserve_collateral_mint.to_account_info(),
+        ctx.accounts.reserve_source_collateral.to_account_info(),
+        ctx.accounts.lending_market_authority.clone(),
+        authority_signer_seeds,
+        burn_collateral_amount,
+    )?;
+
+    lending_checks::post_transfer_vault_balance_liquidity_reserve_checks(
+        token::accessor::amount(&ctx.accounts.reserve_liquidity_supply.to_account_info()).unwrap(),
+        reserve.liquidity.available_amount,
+        initial_reserve_token_balance,
+        initial_reserve_available_liquidity,
+        LendingAction::Additive(0),
+    )?;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct RepayObligationLiquidityWithCollateralCtx<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(mut,
+        has_one = lending_market,
+        has_one = owner
+    )]
+    pub obligation: AccountLoader<'info, Obligation>,
+
+    pub lending_market: AccountLoader<'info, LendingMarket>,
+
+    /// CHECK: market authority PDA
+    #[account(
+        seeds = [seeds::LENDING_MARKET_AUTH, lending_market.key().as_ref()],
+        bump = lending_market.load()?.bump as u8,
+    )]
+    pub lending_market_authority: AccountInfo<'info>,
+
+    #[account(mut,
+        has_one = lending_market
+    )]
+    pub reserve: AccountLoader<'info, Reserve>,
+
+    #[account(mut,
+        address = reserve.load()?.collateral.mint_pubkey
+    )]
+    pub reserve_collateral_mint: Box<Account<'info, Mint>>,
+
+    #[account(mut,
+        address = reserve.load()?.collateral.supply_vault
+    )]
+    pub reserve_source_collateral: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        address = reserve.load()?.liquidity.supply_vault
+    )]
+    pub reserve_liquidity_supply: Box<Account<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+
+    /// CHECK: instruction_sysvar account
+    #[account(address = SysInstructions::id())]
+    pub instruction_sysvar_account: AccountInfo<'info>,
+}
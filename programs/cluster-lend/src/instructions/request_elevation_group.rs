@@ -0,0 +1,53 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    errors::LendingError,
+    lending_market::lending_operations,
+    state::{LendingMarket, Obligation, Reserve},
+    utils::FatAccountLoader,
+    xmsg,
+};
+
+pub fn process_request_elevation_group(
+    ctx: Context<RequestElevationGroupCtx>,
+    elevation_group: u8,
+) -> Result<()> {
+    let obligation = &mut ctx.accounts.obligation.load_mut()?;
+    let clock = &Clock::get()?;
+    let lending_market = &ctx.accounts.lending_market.load()?;
+    let reserves_count = obligation.borrows_count() + obligation.deposits_count();
+
+    if ctx.remaining_accounts.iter().len() != reserves_count {
+        xmsg!("expected_remaining_accounts={}", reserves_count,);
+        return err!(LendingError::InvalidAccountInput);
+    }
+
+    let reserves_iter = ctx
+        .remaining_accounts
+        .iter()
+        .take(reserves_count)
+        .map(|account_info| FatAccountLoader::<Reserve>::try_from(account_info).unwrap());
+
+    lending_operations::request_elevation_group(
+        obligation,
+        lending_market,
+        clock.slot,
+        elevation_group,
+        reserves_iter,
+    )?;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct RequestElevationGroupCtx<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(mut,
+        has_one = lending_market,
+        has_one = owner
+    )]
+    pub obligation: AccountLoader<'info, Obligation>,
+
+    pub lending_market: AccountLoader<'info, LendingMarket>,
+}
@@ -0,0 +1,36 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    errors::LendingError,
+    state::{LendingMarket, ObligationAllowlist},
+    xmsg,
+};
+
+pub fn process_revoke_obligation_owner(ctx: Context<RevokeObligationOwnerCtx>) -> Result<()> {
+    let obligation_allowlist = &ctx.accounts.obligation_allowlist.load()?;
+
+    // Existing obligations are left untouched, only new ones are prevented
+    xmsg!(
+        "Revoked {} from opening obligations",
+        obligation_allowlist.owner
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct RevokeObligationOwnerCtx<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        has_one = owner @ LendingError::InvalidMarketOwner,
+    )]
+    pub lending_market: AccountLoader<'info, LendingMarket>,
+
+    #[account(mut,
+        has_one = lending_market,
+        close = owner,
+    )]
+    pub obligation_allowlist: AccountLoader<'info, ObligationAllowlist>,
+}
@@ -0,0 +1,53 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    errors::LendingError,
+    lending_market::lending_operations,
+    state::{LendingMarket, Obligation, Reserve},
+    utils::FatAccountLoader,
+    xmsg,
+};
+
+pub fn process_set_aggressive_mode(
+    ctx: Context<SetAggressiveModeCtx>,
+    ltv_boost_pct: u8,
+) -> Result<()> {
+    let obligation = &mut ctx.accounts.obligation.load_mut()?;
+    let clock = &Clock::get()?;
+    let lending_market = &ctx.accounts.lending_market.load()?;
+    let reserves_count = obligation.borrows_count() + obligation.deposits_count();
+
+    if ctx.remaining_accounts.iter().len() != reserves_count {
+        xmsg!("expected_remaining_accounts={}", reserves_count,);
+        return err!(LendingError::InvalidAccountInput);
+    }
+
+    let reserves_iter = ctx
+        .remaining_accounts
+        .iter()
+        .take(reserves_count)
+        .map(|account_info| FatAccountLoader::<Reserve>::try_from(account_info).unwrap());
+
+    lending_operations::set_aggressive_mode(
+        obligation,
+        lending_market,
+        clock,
+        ltv_boost_pct,
+        reserves_iter,
+    )?;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetAggressiveModeCtx<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(mut,
+        has_one = lending_market,
+        has_one = owner
+    )]
+    pub obligation: AccountLoader<'info, Obligation>,
+
+    pub lending_market: AccountLoader<'info, LendingMarket>,
+}
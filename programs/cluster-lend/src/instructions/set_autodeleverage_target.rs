@@ -0,0 +1,31 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    lending_market::lending_operations,
+    state::{LendingMarket, Obligation},
+};
+
+pub fn process_set_autodeleverage_target(
+    ctx: Context<SetAutodeleverageTargetCtx>,
+    target_ltv_pct: u8,
+    bonus_bps: u16,
+) -> Result<()> {
+    let obligation = &mut ctx.accounts.obligation.load_mut()?;
+
+    lending_operations::set_autodeleverage_target(obligation, target_ltv_pct, bonus_bps)?;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetAutodeleverageTargetCtx<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(mut,
+        has_one = lending_market,
+        has_one = owner
+    )]
+    pub obligation: AccountLoader<'info, Obligation>,
+
+    pub lending_market: AccountLoader<'info, LendingMarket>,
+}
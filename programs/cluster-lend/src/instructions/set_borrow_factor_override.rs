@@ -0,0 +1,33 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    errors::LendingError,
+    lending_market::lending_operations,
+    state::{LendingMarket, Obligation},
+};
+
+pub fn process_set_borrow_factor_override(
+    ctx: Context<SetBorrowFactorOverrideCtx>,
+    borrow_factor_override_pct: u64,
+) -> Result<()> {
+    let obligation = &mut ctx.accounts.obligation.load_mut()?;
+
+    lending_operations::set_borrow_factor_override(obligation, borrow_factor_override_pct)?;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetBorrowFactorOverrideCtx<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        has_one = owner @ LendingError::InvalidMarketOwner,
+    )]
+    pub lending_market: AccountLoader<'info, LendingMarket>,
+
+    #[account(mut,
+        has_one = lending_market
+    )]
+    pub obligation: AccountLoader<'info, Obligation>,
+}
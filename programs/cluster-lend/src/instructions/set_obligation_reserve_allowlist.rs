@@ -0,0 +1,35 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    lending_market::lending_operations,
+    state::{LendingMarket, Obligation},
+};
+
+pub fn process_set_obligation_reserve_allowlist(
+    ctx: Context<SetObligationReserveAllowlistCtx>,
+    allowed_deposit_reserves: [Pubkey; 4],
+    allowed_borrow_reserves: [Pubkey; 2],
+) -> Result<()> {
+    let obligation = &mut ctx.accounts.obligation.load_mut()?;
+
+    lending_operations::set_obligation_reserve_allowlist(
+        obligation,
+        allowed_deposit_reserves,
+        allowed_borrow_reserves,
+    )?;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetObligationReserveAllowlistCtx<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(mut,
+        has_one = lending_market,
+        has_one = owner
+    )]
+    pub obligation: AccountLoader<'info, Obligation>,
+
+    pub lending_market: AccountLoader<'info, LendingMarket>,
+}
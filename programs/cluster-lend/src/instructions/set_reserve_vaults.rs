@@ -0,0 +1,84 @@
+use anchor_lang::{prelude::*, Accounts};
+use anchor_spl::token::TokenAccount;
+
+use crate::{
+    errors::LendingError,
+    state::{LendingMarket, Reserve, ReserveStatus},
+    utils::seeds,
+    xmsg,
+};
+
+pub fn process_set_reserve_vaults(ctx: Context<SetReserveVaultsCtx>) -> Result<()> {
+    let reserve = &mut ctx.accounts.reserve.load_mut()?;
+
+    if reserve.config.status() != ReserveStatus::Hidden {
+        xmsg!(
+            "Reserve {} must be hidden before its vaults are repointed",
+            reserve.config.token_info.symbol()
+        );
+        return err!(LendingError::ReserveNotHidden);
+    }
+
+    let liquidity_supply_vault = ctx.accounts.liquidity_supply_vault.key();
+    let fee_vault = ctx.accounts.fee_vault.key();
+    let collateral_supply_vault = ctx.accounts.collateral_supply_vault.key();
+
+    xmsg!(
+        "Prev vaults are liquidity {} fee {} collateral {}",
+        reserve.liquidity.supply_vault,
+        reserve.liquidity.fee_vault,
+        reserve.collateral.supply_vault
+    );
+    xmsg!(
+        "New vaults are liquidity {} fee {} collateral {}",
+        liquidity_supply_vault,
+        fee_vault,
+        collateral_supply_vault
+    );
+
+    reserve.liquidity.supply_vault = liquidity_supply_vault;
+    reserve.liquidity.fee_vault = fee_vault;
+    reserve.collateral.supply_vault = collateral_supply_vault;
+    reserve.last_update.mark_stale();
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetReserveVaultsCtx<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(has_one = owner)]
+    pub lending_market: AccountLoader<'info, LendingMarket>,
+
+    #[account(mut,
+        has_one = lending_market
+    )]
+    pub reserve: AccountLoader<'info, Reserve>,
+
+    /// CHECK: market authority PDA
+    #[account(
+        seeds = [seeds::LENDING_MARKET_AUTH, lending_market.key().as_ref()],
+        bump = lending_market.load()?.bump as u8,
+    )]
+    pub lending_market_authority: AccountInfo<'info>,
+
+    #[account(
+        token::mint = reserve.load()?.liquidity.mint_pubkey,
+        token::authority = lending_market_authority,
+    )]
+    pub liquidity_supply_vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        token::mint = reserve.load()?.liquidity.mint_pubkey,
+        token::authority = lending_market_authority,
+        constraint = fee_vault.key() != liquidity_supply_vault.key() @ LendingError::InvalidAccountInput,
+    )]
+    pub fee_vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        token::mint = reserve.load()?.collateral.mint_pubkey,
+        token::authority = lending_market_authority,
+    )]
+    pub collateral_supply_vault: Box<Account<'info, TokenAccount>>,
+}
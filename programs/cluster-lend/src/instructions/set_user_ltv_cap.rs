@@ -0,0 +1,30 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    lending_market::lending_operations,
+    state::{LendingMarket, Obligation},
+};
+
+pub fn process_set_user_ltv_cap(
+    ctx: Context<SetUserLtvCapCtx>,
+    user_ltv_cap_pct: u8,
+) -> Result<()> {
+    let obligation = &mut ctx.accounts.obligation.load_mut()?;
+
+    lending_operations::set_user_ltv_cap(obligation, user_ltv_cap_pct)?;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetUserLtvCapCtx<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(mut,
+        has_one = lending_market,
+        has_one = owner
+    )]
+    pub obligation: AccountLoader<'info, Obligation>,
+
+    pub lending_market: AccountLoader<'info, LendingMarket>,
+}
@@ -0,0 +1,48 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    lending_market::lending_operations,
+    state::{LendingMarket, Obligation, Reserve, SimulateLiquidationResult},
+};
+
+pub fn process_simulate_liquidation(
+    ctx: Context<SimulateLiquidationCtx>,
+    liquidity_amount: u64,
+) -> Result<SimulateLiquidationResult> {
+    let lending_market = &ctx.accounts.lending_market.load()?;
+    let obligation = &ctx.accounts.obligation.load()?;
+    let repay_reserve = &ctx.accounts.repay_reserve.load()?;
+    let withdraw_reserve = &ctx.accounts.withdraw_reserve.load()?;
+    let clock = Clock::get()?;
+
+    lending_operations::simulate_liquidation(
+        lending_market,
+        repay_reserve,
+        withdraw_reserve,
+        obligation,
+        ctx.accounts.repay_reserve.key(),
+        ctx.accounts.withdraw_reserve.key(),
+        clock.slot,
+        liquidity_amount,
+    )
+}
+
+#[derive(Accounts)]
+pub struct SimulateLiquidationCtx<'info> {
+    pub lending_market: AccountLoader<'info, LendingMarket>,
+
+    #[account(
+        has_one = lending_market
+    )]
+    pub obligation: AccountLoader<'info, Obligation>,
+
+    #[account(
+        has_one = lending_market
+    )]
+    pub repay_reserve: AccountLoader<'info, Reserve>,
+
+    #[account(
+        has_one = lending_market
+    )]
+    pub withdraw_reserve: AccountLoader<'info, Reserve>,
+}
@@ -0,0 +1,89 @@
+use anchor_lang::{
+    prelude::*,
+    solana_program::sysvar::{instructions::Instructions as SysInstructions, SysvarId},
+    Accounts,
+};
+
+use crate::{
+    errors::LendingError,
+    lending_market::{lending_checks, lending_operations},
+    state::{CpiActionFlags, LendingMarket, Reserve},
+    utils::close_account_loader,
+    xmsg, Obligation,
+};
+
+pub fn process_transfer_obligation_collateral(
+    ctx: Context<TransferObligationCollateralCtx>,
+    collateral_amount: u64,
+) -> Result<()> {
+    let close_source_obligation = {
+        lending_checks::cpi_allowed_checks(
+            &ctx.accounts.lending_market,
+            &ctx.accounts.instruction_sysvar_account,
+            CpiActionFlags::WITHDRAW,
+        )?;
+
+        let clock = &Clock::get()?;
+
+        let reserve = &mut ctx.accounts.reserve.load_mut()?;
+        let source_obligation = &mut ctx.accounts.source_obligation.load_mut()?;
+        let destination_obligation = &mut ctx.accounts.destination_obligation.load_mut()?;
+        let lending_market = &ctx.accounts.lending_market.load()?;
+
+        let transfer_amount = lending_operations::transfer_obligation_collateral(
+            lending_market,
+            reserve,
+            source_obligation,
+            destination_obligation,
+            collateral_amount,
+            clock.slot,
+            ctx.accounts.reserve.key(),
+        )?;
+
+        xmsg!(
+            "Transferred obligation collateral {} from {} to {}",
+            transfer_amount,
+            ctx.accounts.source_obligation.key(),
+            ctx.accounts.destination_obligation.key()
+        );
+
+        source_obligation.deposits_empty() && source_obligation.borrows_empty()
+    };
+
+    close_account_loader(
+        close_source_obligation,
+        &ctx.accounts.owner,
+        &ctx.accounts.source_obligation,
+    )?;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct TransferObligationCollateralCtx<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(mut,
+        has_one = lending_market,
+        has_one = owner
+    )]
+    pub source_obligation: AccountLoader<'info, Obligation>,
+
+    #[account(mut,
+        has_one = lending_market,
+        has_one = owner,
+        constraint = destination_obligation.key() != source_obligation.key() @ LendingError::InvalidAccountInput
+    )]
+    pub destination_obligation: AccountLoader<'info, Obligation>,
+
+    pub lending_market: AccountLoader<'info, LendingMarket>,
+
+    #[account(mut,
+        has_one = lending_market
+    )]
+    pub reserve: AccountLoader<'info, Reserve>,
+
+    /// CHECK: instruction_sysvar account
+    #[account(address = SysInstructions::id())]
+    pub instruction_sysvar_account: AccountInfo<'info>,
+}
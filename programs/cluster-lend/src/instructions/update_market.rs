@@ -1,9 +1,9 @@
 use anchor_lang::prelude::*;
 
 use crate::{
-    constants::VALUE_BYTE_MAX_ARRAY_LEN_MARKET_UPDATE,
+    constants::{FULL_BPS, VALUE_BYTE_MAX_ARRAY_LEN_MARKET_UPDATE},
     errors::LendingError,
-    state::{LendingMarket, UpdateLendingMarketMode},
+    state::{CpiActionFlags, ElevationGroup, LendingMarket, UpdateLendingMarketMode},
     utils::{validate_numerical_bool, Fraction}, xmsg,
 };
 
@@ -23,6 +23,14 @@ pub fn process_update_market(
         &value[0..32]
     );
 
+    // the risk authority can only switch the emergency mode on
+    if market.owner != ctx.accounts.signer.key()
+        && !(mode == UpdateLendingMarketMode::UpdateEmergencyMode && value[0] == 1)
+    {
+        xmsg!("Risk authority can only enable the emergency mode");
+        return err!(LendingError::RiskAuthorityUpdateNotAllowed);
+    }
+
     match mode {
         UpdateLendingMarketMode::UpdateEmergencyMode => {
             let emergency_mode = value[0];
@@ -61,6 +69,16 @@ pub fn process_update_market(
             xmsg!("Value is {:?}", value);
             market.global_unhealthy_borrow_value = value;
         }
+        UpdateLendingMarketMode::UpdateReferralFeeBps => {
+            let referral_fee_bps = u16::from_le_bytes(value[..2].try_into().unwrap());
+            xmsg!("Prev Value is {:?}", market.referral_fee_bps);
+            xmsg!("New Value is {:?}", referral_fee_bps);
+            if referral_fee_bps > FULL_BPS {
+                xmsg!("Referral fee bps must be in range [0, {}]", FULL_BPS);
+                return err!(LendingError::InvalidFlag);
+            }
+            market.referral_fee_bps = referral_fee_bps;
+        }
         UpdateLendingMarketMode::UpdateMinFullLiquidationThreshold => {
             let value = u64::from_le_bytes(value[..8].try_into().unwrap());
             xmsg!("Value is {:?}", value);
@@ -123,6 +141,152 @@ pub fn process_update_market(
             );
             market.min_net_value_in_obligation_sf = min_net_value_in_obligation_sf;
         }
+        UpdateLendingMarketMode::UpdateElevationGroup => {
+            let new = ElevationGroup {
+                id: value[0],
+                ltv_pct: value[1],
+                liquidation_threshold_pct: value[2],
+                padding: [0; 5],
+            };
+            let prv = market.get_elevation_group(new.id).ok().flatten().copied();
+            xmsg!("Prev Value is {:?}", prv);
+            xmsg!("New Value is {:?}", new);
+            market.set_elevation_group(new)?;
+        }
+        UpdateLendingMarketMode::UpdateLiquidationBonusNetOfProtocolFee => {
+            let liquidation_bonus_net_of_protocol_fee = value[0];
+            xmsg!(
+                "Prev Value is {:?}",
+                market.liquidation_bonus_net_of_protocol_fee
+            );
+            xmsg!("New Value is {:?}", liquidation_bonus_net_of_protocol_fee);
+            validate_numerical_bool(liquidation_bonus_net_of_protocol_fee)?;
+            market.liquidation_bonus_net_of_protocol_fee = liquidation_bonus_net_of_protocol_fee;
+        }
+        UpdateLendingMarketMode::UpdateLiquidationBondRequired => {
+            let liquidation_bond_required = value[0];
+            xmsg!("Prev Value is {:?}", market.liquidation_bond_required);
+            xmsg!("New Value is {:?}", liquidation_bond_required);
+            validate_numerical_bool(liquidation_bond_required)?;
+            market.liquidation_bond_required = liquidation_bond_required;
+        }
+        UpdateLendingMarketMode::UpdateLiquidationBondChallengeWindowSecs => {
+            let value = u64::from_le_bytes(value[..8].try_into().unwrap());
+            xmsg!(
+                "Prev Value is {:?}",
+                market.liquidation_bond_challenge_window_secs
+            );
+            xmsg!("New Value is {:?}", value);
+            market.liquidation_bond_challenge_window_secs = value;
+        }
+        UpdateLendingMarketMode::UpdateHostFeeBps => {
+            let host_fee_bps = u16::from_le_bytes(value[..2].try_into().unwrap());
+            xmsg!("Prev Value is {:?}", market.host_fee_bps);
+            xmsg!("New Value is {:?}", host_fee_bps);
+            if host_fee_bps > FULL_BPS {
+                xmsg!("Host fee bps must be in range [0, {}]", FULL_BPS);
+                return err!(LendingError::InvalidFlag);
+            }
+            market.host_fee_bps = host_fee_bps;
+        }
+        UpdateLendingMarketMode::UpdatePermissioned => {
+            let permissioned = value[0];
+            xmsg!("Prev Value is {:?}", market.permissioned);
+            xmsg!("New Value is {:?}", permissioned);
+            validate_numerical_bool(permissioned)?;
+            market.permissioned = permissioned;
+        }
+        UpdateLendingMarketMode::UpdateAggressiveModeMaxLtvBoostPct => {
+            let max_ltv_boost_pct = value[0];
+            xmsg!(
+                "Prev Value is {:?}",
+                market.aggressive_mode_max_ltv_boost_pct
+            );
+            xmsg!("New Value is {:?}", max_ltv_boost_pct);
+            if max_ltv_boost_pct >= 100 {
+                xmsg!("Aggressive mode max LTV boost must be in range [0, 100)");
+                return err!(LendingError::InvalidConfig);
+            }
+            market.aggressive_mode_max_ltv_boost_pct = max_ltv_boost_pct;
+        }
+        UpdateLendingMarketMode::UpdateAggressiveModeLiquidationBufferPct => {
+            let liquidation_buffer_pct = value[0];
+            xmsg!(
+                "Prev Value is {:?}",
+                market.aggressive_mode_liquidation_buffer_pct
+            );
+            xmsg!("New Value is {:?}", liquidation_buffer_pct);
+            if liquidation_buffer_pct == 0 || liquidation_buffer_pct > 100 {
+                xmsg!("Aggressive mode liquidation buffer must be in range [1, 100]");
+                return err!(LendingError::InvalidConfig);
+            }
+            market.aggressive_mode_liquidation_buffer_pct = liquidation_buffer_pct;
+        }
+        UpdateLendingMarketMode::UpdateAggressiveModeCooldownSecs => {
+            let value = u64::from_le_bytes(value[..8].try_into().unwrap());
+            xmsg!("Prev Value is {:?}", market.aggressive_mode_cooldown_secs);
+            xmsg!("New Value is {:?}", value);
+            market.aggressive_mode_cooldown_secs = value;
+        }
+        UpdateLendingMarketMode::UpdateMaxObligationValue => {
+            let value = u64::from_le_bytes(value[..8].try_into().unwrap());
+            xmsg!("Prev Value is {:?}", market.max_obligation_value);
+            xmsg!("New Value is {:?}", value);
+            market.max_obligation_value = value;
+        }
+        UpdateLendingMarketMode::UpdateLiquidationGraceSlots => {
+            let value = u64::from_le_bytes(value[..8].try_into().unwrap());
+            xmsg!("Prev Value is {:?}", market.liquidation_grace_slots);
+            xmsg!("New Value is {:?}", value);
+            market.liquidation_grace_slots = value;
+        }
+        UpdateLendingMarketMode::UpdateGlobalAllowedDeposit => {
+            let value = u64::from_le_bytes(value[..8].try_into().unwrap());
+            xmsg!("Prev Value is {:?}", market.global_allowed_deposit_value);
+            xmsg!("New Value is {:?}", value);
+            market.global_allowed_deposit_value = value;
+        }
+        UpdateLendingMarketMode::UpdateCpiDisabledFlags => {
+            let value = value[0];
+            xmsg!("Prev Value is {:?}", market.cpi_disabled_flags);
+            xmsg!("New Value is {:?}", value);
+            if CpiActionFlags::from_bits(value).is_none() {
+                return err!(LendingError::InvalidFlag);
+            }
+            market.cpi_disabled_flags = value;
+        }
+        UpdateLendingMarketMode::UpdateDepositsDisabled => {
+            let deposits_disabled = value[0];
+            xmsg!("Prev Value is {:?}", market.deposits_disabled);
+            xmsg!("New Value is {:?}", deposits_disabled);
+            validate_numerical_bool(deposits_disabled)?;
+            market.deposits_disabled = deposits_disabled;
+        }
+        UpdateLendingMarketMode::UpdateDeprecationGraceSlots => {
+            let value = u64::from_le_bytes(value[..8].try_into().unwrap());
+            xmsg!("Prev Value is {:?}", market.deprecation_grace_slots);
+            xmsg!("New Value is {:?}", value);
+            market.deprecation_grace_slots = value;
+        }
+        UpdateLendingMarketMode::UpdateRewardVault => {
+            let value: [u8; 32] = value[..32].try_into().unwrap();
+            let value = Pubkey::new_from_array(value);
+            xmsg!("Prev Value is {:?}", market.reward_vault);
+            xmsg!("New Value is {:?}", value);
+            market.reward_vault = value;
+        }
+        UpdateLendingMarketMode::UpdateMaxObligationReserves => {
+            let max_obligation_reserves = value[0];
+            xmsg!("Prev Value is {:?}", market.max_obligation_reserves);
+            xmsg!("New Value is {:?}", max_obligation_reserves);
+            market.max_obligation_reserves = max_obligation_reserves;
+        }
+        UpdateLendingMarketMode::UpdateIsolatedDebtBorrowValueCap => {
+            let value = u64::from_le_bytes(value[..8].try_into().unwrap());
+            xmsg!("Prev Value is {:?}", market.isolated_debt_borrow_value_cap);
+            xmsg!("New Value is {:?}", value);
+            market.isolated_debt_borrow_value_cap = value;
+        }
     }
 
     Ok(())
@@ -130,10 +294,11 @@ pub fn process_update_market(
 
 #[derive(Accounts)]
 pub struct UpdateMarketCtx<'info> {
-    pub owner: Signer<'info>,
+    /// Market owner, or the risk authority to enable the emergency mode
+    pub signer: Signer<'info>,
 
     #[account(mut,
-        has_one = owner
+        constraint = lending_market.load()?.is_config_authority(signer.key) @ LendingError::InvalidMarketOwner
     )]
     pub lending_market: AccountLoader<'info, LendingMarket>,
 
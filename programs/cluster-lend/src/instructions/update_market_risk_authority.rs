@@ -0,0 +1,27 @@
+use anchor_lang::prelude::*;
+
+use crate::{state::LendingMarket, xmsg};
+
+pub fn process_update_market_risk_authority(
+    ctx: Context<UpdateMarketRiskAuthorityCtx>,
+) -> Result<()> {
+    let market = &mut ctx.accounts.lending_market.load_mut()?;
+    xmsg!("Prev Value is {:?}", market.risk_authority);
+    xmsg!("New Value is {:?}", ctx.accounts.new_risk_authority.key());
+    market.risk_authority = ctx.accounts.new_risk_authority.key();
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct UpdateMarketRiskAuthorityCtx<'info> {
+    pub owner: Signer<'info>,
+
+    /// CHECK: new risk authority account
+    pub new_risk_authority: UncheckedAccount<'info>,
+
+    #[account(mut,
+        has_one = owner,
+    )]
+    pub lending_market: AccountLoader<'info, LendingMarket>,
+}
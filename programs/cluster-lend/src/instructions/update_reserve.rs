@@ -1,6 +1,9 @@
 use anchor_lang::prelude::*;
 
-use crate::{lending_market::lending_operations, state::LendingMarket, xmsg, Reserve, UpdateConfigMode};
+use crate::{
+    errors::LendingError, lending_market::lending_operations, state::LendingMarket, utils::prices,
+    xmsg, Reserve, UpdateConfigMode,
+};
 
 pub fn process_update_reserve(
     ctx: Context<UpdateReserveCtx>,
@@ -10,6 +13,11 @@ pub fn process_update_reserve(
     let mode =
         UpdateConfigMode::try_from(mode).map_err(|_| ProgramError::InvalidInstructionData)?;
 
+    let is_risk_authority = {
+        let market = ctx.accounts.lending_market.load()?;
+        market.owner != ctx.accounts.signer.key()
+    };
+
     let reserve = &mut ctx.accounts.reserve.load_mut()?;
     let name = reserve.config.token_info.symbol();
 
@@ -20,10 +28,42 @@ pub fn process_update_reserve(
         mode,
     );
 
+    // clearing the fallback feed does not need an account
+    let is_new_pyth_feed = mode == UpdateConfigMode::UpdatePythPrice
+        || (mode == UpdateConfigMode::UpdateTokenInfoFallbackOracle
+            && value[0..32] != Pubkey::default().to_bytes());
+    if is_new_pyth_feed {
+        let Some(pyth_oracle) = ctx.accounts.pyth_oracle.as_ref() else {
+            xmsg!("The new pyth price account must be passed to update a pyth feed");
+            return err!(LendingError::InvalidPythPriceAccount);
+        };
+        if pyth_oracle.key.to_bytes()[..] != value[0..32] {
+            xmsg!(
+                "Pyth price account {} does not match the new value",
+                pyth_oracle.key
+            );
+            return err!(LendingError::InvalidPythPriceAccount);
+        }
+        prices::validate_pyth_price_account(
+            pyth_oracle,
+            reserve.config.token_info.effective_max_confidence_pct(),
+        )?;
+    }
+
     let clock = Clock::get()?;
     lending_operations::refresh_reserve(reserve, &clock, None)?;
 
-    lending_operations::update_reserve_config(reserve, mode, &value);
+    let prev_config = reserve.config;
+    lending_operations::update_reserve_config(reserve, mode, &value)?;
+    reserve.update_obsolete_since_slot(prev_config.status(), clock.slot);
+
+    if is_risk_authority {
+        lending_operations::utils::validate_risk_authority_reserve_update(
+            mode,
+            &prev_config,
+            &reserve.config,
+        )?;
+    }
 
     lending_operations::utils::validate_reserve_config(&reserve.config)?;
 
@@ -32,10 +72,11 @@ pub fn process_update_reserve(
 
 #[derive(Accounts)]
 pub struct UpdateReserveCtx<'info> {
-    pub owner: Signer<'info>,
+    /// Market owner, or the risk authority for the modes that tighten the config
+    pub signer: Signer<'info>,
 
     #[account(mut,
-        has_one = owner
+        constraint = lending_market.load()?.is_config_authority(signer.key) @ LendingError::InvalidMarketOwner
     )]
     pub lending_market: AccountLoader<'info, LendingMarket>,
 
@@ -43,4 +84,8 @@ pub struct UpdateReserveCtx<'info> {
         has_one = lending_market
     )]
     reserve: AccountLoader<'info, Reserve>,
+
+    /// CHECK: only required by `UpdatePythPrice` and `UpdateTokenInfoFallbackOracle`, checked to
+    /// be the new price account
+    pub pyth_oracle: Option<AccountInfo<'info>>,
 }
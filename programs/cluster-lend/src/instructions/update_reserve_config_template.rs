@@ -0,0 +1,43 @@
+use anchor_lang::prelude::*;
+use borsh::BorshDeserialize;
+
+use crate::{
+    errors::LendingError,
+    state::{LendingMarket, ReserveConfig, ReserveConfigTemplate},
+    xmsg,
+};
+
+pub fn process_update_reserve_config_template(
+    ctx: Context<UpdateReserveConfigTemplateCtx>,
+    value: &[u8],
+) -> Result<()> {
+    let template = &mut ctx.accounts.reserve_config_template.load_mut()?;
+
+    // The config is validated once applied, with the oracle of the target reserve
+    let new: ReserveConfig = BorshDeserialize::deserialize(&mut &value[..])
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    template.config = new;
+
+    xmsg!(
+        "Updated reserve config template {:?}",
+        ctx.accounts.reserve_config_template.key()
+    );
+    xmsg!("New Value is {:?}", new);
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct UpdateReserveConfigTemplateCtx<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        has_one = owner @ LendingError::InvalidMarketOwner,
+    )]
+    pub lending_market: AccountLoader<'info, LendingMarket>,
+
+    #[account(mut,
+        has_one = lending_market
+    )]
+    pub reserve_config_template: AccountLoader<'info, ReserveConfigTemplate>,
+}
@@ -6,7 +6,7 @@ use anchor_lang::{
 use anchor_spl::token::{Token, TokenAccount};
 
 use crate::{
-    check_refresh_ixs, gen_signer_seeds, lending_market::{lending_checks, lending_operations}, state::{LendingMarket, Reserve}, utils::{close_account_loader, seeds, token_transfer}, xmsg, Obligation, WithdrawObligationCollateralAccounts
+    check_refresh_ixs, gen_signer_seeds, lending_market::{lending_checks, lending_operations}, state::{CpiActionFlags, LendingMarket, Reserve}, utils::{close_account_loader, seeds, token_transfer}, xmsg, Obligation, WithdrawObligationCollateralAccounts
 };
 
 pub fn process_withdraw_obligation_collateral(
@@ -16,6 +16,11 @@ pub fn process_withdraw_obligation_collateral(
     let close_obligation = {
         // check_refresh_ixs!(ctx, withdraw_reserve);
 
+        lending_checks::cpi_allowed_checks(
+            &ctx.accounts.lending_market,
+            &ctx.accounts.instruction_sysvar_account,
+            CpiActionFlags::WITHDRAW,
+        )?;
         lending_checks::withdraw_obligation_collateral_checks(
             &WithdrawObligationCollateralAccounts {
                 lending_market: ctx.accounts.lending_market.clone(),
@@ -30,7 +35,7 @@ pub fn process_withdraw_obligation_collateral(
         )?;
         let clock = &Clock::get()?;
 
-        let withdraw_reserve = &mut ctx.accounts.withdraw_reserve.load()?;
+        let withdraw_reserve = &mut ctx.accounts.withdraw_reserve.load_mut()?;
         let obligation = &mut ctx.accounts.obligation.load_mut()?;
         let lending_market = &mut ctx.accounts.lending_market.load()?;
         let lending_market_key = ctx.accounts.lending_market.key();
@@ -88,7 +93,7 @@ pub struct WithdrawObligationCollateralCtx<'info> {
     )]
     pub lending_market_authority: AccountInfo<'info>,
 
-    #[account(
+    #[account(mut,
         has_one = lending_market
     )]
     pub withdraw_reserve: AccountLoader<'info, Reserve>,
@@ -0,0 +1,142 @@
+use anchor_lang::{
+    prelude::*,
+    solana_program::sysvar::{instructions::Instructions as SysInstructions, SysvarId},
+    Accounts,
+};
+use anchor_spl::token::{self, Mint, Token, TokenAccount};
+
+use crate::{
+    gen_signer_seeds,
+    lending_market::{lending_checks, lending_operations},
+    state::{LendingAction, LendingMarket, Reserve},
+    utils::{close_account_loader, seeds, token_transfer},
+    xmsg, Obligation,
+};
+
+pub fn process_withdraw_obligation_collateral_and_redeem(
+    ctx: Context<WithdrawObligationCollateralAndRedeemCtx>,
+    collateral_amount: u64,
+) -> Result<()> {
+    let close_obligation = {
+        lending_checks::withdraw_obligation_collateral_and_redeem_checks(&ctx)?;
+
+        let clock = Clock::get()?;
+
+        let withdraw_reserve = &mut ctx.accounts.withdraw_reserve.load_mut()?;
+        let obligation = &mut ctx.accounts.obligation.load_mut()?;
+        let lending_market = &ctx.accounts.lending_market.load()?;
+
+        let lending_market_key = ctx.accounts.lending_market.key();
+        let authority_signer_seeds =
+            gen_signer_seeds!(lending_market_key.as_ref(), lending_market.bump as u8);
+
+        let initial_reserve_token_balance =
+            token::accessor::amount(&ctx.accounts.reserve_liquidity_supply.to_account_info())?;
+        withdraw_reserve.reconcile_rebasing_vault_balance(initial_reserve_token_balance)?;
+        let initial_reserve_available_liquidity = withdraw_reserve.liquidity.available_amount;
+
+        let withdraw_amount = lending_operations::withdraw_obligation_collateral(
+            lending_market,
+            withdraw_reserve,
+            obligation,
+            collateral_amount,
+            clock.slot,
+            ctx.accounts.withdraw_reserve.key(),
+        )?;
+
+        let withdraw_liquidity_amount = lending_operations::redeem_reserve_collateral(
+            withdraw_reserve,
+            withdraw_amount,
+            &clock,
+            true,
+        )?;
+        lending_operations::update_reserve_deposit_value(lending_market, withdraw_reserve, false)?;
+
+        xmsg!(
+            "pnl: Withdraw obligation collateral {} and redeem liquidity {}",
+            withdraw_amount,
+            withdraw_liquidity_amount
+        );
+
+        token_transfer::withdraw_and_redeem_reserve_collateral_transfer(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.reserve_collateral_mint.to_account_info(),
+            ctx.accounts.reserve_source_collateral.to_account_info(),
+            ctx.accounts.reserve_liquidity_supply.to_account_info(),
+            ctx.accounts.user_destination_liquidity.to_account_info(),
+            ctx.accounts.lending_market_authority.clone(),
+            authority_signer_seeds,
+            withdraw_amount,
+            withdraw_liquidity_amount,
+        )?;
+
+        lending_checks::post_transfer_vault_balance_liquidity_reserve_checks(
+            token::accessor::amount(&ctx.accounts.reserve_liquidity_supply.to_account_info())
+                .unwrap(),
+            withdraw_reserve.liquidity.available_amount,
+            initial_reserve_token_balance,
+            initial_reserve_available_liquidity,
+            LendingAction::Subtractive(withdraw_liquidity_amount),
+        )?;
+
+        obligation.deposits_empty() && obligation.borrows_empty()
+    };
+
+    close_account_loader(
+        close_obligation,
+        &ctx.accounts.owner,
+        &ctx.accounts.obligation,
+    )?;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct WithdrawObligationCollateralAndRedeemCtx<'info> {
+    pub owner: Signer<'info>,
+    #[account(mut,
+        has_one = lending_market,
+        has_one = owner
+    )]
+    pub obligation: AccountLoader<'info, Obligation>,
+
+    pub lending_market: AccountLoader<'info, LendingMarket>,
+
+    /// CHECK: market authority PDA
+    #[account(
+        seeds = [seeds::LENDING_MARKET_AUTH, lending_market.key().as_ref()],
+        bump = lending_market.load()?.bump as u8,
+    )]
+    pub lending_market_authority: AccountInfo<'info>,
+
+    #[account(mut,
+        has_one = lending_market
+    )]
+    pub withdraw_reserve: AccountLoader<'info, Reserve>,
+
+    #[account(mut,
+        address = withdraw_reserve.load()?.collateral.supply_vault
+    )]
+    pub reserve_source_collateral: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut,
+        address = withdraw_reserve.load()?.collateral.mint_pubkey
+    )]
+    pub reserve_collateral_mint: Box<Account<'info, Mint>>,
+
+    #[account(mut,
+        address = withdraw_reserve.load()?.liquidity.supply_vault
+    )]
+    pub reserve_liquidity_supply: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut,
+        token::mint = withdraw_reserve.load()?.liquidity.mint_pubkey
+    )]
+    pub user_destination_liquidity: Box<Account<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+
+    /// CHECK: instruction_sysvar account
+    #[account(address = SysInstructions::id())]
+    pub instruction_sysvar_account: AccountInfo<'info>,
+}
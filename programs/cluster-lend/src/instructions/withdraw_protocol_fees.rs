@@ -2,6 +2,7 @@ use anchor_lang::{prelude::*, Accounts};
 use anchor_spl::token::{Token, TokenAccount};
 
 use crate::{
+    errors::LendingError,
     gen_signer_seeds,
     state::{LendingMarket, Reserve},
     utils::{seeds, token_transfer}, xmsg,
@@ -13,6 +14,20 @@ pub fn process_withdraw_protocol_fees(
 ) -> Result<()> {
     let market = ctx.accounts.lending_market.load()?;
     let lending_market_key = ctx.accounts.lending_market.key();
+    let reserve = ctx.accounts.reserve.load()?;
+
+    let fee_receiver = if reserve.config.protocol_fee_receiver == Pubkey::default() {
+        market.owner
+    } else {
+        reserve.config.protocol_fee_receiver
+    };
+    if ctx.accounts.lending_market_owner_ata.owner != fee_receiver {
+        xmsg!(
+            "Protocol fees must be withdrawn to an account owned by {}",
+            fee_receiver
+        );
+        return err!(LendingError::InvalidProtocolFeeReceiver);
+    }
 
     let amount = withdraw_amount.min(ctx.accounts.fee_vault.amount);
 
@@ -57,6 +72,7 @@ pub struct WithdrawProtocolFeesCtx<'info> {
     )]
     pub fee_vault: Account<'info, TokenAccount>,
 
+    /// Owned by the reserve protocol fee receiver, or the market owner when unset
     #[account(mut,
         token::mint = reserve.load()?.liquidity.mint_pubkey,
     )]
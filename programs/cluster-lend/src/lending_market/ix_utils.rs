@@ -13,6 +13,10 @@ pub trait InstructionLoader {
     fn load_current_index(&self) -> std::result::Result<u16, ProgramError>;
 
     fn is_flash_forbidden_cpi_call(&self) -> Result<bool> {
+        self.is_cpi_call()
+    }
+
+    fn is_cpi_call(&self) -> Result<bool> {
         let current_index = self.load_current_index()? as usize;
         let current_ixn = self.load_instruction_at(current_index)?;
 
@@ -1,8 +1,11 @@
 use crate::constants::PROGRAM_VERSION;
-use crate::state::{LendingAction, RedeemReserveCollateralAccounts, ReserveStatus};
+use crate::lending_market::ix_utils::{BpfInstructionLoader, InstructionLoader};
+use crate::state::{
+    CpiActionFlags, LendingAction, LendingMarket, RedeemReserveCollateralAccounts, ReserveStatus,
+};
 use crate::{errors::LendingError, state::Reserve};
 use crate::{
-    xmsg, BorrowObligationLiquidityCtx, DepositObligationCollateralAccounts, DepositReserveLiquidityAccounts, FlashBorrowReserveCtx, FlashRepayReserveCtx, LiquidateObligationCtx, RepayObligationLiquidityCtx, WithdrawObligationCollateralAccounts
+    xmsg, BorrowObligationLiquidityCtx, DepositObligationCollateralAccounts, DepositReserveLiquidityAccounts, FlashBorrowReserveCtx, FlashRepayReserveCtx, LiquidationAccounts, RepayObligationLiquidityCtx, WithdrawObligationCollateralAccounts, WithdrawObligationCollateralAndRedeemCtx
 };
 use anchor_lang::prelude::*;
 
@@ -65,6 +68,11 @@ pub fn post_transfer_vault_balance_liquidity_reserve_checks(
 pub fn deposit_reserve_liquidity_checks(accounts: &DepositReserveLiquidityAccounts) -> Result<()> {
     let reserve = accounts.reserve.load()?;
 
+    if accounts.lending_market.load()?.is_deposits_disabled() {
+        xmsg!("Deposits are disabled on the market");
+        return err!(LendingError::DepositsDisabled);
+    }
+
     if reserve.liquidity.supply_vault == accounts.user_source_liquidity.key() {
         xmsg!("Reserve liquidity supply cannot be used as the source liquidity provided");
         return err!(LendingError::InvalidAccountInput);
@@ -131,9 +139,35 @@ pub fn redeem_reserve_collateral_checks(accounts: &RedeemReserveCollateralAccoun
     Ok(())
 }
 
+pub fn cpi_allowed_checks(
+    lending_market: &AccountLoader<LendingMarket>,
+    instruction_sysvar_account_info: &AccountInfo,
+    action: CpiActionFlags,
+) -> Result<()> {
+    if lending_market.load()?.is_cpi_allowed(action) {
+        return Ok(());
+    }
+
+    let instruction_loader = BpfInstructionLoader {
+        instruction_sysvar_account_info,
+    };
+    if instruction_loader.is_cpi_call()? {
+        xmsg!("{:?} is disabled via CPI for this market", action);
+        return err!(LendingError::CpiDisabled);
+    }
+
+    Ok(())
+}
+
 pub fn borrow_obligation_liquidity_checks(
     ctx: &Context<BorrowObligationLiquidityCtx>,
 ) -> Result<()> {
+    cpi_allowed_checks(
+        &ctx.accounts.lending_market,
+        &ctx.accounts.instruction_sysvar_account,
+        CpiActionFlags::BORROW,
+    )?;
+
     let borrow_reserve = &ctx.accounts.borrow_reserve.load()?;
 
     if borrow_reserve.liquidity.supply_vault == ctx.accounts.user_destination_liquidity.key() {
@@ -197,6 +231,30 @@ pub fn withdraw_obligation_collateral_checks(
     Ok(())
 }
 
+pub fn withdraw_obligation_collateral_and_redeem_checks(
+    ctx: &Context<WithdrawObligationCollateralAndRedeemCtx>,
+) -> Result<()> {
+    cpi_allowed_checks(
+        &ctx.accounts.lending_market,
+        &ctx.accounts.instruction_sysvar_account,
+        CpiActionFlags::WITHDRAW,
+    )?;
+
+    let withdraw_reserve = ctx.accounts.withdraw_reserve.load()?;
+
+    if withdraw_reserve.version != PROGRAM_VERSION as u64 {
+        xmsg!("Reserve version does not match the program version");
+        return err!(LendingError::ReserveDeprecated);
+    }
+
+    if withdraw_reserve.liquidity.supply_vault == ctx.accounts.user_destination_liquidity.key() {
+        xmsg!("Withdraw reserve liquidity supply cannot be used as the destination liquidity provided");
+        return err!(LendingError::InvalidAccountInput);
+    }
+
+    Ok(())
+}
+
 pub fn repay_obligation_liquidity_checks(ctx: &Context<RepayObligationLiquidityCtx>) -> Result<()> {
     let repay_reserve = ctx.accounts.repay_reserve.load()?;
 
@@ -213,15 +271,24 @@ pub fn repay_obligation_liquidity_checks(ctx: &Context<RepayObligationLiquidityC
     Ok(())
 }
 
-pub fn liquidate_obligation_checks(ctx: &Context<LiquidateObligationCtx>) -> Result<()> {
-    let repay_reserve = ctx.accounts.repay_reserve.load()?;
-    let withdraw_reserve = ctx.accounts.withdraw_reserve.load()?;
+pub fn liquidate_obligation_checks(
+    accounts: &LiquidationAccounts,
+    instruction_sysvar_account_info: &AccountInfo,
+) -> Result<()> {
+    cpi_allowed_checks(
+        &accounts.lending_market,
+        instruction_sysvar_account_info,
+        CpiActionFlags::LIQUIDATE,
+    )?;
 
-    if repay_reserve.liquidity.supply_vault == ctx.accounts.user_source_liquidity.key() {
+    let repay_reserve = accounts.repay_reserve.load()?;
+    let withdraw_reserve = accounts.withdraw_reserve.load()?;
+
+    if repay_reserve.liquidity.supply_vault == accounts.user_source_liquidity.key() {
         xmsg!("Repay reserve liquidity supply cannot be used as the source liquidity provided");
         return err!(LendingError::InvalidAccountInput);
     }
-    if repay_reserve.collateral.supply_vault == ctx.accounts.user_destination_collateral.key() {
+    if repay_reserve.collateral.supply_vault == accounts.user_destination_collateral.key() {
         xmsg!(
             "Repay reserve collateral supply cannot be used as the destination collateral provided"
         );
@@ -233,11 +300,11 @@ pub fn liquidate_obligation_checks(ctx: &Context<LiquidateObligationCtx>) -> Res
         return err!(LendingError::ReserveDeprecated);
     }
 
-    if withdraw_reserve.liquidity.supply_vault == ctx.accounts.user_source_liquidity.key() {
+    if withdraw_reserve.liquidity.supply_vault == accounts.user_source_liquidity.key() {
         xmsg!("Withdraw reserve liquidity supply cannot be used as the source liquidity provided");
         return err!(LendingError::InvalidAccountInput);
     }
-    if withdraw_reserve.collateral.supply_vault == ctx.accounts.user_destination_collateral.key() {
+    if withdraw_reserve.collateral.supply_vault == accounts.user_destination_collateral.key() {
         xmsg!("Withdraw reserve collateral supply cannot be used as the destination collateral provided");
         return err!(LendingError::InvalidAccountInput);
     }
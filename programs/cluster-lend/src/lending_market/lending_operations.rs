@@ -1,20 +1,24 @@
-use std::{
-    cmp::min,
-    ops::{Add, Div, Mul},
-};
+use std::{cmp::min, ops::Div};
 
 use crate::{borsh::BorshDeserialize, utils::BorrowRateCurve};
 use crate::{
+    constants::{ELEVATION_GROUP_NONE, FULL_BPS, PROGRAM_VERSION, UTILIZATION_SNAPSHOTS_LEN},
     errors::LendingError,
     lending_market::liquidation_operations,
-    state::{LendingMarket, PriceStatusFlags, Reserve},
+    state::{
+        AssetTier, DebtTranche, ElevationGroup, GetObligationValuesResult, GetReserveApyResult,
+        GetUtilizationResult, LendingMarket, PriceAgeContext, PricePinMode, PriceStatusFlags,
+        PythConfiguration, ReconcileReserveResult, Reserve, RewardState, SimulateLiquidationResult,
+        UtilizationSnapshot,
+    },
     utils::GetPriceResult,
-    CalculateLiquidationResult, LiquidateAndRedeemResult, ReserveConfig, UpdateConfigMode,
+    CalculateLiquidationResult, LiquidateAndRedeemResult, LiquidationMode, ReserveConfig,
+    UpdateConfigMode,
 };
 use crate::{
     state::{
-        CalculateBorrowResult, Obligation, RefreshObligationBorrowsResult,
-        RefreshObligationDepositsResult, ReserveStatus,
+        CalculateBorrowResult, Obligation, ObligationHealth, RefreshObligationBorrowsResult,
+        RefreshObligationDepositsResult, ReserveStatus, RoundingDirection,
     },
     xmsg,
 };
@@ -23,17 +27,20 @@ use crate::{
     CalculateRepayResult,
 };
 use crate::{
-    utils::{BigFraction, FractionExtra},
+    utils::{BigFraction, FractionExtra, FRACTION_ONE_SCALED},
     LiquidateObligationResult,
 };
 use anchor_lang::solana_program::clock::Slot;
 use anchor_lang::{prelude::*, solana_program::clock::UnixTimestamp};
 use utils::{
-    calculate_obligation_collateral_market_value, calculate_obligation_liquidity_market_value,
+    apply_aggressive_mode, calculate_obligation_collateral_market_value,
+    calculate_obligation_liquidity_market_value, check_elevation_group_membership,
     check_obligation_collateral_deposit_reserve, check_obligation_fully_refreshed_and_not_null,
-    check_obligation_liquidity_borrow_reserve, post_borrow_obligation_invariants,
+    check_obligation_liquidity_borrow_reserve, check_obligation_value_cap,
+    checked_add_market_value, get_ltv_and_liquidation_threshold, post_borrow_obligation_invariants,
     post_deposit_obligation_invariants, post_repay_obligation_invariants,
-    post_withdraw_obligation_invariants, validate_obligation_asset_tiers,
+    post_repay_with_collateral_obligation_invariants, post_withdraw_obligation_invariants,
+    validate_obligation_asset_tiers,
 };
 
 use super::withdrawal_operations::utils::{add_to_withdrawal_accum, sub_from_withdrawal_accum};
@@ -46,6 +53,24 @@ pub fn refresh_reserve(
     let slot = clock.slot;
 
     reserve.accrue_interest(slot)?;
+    reserve.accrue_rewards(slot)?;
+    reserve.record_utilization_snapshot(slot)?;
+
+    if !reserve.config.is_deposits_disabled() && reserve.is_deposit_limit_crossed_past_grace(slot) {
+        xmsg!(
+            "Deposit limit crossed since slot {} for more than {} slots, disabling deposits",
+            reserve.liquidity.deposit_limit_crossed_slot,
+            reserve.config.limit_crossed_grace_slots
+        );
+        reserve.config.deposits_disabled = true as u8;
+    }
+
+    let price_change_rejected = matches!(
+        price,
+        Some(GetPriceResult { price, .. })
+            if is_price_change_past_bound(reserve, price, clock.unix_timestamp)
+    );
+    let price = price.filter(|_| !price_change_rejected);
 
     let price_status = if let Some(GetPriceResult {
         price,
@@ -57,17 +82,224 @@ pub fn refresh_reserve(
         reserve.liquidity.market_price_last_updated_ts = timestamp;
 
         Some(status)
-    } else if !is_saved_price_age_valid(reserve, clock.unix_timestamp) {
-        Some(PriceStatusFlags::empty())
+    } else if !is_saved_price_age_valid(reserve, clock.unix_timestamp, PriceAgeContext::Standard) {
+        if is_saved_price_age_valid(reserve, clock.unix_timestamp, PriceAgeContext::Liquidation) {
+            Some(
+                reserve
+                    .last_update
+                    .get_price_status()
+                    .difference(PriceStatusFlags::PRICE_AGE_CHECKED),
+            )
+        } else {
+            Some(PriceStatusFlags::empty())
+        }
     } else {
         None
     };
+    // the saved price stays usable for liquidations, borrows and withdrawals wait for an
+    // accepted price
+    let price_status = if price_change_rejected {
+        Some(
+            price_status
+                .unwrap_or_else(|| reserve.last_update.get_price_status())
+                .union(PriceStatusFlags::PRICE_CHANGE_REJECTED)
+                .difference(PriceStatusFlags::PRICE_AGE_CHECKED),
+        )
+    } else {
+        price_status
+    };
 
     reserve.last_update.update_slot(slot, price_status);
 
     Ok(())
 }
 
+pub fn push_reserve_price(
+    reserve: &mut Reserve,
+    price_authority: Pubkey,
+    price: Fraction,
+    clock: &Clock,
+) -> Result<()> {
+    let token_info = &reserve.config.token_info;
+    if !token_info.is_pushed_price() {
+        xmsg!(
+            "Reserve {} is priced by an oracle, its price can't be pushed",
+            token_info.symbol()
+        );
+        return err!(LendingError::PricePushNotEnabled);
+    }
+    if token_info.price_authority != price_authority {
+        xmsg!(
+            "Price authority {} does not match the reserve price authority {}",
+            price_authority,
+            token_info.price_authority
+        );
+        return err!(LendingError::InvalidPriceAuthority);
+    }
+    if price == Fraction::ZERO {
+        return err!(LendingError::PriceIsZero);
+    }
+
+    let timestamp = unix_timestamp_u64(clock)?;
+    refresh_reserve(
+        reserve,
+        clock,
+        Some(GetPriceResult {
+            price,
+            timestamp,
+            status: PriceStatusFlags::ALL_CHECKS,
+        }),
+    )?;
+    refresh_reserve_limit_timestamps(reserve, clock.slot)?;
+
+    Ok(())
+}
+
+pub fn get_utilization(reserve: &Reserve) -> Result<GetUtilizationResult> {
+    let utilization_rate_bps = reserve
+        .liquidity
+        .utilization_rate()?
+        .to_bps()
+        .ok_or(LendingError::MathOverflow)?;
+
+    Ok(GetUtilizationResult {
+        utilization_rate_bps,
+        snapshots: reserve.utilization_snapshots(),
+    })
+}
+
+pub fn get_reserve_apy(reserve: &Reserve) -> Result<GetReserveApyResult> {
+    Ok(GetReserveApyResult {
+        borrow_apy_sf: reserve.current_borrow_apy()?.to_sf(),
+        supply_apy_sf: reserve.current_supply_apy()?.to_sf(),
+    })
+}
+
+pub fn get_obligation_values(obligation: &Obligation) -> GetObligationValuesResult {
+    let (loan_to_value_sf, unhealthy_loan_to_value_sf) = if obligation.deposited_value_sf == 0 {
+        (0, 0)
+    } else {
+        (
+            obligation.loan_to_value().to_sf(),
+            obligation.unhealthy_loan_to_value().to_sf(),
+        )
+    };
+
+    GetObligationValuesResult {
+        scale: FRACTION_ONE_SCALED,
+        deposited_value_sf: obligation.deposited_value_sf,
+        borrow_factor_adjusted_debt_value_sf: obligation.borrow_factor_adjusted_debt_value_sf,
+        borrowed_assets_market_value_sf: obligation.borrowed_assets_market_value_sf,
+        allowed_borrow_value_sf: obligation.allowed_borrow_value_sf,
+        unhealthy_borrow_value_sf: obligation.unhealthy_borrow_value_sf,
+        loan_to_value_sf,
+        unhealthy_loan_to_value_sf,
+    }
+}
+
+pub fn reconcile_reserve(reserve: &Reserve, vault_balance: u64) -> ReconcileReserveResult {
+    let available_amount = reserve.liquidity.available_amount;
+    let drift = i128::from(vault_balance) - i128::from(available_amount);
+    if drift != 0 {
+        xmsg!(
+            "Reserve {} vault balance {} drifted by {} from its available amount {}",
+            reserve.token_symbol(),
+            vault_balance,
+            drift,
+            available_amount
+        );
+    }
+
+    ReconcileReserveResult {
+        vault_balance,
+        available_amount,
+        drift,
+    }
+}
+
+pub fn migrate_reserve(reserve: &mut Reserve) -> Result<()> {
+    if reserve.version >= PROGRAM_VERSION as u64 {
+        xmsg!(
+            "Reserve version {} is not older than the program version {}",
+            reserve.version,
+            PROGRAM_VERSION
+        );
+        return err!(LendingError::ReserveAlreadyMigrated);
+    }
+
+    xmsg!(
+        "Migrating reserve from version {} to {}",
+        reserve.version,
+        PROGRAM_VERSION
+    );
+
+    // the fields added in version 2 are carved out of space that version 1 left unchecked,
+    // later migrations must keep their live values
+    if reserve.version < 2 {
+        reset_reserve_fields_added_in_version_2(reserve);
+    }
+
+    utils::validate_reserve_config(&reserve.config)?;
+
+    reserve.version = PROGRAM_VERSION as u64;
+    reserve.last_update.mark_stale();
+
+    Ok(())
+}
+
+fn reset_reserve_fields_added_in_version_2(reserve: &mut Reserve) {
+    reserve.market_isolated_debt_value = 0;
+    reserve.utilization_snapshots = [UtilizationSnapshot::default(); UTILIZATION_SNAPSHOTS_LEN];
+    reserve.utilization_snapshot_index = 0;
+    reserve.market_deposit_value = 0;
+    reserve.obsolete_since_slot = 0;
+    reserve.reward_state = RewardState::default();
+    reserve.reserved = [0; 1];
+
+    let liquidity = &mut reserve.liquidity;
+    liquidity.accumulated_bad_debt_sf = 0;
+    liquidity.insurance_vault = Pubkey::default();
+    liquidity.padding2 = [0; 29];
+
+    let config = &mut reserve.config;
+    config.flash_loan_referral_fee_bps = 0;
+    config.redeem_fees_liquidity_buffer_bps = 0;
+    config.fees.flat_borrow_fee = 0;
+    config.repay_fee_sf = 0;
+    config.elevation_group = ELEVATION_GROUP_NONE;
+    config.debt_tranche = DebtTranche::Senior.into();
+    config.min_supply_borrow_spread_bps = 0;
+    config.deposits_disabled = false as u8;
+    config.is_rebasing = false as u8;
+    config.borrowing_disabled = false as u8;
+    config.liquidation_close_factor_pct = 0;
+    config.min_available_liquidity = 0;
+    config.liquidation_bond_lamports = 0;
+    config.protocol_fee_receiver = Pubkey::default();
+    config.max_borrow_rate_bps = 0;
+    config.limit_crossed_grace_slots = 0;
+    config.min_initial_deposit_amount = 0;
+    config.virtual_collateral_offset = 0;
+    config.min_deposit_hold_slots = 0;
+    config.reward_emission_per_slot = 0;
+    config.max_single_deposit = 0;
+    config.max_price_change_bps_per_refresh = 0;
+    config.insurance_fee_bps = 0;
+    config.min_borrow_rate_bps = 0;
+    config.reserved = [0; 15];
+
+    let token_info = &mut config.token_info;
+    token_info.max_confidence_pct = 0;
+    token_info.price_pin_mode = PricePinMode::Disabled.into();
+    token_info.price_pin_band_bps = 0;
+    token_info.max_age_price_seconds_liquidation = 0;
+    token_info.fixed_price_sf = 0;
+    token_info.fallback_oracle = PythConfiguration::default();
+    token_info.max_fallback_divergence_bps = 0;
+    token_info.price_authority = Pubkey::default();
+    token_info._padding = [0; 6];
+}
+
 pub fn refresh_reserve_limit_timestamps(reserve: &mut Reserve, slot: Slot) -> Result<()> {
     reserve.update_deposit_limit_crossed_slot(slot)?;
     reserve.update_borrow_limit_crossed_slot(slot)?;
@@ -92,6 +324,24 @@ pub fn deposit_reserve_liquidity(
         return err!(LendingError::ReserveStale);
     }
 
+    if reserve.config.is_deposits_disabled() {
+        xmsg!(
+            "Deposits are disabled on reserve {}, only withdrawals are allowed",
+            reserve.token_symbol()
+        );
+        return err!(LendingError::ReserveDepositsDisabled);
+    }
+
+    let max_single_deposit = reserve.config.max_single_deposit;
+    if max_single_deposit > 0 && liquidity_amount > max_single_deposit {
+        xmsg!(
+            "Cannot deposit more than the reserve single deposit limit in one call. Deposit: {} > limit: {}",
+            liquidity_amount,
+            max_single_deposit
+        );
+        return err!(LendingError::DepositLimitExceeded);
+    }
+
     let liquidity_amount_f = Fraction::from(liquidity_amount);
     let deposit_limit_f = Fraction::from(reserve.config.deposit_limit);
     let reserve_liquidity_supply_f = reserve.liquidity.total_supply()?;
@@ -107,19 +357,183 @@ pub fn deposit_reserve_liquidity(
         return err!(LendingError::DepositLimitExceeded);
     }
 
+    let is_initial_deposit = reserve.collateral.mint_total_supply == 0;
+    let min_initial_deposit_amount = reserve.config.min_initial_deposit_amount;
+    if is_initial_deposit && liquidity_amount < min_initial_deposit_amount {
+        xmsg!(
+            "Initial deposit {} is below the reserve minimum of {}",
+            liquidity_amount,
+            min_initial_deposit_amount
+        );
+        return err!(LendingError::InitialDepositTooSmall);
+    }
+
     sub_from_withdrawal_accum(
         &mut reserve.config.deposit_withdrawal_cap,
         liquidity_amount,
-        u64::try_from(clock.unix_timestamp).unwrap(),
+        unix_timestamp_u64(clock)?,
     )?;
 
-    let collateral_amount = reserve.deposit_liquidity(liquidity_amount)?;
+    // the minimum is a liquidity amount, its dead shares are the collateral it mints
+    let dead_collateral_amount = if is_initial_deposit && min_initial_deposit_amount > 0 {
+        reserve
+            .collateral_exchange_rate()?
+            .liquidity_to_collateral(min_initial_deposit_amount, RoundingDirection::Down)?
+    } else {
+        0
+    };
+
+    let mut collateral_amount = reserve.deposit_liquidity(liquidity_amount)?;
+
+    if dead_collateral_amount > 0 {
+        // counted in the collateral supply but never minted, so the exchange rate can't be
+        // inflated by the first depositor redeeming down to a dust supply
+        xmsg!(
+            "Locking {} collateral of the initial deposit as dead shares",
+            dead_collateral_amount
+        );
+        collateral_amount = collateral_amount
+            .checked_sub(dead_collateral_amount)
+            .ok_or(LendingError::InitialDepositTooSmall)?;
+    }
 
     reserve.last_update.mark_stale();
 
     Ok(collateral_amount)
 }
 
+/// Re-syncs the reserve `market_deposit_value` with the market value of its total supply,
+/// rejecting it past the market cap when `check_cap` is set on deposits
+pub fn update_reserve_deposit_value(
+    lending_market: &LendingMarket,
+    reserve: &mut Reserve,
+    check_cap: bool,
+) -> Result<()> {
+    let reserve_deposit_value: u64 = utils::calculate_market_value_from_liquidity_amount(
+        reserve,
+        reserve.liquidity.total_supply()?,
+    )?
+    .to_floor();
+
+    if check_cap
+        && lending_market.global_allowed_deposit_value > 0
+        && reserve_deposit_value > lending_market.global_allowed_deposit_value
+    {
+        xmsg!(
+            "Reserve deposit value {} would exceed the global deposit cap {}",
+            reserve_deposit_value,
+            lending_market.global_allowed_deposit_value
+        );
+        return err!(LendingError::GlobalDepositValueExceeded);
+    }
+
+    reserve.market_deposit_value = reserve_deposit_value;
+
+    Ok(())
+}
+
+/// Re-syncs the reserve `market_isolated_debt_value` with the market value of its borrows,
+/// rejecting it past the market cap when `check_cap` is set on borrows
+pub fn update_reserve_isolated_debt_value(
+    lending_market: &LendingMarket,
+    reserve: &mut Reserve,
+    check_cap: bool,
+) -> Result<()> {
+    // a reserve moved out of the isolated debt tier drops its previous contribution
+    let reserve_borrow_value: u64 = if reserve.config.get_asset_tier() == AssetTier::IsolatedDebt {
+        utils::calculate_market_value_from_liquidity_amount(
+            reserve,
+            reserve.liquidity.total_borrow(),
+        )?
+        .to_ceil()
+    } else {
+        0
+    };
+
+    if check_cap
+        && lending_market.isolated_debt_borrow_value_cap > 0
+        && reserve_borrow_value > lending_market.isolated_debt_borrow_value_cap
+    {
+        xmsg!(
+            "Reserve isolated debt borrow value {} would exceed the cap {}",
+            reserve_borrow_value,
+            lending_market.isolated_debt_borrow_value_cap
+        );
+        return err!(LendingError::IsolatedDebtBorrowValueExceeded);
+    }
+
+    reserve.market_isolated_debt_value = reserve_borrow_value;
+
+    Ok(())
+}
+
+pub fn donate_reserve_liquidity(
+    reserve: &mut Reserve,
+    clock: &Clock,
+    liquidity_amount: u64,
+) -> Result<()> {
+    if liquidity_amount == 0 {
+        xmsg!("Liquidity amount provided cannot be zero");
+        return err!(LendingError::InvalidAmount);
+    }
+
+    if reserve
+        .last_update
+        .is_stale(clock.slot, PriceStatusFlags::NONE)?
+    {
+        xmsg!("Reserve is stale and must be refreshed in the current slot");
+        return err!(LendingError::ReserveStale);
+    }
+
+    xmsg!(
+        "Donating {} liquidity to reserve {}",
+        liquidity_amount,
+        reserve.token_symbol()
+    );
+
+    reserve.liquidity.deposit(liquidity_amount)?;
+    reserve.last_update.mark_stale();
+
+    Ok(())
+}
+
+pub fn cover_bad_debt_from_insurance(
+    reserve: &mut Reserve,
+    clock: &Clock,
+    liquidity_amount: u64,
+    insurance_vault_balance: u64,
+) -> Result<u64> {
+    if reserve
+        .last_update
+        .is_stale(clock.slot, PriceStatusFlags::NONE)?
+    {
+        xmsg!("Reserve is stale and must be refreshed in the current slot");
+        return err!(LendingError::ReserveStale);
+    }
+
+    let bad_debt: u64 = reserve.liquidity.accumulated_bad_debt().to_ceil();
+    let cover_amount = liquidity_amount.min(insurance_vault_balance).min(bad_debt);
+    if cover_amount == 0 {
+        xmsg!(
+            "Nothing to cover, bad debt {} insurance vault balance {}",
+            bad_debt,
+            insurance_vault_balance
+        );
+        return err!(LendingError::InvalidAmount);
+    }
+
+    let remaining_bad_debt = reserve.liquidity.cover_bad_debt(cover_amount)?;
+    xmsg!(
+        "Covered {} bad debt of reserve {} from insurance, {} left",
+        cover_amount,
+        reserve.token_symbol(),
+        remaining_bad_debt
+    );
+    reserve.last_update.mark_stale();
+
+    Ok(cover_amount)
+}
+
 pub fn redeem_reserve_collateral(
     reserve: &mut Reserve,
     collateral_amount: u64,
@@ -140,6 +554,9 @@ pub fn redeem_reserve_collateral(
     }
 
     let liquidity_amount = reserve.redeem_collateral(collateral_amount)?;
+    if reserve.collateral.mint_total_supply > 0 {
+        reserve.check_min_available_liquidity()?;
+    }
     refresh_reserve_limit_timestamps(reserve, clock.slot)?;
     reserve.last_update.mark_stale();
 
@@ -147,16 +564,87 @@ pub fn redeem_reserve_collateral(
         add_to_withdrawal_accum(
             &mut reserve.config.deposit_withdrawal_cap,
             liquidity_amount,
-            u64::try_from(clock.unix_timestamp).unwrap(),
+            unix_timestamp_u64(clock)?,
         )?;
     }
 
     Ok(liquidity_amount)
 }
 
+pub fn redeem_reserve_collateral_exact_liquidity(
+    reserve: &mut Reserve,
+    liquidity_amount: u64,
+    clock: &Clock,
+) -> Result<u64> {
+    if liquidity_amount == 0 {
+        xmsg!("Liquidity amount provided cannot be zero");
+        return err!(LendingError::InvalidAmount);
+    }
+
+    if reserve
+        .last_update
+        .is_stale(clock.slot, PriceStatusFlags::NONE)?
+    {
+        xmsg!("Reserve is stale and must be refreshed in the current slot");
+        return err!(LendingError::ReserveStale);
+    }
+
+    if liquidity_amount > reserve.liquidity.available_amount {
+        xmsg!(
+            "Requested liquidity {} exceeds the reserve available liquidity {}",
+            liquidity_amount,
+            reserve.liquidity.available_amount
+        );
+        return err!(LendingError::InsufficientLiquidity);
+    }
+
+    let collateral_amount = reserve.redeem_collateral_for_liquidity(liquidity_amount)?;
+    if reserve.collateral.mint_total_supply > 0 {
+        reserve.check_min_available_liquidity()?;
+    }
+    refresh_reserve_limit_timestamps(reserve, clock.slot)?;
+    reserve.last_update.mark_stale();
+
+    add_to_withdrawal_accum(
+        &mut reserve.config.deposit_withdrawal_cap,
+        liquidity_amount,
+        unix_timestamp_u64(clock)?,
+    )?;
+
+    Ok(collateral_amount)
+}
+
+pub fn emergency_redeem_reserve_collateral(
+    reserve: &mut Reserve,
+    collateral_amount: u64,
+    clock: &Clock,
+) -> Result<u64> {
+    if collateral_amount == 0 {
+        xmsg!("Collateral amount provided cannot be zero");
+        return err!(LendingError::InvalidAmount);
+    }
+
+    refresh_reserve(reserve, clock, None)?;
+
+    let liquidity_amount = reserve.redeem_collateral(collateral_amount)?;
+    refresh_reserve_limit_timestamps(reserve, clock.slot)?;
+    reserve.last_update.mark_stale();
+
+    add_to_withdrawal_accum(
+        &mut reserve.config.deposit_withdrawal_cap,
+        liquidity_amount,
+        unix_timestamp_u64(clock)?,
+    )?;
+
+    Ok(liquidity_amount)
+}
+
 pub fn refresh_obligation_deposits<'info, T>(
     obligation: &mut Obligation,
     slot: Slot,
+    elevation_group: Option<&ElevationGroup>,
+    aggressive_mode_liquidation_buffer_pct: u8,
+    deprecation_grace_slots: u64,
     mut reserves_iter: impl Iterator<Item = T>,
 ) -> Result<RefreshObligationDepositsResult>
 where
@@ -167,13 +655,14 @@ where
     let mut allowed_borrow_value = Fraction::ZERO;
     let mut unhealthy_borrow_value = Fraction::ZERO;
     let mut num_of_obsolete_reserves = 0;
-    let mut prices_state = PriceStatusFlags::all();
+    let mut prices_state = PriceStatusFlags::ALL_CHECKS;
+    let aggressive_ltv_boost_pct = obligation.aggressive_ltv_boost_pct;
 
     for (index, deposit) in obligation
         .deposits
         .iter_mut()
         .enumerate()
-        .filter(|(_, deposit)| deposit.deposit_reserve != Pubkey::default())
+        .filter(|(_, deposit)| deposit.is_active())
     {
         let deposit_reserve = reserves_iter
             .next()
@@ -185,7 +674,7 @@ where
             .get()
             .map_err(|_| LendingError::InvalidAccountInput)?;
 
-        if deposit_reserve.config.status() == ReserveStatus::Obsolete {
+        if deposit_reserve.is_obsolete_past_grace(slot, deprecation_grace_slots) {
             num_of_obsolete_reserves += 1;
         }
 
@@ -201,9 +690,10 @@ where
             calculate_obligation_collateral_market_value(&deposit_reserve, deposit)?;
         deposit.market_value_sf = market_value_f.to_bits();
 
-        let (coll_ltv_pct, coll_liquidation_threshold_pct) = (
-            deposit_reserve.config.loan_to_value_pct,
-            deposit_reserve.config.liquidation_threshold_pct,
+        let (coll_ltv_pct, coll_liquidation_threshold_pct) = apply_aggressive_mode(
+            get_ltv_and_liquidation_threshold(&deposit_reserve, elevation_group),
+            aggressive_ltv_boost_pct,
+            aggressive_mode_liquidation_buffer_pct,
         );
 
         lowest_deposit_ltv_accumulator = min(
@@ -211,10 +701,15 @@ where
             coll_ltv_pct,
         );
 
-        deposited_value = deposited_value.add(market_value_f);
-        allowed_borrow_value += market_value_f * Fraction::from_percent(coll_ltv_pct);
-        unhealthy_borrow_value +=
-            market_value_f * Fraction::from_percent(coll_liquidation_threshold_pct);
+        deposited_value = checked_add_market_value(deposited_value, market_value_f)?;
+        allowed_borrow_value = checked_add_market_value(
+            allowed_borrow_value,
+            market_value_f * Fraction::from_percent(coll_ltv_pct),
+        )?;
+        unhealthy_borrow_value = checked_add_market_value(
+            unhealthy_borrow_value,
+            market_value_f * Fraction::from_percent(coll_liquidation_threshold_pct),
+        )?;
 
         obligation.deposits_asset_tiers[index] = deposit_reserve.config.asset_tier;
 
@@ -251,13 +746,13 @@ where
 {
     let mut borrowed_assets_market_value = Fraction::ZERO;
     let mut borrow_factor_adjusted_debt_value = Fraction::ZERO;
-    let mut prices_state = PriceStatusFlags::all();
+    let mut prices_state = PriceStatusFlags::ALL_CHECKS;
 
     for (index, borrow) in obligation
         .borrows
         .iter_mut()
         .enumerate()
-        .filter(|(_, borrow)| borrow.borrow_reserve != Pubkey::default())
+        .filter(|(_, borrow)| borrow.is_active())
     {
         let borrow_reserve = reserves_iter
             .next()
@@ -286,17 +781,24 @@ where
 
         borrow.market_value_sf = market_value_f.to_bits();
 
-        borrowed_assets_market_value += market_value_f;
+        borrowed_assets_market_value =
+            checked_add_market_value(borrowed_assets_market_value, market_value_f)?;
 
-        let borrow_factor_adjusted_market_value: Fraction =
-            market_value_f * borrow_reserve.config.get_borrow_factor();
+        let borrow_factor_adjusted_market_value: Fraction = market_value_f
+            * borrow_reserve
+                .config
+                .get_obligation_borrow_factor(obligation.borrow_factor_override_pct);
 
         borrow.borrow_factor_adjusted_market_value_sf =
             borrow_factor_adjusted_market_value.to_bits();
 
-        borrow_factor_adjusted_debt_value += borrow_factor_adjusted_market_value;
+        borrow_factor_adjusted_debt_value = checked_add_market_value(
+            borrow_factor_adjusted_debt_value,
+            borrow_factor_adjusted_market_value,
+        )?;
 
         obligation.borrows_asset_tiers[index] = borrow_reserve.config.asset_tier;
+        borrow.debt_tranche = borrow_reserve.config.debt_tranche;
 
         obligation.has_debt = 1;
 
@@ -327,6 +829,187 @@ pub fn refresh_obligation<'info, T>(
 where
     T: AnyAccountLoader<'info, Reserve>,
 {
+    let elevation_group = lending_market.get_elevation_group(obligation.elevation_group)?;
+
+    let deposits_result = refresh_obligation_deposits(
+        obligation,
+        slot,
+        elevation_group,
+        lending_market.aggressive_mode_liquidation_buffer_pct,
+        lending_market.deprecation_grace_slots,
+        &mut reserves_iter,
+    )?;
+
+    let borrows_result = refresh_obligation_borrows(obligation, slot, &mut reserves_iter)?;
+
+    update_refreshed_obligation(
+        obligation,
+        lending_market,
+        slot,
+        deposits_result,
+        borrows_result,
+    );
+
+    Ok(())
+}
+
+/// Same as `refresh_obligation` in a single pass over the reserves, loading each once and
+/// without logging every deposit and borrow
+pub fn refresh_obligation_compact<'info, T>(
+    obligation: &mut Obligation,
+    lending_market: &LendingMarket,
+    slot: Slot,
+    mut reserves_iter: impl Iterator<Item = T>,
+) -> Result<()>
+where
+    T: AnyAccountLoader<'info, Reserve>,
+{
+    let elevation_group = lending_market.get_elevation_group(obligation.elevation_group)?;
+    let aggressive_ltv_boost_pct = obligation.aggressive_ltv_boost_pct;
+    let aggressive_mode_liquidation_buffer_pct =
+        lending_market.aggressive_mode_liquidation_buffer_pct;
+
+    let active_deposits: [bool; 8] =
+        std::array::from_fn(|index| obligation.deposits[index].is_active());
+    let active_borrows: [bool; 5] =
+        std::array::from_fn(|index| obligation.borrows[index].is_active());
+    let positions = active_deposits
+        .into_iter()
+        .enumerate()
+        .filter(|(_, active)| *active)
+        .map(|(index, _)| (index, true))
+        .chain(
+            active_borrows
+                .into_iter()
+                .enumerate()
+                .filter(|(_, active)| *active)
+                .map(|(index, _)| (index, false)),
+        );
+
+    let mut deposits_result = RefreshObligationDepositsResult {
+        lowest_deposit_ltv_accumulator: u8::MAX,
+        num_of_obsolete_reserves: 0,
+        deposited_value_f: Fraction::ZERO,
+        allowed_borrow_value_f: Fraction::ZERO,
+        unhealthy_borrow_value_f: Fraction::ZERO,
+        prices_state: PriceStatusFlags::ALL_CHECKS,
+    };
+    let mut borrows_result = RefreshObligationBorrowsResult {
+        borrowed_assets_market_value_f: Fraction::ZERO,
+        borrow_factor_adjusted_debt_value_f: Fraction::ZERO,
+        prices_state: PriceStatusFlags::ALL_CHECKS,
+    };
+
+    for (index, is_deposit) in positions {
+        let reserve_loader = reserves_iter
+            .next()
+            .ok_or(LendingError::InvalidAccountInput)?;
+        let reserve_key = reserve_loader.get_pubkey();
+        let reserve = reserve_loader
+            .get()
+            .map_err(|_| LendingError::InvalidAccountInput)?;
+
+        if is_deposit {
+            let deposit = &mut obligation.deposits[index];
+
+            if reserve.is_obsolete_past_grace(slot, lending_market.deprecation_grace_slots) {
+                deposits_result.num_of_obsolete_reserves += 1;
+            }
+
+            check_obligation_collateral_deposit_reserve(
+                deposit,
+                &reserve,
+                reserve_key,
+                index,
+                slot,
+            )?;
+
+            let market_value_f = calculate_obligation_collateral_market_value(&reserve, deposit)?;
+            deposit.market_value_sf = market_value_f.to_bits();
+
+            let (coll_ltv_pct, coll_liquidation_threshold_pct) = apply_aggressive_mode(
+                get_ltv_and_liquidation_threshold(&reserve, elevation_group),
+                aggressive_ltv_boost_pct,
+                aggressive_mode_liquidation_buffer_pct,
+            );
+
+            deposits_result.lowest_deposit_ltv_accumulator = min(
+                deposits_result
+                    .lowest_deposit_ltv_accumulator
+                    .min(reserve.config.loan_to_value_pct),
+                coll_ltv_pct,
+            );
+
+            deposits_result.deposited_value_f =
+                checked_add_market_value(deposits_result.deposited_value_f, market_value_f)?;
+            deposits_result.allowed_borrow_value_f = checked_add_market_value(
+                deposits_result.allowed_borrow_value_f,
+                market_value_f * Fraction::from_percent(coll_ltv_pct),
+            )?;
+            deposits_result.unhealthy_borrow_value_f = checked_add_market_value(
+                deposits_result.unhealthy_borrow_value_f,
+                market_value_f * Fraction::from_percent(coll_liquidation_threshold_pct),
+            )?;
+
+            obligation.deposits_asset_tiers[index] = reserve.config.asset_tier;
+
+            deposits_result.prices_state &= reserve.last_update.get_price_status();
+        } else {
+            let borrow = &mut obligation.borrows[index];
+
+            check_obligation_liquidity_borrow_reserve(borrow, &reserve, reserve_key, index, slot)?;
+
+            borrow.accrue_interest(BigFraction::from(
+                reserve.liquidity.cumulative_borrow_rate_bsf,
+            ))?;
+
+            let market_value_f = calculate_obligation_liquidity_market_value(&reserve, borrow)?;
+            borrow.market_value_sf = market_value_f.to_bits();
+
+            borrows_result.borrowed_assets_market_value_f = checked_add_market_value(
+                borrows_result.borrowed_assets_market_value_f,
+                market_value_f,
+            )?;
+
+            let borrow_factor_adjusted_market_value: Fraction = market_value_f
+                * reserve
+                    .config
+                    .get_obligation_borrow_factor(obligation.borrow_factor_override_pct);
+            borrow.borrow_factor_adjusted_market_value_sf =
+                borrow_factor_adjusted_market_value.to_bits();
+
+            borrows_result.borrow_factor_adjusted_debt_value_f = checked_add_market_value(
+                borrows_result.borrow_factor_adjusted_debt_value_f,
+                borrow_factor_adjusted_market_value,
+            )?;
+
+            borrow.debt_tranche = reserve.config.debt_tranche;
+            obligation.borrows_asset_tiers[index] = reserve.config.asset_tier;
+
+            obligation.has_debt = 1;
+
+            borrows_result.prices_state &= reserve.last_update.get_price_status();
+        }
+    }
+
+    update_refreshed_obligation(
+        obligation,
+        lending_market,
+        slot,
+        deposits_result,
+        borrows_result,
+    );
+
+    Ok(())
+}
+
+fn update_refreshed_obligation(
+    obligation: &mut Obligation,
+    lending_market: &LendingMarket,
+    slot: Slot,
+    deposits_result: RefreshObligationDepositsResult,
+    borrows_result: RefreshObligationBorrowsResult,
+) {
     let RefreshObligationDepositsResult {
         lowest_deposit_ltv_accumulator,
         num_of_obsolete_reserves,
@@ -334,13 +1017,13 @@ where
         allowed_borrow_value_f: allowed_borrow_value,
         unhealthy_borrow_value_f: unhealthy_borrow_value,
         prices_state: deposits_prices_state,
-    } = refresh_obligation_deposits(obligation, slot, &mut reserves_iter)?;
+    } = deposits_result;
 
     let RefreshObligationBorrowsResult {
         borrow_factor_adjusted_debt_value_f,
         borrowed_assets_market_value_f,
         prices_state: borrows_prices_state,
-    } = refresh_obligation_borrows(obligation, slot, &mut reserves_iter)?;
+    } = borrows_result;
 
     obligation.borrowed_assets_market_value_sf = borrowed_assets_market_value_f.to_bits();
 
@@ -363,8 +1046,223 @@ where
     obligation.lowest_reserve_deposit_ltv = lowest_deposit_ltv_accumulator.into();
     obligation.num_of_obsolete_reserves = num_of_obsolete_reserves;
 
+    if obligation.classify_health() == ObligationHealth::Healthy {
+        obligation.unhealthy_since_slot = 0;
+    } else if obligation.unhealthy_since_slot == 0 {
+        obligation.unhealthy_since_slot = slot;
+    }
+
     let prices_state = deposits_prices_state.intersection(borrows_prices_state);
     obligation.last_update.update_slot(slot, Some(prices_state));
+}
+
+pub fn request_elevation_group<'info, T>(
+    obligation: &mut Obligation,
+    lending_market: &LendingMarket,
+    slot: Slot,
+    new_elevation_group: u8,
+    reserves_iter: impl Iterator<Item = T> + Clone,
+) -> Result<()>
+where
+    T: AnyAccountLoader<'info, Reserve>,
+{
+    lending_market.get_elevation_group(new_elevation_group)?;
+
+    xmsg!(
+        "Requesting elevation group {} (previous {})",
+        new_elevation_group,
+        obligation.elevation_group
+    );
+
+    obligation.elevation_group = new_elevation_group;
+
+    refresh_obligation(obligation, lending_market, slot, reserves_iter.clone())?;
+
+    for reserve in reserves_iter {
+        let reserve = reserve
+            .get()
+            .map_err(|_| LendingError::InvalidAccountInput)?;
+        check_elevation_group_membership(obligation, &reserve)?;
+    }
+
+    let borrow_factor_adjusted_debt_value =
+        Fraction::from_bits(obligation.borrow_factor_adjusted_debt_value_sf);
+    let allowed_borrow_value = Fraction::from_bits(obligation.allowed_borrow_value_sf);
+
+    if borrow_factor_adjusted_debt_value > allowed_borrow_value {
+        xmsg!(
+            "Obligation would exceed its allowed borrow value in elevation group {}: debt {} > allowed {}",
+            new_elevation_group,
+            borrow_factor_adjusted_debt_value.to_display(),
+            allowed_borrow_value.to_display()
+        );
+        return err!(LendingError::WorseLTVBlocked);
+    }
+
+    Ok(())
+}
+
+pub fn reorder_obligation_deposits(obligation: &mut Obligation, order: [u8; 8]) -> Result<()> {
+    xmsg!("Reordering obligation deposits to {:?}", order);
+
+    obligation.reorder_deposits(order)?;
+
+    validate_obligation_asset_tiers(obligation)?;
+
+    obligation.last_update.mark_stale();
+
+    Ok(())
+}
+
+pub fn set_aggressive_mode<'info, T>(
+    obligation: &mut Obligation,
+    lending_market: &LendingMarket,
+    clock: &Clock,
+    ltv_boost_pct: u8,
+    reserves_iter: impl Iterator<Item = T>,
+) -> Result<()>
+where
+    T: AnyAccountLoader<'info, Reserve>,
+{
+    if ltv_boost_pct > lending_market.aggressive_mode_max_ltv_boost_pct {
+        xmsg!(
+            "Aggressive mode LTV boost {}% is above the market max of {}%",
+            ltv_boost_pct,
+            lending_market.aggressive_mode_max_ltv_boost_pct
+        );
+        return err!(LendingError::InvalidAggressiveMode);
+    }
+
+    // the cooldown only runs from a previous change, the first one is always allowed
+    let cooldown_end = obligation.aggressive_mode_updated_at.saturating_add(
+        i64::try_from(lending_market.aggressive_mode_cooldown_secs).unwrap_or(i64::MAX),
+    );
+    if obligation.aggressive_mode_updated_at != 0 && clock.unix_timestamp < cooldown_end {
+        xmsg!(
+            "Aggressive mode can be changed again from {}, current timestamp {}",
+            cooldown_end,
+            clock.unix_timestamp
+        );
+        return err!(LendingError::AggressiveModeCooldown);
+    }
+
+    xmsg!(
+        "Setting aggressive mode LTV boost to {}% (previous {}%)",
+        ltv_boost_pct,
+        obligation.aggressive_ltv_boost_pct
+    );
+
+    obligation.aggressive_ltv_boost_pct = ltv_boost_pct;
+    obligation.aggressive_mode_updated_at = clock.unix_timestamp;
+
+    refresh_obligation(obligation, lending_market, clock.slot, reserves_iter)?;
+
+    let borrow_factor_adjusted_debt_value =
+        Fraction::from_bits(obligation.borrow_factor_adjusted_debt_value_sf);
+    let allowed_borrow_value = Fraction::from_bits(obligation.allowed_borrow_value_sf);
+
+    if borrow_factor_adjusted_debt_value > allowed_borrow_value {
+        xmsg!(
+            "Obligation would exceed its allowed borrow value with an LTV boost of {}%: debt {} > allowed {}",
+            ltv_boost_pct,
+            borrow_factor_adjusted_debt_value.to_display(),
+            allowed_borrow_value.to_display()
+        );
+        return err!(LendingError::WorseLTVBlocked);
+    }
+
+    Ok(())
+}
+
+pub fn set_autodeleverage_target(
+    obligation: &mut Obligation,
+    target_ltv_pct: u8,
+    bonus_bps: u16,
+) -> Result<()> {
+    if target_ltv_pct >= 100 || bonus_bps > FULL_BPS {
+        xmsg!(
+            "Invalid autodeleverage target LTV {}% with bonus {} bps",
+            target_ltv_pct,
+            bonus_bps
+        );
+        return err!(LendingError::InvalidAutodeleverageTarget);
+    }
+
+    xmsg!(
+        "Setting autodeleverage target LTV to {}% with bonus {} bps (previous {}% with {} bps)",
+        target_ltv_pct,
+        bonus_bps,
+        obligation.autodeleverage_target_ltv_pct,
+        obligation.autodeleverage_bonus_bps
+    );
+
+    obligation.autodeleverage_target_ltv_pct = target_ltv_pct;
+    obligation.autodeleverage_bonus_bps = bonus_bps;
+
+    Ok(())
+}
+
+pub fn set_user_ltv_cap(obligation: &mut Obligation, user_ltv_cap_pct: u8) -> Result<()> {
+    if user_ltv_cap_pct >= 100 {
+        xmsg!("Invalid user LTV cap {}%", user_ltv_cap_pct);
+        return err!(LendingError::InvalidUserLtvCap);
+    }
+
+    xmsg!(
+        "Setting user LTV cap to {}% (previous {}%)",
+        user_ltv_cap_pct,
+        obligation.user_ltv_cap_pct
+    );
+
+    obligation.user_ltv_cap_pct = user_ltv_cap_pct;
+
+    Ok(())
+}
+
+pub fn set_obligation_reserve_allowlist(
+    obligation: &mut Obligation,
+    allowed_deposit_reserves: [Pubkey; 4],
+    allowed_borrow_reserves: [Pubkey; 2],
+) -> Result<()> {
+    // the allowlists pin an obligation from the start, they can't be swapped under live positions
+    if obligation.active_deposits().next().is_some() || obligation.active_borrows().next().is_some()
+    {
+        xmsg!("Obligation reserve allowlists can't change once it has deposits or borrows");
+        return err!(LendingError::ObligationNotEmpty);
+    }
+
+    xmsg!(
+        "Setting obligation allowed deposit reserves {:?} and borrow reserves {:?}",
+        allowed_deposit_reserves,
+        allowed_borrow_reserves
+    );
+
+    obligation.allowed_deposit_reserves = allowed_deposit_reserves;
+    obligation.allowed_borrow_reserves = allowed_borrow_reserves;
+
+    Ok(())
+}
+
+pub fn set_borrow_factor_override(
+    obligation: &mut Obligation,
+    borrow_factor_override_pct: u64,
+) -> Result<()> {
+    if borrow_factor_override_pct != 0 && borrow_factor_override_pct < 100 {
+        xmsg!(
+            "Invalid borrow factor override {}%",
+            borrow_factor_override_pct
+        );
+        return err!(LendingError::InvalidBorrowFactorOverride);
+    }
+
+    xmsg!(
+        "Setting borrow factor override to {}% (previous {}%)",
+        borrow_factor_override_pct,
+        obligation.borrow_factor_override_pct
+    );
+
+    obligation.borrow_factor_override_pct = borrow_factor_override_pct;
+    obligation.last_update.mark_stale();
 
     Ok(())
 }
@@ -398,6 +1296,22 @@ pub fn borrow_obligation_liquidity(
         return err!(LendingError::BorrowingDisabled);
     }
 
+    if !borrow_reserve.config.is_borrowing_enabled() {
+        xmsg!(
+            "Borrowing is disabled on reserve {}, it can only be used as collateral",
+            borrow_reserve.token_symbol()
+        );
+        return err!(LendingError::BorrowingDisabled);
+    }
+
+    if !obligation.is_borrow_reserve_allowed(&borrow_reserve_pk) {
+        xmsg!(
+            "Obligation is not allowed to borrow from reserve {}",
+            borrow_reserve_pk
+        );
+        return err!(LendingError::InvalidAccountInput);
+    }
+
     let reserve_liquidity_borrowed_f = borrow_reserve.liquidity.total_borrow();
     let liquidity_amount_f = Fraction::from(liquidity_amount);
     let borrow_limit_f = Fraction::from(borrow_reserve.config.borrow_limit);
@@ -434,12 +1348,17 @@ pub fn borrow_obligation_liquidity(
         liquidity_amount,
         remaining_borrow_value,
         remaining_reserve_capacity,
+        borrow_reserve
+            .config
+            .get_obligation_borrow_factor(obligation.borrow_factor_override_pct),
     )?;
 
     add_to_withdrawal_accum(
         &mut borrow_reserve.config.debt_withdrawal_cap,
-        borrow_amount_f.to_floor(),
-        u64::try_from(clock.unix_timestamp).unwrap(),
+        borrow_amount_f
+            .checked_to_floor()
+            .ok_or_else(|| error!(LendingError::IntegerOverflow))?,
+        unix_timestamp_u64(clock)?,
     )?;
 
     if receive_amount == 0 {
@@ -448,15 +1367,20 @@ pub fn borrow_obligation_liquidity(
     }
 
     borrow_reserve.liquidity.borrow(borrow_amount_f)?;
+    borrow_reserve.check_min_available_liquidity()?;
     borrow_reserve.last_update.mark_stale();
 
     let cumulative_borrow_rate_bf =
         BigFraction::from(borrow_reserve.liquidity.cumulative_borrow_rate_bsf);
 
+    check_elevation_group_membership(obligation, borrow_reserve)?;
+
     let (obligation_liquidity, liquidity_index) = obligation.find_or_add_liquidity_to_borrows(
         borrow_reserve_pk,
         cumulative_borrow_rate_bf,
         borrow_reserve.config.get_asset_tier(),
+        borrow_reserve.config.get_debt_tranche(),
+        lending_market.max_obligation_reserves,
     )?;
 
     obligation_liquidity.borrow(borrow_amount_f);
@@ -473,6 +1397,8 @@ pub fn borrow_obligation_liquidity(
         Fraction::from_bits(lending_market.min_net_value_in_obligation_sf),
     )?;
 
+    check_obligation_value_cap(borrow_amount_f, obligation, borrow_reserve, lending_market)?;
+
     Ok(CalculateBorrowResult {
         borrow_amount_f,
         receive_amount,
@@ -501,33 +1427,117 @@ pub fn deposit_obligation_collateral(
         return err!(LendingError::ReserveStale);
     }
 
-    let (collateral, collateral_index) = obligation.find_or_add_collateral_to_deposits(
+    if lending_market.is_deposits_disabled() {
+        xmsg!("Deposits are disabled on the market");
+        return err!(LendingError::DepositsDisabled);
+    }
+
+    if !obligation.is_deposit_reserve_allowed(&deposit_reserve_pk) {
+        xmsg!(
+            "Obligation is not allowed to deposit into reserve {}",
+            deposit_reserve_pk
+        );
+        return err!(LendingError::InvalidAccountInput);
+    }
+
+    check_elevation_group_membership(obligation, deposit_reserve)?;
+
+    let (_, collateral_index) = obligation.find_or_add_collateral_to_deposits(
         deposit_reserve_pk,
         deposit_reserve.config.get_asset_tier(),
+        lending_market.max_obligation_reserves,
     )?;
+    utils::track_obligation_collateral_rewards(deposit_reserve, obligation, collateral_index)?;
 
+    let collateral = &mut obligation.deposits[collateral_index];
     collateral.deposit(collateral_amount)?;
+    collateral.last_deposit_slot = slot;
+    utils::deposit_obligation_collateral_rewards(deposit_reserve, obligation, collateral_amount)?;
     obligation.last_update.mark_stale();
     deposit_reserve.last_update.mark_stale();
 
     validate_obligation_asset_tiers(obligation)?;
 
+    let deposit_liquidity_amount_f = deposit_reserve
+        .collateral_exchange_rate()?
+        .fraction_collateral_to_liquidity(Fraction::from(collateral_amount));
+
     post_deposit_obligation_invariants(
-        deposit_reserve
-            .collateral_exchange_rate()?
-            .fraction_collateral_to_liquidity(Fraction::from(collateral_amount)),
+        deposit_liquidity_amount_f,
         obligation,
         deposit_reserve,
         Fraction::from_bits(obligation.deposits[collateral_index].market_value_sf),
         Fraction::from_bits(lending_market.min_net_value_in_obligation_sf),
     )?;
 
+    check_obligation_value_cap(
+        deposit_liquidity_amount_f,
+        obligation,
+        deposit_reserve,
+        lending_market,
+    )?;
+
     Ok(())
 }
 
+pub fn claim_obligation_rewards<'info, T>(
+    obligation: &mut Obligation,
+    reward_vault_amount: u64,
+    mut reserves_iter: impl Iterator<Item = T>,
+) -> Result<u64>
+where
+    T: AnyAccountLoader<'info, Reserve>,
+{
+    let mut earned_rewards = Fraction::ZERO;
+    for (index, deposit) in obligation
+        .deposits
+        .iter()
+        .enumerate()
+        .filter(|(_, deposit)| deposit.is_active())
+    {
+        let deposit_reserve = reserves_iter
+            .next()
+            .ok_or(LendingError::InvalidAccountInput)?;
+
+        if deposit_reserve.get_pubkey() != deposit.deposit_reserve {
+            xmsg!(
+                "Deposit reserve provided {} does not match the obligation deposit reserve {}",
+                deposit_reserve.get_pubkey(),
+                deposit.deposit_reserve
+            );
+            return err!(LendingError::InvalidAccountInput);
+        }
+
+        if !obligation.is_deposit_reward_tracked(index) {
+            continue;
+        }
+
+        let deposit_reserve = deposit_reserve
+            .get()
+            .map_err(|_| LendingError::InvalidAccountInput)?;
+        earned_rewards = deposit_reserve
+            .reward_state
+            .reward_per_collateral_token()
+            .checked_mul_int(u128::from(deposit.deposited_amount))
+            .and_then(|deposit_rewards| earned_rewards.checked_add(deposit_rewards))
+            .ok_or(LendingError::MathOverflow)?;
+    }
+
+    let pending_rewards: u64 = obligation
+        .pending_rewards(earned_rewards)?
+        .checked_to_floor()
+        .ok_or_else(|| error!(LendingError::IntegerOverflow))?;
+    let claim_amount = pending_rewards.min(reward_vault_amount);
+
+    // the claimed tokens are settled as debt valued one for one
+    obligation.add_reward_debt(claim_amount, Fraction::ONE)?;
+
+    Ok(claim_amount)
+}
+
 pub fn withdraw_obligation_collateral(
     lending_market: &LendingMarket,
-    withdraw_reserve: &Reserve,
+    withdraw_reserve: &mut Reserve,
     obligation: &mut Obligation,
     collateral_amount: u64,
     slot: Slot,
@@ -573,12 +1583,30 @@ pub fn withdraw_obligation_collateral(
         return err!(LendingError::ObligationCollateralEmpty);
     }
 
+    let held_until_slot = collateral
+        .last_deposit_slot
+        .saturating_add(withdraw_reserve.config.min_deposit_hold_slots);
+    if slot < held_until_slot {
+        xmsg!(
+            "Collateral deposited at slot {} is held until slot {}, current slot {}",
+            collateral.last_deposit_slot,
+            held_until_slot,
+            slot
+        );
+        return err!(LendingError::DepositHoldPeriodNotElapsed);
+    }
+
     if obligation.num_of_obsolete_reserves > 0
         && withdraw_reserve.config.status() == ReserveStatus::Active
     {
         return err!(LendingError::ObligationInDeprecatedReserve);
     }
 
+    if obligation.lowest_reserve_deposit_ltv == 0 && withdraw_reserve.config.loan_to_value_pct > 0 {
+        xmsg!("Obligation has a deposit with LTV 0, it must be withdrawn first");
+        return err!(LendingError::ObligationCollateralLtvZero);
+    }
+
     let withdraw_amount = if is_borrows_empty {
         if collateral_amount == u64::MAX {
             collateral.deposited_amount
@@ -604,7 +1632,9 @@ pub fn withdraw_obligation_collateral(
             let withdraw_ratio = withdraw_value / collateral_value;
 
             let ratioed_amount_f = withdraw_ratio * u128::from(collateral.deposited_amount);
-            let ratioed_amount: u64 = ratioed_amount_f.to_floor();
+            let ratioed_amount: u64 = ratioed_amount_f
+                .checked_to_floor()
+                .ok_or_else(|| error!(LendingError::IntegerOverflow))?;
 
             min(collateral.deposited_amount, ratioed_amount)
         } else {
@@ -632,7 +1662,9 @@ pub fn withdraw_obligation_collateral(
         withdraw_amount
     };
 
+    utils::track_obligation_collateral_rewards(withdraw_reserve, obligation, collateral_index)?;
     obligation.withdraw(withdraw_amount, collateral_index)?;
+    utils::withdraw_obligation_collateral_rewards(withdraw_reserve, obligation, withdraw_amount)?;
     obligation.last_update.mark_stale();
 
     post_withdraw_obligation_invariants(
@@ -644,8 +1676,41 @@ pub fn withdraw_obligation_collateral(
         Fraction::from_bits(obligation.deposits[collateral_index].market_value_sf),
         Fraction::from_bits(lending_market.min_net_value_in_obligation_sf),
     )?;
+    obligation.compact_obligation();
+
+    Ok(withdraw_amount)
+}
+
+/// Moves collateral between two obligations without going through the reserve vaults, the
+/// source is held to the withdraw invariants and the destination to the deposit ones
+pub fn transfer_obligation_collateral(
+    lending_market: &LendingMarket,
+    reserve: &mut Reserve,
+    source_obligation: &mut Obligation,
+    destination_obligation: &mut Obligation,
+    collateral_amount: u64,
+    slot: Slot,
+    reserve_pk: Pubkey,
+) -> Result<u64> {
+    let transfer_amount = withdraw_obligation_collateral(
+        lending_market,
+        reserve,
+        source_obligation,
+        collateral_amount,
+        slot,
+        reserve_pk,
+    )?;
 
-    Ok(withdraw_amount)
+    deposit_obligation_collateral(
+        reserve,
+        destination_obligation,
+        slot,
+        transfer_amount,
+        reserve_pk,
+        lending_market,
+    )?;
+
+    Ok(transfer_amount)
 }
 
 pub fn repay_obligation_liquidity(
@@ -653,6 +1718,7 @@ pub fn repay_obligation_liquidity(
     obligation: &mut Obligation,
     clock: &Clock,
     liquidity_amount: u64,
+    available_liquidity_amount: u64,
     repay_reserve_pk: Pubkey,
     lending_market: &LendingMarket,
 ) -> Result<u64> {
@@ -686,6 +1752,7 @@ pub fn repay_obligation_liquidity(
     } = repay_reserve.calculate_repay(
         liquidity_amount,
         Fraction::from_bits(liquidity.borrowed_amount_sf),
+        available_liquidity_amount,
     )?;
 
     if repay_amount == 0 {
@@ -696,13 +1763,14 @@ pub fn repay_obligation_liquidity(
     sub_from_withdrawal_accum(
         &mut repay_reserve.config.debt_withdrawal_cap,
         repay_amount,
-        u64::try_from(clock.unix_timestamp).unwrap(),
+        unix_timestamp_u64(clock)?,
     )?;
 
     repay_reserve.liquidity.repay(repay_amount, settle_amount)?;
     repay_reserve.last_update.mark_stale();
 
     obligation.repay(settle_amount, liquidity_index)?;
+    utils::forgive_dust_debt(repay_reserve, obligation, liquidity_index)?;
     obligation.update_has_debt();
     obligation.last_update.mark_stale();
 
@@ -713,10 +1781,121 @@ pub fn repay_obligation_liquidity(
         Fraction::from_bits(obligation.borrows[liquidity_index].market_value_sf),
         Fraction::from_bits(lending_market.min_net_value_in_obligation_sf),
     )?;
+    obligation.compact_obligation();
 
     Ok(repay_amount)
 }
 
+pub fn repay_obligation_liquidity_with_collateral(
+    reserve: &mut Reserve,
+    obligation: &mut Obligation,
+    clock: &Clock,
+    collateral_amount: u64,
+    reserve_pk: Pubkey,
+    lending_market: &LendingMarket,
+) -> Result<(u64, u64)> {
+    if collateral_amount == 0 {
+        xmsg!("Collateral amount provided cannot be zero");
+        return err!(LendingError::InvalidAmount);
+    }
+
+    if reserve
+        .last_update
+        .is_stale(clock.slot, PriceStatusFlags::ALL_CHECKS)?
+    {
+        xmsg!(
+            "Reserve is stale and must be refreshed in the current slot, price status: {:08b}",
+            reserve.last_update.get_price_status().0
+        );
+        return err!(LendingError::ReserveStale);
+    }
+
+    if obligation
+        .last_update
+        .is_stale(clock.slot, PriceStatusFlags::ALL_CHECKS)?
+    {
+        xmsg!(
+            "Obligation is stale and must be refreshed in the current slot, price status: {:08b}",
+            obligation.last_update.get_price_status().0
+        );
+        return err!(LendingError::ObligationStale);
+    }
+
+    let (collateral, _) = obligation.find_collateral_in_deposits(reserve_pk)?;
+    if collateral.deposited_amount == 0 {
+        return err!(LendingError::ObligationCollateralEmpty);
+    }
+    let deposited_amount = collateral.deposited_amount;
+
+    let (liquidity, _) = obligation.find_liquidity_in_borrows(reserve_pk)?;
+    if liquidity.borrowed_amount_sf == 0 {
+        xmsg!("Liquidity borrowed amount is zero");
+        return err!(LendingError::ObligationLiquidityEmpty);
+    }
+
+    let exchange_rate = reserve.collateral_exchange_rate()?;
+    let max_collateral_amount = exchange_rate.liquidity_to_collateral(
+        Fraction::from_bits(liquidity.borrowed_amount_sf)
+            .checked_to_floor()
+            .ok_or_else(|| error!(LendingError::IntegerOverflow))?,
+        RoundingDirection::Down,
+    )?;
+    let collateral_amount = collateral_amount
+        .min(deposited_amount)
+        .min(max_collateral_amount);
+    let liquidity_amount =
+        exchange_rate.collateral_to_liquidity(collateral_amount, RoundingDirection::Down)?;
+
+    if liquidity_amount == 0 {
+        xmsg!("Collateral amount is too small to repay liquidity");
+        return err!(LendingError::RepayTooSmall);
+    }
+
+    post_repay_with_collateral_obligation_invariants(
+        Fraction::from(liquidity_amount),
+        obligation,
+        reserve,
+    )?;
+
+    let repay_amount = repay_obligation_liquidity(
+        reserve,
+        obligation,
+        clock,
+        liquidity_amount,
+        liquidity_amount,
+        reserve_pk,
+        lending_market,
+    )?;
+
+    let redeemed_liquidity_amount = reserve.redeem_collateral(collateral_amount)?;
+    if redeemed_liquidity_amount != repay_amount {
+        xmsg!(
+            "Redeemed liquidity {} does not match repaid liquidity {}",
+            redeemed_liquidity_amount,
+            repay_amount
+        );
+        return err!(LendingError::ReserveAccountingMismatch);
+    }
+    refresh_reserve_limit_timestamps(reserve, clock.slot)?;
+    reserve.last_update.mark_stale();
+
+    add_to_withdrawal_accum(
+        &mut reserve.config.deposit_withdrawal_cap,
+        redeemed_liquidity_amount,
+        unix_timestamp_u64(clock)?,
+    )?;
+
+    // the repay compacts the obligation, the deposit may have moved
+    let (_, collateral_index) = obligation.find_collateral_in_deposits(reserve_pk)?;
+    utils::track_obligation_collateral_rewards(reserve, obligation, collateral_index)?;
+    obligation.withdraw(collateral_amount, collateral_index)?;
+    utils::withdraw_obligation_collateral_rewards(reserve, obligation, collateral_amount)?;
+    obligation.compact_obligation();
+    obligation.last_update.mark_stale();
+
+    Ok((collateral_amount, repay_amount))
+}
+
 #[allow(clippy::too_many_arguments)]
 pub fn liquidate_and_redeem(
     lending_market: &LendingMarket,
@@ -726,7 +1905,8 @@ pub fn liquidate_and_redeem(
     clock: &Clock,
     liquidity_amount: u64,
     min_acceptable_received_collateral_amount: u64,
-    max_allowed_ltv_override_pct_opt: Option<u64>,
+    min_acceptable_received_liquidity_amount: u64,
+    mode: LiquidationMode,
 ) -> Result<LiquidateAndRedeemResult> {
     let LiquidateObligationResult {
         repay_amount,
@@ -742,7 +1922,7 @@ pub fn liquidate_and_redeem(
         clock,
         liquidity_amount,
         min_acceptable_received_collateral_amount,
-        max_allowed_ltv_override_pct_opt,
+        mode,
     )?;
 
     let withdraw_reserve = &mut withdraw_reserve.get_mut()?;
@@ -752,6 +1932,7 @@ pub fn liquidate_and_redeem(
         repay_amount,
         withdraw_collateral_amount,
         liquidation_bonus_rate,
+        min_acceptable_received_liquidity_amount,
         clock,
     )?;
 
@@ -772,15 +1953,17 @@ pub fn liquidate_obligation(
     clock: &Clock,
     liquidity_amount: u64,
     min_acceptable_received_collateral_amount: u64,
-    max_allowed_ltv_override_pct_opt: Option<u64>,
+    mode: LiquidationMode,
 ) -> Result<LiquidateObligationResult> {
+    let repay_reserve_ref = repay_reserve.get()?;
+    let withdraw_reserve_ref = withdraw_reserve.get()?;
     xmsg!(
         "Liquidating liquidation_close_factor_pct: {}, liquidation_max_value: {}",
-        lending_market.liquidation_max_debt_close_factor_pct,
+        withdraw_reserve_ref
+            .config
+            .liquidation_close_factor_pct(lending_market.liquidation_max_debt_close_factor_pct),
         lending_market.max_liquidatable_debt_market_value_at_once
     );
-    let repay_reserve_ref = repay_reserve.get()?;
-    let withdraw_reserve_ref = withdraw_reserve.get()?;
 
     let slot = clock.slot;
 
@@ -792,6 +1975,7 @@ pub fn liquidate_obligation(
     }
 
     utils::assert_obligation_liquidatable(
+        lending_market,
         &repay_reserve_ref,
         &withdraw_reserve_ref,
         obligation,
@@ -818,21 +2002,43 @@ pub fn liquidate_obligation(
         repay_amount,
         withdraw_amount,
         liquidation_bonus_rate,
-    } = liquidation_operations::calculate_liquidation(
-        &withdraw_reserve_ref,
-        &repay_reserve_ref,
-        liquidity_amount,
-        lending_market,
-        obligation,
-        liquidity,
-        collateral,
-        slot,
-        max_allowed_ltv_override_pct_opt,
-    )?;
+    } = match mode {
+        LiquidationMode::Standard {
+            max_allowed_ltv_override_pct_opt,
+        } => liquidation_operations::calculate_liquidation(
+            &withdraw_reserve_ref,
+            &repay_reserve_ref,
+            liquidity_amount,
+            lending_market,
+            obligation,
+            liquidity,
+            collateral,
+            slot,
+            max_allowed_ltv_override_pct_opt,
+        )?,
+        LiquidationMode::ToTargetLtv => liquidation_operations::calculate_deleverage_to_target(
+            &withdraw_reserve_ref,
+            &repay_reserve_ref,
+            liquidity_amount,
+            lending_market,
+            obligation,
+            liquidity,
+            collateral,
+        )?,
+    };
 
     drop(repay_reserve_ref);
     drop(withdraw_reserve_ref);
 
+    {
+        let mut withdraw_reserve_ref_mut = withdraw_reserve.get_mut()?;
+        utils::track_obligation_collateral_rewards(
+            &mut withdraw_reserve_ref_mut,
+            obligation,
+            collateral_index,
+        )?;
+    }
+
     {
         let mut repay_reserve_ref_mut = repay_reserve.get_mut()?;
 
@@ -850,9 +2056,16 @@ pub fn liquidate_obligation(
     let withdraw_collateral_amount = {
         let mut withdraw_reserve_ref_mut = withdraw_reserve.get_mut()?;
         refresh_reserve(&mut withdraw_reserve_ref_mut, clock, None)?;
+        utils::withdraw_obligation_collateral_rewards(
+            &mut withdraw_reserve_ref_mut,
+            obligation,
+            withdraw_amount,
+        )?;
         let collateral_exchange_rate = withdraw_reserve_ref_mut.collateral_exchange_rate()?;
-        let max_redeemable_collateral = collateral_exchange_rate
-            .liquidity_to_collateral(withdraw_reserve_ref_mut.liquidity.available_amount);
+        let max_redeemable_collateral = collateral_exchange_rate.liquidity_to_collateral(
+            withdraw_reserve_ref_mut.liquidity.available_amount,
+            RoundingDirection::Down,
+        )?;
         min(withdraw_amount, max_redeemable_collateral)
     };
 
@@ -870,11 +2083,110 @@ pub fn liquidate_obligation(
     })
 }
 
+#[allow(clippy::too_many_arguments)]
+pub fn simulate_liquidation(
+    lending_market: &LendingMarket,
+    repay_reserve: &Reserve,
+    withdraw_reserve: &Reserve,
+    obligation: &Obligation,
+    repay_reserve_pk: Pubkey,
+    withdraw_reserve_pk: Pubkey,
+    slot: Slot,
+    liquidity_amount: u64,
+) -> Result<SimulateLiquidationResult> {
+    if withdraw_reserve.config.loan_to_value_pct == 0
+        || withdraw_reserve.config.liquidation_threshold_pct == 0
+    {
+        xmsg!("Max LTV of the withdraw reserve is 0 and can't be used for liquidation");
+        return err!(LendingError::CollateralNonLiquidatable);
+    }
+
+    utils::assert_obligation_liquidatable(
+        lending_market,
+        repay_reserve,
+        withdraw_reserve,
+        obligation,
+        liquidity_amount,
+        slot,
+    )?;
+
+    let (liquidity, _) = obligation.find_liquidity_in_borrows(repay_reserve_pk)?;
+    if liquidity.borrow_factor_adjusted_market_value_sf == 0 {
+        xmsg!("Obligation borrow value is zero");
+        return err!(LendingError::ObligationLiquidityEmpty);
+    }
+
+    let (collateral, _) = obligation.find_collateral_in_deposits(withdraw_reserve_pk)?;
+    if collateral.market_value_sf == 0 {
+        xmsg!("Obligation deposit value is zero");
+        return err!(LendingError::ObligationCollateralEmpty);
+    }
+
+    let CalculateLiquidationResult {
+        settle_amount_f,
+        repay_amount,
+        withdraw_amount,
+        liquidation_bonus_rate,
+    } = liquidation_operations::calculate_liquidation(
+        withdraw_reserve,
+        repay_reserve,
+        liquidity_amount,
+        lending_market,
+        obligation,
+        liquidity,
+        collateral,
+        slot,
+        None,
+    )?;
+
+    if repay_amount == 0 || withdraw_amount == 0 {
+        xmsg!("Liquidation is too small to transfer liquidity or receive collateral");
+        return err!(LendingError::LiquidationTooSmall);
+    }
+
+    let mut withdraw_liquidity = withdraw_reserve.liquidity;
+    if repay_reserve_pk == withdraw_reserve_pk {
+        withdraw_liquidity.repay(repay_amount, settle_amount_f)?;
+    }
+
+    let collateral_exchange_rate = withdraw_reserve.collateral.exchange_rate(
+        withdraw_liquidity.total_supply()?,
+        withdraw_reserve.config.virtual_collateral_offset,
+    )?;
+    let max_redeemable_collateral = collateral_exchange_rate
+        .liquidity_to_collateral(withdraw_liquidity.available_amount, RoundingDirection::Down)?;
+    let withdraw_collateral_amount = min(withdraw_amount, max_redeemable_collateral);
+
+    let (withdraw_liquidity_amount, protocol_liquidation_fee) = if withdraw_collateral_amount != 0 {
+        let withdraw_liquidity_amount = collateral_exchange_rate
+            .collateral_to_liquidity(withdraw_collateral_amount, RoundingDirection::Down)?;
+        let protocol_fee = liquidation_operations::calculate_protocol_liquidation_fee(
+            withdraw_liquidity_amount,
+            liquidation_bonus_rate,
+            withdraw_reserve.config.protocol_liquidation_fee_pct,
+        );
+        (withdraw_liquidity_amount, protocol_fee)
+    } else {
+        (0, 0)
+    };
+
+    Ok(SimulateLiquidationResult {
+        settle_amount_sf: settle_amount_f.to_bits(),
+        repay_amount,
+        withdraw_amount,
+        liquidation_bonus_rate_sf: liquidation_bonus_rate.to_bits(),
+        withdraw_collateral_amount,
+        withdraw_liquidity_amount,
+        protocol_liquidation_fee,
+    })
+}
+
 pub(crate) fn post_liquidate_redeem(
     withdraw_reserve: &mut Reserve,
     repay_amount: u64,
     withdraw_collateral_amount: u64,
     liquidation_bonus_rate: Fraction,
+    min_acceptable_received_liquidity_amount: u64,
     clock: &Clock,
 ) -> Result<Option<(u64, u64)>> {
     if withdraw_collateral_amount != 0 {
@@ -885,13 +2197,23 @@ pub(crate) fn post_liquidate_redeem(
             liquidation_bonus_rate,
             withdraw_reserve.config.protocol_liquidation_fee_pct,
         );
+        let net_withdraw_liquidity_amount = withdraw_liquidity_amount
+            .checked_sub(protocol_fee)
+            .ok_or_else(|| error!(LendingError::MathOverflow))?;
+        if net_withdraw_liquidity_amount < min_acceptable_received_liquidity_amount {
+            xmsg!("Net withdraw liquidity amount below minimum acceptable liquidity amount");
+            return err!(LendingError::LiquidationSlippageError);
+        }
         xmsg!(
             "pnl: Liquidator repaid {} and withdrew {} collateral with fees {}",
             repay_amount,
-            withdraw_liquidity_amount.checked_sub(protocol_fee).unwrap(),
+            net_withdraw_liquidity_amount,
             protocol_fee
         );
         Ok(Some((withdraw_liquidity_amount, protocol_fee)))
+    } else if min_acceptable_received_liquidity_amount > 0 {
+        xmsg!("No collateral redeemed, below minimum acceptable liquidity amount");
+        err!(LendingError::LiquidationSlippageError)
     } else {
         Ok(None)
     }
@@ -915,14 +2237,16 @@ pub fn flash_repay_reserve_liquidity<'info>(
     reserve: &mut Reserve,
     liquidity_amount: u64,
     slot: Slot,
-) -> Result<(u64, u64)> {
+    has_referrer: bool,
+) -> Result<(u64, u64, u64)> {
     let flash_loan_amount = liquidity_amount;
 
     let flash_loan_amount_f = Fraction::from(flash_loan_amount);
-    let protocol_fee = reserve
-        .config
-        .fees
-        .calculate_flash_loan_fees(flash_loan_amount_f)?;
+    let (protocol_fee, referral_fee) = reserve.config.fees.calculate_flash_loan_fees(
+        flash_loan_amount_f,
+        reserve.config.flash_loan_referral_fee_bps,
+        has_referrer,
+    )?;
 
     reserve
         .liquidity
@@ -930,18 +2254,69 @@ pub fn flash_repay_reserve_liquidity<'info>(
     refresh_reserve_limit_timestamps(reserve, slot)?;
     reserve.last_update.mark_stale();
 
-    Ok((flash_loan_amount, protocol_fee))
+    Ok((flash_loan_amount, protocol_fee, referral_fee))
 }
 
 // Price utilities
-pub fn is_saved_price_age_valid(reserve: &Reserve, current_ts: UnixTimestamp) -> bool {
-    let current_ts: u64 = current_ts.try_into().expect("Negative timestamp");
+fn unix_timestamp_u64(clock: &Clock) -> Result<u64> {
+    u64::try_from(clock.unix_timestamp).map_err(|_| {
+        xmsg!("Negative clock timestamp {}", clock.unix_timestamp);
+        error!(LendingError::IntegerOverflow)
+    })
+}
+
+pub fn is_saved_price_age_valid(
+    reserve: &Reserve,
+    current_ts: UnixTimestamp,
+    context: PriceAgeContext,
+) -> bool {
+    let Ok(current_ts) = u64::try_from(current_ts) else {
+        xmsg!(
+            "Negative timestamp {}, saved price treated as expired",
+            current_ts
+        );
+        return false;
+    };
     let price_last_updated_ts = reserve.liquidity.market_price_last_updated_ts;
-    let price_max_age = reserve.config.token_info.max_age_price_seconds;
+    let price_max_age = reserve.config.token_info.price_max_age(context);
 
     current_ts.saturating_sub(price_last_updated_ts) < price_max_age
 }
 
+/// A new price moving further than `max_price_change_bps_per_refresh` from a still fresh saved
+/// price is treated as a corrupted update, once the saved price has aged out any price is taken
+fn is_price_change_past_bound(
+    reserve: &Reserve,
+    price: Fraction,
+    current_ts: UnixTimestamp,
+) -> bool {
+    let max_change_bps = reserve.config.max_price_change_bps_per_refresh;
+    let saved_price = Fraction::from_bits(reserve.liquidity.market_price_sf);
+    if max_change_bps == 0
+        || saved_price == Fraction::ZERO
+        || !is_saved_price_age_valid(reserve, current_ts, PriceAgeContext::Standard)
+    {
+        return false;
+    }
+
+    let change = if price > saved_price {
+        price - saved_price
+    } else {
+        saved_price - price
+    };
+    if change > saved_price * Fraction::from_bps(max_change_bps) {
+        xmsg!(
+            "Price {} moved more than {} bps from the saved price {}, keeping the saved price",
+            price,
+            max_change_bps,
+            saved_price
+        );
+        return true;
+    }
+
+    false
+}
+
 pub fn is_price_refresh_needed(
     reserve: &Reserve,
     market: &LendingMarket,
@@ -973,13 +2348,29 @@ pub fn redeem_fees(reserve: &mut Reserve, slot: Slot) -> Result<u64> {
         return err!(LendingError::InsufficientProtocolFeesToRedeem);
     }
 
+    let liquidity_buffer = reserve.redeem_fees_liquidity_buffer()?;
+    let remaining_liquidity = reserve.liquidity.available_amount - withdraw_amount;
+    if remaining_liquidity < liquidity_buffer {
+        xmsg!(
+            "Redeeming {} of fees would leave {} available liquidity, below the buffer of {}",
+            withdraw_amount,
+            remaining_liquidity,
+            liquidity_buffer
+        );
+        return err!(LendingError::InsufficientLiquidity);
+    }
+
     reserve.liquidity.redeem_fees(withdraw_amount)?;
     reserve.last_update.mark_stale();
 
     Ok(withdraw_amount)
 }
 
-pub fn update_reserve_config(reserve: &mut Reserve, mode: UpdateConfigMode, value: &[u8]) {
+pub fn update_reserve_config(
+    reserve: &mut Reserve,
+    mode: UpdateConfigMode,
+    value: &[u8],
+) -> Result<()> {
     match mode {
         UpdateConfigMode::UpdateLoanToValuePct => {
             let new = value[0];
@@ -1023,6 +2414,20 @@ pub fn update_reserve_config(reserve: &mut Reserve, mode: UpdateConfigMode, valu
             xmsg!("Prv Value is {:?}", prv);
             xmsg!("New Value is {:?}", new);
         }
+        UpdateConfigMode::UpdateFeesRepayFee => {
+            let new = u64::from_le_bytes(value[..8].try_into().unwrap());
+            let prv = reserve.config.repay_fee_sf;
+            reserve.config.repay_fee_sf = new;
+            xmsg!("Prv Value is {}", Fraction::from_bits(prv.into()));
+            xmsg!("New Value is {}", Fraction::from_bits(new.into()));
+        }
+        UpdateConfigMode::UpdateFlatBorrowFee => {
+            let new = u64::from_le_bytes(value[..8].try_into().unwrap());
+            let prv = reserve.config.fees.flat_borrow_fee;
+            reserve.config.fees.flat_borrow_fee = new;
+            xmsg!("Prv Value is {:?}", prv);
+            xmsg!("New Value is {:?}", new);
+        }
         UpdateConfigMode::UpdateFeesFlashLoanFee => {
             let new = u64::from_le_bytes(value[..8].try_into().unwrap());
             let prv = reserve.config.fees.flash_loan_fee_sf;
@@ -1070,6 +2475,20 @@ pub fn update_reserve_config(reserve: &mut Reserve, mode: UpdateConfigMode, valu
             xmsg!("Prv Value is {:?}", prv);
             xmsg!("New Value is {:?}", new);
         }
+        UpdateConfigMode::UpdateTokenInfoPriceMaxAgeLiquidation => {
+            let new = u64::from_le_bytes(value[..8].try_into().unwrap());
+            let prv = reserve.config.token_info.max_age_price_seconds_liquidation;
+            reserve.config.token_info.max_age_price_seconds_liquidation = new;
+            xmsg!("Prv Value is {:?}", prv);
+            xmsg!("New Value is {:?}", new);
+        }
+        UpdateConfigMode::UpdateTokenInfoMaxConfidencePct => {
+            let new = u64::from_le_bytes(value[..8].try_into().unwrap());
+            let prv = reserve.config.token_info.max_confidence_pct;
+            reserve.config.token_info.max_confidence_pct = new;
+            xmsg!("Prv Value is {:?}", prv);
+            xmsg!("New Value is {:?}", new);
+        }
         UpdateConfigMode::UpdateTokenInfoTwapMaxAge => {
             let new = u64::from_le_bytes(value[..8].try_into().unwrap());
             let prv = reserve.config.token_info.max_age_twap_seconds;
@@ -1077,12 +2496,78 @@ pub fn update_reserve_config(reserve: &mut Reserve, mode: UpdateConfigMode, valu
             xmsg!("Prv Value is {:?}", prv);
             xmsg!("New Value is {:?}", new);
         }
-
-        UpdateConfigMode::UpdatePythPrice => {
+        UpdateConfigMode::UpdateTokenInfoPricePinMode => {
+            let new = u64::from_le_bytes(value[..8].try_into().unwrap());
+            let prv = reserve.config.token_info.price_pin_mode;
+            reserve.config.token_info.price_pin_mode = new;
+            xmsg!("Prv Value is {:?}", prv);
+            xmsg!("New Value is {:?}", new);
+        }
+        UpdateConfigMode::UpdateTokenInfoPricePinBandBps => {
+            let new = u64::from_le_bytes(value[..8].try_into().unwrap());
+            let prv = reserve.config.token_info.price_pin_band_bps;
+            reserve.config.token_info.price_pin_band_bps = new;
+            xmsg!("Prv Value is {:?}", prv);
+            xmsg!("New Value is {:?}", new);
+        }
+
+        UpdateConfigMode::UpdatePythPrice => {
+            let new: [u8; 32] = value[0..32].try_into().unwrap();
+            let new = Pubkey::new_from_array(new);
+            let prv = reserve.config.token_info.pyth_configuration.price;
+            reserve.config.token_info.pyth_configuration.price = new;
+            // switching to pyth drops the fixed price and the price authority
+            reserve.config.token_info.fixed_price_sf = 0;
+            reserve.config.token_info.price_authority = Pubkey::default();
+            xmsg!("Prv Value is {:?}", prv);
+            xmsg!("New Value is {:?}", new);
+        }
+        UpdateConfigMode::UpdateTokenInfoFixedPrice => {
+            let new = u64::from_le_bytes(value[..8].try_into().unwrap());
+            let prv = reserve.config.token_info.fixed_price_sf;
+            reserve.config.token_info.fixed_price_sf = new;
+            // switching to a fixed price drops the pyth feeds and the price authority
+            if new > 0 {
+                reserve.config.token_info.pyth_configuration.price = Pubkey::default();
+                reserve.config.token_info.fallback_oracle.price = Pubkey::default();
+                reserve.config.token_info.price_authority = Pubkey::default();
+            }
+            xmsg!("Prv Value is {}", Fraction::from_bits(prv.into()));
+            xmsg!("New Value is {}", Fraction::from_bits(new.into()));
+        }
+        UpdateConfigMode::UpdateTokenInfoPriceAuthority => {
+            let new: [u8; 32] = value[0..32].try_into().unwrap();
+            let new = Pubkey::new_from_array(new);
+            let prv = reserve.config.token_info.price_authority;
+            reserve.config.token_info.price_authority = new;
+            // pushed prices replace the pyth feeds and the fixed price
+            if new != Pubkey::default() {
+                reserve.config.token_info.pyth_configuration.price = Pubkey::default();
+                reserve.config.token_info.fallback_oracle.price = Pubkey::default();
+                reserve.config.token_info.fixed_price_sf = 0;
+            }
+            xmsg!("Prv Value is {:?}", prv);
+            xmsg!("New Value is {:?}", new);
+        }
+        UpdateConfigMode::UpdateLiquidationCloseFactorPct => {
+            let new = value[0];
+            let prv = reserve.config.liquidation_close_factor_pct;
+            reserve.config.liquidation_close_factor_pct = new;
+            xmsg!("Prv Value is {:?}", prv);
+            xmsg!("New Value is {:?}", new);
+        }
+        UpdateConfigMode::UpdateTokenInfoFallbackOracle => {
             let new: [u8; 32] = value[0..32].try_into().unwrap();
             let new = Pubkey::new_from_array(new);
-            let prv = reserve.config.token_info.pyth_configuration.price;
-            reserve.config.token_info.pyth_configuration.price = new;
+            let prv = reserve.config.token_info.fallback_oracle.price;
+            reserve.config.token_info.fallback_oracle.price = new;
+            xmsg!("Prv Value is {:?}", prv);
+            xmsg!("New Value is {:?}", new);
+        }
+        UpdateConfigMode::UpdateTokenInfoMaxFallbackDivergenceBps => {
+            let new = u64::from_le_bytes(value[..8].try_into().unwrap());
+            let prv = reserve.config.token_info.max_fallback_divergence_bps;
+            reserve.config.token_info.max_fallback_divergence_bps = new;
             xmsg!("Prv Value is {:?}", prv);
             xmsg!("New Value is {:?}", new);
         }
@@ -1093,6 +2578,22 @@ pub fn update_reserve_config(reserve: &mut Reserve, mode: UpdateConfigMode, valu
             xmsg!("Prv Value is {:?}", prv);
             xmsg!("New Value is {:?}", new);
         }
+        UpdateConfigMode::UpdateBorrowRateCurveKinked => {
+            let base_bps = u32::from_le_bytes(value[..4].try_into().unwrap());
+            let optimal_util_bps = u32::from_le_bytes(value[4..8].try_into().unwrap());
+            let rate_at_optimal_bps = u32::from_le_bytes(value[8..12].try_into().unwrap());
+            let max_rate_bps = u32::from_le_bytes(value[12..16].try_into().unwrap());
+            let new = BorrowRateCurve::from_kinked(
+                base_bps,
+                optimal_util_bps,
+                rate_at_optimal_bps,
+                max_rate_bps,
+            )?;
+            let prv = reserve.config.borrow_rate_curve;
+            reserve.config.borrow_rate_curve = new;
+            xmsg!("Prv Value is {:?}", prv);
+            xmsg!("New Value is {:?}", new);
+        }
         UpdateConfigMode::UpdateEntireReserveConfig => {
             let new: ReserveConfig = BorshDeserialize::deserialize(&mut &value[..]).unwrap();
             reserve.config = new;
@@ -1201,6 +2702,20 @@ pub fn update_reserve_config(reserve: &mut Reserve, mode: UpdateConfigMode, valu
             xmsg!("Prv Value is {:?}", prv);
             xmsg!("New Value is {:?}", new);
         }
+        UpdateConfigMode::UpdateDebtTranche => {
+            let new = value[0];
+            let prv = reserve.config.debt_tranche;
+            reserve.config.debt_tranche = new;
+            xmsg!("Prv Value is {:?}", prv);
+            xmsg!("New Value is {:?}", new);
+        }
+        UpdateConfigMode::UpdateElevationGroup => {
+            let new = value[0];
+            let prv = reserve.config.elevation_group;
+            reserve.config.elevation_group = new;
+            xmsg!("Prv Value is {:?}", prv);
+            xmsg!("New Value is {:?}", new);
+        }
         UpdateConfigMode::DeleveragingThresholdSlotsPerBps => {
             let new = u64::from_le_bytes(value[..8].try_into().unwrap());
             let prv = reserve.config.deleveraging_threshold_slots_per_bps;
@@ -1208,6 +2723,145 @@ pub fn update_reserve_config(reserve: &mut Reserve, mode: UpdateConfigMode, valu
             xmsg!("Prv Value is {:?}", prv);
             xmsg!("New Value is {:?}", new);
         }
+        UpdateConfigMode::UpdateFlashLoanReferralFeeBps => {
+            let new: u16 = u16::from_le_bytes(value[..2].try_into().unwrap());
+            let prv = reserve.config.flash_loan_referral_fee_bps;
+            reserve.config.flash_loan_referral_fee_bps = new;
+            xmsg!("Prv Value is {:?}", prv);
+            xmsg!("New Value is {:?}", new);
+        }
+        UpdateConfigMode::UpdateRedeemFeesLiquidityBufferBps => {
+            let new: u16 = u16::from_le_bytes(value[..2].try_into().unwrap());
+            let prv = reserve.config.redeem_fees_liquidity_buffer_bps;
+            reserve.config.redeem_fees_liquidity_buffer_bps = new;
+            xmsg!("Prv Value is {:?}", prv);
+            xmsg!("New Value is {:?}", new);
+        }
+        UpdateConfigMode::UpdateMinAvailableLiquidity => {
+            let new = u64::from_le_bytes(value[..8].try_into().unwrap());
+            let prv = reserve.config.min_available_liquidity;
+            reserve.config.min_available_liquidity = new;
+            xmsg!("Prv Value is {:?}", prv);
+            xmsg!("New Value is {:?}", new);
+        }
+        UpdateConfigMode::UpdateMinSupplyBorrowSpreadBps => {
+            let new = u16::from_le_bytes(value[..2].try_into().unwrap());
+            let prv = reserve.config.min_supply_borrow_spread_bps;
+            reserve.config.min_supply_borrow_spread_bps = new;
+            xmsg!("Prv Value is {:?}", prv);
+            xmsg!("New Value is {:?}", new);
+        }
+        UpdateConfigMode::UpdateLiquidationBondLamports => {
+            let new = u64::from_le_bytes(value[..8].try_into().unwrap());
+            let prv = reserve.config.liquidation_bond_lamports;
+            reserve.config.liquidation_bond_lamports = new;
+            xmsg!("Prv Value is {:?}", prv);
+            xmsg!("New Value is {:?}", new);
+        }
+        UpdateConfigMode::UpdateDepositsDisabled => {
+            let new = value[0];
+            let prv = reserve.config.deposits_disabled;
+            reserve.config.deposits_disabled = new;
+            xmsg!("Prv Value is {:?}", prv);
+            xmsg!("New Value is {:?}", new);
+        }
+        UpdateConfigMode::UpdateIsRebasing => {
+            let new = value[0];
+            let prv = reserve.config.is_rebasing;
+            reserve.config.is_rebasing = new;
+            xmsg!("Prv Value is {:?}", prv);
+            xmsg!("New Value is {:?}", new);
+        }
+        UpdateConfigMode::UpdateBorrowingDisabled => {
+            let new = value[0];
+            let prv = reserve.config.borrowing_disabled;
+            reserve.config.borrowing_disabled = new;
+            xmsg!("Prv Value is {:?}", prv);
+            xmsg!("New Value is {:?}", new);
+        }
+        UpdateConfigMode::UpdateProtocolFeeReceiver => {
+            let new: [u8; 32] = value[0..32].try_into().unwrap();
+            let new = Pubkey::new_from_array(new);
+            let prv = reserve.config.protocol_fee_receiver;
+            reserve.config.protocol_fee_receiver = new;
+            xmsg!("Prv Value is {:?}", prv);
+            xmsg!("New Value is {:?}", new);
+        }
+        UpdateConfigMode::UpdateMaxBorrowRateBps => {
+            let new = u64::from_le_bytes(value[..8].try_into().unwrap());
+            let prv = reserve.config.max_borrow_rate_bps;
+            reserve.config.max_borrow_rate_bps = new;
+            xmsg!("Prv Value is {:?}", prv);
+            xmsg!("New Value is {:?}", new);
+        }
+        UpdateConfigMode::UpdateMinBorrowRateBps => {
+            let new = u64::from_le_bytes(value[..8].try_into().unwrap());
+            let prv = reserve.config.min_borrow_rate_bps;
+            reserve.config.min_borrow_rate_bps = new;
+            xmsg!("Prv Value is {:?}", prv);
+            xmsg!("New Value is {:?}", new);
+        }
+        UpdateConfigMode::UpdateLimitCrossedGraceSlots => {
+            let new = u64::from_le_bytes(value[..8].try_into().unwrap());
+            let prv = reserve.config.limit_crossed_grace_slots;
+            reserve.config.limit_crossed_grace_slots = new;
+            xmsg!("Prv Value is {:?}", prv);
+            xmsg!("New Value is {:?}", new);
+        }
+        UpdateConfigMode::UpdateRewardEmissionPerSlot => {
+            let new = u64::from_le_bytes(value[..8].try_into().unwrap());
+            let prv = reserve.config.reward_emission_per_slot;
+            reserve.config.reward_emission_per_slot = new;
+            xmsg!("Prv Value is {:?}", prv);
+            xmsg!("New Value is {:?}", new);
+        }
+        UpdateConfigMode::UpdateMaxSingleDeposit => {
+            let new = u64::from_le_bytes(value[..8].try_into().unwrap());
+            let prv = reserve.config.max_single_deposit;
+            reserve.config.max_single_deposit = new;
+            xmsg!("Prv Value is {:?}", prv);
+            xmsg!("New Value is {:?}", new);
+        }
+        UpdateConfigMode::UpdateMaxPriceChangeBpsPerRefresh => {
+            let new = u64::from_le_bytes(value[..8].try_into().unwrap());
+            let prv = reserve.config.max_price_change_bps_per_refresh;
+            reserve.config.max_price_change_bps_per_refresh = new;
+            xmsg!("Prv Value is {:?}", prv);
+            xmsg!("New Value is {:?}", new);
+        }
+        UpdateConfigMode::UpdateInsuranceFeeBps => {
+            let new = u64::from_le_bytes(value[..8].try_into().unwrap());
+            let prv = reserve.config.insurance_fee_bps;
+            reserve.config.insurance_fee_bps = new;
+            xmsg!("Prv Value is {:?}", prv);
+            xmsg!("New Value is {:?}", new);
+        }
+        UpdateConfigMode::UpdateMinDepositHoldSlots => {
+            let new = u64::from_le_bytes(value[..8].try_into().unwrap());
+            let prv = reserve.config.min_deposit_hold_slots;
+            reserve.config.min_deposit_hold_slots = new;
+            xmsg!("Prv Value is {:?}", prv);
+            xmsg!("New Value is {:?}", new);
+        }
+        UpdateConfigMode::UpdateMinInitialDepositAmount => {
+            let new = u64::from_le_bytes(value[..8].try_into().unwrap());
+            let prv = reserve.config.min_initial_deposit_amount;
+            reserve.config.min_initial_deposit_amount = new;
+            xmsg!("Prv Value is {:?}", prv);
+            xmsg!("New Value is {:?}", new);
+        }
+        UpdateConfigMode::UpdateVirtualCollateralOffset => {
+            let new = u64::from_le_bytes(value[..8].try_into().unwrap());
+            // changing the offset moves the exchange rate of collateral already minted
+            if reserve.collateral.mint_total_supply > 0 {
+                xmsg!("Virtual collateral offset can only be set before the first deposit");
+                return err!(LendingError::InvalidConfig);
+            }
+            let prv = reserve.config.virtual_collateral_offset;
+            reserve.config.virtual_collateral_offset = new;
+            xmsg!("Prv Value is {:?}", prv);
+            xmsg!("New Value is {:?}", new);
+        }
         UpdateConfigMode::UpdateReserveStatus => {
             let new = ReserveStatus::try_from(value[0]).unwrap();
             let prv = ReserveStatus::try_from(reserve.config.status).unwrap();
@@ -1218,15 +2872,20 @@ pub fn update_reserve_config(reserve: &mut Reserve, mode: UpdateConfigMode, valu
     }
 
     reserve.last_update.mark_stale();
+
+    Ok(())
 }
 
 pub mod utils {
     use super::*;
     use crate::{
-        constants::{ten_pow, FULL_BPS, PROGRAM_VERSION},
+        constants::{
+            ten_pow, DUST_LAMPORT_THRESHOLD, ELEVATION_GROUP_NONE, FULL_BPS, MAX_CONFIDENCE_PCT,
+            MAX_NUM_ELEVATION_GROUPS, PROGRAM_VERSION,
+        },
         state::{ObligationCollateral, ObligationLiquidity, ReserveConfig},
         utils::FRACTION_ONE_SCALED,
-        AssetTier,
+        AssetTier, DebtTranche,
     };
 
     pub(crate) fn repay_and_withdraw_from_obligation_post_liquidation(
@@ -1254,12 +2913,81 @@ pub mod utils {
 
         obligation.repay(settle_amount_f, liquidity_index)?;
         obligation.withdraw(withdraw_amount, collateral_index)?;
+        obligation.compact_obligation();
         obligation.update_has_debt();
         obligation.last_update.mark_stale();
 
         Ok(())
     }
 
+    /// Counts a deposit made before rewards were tracked in its reserve's rewards, it only
+    /// earns from now on
+    pub(crate) fn track_obligation_collateral_rewards(
+        reserve: &mut Reserve,
+        obligation: &mut Obligation,
+        collateral_index: usize,
+    ) -> Result<()> {
+        if obligation.is_deposit_reward_tracked(collateral_index) {
+            return Ok(());
+        }
+
+        let deposited_amount = obligation.deposits[collateral_index].deposited_amount;
+        deposit_obligation_collateral_rewards(reserve, obligation, deposited_amount)?;
+        obligation.deposits_reward_tracked[collateral_index] = true as u8;
+
+        Ok(())
+    }
+
+    pub(crate) fn deposit_obligation_collateral_rewards(
+        deposit_reserve: &mut Reserve,
+        obligation: &mut Obligation,
+        collateral_amount: u64,
+    ) -> Result<()> {
+        deposit_reserve.reward_state.deposit(collateral_amount)?;
+        obligation.add_reward_debt(
+            collateral_amount,
+            deposit_reserve.reward_state.reward_per_collateral_token(),
+        )
+    }
+
+    pub(crate) fn withdraw_obligation_collateral_rewards(
+        withdraw_reserve: &mut Reserve,
+        obligation: &mut Obligation,
+        collateral_amount: u64,
+    ) -> Result<()> {
+        withdraw_reserve.reward_state.withdraw(collateral_amount)?;
+        obligation.remove_reward_debt(
+            collateral_amount,
+            withdraw_reserve.reward_state.reward_per_collateral_token(),
+        )
+    }
+
+    /// Writes off a sub-lamport debt left after a repay as bad debt, otherwise it can never be
+    /// repaid and keeps the obligation from being closed
+    pub(crate) fn forgive_dust_debt(
+        reserve: &mut Reserve,
+        obligation: &mut Obligation,
+        liquidity_index: usize,
+    ) -> Result<()> {
+        let remaining_debt_f =
+            Fraction::from_bits(obligation.borrows[liquidity_index].borrowed_amount_sf);
+        if remaining_debt_f == Fraction::ZERO
+            || remaining_debt_f >= Fraction::from(DUST_LAMPORT_THRESHOLD)
+        {
+            return Ok(());
+        }
+
+        xmsg!(
+            "Forgiving dust debt of {} on reserve {}",
+            remaining_debt_f,
+            reserve.token_symbol()
+        );
+        reserve.liquidity.forgive_debt(remaining_debt_f)?;
+        obligation.repay(remaining_debt_f, liquidity_index)
+    }
+
+    /// `liquidity_amount` is in liquidity mint units, collateral amounts are converted with the
+    /// exchange rate first, so the collateral mint decimals never enter the valuation
     pub(crate) fn calculate_market_value_from_liquidity_amount(
         reserve: &Reserve,
         liquidity_amount: Fraction,
@@ -1268,12 +2996,33 @@ pub mod utils {
             ten_pow(reserve.liquidity.mint_decimals.try_into().unwrap()).into();
         let market_price_f = reserve.liquidity.get_market_price_f();
         let market_value = liquidity_amount
-            .mul(market_price_f)
+            .checked_mul(market_price_f)
+            .ok_or_else(|| {
+                xmsg!(
+                    "Market value of {} {} overflows",
+                    liquidity_amount,
+                    reserve.token_symbol()
+                );
+                error!(LendingError::MathOverflow)
+            })?
             .div(mint_decimal_factor);
 
         Ok(market_value)
     }
 
+    /// Adds a position's market value to an obligation total, the `Fraction` operators wrap
+    /// silently on overflow outside of debug builds
+    pub(crate) fn checked_add_market_value(total: Fraction, value: Fraction) -> Result<Fraction> {
+        total.checked_add(value).ok_or_else(|| {
+            xmsg!(
+                "Obligation market value overflows, total {} + {}",
+                total,
+                value
+            );
+            error!(LendingError::MathOverflow)
+        })
+    }
+
     pub(crate) fn calculate_obligation_collateral_market_value(
         deposit_reserve: &Reserve,
         deposit: &ObligationCollateral,
@@ -1482,10 +3231,13 @@ pub mod utils {
         min_accepted_net_value: Fraction,
     ) -> Result<()> {
         let asset_mv = calculate_market_value_from_liquidity_amount(reserve, amount)?;
+        let borrow_factor = reserve
+            .config
+            .get_obligation_borrow_factor(obligation.borrow_factor_override_pct);
 
         let new_total_bf_debt_mv =
             Fraction::from_bits(obligation.borrow_factor_adjusted_debt_value_sf)
-                + asset_mv * reserve.borrow_factor_f();
+                + asset_mv * borrow_factor;
         let new_total_no_bf_debt_mv =
             Fraction::from_bits(obligation.borrowed_assets_market_value_sf) + asset_mv;
         let new_liquidity_asset_mv = liquidity_asset_mv + asset_mv;
@@ -1522,6 +3274,34 @@ pub mod utils {
         Ok(())
     }
 
+    pub fn check_obligation_value_cap(
+        amount: Fraction,
+        obligation: &Obligation,
+        reserve: &Reserve,
+        lending_market: &LendingMarket,
+    ) -> Result<()> {
+        if lending_market.max_obligation_value == 0 {
+            return Ok(());
+        }
+
+        let asset_mv = calculate_market_value_from_liquidity_amount(reserve, amount)?;
+        let new_total_mv = Fraction::from_bits(obligation.deposited_value_sf)
+            + Fraction::from_bits(obligation.borrowed_assets_market_value_sf)
+            + asset_mv;
+
+        if new_total_mv > Fraction::from(lending_market.max_obligation_value) {
+            xmsg!(
+                "Obligation new total value {} of {} above the market maximum {}",
+                new_total_mv.to_display(),
+                reserve.token_symbol(),
+                lending_market.max_obligation_value
+            );
+            return err!(LendingError::ObligationValueCapExceeded);
+        }
+
+        Ok(())
+    }
+
     pub fn post_repay_obligation_invariants(
         amount: Fraction,
         obligation: &Obligation,
@@ -1530,9 +3310,12 @@ pub mod utils {
         min_accepted_net_value: Fraction,
     ) -> Result<()> {
         let asset_mv = calculate_market_value_from_liquidity_amount(reserve, amount)?;
+        let borrow_factor = reserve
+            .config
+            .get_obligation_borrow_factor(obligation.borrow_factor_override_pct);
         let new_total_bf_debt_mv =
             Fraction::from_bits(obligation.borrow_factor_adjusted_debt_value_sf)
-                - asset_mv * reserve.borrow_factor_f();
+                - asset_mv * borrow_factor;
         let total_deposited_mv = Fraction::from_bits(obligation.deposited_value_sf);
 
         if liquidity_asset_mv != 0 {
@@ -1564,6 +3347,47 @@ pub mod utils {
         Ok(())
     }
 
+    pub fn post_repay_with_collateral_obligation_invariants(
+        amount: Fraction,
+        obligation: &Obligation,
+        reserve: &Reserve,
+    ) -> Result<()> {
+        let asset_mv = calculate_market_value_from_liquidity_amount(reserve, amount)?;
+        let borrow_factor = reserve
+            .config
+            .get_obligation_borrow_factor(obligation.borrow_factor_override_pct);
+        let new_total_deposited_mv =
+            Fraction::from_bits(obligation.deposited_value_sf).saturating_sub(asset_mv);
+        let new_total_bf_debt_mv =
+            Fraction::from_bits(obligation.borrow_factor_adjusted_debt_value_sf)
+                .saturating_sub(asset_mv * borrow_factor);
+
+        if new_total_deposited_mv == 0 {
+            if new_total_bf_debt_mv > 0 {
+                xmsg!(
+                    "Obligation can't have debt without deposits after repay with collateral of {}",
+                    reserve.token_symbol()
+                );
+                return err!(LendingError::LiabilitiesBiggerThanAssets);
+            }
+            return Ok(());
+        }
+
+        let new_ltv = new_total_bf_debt_mv / new_total_deposited_mv;
+
+        if new_ltv > obligation.loan_to_value() {
+            xmsg!(
+                "Obligation new LTV/LTV after repay with collateral {:.2}/{:.2} of {}",
+                new_ltv.to_display(),
+                obligation.loan_to_value().to_display(),
+                reserve.token_symbol()
+            );
+            return err!(LendingError::WorseLTVBlocked);
+        }
+
+        Ok(())
+    }
+
     pub fn check_obligation_fully_refreshed_and_not_null(
         obligation: &Obligation,
         slot: Slot,
@@ -1591,6 +3415,7 @@ pub mod utils {
     }
 
     pub fn assert_obligation_liquidatable(
+        lending_market: &LendingMarket,
         repay_reserve: &Reserve,
         withdraw_reserve: &Reserve,
         obligation: &Obligation,
@@ -1644,6 +3469,25 @@ pub mod utils {
             return err!(LendingError::ObligationBorrowsZero);
         }
 
+        let health = obligation.classify_health();
+        xmsg!("Obligation health is {:?}", health);
+
+        // bad debt only grows while it waits, so it skips the grace period
+        if health != ObligationHealth::BadDebt
+            && obligation.unhealthy_since_slot != 0
+            && slot
+                < obligation
+                    .unhealthy_since_slot
+                    .saturating_add(lending_market.liquidation_grace_slots)
+        {
+            xmsg!(
+                "Obligation is unhealthy since slot {} and within the liquidation grace period of {} slots",
+                obligation.unhealthy_since_slot,
+                lending_market.liquidation_grace_slots
+            );
+            return err!(LendingError::ObligationHealthy);
+        }
+
         Ok(())
     }
 
@@ -1666,22 +3510,88 @@ pub mod utils {
             xmsg!("Borrow fee must be in range [0, 100%]");
             return err!(LendingError::InvalidConfig);
         }
+        if u128::from(config.repay_fee_sf) >= FRACTION_ONE_SCALED {
+            xmsg!("Repay fee must be in range [0, 100%]");
+            return err!(LendingError::InvalidConfig);
+        }
+        if config.flash_loan_referral_fee_bps > FULL_BPS {
+            xmsg!("Flash loan referral fee must be in range [0, 10000] bps");
+            return err!(LendingError::InvalidConfig);
+        }
+        if config.token_info.effective_max_confidence_pct() > MAX_CONFIDENCE_PCT {
+            xmsg!(
+                "Max price confidence must be in range (0, {}]%",
+                MAX_CONFIDENCE_PCT
+            );
+            return err!(LendingError::InvalidConfig);
+        }
+        if config.redeem_fees_liquidity_buffer_bps > FULL_BPS {
+            xmsg!("Redeem fees liquidity buffer must be in range [0, 10000] bps");
+            return err!(LendingError::InvalidConfig);
+        }
         if config.protocol_liquidation_fee_pct > 100 {
             xmsg!("Protocol liquidation fee must be in range [0, 100]");
             return err!(LendingError::InvalidConfig);
         }
+        if config.insurance_fee_bps > FULL_BPS as u64 {
+            xmsg!("Insurance fee must be in range [0, 10000] bps");
+            return err!(LendingError::InvalidConfig);
+        }
+        if config.max_borrow_rate_bps != 0
+            && config.min_borrow_rate_bps > config.max_borrow_rate_bps
+        {
+            xmsg!(
+                "Min borrow rate {} bps cannot exceed the max borrow rate {} bps",
+                config.min_borrow_rate_bps,
+                config.max_borrow_rate_bps
+            );
+            return err!(LendingError::InvalidConfig);
+        }
         if config.protocol_take_rate_pct > 100 {
             xmsg!("Protocol take rate must be in range [0, 100]");
             return err!(LendingError::InvalidConfig);
         }
+        if config.min_supply_borrow_spread_bps > FULL_BPS {
+            xmsg!("Min supply borrow spread must be in range [0, 10000] bps");
+            return err!(LendingError::InvalidConfig);
+        }
+        if config.deposits_disabled > 1 {
+            xmsg!("Deposits disabled must be 0 or 1");
+            return err!(LendingError::InvalidConfig);
+        }
+        if config.is_rebasing > 1 {
+            xmsg!("Is rebasing must be 0 or 1");
+            return err!(LendingError::InvalidConfig);
+        }
+        if config.borrowing_disabled > 1 {
+            xmsg!("Borrowing disabled must be 0 or 1");
+            return err!(LendingError::InvalidConfig);
+        }
+        if config.liquidation_close_factor_pct != 0
+            && !(5..=100).contains(&config.liquidation_close_factor_pct)
+        {
+            xmsg!("Liquidation close factor must be 0 or in range [5, 100]");
+            return err!(LendingError::InvalidConfig);
+        }
         if !config.token_info.is_valid() {
-            xmsg!("Invalid reserve token info");
+            xmsg!(
+                "Invalid reserve token info, exactly one of pyth, a fixed price or a price \
+                 authority must be set and a fallback feed must back up a different pyth feed"
+            );
             return err!(LendingError::InvalidOracleConfig);
         }
         if !config.token_info.is_twap_config_valid() {
             xmsg!("Invalid reserve token twap config");
             return err!(LendingError::InvalidTwapConfig);
         }
+        if !config.token_info.is_price_pin_config_valid() {
+            xmsg!(
+                "Invalid reserve token price pin mode {} or band {} bps",
+                config.token_info.price_pin_mode,
+                config.token_info.price_pin_band_bps
+            );
+            return err!(LendingError::InvalidOracleConfig);
+        }
 
         if config.bad_debt_liquidation_bonus_bps >= 100 {
             xmsg!("Invalid bad debt liquidation bonus, cannot be more than 1%");
@@ -1709,11 +3619,114 @@ pub mod utils {
             xmsg!("Borrow limit must be 0 for isolated collateral assets");
             return Err(LendingError::InvalidConfig.into());
         }
+        if DebtTranche::try_from(config.debt_tranche).is_err() {
+            xmsg!("Invalid debt tranche {}", config.debt_tranche);
+            return err!(LendingError::InvalidConfig);
+        }
+        if config.elevation_group > MAX_NUM_ELEVATION_GROUPS {
+            xmsg!(
+                "Elevation group must be in range [0, {}]",
+                MAX_NUM_ELEVATION_GROUPS
+            );
+            return err!(LendingError::InvalidConfig);
+        }
 
         config.borrow_rate_curve.validate()?;
         Ok(())
     }
 
+    /// The risk authority can only pause a reserve, lower its limits or mark it obsolete,
+    /// every other change requires the market owner
+    pub fn validate_risk_authority_reserve_update(
+        mode: UpdateConfigMode,
+        prev: &ReserveConfig,
+        new: &ReserveConfig,
+    ) -> Result<()> {
+        let is_tightening = match mode {
+            UpdateConfigMode::UpdateDepositLimit => new.deposit_limit <= prev.deposit_limit,
+            UpdateConfigMode::UpdateBorrowLimit => new.borrow_limit <= prev.borrow_limit,
+            UpdateConfigMode::UpdateMaxSingleDeposit => {
+                new.max_single_deposit != 0
+                    && (prev.max_single_deposit == 0
+                        || new.max_single_deposit <= prev.max_single_deposit)
+            }
+            UpdateConfigMode::UpdateDepositsDisabled => {
+                new.is_deposits_disabled() || !prev.is_deposits_disabled()
+            }
+            UpdateConfigMode::UpdateBorrowingDisabled => {
+                !new.is_borrowing_enabled() || prev.is_borrowing_enabled()
+            }
+            UpdateConfigMode::UpdateReserveStatus => {
+                new.status() == ReserveStatus::Obsolete || new.status() == prev.status()
+            }
+            _ => {
+                xmsg!("Mode {:?} can only be updated by the market owner", mode);
+                return err!(LendingError::RiskAuthorityUpdateNotAllowed);
+            }
+        };
+
+        if !is_tightening {
+            xmsg!("Risk authority can only tighten the reserve config");
+            return err!(LendingError::RiskAuthorityUpdateNotAllowed);
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn get_ltv_and_liquidation_threshold(
+        reserve: &Reserve,
+        elevation_group: Option<&ElevationGroup>,
+    ) -> (u8, u8) {
+        match elevation_group {
+            Some(group) if group.id == reserve.config.elevation_group => {
+                (group.ltv_pct, group.liquidation_threshold_pct)
+            }
+            _ => (
+                reserve.config.loan_to_value_pct,
+                reserve.config.liquidation_threshold_pct,
+            ),
+        }
+    }
+
+    /// Raises the LTV by the boost, but never past the liquidation buffer below the liquidation
+    /// threshold, which is in turn tightened to the boosted LTV plus the buffer
+    pub(crate) fn apply_aggressive_mode(
+        (ltv_pct, liquidation_threshold_pct): (u8, u8),
+        ltv_boost_pct: u8,
+        liquidation_buffer_pct: u8,
+    ) -> (u8, u8) {
+        if ltv_boost_pct == 0 || ltv_pct == 0 {
+            return (ltv_pct, liquidation_threshold_pct);
+        }
+
+        let boosted_ltv_pct = ltv_pct
+            .saturating_add(ltv_boost_pct)
+            .min(liquidation_threshold_pct.saturating_sub(liquidation_buffer_pct))
+            .max(ltv_pct);
+        let tightened_liquidation_threshold_pct =
+            liquidation_threshold_pct.min(boosted_ltv_pct.saturating_add(liquidation_buffer_pct));
+
+        (boosted_ltv_pct, tightened_liquidation_threshold_pct)
+    }
+
+    pub(crate) fn check_elevation_group_membership(
+        obligation: &Obligation,
+        reserve: &Reserve,
+    ) -> Result<()> {
+        if obligation.elevation_group != ELEVATION_GROUP_NONE
+            && reserve.config.elevation_group != obligation.elevation_group
+        {
+            xmsg!(
+                "Reserve elevation group {} does not match obligation elevation group {}",
+                reserve.config.elevation_group,
+                obligation.elevation_group
+            );
+            return err!(LendingError::InconsistentElevationGroup);
+        }
+
+        Ok(())
+    }
+
     pub fn validate_obligation_asset_tiers(obligation: &Obligation) -> Result<()> {
         let deposit_tiers = obligation.get_deposit_asset_tiers();
 
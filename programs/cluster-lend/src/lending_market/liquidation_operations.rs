@@ -1,6 +1,6 @@
 use std::cmp::{max, min, Ordering};
 
-use anchor_lang::{err, prelude::msg, solana_program::clock::Slot, Result};
+use anchor_lang::{err, error, prelude::msg, solana_program::clock::Slot, Result};
 
 use crate::{
     constants::{DUST_LAMPORT_THRESHOLD, MIN_AUTODELEVERAGE_BONUS_BPS},
@@ -27,6 +27,11 @@ pub fn max_liquidatable_borrowed_amount(
 
     let liquidation_max_debt_close_factor_rate =
         if user_ltv > Fraction::from_percent(insolvency_risk_ltv_pct) {
+            xmsg!(
+                "LTV {} is above the insolvency risk LTV {}%, full close factor applies",
+                user_ltv.to_display(),
+                insolvency_risk_ltv_pct
+            );
             Fraction::ONE
         } else {
             Fraction::from_percent(liquidation_max_debt_close_factor_pct)
@@ -65,6 +70,11 @@ pub fn calculate_liquidation(
         return err!(LendingError::InvalidObligationCollateral);
     }
 
+    if liquidity.is_subordinated() && obligation.has_senior_debt() {
+        xmsg!("Senior debt must be liquidated before subordinated debt");
+        return err!(LendingError::SeniorDebtOutstanding);
+    }
+
     let LiquidationParams {
         user_ltv,
         liquidation_bonus_rate,
@@ -77,30 +87,75 @@ pub fn calculate_liquidation(
         max_allowed_ltv_override_pct_opt,
     )?;
 
+    let liquidation_bonus_rate = if lending_market.is_liquidation_bonus_net_of_protocol_fee() {
+        // grossing up must not push the bonus past the bad debt cap the net bonus was held to
+        let max_liquidation_bonus_rate = max(
+            liquidation_bonus_rate,
+            Fraction::ONE.saturating_sub(user_ltv),
+        );
+        let gross_liquidation_bonus_rate = min(
+            gross_up_liquidation_bonus(
+                liquidation_bonus_rate,
+                collateral_reserve.config.protocol_liquidation_fee_pct,
+            ),
+            max_liquidation_bonus_rate,
+        );
+        xmsg!(
+            "Liquidation bonus is net of protocol fee: net {}, gross {}",
+            liquidation_bonus_rate.to_display(),
+            gross_liquidation_bonus_rate.to_display()
+        );
+        gross_liquidation_bonus_rate
+    } else {
+        liquidation_bonus_rate
+    };
+
     let bonus_rate = liquidation_bonus_rate + Fraction::ONE;
 
     let borrowed_amount_f = Fraction::from_bits(liquidity.borrowed_amount_sf);
 
     let borrowed_value_f = Fraction::from_bits(liquidity.market_value_sf);
 
+    let is_max_amount_requested = debt_amount_to_liquidate == u64::MAX;
     let debt_amount_to_liquidate =
         Fraction::from_num(debt_amount_to_liquidate).min(borrowed_amount_f);
 
+    let total_borrowed_value_f = Fraction::from_bits(obligation.borrowed_assets_market_value_sf);
     let is_below_min_full_liquidation_value_threshold =
-        borrowed_value_f < lending_market.min_full_liquidation_value_threshold;
+        total_borrowed_value_f < lending_market.min_full_liquidation_value_threshold;
 
     let debt_liquidation_amount_f = if is_below_min_full_liquidation_value_threshold {
+        if debt_amount_to_liquidate < borrowed_amount_f {
+            xmsg!(
+                "Obligation debt value {} is below the full liquidation threshold {}, the whole borrow of {} must be liquidated",
+                total_borrowed_value_f.to_display(),
+                lending_market.min_full_liquidation_value_threshold,
+                borrowed_amount_f.to_display()
+            );
+            return err!(LendingError::FullLiquidationRequired);
+        }
         borrowed_amount_f
     } else {
-        max_liquidatable_borrowed_amount(
+        let max_liquidatable_amount_f = max_liquidatable_borrowed_amount(
             obligation,
-            lending_market.liquidation_max_debt_close_factor_pct,
+            collateral_reserve
+                .config
+                .liquidation_close_factor_pct(lending_market.liquidation_max_debt_close_factor_pct),
             lending_market.max_liquidatable_debt_market_value_at_once,
             liquidity,
             user_ltv,
             lending_market.insolvency_risk_unhealthy_ltv_pct,
-        )
-        .min(debt_amount_to_liquidate)
+        );
+        // u64::MAX liquidates as much as allowed, explicit amounts must fit the bounds
+        if !is_max_amount_requested && debt_amount_to_liquidate > max_liquidatable_amount_f.ceil() {
+            xmsg!(
+                "Liquidation amount {} is above the max liquidatable amount {}",
+                debt_amount_to_liquidate.to_display(),
+                max_liquidatable_amount_f.to_display()
+            );
+            return err!(LendingError::LiquidationTooLarge);
+        }
+        max_liquidatable_amount_f.min(debt_amount_to_liquidate)
     };
 
     xmsg!(
@@ -128,6 +183,140 @@ pub fn calculate_liquidation(
     })
 }
 
+pub fn calculate_deleverage_to_target(
+    collateral_reserve: &Reserve,
+    debt_reserve: &Reserve,
+    debt_amount_to_liquidate: u64,
+    lending_market: &LendingMarket,
+    obligation: &Obligation,
+    liquidity: &ObligationLiquidity,
+    collateral: &ObligationCollateral,
+) -> Result<CalculateLiquidationResult> {
+    if obligation.deposited_value_sf == 0 {
+        xmsg!("Deposited value backing a loan cannot be 0");
+        return err!(LendingError::InvalidObligationCollateral);
+    }
+
+    if liquidity.is_subordinated() && obligation.has_senior_debt() {
+        xmsg!("Senior debt must be liquidated before subordinated debt");
+        return err!(LendingError::SeniorDebtOutstanding);
+    }
+
+    if obligation.autodeleverage_target_ltv_pct == 0 {
+        xmsg!("Obligation has no autodeleverage target LTV");
+        return err!(LendingError::InvalidAutodeleverageTarget);
+    }
+
+    let user_ltv = obligation.loan_to_value();
+    let target_ltv = Fraction::from_percent(obligation.autodeleverage_target_ltv_pct);
+    if user_ltv <= target_ltv {
+        xmsg!(
+            "Obligation LTV {} is not above its autodeleverage target LTV {}%",
+            user_ltv.to_display(),
+            obligation.autodeleverage_target_ltv_pct
+        );
+        return err!(LendingError::ObligationHealthy);
+    }
+
+    let min_reserve_bonus_bps = max(
+        collateral_reserve.config.min_liquidation_bonus_bps,
+        debt_reserve.config.min_liquidation_bonus_bps,
+    );
+    let liquidation_bonus_rate = Fraction::from_bps(min(
+        obligation.autodeleverage_bonus_bps,
+        min_reserve_bonus_bps,
+    ));
+
+    let borrowed_amount_f = Fraction::from_bits(liquidity.borrowed_amount_sf);
+    let borrowed_value_f = Fraction::from_bits(liquidity.market_value_sf);
+
+    let max_deleverageable_amount = max_deleverageable_borrowed_amount(
+        obligation,
+        liquidity,
+        target_ltv,
+        liquidation_bonus_rate,
+        lending_market.max_liquidatable_debt_market_value_at_once,
+    )?;
+
+    let debt_liquidation_amount_f = Fraction::from_num(debt_amount_to_liquidate)
+        .min(Fraction::from_num(
+            max_deleverageable_amount.to_floor::<u64>(),
+        ))
+        .min(borrowed_amount_f);
+    if debt_liquidation_amount_f == Fraction::ZERO {
+        xmsg!("Obligation is already at its autodeleverage target LTV");
+        return err!(LendingError::ObligationHealthy);
+    }
+
+    xmsg!(
+        "Obligation is deleveraged from LTV {} to target {}% with liquidation bonus: {} bps, liquidation amount: {}",
+        user_ltv.to_display(),
+        obligation.autodeleverage_target_ltv_pct,
+        liquidation_bonus_rate.to_bps::<u32>().unwrap(),
+        debt_liquidation_amount_f.to_num::<u64>()
+    );
+
+    let liquidation_ratio = debt_liquidation_amount_f / borrowed_amount_f;
+
+    let total_liquidation_value_including_bonus =
+        borrowed_value_f * liquidation_ratio * (liquidation_bonus_rate + Fraction::ONE);
+
+    let (settle_amount, repay_amount, withdraw_amount) = calculate_liquidation_amounts(
+        total_liquidation_value_including_bonus,
+        collateral,
+        debt_liquidation_amount_f,
+        false,
+    );
+
+    Ok(CalculateLiquidationResult {
+        settle_amount_f: settle_amount,
+        repay_amount,
+        withdraw_amount,
+        liquidation_bonus_rate,
+    })
+}
+
+/// Borrowed amount that brings the obligation LTV down to `target_ltv` once repaid, with the
+/// bonus taken from the collateral
+pub fn max_deleverageable_borrowed_amount(
+    obligation: &Obligation,
+    liquidity: &ObligationLiquidity,
+    target_ltv: Fraction,
+    liquidation_bonus_rate: Fraction,
+    market_max_liquidatable_debt_value_at_once: u64,
+) -> Result<Fraction> {
+    let debt_value = Fraction::from_bits(obligation.borrow_factor_adjusted_debt_value_sf);
+    let deposited_value = Fraction::from_bits(obligation.deposited_value_sf);
+    let obligation_debt_for_liquidity_mv = Fraction::from_bits(liquidity.market_value_sf);
+    let borrow_factor = Fraction::from_bits(liquidity.borrow_factor_adjusted_market_value_sf)
+        / obligation_debt_for_liquidity_mv;
+
+    // (debt - repaid * borrow_factor) / (deposited - repaid * (1 + bonus)) = target
+    let ltv_reduction_per_repaid_value = borrow_factor
+        .checked_sub(target_ltv * (liquidation_bonus_rate + Fraction::ONE))
+        .filter(|reduction| *reduction > Fraction::ZERO)
+        .ok_or_else(|| {
+            xmsg!(
+                "Repaying debt with a borrow factor of {} cannot reach the target LTV {}",
+                borrow_factor.to_display(),
+                target_ltv.to_display()
+            );
+            error!(LendingError::AutodeleverageTargetUnreachable)
+        })?;
+    let excess_debt_value = debt_value.saturating_sub(deposited_value * target_ltv);
+
+    let max_liquidatable_mv = (excess_debt_value / ltv_reduction_per_repaid_value)
+        .min(obligation_debt_for_liquidity_mv)
+        .min(Fraction::from_num(
+            market_max_liquidatable_debt_value_at_once,
+        ));
+
+    let max_liquidation_ratio = max_liquidatable_mv / obligation_debt_for_liquidity_mv;
+
+    let borrowed_amount = Fraction::from_bits(liquidity.borrowed_amount_sf);
+    Ok(borrowed_amount * max_liquidation_ratio)
+}
+
 pub fn get_liquidation_params(
     lending_market: &LendingMarket,
     collateral_reserve: &Reserve,
@@ -137,6 +326,7 @@ pub fn get_liquidation_params(
     max_allowed_ltv_override_pct_opt: Option<u64>,
 ) -> Result<LiquidationParams> {
     if let Some(params) = check_liquidate_obligation(
+        lending_market,
         collateral_reserve,
         debt_reserve,
         obligation,
@@ -169,6 +359,7 @@ pub fn get_liquidation_params(
 }
 
 pub fn check_liquidate_obligation(
+    lending_market: &LendingMarket,
     collateral_reserve: &Reserve,
     debt_reserve: &Reserve,
     obligation: &Obligation,
@@ -176,7 +367,9 @@ pub fn check_liquidate_obligation(
 ) -> Option<LiquidationParams> {
     let user_ltv = obligation.loan_to_value();
     let max_allowed_ltv_user = obligation.unhealthy_loan_to_value();
-    let max_allowed_ltv_override_opt = max_allowed_ltv_override_pct_opt.map(Fraction::from_percent);
+    // An override may only make liquidation stricter, never reach a healthy obligation
+    let max_allowed_ltv_override_opt = max_allowed_ltv_override_pct_opt
+        .map(|pct| Fraction::from_percent(pct).max(max_allowed_ltv_user));
     let max_allowed_ltv = max_allowed_ltv_override_opt.unwrap_or(max_allowed_ltv_user);
 
     if user_ltv >= max_allowed_ltv {
@@ -195,6 +388,7 @@ pub fn check_liquidate_obligation(
                 &collateral_reserve.config,
                 &debt_reserve.config,
                 max_allowed_ltv,
+                Fraction::from_percent(lending_market.insolvency_risk_unhealthy_ltv_pct),
                 user_ltv,
             )
             .unwrap(),
@@ -222,20 +416,20 @@ fn calculate_liquidation_amounts(
                 repay_amount_f
             };
 
-            let repay_amount = settle_amount.to_ceil();
+            let repay_amount = settle_amount.to_ceil_protocol();
 
             let withdraw_amount = collateral.deposited_amount;
             (settle_amount, repay_amount, withdraw_amount)
         }
         Ordering::Equal => {
             let settle_amount = debt_liquidation_amount;
-            let repay_amount = settle_amount.to_ceil();
+            let repay_amount = settle_amount.to_ceil_protocol();
             let withdraw_amount = collateral.deposited_amount;
             (settle_amount, repay_amount, withdraw_amount)
         }
         Ordering::Less => {
             let settle_amount = debt_liquidation_amount;
-            let repay_amount = settle_amount.to_ceil();
+            let repay_amount = settle_amount.to_ceil_protocol();
             let withdraw_pct = total_liquidation_value_including_bonus / collateral_value;
             let withdraw_amount_f = Fraction::from_num(collateral.deposited_amount) * withdraw_pct;
 
@@ -244,7 +438,7 @@ fn calculate_liquidation_amounts(
             {
                 DUST_LAMPORT_THRESHOLD
             } else {
-                withdraw_amount_f.to_floor()
+                withdraw_amount_f.to_floor_protocol()
             };
             (settle_amount, repay_amount, withdraw_amount)
         }
@@ -255,6 +449,7 @@ fn calculate_liquidation_bonus(
     collateral_reserve_config: &ReserveConfig,
     debt_reserve_config: &ReserveConfig,
     max_allowed_ltv: Fraction,
+    max_bonus_ltv: Fraction,
     user_ltv: Fraction,
 ) -> Result<Fraction> {
     let bad_debt_ltv = Fraction::ONE;
@@ -277,26 +472,25 @@ fn calculate_liquidation_bonus(
         return Ok(capped_bonus);
     }
 
-    let unhealthy_factor = user_ltv - max_allowed_ltv;
-
     let max_bonus_bps = max(
         collateral_reserve_config.max_liquidation_bonus_bps,
         debt_reserve_config.max_liquidation_bonus_bps,
     );
 
     let max_bonus_bps = min(max_bonus_bps, u16::MAX);
-    let max_bonus = Fraction::from_bps(max_bonus_bps);
 
     let min_reserve_bonus_bps = max(
         collateral_reserve_config.min_liquidation_bonus_bps,
         debt_reserve_config.min_liquidation_bonus_bps,
     );
 
-    let min_reserve_bonus = Fraction::from_bps(min_reserve_bonus_bps);
-
-    let min_bonus = max(min_reserve_bonus, unhealthy_factor);
-
-    let collared_bonus = min(min_bonus, max_bonus);
+    let collared_bonus = scaled_liquidation_bonus(
+        user_ltv,
+        max_allowed_ltv,
+        max_bonus_ltv,
+        min_reserve_bonus_bps,
+        max_bonus_bps,
+    );
 
     let diff_to_bad_debt = bad_debt_ltv - user_ltv;
     let capped_max_liq_bonus_bad_debt = min(collared_bonus, diff_to_bad_debt);
@@ -464,6 +658,48 @@ fn calculate_autodeleverage_bonus(
     Ok((days_since_deleveraging_started, liquidation_bonus))
 }
 
+/// Bonus growing linearly from `min_bonus_bps` at the liquidation threshold
+/// to `max_bonus_bps` at `max_bonus_ltv`, the market's insolvency risk LTV
+pub fn scaled_liquidation_bonus(
+    current_ltv: Fraction,
+    liquidation_threshold: Fraction,
+    max_bonus_ltv: Fraction,
+    min_bonus_bps: u16,
+    max_bonus_bps: u16,
+) -> Fraction {
+    let min_bonus = Fraction::from_bps(min_bonus_bps);
+    let max_bonus = Fraction::from_bps(max_bonus_bps);
+
+    let unhealthy_progress = if current_ltv <= liquidation_threshold {
+        Fraction::ZERO
+    } else if liquidation_threshold >= max_bonus_ltv {
+        Fraction::ONE
+    } else {
+        let unhealthy_band = max_bonus_ltv - liquidation_threshold;
+        min(
+            (current_ltv - liquidation_threshold) / unhealthy_band,
+            Fraction::ONE,
+        )
+    };
+
+    let scaled_bonus = min_bonus + max_bonus.saturating_sub(min_bonus) * unhealthy_progress;
+
+    min(max(scaled_bonus, min_bonus), max_bonus)
+}
+
+pub fn gross_up_liquidation_bonus(
+    net_liquidation_bonus: Fraction,
+    protocol_liquidation_fee_pct: u8,
+) -> Fraction {
+    if protocol_liquidation_fee_pct >= 100 {
+        return net_liquidation_bonus;
+    }
+
+    let liquidator_share = Fraction::ONE - Fraction::from_percent(protocol_liquidation_fee_pct);
+
+    net_liquidation_bonus / liquidator_share
+}
+
 pub fn calculate_protocol_liquidation_fee(
     amount_liquidated: u64,
     liquidation_bonus: Fraction,
@@ -477,7 +713,14 @@ pub fn calculate_protocol_liquidation_fee(
     let bonus = amount_liquidated - (amount_liquidated / bonus_rate);
 
     let protocol_fee = bonus * protocol_liquidation_fee_rate;
-    let protocol_fee: u64 = protocol_fee.to_ceil();
+    let protocol_fee: u64 = protocol_fee.to_ceil_protocol();
 
     max(protocol_fee, 1)
 }
+
+/// Part of the protocol liquidation fee routed to the insurance vault, the rest stays with the
+/// fee vault
+pub fn calculate_insurance_fee(protocol_fee: u64, insurance_fee_bps: u64) -> u64 {
+    let insurance_fee = Fraction::from(protocol_fee) * Fraction::from_bps(insurance_fee_bps);
+    min(insurance_fee.to_floor(), protocol_fee)
+}
@@ -125,20 +125,22 @@ pub mod utils {
         Ok(())
     }
 
-    fn check_last_interval_elapsed(
+    fn roll_over_elapsed_intervals(
         caps: &mut WithdrawalCaps,
         curr_timestamp: u64,
-    ) -> Result<bool, LendingError> {
+    ) -> Result<(), LendingError> {
         if caps.last_interval_start_timestamp > curr_timestamp {
             return Err(LendingError::LastTimestampGreaterThanCurrent);
         }
-        Ok(caps.config_interval_length_seconds
-            <= curr_timestamp.saturating_sub(caps.last_interval_start_timestamp))
-    }
-
-    fn reset_current_interval_and_counter(caps: &mut WithdrawalCaps, curr_timestamp: u64) {
-        caps.current_total = 0;
-        caps.last_interval_start_timestamp = curr_timestamp;
+        let elapsed_intervals = (curr_timestamp - caps.last_interval_start_timestamp)
+            / caps.config_interval_length_seconds;
+        if elapsed_intervals > 0 {
+            // Usage from any elapsed interval is stale, however many of them went by
+            caps.current_total = 0;
+            caps.last_interval_start_timestamp +=
+                elapsed_intervals * caps.config_interval_length_seconds;
+        }
+        Ok(())
     }
 
     pub fn add_to_withdrawal_accum(
@@ -174,9 +176,7 @@ pub mod utils {
         action: WithdrawalCapAction,
     ) -> Result<(), LendingError> {
         if caps.config_interval_length_seconds != 0 {
-            if check_last_interval_elapsed(caps, curr_timestamp)? {
-                reset_current_interval_and_counter(caps, curr_timestamp);
-            }
+            roll_over_elapsed_intervals(caps, curr_timestamp)?;
             if action == WithdrawalCapAction::Add {
                 check_capacity_allows_withdrawals(caps, requested_amount)?;
             }
@@ -1,5 +1,6 @@
 pub mod constants;
 pub mod errors;
+pub mod events;
 pub mod instructions;
 pub mod lending_market;
 pub mod state;
@@ -9,7 +10,7 @@ use anchor_lang::prelude::*;
 use constants::{VALUE_BYTE_ARRAY_LEN_RESERVE, VALUE_BYTE_MAX_ARRAY_LEN_MARKET_UPDATE};
 use instructions::*;
 pub use state::*;
-use utils::constraints::emergency_mode_disabled;
+use utils::constraints::{emergency_mode_disabled, emergency_mode_enabled};
 
 declare_id!("FtQFCy8pGnywDh1r2wZJWH8e5KHrkJvDzjTGv3LAAWmj");
 
@@ -38,6 +39,10 @@ pub mod cluster_lend {
         process_update_market_owner(ctx)
     }
 
+    pub fn update_market_risk_authority(ctx: Context<UpdateMarketRiskAuthorityCtx>) -> Result<()> {
+        process_update_market_risk_authority(ctx)
+    }
+
     pub fn redeem_fees(ctx: Context<RedeemFeesCtx>) -> Result<()> {
         process_redeem_fees(ctx)
     }
@@ -69,11 +74,93 @@ pub mod cluster_lend {
         process_update_reserve(ctx, mode, &value)
     }
 
-    #[access_control(emergency_mode_disabled(&ctx.accounts.lending_market))]
+    pub fn initialize_reserve_config_template(
+        ctx: Context<InitializeReserveConfigTemplateCtx>,
+        kind: u8,
+    ) -> Result<()> {
+        process_initialize_reserve_config_template(ctx, kind)
+    }
+
+    pub fn update_reserve_config_template(
+        ctx: Context<UpdateReserveConfigTemplateCtx>,
+        value: [u8; VALUE_BYTE_ARRAY_LEN_RESERVE],
+    ) -> Result<()> {
+        process_update_reserve_config_template(ctx, &value)
+    }
+
+    pub fn apply_reserve_config_template(
+        ctx: Context<ApplyReserveConfigTemplateCtx>,
+    ) -> Result<()> {
+        process_apply_reserve_config_template(ctx)
+    }
+
+    pub fn migrate_reserve(ctx: Context<MigrateReserveCtx>) -> Result<()> {
+        process_migrate_reserve(ctx)
+    }
+
+    pub fn set_reserve_vaults(ctx: Context<SetReserveVaultsCtx>) -> Result<()> {
+        process_set_reserve_vaults(ctx)
+    }
+
+    pub fn donate_reserve_liquidity(
+        ctx: Context<DonateReserveLiquidityCtx>,
+        liquidity_amount: u64,
+    ) -> Result<()> {
+        process_donate_reserve_liquidity(ctx, liquidity_amount)
+    }
+
+    pub fn initialize_insurance_vault(ctx: Context<InitializeInsuranceVaultCtx>) -> Result<()> {
+        process_initialize_insurance_vault(ctx)
+    }
+
+    pub fn cover_bad_debt_from_insurance(
+        ctx: Context<CoverBadDebtFromInsuranceCtx>,
+        liquidity_amount: u64,
+    ) -> Result<()> {
+        process_cover_bad_debt_from_insurance(ctx, liquidity_amount)
+    }
+
     pub fn refresh_reserve(ctx: Context<RefreshReserveCtx>) -> Result<()> {
         process_refresh_reserve(ctx)
     }
 
+    pub fn force_refresh_reserve(ctx: Context<RefreshReserveCtx>) -> Result<()> {
+        process_force_refresh_reserve(ctx)
+    }
+
+    pub fn refresh_reserves(ctx: Context<RefreshReservesCtx>) -> Result<()> {
+        process_refresh_reserves(ctx)
+    }
+
+    pub fn push_reserve_price(ctx: Context<PushReservePriceCtx>, price_sf: u128) -> Result<()> {
+        process_push_reserve_price(ctx, price_sf)
+    }
+
+    pub fn get_utilization(ctx: Context<GetUtilizationCtx>) -> Result<GetUtilizationResult> {
+        process_get_utilization(ctx)
+    }
+
+    pub fn get_reserve_apy(ctx: Context<GetReserveApyCtx>) -> Result<GetReserveApyResult> {
+        process_get_reserve_apy(ctx)
+    }
+
+    pub fn get_obligation_values(
+        ctx: Context<GetObligationValuesCtx>,
+    ) -> Result<GetObligationValuesResult> {
+        process_get_obligation_values(ctx)
+    }
+
+    pub fn simulate_liquidation(
+        ctx: Context<SimulateLiquidationCtx>,
+        liquidity_amount: u64,
+    ) -> Result<SimulateLiquidationResult> {
+        process_simulate_liquidation(ctx, liquidity_amount)
+    }
+
+    pub fn reconcile_reserve(ctx: Context<ReconcileReserveCtx>) -> Result<ReconcileReserveResult> {
+        process_reconcile_reserve(ctx)
+    }
+
     #[access_control(emergency_mode_disabled(&ctx.accounts.lending_market))]
     pub fn deposit_reserve_liquidity(
         ctx: Context<DepositReserveLiquidityCtx>,
@@ -90,6 +177,22 @@ pub mod cluster_lend {
         process_redeem_reserve_collateral(ctx, collateral_amount)
     }
 
+    #[access_control(emergency_mode_disabled(&ctx.accounts.lending_market))]
+    pub fn redeem_reserve_collateral_exact_liquidity(
+        ctx: Context<RedeemReserveCollateralCtx>,
+        liquidity_amount: u64,
+    ) -> Result<()> {
+        process_redeem_reserve_collateral_exact_liquidity(ctx, liquidity_amount)
+    }
+
+    #[access_control(emergency_mode_enabled(&ctx.accounts.lending_market))]
+    pub fn emergency_redeem_reserve_collateral(
+        ctx: Context<RedeemReserveCollateralCtx>,
+        collateral_amount: u64,
+    ) -> Result<()> {
+        process_emergency_redeem_reserve_collateral(ctx, collateral_amount)
+    }
+
     // Obligation instructions
     pub fn initialize_obligation(
         ctx: Context<InitializeObligationCtx>,
@@ -98,6 +201,21 @@ pub mod cluster_lend {
         process_initialize_obligation(ctx, args)
     }
 
+    pub fn allow_obligation_owner(ctx: Context<AllowObligationOwnerCtx>) -> Result<()> {
+        process_allow_obligation_owner(ctx)
+    }
+
+    pub fn revoke_obligation_owner(ctx: Context<RevokeObligationOwnerCtx>) -> Result<()> {
+        process_revoke_obligation_owner(ctx)
+    }
+
+    pub fn set_borrow_factor_override(
+        ctx: Context<SetBorrowFactorOverrideCtx>,
+        borrow_factor_override_pct: u64,
+    ) -> Result<()> {
+        process_set_borrow_factor_override(ctx, borrow_factor_override_pct)
+    }
+
     pub fn refresh_obligation(ctx: Context<RefreshObligationCtx>) -> Result<()> {
         process_refresh_obligation(ctx)
     }
@@ -126,37 +244,152 @@ pub mod cluster_lend {
         process_withdraw_obligation_collateral(ctx, collateral_amount)
     }
 
+    #[access_control(emergency_mode_disabled(&ctx.accounts.lending_market))]
+    pub fn withdraw_obligation_collateral_and_redeem(
+        ctx: Context<WithdrawObligationCollateralAndRedeemCtx>,
+        collateral_amount: u64,
+    ) -> Result<()> {
+        process_withdraw_obligation_collateral_and_redeem(ctx, collateral_amount)
+    }
+
+    #[access_control(emergency_mode_disabled(&ctx.accounts.lending_market))]
+    pub fn transfer_obligation_collateral(
+        ctx: Context<TransferObligationCollateralCtx>,
+        collateral_amount: u64,
+    ) -> Result<()> {
+        process_transfer_obligation_collateral(ctx, collateral_amount)
+    }
+
+    #[access_control(emergency_mode_disabled(&ctx.accounts.lending_market))]
+    pub fn claim_rewards(ctx: Context<ClaimRewardsCtx>) -> Result<()> {
+        process_claim_rewards(ctx)
+    }
+
     #[access_control(emergency_mode_disabled(&ctx.accounts.lending_market))]
     pub fn borrow_obligation_liquidity(
         ctx: Context<BorrowObligationLiquidityCtx>,
         liquidity_amount: u64,
-    ) -> Result<()> {
+    ) -> Result<BorrowObligationLiquidityResult> {
         process_borrow_obligation_liquidity(ctx, liquidity_amount)
     }
 
-    #[access_control(emergency_mode_disabled(&ctx.accounts.lending_market))]
     pub fn repay_obligation_liquidity(
         ctx: Context<RepayObligationLiquidityCtx>,
         liquidity_amount: u64,
-    ) -> Result<()> {
+    ) -> Result<RepayObligationLiquidityResult> {
         process_repay_obligation_liquidity(ctx, liquidity_amount)
     }
 
     #[access_control(emergency_mode_disabled(&ctx.accounts.lending_market))]
+    pub fn repay_obligation_liquidity_with_collateral(
+        ctx: Context<RepayObligationLiquidityWithCollateralCtx>,
+        collateral_amount: u64,
+    ) -> Result<()> {
+        process_repay_obligation_liquidity_with_collateral(ctx, collateral_amount)
+    }
+
     pub fn liquidate_obligation(
         ctx: Context<LiquidateObligationCtx>,
         liquidity_amount: u64,
         min_acceptable_received_collateral_amount: u64,
+        min_acceptable_received_liquidity_amount: u64,
         max_allowed_ltv_override_percent: u64,
     ) -> Result<()> {
         process_liquidate_obligation(
             ctx,
             liquidity_amount,
             min_acceptable_received_collateral_amount,
+            min_acceptable_received_liquidity_amount,
             max_allowed_ltv_override_percent,
         )
     }
 
+    #[access_control(emergency_mode_disabled(&ctx.accounts.lending_market))]
+    pub fn deleverage_obligation_to_target(
+        ctx: Context<LiquidateObligationCtx>,
+        liquidity_amount: u64,
+        min_acceptable_received_collateral_amount: u64,
+    ) -> Result<()> {
+        process_deleverage_obligation_to_target(
+            ctx,
+            liquidity_amount,
+            min_acceptable_received_collateral_amount,
+        )
+    }
+
+    pub fn liquidate_obligations_batch(
+        ctx: Context<LiquidateObligationsBatchCtx>,
+        liquidity_amount: u64,
+        min_acceptable_received_collateral_amount: u64,
+        min_acceptable_received_liquidity_amount: u64,
+    ) -> Result<()> {
+        process_liquidate_obligations_batch(
+            ctx,
+            liquidity_amount,
+            min_acceptable_received_collateral_amount,
+            min_acceptable_received_liquidity_amount,
+        )
+    }
+
+    pub fn refund_liquidation_bond(ctx: Context<RefundLiquidationBondCtx>) -> Result<()> {
+        process_refund_liquidation_bond(ctx)
+    }
+
+    pub fn contest_liquidation_bond(ctx: Context<ContestLiquidationBondCtx>) -> Result<()> {
+        process_contest_liquidation_bond(ctx)
+    }
+
+    #[access_control(emergency_mode_disabled(&ctx.accounts.lending_market))]
+    pub fn request_elevation_group(
+        ctx: Context<RequestElevationGroupCtx>,
+        elevation_group: u8,
+    ) -> Result<()> {
+        process_request_elevation_group(ctx, elevation_group)
+    }
+
+    #[access_control(emergency_mode_disabled(&ctx.accounts.lending_market))]
+    pub fn set_aggressive_mode(
+        ctx: Context<SetAggressiveModeCtx>,
+        ltv_boost_pct: u8,
+    ) -> Result<()> {
+        process_set_aggressive_mode(ctx, ltv_boost_pct)
+    }
+
+    #[access_control(emergency_mode_disabled(&ctx.accounts.lending_market))]
+    pub fn reorder_obligation_deposits(
+        ctx: Context<ReorderObligationDepositsCtx>,
+        order: [u8; 8],
+    ) -> Result<()> {
+        process_reorder_obligation_deposits(ctx, order)
+    }
+
+    #[access_control(emergency_mode_disabled(&ctx.accounts.lending_market))]
+    pub fn set_autodeleverage_target(
+        ctx: Context<SetAutodeleverageTargetCtx>,
+        target_ltv_pct: u8,
+        bonus_bps: u16,
+    ) -> Result<()> {
+        process_set_autodeleverage_target(ctx, target_ltv_pct, bonus_bps)
+    }
+
+    #[access_control(emergency_mode_disabled(&ctx.accounts.lending_market))]
+    pub fn set_user_ltv_cap(ctx: Context<SetUserLtvCapCtx>, user_ltv_cap_pct: u8) -> Result<()> {
+        process_set_user_ltv_cap(ctx, user_ltv_cap_pct)
+    }
+
+    #[access_control(emergency_mode_disabled(&ctx.accounts.lending_market))]
+    pub fn set_obligation_reserve_allowlist(
+        ctx: Context<SetObligationReserveAllowlistCtx>,
+        allowed_deposit_reserves: [Pubkey; 4],
+        allowed_borrow_reserves: [Pubkey; 2],
+    ) -> Result<()> {
+        process_set_obligation_reserve_allowlist(
+            ctx,
+            allowed_deposit_reserves,
+            allowed_borrow_reserves,
+        )
+    }
+
     // Flash Loan
     #[access_control(emergency_mode_disabled(&ctx.accounts.lending_market))]
     pub fn flash_repay_reserve_liquidity(
@@ -15,20 +15,28 @@ pub struct PriceStatusFlags(pub u8);
 #[rustfmt::skip]
 bitflags! {
     impl PriceStatusFlags: u8 {
-        const PRICE_LOADED =        0b_0000_0001;
-        const PRICE_AGE_CHECKED =   0b_0000_0010;
-        const TWAP_CHECKED =        0b_0000_0100;
-        const TWAP_AGE_CHECKED =    0b_0000_1000;
+        const PRICE_LOADED =                    0b_0000_0001;
+        const PRICE_AGE_CHECKED =               0b_0000_0010;
+        const TWAP_CHECKED =                    0b_0000_0100;
+        const TWAP_AGE_CHECKED =                0b_0000_1000;
+        const LIQUIDATION_PRICE_AGE_CHECKED =   0b_0001_0000;
+        const FALLBACK_DIVERGENCE_CHECKED =     0b_0010_0000;
+        const FALLBACK_ORACLE_USED =            0b_0100_0000;
+        const PRICE_CHANGE_REJECTED =           0b_1000_0000;
     }
 }
 
 impl PriceStatusFlags {
-    pub const ALL_CHECKS: PriceStatusFlags = PriceStatusFlags::all();
+    /// Every check flag, `FALLBACK_ORACLE_USED` only reports where the price came from and
+    /// `PRICE_CHANGE_REJECTED` that the last oracle price was dropped for the saved one
+    pub const ALL_CHECKS: PriceStatusFlags = PriceStatusFlags::all()
+        .difference(PriceStatusFlags::FALLBACK_ORACLE_USED)
+        .difference(PriceStatusFlags::PRICE_CHANGE_REJECTED);
 
     pub const NONE: PriceStatusFlags = PriceStatusFlags::empty();
 
     pub const LIQUIDATION_CHECKS: PriceStatusFlags =
-        PriceStatusFlags::PRICE_LOADED.union(PriceStatusFlags::PRICE_AGE_CHECKED);
+        PriceStatusFlags::PRICE_LOADED.union(PriceStatusFlags::LIQUIDATION_PRICE_AGE_CHECKED);
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Debug)]
@@ -1,9 +1,15 @@
 use anchor_lang::prelude::*;
+use bitflags::bitflags;
 use derivative::Derivative;
 use num_enum::TryFromPrimitive;
 use strum::EnumString;
 
-use crate::constants::*;
+use crate::{
+    constants::*,
+    errors::LendingError,
+    utils::{Fraction, FractionExtra},
+    xmsg,
+};
 
 // static_assertions::const_assert_eq!(LENDING_MARKET_SIZE, std::mem::size_of::<LendingMarket>());
 static_assertions::const_assert_eq!(0, std::mem::size_of::<LendingMarket>() % 8);
@@ -34,13 +40,73 @@ pub struct LendingMarket {
     pub global_unhealthy_borrow_value: u64,
     pub global_allowed_borrow_value: u64,
 
-    #[derivative(Debug = "ignore")]
-    pub padding: u64,
+    /// When set, reserve liquidation bonuses are what the liquidator keeps after the
+    /// protocol liquidation fee, and are grossed up internally to cover that fee.
+    /// Otherwise the bonuses are gross and the protocol fee is taken out of them.
+    pub liquidation_bonus_net_of_protocol_fee: u8,
+    /// When set, liquidators escrow the repay reserve's liquidation bond in a
+    /// `LiquidationBond` account, refundable once the challenge window has elapsed
+    pub liquidation_bond_required: u8,
+    /// Share of borrow origination fees paid to the host fee receiver of a borrow, when given
+    pub host_fee_bps: u16,
+    /// When set, only owners with an `ObligationAllowlist` entry can open obligations
+    pub permissioned: u8,
+    /// Max LTV increase an obligation can opt into with aggressive mode, 0 disables it
+    pub aggressive_mode_max_ltv_boost_pct: u8,
+    /// Gap kept between the boosted LTV and the liquidation threshold in aggressive mode
+    pub aggressive_mode_liquidation_buffer_pct: u8,
+    /// `CpiActionFlags` of the obligation actions that can't be invoked from another program
+    pub cpi_disabled_flags: u8,
 
     pub min_net_value_in_obligation_sf: u128,
 
+    pub elevation_groups: [ElevationGroup; MAX_NUM_ELEVATION_GROUPS as usize],
+
+    pub liquidation_bond_challenge_window_secs: u64,
+    /// Minimum time between two aggressive mode changes of an obligation
+    pub aggressive_mode_cooldown_secs: u64,
+    /// Max total deposit and borrow market value of a single obligation, 0 disables it
+    pub max_obligation_value: u64,
+    /// Slots an obligation must stay unhealthy before it can be liquidated, 0 disables it
+    pub liquidation_grace_slots: u64,
+    /// Cap on the deposit market value of each reserve, checked against the reserve
+    /// `market_deposit_value` so deposits don't write the market, 0 disables it
+    pub global_allowed_deposit_value: u64,
+    /// When set, new deposits are rejected on every reserve, repays, withdrawals and liquidations
+    /// are unaffected
+    pub deposits_disabled: u8,
+    /// Max distinct reserves across an obligation's deposits and borrows, 0 disables it
+    pub max_obligation_reserves: u8,
+
     #[derivative(Debug = "ignore")]
-    pub reserved: [u64; 32],
+    pub padding_1: [u8; 6],
+    /// Slots after a reserve turns obsolete during which obligations holding it can still
+    /// withdraw from active reserves to unwind
+    pub deprecation_grace_slots: u64,
+    /// Token account of the market authority liquidity mining rewards are claimed from
+    pub reward_vault: Pubkey,
+    /// Lower privilege key that can pause reserves, tighten their limits and enable the
+    /// emergency mode, but never loosen the config
+    pub risk_authority: Pubkey,
+    /// Cap on the borrow market value of each isolated debt reserve, on top of its borrow limit,
+    /// checked against the reserve `market_isolated_debt_value` so borrows don't write the
+    /// market, 0 disables it
+    pub isolated_debt_borrow_value_cap: u64,
+    #[derivative(Debug = "ignore")]
+    pub reserved: [u64; 8],
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+#[repr(transparent)]
+pub struct CpiActionFlags(pub u8);
+
+#[rustfmt::skip]
+bitflags! {
+    impl CpiActionFlags: u8 {
+        const BORROW =      0b_0000_0001;
+        const WITHDRAW =    0b_0000_0010;
+        const LIQUIDATE =   0b_0000_0100;
+    }
 }
 
 impl Default for LendingMarket {
@@ -62,8 +128,27 @@ impl Default for LendingMarket {
             price_refresh_trigger_to_max_age_pct: 0,
             borrow_disabled: 0,
             min_net_value_in_obligation_sf: MIN_NET_VALUE_IN_OBLIGATION.to_bits(),
-            padding: 0,
-            reserved: [0; 32],
+            liquidation_bonus_net_of_protocol_fee: 0,
+            liquidation_bond_required: 0,
+            host_fee_bps: 0,
+            permissioned: 0,
+            aggressive_mode_max_ltv_boost_pct: 0,
+            aggressive_mode_liquidation_buffer_pct: 0,
+            cpi_disabled_flags: 0,
+            elevation_groups: [ElevationGroup::default(); MAX_NUM_ELEVATION_GROUPS as usize],
+            liquidation_bond_challenge_window_secs: 0,
+            aggressive_mode_cooldown_secs: 0,
+            max_obligation_value: 0,
+            liquidation_grace_slots: 0,
+            global_allowed_deposit_value: 0,
+            deposits_disabled: 0,
+            max_obligation_reserves: MAX_OBLIGATION_RESERVES as u8,
+            padding_1: [0; 6],
+            deprecation_grace_slots: 0,
+            reward_vault: Pubkey::default(),
+            risk_authority: Pubkey::default(),
+            isolated_debt_borrow_value_cap: 0,
+            reserved: [0; 8],
         }
     }
 }
@@ -80,6 +165,95 @@ impl LendingMarket {
     pub fn is_borrowing_disabled(&self) -> bool {
         self.borrow_disabled != false as u8
     }
+
+    pub fn is_deposits_disabled(&self) -> bool {
+        self.deposits_disabled != false as u8
+    }
+
+    pub fn is_liquidation_bonus_net_of_protocol_fee(&self) -> bool {
+        self.liquidation_bonus_net_of_protocol_fee != false as u8
+    }
+
+    pub fn is_liquidation_bond_required(&self) -> bool {
+        self.liquidation_bond_required != false as u8
+    }
+
+    pub fn is_permissioned(&self) -> bool {
+        self.permissioned != false as u8
+    }
+
+    pub fn is_config_authority(&self, signer: &Pubkey) -> bool {
+        self.owner == *signer || self.risk_authority == *signer
+    }
+
+    pub fn is_cpi_allowed(&self, action: CpiActionFlags) -> bool {
+        !CpiActionFlags::from_bits_truncate(self.cpi_disabled_flags).intersects(action)
+    }
+
+    pub fn calculate_borrow_fee_split(&self, borrow_fee: u64, has_host: bool) -> (u64, u64) {
+        let host_fee = if has_host && self.host_fee_bps > 0 {
+            (Fraction::from(borrow_fee) * Fraction::from_bps(self.host_fee_bps)).to_floor_protocol()
+        } else {
+            0
+        };
+
+        let protocol_fee = borrow_fee - host_fee;
+
+        (protocol_fee, host_fee)
+    }
+
+    pub fn get_elevation_group(&self, id: u8) -> Result<Option<&ElevationGroup>> {
+        if id == ELEVATION_GROUP_NONE {
+            return Ok(None);
+        }
+
+        let elevation_group = self
+            .elevation_groups
+            .get(id as usize - 1)
+            .filter(|group| group.id == id)
+            .ok_or_else(|| {
+                xmsg!("Elevation group {} is not configured", id);
+                error!(LendingError::InvalidElevationGroup)
+            })?;
+
+        Ok(Some(elevation_group))
+    }
+
+    pub fn set_elevation_group(&mut self, elevation_group: ElevationGroup) -> Result<()> {
+        if elevation_group.id == ELEVATION_GROUP_NONE
+            || elevation_group.id > MAX_NUM_ELEVATION_GROUPS
+        {
+            xmsg!(
+                "Elevation group id must be in range [1, {}]",
+                MAX_NUM_ELEVATION_GROUPS
+            );
+            return err!(LendingError::InvalidElevationGroup);
+        }
+        if elevation_group.ltv_pct >= 100 {
+            xmsg!("Elevation group LTV must be in range [0, 100)");
+            return err!(LendingError::InvalidElevationGroup);
+        }
+        if elevation_group.liquidation_threshold_pct < elevation_group.ltv_pct
+            || elevation_group.liquidation_threshold_pct > 100
+        {
+            xmsg!("Elevation group liquidation threshold must be in range [LTV, 100]");
+            return err!(LendingError::InvalidElevationGroup);
+        }
+
+        self.elevation_groups[elevation_group.id as usize - 1] = elevation_group;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+#[zero_copy]
+#[repr(C)]
+pub struct ElevationGroup {
+    pub id: u8,
+    pub ltv_pct: u8,
+    pub liquidation_threshold_pct: u8,
+    pub padding: [u8; 5],
 }
 
 pub struct InitLendingMarketParams {
@@ -106,10 +280,29 @@ pub enum UpdateLendingMarketMode {
     UpdateLiquidationMaxValue = 3,
     UpdateGlobalUnhealthyBorrow = 4,
     UpdateGlobalAllowedBorrow = 5,
+    UpdateReferralFeeBps = 6,
     UpdateMinFullLiquidationThreshold = 7,
     UpdateInsolvencyRiskLtv = 8,
     UpdatePriceRefreshTriggerToMaxAgePct = 12,
     UpdateAutodeleverageEnabled = 13,
     UpdateBorrowingDisabled = 14,
     UpdateMinNetValueObligationPostAction = 15,
+    UpdateElevationGroup = 16,
+    UpdateLiquidationBonusNetOfProtocolFee = 17,
+    UpdateLiquidationBondRequired = 18,
+    UpdateLiquidationBondChallengeWindowSecs = 19,
+    UpdateHostFeeBps = 20,
+    UpdatePermissioned = 21,
+    UpdateAggressiveModeMaxLtvBoostPct = 22,
+    UpdateAggressiveModeLiquidationBufferPct = 23,
+    UpdateAggressiveModeCooldownSecs = 24,
+    UpdateMaxObligationValue = 25,
+    UpdateLiquidationGraceSlots = 26,
+    UpdateGlobalAllowedDeposit = 27,
+    UpdateCpiDisabledFlags = 28,
+    UpdateDepositsDisabled = 29,
+    UpdateDeprecationGraceSlots = 30,
+    UpdateRewardVault = 31,
+    UpdateMaxObligationReserves = 32,
+    UpdateIsolatedDebtBorrowValueCap = 33,
 }
@@ -0,0 +1,82 @@
+use anchor_lang::prelude::*;
+
+use crate::{errors::LendingError, xmsg};
+
+static_assertions::const_assert_eq!(0, std::mem::size_of::<LiquidationBond>() % 8);
+#[derive(Debug, Default, PartialEq, Eq)]
+#[account(zero_copy)]
+#[repr(C)]
+pub struct LiquidationBond {
+    pub lending_market: Pubkey,
+    pub obligation: Pubkey,
+    pub liquidator: Pubkey,
+
+    /// Lamports escrowed on top of the rent of this account
+    pub amount: u64,
+    /// Timestamp of the latest liquidation, the challenge window starts from it
+    pub posted_at: i64,
+
+    pub bump: u64,
+
+    pub reserved: [u64; 8],
+}
+
+pub struct InitLiquidationBondParams {
+    pub lending_market: Pubkey,
+    pub obligation: Pubkey,
+    pub liquidator: Pubkey,
+    pub bump: u8,
+}
+
+impl LiquidationBond {
+    pub fn init(&mut self, params: InitLiquidationBondParams) {
+        *self = Self::default();
+        self.lending_market = params.lending_market;
+        self.obligation = params.obligation;
+        self.liquidator = params.liquidator;
+        self.bump = params.bump as u64;
+    }
+
+    pub fn post(&mut self, amount: u64, timestamp: i64) -> Result<()> {
+        self.amount = self
+            .amount
+            .checked_add(amount)
+            .ok_or_else(|| error!(LendingError::MathOverflow))?;
+        self.posted_at = timestamp;
+
+        Ok(())
+    }
+
+    pub fn challenge_window_end(&self, challenge_window_secs: u64) -> i64 {
+        self.posted_at
+            .saturating_add(i64::try_from(challenge_window_secs).unwrap_or(i64::MAX))
+    }
+
+    pub fn check_refundable(&self, timestamp: i64, challenge_window_secs: u64) -> Result<()> {
+        let window_end = self.challenge_window_end(challenge_window_secs);
+        if timestamp < window_end {
+            xmsg!(
+                "Liquidation bond can be refunded from {}, current timestamp {}",
+                window_end,
+                timestamp
+            );
+            return err!(LendingError::LiquidationBondChallengeWindowActive);
+        }
+
+        Ok(())
+    }
+
+    pub fn check_contestable(&self, timestamp: i64, challenge_window_secs: u64) -> Result<()> {
+        let window_end = self.challenge_window_end(challenge_window_secs);
+        if timestamp >= window_end {
+            xmsg!(
+                "Liquidation bond challenge window ended at {}, current timestamp {}",
+                window_end,
+                timestamp
+            );
+            return err!(LendingError::LiquidationBondChallengeWindowElapsed);
+        }
+
+        Ok(())
+    }
+}
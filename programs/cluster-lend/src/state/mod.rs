@@ -1,15 +1,21 @@
 pub mod last_update;
 pub mod lending_market;
+pub mod liquidation_bond;
 pub mod nested_accounts;
 pub mod obligation;
+pub mod obligation_allowlist;
 pub mod reserve;
+pub mod reserve_config_template;
 pub mod token_info;
 pub mod types;
 
 pub use last_update::*;
 pub use lending_market::*;
+pub use liquidation_bond::*;
 pub use nested_accounts::*;
 pub use obligation::*;
+pub use obligation_allowlist::*;
 pub use reserve::*;
+pub use reserve_config_template::*;
 pub use token_info::*;
 pub use types::*;
@@ -1,5 +1,5 @@
 use std::{
-    cmp::Ordering,
+    cmp::{min, Ordering},
     fmt::{self, Display, Formatter},
 };
 
@@ -7,7 +7,7 @@ use anchor_lang::{account, err, prelude::*, solana_program::clock::Slot, Result}
 use anchor_spl::token::Mint;
 use derivative::Derivative;
 
-use super::{AssetTier, BigFractionBytes, LastUpdate};
+use super::{AssetTier, BigFractionBytes, DebtTranche, LastUpdate, ObligationHealth};
 use crate::{
     constants::OBLIGATION_SIZE, errors::{LendingError, LendingResult}, utils::{BigFraction, Fraction, FractionExtra, U256}, xmsg
 };
@@ -41,9 +41,38 @@ pub struct Obligation {
     pub borrows_asset_tiers: [u8; 5],
     pub num_of_obsolete_reserves: u8,
     pub has_debt: u8,
-    pub padding_2: u8,
-
-    pub reserved: [u64; 32],
+    pub elevation_group: u8,
+
+    /// LTV increase the owner opted into, traded for a liquidation threshold closer to the LTV
+    pub aggressive_ltv_boost_pct: u8,
+    /// LTV keepers may deleverage the obligation down to once it is crossed, 0 when disabled
+    pub autodeleverage_target_ltv_pct: u8,
+    /// Liquidation bonus the owner grants keepers deleveraging to the target
+    pub autodeleverage_bonus_bps: u16,
+    /// LTV the owner caps their own borrows and withdrawals at, below the protocol's, 0 when unset
+    pub user_ltv_cap_pct: u8,
+    pub padding_2: [u8; 3],
+    /// Timestamp of the latest aggressive mode change, for the market cooldown
+    pub aggressive_mode_updated_at: i64,
+
+    /// Reserves the obligation may deposit into, no restriction when all are unset
+    pub allowed_deposit_reserves: [Pubkey; 4],
+    /// Reserves the obligation may borrow from, no restriction when all are unset
+    pub allowed_borrow_reserves: [Pubkey; 2],
+
+    /// Borrow factor set by the market owner in place of the reserves', 0 when unset
+    pub borrow_factor_override_pct: u64,
+    /// Slot the obligation was first refreshed as unhealthy, 0 while healthy
+    pub unhealthy_since_slot: u64,
+    /// Rewards already accounted for, the deposits valued at their reserves' reward per
+    /// collateral token minus this debt are left to claim. Withdrawing at a higher reward
+    /// index than the deposit was made at can take it below zero
+    pub reward_debt_sf: i128,
+    /// Set for the deposits counted in their reserve's rewards, deposits made before rewards
+    /// were tracked only start earning once they are deposited into or withdrawn from again
+    pub deposits_reward_tracked: [u8; 8],
+
+    pub reserved: [u64; 1],
 }
 
 impl Default for Obligation {
@@ -67,8 +96,20 @@ impl Default for Obligation {
             has_debt: 0,
             padding_0: 0,
             padding_1: 0,
-            padding_2: 0,
-            reserved: [0; 32],
+            elevation_group: 0,
+            aggressive_ltv_boost_pct: 0,
+            autodeleverage_target_ltv_pct: 0,
+            autodeleverage_bonus_bps: 0,
+            user_ltv_cap_pct: 0,
+            padding_2: [0; 3],
+            aggressive_mode_updated_at: 0,
+            allowed_deposit_reserves: [Pubkey::default(); 4],
+            allowed_borrow_reserves: [Pubkey::default(); 2],
+            borrow_factor_override_pct: 0,
+            unhealthy_since_slot: 0,
+            reward_debt_sf: 0,
+            deposits_reward_tracked: [0; 8],
+            reserved: [0; 1],
         }
     }
 }
@@ -84,11 +125,7 @@ impl Display for Obligation {
             if self.deposited_value_sf > 0 {self.loan_to_value().to_percent::<u16>().unwrap()} else { 0 },
         )?;
 
-        for collateral in self
-            .deposits
-            .iter()
-            .filter(|c| c.deposit_reserve != Pubkey::default())
-        {
+        for collateral in self.active_deposits() {
             write!(
                 f,
                 "\n  Collateral reserve: {}, value: ${}, lamports: {}",
@@ -98,11 +135,7 @@ impl Display for Obligation {
             )?;
         }
 
-        for liquidity in self
-            .borrows
-            .iter()
-            .filter(|l| l.borrow_reserve != Pubkey::default())
-        {
+        for liquidity in self.active_borrows() {
             write!(
                 f,
                 "\n  Borrowed reserve  : {}, value: ${}, lamports: {}",
@@ -131,6 +164,14 @@ impl Obligation {
         self.borrows_asset_tiers = [u8::MAX; 5];
     }
 
+    pub fn is_deposit_reserve_allowed(&self, reserve: &Pubkey) -> bool {
+        is_reserve_allowed(&self.allowed_deposit_reserves, reserve)
+    }
+
+    pub fn is_borrow_reserve_allowed(&self, reserve: &Pubkey) -> bool {
+        is_reserve_allowed(&self.allowed_borrow_reserves, reserve)
+    }
+
     pub fn loan_to_value(&self) -> Fraction {
         Fraction::from_bits(self.borrow_factor_adjusted_debt_value_sf)
             / Fraction::from_bits(self.deposited_value_sf)
@@ -141,6 +182,19 @@ impl Obligation {
             / Fraction::from_bits(self.deposited_value_sf)
     }
 
+    /// Health as of the last refresh, the obligation must be refreshed for it to be current
+    pub fn classify_health(&self) -> ObligationHealth {
+        if self.borrowed_assets_market_value_sf > self.deposited_value_sf {
+            ObligationHealth::BadDebt
+        } else if self.borrow_factor_adjusted_debt_value_sf > 0
+            && self.borrow_factor_adjusted_debt_value_sf >= self.unhealthy_borrow_value_sf
+        {
+            ObligationHealth::Liquidatable
+        } else {
+            ObligationHealth::Healthy
+        }
+    }
+
     pub fn repay(&mut self, settle_amount: Fraction, liquidity_index: usize) -> Result<()> {
         let liquidity = &mut self.borrows[liquidity_index];
         if settle_amount == Fraction::from_bits(liquidity.borrowed_amount_sf) {
@@ -157,36 +211,159 @@ impl Obligation {
         if withdraw_amount == collateral.deposited_amount {
             self.deposits[collateral_index] = ObligationCollateral::default();
             self.deposits_asset_tiers[collateral_index] = u8::MAX;
+            self.deposits_reward_tracked[collateral_index] = false as u8;
         } else {
             collateral.withdraw(withdraw_amount)?;
         }
         Ok(())
     }
 
+    pub fn add_reward_debt(
+        &mut self,
+        collateral_amount: u64,
+        reward_per_collateral_token: Fraction,
+    ) -> Result<()> {
+        let reward_debt_sf = reward_value_sf(collateral_amount, reward_per_collateral_token)?;
+        self.reward_debt_sf = self
+            .reward_debt_sf
+            .checked_add(reward_debt_sf)
+            .ok_or(LendingError::MathOverflow)?;
+        Ok(())
+    }
+
+    pub fn remove_reward_debt(
+        &mut self,
+        collateral_amount: u64,
+        reward_per_collateral_token: Fraction,
+    ) -> Result<()> {
+        let reward_debt_sf = reward_value_sf(collateral_amount, reward_per_collateral_token)?;
+        self.reward_debt_sf = self
+            .reward_debt_sf
+            .checked_sub(reward_debt_sf)
+            .ok_or(LendingError::MathOverflow)?;
+        Ok(())
+    }
+
+    pub fn is_deposit_reward_tracked(&self, collateral_index: usize) -> bool {
+        self.deposits_reward_tracked[collateral_index] != false as u8
+    }
+
+    pub fn pending_rewards(&self, earned_rewards: Fraction) -> Result<Fraction> {
+        let earned_rewards_sf =
+            i128::try_from(earned_rewards.to_bits()).map_err(|_| LendingError::MathOverflow)?;
+        let pending_rewards_sf = earned_rewards_sf
+            .checked_sub(self.reward_debt_sf)
+            .ok_or(LendingError::MathOverflow)?;
+        Ok(Fraction::from_bits(pending_rewards_sf.max(0) as u128))
+    }
+
+    /// Shifts the non-empty deposits and borrows to the front of their arrays, keeping their
+    /// order so the reserves passed to a refresh stay the same, and their asset tiers and
+    /// reward tracking with them
+    pub fn compact_obligation(&mut self) {
+        let mut next = 0;
+        for index in 0..self.deposits.len() {
+            if self.deposits[index].is_active() {
+                self.deposits.swap(next, index);
+                self.deposits_asset_tiers.swap(next, index);
+                self.deposits_reward_tracked.swap(next, index);
+                next += 1;
+            }
+        }
+
+        let mut next = 0;
+        for index in 0..self.borrows.len() {
+            if self.borrows[index].is_active() {
+                self.borrows.swap(next, index);
+                self.borrows_asset_tiers.swap(next, index);
+                next += 1;
+            }
+        }
+    }
+
+    /// Moves the deposit in slot `order[i]` to slot `i`, with its asset tier and reward
+    /// tracking, `order` must be a permutation of the deposit slots
+    pub fn reorder_deposits(&mut self, order: [u8; 8]) -> LendingResult<()> {
+        let mut seen = [false; 8];
+        for index in order {
+            match seen.get_mut(usize::from(index)) {
+                Some(seen) if !*seen => *seen = true,
+                _ => {
+                    xmsg!(
+                        "Deposits order {:?} is not a permutation of the slots",
+                        order
+                    );
+                    return Err(LendingError::InvalidDepositsOrder);
+                }
+            }
+        }
+
+        let deposits = self.deposits;
+        let deposits_asset_tiers = self.deposits_asset_tiers;
+        let deposits_reward_tracked = self.deposits_reward_tracked;
+        self.deposits = order.map(|index| deposits[usize::from(index)]);
+        self.deposits_asset_tiers = order.map(|index| deposits_asset_tiers[usize::from(index)]);
+        self.deposits_reward_tracked =
+            order.map(|index| deposits_reward_tracked[usize::from(index)]);
+
+        Ok(())
+    }
+
     pub fn max_withdraw_value(&self, withdraw_collateral_ltv_pct: u8) -> LendingResult<Fraction> {
-        let allowed_borrow_value = Fraction::from_bits(self.allowed_borrow_value_sf);
+        let max_withdraw_value = self.max_withdraw_value_within(
+            Fraction::from_bits(self.allowed_borrow_value_sf),
+            withdraw_collateral_ltv_pct,
+        );
+
+        match self.user_allowed_borrow_value() {
+            // the owner's cap shrinks by its own share of the withdrawn value, whatever the
+            // collateral LTV
+            Some(user_allowed_borrow_value) => Ok(min(
+                max_withdraw_value,
+                self.max_withdraw_value_within(user_allowed_borrow_value, self.user_ltv_cap_pct),
+            )),
+            None => Ok(max_withdraw_value),
+        }
+    }
+
+    fn max_withdraw_value_within(
+        &self,
+        allowed_borrow_value: Fraction,
+        withdraw_collateral_ltv_pct: u8,
+    ) -> Fraction {
         let borrow_factor_adjusted_debt_value =
             Fraction::from_bits(self.borrow_factor_adjusted_debt_value_sf);
 
         if allowed_borrow_value <= borrow_factor_adjusted_debt_value {
-            return Ok(Fraction::ZERO);
+            return Fraction::ZERO;
         }
 
         if withdraw_collateral_ltv_pct == 0 {
-            return Ok(Fraction::from_bits(self.deposited_value_sf));
+            return Fraction::from_bits(self.deposited_value_sf);
         }
 
-        Ok(
-            allowed_borrow_value.saturating_sub(borrow_factor_adjusted_debt_value) * 100_u128
-                / u128::from(withdraw_collateral_ltv_pct),
-        )
+        allowed_borrow_value.saturating_sub(borrow_factor_adjusted_debt_value) * 100_u128
+            / u128::from(withdraw_collateral_ltv_pct)
     }
 
     pub fn remaining_borrow_value(&self) -> Fraction {
-        Fraction::from_bits(
-            self.allowed_borrow_value_sf
-                .saturating_sub(self.borrow_factor_adjusted_debt_value_sf),
-        )
+        let allowed_borrow_value = Fraction::from_bits(self.allowed_borrow_value_sf);
+        let allowed_borrow_value = match self.user_allowed_borrow_value() {
+            Some(user_allowed_borrow_value) => min(allowed_borrow_value, user_allowed_borrow_value),
+            None => allowed_borrow_value,
+        };
+
+        allowed_borrow_value.saturating_sub(Fraction::from_bits(
+            self.borrow_factor_adjusted_debt_value_sf,
+        ))
+    }
+
+    /// Borrow value the owner's LTV cap allows against the deposits, `None` when no cap is set
+    pub fn user_allowed_borrow_value(&self) -> Option<Fraction> {
+        (self.user_ltv_cap_pct > 0).then(|| {
+            Fraction::from_bits(self.deposited_value_sf)
+                * Fraction::from_percent(self.user_ltv_cap_pct)
+        })
     }
 
     pub fn find_collateral_in_deposits(
@@ -207,17 +384,22 @@ impl Obligation {
         &mut self,
         deposit_reserve: Pubkey,
         deposit_reserve_asset_tier: AssetTier,
+        max_obligation_reserves: u8,
     ) -> Result<(&mut ObligationCollateral, usize)> {
         if let Some(collateral_index) = self.find_collateral_index_in_deposits(deposit_reserve) {
-            Ok((&mut self.deposits[collateral_index], collateral_index))
-        } else if let Some((index, collateral)) = self
+            return Ok((&mut self.deposits[collateral_index], collateral_index));
+        }
+
+        self.check_reserves_limit(deposit_reserve, max_obligation_reserves)?;
+        if let Some((index, collateral)) = self
             .deposits
             .iter_mut()
             .enumerate()
-            .find(|c| c.1.deposit_reserve == Pubkey::default())
+            .find(|c| !c.1.is_active())
         {
             *collateral = ObligationCollateral::new(deposit_reserve);
             self.deposits_asset_tiers[index] = deposit_reserve_asset_tier.into();
+            self.deposits_reward_tracked[index] = true as u8;
 
             Ok((collateral, index))
         } else {
@@ -265,16 +447,22 @@ impl Obligation {
         borrow_reserve: Pubkey,
         cumulative_borrow_rate: BigFraction,
         borrow_reserve_asset_tier: AssetTier,
+        borrow_reserve_debt_tranche: DebtTranche,
+        max_obligation_reserves: u8,
     ) -> Result<(&mut ObligationLiquidity, usize)> {
         if let Some(liquidity_index) = self.find_liquidity_index_in_borrows(borrow_reserve) {
-            Ok((&mut self.borrows[liquidity_index], liquidity_index))
-        } else if let Some((index, liquidity)) = self
+            return Ok((&mut self.borrows[liquidity_index], liquidity_index));
+        }
+
+        self.check_reserves_limit(borrow_reserve, max_obligation_reserves)?;
+        if let Some((index, liquidity)) = self
             .borrows
             .iter_mut()
             .enumerate()
-            .find(|c| c.1.borrow_reserve == Pubkey::default())
+            .find(|c| !c.1.is_active())
         {
             *liquidity = ObligationLiquidity::new(borrow_reserve, cumulative_borrow_rate);
+            liquidity.debt_tranche = borrow_reserve_debt_tranche.into();
             self.borrows_asset_tiers[index] = borrow_reserve_asset_tier.into();
 
             Ok((liquidity, index))
@@ -290,30 +478,63 @@ impl Obligation {
             .position(|liquidity| liquidity.borrow_reserve == borrow_reserve)
     }
 
+    /// A reserve already deposited or borrowed doesn't count twice, 0 disables the limit
+    fn check_reserves_limit(&self, reserve: Pubkey, max_obligation_reserves: u8) -> Result<()> {
+        if max_obligation_reserves == 0
+            || self.find_collateral_index_in_deposits(reserve).is_some()
+            || self.find_liquidity_index_in_borrows(reserve).is_some()
+        {
+            return Ok(());
+        }
+
+        let mut reserves: Vec<Pubkey> = self
+            .active_deposits()
+            .map(|c| c.deposit_reserve)
+            .chain(self.active_borrows().map(|l| l.borrow_reserve))
+            .collect();
+        reserves.sort_unstable();
+        reserves.dedup();
+
+        if reserves.len() >= max_obligation_reserves as usize {
+            xmsg!(
+                "Obligation already uses the market maximum of {} reserves",
+                max_obligation_reserves
+            );
+            return err!(LendingError::ObligationReserveLimit);
+        }
+
+        Ok(())
+    }
+
+    /// Deposits in use, in slot order, skipping the empty slots a withdrawal can leave behind
+    pub fn active_deposits(&self) -> impl Iterator<Item = &ObligationCollateral> {
+        self.deposits.iter().filter(|c| c.is_active())
+    }
+
+    /// Borrows in use, in slot order, skipping the empty slots a repay can leave behind
+    pub fn active_borrows(&self) -> impl Iterator<Item = &ObligationLiquidity> {
+        self.borrows.iter().filter(|l| l.is_active())
+    }
+
+    pub fn has_senior_debt(&self) -> bool {
+        self.active_borrows()
+            .any(|l| l.borrowed_amount_sf > 0 && !l.is_subordinated())
+    }
+
     pub fn deposits_empty(&self) -> bool {
-        self.deposits
-            .iter()
-            .all(|c| c.deposit_reserve == Pubkey::default())
+        self.active_deposits().next().is_none()
     }
 
     pub fn borrows_empty(&self) -> bool {
-        self.borrows
-            .iter()
-            .all(|l| l.borrow_reserve == Pubkey::default())
+        self.active_borrows().next().is_none()
     }
 
     pub fn deposits_count(&self) -> usize {
-        self.deposits
-            .iter()
-            .filter(|c| c.deposit_reserve != Pubkey::default())
-            .count()
+        self.active_deposits().count()
     }
 
     pub fn borrows_count(&self) -> usize {
-        self.borrows
-            .iter()
-            .filter(|l| l.borrow_reserve != Pubkey::default())
-            .count()
+        self.active_borrows().count()
     }
 
     pub fn get_deposit_asset_tiers(&self) -> Vec<AssetTier> {
@@ -321,7 +542,7 @@ impl Obligation {
             .iter()
             .enumerate()
             .filter_map(|(index, deposit)| {
-                if deposit.deposit_reserve != Pubkey::default() && deposit.deposited_amount > 0 {
+                if deposit.is_active() && deposit.deposited_amount > 0 {
                     Some(AssetTier::try_from(self.deposits_asset_tiers[index]).unwrap())
                 } else {
                     None
@@ -335,7 +556,7 @@ impl Obligation {
             .iter()
             .enumerate()
             .filter_map(|(index, borrow)| {
-                if borrow.borrow_reserve != Pubkey::default() && borrow.borrowed_amount_sf > 0 {
+                if borrow.is_active() && borrow.borrowed_amount_sf > 0 {
                     Some(AssetTier::try_from(self.borrows_asset_tiers[index]).unwrap())
                 } else {
                     None
@@ -362,19 +583,31 @@ pub struct InitObligationParams {
     pub tag: u64,
 }
 
-#[derive(AnchorDeserialize, AnchorSerialize)]
+#[derive(AnchorDeserialize, AnchorSerialize, Default)]
 pub struct InitObligationArgs {
     pub tag: u8,
     pub id: u8,
 }
 
+fn reward_value_sf(collateral_amount: u64, reward_per_collateral_token: Fraction) -> Result<i128> {
+    let reward_value = reward_per_collateral_token
+        .checked_mul_int(u128::from(collateral_amount))
+        .ok_or(LendingError::MathOverflow)?;
+    i128::try_from(reward_value.to_bits()).map_err(|_| error!(LendingError::MathOverflow))
+}
+
+fn is_reserve_allowed(allowlist: &[Pubkey], reserve: &Pubkey) -> bool {
+    allowlist.iter().all(|r| *r == Pubkey::default()) || allowlist.contains(reserve)
+}
+
 #[derive(Debug, Default, PartialEq, Eq)]
 #[zero_copy]
 #[repr(C)]
 pub struct ObligationCollateral {
     pub deposit_reserve: Pubkey,
     pub deposited_amount: u64,
-    pub padding: u64,
+    /// Slot of the latest deposit into this entry, see `min_deposit_hold_slots`
+    pub last_deposit_slot: u64,
     pub market_value_sf: u128,
 }
 
@@ -384,10 +617,14 @@ impl ObligationCollateral {
             deposit_reserve,
             deposited_amount: 0,
             market_value_sf: 0,
-            padding: 0,
+            last_deposit_slot: 0,
         }
     }
 
+    pub fn is_active(&self) -> bool {
+        self.deposit_reserve != Pubkey::default()
+    }
+
     pub fn deposit(&mut self, collateral_amount: u64) -> Result<()> {
         self.deposited_amount = self
             .deposited_amount
@@ -414,7 +651,10 @@ pub struct ObligationLiquidity {
     pub borrowed_amount_sf: u128,
     pub market_value_sf: u128,
     pub borrow_factor_adjusted_market_value_sf: u128,
-    pub padding: [u64; 8],
+    /// Liquidation priority of this debt, senior debt is liquidated before subordinated debt
+    pub debt_tranche: u8,
+    pub padding_0: [u8; 7],
+    pub padding: [u64; 7],
 }
 
 impl ObligationLiquidity {
@@ -425,10 +665,20 @@ impl ObligationLiquidity {
             borrowed_amount_sf: 0,
             market_value_sf: 0,
             borrow_factor_adjusted_market_value_sf: 0,
-            padding: [0; 8],
+            debt_tranche: DebtTranche::Senior.into(),
+            padding_0: [0; 7],
+            padding: [0; 7],
         }
     }
 
+    pub fn is_active(&self) -> bool {
+        self.borrow_reserve != Pubkey::default()
+    }
+
+    pub fn is_subordinated(&self) -> bool {
+        self.debt_tranche == u8::from(DebtTranche::Subordinated)
+    }
+
     pub fn repay(&mut self, settle_amount: Fraction) {
         self.borrowed_amount_sf =
             (Fraction::from_bits(self.borrowed_amount_sf) - settle_amount).to_bits();
@@ -480,27 +730,31 @@ pub fn check_obligation_seeds(
             );
         }
         1 => {
-            let _mint1_check =
-                Mint::try_deserialize(&mut seed1_account.data.borrow().as_ref()).unwrap();
-            let _mint2_check =
-                Mint::try_deserialize(&mut seed2_account.data.borrow().as_ref()).unwrap();
+            check_seed_is_mint(seed1_account)?;
+            check_seed_is_mint(seed2_account)?;
         }
         2 => {
-            let _mint_check =
-                Mint::try_deserialize(&mut seed1_account.data.borrow().as_ref()).unwrap();
+            check_seed_is_mint(seed1_account)?;
             require!(
                 seed1_key == seed2_key,
                 LendingError::InvalidObligationSeedsValue
             )
         }
-        3 => {
-            let _mint1_check =
-                Mint::try_deserialize(&mut seed1_account.data.borrow().as_ref()).unwrap();
-            let _mint2_check =
-                Mint::try_deserialize(&mut seed2_account.data.borrow().as_ref()).unwrap();
+        _ => {
+            xmsg!("Obligation tag {} is not supported", tag);
+            return err!(LendingError::InvalidObligationSeedsValue);
         }
-        _ => {}
     }
 
     Ok(())
 }
+
+fn check_seed_is_mint(seed_account: &AccountInfo) -> Result<()> {
+    if *seed_account.owner != anchor_spl::token::ID
+        || Mint::try_deserialize(&mut seed_account.data.borrow().as_ref()).is_err()
+    {
+        xmsg!("Obligation seed {} is not a mint", seed_account.key());
+        return err!(LendingError::InvalidObligationSeedsValue);
+    }
+    Ok(())
+}
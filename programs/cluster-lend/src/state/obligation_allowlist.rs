@@ -0,0 +1,30 @@
+use anchor_lang::prelude::*;
+
+static_assertions::const_assert_eq!(0, std::mem::size_of::<ObligationAllowlist>() % 8);
+#[derive(Debug, Default, PartialEq, Eq)]
+#[account(zero_copy)]
+#[repr(C)]
+pub struct ObligationAllowlist {
+    pub lending_market: Pubkey,
+    /// Owner allowed to open obligations in the permissioned market
+    pub owner: Pubkey,
+
+    pub bump: u64,
+
+    pub reserved: [u64; 4],
+}
+
+pub struct InitObligationAllowlistParams {
+    pub lending_market: Pubkey,
+    pub owner: Pubkey,
+    pub bump: u8,
+}
+
+impl ObligationAllowlist {
+    pub fn init(&mut self, params: InitObligationAllowlistParams) {
+        *self = Self::default();
+        self.lending_market = params.lending_market;
+        self.owner = params.owner;
+        self.bump = params.bump as u64;
+    }
+}
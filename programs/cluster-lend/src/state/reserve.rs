@@ -1,5 +1,5 @@
 use std::{
-    cmp::{max, min},
+    cmp::{max, min, Ordering},
     ops::{Add, Div, Mul},
 };
 
@@ -16,10 +16,15 @@ use strum::EnumString;
 
 use super::{LastUpdate, TokenInfo};
 use crate::{
-    constants::{INITIAL_COLLATERAL_RATE, PROGRAM_VERSION, RESERVE_CONFIG_SIZE, SLOTS_PER_YEAR},
+    constants::{
+        INITIAL_COLLATERAL_RATE, MAX_COMPOUNDED_INTEREST_PER_ACCRUAL, PROGRAM_VERSION,
+        RESERVE_CONFIG_SIZE, SLOTS_PER_YEAR, UTILIZATION_SNAPSHOTS_LEN,
+        UTILIZATION_SNAPSHOT_INTERVAL_SLOTS,
+    },
     errors::{LendingError, LendingResult},
+    events::ProtocolFeeAccruedEvent,
     state::{CalculateBorrowResult, CalculateRepayResult},
-    utils::{borrow_rate_curve::BorrowRateCurve, BigFraction, Fraction, FractionExtra},
+    utils::{borrow_rate_curve::BorrowRateCurve, BigFraction, Fraction, FractionExtra, U256},
     xmsg,
 };
 
@@ -46,6 +51,66 @@ impl From<BigFractionBytes> for BigFraction {
     }
 }
 
+#[derive(Debug, Default, PartialEq, Eq)]
+#[zero_copy]
+#[repr(C)]
+pub struct RewardState {
+    /// Rewards emitted per collateral token deposited in obligations, only ever increases
+    pub reward_per_collateral_token_sf: u128,
+    /// Collateral of the obligation deposits tracked for rewards, the emission is split
+    /// between it
+    pub deposited_collateral: u64,
+    pub padding: u64,
+}
+
+impl RewardState {
+    pub fn reward_per_collateral_token(&self) -> Fraction {
+        Fraction::from_bits(self.reward_per_collateral_token_sf)
+    }
+
+    pub fn accrue(&mut self, emission_per_slot: u64, slots_elapsed: u64) -> LendingResult<()> {
+        if emission_per_slot == 0 || self.deposited_collateral == 0 {
+            return Ok(());
+        }
+
+        let reward_per_collateral_token = Fraction::from(emission_per_slot)
+            .checked_mul_int(u128::from(slots_elapsed))
+            .and_then(|emitted| emitted.checked_div_int(u128::from(self.deposited_collateral)))
+            .ok_or(LendingError::MathOverflow)?;
+        self.reward_per_collateral_token_sf = self
+            .reward_per_collateral_token()
+            .checked_add(reward_per_collateral_token)
+            .ok_or(LendingError::MathOverflow)?
+            .to_bits();
+
+        Ok(())
+    }
+
+    pub fn deposit(&mut self, collateral_amount: u64) -> LendingResult<()> {
+        self.deposited_collateral = self
+            .deposited_collateral
+            .checked_add(collateral_amount)
+            .ok_or(LendingError::MathOverflow)?;
+        Ok(())
+    }
+
+    pub fn withdraw(&mut self, collateral_amount: u64) -> LendingResult<()> {
+        self.deposited_collateral = self
+            .deposited_collateral
+            .checked_sub(collateral_amount)
+            .ok_or(LendingError::MathOverflow)?;
+        Ok(())
+    }
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Debug, Default, PartialEq, Eq)]
+#[zero_copy]
+#[repr(C)]
+pub struct UtilizationSnapshot {
+    pub slot: u64,
+    pub utilization_rate_bps: u64,
+}
+
 // static_assertions::const_assert_eq!(RESERVE_SIZE, std::mem::size_of::<Reserve>());
 static_assertions::const_assert_eq!(0, std::mem::size_of::<Reserve>() % 8);
 #[derive(PartialEq, Derivative)]
@@ -59,8 +124,9 @@ pub struct Reserve {
 
     pub lending_market: Pubkey,
 
-    #[derivative(Debug = "ignore")]
-    pub padding: u64,
+    /// Borrow market value as of the last borrow or repay, held to the market
+    /// `isolated_debt_borrow_value_cap`, 0 unless it is an isolated debt reserve
+    pub market_isolated_debt_value: u64,
 
     pub liquidity: ReserveLiquidity,
 
@@ -68,7 +134,20 @@ pub struct Reserve {
 
     pub config: ReserveConfig,
 
-    pub reserved: [u64; 32],
+    /// Ring buffer of utilization samples, taken at most once per snapshot interval on refresh
+    pub utilization_snapshots: [UtilizationSnapshot; UTILIZATION_SNAPSHOTS_LEN],
+    /// Position of the next sample to be written in `utilization_snapshots`
+    pub utilization_snapshot_index: u64,
+    /// Market value of the total supply as of the last deposit or redeem, held to the market
+    /// `global_allowed_deposit_value`
+    pub market_deposit_value: u64,
+    /// Slot at which the reserve was last made obsolete, 0 while it is not obsolete
+    pub obsolete_since_slot: u64,
+
+    /// Liquidity mining rewards earned by the collateral deposited in obligations
+    pub reward_state: RewardState,
+
+    pub reserved: [u64; 1],
 }
 
 impl Default for Reserve {
@@ -80,8 +159,13 @@ impl Default for Reserve {
             liquidity: ReserveLiquidity::default(),
             collateral: ReserveCollateral::default(),
             config: ReserveConfig::default(),
-            padding: 0,
-            reserved: [0; 32],
+            market_isolated_debt_value: 0,
+            utilization_snapshots: [UtilizationSnapshot::default(); UTILIZATION_SNAPSHOTS_LEN],
+            utilization_snapshot_index: 0,
+            market_deposit_value: 0,
+            obsolete_since_slot: 0,
+            reward_state: RewardState::default(),
+            reserved: [0; 1],
         }
     }
 }
@@ -100,9 +184,65 @@ impl Reserve {
     pub fn current_borrow_rate(&self) -> Result<Fraction> {
         let utilization_rate = self.liquidity.utilization_rate()?;
 
-        self.config
+        let borrow_rate = self
+            .config
             .borrow_rate_curve
-            .get_borrow_rate(utilization_rate)
+            .get_borrow_rate(utilization_rate)?;
+
+        let borrow_rate = if self.config.max_borrow_rate_bps == 0 {
+            borrow_rate
+        } else {
+            min(
+                borrow_rate,
+                Fraction::from_bps(self.config.max_borrow_rate_bps),
+            )
+        };
+        Ok(max(
+            borrow_rate,
+            Fraction::from_bps(self.config.min_borrow_rate_bps),
+        ))
+    }
+
+    /// Protocol take rate applied to accrued interest, raised above the configured one when
+    /// needed so the supply rate stays under the borrow rate by the minimum spread.
+    pub fn protocol_take_rate(&self) -> Result<Fraction> {
+        let protocol_take_rate = Fraction::from_percent(self.config.protocol_take_rate_pct);
+        let utilization_rate = self.liquidity.utilization_rate()?;
+        let max_supplier_share =
+            Fraction::ONE - Fraction::from_bps(self.config.min_supply_borrow_spread_bps);
+
+        // supply rate = borrow rate * utilization * (1 - take rate)
+        if utilization_rate * (Fraction::ONE - protocol_take_rate) <= max_supplier_share {
+            return Ok(protocol_take_rate);
+        }
+
+        let clamped_take_rate = Fraction::ONE - max_supplier_share / utilization_rate;
+        xmsg!(
+            "Protocol take rate raised from {} to {} to keep the minimum supply/borrow spread",
+            protocol_take_rate.to_display(),
+            clamped_take_rate.to_display()
+        );
+
+        Ok(clamped_take_rate)
+    }
+
+    pub fn current_supply_rate(&self) -> Result<Fraction> {
+        let borrow_rate = self.current_borrow_rate()?;
+        let utilization_rate = self.liquidity.utilization_rate()?;
+        let protocol_take_rate = self.protocol_take_rate()?;
+
+        Ok(borrow_rate * utilization_rate * (Fraction::ONE - protocol_take_rate))
+    }
+
+    /// Current borrow rate compounded every slot over a year
+    pub fn current_borrow_apy(&self) -> Result<Fraction> {
+        apy_from_rate(self.current_borrow_rate()?)
+    }
+
+    /// Current supply rate, net of utilization and protocol take rate, compounded every slot
+    /// over a year
+    pub fn current_supply_apy(&self) -> Result<Fraction> {
+        apy_from_rate(self.current_supply_rate()?)
     }
 
     pub fn borrow_factor_f(&self) -> Fraction {
@@ -116,7 +256,7 @@ impl Reserve {
     pub fn deposit_liquidity(&mut self, liquidity_amount: u64) -> Result<u64> {
         let collateral_amount = self
             .collateral_exchange_rate()?
-            .liquidity_to_collateral(liquidity_amount);
+            .liquidity_to_collateral(liquidity_amount, RoundingDirection::Down)?;
 
         self.liquidity.deposit(liquidity_amount)?;
         self.collateral.mint(collateral_amount)?;
@@ -124,11 +264,56 @@ impl Reserve {
         Ok(collateral_amount)
     }
 
+    /// Brings `available_amount` in line with the vault balance of a rebasing mint, a positive
+    /// rebase accrues to depositors through the exchange rate and a negative one is bad debt
+    pub fn reconcile_rebasing_vault_balance(&mut self, vault_balance: u64) -> Result<()> {
+        if !self.config.is_rebasing() {
+            return Ok(());
+        }
+
+        let available_amount = self.liquidity.available_amount;
+        match vault_balance.cmp(&available_amount) {
+            Ordering::Greater => {
+                xmsg!(
+                    "Crediting a rebase of {} to the depositors of reserve {}",
+                    vault_balance - available_amount,
+                    self.token_symbol()
+                );
+            }
+            Ordering::Less => {
+                let shortfall = available_amount - vault_balance;
+                xmsg!(
+                    "Writing off a negative rebase of {} on reserve {} as bad debt",
+                    shortfall,
+                    self.token_symbol()
+                );
+                self.liquidity.accumulated_bad_debt_sf =
+                    (self.liquidity.accumulated_bad_debt() + Fraction::from(shortfall)).to_bits();
+            }
+            Ordering::Equal => {}
+        }
+        self.liquidity.available_amount = vault_balance;
+
+        Ok(())
+    }
+
+    pub fn check_min_available_liquidity(&self) -> Result<()> {
+        if self.liquidity.available_amount < self.config.min_available_liquidity {
+            xmsg!(
+                "Available liquidity {} cannot go below the reserve minimum of {}",
+                self.liquidity.available_amount,
+                self.config.min_available_liquidity
+            );
+            return err!(LendingError::InsufficientLiquidity);
+        }
+        Ok(())
+    }
+
     pub fn redeem_collateral(&mut self, collateral_amount: u64) -> Result<u64> {
         let collateral_exchange_rate = self.collateral_exchange_rate()?;
 
-        let liquidity_amount =
-            collateral_exchange_rate.collateral_to_liquidity(collateral_amount)?;
+        let liquidity_amount = collateral_exchange_rate
+            .collateral_to_liquidity(collateral_amount, RoundingDirection::Down)?;
 
         self.collateral.burn(collateral_amount)?;
         self.liquidity.withdraw(liquidity_amount)?;
@@ -136,27 +321,112 @@ impl Reserve {
         Ok(liquidity_amount)
     }
 
+    /// Burns the collateral worth exactly `liquidity_amount`, rounded up in the reserve's favor,
+    /// and returns the burnt amount
+    pub fn redeem_collateral_for_liquidity(&mut self, liquidity_amount: u64) -> Result<u64> {
+        let collateral_exchange_rate = self.collateral_exchange_rate()?;
+
+        let collateral_amount = collateral_exchange_rate
+            .liquidity_to_collateral(liquidity_amount, RoundingDirection::Up)?;
+
+        self.collateral.burn(collateral_amount)?;
+        self.liquidity.withdraw(liquidity_amount)?;
+
+        Ok(collateral_amount)
+    }
+
     pub fn collateral_exchange_rate(&self) -> LendingResult<CollateralExchangeRate> {
         let total_liquidity = self.liquidity.total_supply()?;
-        self.collateral.exchange_rate(total_liquidity)
+        self.collateral
+            .exchange_rate(total_liquidity, self.config.virtual_collateral_offset)
+    }
+
+    /// Records the slot the reserve turned obsolete at, or clears it once it is no longer obsolete
+    pub fn update_obsolete_since_slot(&mut self, prev_status: ReserveStatus, slot: Slot) {
+        if self.config.status() != ReserveStatus::Obsolete {
+            self.obsolete_since_slot = 0;
+        } else if prev_status != ReserveStatus::Obsolete {
+            self.obsolete_since_slot = slot;
+        }
+    }
+
+    /// Whether the reserve is obsolete and the market grace window since then has elapsed
+    pub fn is_obsolete_past_grace(&self, slot: Slot, deprecation_grace_slots: u64) -> bool {
+        self.config.status() == ReserveStatus::Obsolete
+            && slot.saturating_sub(self.obsolete_since_slot) >= deprecation_grace_slots
     }
 
     pub fn accrue_interest(&mut self, current_slot: Slot) -> Result<()> {
         let slots_elapsed = self.last_update.slots_elapsed(current_slot)?;
         if slots_elapsed > 0 {
             let current_borrow_rate = self.current_borrow_rate()?;
-            let protocol_take_rate = Fraction::from_percent(self.config.protocol_take_rate_pct);
+            let protocol_take_rate = self.protocol_take_rate()?;
 
-            self.liquidity.compound_interest(
+            let (net_new_debt_f, protocol_fee_f) = self.liquidity.compound_interest(
                 current_borrow_rate,
                 slots_elapsed,
                 protocol_take_rate,
             )?;
+
+            if net_new_debt_f > Fraction::ZERO {
+                emit!(ProtocolFeeAccruedEvent {
+                    lending_market: self.lending_market,
+                    liquidity_mint: self.liquidity.mint_pubkey,
+                    net_new_debt_sf: net_new_debt_f.to_bits(),
+                    protocol_fee_sf: protocol_fee_f.to_bits(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn accrue_rewards(&mut self, current_slot: Slot) -> Result<()> {
+        let slots_elapsed = self.last_update.slots_elapsed(current_slot)?;
+        if slots_elapsed > 0 {
+            self.reward_state
+                .accrue(self.config.reward_emission_per_slot, slots_elapsed)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn record_utilization_snapshot(&mut self, current_slot: Slot) -> Result<()> {
+        let index = self.utilization_snapshot_index as usize % UTILIZATION_SNAPSHOTS_LEN;
+        let latest = &self.utilization_snapshots
+            [(index + UTILIZATION_SNAPSHOTS_LEN - 1) % UTILIZATION_SNAPSHOTS_LEN];
+        let next_snapshot_slot = latest
+            .slot
+            .saturating_add(UTILIZATION_SNAPSHOT_INTERVAL_SLOTS);
+        if latest.slot != 0 && current_slot < next_snapshot_slot {
+            return Ok(());
         }
 
+        let utilization_rate_bps = self
+            .liquidity
+            .utilization_rate()?
+            .to_bps()
+            .ok_or(LendingError::MathOverflow)?;
+
+        self.utilization_snapshots[index] = UtilizationSnapshot {
+            slot: current_slot,
+            utilization_rate_bps,
+        };
+        self.utilization_snapshot_index = ((index + 1) % UTILIZATION_SNAPSHOTS_LEN) as u64;
+
         Ok(())
     }
 
+    pub fn utilization_snapshots(&self) -> Vec<UtilizationSnapshot> {
+        let index = self.utilization_snapshot_index as usize % UTILIZATION_SNAPSHOTS_LEN;
+        self.utilization_snapshots[index..]
+            .iter()
+            .chain(self.utilization_snapshots[..index].iter())
+            .filter(|snapshot| snapshot.slot != 0)
+            .copied()
+            .collect()
+    }
+
     pub fn update_deposit_limit_crossed_slot(&mut self, current_slot: Slot) -> Result<()> {
         if self.deposit_limit_crossed()? {
             if self.liquidity.deposit_limit_crossed_slot == 0 {
@@ -168,6 +438,14 @@ impl Reserve {
         Ok(())
     }
 
+    pub fn is_deposit_limit_crossed_past_grace(&self, current_slot: Slot) -> bool {
+        let grace_slots = self.config.limit_crossed_grace_slots;
+        let crossed_slot = self.liquidity.deposit_limit_crossed_slot;
+        grace_slots > 0
+            && crossed_slot != 0
+            && current_slot.saturating_sub(crossed_slot) > grace_slots
+    }
+
     pub fn update_borrow_limit_crossed_slot(&mut self, current_slot: Slot) -> Result<()> {
         if self.borrow_limit_crossed()? {
             if self.liquidity.borrow_limit_crossed_slot == 0 {
@@ -184,6 +462,7 @@ impl Reserve {
         amount_to_borrow: u64,
         max_borrow_factor_adjusted_debt_value: Fraction,
         remaining_reserve_borrow: Fraction,
+        borrow_factor: Fraction,
     ) -> Result<CalculateBorrowResult> {
         let decimals = 10u64
             .checked_pow(self.liquidity.mint_decimals as u32)
@@ -193,14 +472,14 @@ impl Reserve {
         if amount_to_borrow == u64::MAX {
             let borrow_amount_f = (max_borrow_factor_adjusted_debt_value * u128::from(decimals)
                 / market_price_f
-                / self.borrow_factor_f())
-            .min(remaining_reserve_borrow)
-            .min(self.liquidity.available_amount.into());
+                / borrow_factor)
+                .min(remaining_reserve_borrow)
+                .min(self.liquidity.available_amount.into());
             let borrow_fee = self
                 .config
                 .fees
                 .calculate_borrow_fees(borrow_amount_f, FeeCalculation::Inclusive)?;
-            let borrow_amount: u64 = borrow_amount_f.to_floor();
+            let borrow_amount: u64 = borrow_amount_f.to_floor_protocol();
             let receive_amount = borrow_amount - borrow_fee;
 
             Ok(CalculateBorrowResult {
@@ -220,7 +499,7 @@ impl Reserve {
             let borrow_factor_adjusted_debt_value = borrow_amount_f
                 .mul(market_price_f)
                 .div(u128::from(decimals))
-                .mul(self.borrow_factor_f());
+                .mul(borrow_factor);
             if borrow_factor_adjusted_debt_value > max_borrow_factor_adjusted_debt_value {
                 xmsg!("Borrow value cannot exceed maximum borrow value, borrow borrow_factor_adjusted_debt_value: {}, max_borrow_factor_adjusted_debt_value: {}",
                     borrow_factor_adjusted_debt_value, max_borrow_factor_adjusted_debt_value);
@@ -239,14 +518,17 @@ impl Reserve {
         &self,
         amount_to_repay: u64,
         borrowed_amount: Fraction,
+        available_amount: u64,
     ) -> LendingResult<CalculateRepayResult> {
         let settle_amount_f = if amount_to_repay == u64::MAX {
-            borrowed_amount
+            // settling fractional debt rounds the transfer up, which must not exceed what the
+            // payer holds, so a short full repay settles only the available amount
+            min(borrowed_amount, Fraction::from(available_amount))
         } else {
             let amount_to_repay_f = Fraction::from(amount_to_repay);
             min(amount_to_repay_f, borrowed_amount)
         };
-        let repay_amount = settle_amount_f.to_ceil();
+        let repay_amount = settle_amount_f.to_ceil_protocol();
 
         Ok(CalculateRepayResult {
             settle_amount_f,
@@ -261,6 +543,12 @@ impl Reserve {
         ))
     }
 
+    pub fn redeem_fees_liquidity_buffer(&self) -> Result<u64> {
+        let total_supply = self.liquidity.total_supply()?;
+        let buffer_rate = Fraction::from_bps(self.config.redeem_fees_liquidity_buffer_bps);
+        Ok((total_supply * buffer_rate).to_ceil())
+    }
+
     pub fn deposit_limit_crossed(&self) -> Result<bool> {
         let crossed = self.liquidity.total_supply()? > Fraction::from(self.config.deposit_limit);
         Ok(crossed)
@@ -299,8 +587,13 @@ pub struct ReserveLiquidity {
 
     pub cumulative_borrow_rate_bsf: BigFractionBytes,
     pub accumulated_protocol_fees_sf: u128,
+    /// Debt written off without repayment, already excluded from `borrowed_amount_sf`
+    pub accumulated_bad_debt_sf: u128,
 
-    pub padding2: [u128; 32],
+    /// Receives the insurance share of protocol liquidation fees, unset until initialized
+    pub insurance_vault: Pubkey,
+
+    pub padding2: [u128; 29],
 }
 
 impl Default for ReserveLiquidity {
@@ -313,13 +606,15 @@ impl Default for ReserveLiquidity {
             borrowed_amount_sf: 0,
             cumulative_borrow_rate_bsf: BigFractionBytes::from(BigFraction::from(Fraction::ONE)),
             accumulated_protocol_fees_sf: 0,
+            accumulated_bad_debt_sf: 0,
+            insurance_vault: Pubkey::default(),
             market_price_sf: 0,
             mint_decimals: 0,
             deposit_limit_crossed_slot: 0,
             borrow_limit_crossed_slot: 0,
             market_price_last_updated_ts: 0,
             padding: 0,
-            padding2: [0; 32],
+            padding2: [0; 29],
         }
     }
 }
@@ -335,20 +630,28 @@ impl ReserveLiquidity {
             borrowed_amount_sf: 0,
             cumulative_borrow_rate_bsf: BigFractionBytes::from(BigFraction::from(Fraction::ONE)),
             accumulated_protocol_fees_sf: 0,
+            accumulated_bad_debt_sf: 0,
+            insurance_vault: Pubkey::default(),
             market_price_sf: params.market_price_sf,
             deposit_limit_crossed_slot: 0,
             borrow_limit_crossed_slot: 0,
             market_price_last_updated_ts: 0,
             padding: 0,
-            padding2: [0; 32],
+            padding2: [0; 29],
         }
     }
 
     pub fn total_supply(&self) -> LendingResult<Fraction> {
-        Ok(
-            Fraction::from(self.available_amount) + Fraction::from_bits(self.borrowed_amount_sf)
-                - Fraction::from_bits(self.accumulated_protocol_fees_sf),
-        )
+        let accumulated_protocol_fees = Fraction::from_bits(self.accumulated_protocol_fees_sf);
+        (Fraction::from(self.available_amount) + Fraction::from_bits(self.borrowed_amount_sf))
+            .checked_sub(accumulated_protocol_fees)
+            .ok_or_else(|| {
+                xmsg!(
+                    "Accumulated protocol fees {} exceed the reserve liquidity",
+                    accumulated_protocol_fees
+                );
+                LendingError::MathOverflow
+            })
     }
 
     pub fn total_borrow(&self) -> Fraction {
@@ -440,13 +743,25 @@ impl ReserveLiquidity {
         current_borrow_rate: Fraction,
         slots_elapsed: u64,
         protocol_take_rate: Fraction,
-    ) -> LendingResult<()> {
+    ) -> LendingResult<(Fraction, Fraction)> {
         let previous_cumulative_borrow_rate = BigFraction::from(self.cumulative_borrow_rate_bsf);
         let previous_debt_f = Fraction::from_bits(self.borrowed_amount_sf);
         let acc_protocol_fees_f = Fraction::from_bits(self.accumulated_protocol_fees_sf);
 
+        // a reserve left unrefreshed long enough to go past the maximum, or to overflow the
+        // approximation, accrues the maximum so it can still be refreshed
         let compounded_interest_rate =
-            approximate_compounded_interest(current_borrow_rate, slots_elapsed);
+            approximate_compounded_interest(current_borrow_rate, slots_elapsed)
+                .ok()
+                .filter(|rate| *rate <= MAX_COMPOUNDED_INTEREST_PER_ACCRUAL)
+                .unwrap_or_else(|| {
+                    xmsg!(
+                        "Compounded interest over {} slots is capped to the per accrual maximum {}",
+                        slots_elapsed,
+                        MAX_COMPOUNDED_INTEREST_PER_ACCRUAL
+                    );
+                    MAX_COMPOUNDED_INTEREST_PER_ACCRUAL
+                });
 
         let new_cumulative_borrow_rate: BigFraction =
             previous_cumulative_borrow_rate * BigFraction::from(compounded_interest_rate);
@@ -456,13 +771,21 @@ impl ReserveLiquidity {
 
         let total_protocol_fee_f = net_new_debt_f * protocol_take_rate;
 
-        let new_acc_protocol_fees_f = total_protocol_fee_f + acc_protocol_fees_f;
+        // fees never claim more than the liquidity backing them, whatever the take rate
+        let max_acc_protocol_fees_f = Fraction::from(self.available_amount) + new_debt_f;
+        let new_acc_protocol_fees_f = min(
+            total_protocol_fee_f + acc_protocol_fees_f,
+            max_acc_protocol_fees_f,
+        );
 
         self.cumulative_borrow_rate_bsf = new_cumulative_borrow_rate.into();
         self.accumulated_protocol_fees_sf = new_acc_protocol_fees_f.to_bits();
         self.borrowed_amount_sf = new_debt_f.to_bits();
 
-        Ok(())
+        Ok((
+            net_new_debt_f,
+            new_acc_protocol_fees_f.saturating_sub(acc_protocol_fees_f),
+        ))
     }
 
     pub fn forgive_debt(&mut self, liquidity_amount: Fraction) -> LendingResult<()> {
@@ -470,9 +793,28 @@ impl ReserveLiquidity {
         let new_amt = amt - liquidity_amount;
         self.borrowed_amount_sf = new_amt.to_bits();
 
+        let bad_debt = Fraction::from_bits(self.accumulated_bad_debt_sf);
+        self.accumulated_bad_debt_sf = (bad_debt + liquidity_amount).to_bits();
+
         Ok(())
     }
 
+    pub fn accumulated_bad_debt(&self) -> Fraction {
+        Fraction::from_bits(self.accumulated_bad_debt_sf)
+    }
+
+    /// Puts liquidity back into the supply against written off debt, returns the bad debt left
+    pub fn cover_bad_debt(&mut self, liquidity_amount: u64) -> Result<Fraction> {
+        self.deposit(liquidity_amount)?;
+
+        let bad_debt = self
+            .accumulated_bad_debt()
+            .saturating_sub(Fraction::from(liquidity_amount));
+        self.accumulated_bad_debt_sf = bad_debt.to_bits();
+
+        Ok(bad_debt)
+    }
+
     pub fn get_market_price_f(&self) -> Fraction {
         Fraction::from_bits(self.market_price_sf)
     }
@@ -520,45 +862,90 @@ impl ReserveCollateral {
         Ok(())
     }
 
-    fn exchange_rate(&self, total_liquidity: Fraction) -> LendingResult<CollateralExchangeRate> {
-        let rate = if self.mint_total_supply == 0 || total_liquidity == Fraction::ZERO {
-            INITIAL_COLLATERAL_RATE
+    pub(crate) fn exchange_rate(
+        &self,
+        total_liquidity: Fraction,
+        virtual_offset: u64,
+    ) -> LendingResult<CollateralExchangeRate> {
+        let (collateral_supply, liquidity) = if virtual_offset > 0 {
+            // ERC4626 style: the virtual shares take their cut of any donation, which makes
+            // inflating the rate ahead of the first depositors cost more than it can take
+            let virtual_offset = Fraction::from(virtual_offset);
+            (
+                Fraction::from(self.mint_total_supply) + virtual_offset,
+                total_liquidity + virtual_offset,
+            )
+        } else if self.mint_total_supply == 0 || total_liquidity == Fraction::ZERO {
+            (INITIAL_COLLATERAL_RATE, Fraction::ONE)
         } else {
-            Fraction::from(self.mint_total_supply) / total_liquidity
+            (Fraction::from(self.mint_total_supply), total_liquidity)
         };
 
-        Ok(CollateralExchangeRate(rate))
+        Ok(CollateralExchangeRate {
+            collateral_supply,
+            liquidity,
+        })
     }
 }
 
+/// Which way a conversion between collateral and liquidity rounds to a whole token amount.
+/// Amounts paid out by the protocol round down and amounts paid in round up.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RoundingDirection {
+    Down,
+    Up,
+}
+
+/// Ratio of collateral supply to total liquidity, kept as both sides so conversions
+/// divide once at the end instead of through a truncated rate.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
-pub struct CollateralExchangeRate(Fraction);
+pub struct CollateralExchangeRate {
+    collateral_supply: Fraction,
+    liquidity: Fraction,
+}
 
 impl CollateralExchangeRate {
-    pub fn collateral_to_liquidity(&self, collateral_amount: u64) -> LendingResult<u64> {
-        Ok(self
-            .fraction_collateral_to_liquidity(collateral_amount.into())
-            .to_floor())
+    pub fn collateral_to_liquidity(
+        &self,
+        collateral_amount: u64,
+        rounding: RoundingDirection,
+    ) -> LendingResult<u64> {
+        let numerator = U256::from(self.liquidity.to_bits()) * U256::from(collateral_amount);
+        let denominator = U256::from(self.collateral_supply.to_bits());
+
+        Self::div_rounded(numerator, denominator, rounding)
     }
 
     pub fn fraction_collateral_to_liquidity(&self, collateral_amount: Fraction) -> Fraction {
-        collateral_amount / self.0
+        collateral_amount * (self.liquidity / self.collateral_supply)
     }
 
-    pub fn liquidity_to_collateral(&self, liquidity_amount: u64) -> u64 {
-        (self.0 * u128::from(liquidity_amount)).to_floor()
-    }
-}
+    pub fn liquidity_to_collateral(
+        &self,
+        liquidity_amount: u64,
+        rounding: RoundingDirection,
+    ) -> LendingResult<u64> {
+        let numerator = U256::from(self.collateral_supply.to_bits()) * U256::from(liquidity_amount);
+        let denominator = U256::from(self.liquidity.to_bits());
 
-impl From<CollateralExchangeRate> for Fraction {
-    fn from(exchange_rate: CollateralExchangeRate) -> Self {
-        exchange_rate.0
+        Self::div_rounded(numerator, denominator, rounding)
     }
-}
 
-impl From<Fraction> for CollateralExchangeRate {
-    fn from(fraction: Fraction) -> Self {
-        Self(fraction)
+    fn div_rounded(
+        numerator: U256,
+        denominator: U256,
+        rounding: RoundingDirection,
+    ) -> LendingResult<u64> {
+        let quotient = numerator / denominator;
+        let quotient = match rounding {
+            RoundingDirection::Down => quotient,
+            RoundingDirection::Up if quotient * denominator < numerator => quotient + 1,
+            RoundingDirection::Up => quotient,
+        };
+
+        quotient
+            .try_into()
+            .map_err(|_| LendingError::IntegerOverflow)
     }
 }
 
@@ -583,9 +970,9 @@ pub struct ReserveConfig {
     pub min_liquidation_bonus_bps: u16,
     pub max_liquidation_bonus_bps: u16,
     pub bad_debt_liquidation_bonus_bps: u16,
-
-    #[derivative(Debug = "ignore")]
-    pub padding_0: [u8; 4],
+    pub flash_loan_referral_fee_bps: u16,
+    /// Share of the total supply that must stay available after redeeming protocol fees
+    pub redeem_fees_liquidity_buffer_bps: u16,
 
     pub deleveraging_margin_call_period_secs: u64,
     pub deleveraging_threshold_slots_per_bps: u64,
@@ -599,12 +986,70 @@ pub struct ReserveConfig {
 
     pub deposit_withdrawal_cap: WithdrawalCaps,
     pub debt_withdrawal_cap: WithdrawalCaps,
-    pub padding_1: u8,
-
-    #[derivative(Debug = "ignore")]
-    pub padding_2: [u8; 7],
-
-    pub reserved: [u64; 32],
+    pub elevation_group: u8,
+    /// Liquidation priority of debt borrowed from this reserve, see `DebtTranche`
+    pub debt_tranche: u8,
+    /// Minimum gap between the borrow and supply rates, as a share of the borrow rate
+    pub min_supply_borrow_spread_bps: u16,
+    /// When set, new deposits are rejected while withdrawals and redeems keep working
+    pub deposits_disabled: u8,
+    /// Set for mints whose balances change outside transfers, the vault balance is then
+    /// reconciled into `available_amount` before each liquidity transfer
+    pub is_rebasing: u8,
+    /// When set the reserve is collateral only, deposits work but it can't be borrowed from
+    pub borrowing_disabled: u8,
+    /// Close factor used instead of the market one when this reserve's collateral is
+    /// liquidated, 0 keeps the market `liquidation_max_debt_close_factor_pct`
+    pub liquidation_close_factor_pct: u8,
+
+    /// Floor on available liquidity for redeems and borrows, waived for the final redeem
+    pub min_available_liquidity: u64,
+
+    /// Lamports a liquidator repaying this reserve escrows when the market requires bonds
+    pub liquidation_bond_lamports: u64,
+
+    /// Owner of the token account protocol fees are withdrawn to, the market owner when unset
+    pub protocol_fee_receiver: Pubkey,
+
+    /// Ceiling applied to the borrow rate read from the curve, 0 when uncapped
+    pub max_borrow_rate_bps: u64,
+
+    /// Slots the deposit limit may stay crossed before deposits are disabled, 0 when unbounded
+    pub limit_crossed_grace_slots: u64,
+
+    /// Minimum first deposit, that much of its collateral is locked as dead shares, 0 disables it
+    pub min_initial_deposit_amount: u64,
+
+    /// Virtual collateral and liquidity added to both sides of the exchange rate so a donation
+    /// can't skew the price of the first mints, 0 disables it
+    pub virtual_collateral_offset: u64,
+
+    /// Slots an obligation deposit must be held before it can be withdrawn, 0 disables it
+    pub min_deposit_hold_slots: u64,
+
+    /// Reward tokens emitted per slot to the collateral deposited in obligations, paid from the
+    /// market `reward_vault`
+    pub reward_emission_per_slot: u64,
+
+    /// Cap on the liquidity of a single deposit, on top of `deposit_limit`, 0 disables it
+    pub max_single_deposit: u64,
+
+    /// Largest move from the saved price a refresh accepts while the saved price is fresh, a
+    /// bigger jump keeps the saved price, 0 disables it
+    pub max_price_change_bps_per_refresh: u64,
+
+    /// Share of the protocol liquidation fee sent to the reserve insurance vault instead of the
+    /// fee vault, 0 disables it
+    pub insurance_fee_bps: u64,
+
+    /// Floor applied to the borrow rate read from the curve, after `max_borrow_rate_bps`
+    pub min_borrow_rate_bps: u64,
+
+    /// Fee charged on top of the repaid principal and sent to the fee vault, `Fraction` bits
+    /// like the `fees` fields, repays with collateral move no liquidity and are exempt
+    pub repay_fee_sf: u64,
+
+    pub reserved: [u64; 15],
 }
 
 impl ReserveConfig {
@@ -612,6 +1057,18 @@ impl ReserveConfig {
         AssetTier::try_from(self.asset_tier).unwrap()
     }
 
+    pub fn liquidation_close_factor_pct(&self, market_close_factor_pct: u8) -> u8 {
+        if self.liquidation_close_factor_pct == 0 {
+            market_close_factor_pct
+        } else {
+            self.liquidation_close_factor_pct
+        }
+    }
+
+    pub fn get_debt_tranche(&self) -> DebtTranche {
+        DebtTranche::try_from(self.debt_tranche).unwrap()
+    }
+
     pub fn get_borrow_factor(&self) -> Fraction {
         max(
             Fraction::ONE,
@@ -619,9 +1076,72 @@ impl ReserveConfig {
         )
     }
 
+    /// Borrow factor of an obligation's debt, its override replacing the reserve's when set
+    pub fn get_obligation_borrow_factor(&self, borrow_factor_override_pct: u64) -> Fraction {
+        if borrow_factor_override_pct == 0 {
+            self.get_borrow_factor()
+        } else {
+            max(
+                Fraction::ONE,
+                Fraction::from_percent(borrow_factor_override_pct),
+            )
+        }
+    }
+
     pub fn status(&self) -> ReserveStatus {
         ReserveStatus::try_from(self.status).unwrap()
     }
+
+    pub fn is_deposits_disabled(&self) -> bool {
+        self.deposits_disabled != false as u8
+    }
+
+    pub fn is_rebasing(&self) -> bool {
+        self.is_rebasing != false as u8
+    }
+
+    pub fn is_borrowing_enabled(&self) -> bool {
+        self.borrowing_disabled == false as u8
+    }
+
+    pub fn calculate_repay_fees(&self, repay_amount: Fraction) -> Result<u64> {
+        let repay_fee_rate = Fraction::from_bits(self.repay_fee_sf.into());
+        if repay_fee_rate == Fraction::ZERO || repay_amount == Fraction::ZERO {
+            return Ok(0);
+        }
+
+        let minimum_fee = 1u64;
+        let repay_fee_f = repay_amount.mul(repay_fee_rate).max(minimum_fee.into());
+
+        Ok(repay_fee_f.to_ceil_protocol())
+    }
+
+    /// Largest amount a payer holding `balance` can repay with the repay fee paid on top
+    pub fn max_repay_amount_before_fees(&self, balance: u64) -> Result<u64> {
+        let repay_fee_rate = Fraction::from_bits(self.repay_fee_sf.into());
+        if repay_fee_rate == Fraction::ZERO {
+            return Ok(balance);
+        }
+
+        let amount_with_fees = |amount: u64| -> Result<u64> {
+            let repay_fee = self.calculate_repay_fees(Fraction::from(amount))?;
+            amount
+                .checked_add(repay_fee)
+                .ok_or_else(|| error!(LendingError::MathOverflow))
+        };
+
+        // the estimate is off by the rounding of the fee, a few units at most
+        let mut amount: u64 =
+            (Fraction::from(balance) / (Fraction::ONE + repay_fee_rate)).to_floor();
+        while amount > 0 && amount_with_fees(amount)? > balance {
+            amount -= 1;
+        }
+        while amount < balance && amount_with_fees(amount + 1)? <= balance {
+            amount += 1;
+        }
+
+        Ok(amount)
+    }
 }
 
 #[repr(u8)]
@@ -659,8 +1179,8 @@ pub struct WithdrawalCaps {
 pub struct ReserveFees {
     pub borrow_fee_sf: u64,
     pub flash_loan_fee_sf: u64,
-    #[derivative(Debug = "ignore")]
-    pub padding: [u8; 8],
+    /// Fixed borrow fee in native units, charged on top of the percentage borrow fee
+    pub flat_borrow_fee: u64,
 }
 
 impl ReserveFees {
@@ -669,17 +1189,54 @@ impl ReserveFees {
         borrow_amount: Fraction,
         fee_calculation: FeeCalculation,
     ) -> Result<u64> {
-        self.calculate_fees(borrow_amount, self.borrow_fee_sf, fee_calculation)
+        let flat_borrow_fee = if borrow_amount > Fraction::ZERO {
+            self.flat_borrow_fee
+        } else {
+            0
+        };
+
+        let percentage_fee_base = match fee_calculation {
+            FeeCalculation::Exclusive => borrow_amount,
+            // the flat fee is taken out of the borrowed amount before the percentage fee
+            FeeCalculation::Inclusive => {
+                borrow_amount.saturating_sub(Fraction::from(flat_borrow_fee))
+            }
+        };
+        let percentage_fee =
+            self.calculate_fees(percentage_fee_base, self.borrow_fee_sf, fee_calculation)?;
+
+        let borrow_fee = percentage_fee
+            .checked_add(flat_borrow_fee)
+            .ok_or_else(|| error!(LendingError::MathOverflow))?;
+        if flat_borrow_fee > 0 && Fraction::from(borrow_fee) >= borrow_amount {
+            xmsg!("Borrow amount is too small to receive liquidity after fees");
+            return err!(LendingError::BorrowTooSmall);
+        }
+
+        Ok(borrow_fee)
     }
 
-    pub fn calculate_flash_loan_fees(&self, flash_loan_amount_f: Fraction) -> Result<u64> {
-        let protocol_fee = self.calculate_fees(
+    pub fn calculate_flash_loan_fees(
+        &self,
+        flash_loan_amount_f: Fraction,
+        referral_fee_bps: u16,
+        has_referrer: bool,
+    ) -> Result<(u64, u64)> {
+        let total_fee = self.calculate_fees(
             flash_loan_amount_f,
             self.flash_loan_fee_sf,
             FeeCalculation::Exclusive,
         )?;
 
-        Ok(protocol_fee)
+        let referral_fee = if has_referrer && referral_fee_bps > 0 {
+            (Fraction::from(total_fee) * Fraction::from_bps(referral_fee_bps)).to_floor_protocol()
+        } else {
+            0
+        };
+
+        let protocol_fee = total_fee - referral_fee;
+
+        Ok((protocol_fee, referral_fee))
     }
 
     fn calculate_fees(
@@ -706,7 +1263,7 @@ impl ReserveFees {
                 return err!(LendingError::BorrowTooSmall);
             }
 
-            let protocol_fee: u64 = borrow_fee_f.to_round();
+            let protocol_fee: u64 = borrow_fee_f.to_ceil_protocol();
             Ok(protocol_fee)
         } else {
             Ok(0)
@@ -736,16 +1293,44 @@ pub enum AssetTier {
     IsolatedDebt = 2,
 }
 
-pub fn approximate_compounded_interest(rate: Fraction, elapsed_slots: u64) -> Fraction {
+#[derive(
+    AnchorSerialize,
+    AnchorDeserialize,
+    Debug,
+    PartialEq,
+    Eq,
+    num_enum::IntoPrimitive,
+    num_enum::TryFromPrimitive,
+)]
+#[repr(u8)]
+pub enum DebtTranche {
+    Senior = 0,
+    Subordinated = 1,
+}
+
+fn apy_from_rate(rate: Fraction) -> Result<Fraction> {
+    let slots_per_year =
+        u32::try_from(SLOTS_PER_YEAR).map_err(|_| error!(LendingError::IntegerOverflow))?;
+    let compounded = (Fraction::ONE + rate / u128::from(SLOTS_PER_YEAR))
+        .checked_pow(slots_per_year)
+        .ok_or_else(|| error!(LendingError::MathOverflow))?;
+
+    Ok(compounded - Fraction::ONE)
+}
+
+pub fn approximate_compounded_interest(
+    rate: Fraction,
+    elapsed_slots: u64,
+) -> LendingResult<Fraction> {
     let base = rate / u128::from(SLOTS_PER_YEAR);
     match elapsed_slots {
-        0 => return Fraction::ONE,
-        1 => return Fraction::ONE + base,
-        2 => return (Fraction::ONE + base) * (Fraction::ONE + base),
-        3 => return (Fraction::ONE + base) * (Fraction::ONE + base) * (Fraction::ONE + base),
+        0 => return Ok(Fraction::ONE),
+        1 => return Ok(Fraction::ONE + base),
+        2 => return Ok((Fraction::ONE + base) * (Fraction::ONE + base)),
+        3 => return Ok((Fraction::ONE + base) * (Fraction::ONE + base) * (Fraction::ONE + base)),
         4 => {
             let pow_two = (Fraction::ONE + base) * (Fraction::ONE + base);
-            return pow_two * pow_two;
+            return Ok(pow_two * pow_two);
         }
         _ => (),
     }
@@ -754,16 +1339,29 @@ pub fn approximate_compounded_interest(rate: Fraction, elapsed_slots: u64) -> Fr
     let exp_minus_one = exp.wrapping_sub(1);
     let exp_minus_two = exp.wrapping_sub(2);
 
-    let base_power_two = base * base;
-    let base_power_three = base_power_two * base;
-
-    let first_term = base * exp;
-
-    let second_term = (base_power_two * exp * exp_minus_one) / 2;
-
-    let third_term = (base_power_three * exp * exp_minus_one * exp_minus_two) / 6;
-
-    Fraction::ONE + first_term + second_term + third_term
+    // powers of the per slot rate underflow the fraction precision, so each term is built
+    // from products of the rate scaled by the elapsed slots instead
+    let first_term = base
+        .checked_mul_int(exp)
+        .ok_or(LendingError::MathOverflow)?;
+
+    let second_term = base
+        .checked_mul_int(exp_minus_one)
+        .and_then(|term| term.checked_mul(first_term))
+        .ok_or(LendingError::MathOverflow)?
+        / 2;
+
+    let third_term = base
+        .checked_mul_int(exp_minus_two)
+        .and_then(|term| term.checked_mul(second_term))
+        .ok_or(LendingError::MathOverflow)?
+        / 3;
+
+    Fraction::ONE
+        .checked_add(first_term)
+        .and_then(|sum| sum.checked_add(second_term))
+        .and_then(|sum| sum.checked_add(third_term))
+        .ok_or(LendingError::MathOverflow)
 }
 
 #[derive(
@@ -805,6 +1403,39 @@ pub enum UpdateConfigMode {
     DeleveragingMarginCallPeriod = 32,
     UpdateBorrowFactor = 33,
     UpdateAssetTier = 34,
+    UpdateElevationGroup = 35,
     DeleveragingThresholdSlotsPerBps = 36,
     UpdateReserveStatus = 39,
+    UpdateFlashLoanReferralFeeBps = 40,
+    UpdateRedeemFeesLiquidityBufferBps = 41,
+    UpdateTokenInfoMaxConfidencePct = 42,
+    UpdateMinAvailableLiquidity = 43,
+    UpdateDebtTranche = 44,
+    UpdateLiquidationBondLamports = 45,
+    UpdateMinSupplyBorrowSpreadBps = 46,
+    UpdateFlatBorrowFee = 47,
+    UpdateTokenInfoPricePinMode = 48,
+    UpdateTokenInfoPricePinBandBps = 49,
+    UpdateDepositsDisabled = 50,
+    UpdateProtocolFeeReceiver = 51,
+    UpdateMaxBorrowRateBps = 52,
+    UpdateTokenInfoPriceMaxAgeLiquidation = 53,
+    UpdateLimitCrossedGraceSlots = 54,
+    UpdateMinInitialDepositAmount = 55,
+    UpdateIsRebasing = 56,
+    UpdateBorrowingDisabled = 57,
+    UpdateBorrowRateCurveKinked = 58,
+    UpdateVirtualCollateralOffset = 59,
+    UpdateFeesRepayFee = 60,
+    UpdateTokenInfoFixedPrice = 61,
+    UpdateTokenInfoFallbackOracle = 62,
+    UpdateTokenInfoMaxFallbackDivergenceBps = 63,
+    UpdateLiquidationCloseFactorPct = 64,
+    UpdateTokenInfoPriceAuthority = 65,
+    UpdateMinDepositHoldSlots = 66,
+    UpdateRewardEmissionPerSlot = 67,
+    UpdateMaxSingleDeposit = 68,
+    UpdateMaxPriceChangeBpsPerRefresh = 69,
+    UpdateInsuranceFeeBps = 70,
+    UpdateMinBorrowRateBps = 71,
 }
@@ -0,0 +1,140 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    utils::{BorrowRateCurve, CurvePoint},
+    AssetTier, ReserveConfig,
+};
+
+static_assertions::const_assert_eq!(0, std::mem::size_of::<ReserveConfigTemplate>() % 8);
+#[derive(Debug, Default, PartialEq, Eq)]
+#[account(zero_copy)]
+#[repr(C)]
+pub struct ReserveConfigTemplate {
+    pub lending_market: Pubkey,
+    /// `ReserveConfigTemplateKind` of this template, also part of its seeds
+    pub kind: u8,
+    pub padding: [u8; 7],
+    pub bump: u64,
+
+    /// Risk parameters applied to reserves, see `ReserveConfigTemplate::apply`
+    pub config: ReserveConfig,
+
+    pub reserved: [u64; 16],
+}
+
+#[derive(
+    AnchorSerialize,
+    AnchorDeserialize,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    num_enum::IntoPrimitive,
+    num_enum::TryFromPrimitive,
+)]
+#[repr(u8)]
+pub enum ReserveConfigTemplateKind {
+    Stablecoin = 0,
+    Bluechip = 1,
+    Longtail = 2,
+}
+
+impl ReserveConfigTemplateKind {
+    pub fn default_config(self) -> ReserveConfig {
+        let (
+            loan_to_value_pct,
+            liquidation_threshold_pct,
+            min_liquidation_bonus_bps,
+            max_liquidation_bonus_bps,
+            protocol_take_rate_pct,
+            borrow_factor_pct,
+            curve,
+        ) = match self {
+            ReserveConfigTemplateKind::Stablecoin => (
+                80,
+                90,
+                200,
+                500,
+                10,
+                100,
+                [(0, 0), (9_000, 800), (10_000, 5_000)],
+            ),
+            ReserveConfigTemplateKind::Bluechip => (
+                70,
+                80,
+                300,
+                800,
+                15,
+                100,
+                [(0, 0), (8_000, 1_000), (10_000, 10_000)],
+            ),
+            ReserveConfigTemplateKind::Longtail => (
+                40,
+                55,
+                500,
+                1_500,
+                20,
+                150,
+                [(0, 0), (5_000, 2_000), (10_000, 30_000)],
+            ),
+        };
+        let curve = curve.map(|(utilization_rate_bps, borrow_rate_bps)| {
+            CurvePoint::new(utilization_rate_bps, borrow_rate_bps)
+        });
+
+        ReserveConfig {
+            asset_tier: AssetTier::Regular.into(),
+            protocol_take_rate_pct,
+            protocol_liquidation_fee_pct: 10,
+            loan_to_value_pct,
+            liquidation_threshold_pct,
+            min_liquidation_bonus_bps,
+            max_liquidation_bonus_bps,
+            bad_debt_liquidation_bonus_bps: 10,
+            deleveraging_margin_call_period_secs: 259200, // 3 days
+            deleveraging_threshold_slots_per_bps: 7200,   // 0.01% per hour
+            borrow_rate_curve: BorrowRateCurve::from_points(&curve).unwrap(),
+            borrow_factor_pct,
+            ..Default::default()
+        }
+    }
+}
+
+pub struct InitReserveConfigTemplateParams {
+    pub lending_market: Pubkey,
+    pub kind: ReserveConfigTemplateKind,
+    pub bump: u8,
+}
+
+impl ReserveConfigTemplate {
+    pub fn init(&mut self, params: InitReserveConfigTemplateParams) {
+        *self = Self::default();
+        self.lending_market = params.lending_market;
+        self.kind = params.kind.into();
+        self.bump = params.bump as u64;
+        self.config = params.kind.default_config();
+    }
+
+    /// Overwrites the risk parameters of `config` with the template's, keeping the status,
+    /// deposits, rebasing and borrowing flags, oracle, limits, elevation group, fee receiver
+    /// and virtual collateral offset which depend on the asset rather than its risk class
+    pub fn apply(&self, config: &mut ReserveConfig) {
+        *config = ReserveConfig {
+            status: config.status,
+            deposits_disabled: config.deposits_disabled,
+            is_rebasing: config.is_rebasing,
+            borrowing_disabled: config.borrowing_disabled,
+            token_info: config.token_info,
+            deposit_limit: config.deposit_limit,
+            borrow_limit: config.borrow_limit,
+            deposit_withdrawal_cap: config.deposit_withdrawal_cap,
+            debt_withdrawal_cap: config.debt_withdrawal_cap,
+            min_available_liquidity: config.min_available_liquidity,
+            elevation_group: config.elevation_group,
+            protocol_fee_receiver: config.protocol_fee_receiver,
+            virtual_collateral_offset: config.virtual_collateral_offset,
+            ..self.config
+        };
+    }
+}
@@ -3,7 +3,12 @@ use std::fmt::Formatter;
 use anchor_lang::prelude::*;
 use borsh::{BorshDeserialize, BorshSerialize};
 
-use crate::{constants::NULL_PUBKEY, errors::LendingError};
+use crate::{
+    constants::{DEFAULT_MAX_CONFIDENCE_PCT, FULL_BPS, NULL_PUBKEY},
+    errors::LendingError,
+    state::PriceStatusFlags,
+    utils::{Fraction, FractionExtra},
+};
 
 #[derive(BorshDeserialize, BorshSerialize, PartialEq, Eq, Default)]
 #[zero_copy]
@@ -15,8 +20,26 @@ pub struct TokenInfo {
     pub max_age_price_seconds: u64,
     pub max_age_twap_seconds: u64,
     pub pyth_configuration: PythConfiguration,
+    pub max_confidence_pct: u64,
 
-    pub _padding: [u64; 20],
+    /// `PricePinMode` of fiat-pinned stablecoins
+    pub price_pin_mode: u64,
+    /// Band around 1.0 the price is clamped to with `PricePinMode::Clamped`
+    pub price_pin_band_bps: u64,
+    /// Max price age tolerated by liquidations, never stricter than `max_age_price_seconds`
+    pub max_age_price_seconds_liquidation: u64,
+    /// Price used in place of an oracle for pegged or wrapped assets, `Fraction` bits like the fee
+    /// fields, 0 when the price comes from pyth
+    pub fixed_price_sf: u64,
+    /// Second pyth feed used when the primary one is stale or fails to load, disabled when unset
+    pub fallback_oracle: PythConfiguration,
+    /// Max gap between the primary and fallback prices when both are fresh, 0 disables the check
+    pub max_fallback_divergence_bps: u64,
+    /// Signer allowed to push prices with `push_reserve_price` in place of an oracle, unset when
+    /// the price comes from pyth or is fixed
+    pub price_authority: Pubkey,
+
+    pub _padding: [u64; 6],
 }
 
 impl std::fmt::Debug for TokenInfo {
@@ -28,24 +51,76 @@ impl std::fmt::Debug for TokenInfo {
             .field("max_age_price_seconds", &self.max_age_price_seconds)
             .field("max_age_twap_seconds", &self.max_age_twap_seconds)
             .field("pyth_configuration", &self.pyth_configuration)
+            .field("max_confidence_pct", &self.max_confidence_pct)
+            .field("price_pin_mode", &self.price_pin_mode)
+            .field("price_pin_band_bps", &self.price_pin_band_bps)
+            .field(
+                "max_age_price_seconds_liquidation",
+                &self.max_age_price_seconds_liquidation,
+            )
+            .field("fixed_price_sf", &self.fixed_price_sf)
+            .field("fallback_oracle", &self.fallback_oracle)
+            .field(
+                "max_fallback_divergence_bps",
+                &self.max_fallback_divergence_bps,
+            )
+            .field("price_authority", &self.price_authority)
             .finish()
     }
 }
 
 impl TokenInfo {
-    pub fn validate_token_info_config(&self, pyth_info: &Option<AccountInfo>) -> Result<()> {
+    pub fn validate_token_info_config(
+        &self,
+        pyth_info: &Option<AccountInfo>,
+        fallback_info: &Option<AccountInfo>,
+    ) -> Result<()> {
         require!(self.is_valid(), LendingError::InvalidOracleConfig);
         require!(self.is_twap_config_valid(), LendingError::InvalidTwapConfig);
         require!(
             self.check_pyth_acc_matches(pyth_info),
             LendingError::InvalidPythPriceAccount
         );
+        require!(
+            self.check_fallback_acc_matches(fallback_info),
+            LendingError::InvalidPythPriceAccount
+        );
         Ok(())
     }
 
+    /// Exactly one price source, pyth, a fixed price or a price authority, must be configured,
+    /// and a fallback feed can only back up a different pyth feed
     #[inline]
     pub fn is_valid(&self) -> bool {
-        self.pyth_configuration.is_enabled()
+        let price_sources = [
+            self.pyth_configuration.is_enabled(),
+            self.is_fixed_price(),
+            self.is_pushed_price(),
+        ];
+        price_sources.iter().filter(|enabled| **enabled).count() == 1
+            && (!self.is_fallback_enabled()
+                || (self.pyth_configuration.is_enabled()
+                    && self.fallback_oracle.price != self.pyth_configuration.price))
+    }
+
+    #[inline]
+    pub fn is_pushed_price(&self) -> bool {
+        self.price_authority != Pubkey::default()
+    }
+
+    #[inline]
+    pub fn is_fallback_enabled(&self) -> bool {
+        self.fallback_oracle.is_enabled()
+    }
+
+    #[inline]
+    pub fn is_fixed_price(&self) -> bool {
+        self.fixed_price_sf > 0
+    }
+
+    #[inline]
+    pub fn fixed_price(&self) -> Fraction {
+        Fraction::from_bits(self.fixed_price_sf.into())
     }
 
     #[inline]
@@ -75,6 +150,54 @@ impl TokenInfo {
         }
     }
 
+    /// The fallback feed is required whenever configured, so the divergence check can't be
+    /// skipped by leaving it out
+    #[inline]
+    pub fn check_fallback_acc_matches(&self, fallback_info: &Option<AccountInfo>) -> bool {
+        if self.is_fallback_enabled() {
+            matches!(fallback_info, Some(a) if *a.key == self.fallback_oracle.price)
+        } else {
+            fallback_info.is_none()
+        }
+    }
+
+    #[inline]
+    pub fn effective_max_confidence_pct(&self) -> u64 {
+        if self.max_confidence_pct == 0 {
+            DEFAULT_MAX_CONFIDENCE_PCT
+        } else {
+            self.max_confidence_pct
+        }
+    }
+
+    #[inline]
+    pub fn is_price_pin_config_valid(&self) -> bool {
+        PricePinMode::try_from(self.price_pin_mode).is_ok()
+            && self.price_pin_band_bps <= u64::from(FULL_BPS)
+    }
+
+    /// Price in the quote currency once pinned, the oracle price is kept as is when pinning is
+    /// disabled
+    pub fn pin_price(&self, price: Fraction) -> Fraction {
+        match PricePinMode::try_from(self.price_pin_mode) {
+            Ok(PricePinMode::Pinned) => Fraction::ONE,
+            Ok(PricePinMode::Clamped) => {
+                let band = Fraction::from_bps(self.price_pin_band_bps);
+                price.clamp(Fraction::ONE.saturating_sub(band), Fraction::ONE + band)
+            }
+            Ok(PricePinMode::Disabled) | Err(_) => price,
+        }
+    }
+
+    pub fn price_max_age(&self, context: PriceAgeContext) -> u64 {
+        match context {
+            PriceAgeContext::Standard => self.max_age_price_seconds,
+            PriceAgeContext::Liquidation => self
+                .max_age_price_seconds
+                .max(self.max_age_price_seconds_liquidation),
+        }
+    }
+
     pub fn symbol(&self) -> &str {
         std::str::from_utf8(&self.name)
             .unwrap_or("InvalidTokenName")
@@ -82,6 +205,44 @@ impl TokenInfo {
     }
 }
 
+#[derive(
+    AnchorSerialize,
+    AnchorDeserialize,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    num_enum::IntoPrimitive,
+    num_enum::TryFromPrimitive,
+)]
+#[repr(u64)]
+pub enum PricePinMode {
+    /// The oracle price is used as is
+    Disabled = 0,
+    /// The price is always 1.0, whatever the oracle reports
+    Pinned = 1,
+    /// The oracle price is clamped to 1.0 +/- `price_pin_band_bps`
+    Clamped = 2,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PriceAgeContext {
+    /// Borrows, withdrawals and every other check requiring a fresh price
+    Standard,
+    /// Liquidations, which tolerate `max_age_price_seconds_liquidation`
+    Liquidation,
+}
+
+impl PriceAgeContext {
+    pub fn price_age_checked_flag(&self) -> PriceStatusFlags {
+        match self {
+            PriceAgeContext::Standard => PriceStatusFlags::PRICE_AGE_CHECKED,
+            PriceAgeContext::Liquidation => PriceStatusFlags::LIQUIDATION_PRICE_AGE_CHECKED,
+        }
+    }
+}
+
 #[derive(BorshDeserialize, BorshSerialize, Debug, PartialEq, Eq, Default)]
 #[zero_copy]
 #[repr(transparent)]
@@ -1,6 +1,8 @@
+use anchor_lang::{prelude::borsh, AnchorDeserialize, AnchorSerialize};
+
 use crate::utils::Fraction;
 
-use super::PriceStatusFlags;
+use super::{PriceStatusFlags, UtilizationSnapshot};
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct CalculateBorrowResult {
     pub borrow_amount_f: Fraction,
@@ -43,6 +45,26 @@ pub struct LiquidationParams {
     pub liquidation_bonus_rate: Fraction,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LiquidationMode {
+    /// Unhealthy or auto-deleveraged obligation
+    Standard {
+        max_allowed_ltv_override_pct_opt: Option<u64>,
+    },
+    /// Keeper deleveraging down to the owner's `autodeleverage_target_ltv_pct`
+    ToTargetLtv,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObligationHealth {
+    /// Debt below the unhealthy borrow value
+    Healthy,
+    /// Debt at or above the unhealthy borrow value, still covered by the deposits
+    Liquidatable,
+    /// Borrowed market value above the deposited value
+    BadDebt,
+}
+
 pub struct RefreshObligationDepositsResult {
     pub lowest_deposit_ltv_accumulator: u8,
     pub num_of_obsolete_reserves: u8,
@@ -58,6 +80,74 @@ pub struct RefreshObligationBorrowsResult {
     pub prices_state: PriceStatusFlags,
 }
 
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct GetUtilizationResult {
+    pub utilization_rate_bps: u64,
+    pub snapshots: Vec<UtilizationSnapshot>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct GetReserveApyResult {
+    pub borrow_apy_sf: u128,
+    pub supply_apy_sf: u128,
+}
+
+/// Return data of `get_obligation_values`, every `_sf` field is scaled by `scale`
+/// (`FRACTION_ONE_SCALED`, 2^60) so clients divide by it rather than hardcode the bit count
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct GetObligationValuesResult {
+    pub scale: u128,
+    pub deposited_value_sf: u128,
+    pub borrow_factor_adjusted_debt_value_sf: u128,
+    pub borrowed_assets_market_value_sf: u128,
+    pub allowed_borrow_value_sf: u128,
+    pub unhealthy_borrow_value_sf: u128,
+    /// Zero while the obligation has no deposits
+    pub loan_to_value_sf: u128,
+    /// Zero while the obligation has no deposits
+    pub unhealthy_loan_to_value_sf: u128,
+}
+
+/// Return data of `borrow_obligation_liquidity`, borsh encoded as 32 little-endian bytes:
+/// `borrow_amount_sf` (u128, bytes 0..16), `receive_amount` (u64, bytes 16..24)
+/// and `borrow_fee` (u64, bytes 24..32)
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BorrowObligationLiquidityResult {
+    /// Debt added to the obligation, including the fee
+    pub borrow_amount_sf: u128,
+    pub receive_amount: u64,
+    pub borrow_fee: u64,
+}
+
+/// Return data of `repay_obligation_liquidity`, borsh encoded as 8 little-endian bytes:
+/// `repay_amount` (u64, bytes 0..8)
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RepayObligationLiquidityResult {
+    /// Liquidity actually transferred, capped to the outstanding debt
+    pub repay_amount: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ReconcileReserveResult {
+    pub vault_balance: u64,
+    /// Includes the protocol fees not yet redeemed to the fee vault
+    pub available_amount: u64,
+    /// Vault balance minus `available_amount`, positive when the vault holds untracked tokens
+    pub drift: i128,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SimulateLiquidationResult {
+    pub settle_amount_sf: u128,
+    pub repay_amount: u64,
+    pub withdraw_amount: u64,
+    pub liquidation_bonus_rate_sf: u128,
+    /// Collateral the liquidator receives, to compare against `min_acceptable_received_collateral_amount`
+    pub withdraw_collateral_amount: u64,
+    pub withdraw_liquidity_amount: u64,
+    pub protocol_liquidation_fee: u64,
+}
+
 pub enum LendingAction {
     Additive(u64),
     Subtractive(u64),
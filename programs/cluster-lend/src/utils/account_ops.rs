@@ -8,7 +8,8 @@ pub fn close_account_loader<'info, T: ZeroCopy + Owner>(
     owner: &Signer<'info>,
     account_to_be_closed: &AccountLoader<'info, T>,
 ) -> Result<()> {
-    if close_account {
+    // the rent goes to the owner, an owner passed read-only keeps the empty account open
+    if close_account && owner.is_writable {
         account_to_be_closed.close(owner.to_account_info().clone())?;
     }
 
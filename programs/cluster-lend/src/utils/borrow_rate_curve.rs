@@ -99,6 +99,7 @@ impl BorrowRateCurve {
         }
 
         let mut last_pt = pts[0];
+        let mut flatlined = false;
         for pt in pts.iter().skip(1) {
             if last_pt.utilization_rate_bps == MAX_UTILIZATION_RATE_BPS {
                 if pt.utilization_rate_bps != MAX_UTILIZATION_RATE_BPS {
@@ -108,7 +109,19 @@ impl BorrowRateCurve {
                     );
                     return err!(LendingError::InvalidBorrowRateCurvePoint);
                 }
-            } else if pt.utilization_rate_bps <= last_pt.utilization_rate_bps {
+                // Unused points are padded with a copy of the last point
+                if pt.borrow_rate_bps != last_pt.borrow_rate_bps {
+                    xmsg!(
+                        "Borrowing rate curve points after the last point must repeat it, found a borrow rate \
+                        of {} instead of {}",
+                        pt.borrow_rate_bps,
+                        last_pt.borrow_rate_bps
+                    );
+                    return err!(LendingError::InvalidBorrowRateCurvePoint);
+                }
+                continue;
+            }
+            if pt.utilization_rate_bps <= last_pt.utilization_rate_bps {
                 xmsg!("Borrowing rate curve points must be sorted by utilization rate");
                 return err!(LendingError::InvalidBorrowRateCurvePoint);
             }
@@ -116,6 +129,16 @@ impl BorrowRateCurve {
                 xmsg!("Borrowing rate must growing in the curve");
                 return err!(LendingError::InvalidBorrowRateCurvePoint);
             }
+            // Once flat, the borrow rate must stay flat up to the last point
+            if pt.borrow_rate_bps == last_pt.borrow_rate_bps {
+                flatlined = true;
+            } else if flatlined {
+                xmsg!(
+                    "Borrowing rate curve flatlines mid-range and grows again at utilization rate {}",
+                    pt.utilization_rate_bps
+                );
+                return err!(LendingError::InvalidBorrowRateCurvePoint);
+            }
             last_pt = *pt;
         }
         Ok(())
@@ -215,6 +238,35 @@ impl BorrowRateCurve {
         Self::from_points(points).unwrap()
     }
 
+    /// Classic two-slope model: the rate grows linearly from `base_bps` to
+    /// `rate_at_optimal_bps` up to the optimal utilization, then steeply to `max_rate_bps`
+    pub fn from_kinked(
+        base_bps: u32,
+        optimal_util_bps: u32,
+        rate_at_optimal_bps: u32,
+        max_rate_bps: u32,
+    ) -> Result<Self> {
+        if optimal_util_bps > MAX_UTILIZATION_RATE_BPS {
+            xmsg!(
+                "Optimal utilization rate {} is above 100%",
+                optimal_util_bps
+            );
+            return err!(LendingError::InvalidBorrowRateCurvePoint);
+        }
+
+        let base = CurvePoint::new(0, base_bps);
+        let optimal = CurvePoint::new(optimal_util_bps, rate_at_optimal_bps);
+        let max = CurvePoint::new(MAX_UTILIZATION_RATE_BPS, max_rate_bps);
+
+        if optimal_util_bps == 0 {
+            Self::from_points(&[CurvePoint::new(0, rate_at_optimal_bps), max])
+        } else if optimal_util_bps == MAX_UTILIZATION_RATE_BPS {
+            Self::from_points(&[base, optimal])
+        } else {
+            Self::from_points(&[base, optimal, max])
+        }
+    }
+
     pub fn get_borrow_rate(&self, utilization_rate: Fraction) -> Result<Fraction> {
         let utilization_rate = if utilization_rate > Fraction::ONE {
             xmsg!(
@@ -9,6 +9,13 @@ pub fn emergency_mode_disabled(lending_market: &AccountLoader<LendingMarket>) ->
     Ok(())
 }
 
+pub fn emergency_mode_enabled(lending_market: &AccountLoader<LendingMarket>) -> Result<()> {
+    if lending_market.load()?.emergency_mode == 0 {
+        return err!(LendingError::EmergencyModeNotEnabled);
+    }
+    Ok(())
+}
+
 pub fn check_remaining_accounts<T>(ctx: &Context<T>) -> Result<()>
 where
     T: Bumps,
@@ -16,8 +16,12 @@ mod uint_types {
 
 pub use uint_types::{U128, U256};
 
+/// Scale of every `_sf` field, the decimal value is the raw `u128` divided by it
 pub const FRACTION_ONE_SCALED: u128 = Fraction::ONE.to_bits();
 
+/// Most decimals `to_decimal_string` renders, enough to round-trip the 60 fractional bits
+pub const FRACTION_MAX_DECIMALS: u32 = 20;
+
 pub fn pow_fraction(fraction: Fraction, power: u32) -> Option<Fraction> {
     if power == 0 {
         return Some(Fraction::ONE);
@@ -67,10 +71,27 @@ pub trait FractionExtra {
     fn to_ceil<Dst: FromFixed>(&self) -> Dst;
     fn to_round<Dst: FromFixed>(&self) -> Dst;
 
+    /// Rounds an amount the protocol pays out, down so the dust stays in the reserve
+    fn to_floor_protocol<Dst: FromFixed>(&self) -> Dst;
+    /// Rounds an amount owed to the protocol, up so the dust is never forgiven
+    fn to_ceil_protocol<Dst: FromFixed>(&self) -> Dst;
+
+    fn checked_to_floor<Dst: FromFixed>(&self) -> Option<Dst>;
+    fn checked_to_ceil<Dst: FromFixed>(&self) -> Option<Dst>;
+    fn checked_to_round<Dst: FromFixed>(&self) -> Option<Dst>;
+
     fn to_sf(&self) -> u128;
     fn from_sf(sf: u128) -> Self;
 
     fn to_display(&self) -> FractionDisplay;
+
+    /// Canonical decimal form of a scaled fraction, e.g. `"1.250000"` for 1.25 with 6 decimals.
+    /// Rounded half up at `decimals`, which is capped at `FRACTION_MAX_DECIMALS`
+    fn to_decimal_string(&self, decimals: u32) -> String;
+    /// Parses the `to_decimal_string` form back, rounding to the nearest representable fraction
+    fn from_decimal_string(decimal: &str) -> Option<Self>
+    where
+        Self: std::marker::Sized;
 }
 
 impl FractionExtra for Fraction {
@@ -119,6 +140,31 @@ impl FractionExtra for Fraction {
         self.round().to_num()
     }
 
+    #[inline]
+    fn to_floor_protocol<Dst: FromFixed>(&self) -> Dst {
+        self.to_floor()
+    }
+
+    #[inline]
+    fn to_ceil_protocol<Dst: FromFixed>(&self) -> Dst {
+        self.to_ceil()
+    }
+
+    #[inline]
+    fn checked_to_floor<Dst: FromFixed>(&self) -> Option<Dst> {
+        self.floor().checked_to_num()
+    }
+
+    #[inline]
+    fn checked_to_ceil<Dst: FromFixed>(&self) -> Option<Dst> {
+        self.checked_ceil()?.checked_to_num()
+    }
+
+    #[inline]
+    fn checked_to_round<Dst: FromFixed>(&self) -> Option<Dst> {
+        self.checked_round()?.checked_to_num()
+    }
+
     #[inline]
     fn to_sf(&self) -> u128 {
         self.to_bits()
@@ -133,6 +179,51 @@ impl FractionExtra for Fraction {
     fn to_display(&self) -> FractionDisplay {
         FractionDisplay(self)
     }
+
+    fn to_decimal_string(&self, decimals: u32) -> String {
+        const FRAC_MASK: u128 = (1 << Fraction::FRAC_NBITS) - 1;
+
+        let decimals = decimals.min(FRACTION_MAX_DECIMALS);
+        let sf = self.to_bits();
+        let mut int_part = sf >> Fraction::FRAC_NBITS;
+
+        // 10^20 * 2^60 stays below 2^127
+        let pow = 10u128.pow(decimals);
+        let half = 1u128 << (Fraction::FRAC_NBITS - 1);
+        let mut frac_part = ((sf & FRAC_MASK) * pow + half) >> Fraction::FRAC_NBITS;
+        if frac_part == pow {
+            int_part += 1;
+            frac_part = 0;
+        }
+
+        if decimals == 0 {
+            int_part.to_string()
+        } else {
+            format!("{int_part}.{frac_part:0>width$}", width = decimals as usize)
+        }
+    }
+
+    fn from_decimal_string(decimal: &str) -> Option<Self> {
+        let (int_str, frac_str) = decimal.split_once('.').unwrap_or((decimal, ""));
+        if int_str.is_empty() || !int_str.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        if !frac_str.bytes().all(|b| b.is_ascii_digit()) || frac_str.len() > 38 {
+            return None;
+        }
+
+        let int_part = Fraction::checked_from_num(int_str.parse::<u128>().ok()?)?;
+        if frac_str.is_empty() {
+            return Some(int_part);
+        }
+
+        let pow = U256::from(10u128.pow(frac_str.len() as u32));
+        let digits = U256::from(frac_str.parse::<u128>().ok()?);
+        let frac_sf: u128 = (((digits << Fraction::FRAC_NBITS) + pow / 2) / pow)
+            .try_into()
+            .ok()?;
+        int_part.checked_add(Fraction::from_bits(frac_sf))
+    }
 }
 
 pub fn to_sf<Src: ToFixed>(src: Src) -> u128 {
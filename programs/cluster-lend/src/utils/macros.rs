@@ -32,7 +32,7 @@ macro_rules! check_refresh_ixs {
         $crate::utils::check_refresh(
             &$ctx.accounts.instruction_sysvar_account,
             &[($ctx.accounts.$reserve.to_account_info().key(), &_reserve)],
-            &$ctx.accounts.obligation.to_account_info().key(),
+            &[$ctx.accounts.obligation.to_account_info().key()],
         )?;
     }};
     ($ctx:expr, $reserve_one:ident, $reserve_two:ident) => {{
@@ -52,7 +52,7 @@ macro_rules! check_refresh_ixs {
                         &_reserve_one,
                     ),
                 ],
-                &$ctx.accounts.obligation.to_account_info().key(),
+                &[$ctx.accounts.obligation.to_account_info().key()],
             )?;
         } else {
             $crate::utils::check_refresh(
@@ -67,7 +67,7 @@ macro_rules! check_refresh_ixs {
                         &_reserve_two,
                     ),
                 ],
-                &$ctx.accounts.obligation.to_account_info().key(),
+                &[$ctx.accounts.obligation.to_account_info().key()],
             )?;
         }
     }};
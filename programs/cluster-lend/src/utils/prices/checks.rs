@@ -3,7 +3,7 @@ use anchor_lang::{prelude::*, solana_program::clock};
 use crate::{
     constants::FULL_BPS,
     errors::LendingError,
-    state::{PriceStatusFlags, TokenInfo},
+    state::{PriceAgeContext, PriceStatusFlags, TokenInfo},
     utils::Fraction, xmsg,
 };
 
@@ -14,7 +14,10 @@ pub(super) fn get_validated_price(
     token_info: &TokenInfo,
     unix_timestamp: clock::UnixTimestamp,
 ) -> Option<GetPriceResult> {
-    let unix_timestamp = u64::try_from(unix_timestamp).unwrap();
+    let Ok(unix_timestamp) = u64::try_from(unix_timestamp) else {
+        xmsg!("Negative timestamp {}", unix_timestamp);
+        return None;
+    };
     xmsg!("unix_timestamp: {}", unix_timestamp);
 
     let TimestampedPriceWithTwap { price, twap } = price_and_twap;
@@ -34,14 +37,16 @@ pub(super) fn get_validated_price(
     };
     xmsg!("price: {:?}", price.timestamp);
 
-    match check_price_age(
-        price.timestamp,
-        token_info.max_age_price_seconds,
-        unix_timestamp,
-    ) {
-        Ok(()) => price_status.set(PriceStatusFlags::PRICE_AGE_CHECKED, true),
-        Err(e) => {
-            xmsg!("Price is too old token=[{price_label}], {e:?}",);
+    for context in [PriceAgeContext::Standard, PriceAgeContext::Liquidation] {
+        match check_price_age(
+            price.timestamp,
+            token_info.price_max_age(context),
+            unix_timestamp,
+        ) {
+            Ok(()) => price_status.set(context.price_age_checked_flag(), true),
+            Err(e) => {
+                xmsg!("Price is too old for {context:?} token=[{price_label}], {e:?}",);
+            }
         }
     }
 
@@ -76,8 +81,13 @@ pub(super) fn get_validated_price(
         price_status.set(PriceStatusFlags::TWAP_AGE_CHECKED, true);
     }
 
+    let pinned_price_dec = token_info.pin_price(price_dec);
+    if pinned_price_dec != price_dec {
+        xmsg!("Price is pinned token=[{price_label}] price={price_dec} pinned={pinned_price_dec}",);
+    }
+
     Some(GetPriceResult {
-        price: price_dec,
+        price: pinned_price_dec,
         timestamp: price.timestamp,
         status: price_status,
     })
@@ -121,3 +131,27 @@ fn check_twap_in_tolerance(price: Fraction, twap: Fraction, token_info: &TokenIn
     }
     Ok(())
 }
+
+pub(super) fn check_fallback_in_tolerance(
+    price: Fraction,
+    fallback_price: Fraction,
+    token_info: &TokenInfo,
+) -> Result<()> {
+    let acceptable_tolerance_bps = token_info.max_fallback_divergence_bps;
+    if acceptable_tolerance_bps == 0 {
+        return Ok(());
+    }
+
+    if !is_within_tolerance(price, fallback_price, acceptable_tolerance_bps) {
+        let token_span = token_info.symbol();
+        xmsg!(
+            "Price is too far from the fallback price \
+              token={token_span} \
+              price={price} \
+              fallback_price={fallback_price} \
+              tolerance_bps={acceptable_tolerance_bps}",
+        );
+        return Err(LendingError::PriceTooDivergentFromFallback.into());
+    }
+    Ok(())
+}
@@ -7,7 +7,9 @@ use anchor_lang::{prelude::*, solana_program::clock};
 use types::TimestampedPrice;
 
 use self::{
-    checks::get_validated_price, pyth::get_pyth_price_and_twap, types::TimestampedPriceWithTwap,
+    checks::{check_fallback_in_tolerance, get_validated_price},
+    pyth::get_pyth_price_and_twap,
+    types::TimestampedPriceWithTwap,
 };
 use crate::{
     errors::LendingError,
@@ -15,10 +17,6 @@ use crate::{
     utils::Fraction, xmsg,
 };
 
-const MAX_CONFIDENCE_PERCENTAGE: u64 = 2u64;
-
-const CONFIDENCE_FACTOR: u64 = 100 / MAX_CONFIDENCE_PERCENTAGE;
-
 #[derive(Debug, Clone)]
 pub struct GetPriceResult {
     pub price: Fraction,
@@ -26,14 +24,116 @@ pub struct GetPriceResult {
     pub status: PriceStatusFlags,
 }
 
+impl GetPriceResult {
+    fn is_fresh(&self) -> bool {
+        self.status.contains(PriceStatusFlags::PRICE_AGE_CHECKED)
+    }
+}
+
 pub fn get_price(
     token_info: &TokenInfo,
     pyth_price_account_info: Option<&AccountInfo>,
+    fallback_price_account_info: Option<&AccountInfo>,
     unix_timestamp: clock::UnixTimestamp,
 ) -> Result<Option<GetPriceResult>> {
-    let price = get_most_recent_price_and_twap(token_info, pyth_price_account_info)?;
+    if token_info.is_fixed_price() {
+        return Ok(get_fixed_price(token_info, unix_timestamp));
+    }
+
+    // pushed prices are written by `push_reserve_price` and age like any saved price
+    if token_info.is_pushed_price() {
+        return Ok(None);
+    }
+
+    let fallback_price = if token_info.is_fallback_enabled() {
+        fallback_price_account_info
+            .and_then(|a| {
+                get_pyth_price_and_twap(a, token_info.effective_max_confidence_pct()).ok()
+            })
+            .and_then(|price| get_validated_price(price, token_info, unix_timestamp))
+    } else {
+        None
+    };
+
+    let primary_price = match get_most_recent_price_and_twap(token_info, pyth_price_account_info) {
+        Ok(price) => get_validated_price(price, token_info, unix_timestamp),
+        Err(e) if fallback_price.is_some() => {
+            xmsg!("Primary price is not available, {e:?}");
+            None
+        }
+        Err(e) => return Err(e),
+    };
+
+    Ok(select_price(token_info, primary_price, fallback_price))
+}
 
-    Ok(get_validated_price(price, token_info, unix_timestamp))
+/// Keeps the primary price unless it is stale or missing and the fallback one is fresh. When
+/// both are fresh they must agree for `FALLBACK_DIVERGENCE_CHECKED` to be set
+fn select_price(
+    token_info: &TokenInfo,
+    primary_price: Option<GetPriceResult>,
+    fallback_price: Option<GetPriceResult>,
+) -> Option<GetPriceResult> {
+    let is_primary_fresh = primary_price.as_ref().is_some_and(GetPriceResult::is_fresh);
+    let is_fallback_fresh = fallback_price
+        .as_ref()
+        .is_some_and(GetPriceResult::is_fresh);
+
+    let mut price = match (primary_price, fallback_price) {
+        (Some(primary), Some(fallback)) if is_primary_fresh && is_fallback_fresh => {
+            if let Err(e) = check_fallback_in_tolerance(primary.price, fallback.price, token_info) {
+                xmsg!(
+                    "Fallback price check failed token=[{}]: {e:?}",
+                    token_info.symbol()
+                );
+                return Some(primary);
+            }
+            primary
+        }
+        (_, Some(mut fallback)) if is_fallback_fresh => {
+            xmsg!(
+                "Primary price is stale or missing, using the fallback price token=[{}]",
+                token_info.symbol()
+            );
+            fallback
+                .status
+                .set(PriceStatusFlags::FALLBACK_ORACLE_USED, true);
+            fallback
+        }
+        (Some(primary), _) => primary,
+        (None, fallback) => fallback?,
+    };
+
+    price
+        .status
+        .set(PriceStatusFlags::FALLBACK_DIVERGENCE_CHECKED, true);
+    Some(price)
+}
+
+/// Loads a pyth feed as a refresh would and checks that its price is non-zero and within the
+/// confidence bound, without looking at its age
+pub fn validate_pyth_price_account(
+    pyth_price_account_info: &AccountInfo,
+    max_confidence_pct: u64,
+) -> Result<()> {
+    get_pyth_price_and_twap(pyth_price_account_info, max_confidence_pct).map(|_| ())
+}
+
+/// A fixed price never goes stale, it is reported as fresh with every check passed
+fn get_fixed_price(
+    token_info: &TokenInfo,
+    unix_timestamp: clock::UnixTimestamp,
+) -> Option<GetPriceResult> {
+    let Ok(timestamp) = u64::try_from(unix_timestamp) else {
+        xmsg!("Negative timestamp {}", unix_timestamp);
+        return None;
+    };
+
+    Some(GetPriceResult {
+        price: token_info.fixed_price(),
+        timestamp,
+        status: PriceStatusFlags::ALL_CHECKS,
+    })
 }
 
 fn get_most_recent_price_and_twap(
@@ -41,7 +141,9 @@ fn get_most_recent_price_and_twap(
     pyth_price_account_info: Option<&AccountInfo>,
 ) -> Result<TimestampedPriceWithTwap> {
     let pyth_price = if token_info.pyth_configuration.is_enabled() {
-        pyth_price_account_info.and_then(|a| get_pyth_price_and_twap(a).ok())
+        pyth_price_account_info.and_then(|a| {
+            get_pyth_price_and_twap(a, token_info.effective_max_confidence_pct()).ok()
+        })
     } else {
         None
     };
@@ -12,6 +12,7 @@ use super::{
 
 pub(super) fn get_pyth_price_and_twap(
     pyth_price_info: &AccountInfo,
+    max_confidence_pct: u64,
 ) -> Result<TimestampedPriceWithTwap> {
     let price_feed = SolanaPriceAccount::account_info_to_feed(pyth_price_info).map_err(|e| {
         xmsg!("Error loading price pyth feed: {:?}", e);
@@ -21,50 +22,66 @@ pub(super) fn get_pyth_price_and_twap(
     let price = price_feed.get_price_unchecked();
     let twap = price_feed.get_ema_price_unchecked();
 
-    validate_pyth_confidence(&price, super::CONFIDENCE_FACTOR)?;
+    validate_pyth_confidence(&price, max_confidence_pct)?;
 
     Ok(TimestampedPriceWithTwap {
-        price: price.into(),
-        twap: Some(twap.into()),
+        price: price.try_into()?,
+        twap: Some(twap.try_into()?),
     })
 }
 
 pub(super) fn validate_pyth_confidence(
     pyth_price: &PythPrice,
-    oracle_confidence_factor: u64,
+    max_confidence_pct: u64,
 ) -> Result<()> {
-    let price = u64::try_from(pyth_price.price).unwrap();
+    let price = u64::try_from(pyth_price.price).map_err(|_| {
+        xmsg!("Negative pyth price {}", pyth_price.price);
+        error!(LendingError::PriceNotValid)
+    })?;
     if price == 0 {
         return err!(LendingError::PriceIsZero);
     }
     let conf: u64 = pyth_price.conf;
-    let conf_50x: u64 = conf.checked_mul(oracle_confidence_factor).unwrap();
-    if conf_50x > price {
+    let conf_pct = u128::from(conf) * 100;
+    let max_conf_pct = u128::from(price) * u128::from(max_confidence_pct);
+    if conf_pct > max_conf_pct {
         xmsg!(
-            "Confidence interval check failed on pyth account {} {} {}",
+            "Confidence interval check failed on pyth account {} {} {}%",
             conf,
             price,
-            oracle_confidence_factor,
+            max_confidence_pct,
         );
         return err!(LendingError::PriceConfidenceTooWide);
     };
     Ok(())
 }
 
-impl From<PythPrice> for TimestampedPrice {
-    fn from(pyth_price: PythPrice) -> Self {
-        let value = u64::try_from(pyth_price.price).unwrap();
-        let exp = pyth_price.expo.checked_abs().unwrap() as u32;
+impl TryFrom<PythPrice> for TimestampedPrice {
+    type Error = anchor_lang::error::Error;
+
+    fn try_from(pyth_price: PythPrice) -> Result<Self> {
+        let value = u64::try_from(pyth_price.price).map_err(|_| {
+            xmsg!("Negative pyth price {}", pyth_price.price);
+            error!(LendingError::PriceNotValid)
+        })?;
+        let exp = pyth_price
+            .expo
+            .checked_abs()
+            .and_then(|exp| u32::try_from(exp).ok())
+            .ok_or_else(|| error!(LendingError::PriceNotValid))?;
 
         let price = Price { value, exp };
 
-        let timestamp = pyth_price.publish_time.try_into().unwrap();
+        let timestamp = pyth_price
+            .publish_time
+            .try_into()
+            .map_err(|_| error!(LendingError::IntegerOverflow))?;
 
-        let price_load = Box::new(move || Ok(utils::price_to_fraction(price)));
+        let price_load = Box::new(move || utils::price_to_fraction(price));
 
-        TimestampedPrice {
+        Ok(TimestampedPrice {
             price_load,
             timestamp,
-        }
+        })
     }
 }
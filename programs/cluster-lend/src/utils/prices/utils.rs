@@ -1,22 +1,31 @@
+use anchor_lang::prelude::*;
+
 use super::types::Price;
-use crate::utils::{BigFraction, Fraction, U128, U256};
+use crate::{
+    errors::LendingError,
+    utils::{BigFraction, Fraction, U128, U256},
+    xmsg,
+};
 
-pub(crate) fn price_to_fraction<T>(price: Price<T>) -> Fraction
+pub(crate) fn price_to_fraction<T>(price: Price<T>) -> Result<Fraction>
 where
     T: Into<U128> + Copy,
 {
     let Price { value, exp } = price;
     let value: U256 = value.into().into();
-    let decimal = ten_pow(exp);
+    let decimal = ten_pow(exp).ok_or_else(|| {
+        xmsg!("Unsupported price exponent: {}", exp);
+        error!(LendingError::PriceNotValid)
+    })?;
 
     let value_bf = BigFraction::from_num(value);
     let price_bf = value_bf / decimal;
     price_bf
         .try_into()
-        .expect("Failed to convert Price stored on BigFraction to Fraction")
+        .map_err(|_| error!(LendingError::IntegerOverflow))
 }
 
-fn ten_pow(exponent: u32) -> U128 {
+fn ten_pow(exponent: u32) -> Option<U128> {
     let value: u128 = match exponent {
         30 => 1_000_000_000_000_000_000_000_000_000_000,
         29 => 100_000_000_000_000_000_000_000_000_000,
@@ -49,8 +58,8 @@ fn ten_pow(exponent: u32) -> U128 {
         2 => 100,
         1 => 10,
         0 => 1,
-        _ => panic!("no support for exponent: {exponent}"),
+        _ => return None,
     };
 
-    U128::from(value)
+    Some(U128::from(value))
 }
@@ -1,7 +1,7 @@
 use anchor_lang::{prelude::*, solana_program::log::sol_log_compute_units, Discriminator};
 
 use crate::{
-    errors::LendingError, instruction::{RefreshObligation, RefreshReserve}, lending_market::ix_utils::{BpfInstructionLoader, InstructionLoader}, xmsg, Reserve
+    errors::LendingError, instruction::{ForceRefreshReserve, RefreshObligation, RefreshReserve}, lending_market::ix_utils::{BpfInstructionLoader, InstructionLoader}, xmsg, Reserve
 };
 
 #[derive(Debug, Clone)]
@@ -23,12 +23,19 @@ impl RequiredIx {
             RequiredIxType::RefreshObligation => RefreshObligation::DISCRIMINATOR,
         }
     }
+
+    /// A forced reserve refresh stands in for a plain one
+    pub fn matches(&self, discriminator: [u8; 8]) -> bool {
+        discriminator == self.discriminator()
+            || (matches!(self.kind, RequiredIxType::RefreshReserve)
+                && discriminator == ForceRefreshReserve::DISCRIMINATOR)
+    }
 }
 
 pub fn check_refresh(
     instruction_sysvar_account_info: &AccountInfo,
     reserves: &[(Pubkey, &Reserve)],
-    obligation_address: &Pubkey,
+    obligation_addresses: &[Pubkey],
 ) -> Result<()> {
     xmsg!("Beginning check_refresh");
     sol_log_compute_units();
@@ -69,7 +76,7 @@ pub fn check_refresh(
 
             require_keys_eq!(ix.program_id, crate::id());
 
-            let ix_discriminator_matches = ix_discriminator == required_ix.discriminator();
+            let ix_discriminator_matches = required_ix.matches(ix_discriminator);
             if !ix_discriminator_matches {
                 for (i, ix) in required_ixns.iter().enumerate() {
                     xmsg!("Required ix: {} {:?}", i, ix);
@@ -101,7 +108,8 @@ pub fn check_refresh(
         reserves.len()
     };
 
-    let mut required_pre_ixs = Vec::with_capacity(refresh_reserve_ixs + 1 + refresh_reserve_ixs);
+    let mut required_pre_ixs =
+        Vec::with_capacity(refresh_reserve_ixs + obligation_addresses.len() + refresh_reserve_ixs);
     let required_post_ixs = Vec::with_capacity(refresh_reserve_ixs);
     for reserve in reserves.iter().take(refresh_reserve_ixs) {
         required_pre_ixs.push(RequiredIx {
@@ -110,10 +118,12 @@ pub fn check_refresh(
         });
     }
 
-    required_pre_ixs.push(RequiredIx {
-        kind: RequiredIxType::RefreshObligation,
-        accounts: vec![(*obligation_address, 1)],
-    });
+    for obligation_address in obligation_addresses {
+        required_pre_ixs.push(RequiredIx {
+            kind: RequiredIxType::RefreshObligation,
+            accounts: vec![(*obligation_address, 1)],
+        });
+    }
 
     required_pre_ixs.reverse();
     check_ixns(required_pre_ixs, AppendedIxType::PreIxs)?;
@@ -133,6 +143,7 @@ enum AppendedIxType {
 fn _discriminator_to_ix(discriminator: [u8; 8]) -> &'static str {
     match discriminator {
         x if x == RefreshReserve::discriminator() => "RefreshReserve",
+        x if x == ForceRefreshReserve::discriminator() => "ForceRefreshReserve",
         x if x == RefreshObligation::discriminator() => "RefreshObligation",
         _ => "unknown",
     }
@@ -1,8 +1,12 @@
 pub const LENDING_MARKET_AUTH: &[u8] = b"lma";
 pub const RESERVE_LIQ_SUPPLY: &[u8] = b"reserve_liq_supply";
 pub const FEE_RECEIVER: &[u8] = b"fee_receiver";
+pub const INSURANCE_VAULT: &[u8] = b"insurance_vault";
 pub const RESERVE_COLL_MINT: &[u8] = b"reserve_coll_mint";
 pub const RESERVE_COLL_SUPPLY: &[u8] = b"reserve_coll_supply";
+pub const LIQUIDATION_BOND: &[u8] = b"liquidation_bond";
+pub const RESERVE_CONFIG_TEMPLATE: &[u8] = b"reserve_config_template";
+pub const OBLIGATION_ALLOWLIST: &[u8] = b"obligation_allowlist";
 
 pub mod pda {
     use anchor_lang::prelude::Pubkey;
@@ -27,6 +31,7 @@ pub mod pda {
         pub collateral_ctoken_mint: Pubkey,
         pub collateral_supply_vault: Pubkey,
         pub fee_vault: Pubkey,
+        pub insurance_vault: Pubkey,
     }
 
     pub fn init_reserve_pdas(market: &Pubkey, mint: &Pubkey) -> InitReservePdas {
@@ -42,6 +47,10 @@ pub mod pda {
             &[FEE_RECEIVER, market.as_ref(), mint.as_ref()],
             program_id,
         );
+        let (insurance_vault, _insurance_vault_bump) = Pubkey::find_program_address(
+            &[INSURANCE_VAULT, market.as_ref(), mint.as_ref()],
+            program_id,
+        );
         let (liquidity_supply_vault, _liquidity_supply_vault_bump) = Pubkey::find_program_address(
             &[RESERVE_LIQ_SUPPLY, market.as_ref(), mint.as_ref()],
             program_id,
@@ -60,9 +69,41 @@ pub mod pda {
             collateral_ctoken_mint,
             collateral_supply_vault,
             fee_vault,
+            insurance_vault,
         }
     }
 
+    pub fn liquidation_bond(obligation: &Pubkey, liquidator: &Pubkey) -> Pubkey {
+        let (pda, _bump) = Pubkey::find_program_address(
+            &[LIQUIDATION_BOND, obligation.as_ref(), liquidator.as_ref()],
+            &ID,
+        );
+
+        pda
+    }
+
+    pub fn reserve_config_template(lending_market: &Pubkey, kind: u8) -> Pubkey {
+        let (pda, _bump) = Pubkey::find_program_address(
+            &[RESERVE_CONFIG_TEMPLATE, lending_market.as_ref(), &[kind]],
+            &ID,
+        );
+
+        pda
+    }
+
+    pub fn obligation_allowlist(lending_market: &Pubkey, owner: &Pubkey) -> Pubkey {
+        let (pda, _bump) = Pubkey::find_program_address(
+            &[
+                OBLIGATION_ALLOWLIST,
+                lending_market.as_ref(),
+                owner.as_ref(),
+            ],
+            &ID,
+        );
+
+        pda
+    }
+
     pub fn init_obligation_pda(
         owner: &Pubkey,
         market: &Pubkey,
@@ -210,6 +210,48 @@ pub fn repay_obligation_liquidity_transfer<'a>(
     Ok(())
 }
 
+pub fn donate_reserve_liquidity_transfer<'a>(
+    token_program: AccountInfo<'a>,
+    source_liquidity: AccountInfo<'a>,
+    reserve_liquidity: AccountInfo<'a>,
+    authority: AccountInfo<'a>,
+    liquidity_amount: u64,
+) -> Result<()> {
+    token::transfer(
+        CpiContext::new(
+            token_program,
+            anchor_spl::token::Transfer {
+                from: source_liquidity,
+                to: reserve_liquidity,
+                authority,
+            },
+        ),
+        liquidity_amount,
+    )?;
+
+    Ok(())
+}
+
+pub fn repay_obligation_liquidity_with_collateral_transfer<'a>(
+    token_program: AccountInfo<'a>,
+    reserve_collateral_mint: AccountInfo<'a>,
+    reserve_source_collateral: AccountInfo<'a>,
+    lending_market_authority: AccountInfo<'a>,
+    authority_signer_seeds: &[&[u8]],
+    collateral_amount: u64,
+) -> Result<()> {
+    spltoken::burn_with_signer(
+        reserve_collateral_mint,
+        reserve_source_collateral,
+        lending_market_authority,
+        token_program,
+        collateral_amount,
+        &[authority_signer_seeds],
+    )?;
+
+    Ok(())
+}
+
 pub fn borrow_obligation_liquidity_transfer<'a>(
     token_program: AccountInfo<'a>,
     reserve_liquidity: AccountInfo<'a>,
@@ -303,3 +345,51 @@ pub fn withdraw_fees_from_reserve<'a>(
 
     Ok(())
 }
+
+pub fn cover_bad_debt_from_insurance_transfer<'a>(
+    token_program: AccountInfo<'a>,
+    insurance_vault: AccountInfo<'a>,
+    reserve_supply_liquidity: AccountInfo<'a>,
+    lending_market_authority: AccountInfo<'a>,
+    authority_signer_seeds: &[&[u8]],
+    cover_amount: u64,
+) -> Result<()> {
+    token::transfer(
+        CpiContext::new_with_signer(
+            token_program,
+            anchor_spl::token::Transfer {
+                from: insurance_vault,
+                to: reserve_supply_liquidity,
+                authority: lending_market_authority,
+            },
+            &[authority_signer_seeds],
+        ),
+        cover_amount,
+    )?;
+
+    Ok(())
+}
+
+pub fn claim_rewards_transfer<'a>(
+    token_program: AccountInfo<'a>,
+    reward_vault: AccountInfo<'a>,
+    user_destination_reward: AccountInfo<'a>,
+    lending_market_authority: AccountInfo<'a>,
+    authority_signer_seeds: &[&[u8]],
+    claim_amount: u64,
+) -> Result<()> {
+    token::transfer(
+        CpiContext::new_with_signer(
+            token_program,
+            anchor_spl::token::Transfer {
+                from: reward_vault,
+                to: user_destination_reward,
+                authority: lending_market_authority,
+            },
+            &[authority_signer_seeds],
+        ),
+        claim_amount,
+    )?;
+
+    Ok(())
+}
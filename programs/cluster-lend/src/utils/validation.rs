@@ -1,9 +1,23 @@
-use anchor_lang::prelude::{require, Result};
+use anchor_lang::prelude::{require, AccountInfo, Result};
 
-use crate::errors::LendingError;
+use crate::{
+    errors::LendingError,
+    state::{check_obligation_seeds, InitObligationArgs},
+};
 
 pub fn validate_numerical_bool(value: u8) -> Result<()> {
     let num_matches_boolean_values = matches!(value, 0 | 1);
     require!(num_matches_boolean_values, LendingError::InvalidFlag);
     Ok(())
 }
+
+/// Checks the args and seed accounts an obligation is initialized with, the id must be 0 and
+/// the seeds must match what the tag expects
+pub fn validate_init_obligation_args(
+    args: &InitObligationArgs,
+    seed1_account: &AccountInfo,
+    seed2_account: &AccountInfo,
+) -> Result<()> {
+    require!(args.id == 0, LendingError::InvalidObligationId);
+    check_obligation_seeds(args.tag, seed1_account, seed2_account)
+}
@@ -0,0 +1,256 @@
+#[cfg(test)]
+mod helpers;
+use std::rc::Rc;
+
+use anchor_lang::prelude::{AccountLoader, Clock};
+use cluster_lend::{
+    errors::LendingError,
+    lending_market::lending_operations::set_aggressive_mode,
+    utils::{pda, Fraction},
+    LendingMarket, Obligation, Reserve, UpdateConfigMode, UpdateLendingMarketMode,
+};
+use solana_program_test::*;
+
+use helpers::*;
+use solana_sdk::{signature::Keypair, signer::Signer};
+use spl::TokenAccountFixture;
+use test::{TestFixture, PYTH_USDC_FEED};
+
+#[tokio::test]
+async fn success_aggressive_mode_borrows_more_and_liquidates_earlier() {
+    let user = Keypair::new();
+
+    // setup market & reserve/obligation, the reserve has a 75% LTV and an 85% threshold
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (market_f, reserve_f, obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+    let reserve_pdas = pda::init_reserve_pdas(&market_f.key, &usdc_mint_f.key);
+
+    let mut max_ltv_boost_pct = [0; 72];
+    max_ltv_boost_pct[0] = 10;
+    let mut liquidation_buffer_pct = [0; 72];
+    liquidation_buffer_pct[0] = 2;
+    let mut cooldown_secs = [0; 72];
+    cooldown_secs[..8].copy_from_slice(&3_600u64.to_le_bytes());
+
+    let owner = test_f.payer_keypair();
+    test_f
+        .send_transaction(
+            &[
+                market_f.update_market_ix(
+                    UpdateLendingMarketMode::UpdateAggressiveModeMaxLtvBoostPct as u64,
+                    max_ltv_boost_pct,
+                ),
+                market_f.update_market_ix(
+                    UpdateLendingMarketMode::UpdateAggressiveModeLiquidationBufferPct as u64,
+                    liquidation_buffer_pct,
+                ),
+                market_f.update_market_ix(
+                    UpdateLendingMarketMode::UpdateAggressiveModeCooldownSecs as u64,
+                    cooldown_secs,
+                ),
+            ],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+
+    let deposit_amount = 100_000_000;
+    let user_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&user, deposit_amount)
+        .await;
+
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_time(120);
+
+    test_f
+        .send_transaction(
+            &[
+                obligation_f.deposit_liquidity_collateral_ix(
+                    deposit_amount,
+                    &reserve_f,
+                    user_liquidity_ata.key,
+                ),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key]),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    // above the 75% LTV
+    let r = test_f
+        .send_transaction(
+            &[
+                obligation_f.borrow_liquidity_ix(76_000_000, &reserve_f, user_liquidity_ata.key),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+            ],
+            &[&user],
+        )
+        .await;
+    assert!(r.is_err());
+
+    // above the market bound
+    let r = test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.set_aggressive_mode_ix(11, vec![reserve_f.key]),
+            ],
+            &[&user],
+        )
+        .await;
+    assert!(r.is_err());
+
+    // a 5% boost gives an 80% LTV, with the threshold tightened to 82%
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.set_aggressive_mode_ix(5, vec![reserve_f.key]),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    let obligation: Obligation = test_f.load_and_deserialize(&obligation_f.key).await;
+    assert_eq!(obligation.aggressive_ltv_boost_pct, 5);
+    assert_eq!(
+        Fraction::from_bits(obligation.allowed_borrow_value_sf)
+            .round()
+            .to_num::<u64>(),
+        80
+    );
+    assert_eq!(
+        Fraction::from_bits(obligation.unhealthy_borrow_value_sf)
+            .round()
+            .to_num::<u64>(),
+        82
+    );
+
+    let borrow_amount = 78_000_000;
+    test_f
+        .send_transaction(
+            &[
+                obligation_f.borrow_liquidity_ix(borrow_amount, &reserve_f, user_liquidity_ata.key),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    // the owner cannot back out during the cooldown
+    let r = test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.set_aggressive_mode_ix(0, vec![reserve_f.key, reserve_f.key]),
+            ],
+            &[&user],
+        )
+        .await;
+    assert!(r.is_err());
+
+    // at a 70% LTV and an 80% threshold the 78% LTV obligation would be healthy, but the
+    // aggressive 75% LTV leaves it only a 77% threshold
+    let mut loan_to_value_pct = [0; 32];
+    loan_to_value_pct[0] = 70;
+    let mut liquidation_threshold_pct = [0; 32];
+    liquidation_threshold_pct[0] = 80;
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.update_reserve_mode_ix(
+                    UpdateConfigMode::UpdateLoanToValuePct as u64,
+                    loan_to_value_pct,
+                ),
+                reserve_f.update_reserve_mode_ix(
+                    UpdateConfigMode::UpdateLiquidationThresholdPct as u64,
+                    liquidation_threshold_pct,
+                ),
+            ],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+
+    let liquidator = test_f.payer_keypair();
+    let liquidator_source_liquidity = usdc_mint_f
+        .create_token_account_and_mint_to(&liquidator, deposit_amount)
+        .await;
+    let liquidator_destination_collateral = TokenAccountFixture::new(
+        Rc::clone(&test_f.context),
+        &reserve_pdas.collateral_ctoken_mint,
+        &liquidator.pubkey(),
+    )
+    .await;
+    let liquidator_destination_liquidity = TokenAccountFixture::new(
+        Rc::clone(&test_f.context),
+        &usdc_mint_f.key,
+        &liquidator.pubkey(),
+    )
+    .await;
+
+    let liquidity_amount = 10_000_000;
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+                obligation_f.liquidate_ix(
+                    liquidity_amount,
+                    0,
+                    0,
+                    0,
+                    liquidator.pubkey(),
+                    reserve_f.key,
+                    reserve_pdas.liquidity_supply_vault,
+                    reserve_f.key,
+                    usdc_mint_f.key,
+                    liquidator_destination_collateral.key,
+                    liquidator_source_liquidity.key,
+                    liquidator_destination_liquidity.key,
+                ),
+            ],
+            &[&liquidator],
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        liquidator_source_liquidity.balance().await,
+        deposit_amount - liquidity_amount
+    );
+}
+
+#[test]
+fn first_aggressive_mode_change_skips_cooldown() {
+    let mut lending_market = LendingMarket::default();
+    lending_market.aggressive_mode_max_ltv_boost_pct = 10;
+    lending_market.aggressive_mode_cooldown_secs = 3_600;
+    let mut obligation = Obligation::default();
+    let no_reserves = std::iter::empty::<AccountLoader<Reserve>>;
+
+    // never changed before, well within a cooldown counted from the epoch
+    let mut clock = Clock {
+        unix_timestamp: 120,
+        ..Clock::default()
+    };
+    set_aggressive_mode(&mut obligation, &lending_market, &clock, 5, no_reserves()).unwrap();
+    assert_eq!(obligation.aggressive_ltv_boost_pct, 5);
+    assert_eq!(obligation.aggressive_mode_updated_at, 120);
+
+    // the following changes wait out the cooldown from the first one
+    clock.unix_timestamp = 120 + 3_599;
+    let err = set_aggressive_mode(&mut obligation, &lending_market, &clock, 0, no_reserves())
+        .unwrap_err();
+    assert_eq!(err, LendingError::AggressiveModeCooldown.into());
+
+    clock.unix_timestamp = 120 + 3_600;
+    set_aggressive_mode(&mut obligation, &lending_market, &clock, 0, no_reserves()).unwrap();
+    assert_eq!(obligation.aggressive_ltv_boost_pct, 0);
+}
@@ -0,0 +1,148 @@
+#[cfg(test)]
+mod helpers;
+use std::rc::Rc;
+
+use cluster_lend::{utils::pda, Obligation};
+use solana_program_test::*;
+
+use helpers::*;
+use solana_sdk::{signature::Keypair, signer::Signer};
+use spl::TokenAccountFixture;
+use test::{TestFixture, PYTH_USDC_FEED};
+
+#[tokio::test]
+async fn success_keeper_deleverages_only_down_to_target() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (market_f, reserve_f, obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+    let reserve_pdas = pda::init_reserve_pdas(&market_f.key, &usdc_mint_f.key);
+
+    let deposit_amount = 1_000_000;
+    let user_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&user, deposit_amount)
+        .await;
+
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_time(120);
+
+    // 70% LTV, healthy for regular liquidations
+    let borrow_amount = 700_000;
+    test_f
+        .send_transaction(
+            &[
+                obligation_f.deposit_liquidity_collateral_ix(
+                    deposit_amount,
+                    &reserve_f,
+                    user_liquidity_ata.key,
+                ),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key]),
+                obligation_f.borrow_liquidity_ix(borrow_amount, &reserve_f, user_liquidity_ata.key),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    let keeper = test_f.payer_keypair();
+    let keeper_source_liquidity = usdc_mint_f
+        .create_token_account_and_mint_to(&keeper, deposit_amount)
+        .await;
+    let keeper_destination_collateral = TokenAccountFixture::new(
+        Rc::clone(&test_f.context),
+        &reserve_pdas.collateral_ctoken_mint,
+        &keeper.pubkey(),
+    )
+    .await;
+    let keeper_destination_liquidity = TokenAccountFixture::new(
+        Rc::clone(&test_f.context),
+        &usdc_mint_f.key,
+        &keeper.pubkey(),
+    )
+    .await;
+    let deleverage_ix = |liquidity_amount| {
+        obligation_f.deleverage_to_target_ix(
+            liquidity_amount,
+            0,
+            keeper.pubkey(),
+            reserve_f.key,
+            reserve_pdas.liquidity_supply_vault,
+            reserve_f.key,
+            usdc_mint_f.key,
+            keeper_destination_collateral.key,
+            keeper_source_liquidity.key,
+            keeper_destination_liquidity.key,
+        )
+    };
+
+    // no target set
+    let r = test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+                deleverage_ix(100_000),
+            ],
+            &[&keeper],
+        )
+        .await;
+    assert!(r.is_err());
+
+    let r = test_f
+        .send_transaction(
+            &[obligation_f.set_autodeleverage_target_ix(100, 100)],
+            &[&user],
+        )
+        .await;
+    assert!(r.is_err());
+
+    // 60% target with a 1% bonus, below the reserves' 2% minimum liquidation bonus
+    test_f
+        .send_transaction(
+            &[obligation_f.set_autodeleverage_target_ix(60, 100)],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    let obligation: Obligation = test_f.load_and_deserialize(&obligation_f.key).await;
+    assert_eq!(obligation.autodeleverage_target_ltv_pct, 60);
+    assert_eq!(obligation.autodeleverage_bonus_bps, 100);
+
+    // the keeper asks for the whole debt but only repays down to the target:
+    // (700_000 - 60% * 1_000_000) / (1 - 60% * 1.01) = 253_807
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+                deleverage_ix(borrow_amount),
+            ],
+            &[&keeper],
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        keeper_source_liquidity.balance().await,
+        deposit_amount - 253_807
+    );
+
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+            ],
+            &[&keeper],
+        )
+        .await
+        .unwrap();
+
+    let obligation: Obligation = test_f.load_and_deserialize(&obligation_f.key).await;
+    let ltv = obligation.loan_to_value().to_num::<f64>();
+    assert!((ltv - 0.6).abs() < 0.000_01, "ltv {ltv}");
+}
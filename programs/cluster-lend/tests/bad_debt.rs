@@ -0,0 +1,74 @@
+#[cfg(test)]
+mod helpers;
+
+use cluster_lend::{
+    utils::{Fraction, FractionExtra},
+    RoundingDirection,
+};
+
+use helpers::*;
+use reserve::reserve_with_debt;
+
+#[test]
+fn forgive_debt_accumulates_bad_debt() {
+    let mut reserve = reserve_with_debt(1_000_000, 500_000);
+    assert_eq!(reserve.liquidity.accumulated_bad_debt(), Fraction::ZERO);
+
+    reserve
+        .liquidity
+        .forgive_debt(Fraction::from(100_000u64))
+        .unwrap();
+    assert_eq!(
+        reserve.liquidity.accumulated_bad_debt(),
+        Fraction::from(100_000u64)
+    );
+
+    reserve
+        .liquidity
+        .forgive_debt(Fraction::from(50_000u64))
+        .unwrap();
+    assert_eq!(
+        reserve.liquidity.accumulated_bad_debt(),
+        Fraction::from(150_000u64)
+    );
+    assert_eq!(reserve.liquidity.total_borrow(), Fraction::from(350_000u64));
+}
+
+#[test]
+fn forgive_debt_is_realized_by_lenders() {
+    let mut reserve = reserve_with_debt(1_000_000, 500_000);
+    let collateral_amount = reserve.collateral.mint_total_supply;
+
+    let exchange_rate = reserve.collateral_exchange_rate().unwrap();
+    assert_eq!(
+        exchange_rate
+            .collateral_to_liquidity(collateral_amount, RoundingDirection::Down)
+            .unwrap(),
+        1_000_000
+    );
+    assert_eq!(
+        reserve.liquidity.utilization_rate().unwrap(),
+        Fraction::from_percent(50)
+    );
+
+    reserve
+        .liquidity
+        .forgive_debt(Fraction::from(100_000u64))
+        .unwrap();
+
+    // the written off debt leaves the supply, so each cToken redeems for less
+    assert_eq!(
+        reserve.liquidity.total_supply().unwrap(),
+        Fraction::from(900_000u64)
+    );
+    let redeemable = reserve
+        .collateral_exchange_rate()
+        .unwrap()
+        .collateral_to_liquidity(collateral_amount, RoundingDirection::Down)
+        .unwrap();
+    assert!(redeemable.abs_diff(900_000) <= 1);
+    assert_eq!(
+        reserve.liquidity.utilization_rate().unwrap(),
+        Fraction::from(400_000u64) / Fraction::from(900_000u64)
+    );
+}
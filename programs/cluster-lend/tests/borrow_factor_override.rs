@@ -0,0 +1,106 @@
+#[cfg(test)]
+mod helpers;
+
+use cluster_lend::{Obligation, UpdateConfigMode};
+use solana_program_test::*;
+
+use helpers::*;
+use solana_sdk::signature::Keypair;
+use test::{TestFixture, PYTH_USDC_FEED};
+
+#[tokio::test]
+async fn success_borrow_factor_override() {
+    let user = Keypair::new();
+
+    // setup market & reserve/obligation
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (market_f, reserve_f, obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+
+    let deposit_amount = 1_000_000;
+    let user_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&user, deposit_amount)
+        .await;
+
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_time(120);
+
+    let mut borrow_factor_pct = [0; 32];
+    borrow_factor_pct[0] = 150;
+
+    let owner = test_f.payer_keypair();
+    test_f
+        .send_transaction(
+            &[reserve_f.update_reserve_mode_ix(
+                UpdateConfigMode::UpdateBorrowFactor as u64,
+                borrow_factor_pct,
+            )],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.deposit_liquidity_collateral_ix(
+                    deposit_amount,
+                    &reserve_f,
+                    user_liquidity_ata.key,
+                ),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key]),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    let borrow_amount = 400_000;
+    test_f
+        .send_transaction(
+            &[
+                obligation_f.borrow_liquidity_ix(borrow_amount, &reserve_f, user_liquidity_ata.key),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    let obligation: Obligation = test_f.load_and_deserialize(&obligation_f.key).await;
+    let market_value_sf = obligation.borrowed_assets_market_value_sf;
+    let adjusted_debt_value_sf = obligation.borrow_factor_adjusted_debt_value_sf;
+    assert!((adjusted_debt_value_sf * 100).abs_diff(market_value_sf * 150) <= 100);
+
+    // an override below 100% is rejected
+    let r = test_f
+        .send_transaction(
+            &[market_f.set_borrow_factor_override_ix(obligation_f.key, 50)],
+            &[&owner],
+        )
+        .await;
+    assert!(r.is_err());
+
+    test_f
+        .send_transaction(
+            &[
+                market_f.set_borrow_factor_override_ix(obligation_f.key, 110),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+            ],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+
+    let obligation: Obligation = test_f.load_and_deserialize(&obligation_f.key).await;
+    assert_eq!(obligation.borrow_factor_override_pct, 110);
+    assert_eq!(obligation.borrowed_assets_market_value_sf, market_value_sf);
+
+    let adjusted_debt_value_with_override_sf = obligation.borrow_factor_adjusted_debt_value_sf;
+    assert!(adjusted_debt_value_with_override_sf < adjusted_debt_value_sf);
+    assert!((adjusted_debt_value_with_override_sf * 100).abs_diff(market_value_sf * 110) <= 100);
+}
@@ -0,0 +1,140 @@
+use cluster_lend::{
+    errors::LendingError,
+    utils::{Fraction, FractionExtra},
+    FeeCalculation, ReserveConfig, ReserveFees,
+};
+
+const FLAT_BORROW_FEE: u64 = 500;
+
+fn fees(borrow_fee_pct: u64, flat_borrow_fee: u64) -> ReserveFees {
+    ReserveFees {
+        borrow_fee_sf: Fraction::from_percent(borrow_fee_pct).to_bits() as u64,
+        flat_borrow_fee,
+        ..ReserveFees::default()
+    }
+}
+
+#[test]
+fn flat_borrow_fee_is_added_to_percentage_fee() {
+    let percentage_only = fees(1, 0);
+    let with_flat_fee = fees(1, FLAT_BORROW_FEE);
+
+    let borrow_fee = |fees: &ReserveFees, amount: u64, fee_calculation| {
+        fees.calculate_borrow_fees(Fraction::from(amount), fee_calculation)
+            .unwrap()
+    };
+
+    // borrowing 100_000 on top of the fees
+    assert_eq!(
+        borrow_fee(&percentage_only, 100_000, FeeCalculation::Exclusive),
+        1_000
+    );
+    assert_eq!(
+        borrow_fee(&with_flat_fee, 100_000, FeeCalculation::Exclusive),
+        1_000 + FLAT_BORROW_FEE
+    );
+
+    // borrowing a total that includes the fees, leaving 100_000 to the borrower either way
+    assert_eq!(
+        borrow_fee(&percentage_only, 101_000, FeeCalculation::Inclusive),
+        1_000
+    );
+    assert_eq!(
+        borrow_fee(&with_flat_fee, 101_500, FeeCalculation::Inclusive),
+        1_000 + FLAT_BORROW_FEE
+    );
+
+    // no borrow, no fee
+    assert_eq!(borrow_fee(&with_flat_fee, 0, FeeCalculation::Exclusive), 0);
+
+    let flat_only = fees(0, FLAT_BORROW_FEE);
+    assert_eq!(
+        borrow_fee(&flat_only, 100_000, FeeCalculation::Exclusive),
+        FLAT_BORROW_FEE
+    );
+}
+
+#[test]
+fn flat_borrow_fee_must_be_below_borrow_amount() {
+    let with_flat_fee = fees(1, FLAT_BORROW_FEE);
+
+    for fee_calculation in [FeeCalculation::Exclusive, FeeCalculation::Inclusive] {
+        let err = with_flat_fee
+            .calculate_borrow_fees(Fraction::from(FLAT_BORROW_FEE), fee_calculation)
+            .unwrap_err();
+        assert_eq!(err, LendingError::BorrowTooSmall.into());
+    }
+
+    assert!(with_flat_fee
+        .calculate_borrow_fees(
+            Fraction::from(FLAT_BORROW_FEE + 10),
+            FeeCalculation::Exclusive
+        )
+        .is_ok());
+}
+
+#[test]
+fn fractional_fees_round_up_to_the_protocol() {
+    let one_pct = Fraction::from_percent(1).to_bits() as u64;
+
+    // 1% of 120 is 1.2
+    let borrow_fees = ReserveFees {
+        borrow_fee_sf: one_pct,
+        ..ReserveFees::default()
+    };
+    assert_eq!(
+        borrow_fees
+            .calculate_borrow_fees(Fraction::from(120u64), FeeCalculation::Exclusive)
+            .unwrap(),
+        2
+    );
+
+    let repay_fees = ReserveConfig {
+        repay_fee_sf: one_pct,
+        ..ReserveConfig::default()
+    };
+    assert_eq!(
+        repay_fees
+            .calculate_repay_fees(Fraction::from(120u64))
+            .unwrap(),
+        2
+    );
+
+    // whole fees are left as they are
+    assert_eq!(
+        repay_fees
+            .calculate_repay_fees(Fraction::from(100_000u64))
+            .unwrap(),
+        1_000
+    );
+}
+
+#[test]
+fn max_repay_amount_leaves_room_for_the_repay_fee() {
+    let config = ReserveConfig {
+        repay_fee_sf: Fraction::from_percent(1).to_bits() as u64,
+        ..ReserveConfig::default()
+    };
+
+    for balance in [0, 1, 2, 101, 1_000, 300_000, 1_234_567] {
+        let amount = config.max_repay_amount_before_fees(balance).unwrap();
+        let amount_with_fees =
+            |amount: u64| amount + config.calculate_repay_fees(Fraction::from(amount)).unwrap();
+        assert!(amount_with_fees(amount) <= balance);
+        assert!(amount == balance || amount_with_fees(amount + 1) > balance);
+    }
+
+    // 297_029 plus its 2_971 fee uses up the whole balance
+    assert_eq!(
+        config.max_repay_amount_before_fees(300_000).unwrap(),
+        297_029
+    );
+
+    // without a fee the whole balance goes to the debt
+    assert_eq!(
+        ReserveConfig::default()
+            .max_repay_amount_before_fees(300_000)
+            .unwrap(),
+        300_000
+    );
+}
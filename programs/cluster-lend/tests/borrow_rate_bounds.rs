@@ -0,0 +1,93 @@
+#[cfg(test)]
+mod helpers;
+
+use cluster_lend::{errors::LendingError, Reserve, UpdateConfigMode};
+use solana_program_test::*;
+
+use helpers::*;
+use solana_sdk::signature::Keypair;
+use test::TestFixture;
+
+fn borrow_rate_bps_value(bps: u64) -> [u8; 32] {
+    let mut value = [0; 32];
+    value[..8].copy_from_slice(&bps.to_le_bytes());
+    value
+}
+
+#[tokio::test]
+async fn fail_min_borrow_rate_above_max() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (_market_f, reserve_f, _obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+    let owner = test_f.payer_keypair();
+
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.update_reserve_mode_ix(
+                    UpdateConfigMode::UpdateMaxBorrowRateBps as u64,
+                    borrow_rate_bps_value(2_000),
+                ),
+                reserve_f.update_reserve_mode_ix(
+                    UpdateConfigMode::UpdateMinBorrowRateBps as u64,
+                    borrow_rate_bps_value(500),
+                ),
+            ],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+
+    let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+    assert_eq!(reserve.config.max_borrow_rate_bps, 2_000);
+    assert_eq!(reserve.config.min_borrow_rate_bps, 500);
+
+    let err = test_f
+        .send_transaction(
+            &[reserve_f.update_reserve_mode_ix(
+                UpdateConfigMode::UpdateMinBorrowRateBps as u64,
+                borrow_rate_bps_value(2_001),
+            )],
+            &[&owner],
+        )
+        .await
+        .unwrap_err();
+    assert_custom_error!(err, LendingError::InvalidConfig);
+
+    // the ceiling can't be lowered under the floor either
+    let err = test_f
+        .send_transaction(
+            &[reserve_f.update_reserve_mode_ix(
+                UpdateConfigMode::UpdateMaxBorrowRateBps as u64,
+                borrow_rate_bps_value(499),
+            )],
+            &[&owner],
+        )
+        .await
+        .unwrap_err();
+    assert_custom_error!(err, LendingError::InvalidConfig);
+
+    // an uncapped reserve accepts any floor
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.update_reserve_mode_ix(
+                    UpdateConfigMode::UpdateMaxBorrowRateBps as u64,
+                    borrow_rate_bps_value(0),
+                ),
+                reserve_f.update_reserve_mode_ix(
+                    UpdateConfigMode::UpdateMinBorrowRateBps as u64,
+                    borrow_rate_bps_value(3_000),
+                ),
+            ],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+
+    let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+    assert_eq!(reserve.config.max_borrow_rate_bps, 0);
+    assert_eq!(reserve.config.min_borrow_rate_bps, 3_000);
+}
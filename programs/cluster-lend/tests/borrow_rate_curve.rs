@@ -0,0 +1,179 @@
+use cluster_lend::{
+    errors::LendingError,
+    lending_market::lending_operations,
+    utils::{BorrowRateCurve, CurvePoint, Fraction, FractionExtra},
+    Reserve, UpdateConfigMode,
+};
+
+fn curve(points: &[(u32, u32)]) -> BorrowRateCurve {
+    let mut curve = BorrowRateCurve::default();
+    let last = *points.last().unwrap();
+    for (i, point) in curve.points.iter_mut().enumerate() {
+        let (utilization_rate_bps, borrow_rate_bps) = *points.get(i).unwrap_or(&last);
+        *point = CurvePoint::new(utilization_rate_bps, borrow_rate_bps);
+    }
+    curve
+}
+
+#[test]
+fn monotonic_curves_are_valid() {
+    curve(&[(0, 100), (5_000, 500), (8_000, 1_500), (10_000, 10_000)])
+        .validate()
+        .unwrap();
+    // a flat tail is fine, e.g. a capped rate
+    curve(&[(0, 0), (8_000, 2_000), (10_000, 2_000)])
+        .validate()
+        .unwrap();
+    // all 11 points used
+    curve(&[
+        (0, 0),
+        (1_000, 100),
+        (2_000, 200),
+        (3_000, 300),
+        (4_000, 400),
+        (5_000, 500),
+        (6_000, 600),
+        (7_000, 700),
+        (8_000, 800),
+        (9_000, 900),
+        (10_000, 5_000),
+    ])
+    .validate()
+    .unwrap();
+    BorrowRateCurve::new_flat(1_000).validate().unwrap();
+}
+
+#[test]
+fn malformed_curves_are_rejected() {
+    let malformed = [
+        // first point not at 0
+        curve(&[(100, 100), (10_000, 1_000)]),
+        // last point not at 10000
+        curve(&[(0, 100), (5_000, 1_000), (9_000, 2_000)]),
+        // decreasing borrow rate
+        curve(&[(0, 100), (5_000, 1_000), (10_000, 500)]),
+        // duplicate mid-range utilization
+        curve(&[(0, 100), (5_000, 500), (5_000, 1_000), (10_000, 2_000)]),
+        // decreasing utilization
+        curve(&[(0, 100), (6_000, 500), (4_000, 1_000), (10_000, 2_000)]),
+        // flatlines mid-range then grows again
+        curve(&[(0, 100), (4_000, 500), (7_000, 500), (10_000, 2_000)]),
+    ];
+    for curve in malformed {
+        let err = curve.validate().unwrap_err();
+        assert_eq!(err, LendingError::InvalidBorrowRateCurvePoint.into());
+    }
+
+    // padding points at 10000 must repeat the last point
+    let mut padded = curve(&[(0, 100), (5_000, 500), (10_000, 1_000)]);
+    padded.points[10].borrow_rate_bps = 2_000;
+    let err = padded.validate().unwrap_err();
+    assert_eq!(err, LendingError::InvalidBorrowRateCurvePoint.into());
+}
+
+/// Closed-form two-slope model, in bps
+fn kinked_rate_bps(base: u32, optimal_util: u32, rate_at_optimal: u32, max: u32, util: u32) -> f64 {
+    let (base, optimal_util, rate_at_optimal, max, util) = (
+        f64::from(base),
+        f64::from(optimal_util),
+        f64::from(rate_at_optimal),
+        f64::from(max),
+        f64::from(util),
+    );
+    if util <= optimal_util {
+        base + (rate_at_optimal - base) * util / optimal_util
+    } else {
+        rate_at_optimal
+            + (max - rate_at_optimal) * (util - optimal_util) / (10_000.0 - optimal_util)
+    }
+}
+
+#[test]
+fn kinked_curve_matches_closed_form() {
+    for (base, optimal_util, rate_at_optimal, max) in [
+        (200, 8_000, 1_000, 10_000),
+        (0, 7_000, 1_500, 30_000),
+        (50, 4_500, 333, 2_000),
+    ] {
+        let curve = BorrowRateCurve::from_kinked(base, optimal_util, rate_at_optimal, max).unwrap();
+        curve.validate().unwrap();
+
+        for util in [
+            0, 1, 2_000, 3_333, 4_500, 7_000, 8_000, 8_001, 9_000, 9_999, 10_000,
+        ] {
+            // to_bps rounds to whole bps, compare unrounded
+            let rate: f64 =
+                (curve.get_borrow_rate(Fraction::from_bps(util)).unwrap() * 10_000).to_num();
+            let expected = kinked_rate_bps(base, optimal_util, rate_at_optimal, max, util);
+            assert!(
+                (rate - expected).abs() < 1e-6,
+                "utilization {util}: got {rate} bps, expected {expected} bps"
+            );
+        }
+    }
+}
+
+#[test]
+fn kinked_curve_edge_cases() {
+    // the kink at either end collapses to a single slope
+    assert_eq!(
+        BorrowRateCurve::from_kinked(100, 0, 500, 2_000).unwrap(),
+        BorrowRateCurve::from_points(&[CurvePoint::new(0, 500), CurvePoint::new(10_000, 2_000)])
+            .unwrap()
+    );
+    assert_eq!(
+        BorrowRateCurve::from_kinked(100, 10_000, 500, 2_000).unwrap(),
+        BorrowRateCurve::from_points(&[CurvePoint::new(0, 100), CurvePoint::new(10_000, 500)])
+            .unwrap()
+    );
+
+    let invalid = [
+        // optimal utilization above 100%
+        (100, 10_001, 500, 2_000),
+        // rate decreasing past the kink
+        (100, 8_000, 500, 400),
+        // rate decreasing before the kink
+        (600, 8_000, 500, 2_000),
+        // flat first slope growing again after the kink
+        (500, 8_000, 500, 2_000),
+    ];
+    for (base, optimal_util, rate_at_optimal, max) in invalid {
+        let err =
+            BorrowRateCurve::from_kinked(base, optimal_util, rate_at_optimal, max).unwrap_err();
+        assert_eq!(err, LendingError::InvalidBorrowRateCurvePoint.into());
+    }
+}
+
+#[test]
+fn update_borrow_rate_curve_kinked_mode() {
+    let mut reserve = Reserve::default();
+    let mut value = [0; 32];
+    for (i, scalar) in [200u32, 8_000, 1_000, 10_000].iter().enumerate() {
+        value[i * 4..(i + 1) * 4].copy_from_slice(&scalar.to_le_bytes());
+    }
+
+    lending_operations::update_reserve_config(
+        &mut reserve,
+        UpdateConfigMode::UpdateBorrowRateCurveKinked,
+        &value,
+    )
+    .unwrap();
+    assert_eq!(
+        reserve.config.borrow_rate_curve,
+        BorrowRateCurve::from_kinked(200, 8_000, 1_000, 10_000).unwrap()
+    );
+
+    // an invalid curve is rejected and leaves the previous one in place
+    value[12..16].copy_from_slice(&500u32.to_le_bytes());
+    let err = lending_operations::update_reserve_config(
+        &mut reserve,
+        UpdateConfigMode::UpdateBorrowRateCurveKinked,
+        &value,
+    )
+    .unwrap_err();
+    assert_eq!(err, LendingError::InvalidBorrowRateCurvePoint.into());
+    assert_eq!(
+        reserve.config.borrow_rate_curve,
+        BorrowRateCurve::from_kinked(200, 8_000, 1_000, 10_000).unwrap()
+    );
+}
@@ -1,10 +1,17 @@
 #[cfg(test)]
 mod helpers;
-use cluster_lend::utils::pda;
+use std::rc::Rc;
+
+use cluster_lend::{
+    errors::LendingError,
+    utils::{pda, Fraction, FractionExtra},
+    Obligation, Reserve, UpdateConfigMode, UpdateLendingMarketMode,
+};
 use solana_program_test::*;
 
 use helpers::*;
-use solana_sdk::{pubkey::Pubkey, signature::Keypair};
+use solana_sdk::{instruction::AccountMeta, pubkey::Pubkey, signature::Keypair, signer::Signer};
+use spl::TokenAccountFixture;
 use test::{TestFixture, PYTH_USDC_FEED};
 
 #[tokio::test]
@@ -62,3 +69,397 @@ async fn success_borrow_repay() {
         .await
         .unwrap();
 }
+
+#[tokio::test]
+async fn success_borrow_with_host_fee() {
+    let user = Keypair::new();
+    let host = Keypair::new();
+
+    // setup market & reserve/obligation
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (market_f, reserve_f, obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+    let reserve_pdas = pda::init_reserve_pdas(&market_f.key, &usdc_mint_f.key);
+
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_time(120);
+
+    // 1% borrow fee, a quarter of it to the host
+    let mut host_fee_bps = [0; 72];
+    host_fee_bps[..2].copy_from_slice(&2_500u16.to_le_bytes());
+    let mut borrow_fee = [0; 32];
+    borrow_fee[..8].copy_from_slice(&(Fraction::from_percent(1).to_bits() as u64).to_le_bytes());
+    test_f
+        .send_transaction(
+            &[
+                market_f.update_market_ix(
+                    UpdateLendingMarketMode::UpdateHostFeeBps as u64,
+                    host_fee_bps,
+                ),
+                reserve_f.update_reserve_mode_ix(
+                    UpdateConfigMode::UpdateFeesBorrowFee as u64,
+                    borrow_fee,
+                ),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+            ],
+            &[&test_f.payer_keypair()],
+        )
+        .await
+        .unwrap();
+
+    let deposit_amount = 1_000_000;
+    let user_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&user, deposit_amount)
+        .await;
+    let host_fee_receiver_f =
+        TokenAccountFixture::new(Rc::clone(&test_f.context), &usdc_mint_f.key, &host.pubkey())
+            .await;
+
+    test_f
+        .send_transaction(
+            &[
+                obligation_f.deposit_liquidity_collateral_ix(
+                    deposit_amount,
+                    &reserve_f,
+                    user_liquidity_ata.key,
+                ),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key]),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    // the vault balance check in the borrow covers both fee transfers
+    let borrow_amount = 300_000;
+    test_f
+        .send_transaction(
+            &[
+                obligation_f.borrow_liquidity_with_host_fee_ix(
+                    borrow_amount,
+                    &reserve_f,
+                    user_liquidity_ata.key,
+                    Some(host_fee_receiver_f.key),
+                ),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    let total_fee = 3_000;
+    let host_fee = 750;
+    assert_eq!(user_liquidity_ata.balance().await, borrow_amount);
+    assert_eq!(host_fee_receiver_f.balance().await, host_fee);
+    assert_eq!(
+        spl::balance_of(Rc::clone(&test_f.context), reserve_pdas.fee_vault).await,
+        total_fee - host_fee
+    );
+    assert_eq!(
+        spl::balance_of(
+            Rc::clone(&test_f.context),
+            reserve_pdas.liquidity_supply_vault
+        )
+        .await,
+        deposit_amount - borrow_amount - total_fee
+    );
+}
+
+#[test]
+fn calculate_repay_max_is_clamped_to_available_balance() {
+    let reserve = Reserve::default();
+    let borrowed = Fraction::from(100u64) + Fraction::from_percent(50);
+
+    // a full repay settles the fractional debt and rounds the transfer up
+    let result = reserve.calculate_repay(u64::MAX, borrowed, 1_000).unwrap();
+    assert_eq!(result.settle_amount_f, borrowed);
+    assert_eq!(result.repay_amount, 101);
+
+    let result = reserve.calculate_repay(u64::MAX, borrowed, 101).unwrap();
+    assert_eq!(result.settle_amount_f, borrowed);
+    assert_eq!(result.repay_amount, 101);
+
+    // one unit short of the rounded debt only settles what the payer holds
+    let result = reserve.calculate_repay(u64::MAX, borrowed, 100).unwrap();
+    assert_eq!(result.settle_amount_f, Fraction::from(100u64));
+    assert_eq!(result.repay_amount, 100);
+
+    let result = reserve.calculate_repay(50, borrowed, 100).unwrap();
+    assert_eq!(result.settle_amount_f, Fraction::from(50u64));
+    assert_eq!(result.repay_amount, 50);
+
+    // an explicit amount is never clamped, the transfer fails when the payer is short
+    let result = reserve.calculate_repay(80, borrowed, 50).unwrap();
+    assert_eq!(result.settle_amount_f, Fraction::from(80u64));
+    assert_eq!(result.repay_amount, 80);
+
+    let result = reserve.calculate_repay(u64::MAX, borrowed, 0).unwrap();
+    assert_eq!(result.repay_amount, 0);
+}
+
+#[tokio::test]
+async fn success_repay_max_with_accrued_interest() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (_, reserve_f, obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+
+    let deposit_amount = 1_000_000;
+    let borrow_amount = 300_000;
+    let user_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&user, deposit_amount)
+        .await;
+
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_time(120);
+
+    test_f
+        .send_transaction(
+            &[
+                obligation_f.deposit_liquidity_collateral_ix(
+                    deposit_amount,
+                    &reserve_f,
+                    user_liquidity_ata.key,
+                ),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key]),
+                obligation_f.borrow_liquidity_ix(borrow_amount, &reserve_f, user_liquidity_ata.key),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    // accrue interest so the debt is fractional and above the borrowed amount
+    test_f.set_slot_and_time(1_000_000, 120);
+
+    // the user only holds the borrowed amount, so the full repay is clamped to it
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+                obligation_f.repay_liquidity_ix(u64::MAX, &reserve_f, user_liquidity_ata.key),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(user_liquidity_ata.balance().await, 0);
+    let obligation: Obligation = test_f.load_and_deserialize(&obligation_f.key).await;
+    assert_eq!(obligation.has_debt, 1);
+    let remaining_debt = Fraction::from_bits(obligation.borrows[0].borrowed_amount_sf);
+    assert!(remaining_debt > Fraction::ZERO);
+
+    // with enough balance the full repay clears the rounded up debt
+    let top_up = 100_000;
+    test_f
+        .send_transaction(
+            &[
+                usdc_mint_f.make_mint_to_ix(&user_liquidity_ata.key, top_up),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+                obligation_f.repay_liquidity_ix(u64::MAX, &reserve_f, user_liquidity_ata.key),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    let obligation: Obligation = test_f.load_and_deserialize(&obligation_f.key).await;
+    assert_eq!(obligation.has_debt, 0);
+    assert_eq!(obligation.borrows[0].borrowed_amount_sf, 0);
+    assert_eq!(
+        user_liquidity_ata.balance().await,
+        top_up - remaining_debt.to_ceil::<u64>()
+    );
+}
+
+#[tokio::test]
+async fn success_repay_with_repay_fee() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (market_f, reserve_f, obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+    let reserve_pdas = pda::init_reserve_pdas(&market_f.key, &usdc_mint_f.key);
+
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_time(120);
+
+    // 1% repay fee
+    let mut repay_fee = [0; 32];
+    repay_fee[..8].copy_from_slice(&(Fraction::from_percent(1).to_bits() as u64).to_le_bytes());
+    test_f
+        .send_transaction(
+            &[reserve_f
+                .update_reserve_mode_ix(UpdateConfigMode::UpdateFeesRepayFee as u64, repay_fee)],
+            &[&test_f.payer_keypair()],
+        )
+        .await
+        .unwrap();
+
+    let deposit_amount = 1_000_000;
+    let borrow_amount = 300_000;
+    let user_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&user, deposit_amount)
+        .await;
+
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.deposit_liquidity_collateral_ix(
+                    deposit_amount,
+                    &reserve_f,
+                    user_liquidity_ata.key,
+                ),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key]),
+                obligation_f.borrow_liquidity_ix(borrow_amount, &reserve_f, user_liquidity_ata.key),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    let obligation_before: Obligation = test_f.load_and_deserialize(&obligation_f.key).await;
+    let reserve_before: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+
+    let repay_amount = 100_000;
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+                obligation_f.repay_liquidity_ix(repay_amount, &reserve_f, user_liquidity_ata.key),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    // the fee is paid on top and goes to the fee vault, the debt only drops by the principal
+    let repay_fee = 1_000;
+    assert_eq!(
+        user_liquidity_ata.balance().await,
+        borrow_amount - repay_amount - repay_fee
+    );
+    assert_eq!(
+        spl::balance_of(Rc::clone(&test_f.context), reserve_pdas.fee_vault).await,
+        repay_fee
+    );
+
+    let obligation: Obligation = test_f.load_and_deserialize(&obligation_f.key).await;
+    let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+    assert_eq!(
+        Fraction::from_bits(obligation_before.borrows[0].borrowed_amount_sf)
+            - Fraction::from_bits(obligation.borrows[0].borrowed_amount_sf),
+        Fraction::from(repay_amount)
+    );
+    assert_eq!(
+        reserve.liquidity.available_amount,
+        reserve_before.liquidity.available_amount + repay_amount
+    );
+}
+
+#[tokio::test]
+async fn success_repay_max_with_repay_fee() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (market_f, reserve_f, obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+    let reserve_pdas = pda::init_reserve_pdas(&market_f.key, &usdc_mint_f.key);
+
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_time(120);
+
+    // 1% repay fee
+    let mut repay_fee = [0; 32];
+    repay_fee[..8].copy_from_slice(&(Fraction::from_percent(1).to_bits() as u64).to_le_bytes());
+    test_f
+        .send_transaction(
+            &[reserve_f
+                .update_reserve_mode_ix(UpdateConfigMode::UpdateFeesRepayFee as u64, repay_fee)],
+            &[&test_f.payer_keypair()],
+        )
+        .await
+        .unwrap();
+
+    let deposit_amount = 1_000_000;
+    let borrow_amount = 300_000;
+    let user_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&user, deposit_amount)
+        .await;
+
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.deposit_liquidity_collateral_ix(
+                    deposit_amount,
+                    &reserve_f,
+                    user_liquidity_ata.key,
+                ),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key]),
+                obligation_f.borrow_liquidity_ix(borrow_amount, &reserve_f, user_liquidity_ata.key),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    let obligation_before: Obligation = test_f.load_and_deserialize(&obligation_f.key).await;
+
+    // leaving out the fee vault doesn't skip the fee
+    let mut repay_ix =
+        obligation_f.repay_liquidity_ix(u64::MAX, &reserve_f, user_liquidity_ata.key);
+    *repay_ix.accounts.last_mut().unwrap() = AccountMeta::new_readonly(cluster_lend::id(), false);
+    let err = test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+                repay_ix,
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap_err();
+    assert_custom_error!(err, LendingError::InvalidAccountInput);
+
+    // the balance covers the debt but not the fee on top, the full repay leaves room for it
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+                obligation_f.repay_liquidity_ix(u64::MAX, &reserve_f, user_liquidity_ata.key),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    let repay_amount: u64 = 297_029;
+    let repay_fee = 2_971;
+    assert_eq!(user_liquidity_ata.balance().await, 0);
+    assert_eq!(
+        spl::balance_of(Rc::clone(&test_f.context), reserve_pdas.fee_vault).await,
+        repay_fee
+    );
+
+    let obligation: Obligation = test_f.load_and_deserialize(&obligation_f.key).await;
+    assert_eq!(
+        Fraction::from_bits(obligation_before.borrows[0].borrowed_amount_sf)
+            - Fraction::from_bits(obligation.borrows[0].borrowed_amount_sf),
+        Fraction::from(repay_amount)
+    );
+}
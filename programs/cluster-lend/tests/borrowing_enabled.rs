@@ -0,0 +1,103 @@
+#[cfg(test)]
+mod helpers;
+
+use cluster_lend::{Obligation, UpdateConfigMode};
+use solana_program_test::*;
+
+use helpers::*;
+use solana_sdk::signature::Keypair;
+use test::{TestFixture, PYTH_USDC_FEED};
+
+fn borrowing_disabled_value(borrowing_disabled: bool) -> [u8; 32] {
+    let mut value = [0; 32];
+    value[0] = borrowing_disabled.into();
+    value
+}
+
+#[tokio::test]
+async fn success_collateral_only_reserve_accepts_deposits_but_not_borrows() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (_market_f, reserve_f, obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+    let owner = test_f.payer_keypair();
+    let mode = UpdateConfigMode::UpdateBorrowingDisabled as u64;
+
+    let deposit_amount = 1_000_000;
+    let user_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&user, deposit_amount)
+        .await;
+
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_time(120);
+
+    test_f
+        .send_transaction(
+            &[reserve_f.update_reserve_mode_ix(mode, borrowing_disabled_value(true))],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+
+    // the reserve still takes collateral
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.deposit_liquidity_collateral_ix(
+                    deposit_amount,
+                    &reserve_f,
+                    user_liquidity_ata.key,
+                ),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key]),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    let obligation: Obligation = test_f.load_and_deserialize(&obligation_f.key).await;
+    assert_eq!(obligation.deposits[0].deposited_amount, deposit_amount);
+
+    // but can't be borrowed from
+    let borrow_amount = 100_000;
+    let r = test_f
+        .send_transaction(
+            &[
+                obligation_f.borrow_liquidity_ix(
+                    borrow_amount / 2,
+                    &reserve_f,
+                    user_liquidity_ata.key,
+                ),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+            ],
+            &[&user],
+        )
+        .await;
+    assert!(r.is_err());
+    assert_eq!(user_liquidity_ata.balance().await, 0);
+
+    test_f
+        .send_transaction(
+            &[reserve_f.update_reserve_mode_ix(mode, borrowing_disabled_value(false))],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key]),
+                obligation_f.borrow_liquidity_ix(borrow_amount, &reserve_f, user_liquidity_ata.key),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+    assert_eq!(user_liquidity_ata.balance().await, borrow_amount);
+}
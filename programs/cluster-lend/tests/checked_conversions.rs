@@ -0,0 +1,56 @@
+#[cfg(test)]
+mod helpers;
+
+use anchor_lang::prelude::*;
+use cluster_lend::{
+    errors::LendingError, lending_market::lending_operations::deposit_reserve_liquidity,
+    PriceStatusFlags, Reserve,
+};
+use solana_program_test::*;
+
+use helpers::*;
+use solana_sdk::signature::Keypair;
+use test::{TestFixture, PYTH_USDC_FEED};
+
+#[test]
+fn fail_deposit_with_negative_timestamp() {
+    let slot = 100;
+    let mut reserve = Reserve::default();
+    reserve.config.deposit_limit = u64::MAX;
+    reserve
+        .last_update
+        .update_slot(slot, Some(PriceStatusFlags::ALL_CHECKS));
+
+    let clock = Clock {
+        slot,
+        unix_timestamp: -1,
+        ..Default::default()
+    };
+    let err = deposit_reserve_liquidity(&mut reserve, &clock, 1_000).unwrap_err();
+    assert_eq!(err, LendingError::IntegerOverflow.into());
+}
+
+#[tokio::test]
+async fn fail_refresh_with_negative_pyth_price() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (_market_f, reserve_f, _obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+
+    test_f
+        .set_pyth_oracle_price(PYTH_USDC_FEED, -1_000_000)
+        .await;
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_time(120);
+
+    // the price is rejected with a program error rather than a panic
+    let err = test_f
+        .send_transaction(
+            &[reserve_f.refresh_ix(Some(PYTH_USDC_FEED))],
+            &[&test_f.payer_keypair()],
+        )
+        .await
+        .unwrap_err();
+    assert_custom_error!(err, LendingError::PriceNotValid);
+}
@@ -0,0 +1,152 @@
+#[cfg(test)]
+mod helpers;
+
+use cluster_lend::{errors::LendingError, CpiActionFlags, LendingMarket, UpdateLendingMarketMode};
+use solana_program::pubkey::Pubkey;
+use solana_program_test::*;
+
+use cpi_caller::{cpi_caller_entry, via_cpi_caller};
+use helpers::*;
+use solana_sdk::signature::Keypair;
+use test::{TestFixture, PYTH_USDC_FEED};
+
+fn cpi_disabled_flags_value(flags: CpiActionFlags) -> [u8; 72] {
+    let mut value = [0; 72];
+    value[0] = flags.bits();
+    value
+}
+
+#[tokio::test]
+async fn success_borrow_via_cpi_follows_market_flag() {
+    let user = Keypair::new();
+    let cpi_caller_id = Pubkey::new_unique();
+
+    let test_f = TestFixture::new_with_programs(|program| {
+        program.add_program("cpi_caller", cpi_caller_id, processor!(cpi_caller_entry));
+    })
+    .await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (market_f, reserve_f, obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+    let owner = test_f.payer_keypair();
+
+    // new markets allow every action via CPI, as markets from before the flags do
+    let market: LendingMarket = test_f.load_and_deserialize(&market_f.key).await;
+    assert!(market.is_cpi_allowed(CpiActionFlags::all()));
+
+    let deposit_amount = 1_000_000;
+    let user_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&user, deposit_amount)
+        .await;
+
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_time(120);
+
+    test_f
+        .send_transaction(
+            &[
+                obligation_f.deposit_liquidity_collateral_ix(
+                    deposit_amount,
+                    &reserve_f,
+                    user_liquidity_ata.key,
+                ),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key]),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    let borrow = |borrow_amount, via_cpi| {
+        let borrow_ix =
+            obligation_f.borrow_liquidity_ix(borrow_amount, &reserve_f, user_liquidity_ata.key);
+        [
+            if via_cpi {
+                via_cpi_caller(cpi_caller_id, borrow_ix)
+            } else {
+                borrow_ix
+            },
+            reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+            obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+        ]
+    };
+
+    test_f
+        .send_transaction(&borrow(100_000, true), &[&user])
+        .await
+        .unwrap();
+
+    // block borrowing via CPI only
+    test_f
+        .send_transaction(
+            &[market_f.update_market_ix(
+                UpdateLendingMarketMode::UpdateCpiDisabledFlags as u64,
+                cpi_disabled_flags_value(CpiActionFlags::BORROW),
+            )],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+
+    let err = test_f
+        .send_transaction(&borrow(110_000, true), &[&user])
+        .await
+        .unwrap_err();
+    assert_custom_error!(err, LendingError::CpiDisabled);
+
+    // a top-level borrow is unaffected
+    test_f
+        .send_transaction(&borrow(120_000, false), &[&user])
+        .await
+        .unwrap();
+    assert_eq!(user_liquidity_ata.balance().await, 220_000);
+
+    // clearing the flag re-enables it
+    test_f
+        .send_transaction(
+            &[market_f.update_market_ix(
+                UpdateLendingMarketMode::UpdateCpiDisabledFlags as u64,
+                cpi_disabled_flags_value(CpiActionFlags::empty()),
+            )],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+
+    test_f
+        .send_transaction(&borrow(130_000, true), &[&user])
+        .await
+        .unwrap();
+    assert_eq!(user_liquidity_ata.balance().await, 350_000);
+}
+
+#[tokio::test]
+async fn failure_update_cpi_disabled_flags_unknown_bits() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (market_f, _reserve_f, _obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+
+    let mut value = [0; 72];
+    value[0] = 0b_1000_0000;
+    let err = test_f
+        .send_transaction(
+            &[market_f.update_market_ix(
+                UpdateLendingMarketMode::UpdateCpiDisabledFlags as u64,
+                value,
+            )],
+            &[&test_f.payer_keypair()],
+        )
+        .await
+        .unwrap_err();
+    assert_custom_error!(err, LendingError::InvalidFlag);
+}
+
+#[test]
+fn legacy_market_allows_every_action_via_cpi() {
+    // the flags were carved from padding, which is zeroed on every deployed market
+    let market: LendingMarket = bytemuck::Zeroable::zeroed();
+    assert_eq!(market.cpi_disabled_flags, 0);
+    assert!(market.is_cpi_allowed(CpiActionFlags::all()));
+}
@@ -0,0 +1,88 @@
+#[cfg(test)]
+mod helpers;
+
+use cluster_lend::{errors::LendingError, Obligation, Reserve, UpdateConfigMode};
+use solana_program_test::*;
+
+use helpers::*;
+use solana_sdk::{compute_budget::ComputeBudgetInstruction, signature::Keypair};
+use test::{TestFixture, PYTH_USDC_FEED};
+
+fn min_deposit_hold_slots_value(slots: u64) -> [u8; 32] {
+    let mut value = [0; 32];
+    value[..8].copy_from_slice(&slots.to_le_bytes());
+    value
+}
+
+#[tokio::test]
+async fn failure_withdraw_in_deposit_slot_during_hold() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (_market_f, reserve_f, obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+
+    test_f
+        .send_transaction(
+            &[reserve_f.update_reserve_mode_ix(
+                UpdateConfigMode::UpdateMinDepositHoldSlots as u64,
+                min_deposit_hold_slots_value(10),
+            )],
+            &[&test_f.payer_keypair()],
+        )
+        .await
+        .unwrap();
+    let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+    assert_eq!(reserve.config.min_deposit_hold_slots, 10);
+
+    let deposit_amount = 1_000_000;
+    let user_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&user, deposit_amount)
+        .await;
+
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_slot_and_time(100, 120);
+
+    let withdraw_ixs = || {
+        vec![
+            reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+            obligation_f.refresh_ix(vec![reserve_f.key]),
+            obligation_f.withdraw_collateral_and_redeem_ix(
+                u64::MAX,
+                &reserve_f,
+                user_liquidity_ata.key,
+            ),
+        ]
+    };
+
+    // a flash loan would have to deposit and withdraw in the same slot
+    let mut ixs = vec![
+        reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+        obligation_f.deposit_liquidity_collateral_ix(
+            deposit_amount,
+            &reserve_f,
+            user_liquidity_ata.key,
+        ),
+    ];
+    ixs.extend(withdraw_ixs());
+    let err = test_f.send_transaction(&ixs, &[&user]).await.unwrap_err();
+    assert_custom_error!(err, LendingError::DepositHoldPeriodNotElapsed);
+
+    test_f.send_transaction(&ixs[..2], &[&user]).await.unwrap();
+    let obligation: Obligation = test_f.load_and_deserialize(&obligation_f.key).await;
+    assert_eq!(obligation.deposits[0].last_deposit_slot, 100);
+
+    test_f.set_slot_and_time(109, 120);
+    let err = test_f
+        .send_transaction(&withdraw_ixs(), &[&user])
+        .await
+        .unwrap_err();
+    assert_custom_error!(err, LendingError::DepositHoldPeriodNotElapsed);
+
+    // same instructions as the rejected attempt, the compute limit keeps the signature unique
+    test_f.set_slot_and_time(110, 120);
+    let mut ixs = vec![ComputeBudgetInstruction::set_compute_unit_limit(400_000)];
+    ixs.extend(withdraw_ixs());
+    test_f.send_transaction(&ixs, &[&user]).await.unwrap();
+    assert_eq!(user_liquidity_ata.balance().await, deposit_amount);
+}
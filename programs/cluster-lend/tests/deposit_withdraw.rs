@@ -26,8 +26,8 @@ use solana_sdk::{
 };
 use spl::{MintFixture, TokenAccountFixture};
 use test::{
-    TestFixture, PYTH_SOL_FEED, SOL_MINT_DECIMALS, SOL_QUOTE_CURRENCY, TEST_RESERVE_CONFIG,
-    USDC_MINT_DECIMALS, USDC_QUOTE_CURRENCY,
+    TestFixture, PYTH_SOL_FEED, PYTH_USDC_FEED, SOL_MINT_DECIMALS, SOL_QUOTE_CURRENCY,
+    TEST_RESERVE_CONFIG, USDC_MINT_DECIMALS, USDC_QUOTE_CURRENCY,
 };
 
 #[tokio::test]
@@ -114,3 +114,246 @@ async fn success_deposit_withdraw() {
         .await;
     assert_eq!(collateral_balance, collateral_supply_vault.amount);
 }
+
+#[tokio::test]
+async fn failure_withdraw_before_ltv_zero_collateral() {
+    let user = Keypair::new();
+
+    // setup market & regular reserve/obligation
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let sol_mint_f = test_f.sol_mint.clone();
+    let (market_f, usdc_reserve_f, obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+    let usdc_reserve_pdas = pda::init_reserve_pdas(&market_f.key, &usdc_mint_f.key);
+    let sol_reserve_pdas = pda::init_reserve_pdas(&market_f.key, &sol_mint_f.key);
+
+    // setup a second reserve with LTV 0
+    let payer = test_f.payer_keypair();
+    let sol_reserve_key = Keypair::new();
+    let sol_reserve_f = ReserveFixture {
+        key: sol_reserve_key.pubkey(),
+        owner: payer.pubkey(),
+        payer: payer.pubkey(),
+        lending_market: market_f.key,
+        liquidity_mint: sol_mint_f.key,
+    };
+    let mut ltv_zero_config = TEST_RESERVE_CONFIG;
+    ltv_zero_config.loan_to_value_pct = 0;
+    ltv_zero_config.token_info.pyth_configuration.price = PYTH_SOL_FEED;
+    test_f
+        .send_transaction(
+            &[
+                sol_reserve_f.initialize_reserve_ix(),
+                sol_reserve_f.update_reserve_ix(ltv_zero_config),
+            ],
+            &[&payer, &sol_reserve_key],
+        )
+        .await
+        .unwrap();
+
+    // deposit both collaterals
+    let deposit_amount = 1_000_000;
+    let user_usdc_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&user, deposit_amount)
+        .await;
+    let user_sol_ata = sol_mint_f
+        .create_token_account_and_mint_to(&user, deposit_amount)
+        .await;
+
+    let refresh_ixs = [
+        usdc_reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+        sol_reserve_f.refresh_ix(Some(PYTH_SOL_FEED)),
+        obligation_f.refresh_ix(vec![usdc_reserve_f.key, sol_reserve_f.key]),
+    ];
+
+    test_f
+        .send_transaction(
+            &[
+                usdc_reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                sol_reserve_f.refresh_ix(Some(PYTH_SOL_FEED)),
+                obligation_f.deposit_liquidity_collateral_ix(
+                    deposit_amount,
+                    &usdc_reserve_f,
+                    user_usdc_ata.key,
+                ),
+                obligation_f.deposit_liquidity_collateral_ix(
+                    deposit_amount,
+                    &sol_reserve_f,
+                    user_sol_ata.key,
+                ),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    let user_usdc_collateral_f = TokenAccountFixture::new(
+        Rc::clone(&test_f.context),
+        &usdc_reserve_pdas.collateral_ctoken_mint,
+        &user.pubkey(),
+    )
+    .await;
+    let user_sol_collateral_f = TokenAccountFixture::new(
+        Rc::clone(&test_f.context),
+        &sol_reserve_pdas.collateral_ctoken_mint,
+        &user.pubkey(),
+    )
+    .await;
+
+    // withdrawing the regular collateral first is rejected
+    let r = test_f
+        .send_transaction(
+            &[
+                refresh_ixs.to_vec(),
+                vec![obligation_f.withdraw_collateral_ix(
+                    1_000,
+                    &usdc_reserve_f,
+                    user_usdc_collateral_f.key,
+                )],
+            ]
+            .concat(),
+            &[&user],
+        )
+        .await;
+    assert!(r.is_err());
+
+    // withdrawing all the LTV 0 collateral is allowed
+    test_f
+        .send_transaction(
+            &[
+                refresh_ixs.to_vec(),
+                vec![obligation_f.withdraw_collateral_ix(
+                    u64::MAX,
+                    &sol_reserve_f,
+                    user_sol_collateral_f.key,
+                )],
+            ]
+            .concat(),
+            &[&user],
+        )
+        .await
+        .unwrap();
+    assert_eq!(user_sol_collateral_f.balance().await, deposit_amount);
+
+    // then the regular collateral can be withdrawn
+    test_f
+        .send_transaction(
+            &[
+                usdc_reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![usdc_reserve_f.key]),
+                obligation_f.withdraw_collateral_ix(
+                    1_000,
+                    &usdc_reserve_f,
+                    user_usdc_collateral_f.key,
+                ),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+    assert_eq!(user_usdc_collateral_f.balance().await, 1_000);
+}
+
+#[tokio::test]
+async fn success_withdraw_and_redeem_matches_two_step_flow() {
+    let user = Keypair::new();
+    let one_step_user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (market_f, reserve_f, obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+    let reserve_pdas = pda::init_reserve_pdas(&market_f.key, &usdc_mint_f.key);
+    let one_step_obligation_f = ObligationFixture::new(
+        &test_f,
+        &one_step_user,
+        market_f.key,
+        InitObligationArgs::default(),
+    )
+    .await;
+
+    let deposit_amount = 1_000_000;
+    let user_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&user, deposit_amount)
+        .await;
+    let one_step_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&one_step_user, deposit_amount)
+        .await;
+    let user_collateral_f = TokenAccountFixture::new(
+        Rc::clone(&test_f.context),
+        &reserve_pdas.collateral_ctoken_mint,
+        &user.pubkey(),
+    )
+    .await;
+
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_time(120);
+
+    for (obligation_f, owner, ata) in [
+        (&obligation_f, &user, &user_liquidity_ata),
+        (
+            &one_step_obligation_f,
+            &one_step_user,
+            &one_step_liquidity_ata,
+        ),
+    ] {
+        test_f
+            .send_transaction(
+                &[
+                    obligation_f.deposit_liquidity_collateral_ix(
+                        deposit_amount,
+                        &reserve_f,
+                        ata.key,
+                    ),
+                    reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                    obligation_f.refresh_ix(vec![reserve_f.key]),
+                ],
+                &[owner],
+            )
+            .await
+            .unwrap();
+    }
+
+    // withdraw the cTokens, then redeem them
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key]),
+                obligation_f.withdraw_collateral_ix(u64::MAX, &reserve_f, user_collateral_f.key),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                reserve_f.redeem_collateral_with_owner_ix(
+                    user.pubkey(),
+                    deposit_amount,
+                    user_collateral_f.key,
+                    user_liquidity_ata.key,
+                ),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    // the same exit in a single instruction
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                one_step_obligation_f.refresh_ix(vec![reserve_f.key]),
+                one_step_obligation_f.withdraw_collateral_and_redeem_ix(
+                    u64::MAX,
+                    &reserve_f,
+                    one_step_liquidity_ata.key,
+                ),
+            ],
+            &[&one_step_user],
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(user_collateral_f.balance().await, 0);
+    assert_eq!(user_liquidity_ata.balance().await, deposit_amount);
+    assert_eq!(
+        one_step_liquidity_ata.balance().await,
+        user_liquidity_ata.balance().await
+    );
+}
@@ -0,0 +1,346 @@
+#[cfg(test)]
+mod helpers;
+use std::rc::Rc;
+
+use anchor_lang::prelude::Clock;
+use cluster_lend::{
+    errors::LendingError,
+    lending_market::lending_operations::{refresh_reserve, refresh_reserve_limit_timestamps},
+    utils::pda,
+    LendingMarket, Reserve, UpdateConfigMode, UpdateLendingMarketMode,
+};
+use solana_program_test::*;
+
+use helpers::*;
+use solana_sdk::{signature::Keypair, signer::Signer};
+use spl::TokenAccountFixture;
+use test::{TestFixture, PYTH_USDC_FEED, TEST_RESERVE_CONFIG};
+
+fn deposits_disabled_value(deposits_disabled: bool) -> [u8; 32] {
+    let mut value = [0; 32];
+    value[0] = deposits_disabled.into();
+    value
+}
+
+fn market_deposits_disabled_value(deposits_disabled: bool) -> [u8; 72] {
+    let mut value = [0; 72];
+    value[0] = deposits_disabled.into();
+    value
+}
+
+#[test]
+fn deposits_disabled_once_limit_crossed_past_grace() {
+    let mut reserve = Reserve::default();
+    reserve.config = TEST_RESERVE_CONFIG;
+    reserve.deposit_liquidity(1_000_000).unwrap();
+    reserve.config.deposit_limit = 500_000;
+    reserve.config.limit_crossed_grace_slots = 100;
+    let clock_at = |slot| Clock {
+        slot,
+        ..Default::default()
+    };
+
+    refresh_reserve(&mut reserve, &clock_at(10), None).unwrap();
+    refresh_reserve_limit_timestamps(&mut reserve, 10).unwrap();
+    assert_eq!(reserve.liquidity.deposit_limit_crossed_slot, 10);
+
+    // still within the grace window
+    refresh_reserve(&mut reserve, &clock_at(110), None).unwrap();
+    assert!(!reserve.config.is_deposits_disabled());
+
+    refresh_reserve(&mut reserve, &clock_at(111), None).unwrap();
+    assert!(reserve.config.is_deposits_disabled());
+}
+
+#[tokio::test]
+async fn success_wind_down_blocks_deposits_but_not_withdrawals() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (market_f, reserve_f, obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+    let reserve_pdas = pda::init_reserve_pdas(&market_f.key, &usdc_mint_f.key);
+    let owner = test_f.payer_keypair();
+    let mode = UpdateConfigMode::UpdateDepositsDisabled as u64;
+
+    // an obligation deposit and a plain reserve deposit before the wind-down
+    let deposit_amount = 1_000_000;
+    let user_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&user, 2 * deposit_amount)
+        .await;
+    let owner_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&owner, 2 * deposit_amount)
+        .await;
+    let user_collateral_ata = TokenAccountFixture::new(
+        Rc::clone(&test_f.context),
+        &reserve_pdas.collateral_ctoken_mint,
+        &user.pubkey(),
+    )
+    .await;
+    let owner_collateral_ata = TokenAccountFixture::new(
+        Rc::clone(&test_f.context),
+        &reserve_pdas.collateral_ctoken_mint,
+        &owner.pubkey(),
+    )
+    .await;
+
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                reserve_f.deposit_liquidity_ix(
+                    deposit_amount,
+                    owner_liquidity_ata.key,
+                    owner_collateral_ata.key,
+                ),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.deposit_liquidity_collateral_ix(
+                    deposit_amount,
+                    &reserve_f,
+                    user_liquidity_ata.key,
+                ),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key]),
+            ],
+            &[&owner, &user],
+        )
+        .await
+        .unwrap();
+
+    test_f
+        .send_transaction(
+            &[reserve_f.update_reserve_mode_ix(mode, deposits_disabled_value(true))],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+    let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+    assert!(reserve.config.is_deposits_disabled());
+
+    // no new inflows through either deposit path
+    let r = test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                reserve_f.deposit_liquidity_ix(
+                    1_000,
+                    owner_liquidity_ata.key,
+                    owner_collateral_ata.key,
+                ),
+            ],
+            &[&owner],
+        )
+        .await;
+    assert!(r.is_err());
+
+    let r = test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.deposit_liquidity_collateral_ix(
+                    1_000,
+                    &reserve_f,
+                    user_liquidity_ata.key,
+                ),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key]),
+            ],
+            &[&user],
+        )
+        .await;
+    assert!(r.is_err());
+
+    // existing depositors can still exit
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key]),
+                obligation_f.withdraw_collateral_ix(
+                    deposit_amount,
+                    &reserve_f,
+                    user_collateral_ata.key,
+                ),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                reserve_f.redeem_collateral_ix(
+                    deposit_amount,
+                    owner_collateral_ata.key,
+                    owner_liquidity_ata.key,
+                ),
+            ],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+
+    // lifting the wind-down reopens deposits
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.update_reserve_mode_ix(mode, deposits_disabled_value(false)),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                reserve_f.deposit_liquidity_ix(
+                    2_000,
+                    owner_liquidity_ata.key,
+                    owner_collateral_ata.key,
+                ),
+            ],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn success_market_deposits_disabled_keeps_repay_and_withdraw() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (market_f, reserve_f, obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+    let reserve_pdas = pda::init_reserve_pdas(&market_f.key, &usdc_mint_f.key);
+    let owner = test_f.payer_keypair();
+    let mode = UpdateLendingMarketMode::UpdateDepositsDisabled as u64;
+
+    let deposit_amount = 1_000_000;
+    let user_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&user, deposit_amount)
+        .await;
+    let owner_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&owner, deposit_amount)
+        .await;
+    let user_collateral_ata = TokenAccountFixture::new(
+        Rc::clone(&test_f.context),
+        &reserve_pdas.collateral_ctoken_mint,
+        &user.pubkey(),
+    )
+    .await;
+    let owner_collateral_ata = TokenAccountFixture::new(
+        Rc::clone(&test_f.context),
+        &reserve_pdas.collateral_ctoken_mint,
+        &owner.pubkey(),
+    )
+    .await;
+
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_time(120);
+
+    let borrow_amount = 100_000;
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.deposit_liquidity_collateral_ix(
+                    deposit_amount / 2,
+                    &reserve_f,
+                    user_liquidity_ata.key,
+                ),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key]),
+                obligation_f.borrow_liquidity_ix(borrow_amount, &reserve_f, user_liquidity_ata.key),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    test_f
+        .send_transaction(
+            &[market_f.update_market_ix(mode, market_deposits_disabled_value(true))],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+    let market: LendingMarket = test_f.load_and_deserialize(&market_f.key).await;
+    assert!(market.is_deposits_disabled());
+
+    // both deposit paths are closed on every reserve of the market
+    let err = test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                reserve_f.deposit_liquidity_ix(
+                    1_000,
+                    owner_liquidity_ata.key,
+                    owner_collateral_ata.key,
+                ),
+            ],
+            &[&owner],
+        )
+        .await
+        .unwrap_err();
+    assert_custom_error!(err, LendingError::DepositsDisabled);
+
+    let err = test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.deposit_liquidity_collateral_ix(
+                    1_000,
+                    &reserve_f,
+                    user_liquidity_ata.key,
+                ),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap_err();
+    assert_custom_error!(err, LendingError::DepositsDisabled);
+
+    // repaying and withdrawing are unaffected
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+                obligation_f.repay_liquidity_ix(u64::MAX, &reserve_f, user_liquidity_ata.key),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key]),
+                obligation_f.withdraw_collateral_ix(
+                    deposit_amount / 4,
+                    &reserve_f,
+                    user_collateral_ata.key,
+                ),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    // re-enabling deposits reopens them
+    test_f
+        .send_transaction(
+            &[
+                market_f.update_market_ix(mode, market_deposits_disabled_value(false)),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                reserve_f.deposit_liquidity_ix(
+                    2_000,
+                    owner_liquidity_ata.key,
+                    owner_collateral_ata.key,
+                ),
+            ],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+    assert_eq!(owner_liquidity_ata.balance().await, deposit_amount - 2_000);
+}
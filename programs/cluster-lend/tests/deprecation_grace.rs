@@ -0,0 +1,161 @@
+#[cfg(test)]
+mod helpers;
+use std::rc::Rc;
+
+use cluster_lend::{
+    errors::LendingError, utils::pda, LendingMarket, Reserve, ReserveStatus, UpdateConfigMode,
+    UpdateLendingMarketMode,
+};
+use reserve::{reserve_status_value, ReserveFixture};
+use solana_program_test::*;
+
+use helpers::*;
+use solana_sdk::{signature::Keypair, signer::Signer};
+use spl::TokenAccountFixture;
+use test::{TestFixture, PYTH_SOL_FEED, PYTH_USDC_FEED, TEST_RESERVE_CONFIG};
+
+fn deprecation_grace_slots_value(slots: u64) -> [u8; 72] {
+    let mut value = [0; 72];
+    value[..8].copy_from_slice(&slots.to_le_bytes());
+    value
+}
+
+#[test]
+fn obsolete_since_slot_tracks_status_changes() {
+    let mut reserve = Reserve::default();
+    reserve.config.status = ReserveStatus::Obsolete as u8;
+    reserve.update_obsolete_since_slot(ReserveStatus::Active, 1_000);
+    assert_eq!(reserve.obsolete_since_slot, 1_000);
+
+    // staying obsolete keeps the original slot
+    reserve.update_obsolete_since_slot(ReserveStatus::Obsolete, 2_000);
+    assert_eq!(reserve.obsolete_since_slot, 1_000);
+    assert!(!reserve.is_obsolete_past_grace(1_499, 500));
+    assert!(reserve.is_obsolete_past_grace(1_500, 500));
+    assert!(reserve.is_obsolete_past_grace(1_000, 0));
+
+    reserve.config.status = ReserveStatus::Active as u8;
+    reserve.update_obsolete_since_slot(ReserveStatus::Obsolete, 3_000);
+    assert_eq!(reserve.obsolete_since_slot, 0);
+    assert!(!reserve.is_obsolete_past_grace(3_000, 0));
+}
+
+#[tokio::test]
+async fn success_withdraw_active_collateral_within_deprecation_grace() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let sol_mint_f = test_f.sol_mint.clone();
+    let (market_f, usdc_reserve_f, obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+    let usdc_reserve_pdas = pda::init_reserve_pdas(&market_f.key, &usdc_mint_f.key);
+
+    let payer = test_f.payer_keypair();
+    let sol_reserve_key = Keypair::new();
+    let sol_reserve_f = ReserveFixture {
+        key: sol_reserve_key.pubkey(),
+        owner: payer.pubkey(),
+        payer: payer.pubkey(),
+        lending_market: market_f.key,
+        liquidity_mint: sol_mint_f.key,
+    };
+    let mut sol_config = TEST_RESERVE_CONFIG;
+    sol_config.token_info.pyth_configuration.price = PYTH_SOL_FEED;
+    test_f
+        .send_transaction(
+            &[
+                sol_reserve_f.initialize_reserve_ix(),
+                sol_reserve_f.update_reserve_ix(sol_config),
+                market_f.update_market_ix(
+                    UpdateLendingMarketMode::UpdateDeprecationGraceSlots as u64,
+                    deprecation_grace_slots_value(500),
+                ),
+            ],
+            &[&payer, &sol_reserve_key],
+        )
+        .await
+        .unwrap();
+    let market: LendingMarket = test_f.load_and_deserialize(&market_f.key).await;
+    assert_eq!(market.deprecation_grace_slots, 500);
+
+    let deposit_amount = 1_000_000;
+    let user_usdc_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&user, deposit_amount)
+        .await;
+    let user_sol_ata = sol_mint_f
+        .create_token_account_and_mint_to(&user, deposit_amount)
+        .await;
+    let user_usdc_collateral_f = TokenAccountFixture::new(
+        Rc::clone(&test_f.context),
+        &usdc_reserve_pdas.collateral_ctoken_mint,
+        &user.pubkey(),
+    )
+    .await;
+
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_pyth_oracle_timestamp(PYTH_SOL_FEED, 120).await;
+    test_f.set_slot_and_time(1_000, 120);
+
+    test_f
+        .send_transaction(
+            &[
+                usdc_reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                sol_reserve_f.refresh_ix(Some(PYTH_SOL_FEED)),
+                obligation_f.deposit_liquidity_collateral_ix(
+                    deposit_amount,
+                    &usdc_reserve_f,
+                    user_usdc_ata.key,
+                ),
+                obligation_f.deposit_liquidity_collateral_ix(
+                    deposit_amount,
+                    &sol_reserve_f,
+                    user_sol_ata.key,
+                ),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    test_f
+        .send_transaction(
+            &[sol_reserve_f.update_reserve_mode_ix(
+                UpdateConfigMode::UpdateReserveStatus as u64,
+                reserve_status_value(ReserveStatus::Obsolete),
+            )],
+            &[&payer],
+        )
+        .await
+        .unwrap();
+    let sol_reserve: Reserve = test_f.load_and_deserialize(&sol_reserve_f.key).await;
+    assert_eq!(sol_reserve.obsolete_since_slot, 1_000);
+
+    let withdraw_ixs = |amount: u64| {
+        vec![
+            usdc_reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+            sol_reserve_f.refresh_ix(Some(PYTH_SOL_FEED)),
+            obligation_f.refresh_ix(vec![usdc_reserve_f.key, sol_reserve_f.key]),
+            obligation_f.withdraw_collateral_ix(
+                amount,
+                &usdc_reserve_f,
+                user_usdc_collateral_f.key,
+            ),
+        ]
+    };
+
+    // inside the grace window the active collateral can still be withdrawn
+    test_f.set_slot_and_time(1_499, 120);
+    test_f
+        .send_transaction(&withdraw_ixs(1_000), &[&user])
+        .await
+        .unwrap();
+    assert_eq!(user_usdc_collateral_f.balance().await, 1_000);
+
+    // past it, the obsolete deposit has to be withdrawn first
+    test_f.set_slot_and_time(1_500, 120);
+    let err = test_f
+        .send_transaction(&withdraw_ixs(2_000), &[&user])
+        .await
+        .unwrap_err();
+    assert_custom_error!(err, LendingError::ObligationInDeprecatedReserve);
+}
@@ -0,0 +1,119 @@
+#[cfg(test)]
+mod helpers;
+use std::rc::Rc;
+
+use cluster_lend::{
+    utils::{pda, Fraction},
+    Obligation, Reserve,
+};
+use solana_program_test::*;
+
+use helpers::*;
+use solana_sdk::{signature::Keypair, signer::Signer};
+use spl::TokenAccountFixture;
+use test::{TestFixture, PYTH_USDC_FEED};
+
+#[tokio::test]
+async fn success_repay_forgives_dust_debt_and_closes_obligation() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (market_f, reserve_f, obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+    let reserve_pdas = pda::init_reserve_pdas(&market_f.key, &usdc_mint_f.key);
+
+    let deposit_amount = 1_000_000;
+    let borrow_amount = 300_000;
+    let user_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&user, deposit_amount)
+        .await;
+    let user_collateral_ata = TokenAccountFixture::new(
+        Rc::clone(&test_f.context),
+        &reserve_pdas.collateral_ctoken_mint,
+        &user.pubkey(),
+    )
+    .await;
+
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_time(120);
+
+    test_f
+        .send_transaction(
+            &[
+                obligation_f.deposit_liquidity_collateral_ix(
+                    deposit_amount,
+                    &reserve_f,
+                    user_liquidity_ata.key,
+                ),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key]),
+                obligation_f.borrow_liquidity_ix(borrow_amount, &reserve_f, user_liquidity_ata.key),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    // half a lamport of debt on top of the borrow, on both the obligation and the reserve
+    let dust = Fraction::ONE / 2;
+    test_f
+        .update_obligation_account(obligation_f.key, |obligation| {
+            obligation.borrows[0].borrowed_amount_sf =
+                (Fraction::from_bits(obligation.borrows[0].borrowed_amount_sf) + dust).to_bits();
+        })
+        .await;
+    test_f
+        .update_reserve_account(reserve_f.key, |reserve| {
+            reserve.liquidity.borrowed_amount_sf =
+                (Fraction::from_bits(reserve.liquidity.borrowed_amount_sf) + dust).to_bits();
+        })
+        .await;
+
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+                obligation_f.repay_liquidity_ix(borrow_amount, &reserve_f, user_liquidity_ata.key),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    let obligation: Obligation = test_f.load_and_deserialize(&obligation_f.key).await;
+    assert_eq!(obligation.has_debt, 0);
+    assert!(obligation.borrows_empty());
+    let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+    assert_eq!(reserve.liquidity.borrowed_amount_sf, 0);
+    assert_eq!(reserve.liquidity.accumulated_bad_debt(), dust);
+
+    // with the debt gone, withdrawing the whole deposit closes the obligation
+    let mut withdraw_ix = obligation_f.withdraw_collateral_ix(
+        obligation.deposits[0].deposited_amount,
+        &reserve_f,
+        user_collateral_ata.key,
+    );
+    // the owner collects the obligation rent, so it is passed writable
+    withdraw_ix.accounts[0].is_writable = true;
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key]),
+                withdraw_ix,
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    let obligation_account = test_f
+        .context
+        .borrow_mut()
+        .banks_client
+        .get_account(obligation_f.key)
+        .await
+        .unwrap();
+    assert!(obligation_account.is_none());
+}
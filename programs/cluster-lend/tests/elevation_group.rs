@@ -0,0 +1,139 @@
+#[cfg(test)]
+mod helpers;
+
+use cluster_lend::{Obligation, UpdateConfigMode, UpdateLendingMarketMode};
+use solana_program_test::*;
+
+use helpers::*;
+use solana_sdk::signature::Keypair;
+use test::{TestFixture, PYTH_USDC_FEED};
+
+const ELEVATION_GROUP_ID: u8 = 1;
+
+#[tokio::test]
+async fn success_elevation_group_boosts_borrowing_power() {
+    let user = Keypair::new();
+
+    // setup market & reserve/obligation
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (market_f, reserve_f, obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_time(120);
+
+    let deposit_amount = 1_000_000;
+    let user_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&user, deposit_amount)
+        .await;
+
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.deposit_liquidity_collateral_ix(
+                    deposit_amount,
+                    &reserve_f,
+                    user_liquidity_ata.key,
+                ),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key]),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    // 80% is above the reserve LTV of 75%
+    let r = test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key]),
+                obligation_f.borrow_liquidity_ix(800_000, &reserve_f, user_liquidity_ata.key),
+            ],
+            &[&user],
+        )
+        .await;
+    assert!(r.is_err());
+
+    // configure elevation group with 90% LTV and add the reserve to it
+    let mut market_value = [0; 72];
+    market_value[..3].copy_from_slice(&[ELEVATION_GROUP_ID, 90, 95]);
+    let mut reserve_value = [0; 32];
+    reserve_value[0] = ELEVATION_GROUP_ID;
+
+    test_f
+        .send_transaction(
+            &[
+                market_f.update_market_ix(
+                    UpdateLendingMarketMode::UpdateElevationGroup as u64,
+                    market_value,
+                ),
+                reserve_f.update_reserve_mode_ix(
+                    UpdateConfigMode::UpdateElevationGroup as u64,
+                    reserve_value,
+                ),
+            ],
+            &[&test_f.payer_keypair()],
+        )
+        .await
+        .unwrap();
+
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.request_elevation_group_ix(ELEVATION_GROUP_ID, vec![reserve_f.key]),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    let obligation: Obligation = test_f.load_and_deserialize(&obligation_f.key).await;
+    assert_eq!(obligation.elevation_group, ELEVATION_GROUP_ID);
+
+    // 85% fits within the elevation group LTV of 90%
+    let borrow_amount = 850_000;
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key]),
+                obligation_f.borrow_liquidity_ix(borrow_amount, &reserve_f, user_liquidity_ata.key),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    // leaving the group would put the obligation above its allowed borrow value
+    let r = test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.request_elevation_group_ix(0, vec![reserve_f.key, reserve_f.key]),
+            ],
+            &[&user],
+        )
+        .await;
+    assert!(r.is_err());
+}
+
+#[tokio::test]
+async fn failure_request_unconfigured_elevation_group() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (_market_f, _reserve_f, obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+
+    let r = test_f
+        .send_transaction(
+            &[obligation_f.request_elevation_group_ix(ELEVATION_GROUP_ID, vec![])],
+            &[&user],
+        )
+        .await;
+    assert!(r.is_err());
+}
@@ -0,0 +1,87 @@
+#[cfg(test)]
+mod helpers;
+
+use cluster_lend::{errors::LendingError, utils::Fraction, Obligation, UpdateLendingMarketMode};
+use solana_program_test::*;
+
+use helpers::*;
+use solana_sdk::signature::Keypair;
+use test::{TestFixture, PYTH_USDC_FEED};
+
+#[tokio::test]
+async fn success_repay_while_borrow_blocked_in_emergency_mode() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (market_f, reserve_f, obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+
+    let deposit_amount = 1_000_000;
+    let user_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&user, deposit_amount)
+        .await;
+
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_time(120);
+
+    test_f
+        .send_transaction(
+            &[
+                obligation_f.deposit_liquidity_collateral_ix(
+                    deposit_amount,
+                    &reserve_f,
+                    user_liquidity_ata.key,
+                ),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key]),
+                obligation_f.borrow_liquidity_ix(300_000, &reserve_f, user_liquidity_ata.key),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    let mode = UpdateLendingMarketMode::UpdateEmergencyMode as u64;
+    let mut value = [0; 72];
+    value[0] = 1;
+    test_f
+        .send_transaction(
+            &[market_f.update_market_ix(mode, value)],
+            &[&test_f.payer_keypair()],
+        )
+        .await
+        .unwrap();
+
+    // new debt is risk increasing and stays blocked
+    let err = test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+                obligation_f.borrow_liquidity_ix(100_000, &reserve_f, user_liquidity_ata.key),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap_err();
+    assert_custom_error!(err, LendingError::GlobalEmergencyMode);
+
+    // repaying still goes through, with the reserve refresh it requires
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+                obligation_f.repay_liquidity_ix(100_000, &reserve_f, user_liquidity_ata.key),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    let obligation: Obligation = test_f.load_and_deserialize(&obligation_f.key).await;
+    assert_eq!(
+        Fraction::from_bits(obligation.borrows[0].borrowed_amount_sf),
+        Fraction::from(200_000u64)
+    );
+}
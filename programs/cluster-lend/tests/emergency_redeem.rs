@@ -0,0 +1,114 @@
+#[cfg(test)]
+mod helpers;
+use std::rc::Rc;
+
+use cluster_lend::{utils::pda, UpdateLendingMarketMode};
+use solana_program_test::*;
+
+use helpers::*;
+use solana_sdk::{signature::Keypair, signer::Signer};
+use spl::TokenAccountFixture;
+use test::{TestFixture, PYTH_USDC_FEED};
+
+#[tokio::test]
+async fn success_emergency_redeem_reserve_collateral() {
+    let user = Keypair::new();
+
+    // setup market & reserve
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (market_f, reserve_f, _obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+    let reserve_pdas = pda::init_reserve_pdas(&market_f.key, &usdc_mint_f.key);
+
+    // deposit liquidity to the reserve as the reserve owner
+    let owner = test_f.payer_keypair();
+    let deposit_amount = 1_000_000;
+    let owner_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&owner, deposit_amount)
+        .await;
+    let owner_collateral_ata_f = TokenAccountFixture::new(
+        Rc::clone(&test_f.context),
+        &reserve_pdas.collateral_ctoken_mint,
+        &owner.pubkey(),
+    )
+    .await;
+
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                reserve_f.deposit_liquidity_ix(
+                    deposit_amount,
+                    owner_liquidity_ata.key,
+                    owner_collateral_ata_f.key,
+                ),
+            ],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+    assert_eq!(owner_collateral_ata_f.balance().await, deposit_amount);
+
+    // emergency redeem is rejected while the market is operating normally
+    let redeem_amount = 400_000;
+    let r = test_f
+        .send_transaction(
+            &[reserve_f.emergency_redeem_collateral_ix(
+                redeem_amount + 1,
+                owner_collateral_ata_f.key,
+                owner_liquidity_ata.key,
+            )],
+            &[&owner],
+        )
+        .await;
+    assert!(r.is_err());
+
+    // enable emergency mode
+    let mode = UpdateLendingMarketMode::UpdateEmergencyMode as u64;
+    let mut value = [0; 72];
+    value[0] = 1;
+    test_f
+        .send_transaction(&[market_f.update_market_ix(mode, value)], &[&owner])
+        .await
+        .unwrap();
+
+    // the regular redeem is blocked by emergency mode
+    let r = test_f
+        .send_transaction(
+            &[reserve_f.redeem_collateral_ix(
+                redeem_amount,
+                owner_collateral_ata_f.key,
+                owner_liquidity_ata.key,
+            )],
+            &[&owner],
+        )
+        .await;
+    assert!(r.is_err());
+
+    // the emergency redeem goes through without refreshing the reserve
+    let r = test_f
+        .send_transaction(
+            &[reserve_f.emergency_redeem_collateral_ix(
+                redeem_amount,
+                owner_collateral_ata_f.key,
+                owner_liquidity_ata.key,
+            )],
+            &[&owner],
+        )
+        .await;
+    assert!(r.is_ok());
+
+    assert_eq!(
+        owner_collateral_ata_f.balance().await,
+        deposit_amount - redeem_amount
+    );
+    assert_eq!(owner_liquidity_ata.balance().await, redeem_amount);
+    assert_eq!(
+        spl::balance_of(
+            Rc::clone(&test_f.context),
+            reserve_pdas.liquidity_supply_vault
+        )
+        .await,
+        deposit_amount - redeem_amount
+    );
+}
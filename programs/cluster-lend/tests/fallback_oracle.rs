@@ -0,0 +1,161 @@
+#[cfg(test)]
+mod helpers;
+
+use cluster_lend::{
+    errors::LendingError, utils::Fraction, PriceStatusFlags, Reserve, UpdateConfigMode,
+};
+use solana_program_test::*;
+
+use helpers::*;
+use solana_sdk::signature::Keypair;
+use test::{TestFixture, PYTH_USDC_FALLBACK_FEED, PYTH_USDC_FEED, TEST_RESERVE_CONFIG};
+
+const USDC_NATIVE_ONE: i64 = 1_000_000;
+
+fn max_fallback_divergence_bps_value(bps: u64) -> [u8; 32] {
+    let mut value = [0; 32];
+    value[..8].copy_from_slice(&bps.to_le_bytes());
+    value
+}
+
+#[tokio::test]
+async fn success_refresh_primary_only() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (_market_f, reserve_f, _obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_time(120);
+
+    test_f
+        .send_transaction(&[reserve_f.refresh_ix(Some(PYTH_USDC_FEED))], &[])
+        .await
+        .unwrap();
+
+    let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+    assert_eq!(reserve.liquidity.get_market_price_f(), Fraction::ONE);
+    assert_eq!(
+        reserve.last_update.get_price_status(),
+        PriceStatusFlags::ALL_CHECKS
+    );
+
+    // without a fallback configured, none can be passed
+    let err = test_f
+        .send_transaction(
+            &[reserve_f
+                .refresh_with_fallback_ix(Some(PYTH_USDC_FEED), Some(PYTH_USDC_FALLBACK_FEED))],
+            &[],
+        )
+        .await
+        .unwrap_err();
+    assert_custom_error!(err, LendingError::InvalidPythPriceAccount);
+}
+
+#[tokio::test]
+async fn success_refresh_uses_fallback_when_primary_is_stale() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (_market_f, reserve_f, _obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+    let owner = test_f.payer_keypair();
+
+    let mut config = TEST_RESERVE_CONFIG;
+    config.token_info.max_age_price_seconds = 60;
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.update_reserve_ix(config),
+                reserve_f.update_reserve_fallback_oracle_ix(PYTH_USDC_FALLBACK_FEED),
+                reserve_f.update_reserve_mode_ix(
+                    UpdateConfigMode::UpdateTokenInfoMaxFallbackDivergenceBps as u64,
+                    max_fallback_divergence_bps_value(100),
+                ),
+            ],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+
+    // the primary feed was last published at 0, well past the 60 seconds max age
+    test_f
+        .set_pyth_oracle_price(PYTH_USDC_FALLBACK_FEED, 2 * USDC_NATIVE_ONE)
+        .await;
+    test_f
+        .set_pyth_oracle_timestamp(PYTH_USDC_FALLBACK_FEED, 120)
+        .await;
+    test_f.set_time(120);
+
+    // the configured fallback feed has to be passed
+    let err = test_f
+        .send_transaction(&[reserve_f.refresh_ix(Some(PYTH_USDC_FEED))], &[])
+        .await
+        .unwrap_err();
+    assert_custom_error!(err, LendingError::InvalidPythPriceAccount);
+
+    test_f
+        .send_transaction(
+            &[reserve_f
+                .refresh_with_fallback_ix(Some(PYTH_USDC_FEED), Some(PYTH_USDC_FALLBACK_FEED))],
+            &[],
+        )
+        .await
+        .unwrap();
+
+    let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+    assert_eq!(reserve.liquidity.get_market_price_f(), Fraction::from(2u64));
+    assert_eq!(reserve.liquidity.market_price_last_updated_ts, 120);
+    assert_eq!(
+        reserve.last_update.get_price_status(),
+        PriceStatusFlags::ALL_CHECKS | PriceStatusFlags::FALLBACK_ORACLE_USED
+    );
+}
+
+#[tokio::test]
+async fn failure_refresh_with_divergent_fallback() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (_market_f, reserve_f, _obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+
+    let mut config = TEST_RESERVE_CONFIG;
+    config.token_info.fallback_oracle.price = PYTH_USDC_FALLBACK_FEED;
+    config.token_info.max_fallback_divergence_bps = 100;
+    test_f
+        .send_transaction(
+            &[reserve_f.update_reserve_ix(config)],
+            &[&test_f.payer_keypair()],
+        )
+        .await
+        .unwrap();
+
+    // both feeds are fresh but 2% apart
+    test_f
+        .set_pyth_oracle_price(PYTH_USDC_FALLBACK_FEED, USDC_NATIVE_ONE * 102 / 100)
+        .await;
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f
+        .set_pyth_oracle_timestamp(PYTH_USDC_FALLBACK_FEED, 120)
+        .await;
+    test_f.set_time(120);
+
+    test_f
+        .send_transaction(
+            &[reserve_f
+                .refresh_with_fallback_ix(Some(PYTH_USDC_FEED), Some(PYTH_USDC_FALLBACK_FEED))],
+            &[],
+        )
+        .await
+        .unwrap();
+
+    // the primary price is kept but cannot be relied on
+    let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+    assert_eq!(reserve.liquidity.get_market_price_f(), Fraction::ONE);
+    assert_eq!(
+        reserve.last_update.get_price_status(),
+        PriceStatusFlags::ALL_CHECKS.difference(PriceStatusFlags::FALLBACK_DIVERGENCE_CHECKED)
+    );
+}
@@ -0,0 +1,140 @@
+#[cfg(test)]
+mod helpers;
+
+use cluster_lend::{
+    errors::LendingError, utils::Fraction, PriceStatusFlags, Reserve, UpdateConfigMode,
+};
+use solana_program::pubkey::Pubkey;
+use solana_program_test::*;
+
+use helpers::*;
+use solana_sdk::signature::Keypair;
+use test::{TestFixture, PYTH_USDC_FEED};
+
+fn fixed_price_value(price: Fraction) -> [u8; 32] {
+    let mut value = [0; 32];
+    value[..8].copy_from_slice(&(price.to_bits() as u64).to_le_bytes());
+    value
+}
+
+#[tokio::test]
+async fn success_refresh_fixed_price_reserve_without_oracle() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (_market_f, reserve_f, obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+
+    test_f
+        .send_transaction(
+            &[reserve_f.update_reserve_mode_ix(
+                UpdateConfigMode::UpdateTokenInfoFixedPrice as u64,
+                fixed_price_value(Fraction::ONE),
+            )],
+            &[&test_f.payer_keypair()],
+        )
+        .await
+        .unwrap();
+
+    let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+    assert_eq!(
+        reserve.config.token_info.pyth_configuration.price,
+        Pubkey::default()
+    );
+    assert_eq!(reserve.config.token_info.fixed_price(), Fraction::ONE);
+
+    // long after the last oracle update, the fixed price is still fresh
+    test_f.set_time(1_000_000);
+
+    let deposit_amount = 1_000_000;
+    let user_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&user, deposit_amount)
+        .await;
+
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(None),
+                obligation_f.deposit_liquidity_collateral_ix(
+                    deposit_amount,
+                    &reserve_f,
+                    user_liquidity_ata.key,
+                ),
+                reserve_f.refresh_ix(None),
+                obligation_f.refresh_ix(vec![reserve_f.key]),
+                obligation_f.borrow_liquidity_ix(300_000, &reserve_f, user_liquidity_ata.key),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(user_liquidity_ata.balance().await, 300_000);
+    let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+    assert_eq!(reserve.liquidity.get_market_price_f(), Fraction::ONE);
+    assert_eq!(reserve.liquidity.market_price_last_updated_ts, 1_000_000);
+    assert_eq!(
+        reserve.last_update.get_price_status(),
+        PriceStatusFlags::ALL_CHECKS
+    );
+
+    // an oracle account is not expected anymore
+    let err = test_f
+        .send_transaction(&[reserve_f.refresh_ix(Some(PYTH_USDC_FEED))], &[])
+        .await
+        .unwrap_err();
+    assert_custom_error!(err, LendingError::InvalidPythPriceAccount);
+}
+
+#[tokio::test]
+async fn success_switch_fixed_price_reserve_back_to_pyth() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (_market_f, reserve_f, _obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+    let owner = test_f.payer_keypair();
+
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.update_reserve_mode_ix(
+                    UpdateConfigMode::UpdateTokenInfoFixedPrice as u64,
+                    fixed_price_value(Fraction::ONE),
+                ),
+                reserve_f.update_reserve_pyth_price_ix(PYTH_USDC_FEED),
+            ],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+
+    let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+    assert_eq!(
+        reserve.config.token_info.pyth_configuration.price,
+        PYTH_USDC_FEED
+    );
+    assert!(!reserve.config.token_info.is_fixed_price());
+}
+
+#[tokio::test]
+async fn failure_update_reserve_with_pyth_and_fixed_price() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (_market_f, reserve_f, _obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+
+    let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+    let mut config = reserve.config;
+    config.token_info.fixed_price_sf = Fraction::ONE.to_bits() as u64;
+
+    let err = test_f
+        .send_transaction(
+            &[reserve_f.update_reserve_ix(config)],
+            &[&test_f.payer_keypair()],
+        )
+        .await
+        .unwrap_err();
+    assert_custom_error!(err, LendingError::InvalidOracleConfig);
+}
@@ -0,0 +1,157 @@
+#[cfg(test)]
+mod helpers;
+use std::rc::Rc;
+
+use cluster_lend::{
+    utils::{pda, Fraction, FractionExtra},
+    UpdateConfigMode,
+};
+use solana_program_test::*;
+
+use helpers::*;
+use solana_sdk::{signature::Keypair, signer::Signer};
+use spl::{balance_of, TokenAccountFixture};
+use test::{TestFixture, PYTH_USDC_FEED};
+
+async fn setup_flash_loan_reserve(
+    test_f: &TestFixture,
+    referral_fee_bps: u16,
+) -> reserve::ReserveFixture {
+    let depositor = Keypair::new();
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (_market_f, reserve_f, obligation_f) = test_f.setup(&depositor, &usdc_mint_f.key).await;
+
+    // supply liquidity to the reserve
+    let deposit_amount = 10_000_000;
+    let depositor_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&depositor, deposit_amount)
+        .await;
+    test_f
+        .send_transaction(
+            &[
+                obligation_f.deposit_liquidity_collateral_ix(
+                    deposit_amount,
+                    &reserve_f,
+                    depositor_liquidity_ata.key,
+                ),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key]),
+            ],
+            &[&depositor],
+        )
+        .await
+        .unwrap();
+
+    // 1% flash loan fee
+    let mut fee_value = [0; 32];
+    fee_value[..8].copy_from_slice(&(Fraction::from_percent(1).to_bits() as u64).to_le_bytes());
+    let mut referral_value = [0; 32];
+    referral_value[..2].copy_from_slice(&referral_fee_bps.to_le_bytes());
+
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.update_reserve_mode_ix(
+                    UpdateConfigMode::UpdateFeesFlashLoanFee as u64,
+                    fee_value,
+                ),
+                reserve_f.update_reserve_mode_ix(
+                    UpdateConfigMode::UpdateFlashLoanReferralFeeBps as u64,
+                    referral_value,
+                ),
+            ],
+            &[&test_f.payer_keypair()],
+        )
+        .await
+        .unwrap();
+
+    reserve_f
+}
+
+#[tokio::test]
+async fn success_flash_loan_fee_split_with_referrer() {
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let reserve_f = setup_flash_loan_reserve(&test_f, 2_500).await;
+    let reserve_pdas = pda::init_reserve_pdas(&reserve_f.lending_market, &usdc_mint_f.key);
+
+    let borrower = Keypair::new();
+    let borrower_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&borrower, 1_000_000)
+        .await;
+    let referrer = Keypair::new();
+    let referrer_ata_f = TokenAccountFixture::new(
+        Rc::clone(&test_f.context),
+        &usdc_mint_f.key,
+        &referrer.pubkey(),
+    )
+    .await;
+
+    let flash_amount = 1_000_000;
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.flash_borrow_ix(
+                    flash_amount,
+                    borrower.pubkey(),
+                    borrower_ata.key,
+                    Some(referrer_ata_f.key),
+                ),
+                reserve_f.flash_repay_ix(
+                    flash_amount,
+                    0,
+                    borrower.pubkey(),
+                    borrower_ata.key,
+                    Some(referrer_ata_f.key),
+                ),
+            ],
+            &[&borrower],
+        )
+        .await
+        .unwrap();
+
+    // 1% fee of 10_000, 25% of which goes to the referrer
+    assert_eq!(referrer_ata_f.balance().await, 2_500);
+    assert_eq!(
+        balance_of(Rc::clone(&test_f.context), reserve_pdas.fee_vault).await,
+        7_500
+    );
+    assert_eq!(borrower_ata.balance().await, 1_000_000 - 10_000);
+}
+
+#[tokio::test]
+async fn success_flash_loan_fee_without_referrer() {
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let reserve_f = setup_flash_loan_reserve(&test_f, 2_500).await;
+    let reserve_pdas = pda::init_reserve_pdas(&reserve_f.lending_market, &usdc_mint_f.key);
+
+    let borrower = Keypair::new();
+    let borrower_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&borrower, 1_000_000)
+        .await;
+
+    let flash_amount = 1_000_000;
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.flash_borrow_ix(flash_amount, borrower.pubkey(), borrower_ata.key, None),
+                reserve_f.flash_repay_ix(
+                    flash_amount,
+                    0,
+                    borrower.pubkey(),
+                    borrower_ata.key,
+                    None,
+                ),
+            ],
+            &[&borrower],
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(
+        balance_of(Rc::clone(&test_f.context), reserve_pdas.fee_vault).await,
+        10_000
+    );
+    assert_eq!(borrower_ata.balance().await, 1_000_000 - 10_000);
+}
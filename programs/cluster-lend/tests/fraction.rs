@@ -0,0 +1,425 @@
+use cluster_lend::{
+    errors::LendingError,
+    utils::{
+        bps_u128_to_fraction, pct_u128_to_fraction, pow_fraction, to_sf, to_sf_const, BigFraction,
+        Fraction, FractionExtra, FRACTION_MAX_DECIMALS, FRACTION_ONE_SCALED, U128, U256,
+    },
+    BigFractionBytes, ObligationLiquidity,
+};
+
+#[test]
+fn fraction_one_scaled() {
+    assert_eq!(Fraction::FRAC_NBITS, 60);
+    assert_eq!(FRACTION_ONE_SCALED, 1u128 << 60);
+    assert_eq!(Fraction::ONE.to_bits(), FRACTION_ONE_SCALED);
+    assert_eq!(Fraction::ONE.to_sf(), FRACTION_ONE_SCALED);
+    assert_eq!(Fraction::from_sf(FRACTION_ONE_SCALED), Fraction::ONE);
+    assert_eq!(to_sf(1u64), FRACTION_ONE_SCALED);
+    assert_eq!(to_sf_const(1), FRACTION_ONE_SCALED);
+    assert_eq!(to_sf(42u64), 42 * FRACTION_ONE_SCALED);
+    assert_eq!(to_sf_const(42), 42 * FRACTION_ONE_SCALED);
+}
+
+#[test]
+fn fraction_from_percent() {
+    assert_eq!(Fraction::from_percent(0), Fraction::ZERO);
+    assert_eq!(Fraction::from_percent(100), Fraction::ONE);
+    assert_eq!(Fraction::from_percent(50), Fraction::ONE / 2);
+    assert_eq!(Fraction::from_percent(250), Fraction::from_num(2.5));
+    assert_eq!(Fraction::from_percent(50u8), pct_u128_to_fraction(50));
+    assert_eq!(pct_u128_to_fraction(100), Fraction::ONE);
+
+    for pct in 0..=200u64 {
+        assert_eq!(Fraction::from_percent(pct).to_percent::<u64>(), Some(pct));
+    }
+}
+
+#[test]
+fn fraction_from_bps() {
+    assert_eq!(Fraction::from_bps(0), Fraction::ZERO);
+    assert_eq!(Fraction::from_bps(10_000), Fraction::ONE);
+    assert_eq!(Fraction::from_bps(5_000), Fraction::ONE / 2);
+    assert_eq!(Fraction::from_bps(2_500u16), bps_u128_to_fraction(2_500));
+    assert_eq!(bps_u128_to_fraction(10_000), Fraction::ONE);
+    assert_eq!(Fraction::from_bps(100), Fraction::from_percent(1));
+
+    for bps in (0..=20_000u64).step_by(7) {
+        assert_eq!(Fraction::from_bps(bps).to_bps::<u64>(), Some(bps));
+    }
+}
+
+#[test]
+fn fraction_bits_round_trip() {
+    let values = [
+        Fraction::ZERO,
+        Fraction::DELTA,
+        Fraction::ONE,
+        Fraction::from_num(1.5),
+        Fraction::from_bps(1),
+        Fraction::from_num(u64::MAX),
+        Fraction::MAX,
+    ];
+    for value in values {
+        assert_eq!(Fraction::from_bits(value.to_bits()), value);
+        assert_eq!(Fraction::from_sf(value.to_sf()), value);
+    }
+
+    assert_eq!(Fraction::DELTA.to_bits(), 1);
+    assert_eq!(Fraction::MAX.to_bits(), u128::MAX);
+}
+
+#[test]
+fn fraction_rounding() {
+    let two_and_half = Fraction::from_num(2.5);
+    assert_eq!(two_and_half.to_floor::<u64>(), 2);
+    assert_eq!(two_and_half.to_ceil::<u64>(), 3);
+    assert_eq!(two_and_half.to_round::<u64>(), 3);
+
+    let two_and_quarter = Fraction::from_num(2.25);
+    assert_eq!(two_and_quarter.to_floor::<u64>(), 2);
+    assert_eq!(two_and_quarter.to_ceil::<u64>(), 3);
+    assert_eq!(two_and_quarter.to_round::<u64>(), 2);
+
+    let just_above_two = Fraction::from_num(2) + Fraction::DELTA;
+    assert_eq!(just_above_two.to_floor::<u64>(), 2);
+    assert_eq!(just_above_two.to_ceil::<u64>(), 3);
+    assert_eq!(just_above_two.to_round::<u64>(), 2);
+
+    let just_below_two = Fraction::from_num(2) - Fraction::DELTA;
+    assert_eq!(just_below_two.to_floor::<u64>(), 1);
+    assert_eq!(just_below_two.to_ceil::<u64>(), 2);
+    assert_eq!(just_below_two.to_round::<u64>(), 2);
+
+    let exact = Fraction::from_num(7);
+    assert_eq!(exact.to_floor::<u64>(), 7);
+    assert_eq!(exact.to_ceil::<u64>(), 7);
+    assert_eq!(exact.to_round::<u64>(), 7);
+
+    let max_u64 = Fraction::from_num(u64::MAX);
+    assert_eq!(max_u64.to_floor::<u64>(), u64::MAX);
+    assert_eq!(max_u64.to_ceil::<u64>(), u64::MAX);
+}
+
+#[test]
+fn fraction_checked_rounding() {
+    let two_and_half = Fraction::from_num(2.5);
+    assert_eq!(two_and_half.checked_to_floor::<u64>(), Some(2));
+    assert_eq!(two_and_half.checked_to_ceil::<u64>(), Some(3));
+    assert_eq!(two_and_half.checked_to_round::<u64>(), Some(3));
+
+    // values above the destination type are rejected instead of panicking
+    let above_u64 = Fraction::from_num(u64::MAX) + Fraction::ONE;
+    assert_eq!(above_u64.checked_to_floor::<u64>(), None);
+    assert_eq!(above_u64.checked_to_ceil::<u64>(), None);
+    assert_eq!(above_u64.checked_to_round::<u64>(), None);
+
+    // rounding up the max fraction overflows the fraction itself
+    assert_eq!(Fraction::MAX.checked_to_ceil::<u128>(), None);
+    assert_eq!(
+        Fraction::MAX.checked_to_floor::<u128>(),
+        Some(Fraction::MAX.to_floor())
+    );
+}
+
+#[test]
+fn fraction_abs_diff() {
+    let a = Fraction::from_num(3.75);
+    let b = Fraction::from_num(1.25);
+    assert_eq!(Fraction::abs_diff(a, b), Fraction::from_num(2.5));
+    assert_eq!(Fraction::abs_diff(b, a), Fraction::from_num(2.5));
+    assert_eq!(Fraction::abs_diff(a, a), Fraction::ZERO);
+    assert_eq!(
+        Fraction::abs_diff(Fraction::MAX, Fraction::ZERO),
+        Fraction::MAX
+    );
+}
+
+#[test]
+fn fraction_mul_div_boundaries() {
+    assert_eq!(
+        Fraction::MAX.checked_mul(Fraction::ONE),
+        Some(Fraction::MAX)
+    );
+    assert_eq!(Fraction::MAX.checked_mul(Fraction::from_num(2)), None);
+    assert_eq!(
+        Fraction::MAX.checked_div(Fraction::ONE),
+        Some(Fraction::MAX)
+    );
+    assert_eq!(Fraction::ONE.checked_div(Fraction::ZERO), None);
+    assert_eq!(
+        Fraction::DELTA.checked_mul(Fraction::DELTA),
+        Some(Fraction::ZERO)
+    );
+
+    // 2^34 * 2^34 = 2^68 is one past the integer range of U68F60
+    let big = Fraction::from_num(1u64 << 34);
+    assert_eq!(big.checked_mul(big), None);
+    let below = Fraction::from_num((1u64 << 34) - 1);
+    assert!(below.checked_mul(big).is_some());
+
+    let amount = Fraction::from_num(u64::MAX);
+    assert_eq!(amount * Fraction::ONE, amount);
+    assert_eq!(amount / Fraction::ONE, amount);
+    assert_eq!(
+        (amount * Fraction::from_percent(50)).to_floor::<u64>(),
+        u64::MAX / 2
+    );
+
+    assert!(Fraction::ONE / 3 * 3 <= Fraction::ONE);
+    assert_eq!(Fraction::ONE.checked_sub(Fraction::from_num(2)), None);
+}
+
+#[test]
+fn fraction_add_near_max() {
+    let half = Fraction::MAX / 2;
+    assert_eq!(
+        half.checked_add(half),
+        Some(Fraction::MAX - Fraction::DELTA)
+    );
+    assert_eq!(
+        (Fraction::MAX - Fraction::ONE).checked_add(Fraction::ONE),
+        Some(Fraction::MAX)
+    );
+    assert_eq!(Fraction::MAX.checked_add(Fraction::DELTA), None);
+
+    // eight market values of 2^65 fill the whole 68 bit integer range
+    let value = Fraction::from_num(1u128 << 65);
+    let total = (0..7).try_fold(value, |total, _| total.checked_add(value));
+    assert_eq!(total, None);
+    let total = (0..6).try_fold(value, |total, _| total.checked_add(value));
+    assert_eq!(total, Some(Fraction::from_num(7u128 << 65)));
+}
+
+#[test]
+fn fraction_pow() {
+    assert_eq!(pow_fraction(Fraction::from_num(2), 0), Some(Fraction::ONE));
+    assert_eq!(
+        pow_fraction(Fraction::from_num(2), 1),
+        Some(Fraction::from_num(2))
+    );
+    assert_eq!(
+        pow_fraction(Fraction::from_num(2), 10),
+        Some(Fraction::from_num(1024))
+    );
+    assert_eq!(
+        Fraction::from_num(0.5).checked_pow(3),
+        Some(Fraction::from_num(0.125))
+    );
+    assert_eq!(
+        pow_fraction(Fraction::from_num(2), 67),
+        Some(Fraction::from_num(1u128 << 67))
+    );
+    assert_eq!(pow_fraction(Fraction::from_num(2), 68), None);
+}
+
+#[test]
+fn fraction_display() {
+    assert_eq!(Fraction::ZERO.to_display().to_string(), "0.0000");
+    assert_eq!(Fraction::ONE.to_display().to_string(), "1.0000");
+    assert_eq!(Fraction::from_num(1.25).to_display().to_string(), "1.2500");
+    assert_eq!(Fraction::from_bps(1).to_display().to_string(), "0.0001");
+}
+
+#[test]
+fn fraction_to_decimal_string() {
+    assert_eq!(Fraction::ZERO.to_decimal_string(6), "0.000000");
+    assert_eq!(Fraction::ONE.to_decimal_string(0), "1");
+    assert_eq!(Fraction::from_num(1.25).to_decimal_string(6), "1.250000");
+    assert_eq!(Fraction::from_num(1.25).to_decimal_string(1), "1.3");
+    assert_eq!(Fraction::from_num(2.5).to_decimal_string(0), "3");
+    assert_eq!(Fraction::from_bps(1).to_decimal_string(4), "0.0001");
+    assert_eq!(
+        (Fraction::ONE - Fraction::DELTA).to_decimal_string(6),
+        "1.000000"
+    );
+    assert_eq!(
+        Fraction::DELTA.to_decimal_string(FRACTION_MAX_DECIMALS + 10),
+        "0.00000000000000000087"
+    );
+    assert_eq!(
+        Fraction::from_num(123_456_789u64).to_decimal_string(2),
+        "123456789.00"
+    );
+}
+
+#[test]
+fn fraction_from_decimal_string() {
+    assert_eq!(Fraction::from_decimal_string("0"), Some(Fraction::ZERO));
+    assert_eq!(Fraction::from_decimal_string("1.0"), Some(Fraction::ONE));
+    assert_eq!(
+        Fraction::from_decimal_string("1.25"),
+        Some(Fraction::from_num(1.25))
+    );
+    assert_eq!(
+        Fraction::from_decimal_string("0.0625"),
+        Some(Fraction::ONE / 16)
+    );
+
+    for invalid in ["", ".5", "-1", "1.2.3", "1e3", " 1", "abc"] {
+        assert_eq!(Fraction::from_decimal_string(invalid), None, "{invalid}");
+    }
+    assert_eq!(Fraction::from_decimal_string("1."), Some(Fraction::ONE));
+    assert_eq!(
+        Fraction::from_decimal_string(&format!("{}", u128::MAX)),
+        None
+    );
+}
+
+#[test]
+fn fraction_decimal_string_round_trip() {
+    let values = [
+        Fraction::ZERO,
+        Fraction::DELTA,
+        Fraction::ONE,
+        Fraction::ONE - Fraction::DELTA,
+        Fraction::from_num(1.25),
+        Fraction::from_num(123.456),
+        Fraction::from_bps(1),
+        Fraction::from_percent(33),
+        Fraction::ONE / 3,
+        Fraction::from_num(u64::MAX) + Fraction::ONE / 7,
+        Fraction::MAX,
+    ];
+    for value in values {
+        let decimal = value.to_decimal_string(FRACTION_MAX_DECIMALS);
+        assert_eq!(
+            Fraction::from_decimal_string(&decimal),
+            Some(value),
+            "{decimal}"
+        );
+    }
+
+    for decimal in ["0.000001", "1.500000", "42.123456", "1000000.999999"] {
+        let value = Fraction::from_decimal_string(decimal).unwrap();
+        assert_eq!(value.to_decimal_string(6), decimal);
+    }
+}
+
+#[test]
+fn big_fraction_from_fraction() {
+    let one = BigFraction::from(Fraction::ONE);
+    assert_eq!(one.0, U256::from(FRACTION_ONE_SCALED));
+    assert_eq!(one, BigFraction::from_num(1u64));
+    assert_eq!(one.to_u128_sf(), FRACTION_ONE_SCALED);
+
+    let value = Fraction::from_num(123.456);
+    let big = BigFraction::from(value);
+    assert_eq!(big.to_u128_sf(), value.to_bits());
+    assert_eq!(Fraction::try_from(big).unwrap(), value);
+
+    let max = BigFraction::from(Fraction::MAX);
+    assert_eq!(Fraction::try_from(max).unwrap(), Fraction::MAX);
+}
+
+#[test]
+fn big_fraction_overflow_to_fraction() {
+    let too_big = BigFraction::from(Fraction::MAX) + BigFraction::from(Fraction::DELTA);
+    assert!(matches!(
+        Fraction::try_from(too_big),
+        Err(LendingError::IntegerOverflow)
+    ));
+
+    let too_big = BigFraction::from_num(u128::MAX);
+    assert!(matches!(
+        Fraction::try_from(too_big),
+        Err(LendingError::IntegerOverflow)
+    ));
+}
+
+#[test]
+fn big_fraction_arithmetic() {
+    let two = BigFraction::from_num(2u64);
+    let three = BigFraction::from_num(3u64);
+
+    assert_eq!(two + three, BigFraction::from_num(5u64));
+    assert_eq!(three - two, BigFraction::from_num(1u64));
+    assert_eq!(two * three, BigFraction::from_num(6u64));
+    assert_eq!(three / two, BigFraction::from(Fraction::from_num(1.5)));
+    assert_eq!(two * 3u64, BigFraction::from_num(6u64));
+    assert_eq!(BigFraction::from_num(6u64) / 3u64, two);
+
+    let mut acc = two;
+    acc += three;
+    acc *= two;
+    acc -= three;
+    acc /= BigFraction::from_num(7u64);
+    assert_eq!(acc, BigFraction::from_num(1u64));
+
+    // results beyond the Fraction range are still representable
+    let huge = BigFraction::from(Fraction::MAX) * BigFraction::from_num(1u64 << 32);
+    assert_eq!(
+        huge / BigFraction::from_num(1u64 << 32),
+        BigFraction::from(Fraction::MAX)
+    );
+}
+
+#[test]
+fn big_fraction_bits_round_trip() {
+    let value = BigFraction::from(Fraction::from_num(1.0001)) * BigFraction::from_num(1u64 << 40);
+    assert_eq!(BigFraction::from_bits(value.to_bits()), value);
+
+    let bytes = BigFractionBytes::from(value);
+    assert_eq!(bytes.value, value.to_bits());
+    assert_eq!(bytes.padding, [0; 2]);
+    assert_eq!(BigFraction::from(bytes), value);
+}
+
+#[test]
+fn u128_u256_conversions() {
+    let value = U128::from(u128::MAX);
+    let widened = U256::from(value);
+    assert_eq!(widened, U256::from(u128::MAX));
+    assert_eq!(U128::try_from(widened).unwrap(), value);
+
+    let overflow = widened + U256::from(1u64);
+    assert!(matches!(
+        U128::try_from(overflow),
+        Err(LendingError::IntegerOverflow)
+    ));
+}
+
+#[test]
+fn cumulative_borrow_rate_compounding() {
+    let initial = BigFraction::from(Fraction::ONE);
+    let rate = BigFraction::from(Fraction::from_num(1.1));
+
+    let mut cumulative = initial;
+    for _ in 0..10 {
+        cumulative *= rate;
+    }
+    let expected = Fraction::from_num(1.1).checked_pow(10).unwrap();
+    let cumulative_f = Fraction::try_from(cumulative).unwrap();
+    assert!(Fraction::abs_diff(cumulative_f, expected) < Fraction::from_num(0.000_000_001));
+
+    let bytes = BigFractionBytes::from(cumulative);
+    assert_eq!(BigFraction::from(bytes), cumulative);
+}
+
+#[test]
+fn obligation_liquidity_accrue_interest() {
+    let initial_rate = BigFraction::from(Fraction::ONE);
+    let mut liquidity = ObligationLiquidity::new(Default::default(), initial_rate);
+    liquidity.borrow(Fraction::from_num(1_000));
+
+    // same rate leaves the debt untouched
+    liquidity.accrue_interest(initial_rate).unwrap();
+    assert_eq!(
+        Fraction::from_bits(liquidity.borrowed_amount_sf),
+        Fraction::from_num(1_000)
+    );
+
+    // 5% growth of the cumulative rate grows the debt by 5%
+    let new_rate = BigFraction::from(Fraction::from_num(1.05));
+    liquidity.accrue_interest(new_rate).unwrap();
+    let borrowed = Fraction::from_bits(liquidity.borrowed_amount_sf);
+    assert!(
+        Fraction::abs_diff(borrowed, Fraction::from_num(1_050)) < Fraction::from_num(0.000_001)
+    );
+    assert_eq!(
+        BigFraction::from(liquidity.cumulative_borrow_rate_bsf),
+        new_rate
+    );
+
+    // a decreasing cumulative rate is rejected
+    assert!(liquidity.accrue_interest(initial_rate).is_err());
+}
@@ -0,0 +1,120 @@
+#[cfg(test)]
+mod helpers;
+use std::rc::Rc;
+
+use cluster_lend::{utils::pda, Reserve, UpdateLendingMarketMode};
+use solana_program_test::*;
+
+use helpers::*;
+use solana_sdk::{signature::Keypair, signer::Signer};
+use spl::TokenAccountFixture;
+use test::{TestFixture, PYTH_USDC_FEED};
+
+#[tokio::test]
+async fn success_global_deposit_cap_blocks_deposits_past_it() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (market_f, reserve_f, _obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+    let reserve_pdas = pda::init_reserve_pdas(&market_f.key, &usdc_mint_f.key);
+    let owner = test_f.payer_keypair();
+
+    let deposit_amount = 100_000_000;
+    let owner_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&owner, 2 * deposit_amount)
+        .await;
+    let owner_collateral_ata = TokenAccountFixture::new(
+        Rc::clone(&test_f.context),
+        &reserve_pdas.collateral_ctoken_mint,
+        &owner.pubkey(),
+    )
+    .await;
+
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_time(120);
+
+    let deposit = |liquidity_amount| {
+        [
+            reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+            reserve_f.deposit_liquidity_ix(
+                liquidity_amount,
+                owner_liquidity_ata.key,
+                owner_collateral_ata.key,
+            ),
+        ]
+    };
+
+    // the running total is kept on the reserve, deposits don't write the market
+    let deposit_ixs = deposit(deposit_amount);
+    assert!(deposit_ixs[1]
+        .accounts
+        .iter()
+        .any(|meta| meta.pubkey == market_f.key && !meta.is_writable));
+
+    // without a cap the deposit only updates the running total
+    test_f
+        .send_transaction(&deposit_ixs, &[&owner])
+        .await
+        .unwrap();
+
+    let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+    let deposit_value = reserve.market_deposit_value;
+    assert!(deposit_value > 0);
+
+    // cap the reserves at 1.5x the current deposit value
+    let global_allowed_deposit_value = deposit_value * 3 / 2;
+    let mut value = [0; 72];
+    value[..8].copy_from_slice(&global_allowed_deposit_value.to_le_bytes());
+    test_f
+        .send_transaction(
+            &[market_f.update_market_ix(
+                UpdateLendingMarketMode::UpdateGlobalAllowedDeposit as u64,
+                value,
+            )],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+
+    test_f
+        .send_transaction(&deposit(40_000_000), &[&owner])
+        .await
+        .unwrap();
+
+    // 1.6x is past the cap
+    let r = test_f
+        .send_transaction(&deposit(20_000_000), &[&owner])
+        .await;
+    assert!(r.is_err());
+
+    let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+    assert!(reserve.market_deposit_value <= global_allowed_deposit_value);
+
+    // redeeming frees up room under the cap
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                reserve_f.redeem_collateral_ix(
+                    50_000_000,
+                    owner_collateral_ata.key,
+                    owner_liquidity_ata.key,
+                ),
+            ],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+
+    let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+    assert!(reserve.market_deposit_value < deposit_value);
+
+    test_f
+        .send_transaction(&deposit(30_000_000), &[&owner])
+        .await
+        .unwrap();
+
+    let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+    assert!(reserve.market_deposit_value <= global_allowed_deposit_value);
+}
@@ -0,0 +1,54 @@
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction},
+    program::{get_return_data, invoke, set_return_data},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+/// Forwards its instruction to cluster_lend, passed as the first account, and re-emits the
+/// return data it reads back so the test can check what a calling program sees
+pub fn cpi_caller_entry(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let (lending_program, cpi_accounts) = accounts
+        .split_first()
+        .ok_or(ProgramError::NotEnoughAccountKeys)?;
+
+    let ix = Instruction {
+        program_id: *lending_program.key,
+        accounts: cpi_accounts
+            .iter()
+            .map(|account| AccountMeta {
+                pubkey: *account.key,
+                is_signer: account.is_signer,
+                is_writable: account.is_writable,
+            })
+            .collect(),
+        data: data.to_vec(),
+    };
+    invoke(&ix, accounts)?;
+
+    let (return_program_id, return_data) =
+        get_return_data().ok_or(ProgramError::InvalidInstructionData)?;
+    if return_program_id != cluster_lend::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    set_return_data(&return_data);
+
+    Ok(())
+}
+
+pub fn via_cpi_caller(cpi_caller_id: Pubkey, ix: Instruction) -> Instruction {
+    let mut accounts = vec![AccountMeta::new_readonly(ix.program_id, false)];
+    accounts.extend(ix.accounts);
+
+    Instruction {
+        program_id: cpi_caller_id,
+        accounts,
+        data: ix.data,
+    }
+}
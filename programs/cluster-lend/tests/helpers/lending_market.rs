@@ -1,7 +1,11 @@
 use anchor_lang::{prelude::*, system_program, InstructionData, ToAccountMetas};
 use anchor_spl::token;
 use anyhow::Result;
-use cluster_lend::utils::pda::lending_market_auth;
+use cluster_lend::{
+    constants::VALUE_BYTE_ARRAY_LEN_RESERVE,
+    utils::pda::{lending_market_auth, obligation_allowlist, reserve_config_template},
+    ReserveConfig,
+};
 use solana_program::{instruction::Instruction, sysvar};
 use solana_program_test::{BanksClientError, ProgramTestContext};
 use solana_sdk::{
@@ -35,8 +39,17 @@ impl LendingMarketFixture {
     }
 
     pub fn update_market_ix(&self, mode: u64, value: [u8; 72]) -> Instruction {
+        self.update_market_with_signer_ix(self.owner, mode, value)
+    }
+
+    pub fn update_market_with_signer_ix(
+        &self,
+        signer: Pubkey,
+        mode: u64,
+        value: [u8; 72],
+    ) -> Instruction {
         let accounts = cluster_lend::accounts::UpdateMarketCtx {
-            owner: self.owner,
+            signer,
             lending_market: self.key,
             system_program: system_program::ID,
         };
@@ -49,6 +62,137 @@ impl LendingMarketFixture {
         ix
     }
 
+    pub fn init_reserve_config_template_ix(&self, kind: u8) -> Instruction {
+        let accounts = cluster_lend::accounts::InitializeReserveConfigTemplateCtx {
+            owner: self.owner,
+            lending_market: self.key,
+            reserve_config_template: reserve_config_template(&self.key, kind),
+            system_program: system_program::ID,
+        };
+        let ix = Instruction {
+            program_id: cluster_lend::id(),
+            accounts: accounts.to_account_metas(Some(true)),
+            data: cluster_lend::instruction::InitializeReserveConfigTemplate { kind }.data(),
+        };
+
+        ix
+    }
+
+    pub fn update_reserve_config_template_ix(
+        &self,
+        kind: u8,
+        config: ReserveConfig,
+    ) -> Instruction {
+        let mut value = [0; VALUE_BYTE_ARRAY_LEN_RESERVE];
+        let data = borsh::BorshSerialize::try_to_vec(&config).unwrap();
+        value.copy_from_slice(data.as_slice());
+
+        let accounts = cluster_lend::accounts::UpdateReserveConfigTemplateCtx {
+            owner: self.owner,
+            lending_market: self.key,
+            reserve_config_template: reserve_config_template(&self.key, kind),
+        };
+        let ix = Instruction {
+            program_id: cluster_lend::id(),
+            accounts: accounts.to_account_metas(Some(true)),
+            data: cluster_lend::instruction::UpdateReserveConfigTemplate { value }.data(),
+        };
+
+        ix
+    }
+
+    pub fn allow_obligation_owner_ix(&self, allowed_owner: Pubkey) -> Instruction {
+        let accounts = cluster_lend::accounts::AllowObligationOwnerCtx {
+            owner: self.owner,
+            lending_market: self.key,
+            allowed_owner,
+            obligation_allowlist: obligation_allowlist(&self.key, &allowed_owner),
+            system_program: system_program::ID,
+        };
+        let ix = Instruction {
+            program_id: cluster_lend::id(),
+            accounts: accounts.to_account_metas(Some(true)),
+            data: cluster_lend::instruction::AllowObligationOwner {}.data(),
+        };
+
+        ix
+    }
+
+    pub fn set_borrow_factor_override_ix(
+        &self,
+        obligation: Pubkey,
+        borrow_factor_override_pct: u64,
+    ) -> Instruction {
+        let accounts = cluster_lend::accounts::SetBorrowFactorOverrideCtx {
+            owner: self.owner,
+            lending_market: self.key,
+            obligation,
+        };
+        let ix = Instruction {
+            program_id: cluster_lend::id(),
+            accounts: accounts.to_account_metas(Some(true)),
+            data: cluster_lend::instruction::SetBorrowFactorOverride {
+                borrow_factor_override_pct,
+            }
+            .data(),
+        };
+
+        ix
+    }
+
+    pub fn revoke_obligation_owner_ix(&self, allowed_owner: Pubkey) -> Instruction {
+        let accounts = cluster_lend::accounts::RevokeObligationOwnerCtx {
+            owner: self.owner,
+            lending_market: self.key,
+            obligation_allowlist: obligation_allowlist(&self.key, &allowed_owner),
+        };
+        let ix = Instruction {
+            program_id: cluster_lend::id(),
+            accounts: accounts.to_account_metas(Some(true)),
+            data: cluster_lend::instruction::RevokeObligationOwner {}.data(),
+        };
+
+        ix
+    }
+
+    pub fn refresh_reserves_ix(&self, reserves: Vec<(Pubkey, Option<Pubkey>)>) -> Instruction {
+        let remain_accounts: Vec<AccountMeta> = reserves
+            .iter()
+            .flat_map(|(reserve, pyth_oracle)| {
+                [
+                    AccountMeta::new(*reserve, false),
+                    AccountMeta::new_readonly(pyth_oracle.unwrap_or(cluster_lend::id()), false),
+                ]
+            })
+            .collect();
+
+        let accounts = cluster_lend::accounts::RefreshReservesCtx {
+            lending_market: self.key,
+        };
+        let ix = Instruction {
+            program_id: cluster_lend::id(),
+            accounts: [accounts.to_account_metas(Some(true)), remain_accounts].concat(),
+            data: cluster_lend::instruction::RefreshReserves {}.data(),
+        };
+
+        ix
+    }
+
+    pub fn contest_liquidation_bond_ix(&self, liquidation_bond: Pubkey) -> Instruction {
+        let accounts = cluster_lend::accounts::ContestLiquidationBondCtx {
+            owner: self.owner,
+            lending_market: self.key,
+            liquidation_bond,
+        };
+        let ix = Instruction {
+            program_id: cluster_lend::id(),
+            accounts: accounts.to_account_metas(Some(true)),
+            data: cluster_lend::instruction::ContestLiquidationBond {}.data(),
+        };
+
+        ix
+    }
+
     pub fn update_owner_ix(&self, new_owner: Pubkey) -> Instruction {
         let accounts = cluster_lend::accounts::UpdateMarketOwnerCtx {
             owner: self.owner,
@@ -64,4 +208,19 @@ impl LendingMarketFixture {
 
         ix
     }
+
+    pub fn update_risk_authority_ix(&self, new_risk_authority: Pubkey) -> Instruction {
+        let accounts = cluster_lend::accounts::UpdateMarketRiskAuthorityCtx {
+            owner: self.owner,
+            lending_market: self.key,
+            new_risk_authority,
+        };
+        let ix = Instruction {
+            program_id: cluster_lend::id(),
+            accounts: accounts.to_account_metas(Some(true)),
+            data: cluster_lend::instruction::UpdateMarketRiskAuthority {}.data(),
+        };
+
+        ix
+    }
 }
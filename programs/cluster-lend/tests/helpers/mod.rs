@@ -1,3 +1,4 @@
+pub mod cpi_caller;
 pub mod lending_market;
 pub mod obligation;
 pub mod reserve;
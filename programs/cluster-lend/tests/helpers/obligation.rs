@@ -2,7 +2,9 @@ use anchor_lang::{prelude::*, system_program, InstructionData, ToAccountMetas};
 use anchor_spl::token;
 use anyhow::Result;
 use cluster_lend::{
-    utils::pda::{init_obligation_pda, init_reserve_pdas_program_id, lending_market_auth},
+    utils::pda::{
+        init_obligation_pda, init_reserve_pdas_program_id, lending_market_auth, liquidation_bond,
+    },
     InitObligationArgs,
 };
 use solana_program::{instruction::Instruction, rent::Rent, sysvar::SysvarId};
@@ -23,12 +25,32 @@ impl ObligationFixture {
         owner: &Keypair,
         lending_market: Pubkey,
         args: InitObligationArgs,
+    ) -> ObligationFixture {
+        Self::new_with_seeds(
+            ctx,
+            owner,
+            lending_market,
+            args,
+            Pubkey::default(),
+            Pubkey::default(),
+        )
+        .await
+    }
+
+    /// Obligation scoped by its seed accounts, mints for tags 1 to 3
+    pub async fn new_with_seeds(
+        ctx: &TestFixture,
+        owner: &Keypair,
+        lending_market: Pubkey,
+        args: InitObligationArgs,
+        seed1_account: Pubkey,
+        seed2_account: Pubkey,
     ) -> ObligationFixture {
         let obligation_key = init_obligation_pda(
             &owner.pubkey(),
             &lending_market,
-            &Pubkey::default(),
-            &Pubkey::default(),
+            &seed1_account,
+            &seed2_account,
             &args,
         );
 
@@ -37,8 +59,9 @@ impl ObligationFixture {
             fee_payer: ctx.payer(),
             lending_market,
             obligation: obligation_key,
-            seed1_account: Pubkey::default(),
-            seed2_account: Pubkey::default(),
+            seed1_account,
+            seed2_account,
+            obligation_allowlist: None,
             rent: Rent::id(),
             token_program: token::ID,
             system_program: system_program::ID,
@@ -60,13 +83,37 @@ impl ObligationFixture {
     }
 
     pub fn initialize_obligation_ix(&self, args: InitObligationArgs) -> Instruction {
+        self.initialize_obligation_with_allowlist_ix(args, None)
+    }
+
+    pub fn initialize_obligation_with_allowlist_ix(
+        &self,
+        args: InitObligationArgs,
+        obligation_allowlist: Option<Pubkey>,
+    ) -> Instruction {
+        self.initialize_obligation_with_seeds_ix(
+            args,
+            Pubkey::default(),
+            Pubkey::default(),
+            obligation_allowlist,
+        )
+    }
+
+    pub fn initialize_obligation_with_seeds_ix(
+        &self,
+        args: InitObligationArgs,
+        seed1_account: Pubkey,
+        seed2_account: Pubkey,
+        obligation_allowlist: Option<Pubkey>,
+    ) -> Instruction {
         let accounts = cluster_lend::accounts::InitializeObligationCtx {
             owner: self.owner,
             fee_payer: self.payer,
             lending_market: self.lending_market,
             obligation: self.key,
-            seed1_account: Pubkey::default(),
-            seed2_account: Pubkey::default(),
+            seed1_account,
+            seed2_account,
+            obligation_allowlist,
             rent: Rent::id(),
             token_program: token::ID,
             system_program: system_program::ID,
@@ -104,6 +151,140 @@ impl ObligationFixture {
         ix
     }
 
+    pub fn request_elevation_group_ix(
+        &self,
+        elevation_group: u8,
+        reserve_keys: Vec<Pubkey>,
+    ) -> Instruction {
+        let remain_accounts: Vec<AccountMeta> = reserve_keys
+            .iter()
+            .map(|t| AccountMeta {
+                is_signer: false,
+                is_writable: true,
+                pubkey: *t,
+            })
+            .collect();
+
+        let accounts = cluster_lend::accounts::RequestElevationGroupCtx {
+            owner: self.owner,
+            obligation: self.key,
+            lending_market: self.lending_market,
+        };
+
+        let ix = Instruction {
+            program_id: cluster_lend::id(),
+            accounts: [accounts.to_account_metas(Some(true)), remain_accounts].concat(),
+            data: cluster_lend::instruction::RequestElevationGroup { elevation_group }.data(),
+        };
+
+        ix
+    }
+
+    pub fn set_aggressive_mode_ix(
+        &self,
+        ltv_boost_pct: u8,
+        reserve_keys: Vec<Pubkey>,
+    ) -> Instruction {
+        let remain_accounts: Vec<AccountMeta> = reserve_keys
+            .iter()
+            .map(|t| AccountMeta {
+                is_signer: false,
+                is_writable: true,
+                pubkey: *t,
+            })
+            .collect();
+
+        let accounts = cluster_lend::accounts::SetAggressiveModeCtx {
+            owner: self.owner,
+            obligation: self.key,
+            lending_market: self.lending_market,
+        };
+
+        let ix = Instruction {
+            program_id: cluster_lend::id(),
+            accounts: [accounts.to_account_metas(Some(true)), remain_accounts].concat(),
+            data: cluster_lend::instruction::SetAggressiveMode { ltv_boost_pct }.data(),
+        };
+
+        ix
+    }
+
+    pub fn set_autodeleverage_target_ix(&self, target_ltv_pct: u8, bonus_bps: u16) -> Instruction {
+        let accounts = cluster_lend::accounts::SetAutodeleverageTargetCtx {
+            owner: self.owner,
+            obligation: self.key,
+            lending_market: self.lending_market,
+        };
+
+        let ix = Instruction {
+            program_id: cluster_lend::id(),
+            accounts: accounts.to_account_metas(Some(true)),
+            data: cluster_lend::instruction::SetAutodeleverageTarget {
+                target_ltv_pct,
+                bonus_bps,
+            }
+            .data(),
+        };
+
+        ix
+    }
+
+    pub fn set_user_ltv_cap_ix(&self, user_ltv_cap_pct: u8) -> Instruction {
+        let accounts = cluster_lend::accounts::SetUserLtvCapCtx {
+            owner: self.owner,
+            obligation: self.key,
+            lending_market: self.lending_market,
+        };
+
+        let ix = Instruction {
+            program_id: cluster_lend::id(),
+            accounts: accounts.to_account_metas(Some(true)),
+            data: cluster_lend::instruction::SetUserLtvCap { user_ltv_cap_pct }.data(),
+        };
+
+        ix
+    }
+
+    pub fn set_reserve_allowlist_ix(
+        &self,
+        allowed_deposit_reserves: [Pubkey; 4],
+        allowed_borrow_reserves: [Pubkey; 2],
+    ) -> Instruction {
+        let accounts = cluster_lend::accounts::SetObligationReserveAllowlistCtx {
+            owner: self.owner,
+            obligation: self.key,
+            lending_market: self.lending_market,
+        };
+
+        let ix = Instruction {
+            program_id: cluster_lend::id(),
+            accounts: accounts.to_account_metas(Some(true)),
+            data: cluster_lend::instruction::SetObligationReserveAllowlist {
+                allowed_deposit_reserves,
+                allowed_borrow_reserves,
+            }
+            .data(),
+        };
+
+        ix
+    }
+
+    pub fn reorder_deposits_ix(&self, order: [u8; 8]) -> Instruction {
+        let accounts = cluster_lend::accounts::ReorderObligationDepositsCtx {
+            owner: self.owner,
+            obligation: self.key,
+            lending_market: self.lending_market,
+        };
+
+        let ix = Instruction {
+            program_id: cluster_lend::id(),
+            accounts: accounts.to_account_metas(Some(true)),
+            data: cluster_lend::instruction::ReorderObligationDeposits { order }.data(),
+        };
+
+        ix
+    }
+
     pub fn deposit_collateral_ix(
         &self,
         collateral_amount: u64,
@@ -205,11 +386,125 @@ impl ObligationFixture {
         ix
     }
 
+    pub fn withdraw_collateral_and_redeem_ix(
+        &self,
+        collateral_amount: u64,
+        reserve: &ReserveFixture,
+        user_destination_liquidity: Pubkey,
+    ) -> Instruction {
+        let lending_market_authority = lending_market_auth(&self.lending_market);
+
+        let pdas = init_reserve_pdas_program_id(
+            &cluster_lend::ID,
+            &self.lending_market,
+            &reserve.liquidity_mint,
+        );
+
+        let accounts = cluster_lend::accounts::WithdrawObligationCollateralAndRedeemCtx {
+            owner: self.owner,
+            lending_market: self.lending_market,
+            lending_market_authority,
+            obligation: self.key,
+            withdraw_reserve: reserve.key,
+            reserve_source_collateral: pdas.collateral_supply_vault,
+            reserve_collateral_mint: pdas.collateral_ctoken_mint,
+            reserve_liquidity_supply: pdas.liquidity_supply_vault,
+            user_destination_liquidity,
+            token_program: token::ID,
+            instruction_sysvar_account: Instructions::id(),
+        };
+
+        let ix = Instruction {
+            program_id: cluster_lend::id(),
+            accounts: accounts.to_account_metas(Some(true)),
+            data: cluster_lend::instruction::WithdrawObligationCollateralAndRedeem {
+                collateral_amount,
+            }
+            .data(),
+        };
+
+        ix
+    }
+
+    pub fn transfer_collateral_ix(
+        &self,
+        collateral_amount: u64,
+        reserve: &ReserveFixture,
+        destination_obligation: Pubkey,
+    ) -> Instruction {
+        let accounts = cluster_lend::accounts::TransferObligationCollateralCtx {
+            owner: self.owner,
+            source_obligation: self.key,
+            destination_obligation,
+            lending_market: self.lending_market,
+            reserve: reserve.key,
+            instruction_sysvar_account: Instructions::id(),
+        };
+
+        let ix = Instruction {
+            program_id: cluster_lend::id(),
+            accounts: accounts.to_account_metas(Some(true)),
+            data: cluster_lend::instruction::TransferObligationCollateral { collateral_amount }
+                .data(),
+        };
+
+        ix
+    }
+
+    pub fn claim_rewards_ix(
+        &self,
+        reserve_keys: Vec<Pubkey>,
+        reward_vault: Pubkey,
+        user_destination_reward: Pubkey,
+    ) -> Instruction {
+        let remain_accounts: Vec<AccountMeta> = reserve_keys
+            .iter()
+            .map(|t| AccountMeta {
+                is_signer: false,
+                is_writable: false,
+                pubkey: *t,
+            })
+            .collect();
+
+        let accounts = cluster_lend::accounts::ClaimRewardsCtx {
+            owner: self.owner,
+            obligation: self.key,
+            lending_market: self.lending_market,
+            lending_market_authority: lending_market_auth(&self.lending_market),
+            reward_vault,
+            user_destination_reward,
+            token_program: token::ID,
+        };
+
+        let ix = Instruction {
+            program_id: cluster_lend::id(),
+            accounts: [accounts.to_account_metas(Some(true)), remain_accounts].concat(),
+            data: cluster_lend::instruction::ClaimRewards {}.data(),
+        };
+
+        ix
+    }
+
     pub fn borrow_liquidity_ix(
         &self,
         liquidity_amount: u64,
         reserve: &ReserveFixture,
         user_destination_liquidity: Pubkey,
+    ) -> Instruction {
+        self.borrow_liquidity_with_host_fee_ix(
+            liquidity_amount,
+            reserve,
+            user_destination_liquidity,
+            None,
+        )
+    }
+
+    pub fn borrow_liquidity_with_host_fee_ix(
+        &self,
+        liquidity_amount: u64,
+        reserve: &ReserveFixture,
+        user_destination_liquidity: Pubkey,
+        host_fee_receiver: Option<Pubkey>,
     ) -> Instruction {
         let lending_market_authority = lending_market_auth(&self.lending_market);
 
@@ -230,6 +525,7 @@ impl ObligationFixture {
             user_destination_liquidity,
             token_program: token::ID,
             instruction_sysvar_account: Instructions::id(),
+            host_fee_receiver,
         };
         let ix = Instruction {
             program_id: cluster_lend::id(),
@@ -261,6 +557,7 @@ impl ObligationFixture {
             user_source_liquidity,
             token_program: token::ID,
             instruction_sysvar_account: Instructions::id(),
+            repay_reserve_liquidity_fee_receiver: Some(pdas.fee_vault),
         };
         let ix = Instruction {
             program_id: cluster_lend::id(),
@@ -271,11 +568,49 @@ impl ObligationFixture {
         ix
     }
 
+    pub fn repay_with_collateral_ix(
+        &self,
+        collateral_amount: u64,
+        reserve: &ReserveFixture,
+    ) -> Instruction {
+        let lending_market_authority = lending_market_auth(&self.lending_market);
+
+        let pdas = init_reserve_pdas_program_id(
+            &cluster_lend::ID,
+            &self.lending_market,
+            &reserve.liquidity_mint,
+        );
+
+        let accounts = cluster_lend::accounts::RepayObligationLiquidityWithCollateralCtx {
+            owner: self.owner,
+            obligation: self.key,
+            lending_market: self.lending_market,
+            lending_market_authority,
+            reserve: reserve.key,
+            reserve_collateral_mint: pdas.collateral_ctoken_mint,
+            reserve_source_collateral: pdas.collateral_supply_vault,
+            reserve_liquidity_supply: pdas.liquidity_supply_vault,
+            token_program: token::ID,
+            instruction_sysvar_account: Instructions::id(),
+        };
+        let ix = Instruction {
+            program_id: cluster_lend::id(),
+            accounts: accounts.to_account_metas(Some(true)),
+            data: cluster_lend::instruction::RepayObligationLiquidityWithCollateral {
+                collateral_amount,
+            }
+            .data(),
+        };
+
+        ix
+    }
+
     pub fn liquidate_ix(
         &self,
         liquidity_amount: u64,
         max_allowed_ltv_override_percent: u64,
         min_acceptable_received_collateral_amount: u64,
+        min_acceptable_received_liquidity_amount: u64,
         liquidator: Pubkey,
         repay_reserve: Pubkey,
         repay_reserve_liquidity_supply: Pubkey,
@@ -284,6 +619,39 @@ impl ObligationFixture {
         user_destination_collateral: Pubkey,
         user_source_liquidity: Pubkey,
         user_destination_liquidity: Pubkey,
+    ) -> Instruction {
+        self.liquidate_with_insurance_vault_ix(
+            liquidity_amount,
+            max_allowed_ltv_override_percent,
+            min_acceptable_received_collateral_amount,
+            min_acceptable_received_liquidity_amount,
+            liquidator,
+            repay_reserve,
+            repay_reserve_liquidity_supply,
+            withdraw_reserve,
+            withdraw_liquidity_mint,
+            user_destination_collateral,
+            user_source_liquidity,
+            user_destination_liquidity,
+            None,
+        )
+    }
+
+    pub fn liquidate_with_insurance_vault_ix(
+        &self,
+        liquidity_amount: u64,
+        max_allowed_ltv_override_percent: u64,
+        min_acceptable_received_collateral_amount: u64,
+        min_acceptable_received_liquidity_amount: u64,
+        liquidator: Pubkey,
+        repay_reserve: Pubkey,
+        repay_reserve_liquidity_supply: Pubkey,
+        withdraw_reserve: Pubkey,
+        withdraw_liquidity_mint: Pubkey,
+        user_destination_collateral: Pubkey,
+        user_source_liquidity: Pubkey,
+        user_destination_liquidity: Pubkey,
+        withdraw_reserve_insurance_vault: Option<Pubkey>,
     ) -> Instruction {
         let lending_market_authority = lending_market_auth(&self.lending_market);
 
@@ -310,6 +678,9 @@ impl ObligationFixture {
             user_destination_liquidity,
             token_program: token::ID,
             instruction_sysvar_account: Instructions::id(),
+            liquidation_bond: Some(liquidation_bond(&self.key, &liquidator)),
+            system_program: Some(system_program::ID),
+            withdraw_reserve_insurance_vault,
         };
         let ix = Instruction {
             program_id: cluster_lend::id(),
@@ -318,10 +689,155 @@ impl ObligationFixture {
                 liquidity_amount,
                 max_allowed_ltv_override_percent,
                 min_acceptable_received_collateral_amount,
+                min_acceptable_received_liquidity_amount,
+            }
+            .data(),
+        };
+
+        ix
+    }
+
+    pub fn simulate_liquidation_ix(
+        &self,
+        liquidity_amount: u64,
+        repay_reserve: Pubkey,
+        withdraw_reserve: Pubkey,
+    ) -> Instruction {
+        let accounts = cluster_lend::accounts::SimulateLiquidationCtx {
+            lending_market: self.lending_market,
+            obligation: self.key,
+            repay_reserve,
+            withdraw_reserve,
+        };
+        let ix = Instruction {
+            program_id: cluster_lend::id(),
+            accounts: accounts.to_account_metas(Some(true)),
+            data: cluster_lend::instruction::SimulateLiquidation { liquidity_amount }.data(),
+        };
+
+        ix
+    }
+
+    pub fn deleverage_to_target_ix(
+        &self,
+        liquidity_amount: u64,
+        min_acceptable_received_collateral_amount: u64,
+        liquidator: Pubkey,
+        repay_reserve: Pubkey,
+        repay_reserve_liquidity_supply: Pubkey,
+        withdraw_reserve: Pubkey,
+        withdraw_liquidity_mint: Pubkey,
+        user_destination_collateral: Pubkey,
+        user_source_liquidity: Pubkey,
+        user_destination_liquidity: Pubkey,
+    ) -> Instruction {
+        let lending_market_authority = lending_market_auth(&self.lending_market);
+
+        let pdas = init_reserve_pdas_program_id(
+            &cluster_lend::ID,
+            &self.lending_market,
+            &withdraw_liquidity_mint,
+        );
+
+        let accounts = cluster_lend::accounts::LiquidateObligationCtx {
+            liquidator,
+            lending_market: self.lending_market,
+            lending_market_authority,
+            obligation: self.key,
+            repay_reserve,
+            repay_reserve_liquidity_supply,
+            withdraw_reserve,
+            withdraw_reserve_collateral_mint: pdas.collateral_ctoken_mint,
+            withdraw_reserve_collateral_supply: pdas.collateral_supply_vault,
+            withdraw_reserve_liquidity_fee_receiver: pdas.fee_vault,
+            withdraw_reserve_liquidity_supply: pdas.liquidity_supply_vault,
+            user_source_liquidity,
+            user_destination_collateral,
+            user_destination_liquidity,
+            token_program: token::ID,
+            instruction_sysvar_account: Instructions::id(),
+            liquidation_bond: None,
+            system_program: None,
+            withdraw_reserve_insurance_vault: None,
+        };
+        let ix = Instruction {
+            program_id: cluster_lend::id(),
+            accounts: accounts.to_account_metas(Some(true)),
+            data: cluster_lend::instruction::DeleverageObligationToTarget {
+                liquidity_amount,
+                min_acceptable_received_collateral_amount,
             }
             .data(),
         };
 
         ix
     }
+
+    pub fn refund_liquidation_bond_ix(&self, liquidator: Pubkey) -> Instruction {
+        let accounts = cluster_lend::accounts::RefundLiquidationBondCtx {
+            liquidator,
+            lending_market: self.lending_market,
+            liquidation_bond: liquidation_bond(&self.key, &liquidator),
+        };
+        let ix = Instruction {
+            program_id: cluster_lend::id(),
+            accounts: accounts.to_account_metas(Some(true)),
+            data: cluster_lend::instruction::RefundLiquidationBond {}.data(),
+        };
+
+        ix
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn liquidate_obligations_batch_ix(
+    lending_market: Pubkey,
+    obligations: Vec<Pubkey>,
+    liquidity_amount: u64,
+    liquidator: Pubkey,
+    repay_reserve: Pubkey,
+    repay_reserve_liquidity_supply: Pubkey,
+    withdraw_reserve: Pubkey,
+    withdraw_liquidity_mint: Pubkey,
+    user_destination_collateral: Pubkey,
+    user_source_liquidity: Pubkey,
+    user_destination_liquidity: Pubkey,
+) -> Instruction {
+    let remain_accounts: Vec<AccountMeta> = obligations
+        .iter()
+        .map(|obligation| AccountMeta::new(*obligation, false))
+        .collect();
+
+    let pdas =
+        init_reserve_pdas_program_id(&cluster_lend::ID, &lending_market, &withdraw_liquidity_mint);
+
+    let accounts = cluster_lend::accounts::LiquidateObligationsBatchCtx {
+        liquidator,
+        lending_market,
+        lending_market_authority: lending_market_auth(&lending_market),
+        repay_reserve,
+        repay_reserve_liquidity_supply,
+        withdraw_reserve,
+        withdraw_reserve_collateral_mint: pdas.collateral_ctoken_mint,
+        withdraw_reserve_collateral_supply: pdas.collateral_supply_vault,
+        withdraw_reserve_liquidity_supply: pdas.liquidity_supply_vault,
+        withdraw_reserve_liquidity_fee_receiver: pdas.fee_vault,
+        user_source_liquidity,
+        user_destination_collateral,
+        user_destination_liquidity,
+        token_program: token::ID,
+        instruction_sysvar_account: Instructions::id(),
+        withdraw_reserve_insurance_vault: None,
+    };
+
+    Instruction {
+        program_id: cluster_lend::id(),
+        accounts: [accounts.to_account_metas(Some(true)), remain_accounts].concat(),
+        data: cluster_lend::instruction::LiquidateObligationsBatch {
+            liquidity_amount,
+            min_acceptable_received_collateral_amount: 0,
+            min_acceptable_received_liquidity_amount: 0,
+        }
+        .data(),
+    }
 }
@@ -1,10 +1,13 @@
-use anchor_lang::{prelude::*, system_program, InstructionData, ToAccountMetas};
+use anchor_lang::{prelude::*, system_program, Discriminator, InstructionData, ToAccountMetas};
 use anchor_spl::token::{self, Token};
 use anyhow::Result;
 use cluster_lend::{
-    constants::VALUE_BYTE_ARRAY_LEN_RESERVE,
-    utils::pda::{init_reserve_pdas_program_id, lending_market_auth},
-    ReserveConfig,
+    constants::{PROGRAM_VERSION, VALUE_BYTE_ARRAY_LEN_RESERVE},
+    utils::{
+        pda::{init_reserve_pdas_program_id, lending_market_auth},
+        BigFraction, Fraction,
+    },
+    PriceStatusFlags, Reserve, ReserveConfig, ReserveStatus, UpdateConfigMode,
 };
 use solana_program::instruction::Instruction;
 use solana_sdk::{
@@ -22,6 +25,13 @@ pub struct ReserveFixture {
 
 impl ReserveFixture {
     pub fn initialize_reserve_ix(&self) -> Instruction {
+        self.initialize_reserve_with_template_ix(None)
+    }
+
+    pub fn initialize_reserve_with_template_ix(
+        &self,
+        reserve_config_template: Option<Pubkey>,
+    ) -> Instruction {
         let lending_market_authority = lending_market_auth(&self.lending_market);
         let pdas = init_reserve_pdas_program_id(
             &cluster_lend::ID,
@@ -39,6 +49,7 @@ impl ReserveFixture {
             reserve_liquidity_supply: pdas.liquidity_supply_vault,
             reserve_collateral_supply: pdas.collateral_supply_vault,
             fee_receiver: pdas.fee_vault,
+            reserve_config_template,
             rent: rent::Rent::id(),
             token_program: token::ID,
             system_program: system_program::ID,
@@ -60,7 +71,8 @@ impl ReserveFixture {
         let accounts = cluster_lend::accounts::UpdateReserveCtx {
             reserve: self.key,
             lending_market: self.lending_market,
-            owner: self.owner,
+            signer: self.owner,
+            pyth_oracle: None,
         };
         let ix = Instruction {
             program_id: cluster_lend::id(),
@@ -71,11 +83,61 @@ impl ReserveFixture {
         ix
     }
 
+    pub fn update_reserve_pyth_price_ix(&self, pyth_price: Pubkey) -> Instruction {
+        let accounts = cluster_lend::accounts::UpdateReserveCtx {
+            reserve: self.key,
+            lending_market: self.lending_market,
+            signer: self.owner,
+            pyth_oracle: Some(pyth_price),
+        };
+        let ix = Instruction {
+            program_id: cluster_lend::id(),
+            accounts: accounts.to_account_metas(Some(true)),
+            data: cluster_lend::instruction::UpdateReserveMode {
+                mode: UpdateConfigMode::UpdatePythPrice as u64,
+                value: pyth_price.to_bytes(),
+            }
+            .data(),
+        };
+
+        ix
+    }
+
+    pub fn update_reserve_fallback_oracle_ix(&self, fallback_oracle: Pubkey) -> Instruction {
+        let accounts = cluster_lend::accounts::UpdateReserveCtx {
+            reserve: self.key,
+            lending_market: self.lending_market,
+            signer: self.owner,
+            pyth_oracle: Some(fallback_oracle),
+        };
+        let ix = Instruction {
+            program_id: cluster_lend::id(),
+            accounts: accounts.to_account_metas(Some(true)),
+            data: cluster_lend::instruction::UpdateReserveMode {
+                mode: UpdateConfigMode::UpdateTokenInfoFallbackOracle as u64,
+                value: fallback_oracle.to_bytes(),
+            }
+            .data(),
+        };
+
+        ix
+    }
+
     pub fn update_reserve_mode_ix(&self, mode: u64, value: [u8; 32]) -> Instruction {
+        self.update_reserve_mode_with_signer_ix(self.owner, mode, value)
+    }
+
+    pub fn update_reserve_mode_with_signer_ix(
+        &self,
+        signer: Pubkey,
+        mode: u64,
+        value: [u8; 32],
+    ) -> Instruction {
         let accounts = cluster_lend::accounts::UpdateReserveCtx {
             reserve: self.key,
             lending_market: self.lending_market,
-            owner: self.owner,
+            signer,
+            pyth_oracle: None,
         };
         let ix = Instruction {
             program_id: cluster_lend::id(),
@@ -86,16 +148,112 @@ impl ReserveFixture {
         ix
     }
 
+    pub fn apply_reserve_config_template_ix(&self, reserve_config_template: Pubkey) -> Instruction {
+        let accounts = cluster_lend::accounts::ApplyReserveConfigTemplateCtx {
+            owner: self.owner,
+            lending_market: self.lending_market,
+            reserve: self.key,
+            reserve_config_template,
+        };
+        let ix = Instruction {
+            program_id: cluster_lend::id(),
+            accounts: accounts.to_account_metas(Some(true)),
+            data: cluster_lend::instruction::ApplyReserveConfigTemplate {}.data(),
+        };
+
+        ix
+    }
+
+    pub fn migrate_reserve_ix(&self) -> Instruction {
+        let accounts = cluster_lend::accounts::MigrateReserveCtx {
+            owner: self.owner,
+            lending_market: self.lending_market,
+            reserve: self.key,
+        };
+        let ix = Instruction {
+            program_id: cluster_lend::id(),
+            accounts: accounts.to_account_metas(Some(true)),
+            data: cluster_lend::instruction::MigrateReserve {}.data(),
+        };
+
+        ix
+    }
+
+    pub fn set_reserve_vaults_ix(
+        &self,
+        liquidity_supply_vault: Pubkey,
+        fee_vault: Pubkey,
+        collateral_supply_vault: Pubkey,
+    ) -> Instruction {
+        let accounts = cluster_lend::accounts::SetReserveVaultsCtx {
+            owner: self.owner,
+            lending_market: self.lending_market,
+            reserve: self.key,
+            lending_market_authority: lending_market_auth(&self.lending_market),
+            liquidity_supply_vault,
+            fee_vault,
+            collateral_supply_vault,
+        };
+        let ix = Instruction {
+            program_id: cluster_lend::id(),
+            accounts: accounts.to_account_metas(Some(true)),
+            data: cluster_lend::instruction::SetReserveVaults {}.data(),
+        };
+
+        ix
+    }
+
     pub fn refresh_ix(&self, pyth_oracle: Option<Pubkey>) -> Instruction {
+        self.refresh_with_fallback_ix(pyth_oracle, None)
+    }
+
+    pub fn refresh_with_fallback_ix(
+        &self,
+        pyth_oracle: Option<Pubkey>,
+        fallback_oracle: Option<Pubkey>,
+    ) -> Instruction {
+        self.refresh_reserve_ix(pyth_oracle, fallback_oracle, false)
+    }
+
+    pub fn force_refresh_ix(&self, pyth_oracle: Option<Pubkey>) -> Instruction {
+        self.refresh_reserve_ix(pyth_oracle, None, true)
+    }
+
+    fn refresh_reserve_ix(
+        &self,
+        pyth_oracle: Option<Pubkey>,
+        fallback_oracle: Option<Pubkey>,
+        force_price_refresh: bool,
+    ) -> Instruction {
         let accounts = cluster_lend::accounts::RefreshReserveCtx {
             reserve: self.key,
             lending_market: self.lending_market,
             pyth_oracle,
+            fallback_oracle,
         };
         let ix = Instruction {
             program_id: cluster_lend::id(),
             accounts: accounts.to_account_metas(Some(true)),
-            data: cluster_lend::instruction::RefreshReserve {}.data(),
+            data: if force_price_refresh {
+                cluster_lend::instruction::ForceRefreshReserve {}.data()
+            } else {
+                cluster_lend::instruction::RefreshReserve {}.data()
+            },
+        };
+
+        ix
+    }
+
+    pub fn push_price_ix(&self, price_authority: Pubkey, price_sf: u128) -> Instruction {
+        let accounts = cluster_lend::accounts::PushReservePriceCtx {
+            price_authority,
+            lending_market: self.lending_market,
+            reserve: self.key,
+        };
+        let ix = Instruction {
+            program_id: cluster_lend::id(),
+            accounts: accounts.to_account_metas(Some(true)),
+            data: cluster_lend::instruction::PushReservePrice { price_sf }.data(),
         };
 
         ix
@@ -136,11 +294,271 @@ impl ReserveFixture {
         ix
     }
 
+    pub fn donate_liquidity_ix(
+        &self,
+        liquidity_amount: u64,
+        owner_source_liquidity: Pubkey,
+    ) -> Instruction {
+        let pdas = init_reserve_pdas_program_id(
+            &cluster_lend::ID,
+            &self.lending_market,
+            &self.liquidity_mint,
+        );
+
+        let accounts = cluster_lend::accounts::DonateReserveLiquidityCtx {
+            owner: self.owner,
+            lending_market: self.lending_market,
+            reserve: self.key,
+            reserve_liquidity_supply: pdas.liquidity_supply_vault,
+            owner_source_liquidity,
+            token_program: Token::id(),
+        };
+        let ix = Instruction {
+            program_id: cluster_lend::id(),
+            accounts: accounts.to_account_metas(Some(true)),
+            data: cluster_lend::instruction::DonateReserveLiquidity { liquidity_amount }.data(),
+        };
+
+        ix
+    }
+
+    pub fn reconcile_ix(&self) -> Instruction {
+        let pdas = init_reserve_pdas_program_id(
+            &cluster_lend::ID,
+            &self.lending_market,
+            &self.liquidity_mint,
+        );
+
+        let accounts = cluster_lend::accounts::ReconcileReserveCtx {
+            reserve: self.key,
+            reserve_liquidity_supply: pdas.liquidity_supply_vault,
+        };
+        let ix = Instruction {
+            program_id: cluster_lend::id(),
+            accounts: accounts.to_account_metas(Some(true)),
+            data: cluster_lend::instruction::ReconcileReserve {}.data(),
+        };
+
+        ix
+    }
+
+    pub fn redeem_fees_ix(&self) -> Instruction {
+        let pdas = init_reserve_pdas_program_id(
+            &cluster_lend::ID,
+            &self.lending_market,
+            &self.liquidity_mint,
+        );
+
+        let accounts = cluster_lend::accounts::RedeemFeesCtx {
+            reserve: self.key,
+            reserve_liquidity_fee_receiver: pdas.fee_vault,
+            reserve_supply_liquidity: pdas.liquidity_supply_vault,
+            lending_market: self.lending_market,
+            lending_market_authority: lending_market_auth(&self.lending_market),
+            token_program: Token::id(),
+        };
+        let ix = Instruction {
+            program_id: cluster_lend::id(),
+            accounts: accounts.to_account_metas(Some(true)),
+            data: cluster_lend::instruction::RedeemFees {}.data(),
+        };
+
+        ix
+    }
+
+    pub fn withdraw_protocol_fees_ix(&self, destination: Pubkey, amount: u64) -> Instruction {
+        let pdas = init_reserve_pdas_program_id(
+            &cluster_lend::ID,
+            &self.lending_market,
+            &self.liquidity_mint,
+        );
+
+        let accounts = cluster_lend::accounts::WithdrawProtocolFeesCtx {
+            owner: self.owner,
+            lending_market: self.lending_market,
+            reserve: self.key,
+            lending_market_authority: lending_market_auth(&self.lending_market),
+            fee_vault: pdas.fee_vault,
+            lending_market_owner_ata: destination,
+            token_program: Token::id(),
+        };
+        let ix = Instruction {
+            program_id: cluster_lend::id(),
+            accounts: accounts.to_account_metas(Some(true)),
+            data: cluster_lend::instruction::WithdrawProtocolFees { amount }.data(),
+        };
+
+        ix
+    }
+
+    pub fn initialize_insurance_vault_ix(&self) -> Instruction {
+        let pdas = init_reserve_pdas_program_id(
+            &cluster_lend::ID,
+            &self.lending_market,
+            &self.liquidity_mint,
+        );
+
+        let accounts = cluster_lend::accounts::InitializeInsuranceVaultCtx {
+            owner: self.owner,
+            lending_market: self.lending_market,
+            reserve: self.key,
+            lending_market_authority: lending_market_auth(&self.lending_market),
+            reserve_liquidity_mint: self.liquidity_mint,
+            insurance_vault: pdas.insurance_vault,
+            rent: rent::Rent::id(),
+            token_program: Token::id(),
+            system_program: system_program::ID,
+        };
+
+        Instruction {
+            program_id: cluster_lend::id(),
+            accounts: accounts.to_account_metas(Some(true)),
+            data: cluster_lend::instruction::InitializeInsuranceVault {}.data(),
+        }
+    }
+
+    pub fn cover_bad_debt_from_insurance_ix(&self, liquidity_amount: u64) -> Instruction {
+        let pdas = init_reserve_pdas_program_id(
+            &cluster_lend::ID,
+            &self.lending_market,
+            &self.liquidity_mint,
+        );
+
+        let accounts = cluster_lend::accounts::CoverBadDebtFromInsuranceCtx {
+            owner: self.owner,
+            lending_market: self.lending_market,
+            reserve: self.key,
+            lending_market_authority: lending_market_auth(&self.lending_market),
+            reserve_liquidity_supply: pdas.liquidity_supply_vault,
+            insurance_vault: pdas.insurance_vault,
+            token_program: Token::id(),
+        };
+
+        Instruction {
+            program_id: cluster_lend::id(),
+            accounts: accounts.to_account_metas(Some(true)),
+            data: cluster_lend::instruction::CoverBadDebtFromInsurance { liquidity_amount }.data(),
+        }
+    }
+
+    pub fn redeem_collateral_ix(
+        &self,
+        collateral_amount: u64,
+        user_source_collateral: Pubkey,
+        user_destination_liquidity: Pubkey,
+    ) -> Instruction {
+        self.redeem_collateral_with_owner_ix(
+            self.owner,
+            collateral_amount,
+            user_source_collateral,
+            user_destination_liquidity,
+        )
+    }
+
+    pub fn redeem_collateral_with_owner_ix(
+        &self,
+        owner: Pubkey,
+        collateral_amount: u64,
+        user_source_collateral: Pubkey,
+        user_destination_liquidity: Pubkey,
+    ) -> Instruction {
+        let mut accounts =
+            self.redeem_collateral_accounts(user_source_collateral, user_destination_liquidity);
+        accounts.owner = owner;
+
+        Instruction {
+            program_id: cluster_lend::id(),
+            accounts: accounts.to_account_metas(Some(true)),
+            data: cluster_lend::instruction::RedeemReserveCollateral { collateral_amount }.data(),
+        }
+    }
+
+    pub fn redeem_collateral_with_authority_ix(
+        &self,
+        owner: Pubkey,
+        user_transfer_authority: Pubkey,
+        collateral_amount: u64,
+        user_source_collateral: Pubkey,
+        user_destination_liquidity: Pubkey,
+    ) -> Instruction {
+        let mut accounts =
+            self.redeem_collateral_accounts(user_source_collateral, user_destination_liquidity);
+        accounts.owner = owner;
+        accounts.user_transfer_authority = Some(user_transfer_authority);
+
+        Instruction {
+            program_id: cluster_lend::id(),
+            accounts: accounts.to_account_metas(Some(true)),
+            data: cluster_lend::instruction::RedeemReserveCollateral { collateral_amount }.data(),
+        }
+    }
+
+    pub fn redeem_collateral_exact_liquidity_ix(
+        &self,
+        liquidity_amount: u64,
+        user_source_collateral: Pubkey,
+        user_destination_liquidity: Pubkey,
+    ) -> Instruction {
+        Instruction {
+            program_id: cluster_lend::id(),
+            accounts: self
+                .redeem_collateral_accounts(user_source_collateral, user_destination_liquidity)
+                .to_account_metas(Some(true)),
+            data: cluster_lend::instruction::RedeemReserveCollateralExactLiquidity {
+                liquidity_amount,
+            }
+            .data(),
+        }
+    }
+
+    pub fn emergency_redeem_collateral_ix(
+        &self,
+        collateral_amount: u64,
+        user_source_collateral: Pubkey,
+        user_destination_liquidity: Pubkey,
+    ) -> Instruction {
+        Instruction {
+            program_id: cluster_lend::id(),
+            accounts: self
+                .redeem_collateral_accounts(user_source_collateral, user_destination_liquidity)
+                .to_account_metas(Some(true)),
+            data: cluster_lend::instruction::EmergencyRedeemReserveCollateral { collateral_amount }
+                .data(),
+        }
+    }
+
+    fn redeem_collateral_accounts(
+        &self,
+        user_source_collateral: Pubkey,
+        user_destination_liquidity: Pubkey,
+    ) -> cluster_lend::accounts::RedeemReserveCollateralCtx {
+        let pdas = init_reserve_pdas_program_id(
+            &cluster_lend::ID,
+            &self.lending_market,
+            &self.liquidity_mint,
+        );
+
+        cluster_lend::accounts::RedeemReserveCollateralCtx {
+            owner: self.owner,
+            lending_market: self.lending_market,
+            reserve: self.key,
+            lending_market_authority: lending_market_auth(&self.lending_market),
+            reserve_collateral_mint: pdas.collateral_ctoken_mint,
+            reserve_liquidity_supply: pdas.liquidity_supply_vault,
+            user_source_collateral,
+            user_destination_liquidity,
+            token_program: Token::id(),
+            instruction_sysvar_account: instructions::id(),
+            user_transfer_authority: None,
+        }
+    }
+
     pub fn flash_borrow_ix(
         &self,
         liquidity_amount: u64,
         user_transfer_authority: Pubkey,
         user_destination_liquidity: Pubkey,
+        referrer_token_account: Option<Pubkey>,
     ) -> Instruction {
         let lending_market_authority = lending_market_auth(&self.lending_market);
 
@@ -160,6 +578,7 @@ impl ReserveFixture {
             user_destination_liquidity,
             sysvar_info: Instructions::id(),
             token_program: token::ID,
+            referrer_token_account,
         };
         let ix = Instruction {
             program_id: cluster_lend::id(),
@@ -177,6 +596,7 @@ impl ReserveFixture {
         borrow_instruction_index: u8,
         user_transfer_authority: Pubkey,
         user_source_liquidity: Pubkey,
+        referrer_token_account: Option<Pubkey>,
     ) -> Instruction {
         let lending_market_authority = lending_market_auth(&self.lending_market);
 
@@ -196,6 +616,7 @@ impl ReserveFixture {
             user_source_liquidity,
             sysvar_info: Instructions::id(),
             token_program: token::ID,
+            referrer_token_account,
         };
         let ix = Instruction {
             program_id: cluster_lend::id(),
@@ -210,3 +631,60 @@ impl ReserveFixture {
         ix
     }
 }
+
+/// `update_reserve_mode_ix` value of the u64 config fields
+pub fn u64_value(value: u64) -> [u8; 32] {
+    let mut bytes = [0; 32];
+    bytes[..8].copy_from_slice(&value.to_le_bytes());
+    bytes
+}
+
+/// `update_reserve_mode_ix` value of `UpdateConfigMode::UpdateReserveStatus`
+pub fn reserve_status_value(status: ReserveStatus) -> [u8; 32] {
+    let mut value = [0; 32];
+    value[0] = status as u8;
+    value
+}
+
+/// Reserve holding `deposit_amount` of liquidity, `borrow_amount` of it borrowed
+pub fn reserve_with_debt(deposit_amount: u64, borrow_amount: u64) -> Reserve {
+    let mut reserve = Reserve::default();
+    reserve.deposit_liquidity(deposit_amount).unwrap();
+    reserve
+        .liquidity
+        .borrow(Fraction::from(borrow_amount))
+        .unwrap();
+    reserve
+}
+
+/// Reserve on `config` holding `deposit_amount` of liquidity, `borrow_amount` of it borrowed
+pub fn reserve_with_utilization(
+    config: ReserveConfig,
+    deposit_amount: u64,
+    borrow_amount: u64,
+) -> Reserve {
+    let mut reserve = reserve_with_debt(deposit_amount, borrow_amount);
+    reserve.config = config;
+    reserve
+}
+
+/// Reserve holding `deposit_amount` of liquidity, priced and refreshed at `slot`
+pub fn refreshed_reserve(market_price: u64, deposit_amount: u64, slot: u64) -> Reserve {
+    let mut reserve = Reserve::default();
+    reserve.version = PROGRAM_VERSION as u64;
+    reserve.config.loan_to_value_pct = 70;
+    reserve.config.liquidation_threshold_pct = 80;
+    reserve.config.borrow_factor_pct = 100;
+    reserve.deposit_liquidity(deposit_amount).unwrap();
+    reserve.liquidity.market_price_sf = Fraction::from(market_price).to_bits();
+    reserve.liquidity.cumulative_borrow_rate_bsf = BigFraction::from(Fraction::ONE).into();
+    reserve
+        .last_update
+        .update_slot(slot, Some(PriceStatusFlags::ALL_CHECKS));
+    reserve
+}
+
+/// Account data of `reserve`, for the lending operations loading reserves from account infos
+pub fn reserve_account_data(reserve: &Reserve) -> Vec<u8> {
+    [&Reserve::discriminator()[..], bytemuck::bytes_of(reserve)].concat()
+}
@@ -9,8 +9,8 @@ use cluster_lend::{
         pda::{init_obligation_pda, init_reserve_pdas},
         BorrowRateCurve, CurvePoint,
     },
-    AssetTier, InitObligationArgs, PythConfiguration, ReserveConfig, ReserveFees, ReserveStatus,
-    TokenInfo, WithdrawalCaps,
+    AssetTier, InitObligationArgs, Obligation, PythConfiguration, Reserve, ReserveConfig,
+    ReserveFees, ReserveStatus, TokenInfo, WithdrawalCaps,
 };
 use pyth_sdk_solana::state::SolanaPriceAccount;
 use solana_program::{hash::Hash, sysvar};
@@ -38,6 +38,7 @@ pub const SOL_QUOTE_CURRENCY: [u8; 32] =
 
 pub const PYTH_USDC_FEED: Pubkey = pubkey!("PythUsdcPrice111111111111111111111111111111");
 pub const PYTH_SOL_FEED: Pubkey = pubkey!("PythSo1Price1111111111111111111111111111111");
+pub const PYTH_USDC_FALLBACK_FEED: Pubkey = pubkey!("PythUsdcFa11backPrice1111111111111111111111");
 pub const PYTH_SOL_EQUIVALENT_FEED: Pubkey = pubkey!("PythSo1Equiva1entPrice111111111111111111111");
 pub const PYTH_MNDE_FEED: Pubkey = pubkey!("PythMndePrice111111111111111111111111111111");
 pub const FAKE_PYTH_USDC_FEED: Pubkey = pubkey!("FakePythUsdcPrice11111111111111111111111111");
@@ -52,13 +53,15 @@ pub const TEST_RESERVE_CONFIG: ReserveConfig = ReserveConfig {
     min_liquidation_bonus_bps: 200,
     max_liquidation_bonus_bps: 500,
     bad_debt_liquidation_bonus_bps: 10,
+    flash_loan_referral_fee_bps: 0,
+    redeem_fees_liquidity_buffer_bps: 0,
 
     deleveraging_margin_call_period_secs: 259200, // 3 days
     deleveraging_threshold_slots_per_bps: 7200,   // 0.01% per hour
     fees: ReserveFees {
         borrow_fee_sf: 0,
         flash_loan_fee_sf: 0,
-        padding: [0; 8],
+        flat_borrow_fee: 0,
     },
     borrow_rate_curve: BorrowRateCurve {
         points: [
@@ -122,8 +125,18 @@ pub const TEST_RESERVE_CONFIG: ReserveConfig = ReserveConfig {
         pyth_configuration: PythConfiguration {
             price: PYTH_USDC_FEED,
         },
+        max_confidence_pct: 0,
+        price_pin_mode: 0,
+        price_pin_band_bps: 0,
+        max_age_price_seconds_liquidation: 0,
+        fixed_price_sf: 0,
+        fallback_oracle: PythConfiguration {
+            price: Pubkey::new_from_array([0; 32]),
+        },
+        max_fallback_divergence_bps: 0,
+        price_authority: Pubkey::new_from_array([0; 32]),
 
-        _padding: [0; 20],
+        _padding: [0; 6],
     },
 
     deposit_withdrawal_cap: WithdrawalCaps {
@@ -139,10 +152,28 @@ pub const TEST_RESERVE_CONFIG: ReserveConfig = ReserveConfig {
         config_interval_length_seconds: 0,
     },
 
-    padding_0: [0; 4],
-    padding_1: 0,
-    padding_2: [0; 7],
-    reserved: [0; 32],
+    elevation_group: 0,
+    debt_tranche: 0,
+    min_supply_borrow_spread_bps: 0,
+    deposits_disabled: 0,
+    is_rebasing: 0,
+    borrowing_disabled: 0,
+    liquidation_close_factor_pct: 0,
+    min_available_liquidity: 0,
+    liquidation_bond_lamports: 0,
+    protocol_fee_receiver: Pubkey::new_from_array([0; 32]),
+    max_borrow_rate_bps: 0,
+    limit_crossed_grace_slots: 0,
+    min_initial_deposit_amount: 0,
+    virtual_collateral_offset: 0,
+    min_deposit_hold_slots: 0,
+    reward_emission_per_slot: 0,
+    max_single_deposit: 0,
+    max_price_change_bps_per_refresh: 0,
+    insurance_fee_bps: 0,
+    min_borrow_rate_bps: 0,
+    repay_fee_sf: 0,
+    reserved: [0; 15],
 };
 
 pub struct TestFixture {
@@ -154,6 +185,11 @@ pub struct TestFixture {
 
 impl TestFixture {
     pub async fn new() -> TestFixture {
+        Self::new_with_programs(|_| {}).await
+    }
+
+    /// Same as `new`, letting the caller register extra programs before the bank starts
+    pub async fn new_with_programs(add_programs: impl FnOnce(&mut ProgramTest)) -> TestFixture {
         pub fn fixed_entry(
             program_id: &Pubkey,
             accounts: &[anchor_lang::prelude::AccountInfo],
@@ -182,6 +218,13 @@ impl TestFixture {
             create_pyth_price_account(usdc_keypair.pubkey(), 1, USDC_MINT_DECIMALS.into(), None),
         );
 
+        program.add_account(
+            PYTH_USDC_FALLBACK_FEED,
+            create_pyth_price_account(usdc_keypair.pubkey(), 1, USDC_MINT_DECIMALS.into(), None),
+        );
+
+        add_programs(&mut program);
+
         let context = Rc::new(RefCell::new(program.start_with_context().await));
         let usdc_mint_f = MintFixture::new(
             Rc::clone(&context),
@@ -226,7 +269,7 @@ impl TestFixture {
             liquidity_mint: liquidity_mint.clone(),
         };
 
-        let init_obligation_args = InitObligationArgs { tag: 0, id: 0 };
+        let init_obligation_args = InitObligationArgs::default();
         let obligation_key = init_obligation_pda(
             &user.pubkey(),
             &lending_market_f.key,
@@ -258,6 +301,35 @@ impl TestFixture {
         (lending_market_f, reserve_f, obligation_f)
     }
 
+    pub async fn add_reserve(
+        &self,
+        lending_market_f: &LendingMarketFixture,
+        liquidity_mint: &Pubkey,
+    ) -> ReserveFixture {
+        let payer = self.payer_keypair();
+
+        let reserve_key = Keypair::new();
+        let reserve_f = ReserveFixture {
+            key: reserve_key.pubkey(),
+            owner: payer.pubkey(),
+            payer: payer.pubkey(),
+            lending_market: lending_market_f.key,
+            liquidity_mint: liquidity_mint.clone(),
+        };
+
+        self.send_transaction(
+            &[
+                reserve_f.initialize_reserve_ix(),
+                reserve_f.update_reserve_ix(TEST_RESERVE_CONFIG),
+            ],
+            &[&payer, &reserve_key],
+        )
+        .await
+        .unwrap();
+
+        reserve_f
+    }
+
     pub async fn load_and_deserialize<T: anchor_lang::AccountDeserialize>(
         &self,
         address: &Pubkey,
@@ -307,6 +379,102 @@ impl TestFixture {
         ctx.set_account(&address, &aso);
     }
 
+    pub async fn set_pyth_oracle_confidence(&self, address: Pubkey, conf: u64) {
+        let mut ctx = self.context.borrow_mut();
+
+        let mut account = ctx
+            .banks_client
+            .get_account(address)
+            .await
+            .unwrap()
+            .unwrap();
+
+        let data = account.data.as_mut_slice();
+        let mut data: SolanaPriceAccount =
+            *pyth_sdk_solana::state::load_price_account(data).unwrap();
+
+        data.agg.conf = conf;
+
+        let bytes = bytemuck::bytes_of(&data);
+
+        let mut aso = AccountSharedData::from(account);
+        aso.set_data_from_slice(bytes);
+
+        ctx.set_account(&address, &aso);
+    }
+
+    pub async fn set_pyth_oracle_price(&self, address: Pubkey, native_price: i64) {
+        let mut ctx = self.context.borrow_mut();
+
+        let mut account = ctx
+            .banks_client
+            .get_account(address)
+            .await
+            .unwrap()
+            .unwrap();
+
+        let data = account.data.as_mut_slice();
+        let mut data: SolanaPriceAccount =
+            *pyth_sdk_solana::state::load_price_account(data).unwrap();
+
+        data.agg.price = native_price;
+        data.ema_price.val = native_price;
+        data.ema_price.numer = native_price;
+
+        let bytes = bytemuck::bytes_of(&data);
+
+        let mut aso = AccountSharedData::from(account);
+        aso.set_data_from_slice(bytes);
+
+        ctx.set_account(&address, &aso);
+    }
+
+    pub async fn update_reserve_account(&self, address: Pubkey, update: impl FnOnce(&mut Reserve)) {
+        let mut ctx = self.context.borrow_mut();
+
+        let account = ctx
+            .banks_client
+            .get_account(address)
+            .await
+            .unwrap()
+            .unwrap();
+
+        let mut data = account.data.clone();
+        let mut reserve: Reserve = bytemuck::pod_read_unaligned(&data[8..]);
+        update(&mut reserve);
+        data[8..].copy_from_slice(bytemuck::bytes_of(&reserve));
+
+        let mut aso = AccountSharedData::from(account);
+        aso.set_data_from_slice(&data);
+
+        ctx.set_account(&address, &aso);
+    }
+
+    pub async fn update_obligation_account(
+        &self,
+        address: Pubkey,
+        update: impl FnOnce(&mut Obligation),
+    ) {
+        let mut ctx = self.context.borrow_mut();
+
+        let account = ctx
+            .banks_client
+            .get_account(address)
+            .await
+            .unwrap()
+            .unwrap();
+
+        let mut data = account.data.clone();
+        let mut obligation: Obligation = bytemuck::pod_read_unaligned(&data[8..]);
+        update(&mut obligation);
+        data[8..].copy_from_slice(bytemuck::bytes_of(&obligation));
+
+        let mut aso = AccountSharedData::from(account);
+        aso.set_data_from_slice(&data);
+
+        ctx.set_account(&address, &aso);
+    }
+
     pub fn set_time(&self, timestamp: i64) {
         let clock = Clock {
             unix_timestamp: timestamp,
@@ -315,6 +483,15 @@ impl TestFixture {
         self.context.borrow_mut().set_sysvar(&clock);
     }
 
+    pub fn set_slot_and_time(&self, slot: u64, timestamp: i64) {
+        let clock = Clock {
+            slot,
+            unix_timestamp: timestamp,
+            ..Default::default()
+        };
+        self.context.borrow_mut().set_sysvar(&clock);
+    }
+
     pub async fn get_minimum_rent_for_size(&self, size: usize) -> u64 {
         self.context
             .borrow_mut()
@@ -379,4 +556,63 @@ impl TestFixture {
 
         Ok(())
     }
+
+    pub async fn send_transaction_with_compute_units(
+        &self,
+        ixs: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<u64, BanksClientError> {
+        let mut ctx = self.context.borrow_mut();
+
+        let mut signers = signers.to_vec();
+        signers.push(&ctx.payer);
+
+        let tx = Transaction::new_signed_with_payer(
+            ixs,
+            Some(&ctx.payer.pubkey()),
+            signers.as_slice(),
+            ctx.last_blockhash,
+        );
+
+        let result = ctx
+            .banks_client
+            .process_transaction_with_metadata(tx)
+            .await?;
+        result.result?;
+
+        Ok(result
+            .metadata
+            .map(|metadata| metadata.compute_units_consumed)
+            .unwrap_or_default())
+    }
+
+    pub async fn send_transaction_with_return_data(
+        &self,
+        ixs: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<Vec<u8>, BanksClientError> {
+        let mut ctx = self.context.borrow_mut();
+
+        let mut signers = signers.to_vec();
+        signers.push(&ctx.payer);
+
+        let tx = Transaction::new_signed_with_payer(
+            ixs,
+            Some(&ctx.payer.pubkey()),
+            signers.as_slice(),
+            ctx.last_blockhash,
+        );
+
+        let result = ctx
+            .banks_client
+            .process_transaction_with_metadata(tx)
+            .await?;
+        result.result?;
+
+        Ok(result
+            .metadata
+            .and_then(|metadata| metadata.return_data)
+            .map(|return_data| return_data.data)
+            .unwrap_or_default())
+    }
 }
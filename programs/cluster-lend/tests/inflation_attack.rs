@@ -0,0 +1,194 @@
+#[cfg(test)]
+mod helpers;
+
+use anchor_lang::prelude::Clock;
+use cluster_lend::{
+    errors::LendingError,
+    lending_market::lending_operations::{
+        deposit_reserve_liquidity, donate_reserve_liquidity, redeem_reserve_collateral,
+        refresh_reserve, update_reserve_config,
+    },
+    Reserve, UpdateConfigMode,
+};
+use solana_program_test::*;
+
+use helpers::*;
+use solana_sdk::signature::Keypair;
+use test::{TestFixture, PYTH_USDC_FEED, TEST_RESERVE_CONFIG};
+
+const DONATION_AMOUNT: u64 = 1_000_000;
+const VICTIM_DEPOSIT_AMOUNT: u64 = 999_999;
+
+/// The attacker makes the first deposit, redeems down to a single cToken and inflates its
+/// value with a donation, returns what the victim depositing next can redeem
+fn victim_redeemable_after_attack(min_initial_deposit_amount: u64) -> u64 {
+    let mut reserve = Reserve::default();
+    reserve.config = TEST_RESERVE_CONFIG;
+    reserve.config.min_initial_deposit_amount = min_initial_deposit_amount;
+    let clock = Clock::default();
+
+    refresh_reserve(&mut reserve, &clock, None).unwrap();
+    if min_initial_deposit_amount > 1 {
+        assert!(deposit_reserve_liquidity(&mut reserve, &clock, 1).is_err());
+    }
+    let attacker_collateral =
+        deposit_reserve_liquidity(&mut reserve, &clock, min_initial_deposit_amount + 1).unwrap();
+    assert_eq!(attacker_collateral, 1);
+
+    refresh_reserve(&mut reserve, &clock, None).unwrap();
+    donate_reserve_liquidity(&mut reserve, &clock, DONATION_AMOUNT).unwrap();
+
+    refresh_reserve(&mut reserve, &clock, None).unwrap();
+    let victim_collateral =
+        deposit_reserve_liquidity(&mut reserve, &clock, VICTIM_DEPOSIT_AMOUNT).unwrap();
+    if victim_collateral == 0 {
+        return 0;
+    }
+
+    refresh_reserve(&mut reserve, &clock, None).unwrap();
+    redeem_reserve_collateral(&mut reserve, victim_collateral, &clock, false).unwrap()
+}
+
+#[test]
+fn dead_shares_mitigate_inflation_attack() {
+    // without dead shares the victim's deposit rounds down to no collateral at all
+    assert_eq!(victim_redeemable_after_attack(0), 0);
+
+    // with them the donation is spread over the locked supply and the victim loses at most
+    // the rounding of one cToken
+    let redeemable = victim_redeemable_after_attack(1_000);
+    assert!(redeemable < VICTIM_DEPOSIT_AMOUNT);
+    assert!(VICTIM_DEPOSIT_AMOUNT - redeemable <= VICTIM_DEPOSIT_AMOUNT / 1_000);
+}
+
+#[test]
+fn dead_shares_are_locked_at_the_initial_exchange_rate() {
+    let mut reserve = Reserve::default();
+    reserve.config = TEST_RESERVE_CONFIG;
+    reserve.config.min_initial_deposit_amount = 1_000;
+    reserve.config.virtual_collateral_offset = 1_000;
+    let clock = Clock::default();
+
+    // a donation ahead of the first deposit prices the collateral at 10 liquidity each
+    refresh_reserve(&mut reserve, &clock, None).unwrap();
+    donate_reserve_liquidity(&mut reserve, &clock, 9_000).unwrap();
+
+    // the 1_000 liquidity minimum only locks the 100 collateral it is worth
+    refresh_reserve(&mut reserve, &clock, None).unwrap();
+    let collateral_amount = deposit_reserve_liquidity(&mut reserve, &clock, 2_000).unwrap();
+    assert_eq!(collateral_amount, 100);
+    assert_eq!(reserve.collateral.mint_total_supply, 200);
+}
+
+/// The attacker donates to the empty reserve, e.g. straight to the vault of a rebasing mint,
+/// then makes a dust first deposit ahead of the victim, returns what each of them can redeem
+fn redeemable_after_donation_before_first_deposit(virtual_collateral_offset: u64) -> (u64, u64) {
+    let mut reserve = Reserve::default();
+    reserve.config = TEST_RESERVE_CONFIG;
+    reserve.config.virtual_collateral_offset = virtual_collateral_offset;
+    let clock = Clock::default();
+
+    refresh_reserve(&mut reserve, &clock, None).unwrap();
+    donate_reserve_liquidity(&mut reserve, &clock, DONATION_AMOUNT).unwrap();
+
+    refresh_reserve(&mut reserve, &clock, None).unwrap();
+    let attacker_collateral = deposit_reserve_liquidity(&mut reserve, &clock, 1).unwrap();
+
+    refresh_reserve(&mut reserve, &clock, None).unwrap();
+    let victim_collateral =
+        deposit_reserve_liquidity(&mut reserve, &clock, VICTIM_DEPOSIT_AMOUNT).unwrap();
+
+    let mut redeem = |collateral_amount| {
+        if collateral_amount == 0 {
+            return 0;
+        }
+        refresh_reserve(&mut reserve, &clock, None).unwrap();
+        redeem_reserve_collateral(&mut reserve, collateral_amount, &clock, false).unwrap()
+    };
+    let victim_redeemable = redeem(victim_collateral);
+    let attacker_redeemable = redeem(attacker_collateral);
+
+    (attacker_redeemable, victim_redeemable)
+}
+
+#[test]
+fn virtual_collateral_offset_mitigates_donation_before_first_deposit() {
+    // without the offset the dust first deposit is priced 1:1 and owns the donation, the
+    // victim's deposit rounds down to nothing and the attacker takes it all
+    let (attacker_redeemable, victim_redeemable) =
+        redeemable_after_donation_before_first_deposit(0);
+    assert_eq!(victim_redeemable, 0);
+    assert_eq!(
+        attacker_redeemable,
+        DONATION_AMOUNT + 1 + VICTIM_DEPOSIT_AMOUNT
+    );
+
+    // with it the virtual shares absorb the donation, the attacker loses it and the victim
+    // loses at most the rounding
+    let (attacker_redeemable, victim_redeemable) =
+        redeemable_after_donation_before_first_deposit(1_000);
+    assert!(attacker_redeemable < DONATION_AMOUNT);
+    assert!(victim_redeemable <= VICTIM_DEPOSIT_AMOUNT);
+    assert!(VICTIM_DEPOSIT_AMOUNT - victim_redeemable <= VICTIM_DEPOSIT_AMOUNT / 1_000);
+}
+
+#[test]
+fn virtual_collateral_offset_is_fixed_after_first_deposit() {
+    let mut reserve = Reserve::default();
+    reserve.config = TEST_RESERVE_CONFIG;
+    let clock = Clock::default();
+    let mut value = [0; 32];
+    value[..8].copy_from_slice(&1_000u64.to_le_bytes());
+
+    update_reserve_config(
+        &mut reserve,
+        UpdateConfigMode::UpdateVirtualCollateralOffset,
+        &value,
+    )
+    .unwrap();
+    assert_eq!(reserve.config.virtual_collateral_offset, 1_000);
+
+    refresh_reserve(&mut reserve, &clock, None).unwrap();
+    deposit_reserve_liquidity(&mut reserve, &clock, VICTIM_DEPOSIT_AMOUNT).unwrap();
+
+    let err = update_reserve_config(
+        &mut reserve,
+        UpdateConfigMode::UpdateVirtualCollateralOffset,
+        &[0; 32],
+    )
+    .unwrap_err();
+    assert_eq!(err, LendingError::InvalidConfig.into());
+    assert_eq!(reserve.config.virtual_collateral_offset, 1_000);
+}
+
+#[tokio::test]
+async fn success_donate_reserve_liquidity() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (_market_f, reserve_f, _obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+
+    let donation_amount = 1_000_000;
+    let owner = test_f.payer_keypair();
+    let owner_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&owner, donation_amount)
+        .await;
+
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_time(120);
+
+    test_f
+        .send_transaction(
+            &[reserve_f.donate_liquidity_ix(donation_amount, owner_liquidity_ata.key)],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+
+    // the liquidity is added without minting any collateral for it
+    let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+    assert_eq!(reserve.liquidity.available_amount, donation_amount);
+    assert_eq!(reserve.collateral.mint_total_supply, 0);
+    assert_eq!(owner_liquidity_ata.balance().await, 0);
+}
@@ -132,3 +132,58 @@ async fn failure_update_lending_market_with_invalid_owner() {
         .await;
     assert!(r.is_err());
 }
+
+#[tokio::test]
+async fn success_update_lending_market_referral_fee_bps() {
+    let test_f = TestFixture::new().await;
+
+    let lending_market_key = Keypair::new();
+    let lending_market_f = LendingMarketFixture {
+        key: lending_market_key.pubkey(),
+        owner: test_f.payer(),
+    };
+
+    let mode = UpdateLendingMarketMode::UpdateReferralFeeBps as u64;
+    let mut value: [u8; 72] = [0; 72];
+    value[..2].copy_from_slice(&2_500u16.to_le_bytes());
+
+    test_f
+        .send_transaction(
+            &[
+                lending_market_f.init_market_ix(USDC_QUOTE_CURRENCY),
+                lending_market_f.update_market_ix(mode, value),
+            ],
+            &[&test_f.payer_keypair(), &lending_market_key],
+        )
+        .await
+        .unwrap();
+
+    let lending_market: LendingMarket = test_f.load_and_deserialize(&lending_market_f.key).await;
+    assert_eq!(lending_market.referral_fee_bps, 2_500);
+}
+
+#[tokio::test]
+async fn failure_update_lending_market_referral_fee_bps_above_full_bps() {
+    let test_f = TestFixture::new().await;
+
+    let lending_market_key = Keypair::new();
+    let lending_market_f = LendingMarketFixture {
+        key: lending_market_key.pubkey(),
+        owner: test_f.payer(),
+    };
+
+    let mode = UpdateLendingMarketMode::UpdateReferralFeeBps as u64;
+    let mut value: [u8; 72] = [0; 72];
+    value[..2].copy_from_slice(&10_001u16.to_le_bytes());
+
+    let r = test_f
+        .send_transaction(
+            &[
+                lending_market_f.init_market_ix(USDC_QUOTE_CURRENCY),
+                lending_market_f.update_market_ix(mode, value),
+            ],
+            &[&test_f.payer_keypair(), &lending_market_key],
+        )
+        .await;
+    assert!(r.is_err());
+}
@@ -1,7 +1,12 @@
 #[cfg(test)]
 mod helpers;
 
-use cluster_lend::{utils::pda, InitObligationArgs, Obligation};
+use anchor_lang::prelude::AccountInfo;
+use cluster_lend::{
+    errors::LendingError,
+    utils::{pda, validate_init_obligation_args},
+    InitObligationArgs, Obligation, UpdateLendingMarketMode,
+};
 use lending_market::LendingMarketFixture;
 
 use obligation::ObligationFixture;
@@ -9,7 +14,51 @@ use solana_program_test::*;
 
 use helpers::*;
 use solana_sdk::{pubkey::Pubkey, signature::Keypair, signer::Signer};
-use test::{TestFixture, USDC_QUOTE_CURRENCY};
+use test::{TestFixture, PYTH_USDC_FEED, USDC_QUOTE_CURRENCY};
+
+/// Runs `validate_init_obligation_args` with two default-key system accounts as seeds
+fn validate_with_default_seeds(args: InitObligationArgs) -> anchor_lang::Result<()> {
+    let key = Pubkey::default();
+    let owner = Pubkey::default();
+    let mut lamports = [0; 2];
+    let mut data: [Vec<u8>; 2] = [vec![], vec![]];
+    let [lamports1, lamports2] = &mut lamports;
+    let [data1, data2] = &mut data;
+    let seed1 = AccountInfo::new(&key, false, false, lamports1, data1, &owner, false, 0);
+    let seed2 = AccountInfo::new(&key, false, false, lamports2, data2, &owner, false, 0);
+    validate_init_obligation_args(&args, &seed1, &seed2)
+}
+
+#[test]
+fn validate_init_obligation_args_combinations() {
+    assert!(validate_with_default_seeds(InitObligationArgs::default()).is_ok());
+
+    let r = validate_with_default_seeds(InitObligationArgs {
+        id: 1,
+        ..InitObligationArgs::default()
+    });
+    assert_eq!(r.unwrap_err(), LendingError::InvalidObligationId.into());
+
+    // only tags 0, 1 and 2 exist
+    let r = validate_with_default_seeds(InitObligationArgs {
+        tag: 3,
+        ..InitObligationArgs::default()
+    });
+    assert_eq!(
+        r.unwrap_err(),
+        LendingError::InvalidObligationSeedsValue.into()
+    );
+
+    // tag 1 needs two mints as seeds
+    let r = validate_with_default_seeds(InitObligationArgs {
+        tag: 1,
+        ..InitObligationArgs::default()
+    });
+    assert_eq!(
+        r.unwrap_err(),
+        LendingError::InvalidObligationSeedsValue.into()
+    );
+}
 
 #[tokio::test]
 async fn success_init_update_obligation() {
@@ -23,7 +72,7 @@ async fn success_init_update_obligation() {
         owner: payer.pubkey(),
     };
 
-    let init_obligation_args = InitObligationArgs { tag: 0, id: 0 };
+    let init_obligation_args = InitObligationArgs::default();
     let obligation_key = pda::init_obligation_pda(
         &payer.pubkey(),
         &lending_market_f.key,
@@ -62,3 +111,232 @@ async fn success_init_update_obligation() {
         .await;
     assert!(r.is_ok());
 }
+
+#[tokio::test]
+async fn permissioned_market_requires_allowlisted_owner() {
+    let test_f = TestFixture::new().await;
+
+    let payer = test_f.payer_keypair();
+
+    let lending_market_key = Keypair::new();
+    let lending_market_f = LendingMarketFixture {
+        key: lending_market_key.pubkey(),
+        owner: payer.pubkey(),
+    };
+
+    let mut permissioned = [0; 72];
+    permissioned[0] = 1;
+
+    let allowed_user = Keypair::new();
+    test_f
+        .send_transaction(
+            &[
+                lending_market_f.init_market_ix(USDC_QUOTE_CURRENCY),
+                lending_market_f.update_market_ix(
+                    UpdateLendingMarketMode::UpdatePermissioned as u64,
+                    permissioned,
+                ),
+                lending_market_f.allow_obligation_owner_ix(allowed_user.pubkey()),
+            ],
+            &[&payer, &lending_market_key],
+        )
+        .await
+        .unwrap();
+
+    let obligation_fixture = |owner: &Keypair| ObligationFixture {
+        key: pda::init_obligation_pda(
+            &owner.pubkey(),
+            &lending_market_f.key,
+            &Pubkey::default(),
+            &Pubkey::default(),
+            &InitObligationArgs::default(),
+        ),
+        owner: owner.pubkey(),
+        payer: payer.pubkey(),
+        lending_market: lending_market_f.key,
+    };
+    let allowlist_key = pda::obligation_allowlist(&lending_market_f.key, &allowed_user.pubkey());
+
+    // a user without an allowlist entry is rejected, even with someone else's entry
+    let disallowed_user = Keypair::new();
+    let disallowed_obligation_f = obligation_fixture(&disallowed_user);
+    let r = test_f
+        .send_transaction(
+            &[disallowed_obligation_f.initialize_obligation_ix(InitObligationArgs::default())],
+            &[&payer, &disallowed_user],
+        )
+        .await;
+    assert!(r.is_err());
+    let r = test_f
+        .send_transaction(
+            &[
+                disallowed_obligation_f.initialize_obligation_with_allowlist_ix(
+                    InitObligationArgs::default(),
+                    Some(allowlist_key),
+                ),
+            ],
+            &[&payer, &disallowed_user],
+        )
+        .await;
+    assert!(r.is_err());
+
+    let allowed_obligation_f = obligation_fixture(&allowed_user);
+    test_f
+        .send_transaction(
+            &[
+                allowed_obligation_f.initialize_obligation_with_allowlist_ix(
+                    InitObligationArgs::default(),
+                    Some(allowlist_key),
+                ),
+            ],
+            &[&payer, &allowed_user],
+        )
+        .await
+        .unwrap();
+
+    let obligation: Obligation = test_f.load_and_deserialize(&allowed_obligation_f.key).await;
+    assert_eq!(obligation.owner, allowed_user.pubkey());
+}
+
+#[tokio::test]
+async fn success_mint_scoped_obligations() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let sol_mint_f = test_f.sol_mint.clone();
+    let (market_f, reserve_f, _obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+
+    // tag 1 is scoped by a pair of mints
+    let pair_obligation_f = ObligationFixture::new_with_seeds(
+        &test_f,
+        &user,
+        market_f.key,
+        InitObligationArgs {
+            tag: 1,
+            ..InitObligationArgs::default()
+        },
+        usdc_mint_f.key,
+        sol_mint_f.key,
+    )
+    .await;
+    let obligation: Obligation = test_f.load_and_deserialize(&pair_obligation_f.key).await;
+    assert_eq!(obligation.tag, 1);
+
+    // tag 2 by a single mint
+    let obligation_f = ObligationFixture::new_with_seeds(
+        &test_f,
+        &user,
+        market_f.key,
+        InitObligationArgs {
+            tag: 2,
+            ..InitObligationArgs::default()
+        },
+        usdc_mint_f.key,
+        usdc_mint_f.key,
+    )
+    .await;
+    let obligation: Obligation = test_f.load_and_deserialize(&obligation_f.key).await;
+    assert_eq!(obligation.tag, 2);
+    assert_eq!(obligation.owner, user.pubkey());
+
+    let deposit_amount = 1_000_000;
+    let user_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&user, deposit_amount)
+        .await;
+
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_time(120);
+
+    test_f
+        .send_transaction(
+            &[
+                obligation_f.deposit_liquidity_collateral_ix(
+                    deposit_amount,
+                    &reserve_f,
+                    user_liquidity_ata.key,
+                ),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key]),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    let borrow_amount = 100_000;
+    test_f
+        .send_transaction(
+            &[
+                obligation_f.borrow_liquidity_ix(borrow_amount, &reserve_f, user_liquidity_ata.key),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+    assert_eq!(user_liquidity_ata.balance().await, borrow_amount);
+
+    let obligation: Obligation = test_f.load_and_deserialize(&obligation_f.key).await;
+    assert_eq!(obligation.deposits[0].deposit_reserve, reserve_f.key);
+    assert_eq!(obligation.deposits[0].deposited_amount, deposit_amount);
+    assert_eq!(obligation.borrows[0].borrow_reserve, reserve_f.key);
+}
+
+#[tokio::test]
+async fn failure_mint_scoped_obligation_with_invalid_seeds() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let sol_mint_f = test_f.sol_mint.clone();
+    let (market_f, _reserve_f, _obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+
+    let init_obligation_ix = |tag, seed1_account, seed2_account| {
+        let args = InitObligationArgs {
+            tag,
+            ..InitObligationArgs::default()
+        };
+        let obligation_f = ObligationFixture {
+            key: pda::init_obligation_pda(
+                &user.pubkey(),
+                &market_f.key,
+                &seed1_account,
+                &seed2_account,
+                &args,
+            ),
+            owner: user.pubkey(),
+            payer: test_f.payer(),
+            lending_market: market_f.key,
+        };
+        obligation_f.initialize_obligation_with_seeds_ix(args, seed1_account, seed2_account, None)
+    };
+
+    // a tag 1 obligation must be scoped by mints
+    let r = test_f
+        .send_transaction(
+            &[init_obligation_ix(1, Pubkey::default(), Pubkey::default())],
+            &[&user],
+        )
+        .await;
+    assert!(r.is_err());
+
+    // a tag 2 obligation by a single mint
+    let r = test_f
+        .send_transaction(
+            &[init_obligation_ix(2, usdc_mint_f.key, sol_mint_f.key)],
+            &[&user],
+        )
+        .await;
+    assert!(r.is_err());
+
+    // a tag 0 obligation by none
+    let r = test_f
+        .send_transaction(
+            &[init_obligation_ix(0, usdc_mint_f.key, usdc_mint_f.key)],
+            &[&user],
+        )
+        .await;
+    assert!(r.is_err());
+}
@@ -1,7 +1,13 @@
 #[cfg(test)]
 mod helpers;
 
-use cluster_lend::{Reserve, ReserveStatus, UpdateConfigMode};
+use std::rc::Rc;
+
+use anchor_spl::token::Mint;
+use cluster_lend::{
+    utils::{pda, Fraction},
+    Obligation, Reserve, ReserveStatus, UpdateConfigMode,
+};
 use lending_market::LendingMarketFixture;
 
 use reserve::ReserveFixture;
@@ -9,7 +15,11 @@ use solana_program_test::*;
 
 use helpers::*;
 use solana_sdk::{signature::Keypair, signer::Signer};
-use test::{TestFixture, PYTH_SOL_FEED, TEST_RESERVE_CONFIG, USDC_QUOTE_CURRENCY};
+use spl::TokenAccountFixture;
+use test::{
+    TestFixture, PYTH_SOL_FEED, PYTH_USDC_FEED, SOL_MINT_DECIMALS, TEST_RESERVE_CONFIG,
+    USDC_QUOTE_CURRENCY,
+};
 
 #[tokio::test]
 async fn success_init_update_reserve() {
@@ -84,3 +94,76 @@ async fn success_init_update_reserve() {
     let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
     assert_eq!(reserve.config.loan_to_value_pct, 32);
 }
+
+#[tokio::test]
+async fn success_collateral_mint_matches_liquidity_decimals() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let sol_mint_f = test_f.sol_mint.clone();
+    let (market_f, reserve_f, obligation_f) = test_f.setup(&user, &sol_mint_f.key).await;
+    let reserve_pdas = pda::init_reserve_pdas(&market_f.key, &sol_mint_f.key);
+
+    let collateral_mint: Mint = test_f
+        .load_and_deserialize(&reserve_pdas.collateral_ctoken_mint)
+        .await;
+    assert_eq!(collateral_mint.decimals, SOL_MINT_DECIMALS);
+
+    // an amount using every lamport digit
+    let deposit_amount = 1_234_567_891;
+    let owner = test_f.payer_keypair();
+    let owner_liquidity_ata = sol_mint_f
+        .create_token_account_and_mint_to(&owner, deposit_amount)
+        .await;
+    let owner_collateral_ata = TokenAccountFixture::new(
+        Rc::clone(&test_f.context),
+        &reserve_pdas.collateral_ctoken_mint,
+        &owner.pubkey(),
+    )
+    .await;
+    let user_liquidity_ata = sol_mint_f
+        .create_token_account_and_mint_to(&user, deposit_amount)
+        .await;
+
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                reserve_f.deposit_liquidity_ix(
+                    deposit_amount,
+                    owner_liquidity_ata.key,
+                    owner_collateral_ata.key,
+                ),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.deposit_liquidity_collateral_ix(
+                    deposit_amount,
+                    &reserve_f,
+                    user_liquidity_ata.key,
+                ),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key]),
+            ],
+            &[&owner, &user],
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(owner_collateral_ata.balance().await, deposit_amount);
+
+    let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+    let exchange_rate = reserve.collateral_exchange_rate().unwrap();
+    assert_eq!(
+        exchange_rate.fraction_collateral_to_liquidity(Fraction::from(deposit_amount)),
+        Fraction::from(deposit_amount)
+    );
+
+    // the deposit is valued in whole SOL, not in 10^6 collateral units
+    let obligation: Obligation = test_f.load_and_deserialize(&obligation_f.key).await;
+    assert_eq!(obligation.deposits[0].deposited_amount, deposit_amount);
+    assert_eq!(
+        obligation.deposits[0].market_value_sf,
+        (Fraction::from(deposit_amount) * reserve.liquidity.get_market_price_f()
+            / 1_000_000_000u128)
+            .to_bits()
+    );
+}
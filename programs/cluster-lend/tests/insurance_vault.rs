@@ -0,0 +1,272 @@
+#[cfg(test)]
+mod helpers;
+use std::rc::Rc;
+
+use cluster_lend::{
+    errors::LendingError,
+    lending_market::calculate_insurance_fee,
+    utils::{pda, Fraction},
+    Reserve,
+};
+use reserve::ReserveFixture;
+use solana_program_test::*;
+
+use helpers::*;
+use solana_sdk::{compute_budget::ComputeBudgetInstruction, signature::Keypair, signer::Signer};
+use spl::{balance_of, TokenAccountFixture};
+use test::{TestFixture, PYTH_SOL_FEED, PYTH_USDC_FEED, TEST_RESERVE_CONFIG};
+
+const SOL: u64 = 1_000_000_000;
+const SOL_NATIVE_ONE: i64 = 1_000_000;
+
+#[test]
+fn insurance_fee_is_a_floored_share_of_the_protocol_fee() {
+    assert_eq!(calculate_insurance_fee(1_000, 0), 0);
+    assert_eq!(calculate_insurance_fee(1_000, 2_500), 250);
+    assert_eq!(calculate_insurance_fee(999, 2_500), 249);
+    assert_eq!(calculate_insurance_fee(1_000, 10_000), 1_000);
+}
+
+#[tokio::test]
+async fn success_liquidation_fee_split_to_insurance_vault() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let sol_mint_f = test_f.sol_mint.clone();
+    let (market_f, usdc_reserve_f, obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+    let usdc_reserve_pdas = pda::init_reserve_pdas(&market_f.key, &usdc_mint_f.key);
+    let sol_reserve_pdas = pda::init_reserve_pdas(&market_f.key, &sol_mint_f.key);
+
+    let owner = test_f.payer_keypair();
+    let sol_reserve_key = Keypair::new();
+    let sol_reserve_f = ReserveFixture {
+        key: sol_reserve_key.pubkey(),
+        owner: owner.pubkey(),
+        payer: owner.pubkey(),
+        lending_market: market_f.key,
+        liquidity_mint: sol_mint_f.key,
+    };
+    let insurance_fee_bps = 4_000;
+    let mut sol_config = TEST_RESERVE_CONFIG;
+    sol_config.token_info.pyth_configuration.price = PYTH_SOL_FEED;
+    sol_config.protocol_liquidation_fee_pct = 50;
+    sol_config.insurance_fee_bps = insurance_fee_bps;
+    test_f
+        .send_transaction(
+            &[
+                sol_reserve_f.initialize_reserve_ix(),
+                sol_reserve_f.update_reserve_ix(sol_config),
+                sol_reserve_f.initialize_insurance_vault_ix(),
+            ],
+            &[&owner, &sol_reserve_key],
+        )
+        .await
+        .unwrap();
+
+    let sol_reserve: Reserve = test_f.load_and_deserialize(&sol_reserve_f.key).await;
+    assert_eq!(
+        sol_reserve.liquidity.insurance_vault,
+        sol_reserve_pdas.insurance_vault
+    );
+
+    let usdc_liquidity = 100_000_000;
+    let owner_usdc_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&owner, usdc_liquidity)
+        .await;
+    let owner_usdc_collateral_ata = TokenAccountFixture::new(
+        Rc::clone(&test_f.context),
+        &usdc_reserve_pdas.collateral_ctoken_mint,
+        &owner.pubkey(),
+    )
+    .await;
+    let user_sol_ata = sol_mint_f
+        .create_token_account_and_mint_to(&user, 10 * SOL)
+        .await;
+    let user_usdc_ata = usdc_mint_f.create_token_account_and_mint_to(&user, 0).await;
+
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_pyth_oracle_timestamp(PYTH_SOL_FEED, 120).await;
+    test_f.set_slot_and_time(10, 120);
+
+    // $10 of SOL collateral against $7 of USDC debt
+    test_f
+        .send_transaction(
+            &[
+                usdc_reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                usdc_reserve_f.deposit_liquidity_ix(
+                    usdc_liquidity,
+                    owner_usdc_ata.key,
+                    owner_usdc_collateral_ata.key,
+                ),
+            ],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+    test_f
+        .send_transaction(
+            &[
+                sol_reserve_f.refresh_ix(Some(PYTH_SOL_FEED)),
+                obligation_f.deposit_liquidity_collateral_ix(
+                    10 * SOL,
+                    &sol_reserve_f,
+                    user_sol_ata.key,
+                ),
+                sol_reserve_f.refresh_ix(Some(PYTH_SOL_FEED)),
+                usdc_reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![sol_reserve_f.key]),
+                obligation_f.borrow_liquidity_ix(7_000_000, &usdc_reserve_f, user_usdc_ata.key),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    let liquidator = test_f.payer_keypair();
+    let liquidator_source_liquidity = usdc_mint_f
+        .create_token_account_and_mint_to(&liquidator, usdc_liquidity)
+        .await;
+    let liquidator_destination_collateral = TokenAccountFixture::new(
+        Rc::clone(&test_f.context),
+        &sol_reserve_pdas.collateral_ctoken_mint,
+        &liquidator.pubkey(),
+    )
+    .await;
+    let liquidator_destination_liquidity = TokenAccountFixture::new(
+        Rc::clone(&test_f.context),
+        &sol_mint_f.key,
+        &liquidator.pubkey(),
+    )
+    .await;
+
+    // SOL drops to $0.80, $6.80 of liquidation threshold against $7 of debt
+    test_f.set_slot_and_time(20, 120);
+    test_f
+        .set_pyth_oracle_price(PYTH_SOL_FEED, SOL_NATIVE_ONE * 80 / 100)
+        .await;
+
+    // the compute limit keeps the two attempts apart
+    let liquidity_amount = 1_000_000;
+    let refresh_and_liquidate_ixs = |compute_units: u32, insurance_vault| {
+        vec![
+            ComputeBudgetInstruction::set_compute_unit_limit(compute_units),
+            sol_reserve_f.refresh_ix(Some(PYTH_SOL_FEED)),
+            usdc_reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+            obligation_f.refresh_ix(vec![sol_reserve_f.key, usdc_reserve_f.key]),
+            obligation_f.liquidate_with_insurance_vault_ix(
+                liquidity_amount,
+                0,
+                0,
+                0,
+                liquidator.pubkey(),
+                usdc_reserve_f.key,
+                usdc_reserve_pdas.liquidity_supply_vault,
+                sol_reserve_f.key,
+                sol_mint_f.key,
+                liquidator_destination_collateral.key,
+                liquidator_source_liquidity.key,
+                liquidator_destination_liquidity.key,
+                insurance_vault,
+            ),
+        ]
+    };
+
+    let err = test_f
+        .send_transaction(&refresh_and_liquidate_ixs(300_000, None), &[&liquidator])
+        .await
+        .unwrap_err();
+    assert_custom_error!(err, LendingError::InsuranceVaultRequired);
+
+    test_f
+        .send_transaction(
+            &refresh_and_liquidate_ixs(310_000, Some(sol_reserve_pdas.insurance_vault)),
+            &[&liquidator],
+        )
+        .await
+        .unwrap();
+
+    let fee_vault_balance = balance_of(test_f.context.clone(), sol_reserve_pdas.fee_vault).await;
+    let insurance_balance =
+        balance_of(test_f.context.clone(), sol_reserve_pdas.insurance_vault).await;
+    let protocol_fee = fee_vault_balance + insurance_balance;
+    assert!(insurance_balance > 0);
+    assert_eq!(
+        insurance_balance,
+        calculate_insurance_fee(protocol_fee, insurance_fee_bps)
+    );
+}
+
+#[tokio::test]
+async fn success_cover_bad_debt_from_insurance() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (market_f, reserve_f, _obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+    let reserve_pdas = pda::init_reserve_pdas(&market_f.key, &usdc_mint_f.key);
+
+    let owner = test_f.payer_keypair();
+    let insurance_amount = 300_000;
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.initialize_insurance_vault_ix(),
+                usdc_mint_f.make_mint_to_ix(&reserve_pdas.insurance_vault, insurance_amount),
+            ],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_slot_and_time(10, 120);
+
+    // nothing to cover before any debt is written off
+    let err = test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                reserve_f.cover_bad_debt_from_insurance_ix(insurance_amount),
+            ],
+            &[&owner],
+        )
+        .await
+        .unwrap_err();
+    assert_custom_error!(err, LendingError::InvalidAmount);
+
+    let bad_debt = 200_000u64;
+    test_f
+        .update_reserve_account(reserve_f.key, |reserve| {
+            reserve.liquidity.accumulated_bad_debt_sf = Fraction::from(bad_debt).to_bits();
+        })
+        .await;
+    let reserve_before: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+
+    // covering more than the bad debt only draws what was written off
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                reserve_f.cover_bad_debt_from_insurance_ix(u64::MAX),
+            ],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+
+    let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+    assert_eq!(reserve.liquidity.accumulated_bad_debt_sf, 0);
+    assert_eq!(
+        reserve.liquidity.available_amount,
+        reserve_before.liquidity.available_amount + bad_debt
+    );
+    assert_eq!(
+        balance_of(test_f.context.clone(), reserve_pdas.insurance_vault).await,
+        insurance_amount - bad_debt
+    );
+    assert_eq!(
+        balance_of(test_f.context.clone(), reserve_pdas.liquidity_supply_vault).await,
+        reserve.liquidity.available_amount
+    );
+}
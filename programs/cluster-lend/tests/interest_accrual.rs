@@ -0,0 +1,210 @@
+use std::sync::Mutex;
+
+use anchor_lang::{prelude::Pubkey, AnchorDeserialize, Discriminator};
+use cluster_lend::{
+    approximate_compounded_interest,
+    constants::SLOTS_PER_YEAR,
+    events::ProtocolFeeAccruedEvent,
+    utils::{BorrowRateCurve, CurvePoint, Fraction, FractionExtra},
+    Reserve,
+};
+use solana_program::program_stubs::{set_syscall_stubs, SyscallStubs};
+
+static LOGGED_DATA: Mutex<Vec<Vec<u8>>> = Mutex::new(Vec::new());
+
+struct LogDataCapture;
+
+impl SyscallStubs for LogDataCapture {
+    fn sol_log_data(&self, data: &[&[u8]]) {
+        LOGGED_DATA.lock().unwrap().push(data.concat());
+    }
+}
+
+fn protocol_fee_events(lending_market: Pubkey) -> Vec<ProtocolFeeAccruedEvent> {
+    LOGGED_DATA
+        .lock()
+        .unwrap()
+        .iter()
+        .filter_map(|data| data.strip_prefix(&ProtocolFeeAccruedEvent::DISCRIMINATOR))
+        .map(|mut data| ProtocolFeeAccruedEvent::deserialize(&mut data).unwrap())
+        .filter(|event| event.lending_market == lending_market)
+        .collect()
+}
+
+fn reserve_with_borrows(borrow_rate_bps: u32) -> Reserve {
+    let mut reserve = Reserve::default();
+    reserve.config.borrow_rate_curve = BorrowRateCurve::new_flat(borrow_rate_bps);
+    reserve.deposit_liquidity(1_000_000).unwrap();
+    reserve
+        .liquidity
+        .borrow(Fraction::from(500_000u64))
+        .unwrap();
+    reserve
+}
+
+#[test]
+fn compounded_interest_tracks_exact_compounding() {
+    for rate_bps in [100u64, 1_000, 10_000] {
+        let rate = Fraction::from_bps(rate_bps);
+        let rate_per_slot = rate_bps as f64 / 10_000.0 / SLOTS_PER_YEAR as f64;
+
+        for slots in [
+            1,
+            4,
+            5,
+            10,
+            100,
+            1_000,
+            10_000,
+            100_000,
+            1_000_000,
+            SLOTS_PER_YEAR / 12,
+        ] {
+            let approximation = approximate_compounded_interest(rate, slots)
+                .unwrap()
+                .to_num::<f64>();
+            let exact = (slots as f64 * rate_per_slot.ln_1p()).exp();
+
+            // the truncated series only drops positive terms
+            assert!(approximation <= exact * (1.0 + 1e-12));
+            assert!(
+                (exact - approximation) / exact < 1e-5,
+                "rate {rate_bps} bps over {slots} slots: {approximation} vs {exact}"
+            );
+        }
+    }
+}
+
+#[test]
+fn max_borrow_rate_caps_curve_rate() {
+    let mut reserve = reserve_with_borrows(10_000);
+    assert_eq!(
+        reserve.current_borrow_rate().unwrap(),
+        Fraction::from_bps(10_000)
+    );
+
+    reserve.config.max_borrow_rate_bps = 2_000;
+    assert_eq!(
+        reserve.current_borrow_rate().unwrap(),
+        Fraction::from_bps(2_000)
+    );
+
+    // a cap above the curve leaves the rate untouched
+    reserve.config.max_borrow_rate_bps = 20_000;
+    assert_eq!(
+        reserve.current_borrow_rate().unwrap(),
+        Fraction::from_bps(10_000)
+    );
+}
+
+#[test]
+fn borrow_rate_bounds_clamp_curve_at_extreme_utilization() {
+    let mut reserve = Reserve::default();
+    reserve.config.borrow_rate_curve = BorrowRateCurve::from_points(&[
+        CurvePoint {
+            utilization_rate_bps: 0,
+            borrow_rate_bps: 0,
+        },
+        CurvePoint {
+            utilization_rate_bps: 10_000,
+            borrow_rate_bps: 100_000,
+        },
+    ])
+    .unwrap();
+    reserve.config.min_borrow_rate_bps = 200;
+    reserve.config.max_borrow_rate_bps = 5_000;
+    reserve.deposit_liquidity(1_000_000).unwrap();
+
+    // nothing borrowed, the curve gives 0% and the floor applies
+    assert_eq!(
+        reserve.liquidity.utilization_rate().unwrap(),
+        Fraction::ZERO
+    );
+    assert_eq!(
+        reserve.current_borrow_rate().unwrap(),
+        Fraction::from_bps(200)
+    );
+
+    // fully borrowed, the curve gives 1000% and the ceiling applies
+    reserve
+        .liquidity
+        .borrow(Fraction::from(1_000_000u64))
+        .unwrap();
+    assert_eq!(reserve.liquidity.utilization_rate().unwrap(), Fraction::ONE);
+    assert_eq!(
+        reserve.current_borrow_rate().unwrap(),
+        Fraction::from_bps(5_000)
+    );
+
+    // an uncapped reserve keeps its floor
+    reserve.config.max_borrow_rate_bps = 0;
+    assert_eq!(
+        reserve.current_borrow_rate().unwrap(),
+        Fraction::from_bps(100_000)
+    );
+}
+
+#[test]
+fn accrual_caps_runaway_compounding() {
+    // 1000% a year left unrefreshed for a year only doubles the debt
+    let mut reserve = reserve_with_borrows(100_000);
+    reserve.accrue_interest(SLOTS_PER_YEAR).unwrap();
+    assert_eq!(
+        Fraction::from_bits(reserve.liquidity.borrowed_amount_sf),
+        Fraction::from(1_000_000u64)
+    );
+
+    // so does an elapsed time overflowing the approximation
+    let mut reserve = reserve_with_borrows(100_000);
+    reserve.accrue_interest(u64::MAX).unwrap();
+    assert_eq!(
+        Fraction::from_bits(reserve.liquidity.borrowed_amount_sf),
+        Fraction::from(1_000_000u64)
+    );
+
+    // capping the rate brings the same accrual back within bounds
+    let mut reserve = reserve_with_borrows(100_000);
+    reserve.config.max_borrow_rate_bps = 1_000;
+    reserve.accrue_interest(SLOTS_PER_YEAR).unwrap();
+    let borrowed = Fraction::from_bits(reserve.liquidity.borrowed_amount_sf);
+    assert!(borrowed > Fraction::from(550_000u64));
+    assert!(borrowed < Fraction::from(560_000u64));
+}
+
+#[test]
+fn accrual_emits_protocol_fee_portion_of_new_debt() {
+    set_syscall_stubs(Box::new(LogDataCapture));
+
+    let mut reserve = reserve_with_borrows(1_000);
+    reserve.lending_market = Pubkey::new_unique();
+    reserve.config.protocol_take_rate_pct = 20;
+
+    let slots = 1_000;
+    reserve.accrue_interest(slots).unwrap();
+
+    let events = protocol_fee_events(reserve.lending_market);
+    assert_eq!(events.len(), 1);
+    let event = &events[0];
+
+    let net_new_debt = Fraction::from_bits(event.net_new_debt_sf);
+    assert_eq!(
+        Fraction::from_bits(reserve.liquidity.borrowed_amount_sf) - Fraction::from(500_000u64),
+        net_new_debt
+    );
+    assert!(net_new_debt > Fraction::ZERO);
+    assert_eq!(
+        Fraction::from_bits(event.protocol_fee_sf),
+        net_new_debt * Fraction::from_percent(20)
+    );
+    assert_eq!(
+        reserve.liquidity.accumulated_protocol_fees_sf,
+        event.protocol_fee_sf
+    );
+
+    // a reserve without borrows has nothing to report
+    let mut idle_reserve = Reserve::default();
+    idle_reserve.lending_market = reserve.lending_market;
+    idle_reserve.deposit_liquidity(1_000_000).unwrap();
+    idle_reserve.accrue_interest(slots).unwrap();
+    assert_eq!(protocol_fee_events(reserve.lending_market).len(), 1);
+}
@@ -0,0 +1,89 @@
+#[cfg(test)]
+mod helpers;
+
+use cluster_lend::{utils::Fraction, Obligation, Reserve};
+use solana_program_test::*;
+
+use helpers::*;
+use solana_sdk::{compute_budget::ComputeBudgetInstruction, signature::Keypair};
+use test::{TestFixture, PYTH_USDC_FEED};
+
+#[tokio::test]
+async fn success_obligation_debt_tracks_reserve_debt() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (_market_f, reserve_f, obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+
+    let deposit_amount = 1_000_000;
+    let user_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&user, deposit_amount)
+        .await;
+
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_slot_and_time(1, 120);
+
+    test_f
+        .send_transaction(
+            &[
+                obligation_f.deposit_liquidity_collateral_ix(
+                    deposit_amount,
+                    &reserve_f,
+                    user_liquidity_ata.key,
+                ),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key]),
+                obligation_f.borrow_liquidity_ix(500_000, &reserve_f, user_liquidity_ata.key),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    // the reserve compounds every 1,000 slots, the obligation only catches up at the end
+    let start_slot = 1;
+    for step in 1..=10u32 {
+        test_f.set_slot_and_time(start_slot + step as u64 * 1_000, 120);
+        test_f
+            .send_transaction(
+                &[
+                    ComputeBudgetInstruction::set_compute_unit_limit(200_000 + step),
+                    reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                ],
+                &[],
+            )
+            .await
+            .unwrap();
+    }
+
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+            ],
+            &[],
+        )
+        .await
+        .unwrap();
+
+    let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+    let obligation: Obligation = test_f.load_and_deserialize(&obligation_f.key).await;
+
+    let reserve_debt = Fraction::from_bits(reserve.liquidity.borrowed_amount_sf);
+    let obligation_debt = Fraction::from_bits(obligation.borrows[0].borrowed_amount_sf);
+    assert!(reserve_debt > Fraction::from(500_000u64));
+    assert_eq!(
+        obligation.borrows[0].cumulative_borrow_rate_bsf,
+        reserve.liquidity.cumulative_borrow_rate_bsf
+    );
+
+    // a single borrower owes the whole reserve debt, up to rounding
+    let diff = if reserve_debt > obligation_debt {
+        reserve_debt - obligation_debt
+    } else {
+        obligation_debt - reserve_debt
+    };
+    assert!(diff <= Fraction::ONE);
+}
@@ -0,0 +1,210 @@
+#[cfg(test)]
+mod helpers;
+use std::rc::Rc;
+
+use cluster_lend::{utils::pda, AssetTier, InitObligationArgs, Reserve, UpdateLendingMarketMode};
+use obligation::ObligationFixture;
+use reserve::ReserveFixture;
+use solana_program_test::*;
+
+use helpers::*;
+use solana_sdk::{signature::Keypair, signer::Signer};
+use spl::TokenAccountFixture;
+use test::{TestFixture, PYTH_SOL_FEED, PYTH_USDC_FEED, TEST_RESERVE_CONFIG};
+
+const SOL: u64 = 1_000_000_000;
+
+#[tokio::test]
+async fn success_isolated_debt_borrows_capped_independently_of_regular_borrows() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let sol_mint_f = test_f.sol_mint.clone();
+    let (market_f, usdc_reserve_f, regular_obligation_f) =
+        test_f.setup(&user, &usdc_mint_f.key).await;
+    // isolated debt can't be borrowed next to other debt, it gets its own obligation
+    let isolated_obligation_f = ObligationFixture::new_with_seeds(
+        &test_f,
+        &user,
+        market_f.key,
+        InitObligationArgs {
+            tag: 2,
+            ..InitObligationArgs::default()
+        },
+        usdc_mint_f.key,
+        usdc_mint_f.key,
+    )
+    .await;
+
+    let owner = test_f.payer_keypair();
+    let sol_reserve_key = Keypair::new();
+    let sol_reserve_f = ReserveFixture {
+        key: sol_reserve_key.pubkey(),
+        owner: owner.pubkey(),
+        payer: owner.pubkey(),
+        lending_market: market_f.key,
+        liquidity_mint: sol_mint_f.key,
+    };
+    let mut sol_config = TEST_RESERVE_CONFIG;
+    sol_config.token_info.pyth_configuration.price = PYTH_SOL_FEED;
+    sol_config.asset_tier = AssetTier::IsolatedDebt as u8;
+    sol_config.loan_to_value_pct = 0;
+    sol_config.liquidation_threshold_pct = 0;
+
+    // $10 with SOL at $1
+    let isolated_debt_borrow_value_cap: u64 = 10;
+    let mut value = [0; 72];
+    value[..8].copy_from_slice(&isolated_debt_borrow_value_cap.to_le_bytes());
+    test_f
+        .send_transaction(
+            &[
+                sol_reserve_f.initialize_reserve_ix(),
+                sol_reserve_f.update_reserve_ix(sol_config),
+                market_f.update_market_ix(
+                    UpdateLendingMarketMode::UpdateIsolatedDebtBorrowValueCap as u64,
+                    value,
+                ),
+            ],
+            &[&owner, &sol_reserve_key],
+        )
+        .await
+        .unwrap();
+
+    let deposit_amount = 100_000_000;
+    let user_usdc_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&user, 2 * deposit_amount)
+        .await;
+    let user_sol_ata = sol_mint_f.create_token_account_and_mint_to(&user, 0).await;
+    let owner_sol_ata = sol_mint_f
+        .create_token_account_and_mint_to(&owner, 100 * SOL)
+        .await;
+    let sol_reserve_pdas = pda::init_reserve_pdas(&market_f.key, &sol_mint_f.key);
+    let owner_sol_collateral_ata = TokenAccountFixture::new(
+        Rc::clone(&test_f.context),
+        &sol_reserve_pdas.collateral_ctoken_mint,
+        &owner.pubkey(),
+    )
+    .await;
+
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_pyth_oracle_timestamp(PYTH_SOL_FEED, 120).await;
+    test_f.set_slot_and_time(1, 120);
+
+    test_f
+        .send_transaction(
+            &[
+                sol_reserve_f.refresh_ix(Some(PYTH_SOL_FEED)),
+                sol_reserve_f.deposit_liquidity_ix(
+                    100 * SOL,
+                    owner_sol_ata.key,
+                    owner_sol_collateral_ata.key,
+                ),
+            ],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+
+    test_f
+        .send_transaction(
+            &[
+                usdc_reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                regular_obligation_f.deposit_liquidity_collateral_ix(
+                    deposit_amount,
+                    &usdc_reserve_f,
+                    user_usdc_ata.key,
+                ),
+                usdc_reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                isolated_obligation_f.deposit_liquidity_collateral_ix(
+                    deposit_amount,
+                    &usdc_reserve_f,
+                    user_usdc_ata.key,
+                ),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    // a $20 regular borrow goes past the isolated cap and doesn't count towards it
+    test_f
+        .send_transaction(
+            &[
+                usdc_reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                regular_obligation_f.refresh_ix(vec![usdc_reserve_f.key]),
+                regular_obligation_f.borrow_liquidity_ix(
+                    20_000_000,
+                    &usdc_reserve_f,
+                    user_usdc_ata.key,
+                ),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    let usdc_reserve: Reserve = test_f.load_and_deserialize(&usdc_reserve_f.key).await;
+    assert_eq!(usdc_reserve.market_isolated_debt_value, 0);
+
+    let borrow_sol_ixs = |amount: u64, borrowed: bool| {
+        let mut obligation_reserves = vec![usdc_reserve_f.key];
+        if borrowed {
+            obligation_reserves.push(sol_reserve_f.key);
+        }
+        vec![
+            usdc_reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+            sol_reserve_f.refresh_ix(Some(PYTH_SOL_FEED)),
+            isolated_obligation_f.refresh_ix(obligation_reserves),
+            isolated_obligation_f.borrow_liquidity_ix(amount, &sol_reserve_f, user_sol_ata.key),
+        ]
+    };
+
+    // the isolated debt value is kept on the reserve, borrows don't write the market
+    let borrow_ixs = borrow_sol_ixs(5 * SOL, false);
+    assert!(borrow_ixs[3]
+        .accounts
+        .iter()
+        .any(|meta| meta.pubkey == market_f.key && !meta.is_writable));
+
+    test_f
+        .send_transaction(&borrow_ixs, &[&user])
+        .await
+        .unwrap();
+
+    let sol_reserve: Reserve = test_f.load_and_deserialize(&sol_reserve_f.key).await;
+    assert_eq!(sol_reserve.market_isolated_debt_value, 5);
+
+    // $11 of isolated debt is past the cap
+    let r = test_f
+        .send_transaction(&borrow_sol_ixs(6 * SOL, true), &[&user])
+        .await;
+    assert!(r.is_err());
+    assert_eq!(user_sol_ata.balance().await, 5 * SOL);
+
+    // repays free up room under the cap
+    test_f
+        .send_transaction(
+            &[
+                usdc_reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                sol_reserve_f.refresh_ix(Some(PYTH_SOL_FEED)),
+                isolated_obligation_f.refresh_ix(vec![usdc_reserve_f.key, sol_reserve_f.key]),
+                isolated_obligation_f.repay_liquidity_ix(2 * SOL, &sol_reserve_f, user_sol_ata.key),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    let sol_reserve: Reserve = test_f.load_and_deserialize(&sol_reserve_f.key).await;
+    assert_eq!(sol_reserve.market_isolated_debt_value, 3);
+
+    test_f
+        .send_transaction(&borrow_sol_ixs(7 * SOL, true), &[&user])
+        .await
+        .unwrap();
+
+    let sol_reserve: Reserve = test_f.load_and_deserialize(&sol_reserve_f.key).await;
+    assert_eq!(sol_reserve.market_isolated_debt_value, 10);
+    assert_eq!(user_sol_ata.balance().await, 10 * SOL);
+}
@@ -0,0 +1,518 @@
+use anchor_lang::prelude::Pubkey;
+use cluster_lend::{
+    errors::LendingError,
+    lending_market::liquidation_operations::{
+        calculate_liquidation, calculate_protocol_liquidation_fee, gross_up_liquidation_bonus,
+        scaled_liquidation_bonus,
+    },
+    utils::{Fraction, FractionExtra},
+    CalculateLiquidationResult, DebtTranche, LendingMarket, Obligation, ObligationCollateral,
+    ObligationHealth, ObligationLiquidity, Reserve,
+};
+
+const PROTOCOL_LIQUIDATION_FEE_PCT: u8 = 20;
+const MIN_LIQUIDATION_BONUS_BPS: u16 = 200;
+const MAX_LIQUIDATION_BONUS_BPS: u16 = 500;
+// 70% LTV is a quarter of the way from the 60% threshold to a 100% insolvency risk LTV
+const SCALED_LIQUIDATION_BONUS_BPS: u16 = 275;
+
+fn reserve() -> Reserve {
+    let mut reserve = Reserve::default();
+    reserve.config.loan_to_value_pct = 50;
+    reserve.config.liquidation_threshold_pct = 60;
+    reserve.config.min_liquidation_bonus_bps = MIN_LIQUIDATION_BONUS_BPS;
+    reserve.config.max_liquidation_bonus_bps = MAX_LIQUIDATION_BONUS_BPS;
+    reserve.config.bad_debt_liquidation_bonus_bps = 10;
+    reserve.config.protocol_liquidation_fee_pct = PROTOCOL_LIQUIDATION_FEE_PCT;
+    reserve
+}
+
+// 1_000_000 deposited and 700_000 borrowed at a price of 1, against a 60% threshold
+fn unhealthy_obligation(reserve_key: Pubkey) -> Obligation {
+    let mut obligation = Obligation::default();
+
+    let mut collateral = ObligationCollateral::new(reserve_key);
+    collateral.deposited_amount = 1_000_000;
+    collateral.market_value_sf = Fraction::from(1_000_000u64).to_bits();
+    obligation.deposits[0] = collateral;
+
+    let mut liquidity = ObligationLiquidity::new(reserve_key, Fraction::ONE.into());
+    liquidity.borrowed_amount_sf = Fraction::from(700_000u64).to_bits();
+    liquidity.market_value_sf = liquidity.borrowed_amount_sf;
+    liquidity.borrow_factor_adjusted_market_value_sf = liquidity.borrowed_amount_sf;
+    obligation.borrows[0] = liquidity;
+
+    obligation.deposited_value_sf = Fraction::from(1_000_000u64).to_bits();
+    obligation.borrowed_assets_market_value_sf = Fraction::from(700_000u64).to_bits();
+    obligation.borrow_factor_adjusted_debt_value_sf = Fraction::from(700_000u64).to_bits();
+    obligation.unhealthy_borrow_value_sf = Fraction::from(600_000u64).to_bits();
+    obligation
+}
+
+fn liquidate(
+    lending_market: &LendingMarket,
+    repay_amount: u64,
+) -> (CalculateLiquidationResult, u64) {
+    let reserve = reserve();
+    let obligation = unhealthy_obligation(Pubkey::new_unique());
+
+    let result = calculate_liquidation(
+        &reserve,
+        &reserve,
+        repay_amount,
+        lending_market,
+        &obligation,
+        &obligation.borrows[0],
+        &obligation.deposits[0],
+        0,
+        None,
+    )
+    .unwrap();
+
+    let protocol_fee = calculate_protocol_liquidation_fee(
+        result.withdraw_amount,
+        result.liquidation_bonus_rate,
+        PROTOCOL_LIQUIDATION_FEE_PCT,
+    );
+
+    (result, protocol_fee)
+}
+
+#[test]
+fn gross_up_liquidation_bonus_covers_protocol_fee() {
+    let net_bonus = Fraction::from_bps(500);
+
+    assert_eq!(gross_up_liquidation_bonus(net_bonus, 0), net_bonus);
+    assert_eq!(
+        gross_up_liquidation_bonus(net_bonus, 20).to_bps::<u16>(),
+        Some(625)
+    );
+    assert_eq!(
+        gross_up_liquidation_bonus(net_bonus, 50).to_bps::<u16>(),
+        Some(1000)
+    );
+    assert_eq!(gross_up_liquidation_bonus(net_bonus, 100), net_bonus);
+}
+
+fn realized_bonus_bps(repay_amount: u64, liquidator_received: u64) -> u16 {
+    (Fraction::from(liquidator_received) / Fraction::from(repay_amount) - Fraction::ONE)
+        .to_bps()
+        .unwrap()
+}
+
+#[test]
+fn liquidation_bonus_is_gross_by_default() {
+    let mut lending_market = LendingMarket::default();
+    lending_market.insolvency_risk_unhealthy_ltv_pct = 100;
+    let repay_amount = 100_000;
+
+    let (result, protocol_fee) = liquidate(&lending_market, repay_amount);
+
+    assert_eq!(
+        result.liquidation_bonus_rate.to_bps::<u16>(),
+        Some(SCALED_LIQUIDATION_BONUS_BPS)
+    );
+    assert_eq!(result.repay_amount, repay_amount);
+    assert!(result.withdraw_amount.abs_diff(102_750) <= 1);
+
+    // the protocol takes its share out of the configured bonus
+    let liquidator_received = result.withdraw_amount - protocol_fee;
+    assert_eq!(realized_bonus_bps(repay_amount, liquidator_received), 220);
+}
+
+#[test]
+fn liquidation_bonus_net_of_protocol_fee() {
+    let mut lending_market = LendingMarket::default();
+    lending_market.insolvency_risk_unhealthy_ltv_pct = 100;
+    lending_market.liquidation_bonus_net_of_protocol_fee = 1;
+    let repay_amount = 100_000;
+
+    let (result, protocol_fee) = liquidate(&lending_market, repay_amount);
+
+    assert_eq!(result.liquidation_bonus_rate.to_bps::<u16>(), Some(344));
+    assert_eq!(result.repay_amount, repay_amount);
+    assert!(result.withdraw_amount.abs_diff(103_437) <= 1);
+
+    // the liquidator keeps the configured bonus after the protocol fee
+    let liquidator_received = result.withdraw_amount - protocol_fee;
+    assert_eq!(
+        realized_bonus_bps(repay_amount, liquidator_received),
+        SCALED_LIQUIDATION_BONUS_BPS
+    );
+}
+
+#[test]
+fn net_liquidation_bonus_is_held_to_the_bad_debt_cap() {
+    let mut lending_market = LendingMarket::default();
+    lending_market.insolvency_risk_unhealthy_ltv_pct = 100;
+    lending_market.liquidation_bonus_net_of_protocol_fee = 1;
+    let reserve = reserve();
+
+    // at 97% LTV the bonus is capped at the 3% left before bad debt
+    let mut obligation = unhealthy_obligation(Pubkey::new_unique());
+    let debt_sf = Fraction::from(970_000u64).to_bits();
+    obligation.borrows[0].borrowed_amount_sf = debt_sf;
+    obligation.borrows[0].market_value_sf = debt_sf;
+    obligation.borrows[0].borrow_factor_adjusted_market_value_sf = debt_sf;
+    obligation.borrowed_assets_market_value_sf = debt_sf;
+    obligation.borrow_factor_adjusted_debt_value_sf = debt_sf;
+
+    let result = calculate_liquidation(
+        &reserve,
+        &reserve,
+        100_000,
+        &lending_market,
+        &obligation,
+        &obligation.borrows[0],
+        &obligation.deposits[0],
+        0,
+        None,
+    )
+    .unwrap();
+
+    assert_eq!(result.liquidation_bonus_rate.to_bps::<u16>(), Some(300));
+    assert!(result.withdraw_amount <= 103_000);
+}
+
+#[test]
+fn liquidation_bonus_scales_through_unhealthy_band() {
+    let liquidation_threshold = Fraction::from_percent(60);
+    let bonus_bps = |ltv_pct: u8, max_bonus_ltv_pct: u8| {
+        scaled_liquidation_bonus(
+            Fraction::from_percent(ltv_pct),
+            liquidation_threshold,
+            Fraction::from_percent(max_bonus_ltv_pct),
+            MIN_LIQUIDATION_BONUS_BPS,
+            MAX_LIQUIDATION_BONUS_BPS,
+        )
+        .to_bps::<u16>()
+        .unwrap()
+    };
+
+    // the band runs from the liquidation threshold up to the insolvency risk LTV
+    assert_eq!(bonus_bps(60, 95), MIN_LIQUIDATION_BONUS_BPS);
+    assert_eq!(bonus_bps(74, 95), 320);
+    assert_eq!(bonus_bps(88, 95), 440);
+    assert_eq!(bonus_bps(95, 95), MAX_LIQUIDATION_BONUS_BPS);
+    assert_eq!(bonus_bps(80, 100), 350);
+    assert_eq!(bonus_bps(96, 100), 470);
+
+    // clamped to the configured bounds outside of the band
+    assert_eq!(bonus_bps(50, 95), MIN_LIQUIDATION_BONUS_BPS);
+    assert_eq!(bonus_bps(97, 95), MAX_LIQUIDATION_BONUS_BPS);
+    assert_eq!(bonus_bps(120, 100), MAX_LIQUIDATION_BONUS_BPS);
+
+    // an insolvency risk LTV at or below the threshold pays the max bonus right away
+    assert_eq!(bonus_bps(61, 60), MAX_LIQUIDATION_BONUS_BPS);
+    assert_eq!(bonus_bps(60, 60), MIN_LIQUIDATION_BONUS_BPS);
+}
+
+#[test]
+fn liquidation_bonus_interpolates_up_to_insolvency_risk_ltv() {
+    // 95% insolvency risk LTV by default
+    let lending_market = LendingMarket::default();
+    let reserve = reserve();
+    let bonus_bps_at_ltv = |borrowed_amount: u64| {
+        let mut obligation = unhealthy_obligation(Pubkey::new_unique());
+        let borrowed_amount_sf = Fraction::from(borrowed_amount).to_bits();
+        obligation.borrows[0].borrowed_amount_sf = borrowed_amount_sf;
+        obligation.borrows[0].market_value_sf = borrowed_amount_sf;
+        obligation.borrows[0].borrow_factor_adjusted_market_value_sf = borrowed_amount_sf;
+        obligation.borrowed_assets_market_value_sf = borrowed_amount_sf;
+        obligation.borrow_factor_adjusted_debt_value_sf = borrowed_amount_sf;
+
+        calculate_liquidation(
+            &reserve,
+            &reserve,
+            10_000,
+            &lending_market,
+            &obligation,
+            &obligation.borrows[0],
+            &obligation.deposits[0],
+            0,
+            None,
+        )
+        .unwrap()
+        .liquidation_bonus_rate
+        .to_bps::<u16>()
+        .unwrap()
+    };
+
+    // barely unhealthy positions get the min bonus
+    assert_eq!(bonus_bps_at_ltv(600_000), MIN_LIQUIDATION_BONUS_BPS);
+    assert_eq!(bonus_bps_at_ltv(670_000), 260);
+    assert_eq!(bonus_bps_at_ltv(700_000), 286);
+    assert_eq!(bonus_bps_at_ltv(810_000), 380);
+    assert_eq!(bonus_bps_at_ltv(950_000), MAX_LIQUIDATION_BONUS_BPS);
+
+    // past the insolvency risk LTV the bonus stays capped by the distance to bad debt
+    assert_eq!(bonus_bps_at_ltv(970_000), 300);
+}
+
+// the unhealthy obligation with its debt split into a senior and a subordinated borrow
+fn tranched_obligation(senior_reserve_key: Pubkey, subordinated_reserve_key: Pubkey) -> Obligation {
+    let mut obligation = unhealthy_obligation(senior_reserve_key);
+
+    let mut senior = ObligationLiquidity::new(senior_reserve_key, Fraction::ONE.into());
+    senior.borrowed_amount_sf = Fraction::from(400_000u64).to_bits();
+    senior.market_value_sf = senior.borrowed_amount_sf;
+    senior.borrow_factor_adjusted_market_value_sf = senior.borrowed_amount_sf;
+    obligation.borrows[0] = senior;
+
+    let mut subordinated = ObligationLiquidity::new(subordinated_reserve_key, Fraction::ONE.into());
+    subordinated.debt_tranche = DebtTranche::Subordinated.into();
+    subordinated.borrowed_amount_sf = Fraction::from(300_000u64).to_bits();
+    subordinated.market_value_sf = subordinated.borrowed_amount_sf;
+    subordinated.borrow_factor_adjusted_market_value_sf = subordinated.borrowed_amount_sf;
+    obligation.borrows[1] = subordinated;
+
+    obligation
+}
+
+fn liquidate_borrow(
+    obligation: &Obligation,
+    borrow_index: usize,
+) -> anchor_lang::Result<CalculateLiquidationResult> {
+    let reserve = reserve();
+    calculate_liquidation(
+        &reserve,
+        &reserve,
+        100_000,
+        &LendingMarket::default(),
+        obligation,
+        &obligation.borrows[borrow_index],
+        &obligation.deposits[0],
+        0,
+        None,
+    )
+}
+
+#[test]
+fn liquidation_repays_senior_debt_first() {
+    let mut obligation = tranched_obligation(Pubkey::new_unique(), Pubkey::new_unique());
+    assert!(obligation.has_senior_debt());
+
+    let err = liquidate_borrow(&obligation, 1).unwrap_err();
+    assert_eq!(err, LendingError::SeniorDebtOutstanding.into());
+
+    let result = liquidate_borrow(&obligation, 0).unwrap();
+    assert_eq!(result.repay_amount, 100_000);
+
+    // once the senior debt is settled the subordinated debt can be liquidated
+    obligation.borrows[0].borrowed_amount_sf = 0;
+    assert!(!obligation.has_senior_debt());
+
+    let result = liquidate_borrow(&obligation, 1).unwrap();
+    assert_eq!(result.repay_amount, 100_000);
+}
+
+#[test]
+fn small_position_must_be_fully_liquidated() {
+    // the 700_000 of debt is below the full liquidation threshold
+    let lending_market = LendingMarket {
+        min_full_liquidation_value_threshold: 1_000_000,
+        ..LendingMarket::default()
+    };
+    let reserve = reserve();
+    let obligation = unhealthy_obligation(Pubkey::new_unique());
+    let liquidate_amount = |repay_amount| {
+        calculate_liquidation(
+            &reserve,
+            &reserve,
+            repay_amount,
+            &lending_market,
+            &obligation,
+            &obligation.borrows[0],
+            &obligation.deposits[0],
+            0,
+            None,
+        )
+    };
+
+    // the close factor would allow a partial liquidation, the threshold does not
+    let err = liquidate_amount(100_000).unwrap_err();
+    assert_eq!(err, LendingError::FullLiquidationRequired.into());
+    let err = liquidate_amount(699_999).unwrap_err();
+    assert_eq!(err, LendingError::FullLiquidationRequired.into());
+
+    let result = liquidate_amount(700_000).unwrap();
+    assert_eq!(result.repay_amount, 700_000);
+    assert_eq!(result.settle_amount_f, Fraction::from(700_000u64));
+
+    let result = liquidate_amount(u64::MAX).unwrap();
+    assert_eq!(result.repay_amount, 700_000);
+}
+
+#[test]
+fn close_factor_escalates_above_insolvency_risk_ltv() {
+    let lending_market = LendingMarket {
+        max_liquidatable_debt_market_value_at_once: u64::MAX,
+        ..LendingMarket::default()
+    };
+    let reserve = reserve();
+    let liquidate_at_ltv = |borrowed_amount: u64| {
+        let mut obligation = unhealthy_obligation(Pubkey::new_unique());
+        let borrowed_amount_sf = Fraction::from(borrowed_amount).to_bits();
+        obligation.borrows[0].borrowed_amount_sf = borrowed_amount_sf;
+        obligation.borrows[0].market_value_sf = borrowed_amount_sf;
+        obligation.borrows[0].borrow_factor_adjusted_market_value_sf = borrowed_amount_sf;
+        obligation.borrowed_assets_market_value_sf = borrowed_amount_sf;
+        obligation.borrow_factor_adjusted_debt_value_sf = borrowed_amount_sf;
+
+        calculate_liquidation(
+            &reserve,
+            &reserve,
+            u64::MAX,
+            &lending_market,
+            &obligation,
+            &obligation.borrows[0],
+            &obligation.deposits[0],
+            0,
+            None,
+        )
+        .unwrap()
+    };
+
+    // 94% LTV, just below the 95% insolvency risk LTV: capped by the 20% close factor
+    let result = liquidate_at_ltv(940_000);
+    assert!(result.repay_amount.abs_diff(188_000) <= 1);
+
+    // 96% LTV: the whole debt can be closed at once
+    let result = liquidate_at_ltv(960_000);
+    assert_eq!(result.repay_amount, 960_000);
+}
+
+#[test]
+fn partial_liquidation_is_capped_by_close_factor_and_market_max() {
+    let reserve = reserve();
+    let obligation = unhealthy_obligation(Pubkey::new_unique());
+    let liquidate_amount = |lending_market: &LendingMarket, repay_amount| {
+        calculate_liquidation(
+            &reserve,
+            &reserve,
+            repay_amount,
+            lending_market,
+            &obligation,
+            &obligation.borrows[0],
+            &obligation.deposits[0],
+            0,
+            None,
+        )
+    };
+
+    // the 20% close factor allows 140_000 of the 700_000 debt
+    let lending_market = LendingMarket::default();
+    let result = liquidate_amount(&lending_market, u64::MAX).unwrap();
+    assert_eq!(result.repay_amount, 140_000);
+    let result = liquidate_amount(&lending_market, 140_000).unwrap();
+    assert_eq!(result.repay_amount, 140_000);
+    let err = liquidate_amount(&lending_market, 140_001).unwrap_err();
+    assert_eq!(err, LendingError::LiquidationTooLarge.into());
+
+    // the market max value at once tightens it further
+    let lending_market = LendingMarket {
+        max_liquidatable_debt_market_value_at_once: 100_000,
+        ..LendingMarket::default()
+    };
+    let result = liquidate_amount(&lending_market, u64::MAX).unwrap();
+    assert_eq!(result.repay_amount, 100_000);
+    let err = liquidate_amount(&lending_market, 120_000).unwrap_err();
+    assert_eq!(err, LendingError::LiquidationTooLarge.into());
+}
+
+#[test]
+fn collateral_reserve_close_factor_overrides_market() {
+    let lending_market = LendingMarket {
+        max_liquidatable_debt_market_value_at_once: u64::MAX,
+        ..LendingMarket::default()
+    };
+    let debt_reserve = reserve();
+    let obligation = unhealthy_obligation(Pubkey::new_unique());
+    let liquidate_against = |collateral_reserve: &Reserve| {
+        calculate_liquidation(
+            collateral_reserve,
+            &debt_reserve,
+            u64::MAX,
+            &lending_market,
+            &obligation,
+            &obligation.borrows[0],
+            &obligation.deposits[0],
+            0,
+            None,
+        )
+        .unwrap()
+    };
+
+    // unset, the market 20% close factor applies
+    let stable_collateral = reserve();
+    assert_eq!(liquidate_against(&stable_collateral).repay_amount, 140_000);
+
+    let mut bluechip_collateral = reserve();
+    bluechip_collateral.config.liquidation_close_factor_pct = 50;
+    assert_eq!(
+        liquidate_against(&bluechip_collateral).repay_amount,
+        350_000
+    );
+
+    // volatile collateral is closed out at once
+    let mut volatile_collateral = reserve();
+    volatile_collateral.config.liquidation_close_factor_pct = 100;
+    assert_eq!(
+        liquidate_against(&volatile_collateral).repay_amount,
+        700_000
+    );
+}
+
+#[test]
+fn ltv_override_cannot_liquidate_healthy_obligation() {
+    let lending_market = LendingMarket::default();
+    let reserve = reserve();
+    let mut obligation = unhealthy_obligation(Pubkey::new_unique());
+    let liquidate_with_override = |obligation: &Obligation, max_allowed_ltv_override_pct| {
+        calculate_liquidation(
+            &reserve,
+            &reserve,
+            100_000,
+            &lending_market,
+            obligation,
+            &obligation.borrows[0],
+            &obligation.deposits[0],
+            0,
+            Some(max_allowed_ltv_override_pct),
+        )
+    };
+
+    // an override above the unhealthy LTV still applies
+    let result = liquidate_with_override(&obligation, 65).unwrap();
+    assert_eq!(result.repay_amount, 100_000);
+
+    // at a 70% LTV against an 80% unhealthy LTV, a lower override is clamped back up
+    obligation.unhealthy_borrow_value_sf = Fraction::from(800_000u64).to_bits();
+    let err = liquidate_with_override(&obligation, 50).unwrap_err();
+    assert_eq!(err, LendingError::ObligationHealthy.into());
+}
+
+#[test]
+fn classify_obligation_health() {
+    assert_eq!(
+        Obligation::default().classify_health(),
+        ObligationHealth::Healthy
+    );
+
+    // 700_000 debt against a 600_000 unhealthy borrow value
+    let mut obligation = unhealthy_obligation(Pubkey::new_unique());
+    assert_eq!(obligation.classify_health(), ObligationHealth::Liquidatable);
+
+    // exactly at the unhealthy borrow value
+    obligation.borrow_factor_adjusted_debt_value_sf = obligation.unhealthy_borrow_value_sf;
+    assert_eq!(obligation.classify_health(), ObligationHealth::Liquidatable);
+
+    obligation.borrowed_assets_market_value_sf = Fraction::from(500_000u64).to_bits();
+    obligation.borrow_factor_adjusted_debt_value_sf = Fraction::from(500_000u64).to_bits();
+    assert_eq!(obligation.classify_health(), ObligationHealth::Healthy);
+
+    // debt worth more than the deposits
+    obligation.borrowed_assets_market_value_sf = Fraction::from(1_100_000u64).to_bits();
+    obligation.borrow_factor_adjusted_debt_value_sf = Fraction::from(1_100_000u64).to_bits();
+    assert_eq!(obligation.classify_health(), ObligationHealth::BadDebt);
+}
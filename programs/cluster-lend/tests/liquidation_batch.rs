@@ -0,0 +1,200 @@
+#[cfg(test)]
+mod helpers;
+use std::rc::Rc;
+
+use cluster_lend::{
+    utils::{pda, Fraction},
+    InitObligationArgs, Obligation,
+};
+use obligation::{liquidate_obligations_batch_ix, ObligationFixture};
+use reserve::ReserveFixture;
+use solana_program_test::*;
+
+use helpers::*;
+use solana_sdk::{signature::Keypair, signer::Signer};
+use spl::TokenAccountFixture;
+use test::{TestFixture, PYTH_SOL_FEED, PYTH_USDC_FEED, TEST_RESERVE_CONFIG};
+
+const SOL: u64 = 1_000_000_000;
+const SOL_NATIVE_ONE: i64 = 1_000_000;
+
+#[tokio::test]
+async fn success_liquidate_batch_skipping_healthy_obligation() {
+    let user = Keypair::new();
+    let healthy_user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let sol_mint_f = test_f.sol_mint.clone();
+    let (market_f, usdc_reserve_f, obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+    let healthy_obligation_f = ObligationFixture::new(
+        &test_f,
+        &healthy_user,
+        market_f.key,
+        InitObligationArgs::default(),
+    )
+    .await;
+    let usdc_reserve_pdas = pda::init_reserve_pdas(&market_f.key, &usdc_mint_f.key);
+    let sol_reserve_pdas = pda::init_reserve_pdas(&market_f.key, &sol_mint_f.key);
+
+    let owner = test_f.payer_keypair();
+    let sol_reserve_key = Keypair::new();
+    let sol_reserve_f = ReserveFixture {
+        key: sol_reserve_key.pubkey(),
+        owner: owner.pubkey(),
+        payer: owner.pubkey(),
+        lending_market: market_f.key,
+        liquidity_mint: sol_mint_f.key,
+    };
+    let mut sol_config = TEST_RESERVE_CONFIG;
+    sol_config.token_info.pyth_configuration.price = PYTH_SOL_FEED;
+    test_f
+        .send_transaction(
+            &[
+                sol_reserve_f.initialize_reserve_ix(),
+                sol_reserve_f.update_reserve_ix(sol_config),
+            ],
+            &[&owner, &sol_reserve_key],
+        )
+        .await
+        .unwrap();
+
+    let usdc_liquidity = 100_000_000;
+    let owner_usdc_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&owner, usdc_liquidity)
+        .await;
+    let owner_usdc_collateral_ata = TokenAccountFixture::new(
+        Rc::clone(&test_f.context),
+        &usdc_reserve_pdas.collateral_ctoken_mint,
+        &owner.pubkey(),
+    )
+    .await;
+
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_pyth_oracle_timestamp(PYTH_SOL_FEED, 120).await;
+    test_f.set_slot_and_time(10, 120);
+
+    test_f
+        .send_transaction(
+            &[
+                usdc_reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                usdc_reserve_f.deposit_liquidity_ix(
+                    usdc_liquidity,
+                    owner_usdc_ata.key,
+                    owner_usdc_collateral_ata.key,
+                ),
+            ],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+
+    // $10 of SOL collateral each, against $7 and $3 of USDC debt
+    for (user, obligation_f, borrow_amount) in [
+        (&user, &obligation_f, 7_000_000),
+        (&healthy_user, &healthy_obligation_f, 3_000_000),
+    ] {
+        let user_sol_ata = sol_mint_f
+            .create_token_account_and_mint_to(user, 10 * SOL)
+            .await;
+        let user_usdc_ata = usdc_mint_f.create_token_account_and_mint_to(user, 0).await;
+        test_f
+            .send_transaction(
+                &[
+                    sol_reserve_f.refresh_ix(Some(PYTH_SOL_FEED)),
+                    obligation_f.deposit_liquidity_collateral_ix(
+                        10 * SOL,
+                        &sol_reserve_f,
+                        user_sol_ata.key,
+                    ),
+                    sol_reserve_f.refresh_ix(Some(PYTH_SOL_FEED)),
+                    usdc_reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                    obligation_f.refresh_ix(vec![sol_reserve_f.key]),
+                    obligation_f.borrow_liquidity_ix(
+                        borrow_amount,
+                        &usdc_reserve_f,
+                        user_usdc_ata.key,
+                    ),
+                ],
+                &[user],
+            )
+            .await
+            .unwrap();
+    }
+
+    let liquidator = test_f.payer_keypair();
+    let liquidator_source_liquidity = usdc_mint_f
+        .create_token_account_and_mint_to(&liquidator, usdc_liquidity)
+        .await;
+    let liquidator_destination_collateral = TokenAccountFixture::new(
+        Rc::clone(&test_f.context),
+        &sol_reserve_pdas.collateral_ctoken_mint,
+        &liquidator.pubkey(),
+    )
+    .await;
+    let liquidator_destination_liquidity = TokenAccountFixture::new(
+        Rc::clone(&test_f.context),
+        &sol_mint_f.key,
+        &liquidator.pubkey(),
+    )
+    .await;
+
+    // SOL drops to $0.80, only the $7 position is past its liquidation threshold
+    test_f.set_slot_and_time(20, 120);
+    test_f
+        .set_pyth_oracle_price(PYTH_SOL_FEED, SOL_NATIVE_ONE * 80 / 100)
+        .await;
+
+    let unhealthy_before: Obligation = test_f.load_and_deserialize(&obligation_f.key).await;
+    let healthy_before: Obligation = test_f.load_and_deserialize(&healthy_obligation_f.key).await;
+
+    let liquidity_amount = 1_000_000;
+    test_f
+        .send_transaction(
+            &[
+                sol_reserve_f.refresh_ix(Some(PYTH_SOL_FEED)),
+                usdc_reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![sol_reserve_f.key, usdc_reserve_f.key]),
+                healthy_obligation_f.refresh_ix(vec![sol_reserve_f.key, usdc_reserve_f.key]),
+                liquidate_obligations_batch_ix(
+                    market_f.key,
+                    vec![obligation_f.key, healthy_obligation_f.key],
+                    liquidity_amount,
+                    liquidator.pubkey(),
+                    usdc_reserve_f.key,
+                    usdc_reserve_pdas.liquidity_supply_vault,
+                    sol_reserve_f.key,
+                    sol_mint_f.key,
+                    liquidator_destination_collateral.key,
+                    liquidator_source_liquidity.key,
+                    liquidator_destination_liquidity.key,
+                ),
+            ],
+            &[&liquidator],
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(
+        liquidator_source_liquidity.balance().await,
+        usdc_liquidity - liquidity_amount
+    );
+    assert!(liquidator_destination_liquidity.balance().await > 0);
+
+    let liquidated: Obligation = test_f.load_and_deserialize(&obligation_f.key).await;
+    assert!(
+        Fraction::from_bits(liquidated.borrows[0].borrowed_amount_sf)
+            < Fraction::from_bits(unhealthy_before.borrows[0].borrowed_amount_sf)
+    );
+    assert!(
+        liquidated.deposits[0].deposited_amount < unhealthy_before.deposits[0].deposited_amount
+    );
+
+    let healthy: Obligation = test_f.load_and_deserialize(&healthy_obligation_f.key).await;
+    // only interest accrued on the skipped position
+    assert!(healthy.borrows[0].borrowed_amount_sf >= healthy_before.borrows[0].borrowed_amount_sf);
+    assert_eq!(
+        healthy.deposits[0].deposited_amount,
+        healthy_before.deposits[0].deposited_amount
+    );
+}
@@ -0,0 +1,247 @@
+#[cfg(test)]
+mod helpers;
+use std::rc::Rc;
+
+use cluster_lend::{
+    errors::LendingError, utils::pda, LiquidationBond, UpdateConfigMode, UpdateLendingMarketMode,
+};
+use solana_program_test::*;
+
+use helpers::*;
+use solana_sdk::{signature::Keypair, signer::Signer, system_instruction};
+use spl::TokenAccountFixture;
+use test::{TestFixture, PYTH_USDC_FEED};
+
+const CHALLENGE_WINDOW_SECS: u64 = 3_600;
+const BOND_LAMPORTS: u64 = 10_000_000;
+
+#[test]
+fn liquidation_bond_challenge_window_boundary() {
+    let mut bond = LiquidationBond::default();
+    bond.post(BOND_LAMPORTS, 100).unwrap();
+    bond.post(BOND_LAMPORTS, 200).unwrap();
+    assert_eq!(bond.amount, 2 * BOND_LAMPORTS);
+
+    // a new liquidation restarts the window
+    let window_end = 200 + CHALLENGE_WINDOW_SECS as i64;
+    assert_eq!(bond.challenge_window_end(CHALLENGE_WINDOW_SECS), window_end);
+
+    let err = bond
+        .check_refundable(window_end - 1, CHALLENGE_WINDOW_SECS)
+        .unwrap_err();
+    assert_eq!(
+        err,
+        LendingError::LiquidationBondChallengeWindowActive.into()
+    );
+    assert!(bond
+        .check_contestable(window_end - 1, CHALLENGE_WINDOW_SECS)
+        .is_ok());
+
+    assert!(bond
+        .check_refundable(window_end, CHALLENGE_WINDOW_SECS)
+        .is_ok());
+    let err = bond
+        .check_contestable(window_end, CHALLENGE_WINDOW_SECS)
+        .unwrap_err();
+    assert_eq!(
+        err,
+        LendingError::LiquidationBondChallengeWindowElapsed.into()
+    );
+}
+
+#[tokio::test]
+async fn success_liquidation_bond_refunded_after_window() {
+    liquidate_and_refund_bond(0).await;
+}
+
+#[tokio::test]
+async fn success_liquidation_bond_posted_to_a_prefunded_address() {
+    // funding the bond address ahead of the liquidator can't block the liquidation
+    liquidate_and_refund_bond(1_000_000).await;
+}
+
+async fn liquidate_and_refund_bond(prefunded_bond_lamports: u64) {
+    let user = Keypair::new();
+
+    // setup market & reserve/obligation
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (market_f, reserve_f, obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+    let reserve_pdas = pda::init_reserve_pdas(&market_f.key, &usdc_mint_f.key);
+
+    let deposit_amount = 10_000_000;
+    let user_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&user, deposit_amount)
+        .await;
+
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_time(120);
+
+    test_f
+        .send_transaction(
+            &[
+                obligation_f.deposit_liquidity_collateral_ix(
+                    deposit_amount,
+                    &reserve_f,
+                    user_liquidity_ata.key,
+                ),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key]),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    let borrow_amount = 7_000_000;
+    test_f
+        .send_transaction(
+            &[
+                obligation_f.borrow_liquidity_ix(borrow_amount, &reserve_f, user_liquidity_ata.key),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    // require bonds and make the obligation unhealthy
+    let mut bond_required = [0; 72];
+    bond_required[0] = 1;
+    let mut challenge_window = [0; 72];
+    challenge_window[..8].copy_from_slice(&CHALLENGE_WINDOW_SECS.to_le_bytes());
+    let mut bond_lamports = [0; 32];
+    bond_lamports[..8].copy_from_slice(&BOND_LAMPORTS.to_le_bytes());
+    let mut loan_to_value_pct = [0; 32];
+    loan_to_value_pct[0] = 50;
+    let mut liquidation_threshold_pct = [0; 32];
+    liquidation_threshold_pct[0] = 60;
+
+    let owner = test_f.payer_keypair();
+    test_f
+        .send_transaction(
+            &[
+                market_f.update_market_ix(
+                    UpdateLendingMarketMode::UpdateLiquidationBondRequired as u64,
+                    bond_required,
+                ),
+                market_f.update_market_ix(
+                    UpdateLendingMarketMode::UpdateLiquidationBondChallengeWindowSecs as u64,
+                    challenge_window,
+                ),
+                reserve_f.update_reserve_mode_ix(
+                    UpdateConfigMode::UpdateLiquidationBondLamports as u64,
+                    bond_lamports,
+                ),
+                reserve_f.update_reserve_mode_ix(
+                    UpdateConfigMode::UpdateLoanToValuePct as u64,
+                    loan_to_value_pct,
+                ),
+                reserve_f.update_reserve_mode_ix(
+                    UpdateConfigMode::UpdateLiquidationThresholdPct as u64,
+                    liquidation_threshold_pct,
+                ),
+            ],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+
+    // liquidate, posting the bond
+    let liquidator = test_f.payer_keypair();
+    let liquidator_source_liquidity = usdc_mint_f
+        .create_token_account_and_mint_to(&liquidator, deposit_amount)
+        .await;
+    let liquidator_destination_collateral = TokenAccountFixture::new(
+        Rc::clone(&test_f.context),
+        &reserve_pdas.collateral_ctoken_mint,
+        &liquidator.pubkey(),
+    )
+    .await;
+    let liquidator_destination_liquidity = TokenAccountFixture::new(
+        Rc::clone(&test_f.context),
+        &usdc_mint_f.key,
+        &liquidator.pubkey(),
+    )
+    .await;
+
+    let liquidation_bond_key = pda::liquidation_bond(&obligation_f.key, &liquidator.pubkey());
+    if prefunded_bond_lamports > 0 {
+        test_f
+            .send_transaction(
+                &[system_instruction::transfer(
+                    &owner.pubkey(),
+                    &liquidation_bond_key,
+                    prefunded_bond_lamports,
+                )],
+                &[&owner],
+            )
+            .await
+            .unwrap();
+    }
+
+    let liquidity_amount = 1_000_000;
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+                obligation_f.liquidate_ix(
+                    liquidity_amount,
+                    0,
+                    0,
+                    0,
+                    liquidator.pubkey(),
+                    reserve_f.key,
+                    reserve_pdas.liquidity_supply_vault,
+                    reserve_f.key,
+                    usdc_mint_f.key,
+                    liquidator_destination_collateral.key,
+                    liquidator_source_liquidity.key,
+                    liquidator_destination_liquidity.key,
+                ),
+            ],
+            &[&liquidator],
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        liquidator_source_liquidity.balance().await,
+        deposit_amount - liquidity_amount
+    );
+
+    let liquidation_bond: LiquidationBond =
+        test_f.load_and_deserialize(&liquidation_bond_key).await;
+    assert_eq!(liquidation_bond.obligation, obligation_f.key);
+    assert_eq!(liquidation_bond.liquidator, liquidator.pubkey());
+    assert_eq!(liquidation_bond.amount, BOND_LAMPORTS);
+    assert_eq!(liquidation_bond.posted_at, 120);
+
+    // uncontested through the window, the owner can no longer contest it
+    test_f.set_time(120 + CHALLENGE_WINDOW_SECS as i64);
+    let r = test_f
+        .send_transaction(
+            &[market_f.contest_liquidation_bond_ix(liquidation_bond_key)],
+            &[&owner],
+        )
+        .await;
+    assert!(r.is_err());
+
+    test_f
+        .send_transaction(
+            &[obligation_f.refund_liquidation_bond_ix(liquidator.pubkey())],
+            &[&liquidator],
+        )
+        .await
+        .unwrap();
+
+    let liquidation_bond_account = test_f
+        .context
+        .borrow_mut()
+        .banks_client
+        .get_account(liquidation_bond_key)
+        .await
+        .unwrap();
+    assert!(liquidation_bond_account.is_none());
+}
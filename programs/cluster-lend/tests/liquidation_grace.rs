@@ -0,0 +1,394 @@
+#[cfg(test)]
+mod helpers;
+use std::rc::Rc;
+
+use cluster_lend::{utils::pda, Obligation, UpdateConfigMode, UpdateLendingMarketMode};
+use reserve::ReserveFixture;
+use solana_program_test::*;
+
+use helpers::*;
+use solana_sdk::{compute_budget::ComputeBudgetInstruction, signature::Keypair, signer::Signer};
+use spl::TokenAccountFixture;
+use test::{TestFixture, PYTH_SOL_FEED, PYTH_USDC_FEED, TEST_RESERVE_CONFIG};
+
+const LIQUIDATION_GRACE_SLOTS: u64 = 100;
+const SOL: u64 = 1_000_000_000;
+const SOL_NATIVE_ONE: i64 = 1_000_000;
+
+#[tokio::test]
+async fn success_liquidate_after_grace_period() {
+    let user = Keypair::new();
+
+    // setup market & reserve/obligation
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (market_f, reserve_f, obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+    let reserve_pdas = pda::init_reserve_pdas(&market_f.key, &usdc_mint_f.key);
+
+    let deposit_amount = 10_000_000;
+    let user_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&user, deposit_amount)
+        .await;
+
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_slot_and_time(10, 120);
+
+    let mut grace_slots = [0; 72];
+    grace_slots[..8].copy_from_slice(&LIQUIDATION_GRACE_SLOTS.to_le_bytes());
+
+    let owner = test_f.payer_keypair();
+    test_f
+        .send_transaction(
+            &[market_f.update_market_ix(
+                UpdateLendingMarketMode::UpdateLiquidationGraceSlots as u64,
+                grace_slots,
+            )],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.deposit_liquidity_collateral_ix(
+                    deposit_amount,
+                    &reserve_f,
+                    user_liquidity_ata.key,
+                ),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key]),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    let borrow_amount = 7_000_000;
+    test_f
+        .send_transaction(
+            &[
+                obligation_f.borrow_liquidity_ix(borrow_amount, &reserve_f, user_liquidity_ata.key),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    let obligation: Obligation = test_f.load_and_deserialize(&obligation_f.key).await;
+    assert_eq!(obligation.unhealthy_since_slot, 0);
+
+    // make the obligation unhealthy
+    let mut loan_to_value_pct = [0; 32];
+    loan_to_value_pct[0] = 50;
+    let mut liquidation_threshold_pct = [0; 32];
+    liquidation_threshold_pct[0] = 60;
+
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.update_reserve_mode_ix(
+                    UpdateConfigMode::UpdateLoanToValuePct as u64,
+                    loan_to_value_pct,
+                ),
+                reserve_f.update_reserve_mode_ix(
+                    UpdateConfigMode::UpdateLiquidationThresholdPct as u64,
+                    liquidation_threshold_pct,
+                ),
+            ],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+
+    let unhealthy_since_slot = 20;
+    test_f.set_slot_and_time(unhealthy_since_slot, 120);
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+            ],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+
+    let obligation: Obligation = test_f.load_and_deserialize(&obligation_f.key).await;
+    assert_eq!(obligation.unhealthy_since_slot, unhealthy_since_slot);
+
+    let liquidator = test_f.payer_keypair();
+    let liquidator_source_liquidity = usdc_mint_f
+        .create_token_account_and_mint_to(&liquidator, deposit_amount)
+        .await;
+    let liquidator_destination_collateral = TokenAccountFixture::new(
+        Rc::clone(&test_f.context),
+        &reserve_pdas.collateral_ctoken_mint,
+        &liquidator.pubkey(),
+    )
+    .await;
+    let liquidator_destination_liquidity = TokenAccountFixture::new(
+        Rc::clone(&test_f.context),
+        &usdc_mint_f.key,
+        &liquidator.pubkey(),
+    )
+    .await;
+
+    let liquidity_amount = 1_000_000;
+    let liquidate_ix = || {
+        obligation_f.liquidate_ix(
+            liquidity_amount,
+            0,
+            0,
+            0,
+            liquidator.pubkey(),
+            reserve_f.key,
+            reserve_pdas.liquidity_supply_vault,
+            reserve_f.key,
+            usdc_mint_f.key,
+            liquidator_destination_collateral.key,
+            liquidator_source_liquidity.key,
+            liquidator_destination_liquidity.key,
+        )
+    };
+
+    // within the grace period, the compute limit keeps the signature apart from the retry
+    test_f.set_slot_and_time(unhealthy_since_slot + LIQUIDATION_GRACE_SLOTS - 1, 120);
+    let r = test_f
+        .send_transaction(
+            &[
+                ComputeBudgetInstruction::set_compute_unit_limit(400_000),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+                liquidate_ix(),
+            ],
+            &[&liquidator],
+        )
+        .await;
+    assert!(r.is_err());
+    assert_eq!(liquidator_source_liquidity.balance().await, deposit_amount);
+
+    // once the grace period passes
+    test_f.set_slot_and_time(unhealthy_since_slot + LIQUIDATION_GRACE_SLOTS, 120);
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+                liquidate_ix(),
+            ],
+            &[&liquidator],
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        liquidator_source_liquidity.balance().await,
+        deposit_amount - liquidity_amount
+    );
+}
+
+#[tokio::test]
+async fn fail_liquidate_on_one_slot_oracle_blip() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let sol_mint_f = test_f.sol_mint.clone();
+    let (market_f, usdc_reserve_f, obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+    let usdc_reserve_pdas = pda::init_reserve_pdas(&market_f.key, &usdc_mint_f.key);
+    let sol_reserve_pdas = pda::init_reserve_pdas(&market_f.key, &sol_mint_f.key);
+
+    let owner = test_f.payer_keypair();
+    let sol_reserve_key = Keypair::new();
+    let sol_reserve_f = ReserveFixture {
+        key: sol_reserve_key.pubkey(),
+        owner: owner.pubkey(),
+        payer: owner.pubkey(),
+        lending_market: market_f.key,
+        liquidity_mint: sol_mint_f.key,
+    };
+    let mut sol_config = TEST_RESERVE_CONFIG;
+    sol_config.token_info.pyth_configuration.price = PYTH_SOL_FEED;
+
+    let mut grace_slots = [0; 72];
+    grace_slots[..8].copy_from_slice(&LIQUIDATION_GRACE_SLOTS.to_le_bytes());
+    test_f
+        .send_transaction(
+            &[
+                sol_reserve_f.initialize_reserve_ix(),
+                sol_reserve_f.update_reserve_ix(sol_config),
+                market_f.update_market_ix(
+                    UpdateLendingMarketMode::UpdateLiquidationGraceSlots as u64,
+                    grace_slots,
+                ),
+            ],
+            &[&owner, &sol_reserve_key],
+        )
+        .await
+        .unwrap();
+
+    let usdc_liquidity = 100_000_000;
+    let owner_usdc_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&owner, usdc_liquidity)
+        .await;
+    let owner_usdc_collateral_ata = TokenAccountFixture::new(
+        Rc::clone(&test_f.context),
+        &usdc_reserve_pdas.collateral_ctoken_mint,
+        &owner.pubkey(),
+    )
+    .await;
+    let user_sol_ata = sol_mint_f
+        .create_token_account_and_mint_to(&user, 10 * SOL)
+        .await;
+    let user_usdc_ata = usdc_mint_f.create_token_account_and_mint_to(&user, 0).await;
+
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_pyth_oracle_timestamp(PYTH_SOL_FEED, 120).await;
+    test_f.set_slot_and_time(10, 120);
+
+    // $10 of SOL collateral against $7 of USDC debt
+    test_f
+        .send_transaction(
+            &[
+                usdc_reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                usdc_reserve_f.deposit_liquidity_ix(
+                    usdc_liquidity,
+                    owner_usdc_ata.key,
+                    owner_usdc_collateral_ata.key,
+                ),
+            ],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+    test_f
+        .send_transaction(
+            &[
+                sol_reserve_f.refresh_ix(Some(PYTH_SOL_FEED)),
+                obligation_f.deposit_liquidity_collateral_ix(
+                    10 * SOL,
+                    &sol_reserve_f,
+                    user_sol_ata.key,
+                ),
+                sol_reserve_f.refresh_ix(Some(PYTH_SOL_FEED)),
+                usdc_reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![sol_reserve_f.key]),
+                obligation_f.borrow_liquidity_ix(7_000_000, &usdc_reserve_f, user_usdc_ata.key),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    let liquidator = test_f.payer_keypair();
+    let liquidator_source_liquidity = usdc_mint_f
+        .create_token_account_and_mint_to(&liquidator, usdc_liquidity)
+        .await;
+    let liquidator_destination_collateral = TokenAccountFixture::new(
+        Rc::clone(&test_f.context),
+        &sol_reserve_pdas.collateral_ctoken_mint,
+        &liquidator.pubkey(),
+    )
+    .await;
+    let liquidator_destination_liquidity = TokenAccountFixture::new(
+        Rc::clone(&test_f.context),
+        &sol_mint_f.key,
+        &liquidator.pubkey(),
+    )
+    .await;
+
+    // the compute limit keeps each refresh apart from the identical ones in other slots
+    let refresh_ixs = |compute_units: u32| {
+        vec![
+            ComputeBudgetInstruction::set_compute_unit_limit(compute_units),
+            sol_reserve_f.refresh_ix(Some(PYTH_SOL_FEED)),
+            usdc_reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+            obligation_f.refresh_ix(vec![sol_reserve_f.key, usdc_reserve_f.key]),
+        ]
+    };
+    let liquidity_amount = 1_000_000;
+    let refresh_and_liquidate_ixs = |compute_units: u32| {
+        let mut ixs = refresh_ixs(compute_units);
+        ixs.push(obligation_f.liquidate_ix(
+            liquidity_amount,
+            0,
+            0,
+            0,
+            liquidator.pubkey(),
+            usdc_reserve_f.key,
+            usdc_reserve_pdas.liquidity_supply_vault,
+            sol_reserve_f.key,
+            sol_mint_f.key,
+            liquidator_destination_collateral.key,
+            liquidator_source_liquidity.key,
+            liquidator_destination_liquidity.key,
+        ));
+        ixs
+    };
+
+    // SOL dips to $0.80 for a slot, $6.80 of liquidation threshold against $7 of debt
+    let blip_slot = 20;
+    test_f.set_slot_and_time(blip_slot, 120);
+    test_f
+        .set_pyth_oracle_price(PYTH_SOL_FEED, SOL_NATIVE_ONE * 80 / 100)
+        .await;
+    test_f
+        .send_transaction(&refresh_ixs(200_000), &[&owner])
+        .await
+        .unwrap();
+
+    let obligation: Obligation = test_f.load_and_deserialize(&obligation_f.key).await;
+    assert_eq!(obligation.unhealthy_since_slot, blip_slot);
+
+    let r = test_f
+        .send_transaction(&refresh_and_liquidate_ixs(210_000), &[&liquidator])
+        .await;
+    assert!(r.is_err());
+
+    // the price recovers the next slot
+    test_f.set_slot_and_time(blip_slot + 1, 120);
+    test_f
+        .set_pyth_oracle_price(PYTH_SOL_FEED, SOL_NATIVE_ONE)
+        .await;
+    test_f
+        .send_transaction(&refresh_ixs(220_000), &[&owner])
+        .await
+        .unwrap();
+
+    let obligation: Obligation = test_f.load_and_deserialize(&obligation_f.key).await;
+    assert_eq!(obligation.unhealthy_since_slot, 0);
+
+    // a later dip starts its own window, the earlier blip doesn't count towards it
+    let dip_slot = blip_slot + LIQUIDATION_GRACE_SLOTS;
+    test_f.set_slot_and_time(dip_slot, 120);
+    test_f
+        .set_pyth_oracle_price(PYTH_SOL_FEED, SOL_NATIVE_ONE * 80 / 100)
+        .await;
+    test_f
+        .send_transaction(&refresh_ixs(230_000), &[&owner])
+        .await
+        .unwrap();
+
+    let obligation: Obligation = test_f.load_and_deserialize(&obligation_f.key).await;
+    assert_eq!(obligation.unhealthy_since_slot, dip_slot);
+
+    let r = test_f
+        .send_transaction(&refresh_and_liquidate_ixs(240_000), &[&liquidator])
+        .await;
+    assert!(r.is_err());
+    assert_eq!(liquidator_source_liquidity.balance().await, usdc_liquidity);
+
+    // the dip persisting for the whole window allows the liquidation
+    test_f.set_slot_and_time(dip_slot + LIQUIDATION_GRACE_SLOTS, 120);
+    test_f
+        .send_transaction(&refresh_and_liquidate_ixs(250_000), &[&liquidator])
+        .await
+        .unwrap();
+    assert_eq!(
+        liquidator_source_liquidity.balance().await,
+        usdc_liquidity - liquidity_amount
+    );
+}
@@ -0,0 +1,179 @@
+#[cfg(test)]
+mod helpers;
+use std::rc::Rc;
+
+use cluster_lend::{utils::pda, UpdateConfigMode};
+use solana_program_test::*;
+
+use helpers::*;
+use reserve::u64_value;
+use solana_sdk::{signature::Keypair, signer::Signer};
+use spl::TokenAccountFixture;
+use test::{TestFixture, PYTH_USDC_FEED};
+
+const PRICE_MAX_AGE: u64 = 60;
+const PRICE_MAX_AGE_LIQUIDATION: u64 = 600;
+
+#[tokio::test]
+async fn success_liquidate_with_price_too_old_to_borrow() {
+    let user = Keypair::new();
+
+    // setup market & reserve/obligation
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (market_f, reserve_f, obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+    let reserve_pdas = pda::init_reserve_pdas(&market_f.key, &usdc_mint_f.key);
+
+    let deposit_amount = 10_000_000;
+    let user_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&user, deposit_amount)
+        .await;
+
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_time(120);
+
+    test_f
+        .send_transaction(
+            &[
+                obligation_f.deposit_liquidity_collateral_ix(
+                    deposit_amount,
+                    &reserve_f,
+                    user_liquidity_ata.key,
+                ),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key]),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    let borrow_amount = 7_000_000;
+    test_f
+        .send_transaction(
+            &[
+                obligation_f.borrow_liquidity_ix(borrow_amount, &reserve_f, user_liquidity_ata.key),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    let owner = test_f.payer_keypair();
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.update_reserve_mode_ix(
+                    UpdateConfigMode::UpdateTokenInfoPriceMaxAge as u64,
+                    u64_value(PRICE_MAX_AGE),
+                ),
+                reserve_f.update_reserve_mode_ix(
+                    UpdateConfigMode::UpdateTokenInfoPriceMaxAgeLiquidation as u64,
+                    u64_value(PRICE_MAX_AGE_LIQUIDATION),
+                ),
+            ],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+
+    // the price is too old to borrow against
+    test_f.set_time(120 + 300);
+    let r = test_f
+        .send_transaction(
+            &[
+                obligation_f.borrow_liquidity_ix(1_000, &reserve_f, user_liquidity_ata.key),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+            ],
+            &[&user],
+        )
+        .await;
+    assert!(r.is_err());
+
+    // make the obligation unhealthy
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.update_reserve_mode_ix(
+                    UpdateConfigMode::UpdateLoanToValuePct as u64,
+                    u64_value(50),
+                ),
+                reserve_f.update_reserve_mode_ix(
+                    UpdateConfigMode::UpdateLiquidationThresholdPct as u64,
+                    u64_value(60),
+                ),
+            ],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+
+    let liquidator = test_f.payer_keypair();
+    let liquidator_source_liquidity = usdc_mint_f
+        .create_token_account_and_mint_to(&liquidator, deposit_amount)
+        .await;
+    let liquidator_destination_collateral = TokenAccountFixture::new(
+        Rc::clone(&test_f.context),
+        &reserve_pdas.collateral_ctoken_mint,
+        &liquidator.pubkey(),
+    )
+    .await;
+    let liquidator_destination_liquidity = TokenAccountFixture::new(
+        Rc::clone(&test_f.context),
+        &usdc_mint_f.key,
+        &liquidator.pubkey(),
+    )
+    .await;
+
+    let liquidate_ix = |liquidity_amount| {
+        obligation_f.liquidate_ix(
+            liquidity_amount,
+            0,
+            0,
+            0,
+            liquidator.pubkey(),
+            reserve_f.key,
+            reserve_pdas.liquidity_supply_vault,
+            reserve_f.key,
+            usdc_mint_f.key,
+            liquidator_destination_collateral.key,
+            liquidator_source_liquidity.key,
+            liquidator_destination_liquidity.key,
+        )
+    };
+
+    // but still fresh enough to liquidate against
+    let liquidity_amount = 1_000_000;
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+                liquidate_ix(liquidity_amount),
+            ],
+            &[&liquidator],
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        liquidator_source_liquidity.balance().await,
+        deposit_amount - liquidity_amount
+    );
+
+    // past the liquidation max age the price can't be used at all
+    test_f.set_time(120 + PRICE_MAX_AGE_LIQUIDATION as i64 + 1);
+    let r = test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+                liquidate_ix(liquidity_amount / 2),
+            ],
+            &[&liquidator],
+        )
+        .await;
+    assert!(r.is_err());
+}
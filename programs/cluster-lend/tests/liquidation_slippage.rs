@@ -0,0 +1,172 @@
+#[cfg(test)]
+mod helpers;
+use std::rc::Rc;
+
+use anchor_lang::AnchorDeserialize;
+use cluster_lend::{errors::LendingError, utils::pda, SimulateLiquidationResult, UpdateConfigMode};
+use solana_program_test::*;
+
+use helpers::*;
+use solana_sdk::{signature::Keypair, signer::Signer};
+use spl::TokenAccountFixture;
+use test::{TestFixture, PYTH_USDC_FEED};
+
+#[tokio::test]
+async fn failure_liquidation_net_liquidity_below_min() {
+    let user = Keypair::new();
+
+    // setup market & reserve/obligation
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (market_f, reserve_f, obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+    let reserve_pdas = pda::init_reserve_pdas(&market_f.key, &usdc_mint_f.key);
+
+    let deposit_amount = 10_000_000;
+    let user_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&user, deposit_amount)
+        .await;
+
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_time(120);
+
+    test_f
+        .send_transaction(
+            &[
+                obligation_f.deposit_liquidity_collateral_ix(
+                    deposit_amount,
+                    &reserve_f,
+                    user_liquidity_ata.key,
+                ),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key]),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    let borrow_amount = 7_000_000;
+    test_f
+        .send_transaction(
+            &[
+                obligation_f.borrow_liquidity_ix(borrow_amount, &reserve_f, user_liquidity_ata.key),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    // make the obligation unhealthy
+    let mut loan_to_value_pct = [0; 32];
+    loan_to_value_pct[0] = 50;
+    let mut liquidation_threshold_pct = [0; 32];
+    liquidation_threshold_pct[0] = 60;
+
+    let owner = test_f.payer_keypair();
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.update_reserve_mode_ix(
+                    UpdateConfigMode::UpdateLoanToValuePct as u64,
+                    loan_to_value_pct,
+                ),
+                reserve_f.update_reserve_mode_ix(
+                    UpdateConfigMode::UpdateLiquidationThresholdPct as u64,
+                    liquidation_threshold_pct,
+                ),
+            ],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+
+    let liquidity_amount = 1_000_000;
+    let return_data = test_f
+        .send_transaction_with_return_data(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+                obligation_f.simulate_liquidation_ix(
+                    liquidity_amount,
+                    reserve_f.key,
+                    reserve_f.key,
+                ),
+            ],
+            &[],
+        )
+        .await
+        .unwrap();
+    let simulated = SimulateLiquidationResult::try_from_slice(&return_data).unwrap();
+    assert_eq!(simulated.repay_amount, liquidity_amount);
+    assert!(simulated.withdraw_collateral_amount > 0);
+
+    let liquidator = test_f.payer_keypair();
+    let liquidator_source_liquidity = usdc_mint_f
+        .create_token_account_and_mint_to(&liquidator, deposit_amount)
+        .await;
+    let liquidator_destination_collateral = TokenAccountFixture::new(
+        Rc::clone(&test_f.context),
+        &reserve_pdas.collateral_ctoken_mint,
+        &liquidator.pubkey(),
+    )
+    .await;
+    let liquidator_destination_liquidity = TokenAccountFixture::new(
+        Rc::clone(&test_f.context),
+        &usdc_mint_f.key,
+        &liquidator.pubkey(),
+    )
+    .await;
+
+    let liquidate_ix = |min_acceptable_received_liquidity_amount| {
+        obligation_f.liquidate_ix(
+            liquidity_amount,
+            0,
+            simulated.withdraw_collateral_amount,
+            min_acceptable_received_liquidity_amount,
+            liquidator.pubkey(),
+            reserve_f.key,
+            reserve_pdas.liquidity_supply_vault,
+            reserve_f.key,
+            usdc_mint_f.key,
+            liquidator_destination_collateral.key,
+            liquidator_source_liquidity.key,
+            liquidator_destination_liquidity.key,
+        )
+    };
+
+    let net_liquidity_amount =
+        simulated.withdraw_liquidity_amount - simulated.protocol_liquidation_fee;
+
+    // the collateral bound is met exactly, but the liquidity left after the protocol fee is not
+    let err = test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+                liquidate_ix(net_liquidity_amount + 1),
+            ],
+            &[&liquidator],
+        )
+        .await
+        .unwrap_err();
+    assert_custom_error!(err, LendingError::LiquidationSlippageError);
+
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+                liquidate_ix(net_liquidity_amount),
+            ],
+            &[&liquidator],
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(
+        liquidator_destination_liquidity.balance().await,
+        net_liquidity_amount
+    );
+}
@@ -0,0 +1,104 @@
+#[cfg(test)]
+mod helpers;
+
+use cluster_lend::{LendingMarket, UpdateLendingMarketMode};
+use solana_program_test::*;
+
+use helpers::*;
+use solana_sdk::signature::Keypair;
+use test::{TestFixture, PYTH_USDC_FEED};
+
+#[tokio::test]
+async fn success_obligation_grows_up_to_max_value() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (market_f, reserve_f, obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+
+    // the cap is a market value, $1,500 with USDC at $1
+    let max_obligation_value = 1_500u64;
+    let mut value = [0; 72];
+    value[..8].copy_from_slice(&max_obligation_value.to_le_bytes());
+
+    test_f
+        .send_transaction(
+            &[market_f.update_market_ix(
+                UpdateLendingMarketMode::UpdateMaxObligationValue as u64,
+                value,
+            )],
+            &[&test_f.payer_keypair()],
+        )
+        .await
+        .unwrap();
+
+    let market: LendingMarket = test_f.load_and_deserialize(&market_f.key).await;
+    assert_eq!(market.max_obligation_value, max_obligation_value);
+
+    let deposit_amount = 1_000_000_000;
+    let user_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&user, 2 * deposit_amount)
+        .await;
+
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_time(120);
+
+    test_f
+        .send_transaction(
+            &[
+                obligation_f.deposit_liquidity_collateral_ix(
+                    deposit_amount,
+                    &reserve_f,
+                    user_liquidity_ata.key,
+                ),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key]),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    // $1,000 deposited and $400 borrowed stays under the cap
+    test_f
+        .send_transaction(
+            &[
+                obligation_f.borrow_liquidity_ix(400_000_000, &reserve_f, user_liquidity_ata.key),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    // borrowing more pushes the obligation above the cap
+    let r = test_f
+        .send_transaction(
+            &[
+                obligation_f.borrow_liquidity_ix(200_000_000, &reserve_f, user_liquidity_ata.key),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+            ],
+            &[&user],
+        )
+        .await;
+    assert!(r.is_err());
+
+    // and so does depositing more
+    let r = test_f
+        .send_transaction(
+            &[
+                obligation_f.deposit_liquidity_collateral_ix(
+                    200_000_000,
+                    &reserve_f,
+                    user_liquidity_ata.key,
+                ),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+            ],
+            &[&user],
+        )
+        .await;
+    assert!(r.is_err());
+}
@@ -0,0 +1,164 @@
+#[cfg(test)]
+mod helpers;
+use std::rc::Rc;
+
+use cluster_lend::{errors::LendingError, utils::pda, Reserve, UpdateConfigMode};
+use solana_program_test::*;
+
+use helpers::*;
+use solana_sdk::{signature::Keypair, signer::Signer};
+use spl::TokenAccountFixture;
+use test::{TestFixture, PYTH_USDC_FEED};
+
+fn max_single_deposit_value(max_single_deposit: u64) -> [u8; 32] {
+    let mut value = [0; 32];
+    value[..8].copy_from_slice(&max_single_deposit.to_le_bytes());
+    value
+}
+
+#[tokio::test]
+async fn success_deposit_up_to_max_single_deposit() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (market_f, reserve_f, _obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+    let reserve_pdas = pda::init_reserve_pdas(&market_f.key, &usdc_mint_f.key);
+
+    let max_single_deposit = 1_000_000;
+    test_f
+        .send_transaction(
+            &[reserve_f.update_reserve_mode_ix(
+                UpdateConfigMode::UpdateMaxSingleDeposit as u64,
+                max_single_deposit_value(max_single_deposit),
+            )],
+            &[&test_f.payer_keypair()],
+        )
+        .await
+        .unwrap();
+
+    let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+    assert_eq!(reserve.config.max_single_deposit, max_single_deposit);
+
+    // reserve deposits go through the reserve owner
+    let owner = test_f.payer_keypair();
+    let owner_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&owner, 2 * max_single_deposit)
+        .await;
+    let owner_collateral_ata = TokenAccountFixture::new(
+        Rc::clone(&test_f.context),
+        &reserve_pdas.collateral_ctoken_mint,
+        &owner.pubkey(),
+    )
+    .await;
+
+    // the cap is per call, not per depositor
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                reserve_f.deposit_liquidity_ix(
+                    max_single_deposit,
+                    owner_liquidity_ata.key,
+                    owner_collateral_ata.key,
+                ),
+                reserve_f.deposit_liquidity_ix(
+                    max_single_deposit,
+                    owner_liquidity_ata.key,
+                    owner_collateral_ata.key,
+                ),
+            ],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(owner_collateral_ata.balance().await, 2 * max_single_deposit);
+
+    // withdrawals are not capped
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                reserve_f.redeem_collateral_ix(
+                    2 * max_single_deposit,
+                    owner_collateral_ata.key,
+                    owner_liquidity_ata.key,
+                ),
+            ],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(owner_liquidity_ata.balance().await, 2 * max_single_deposit);
+}
+
+#[tokio::test]
+async fn failure_deposit_above_max_single_deposit() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (market_f, reserve_f, obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+    let reserve_pdas = pda::init_reserve_pdas(&market_f.key, &usdc_mint_f.key);
+
+    let max_single_deposit = 1_000_000;
+    test_f
+        .send_transaction(
+            &[reserve_f.update_reserve_mode_ix(
+                UpdateConfigMode::UpdateMaxSingleDeposit as u64,
+                max_single_deposit_value(max_single_deposit),
+            )],
+            &[&test_f.payer_keypair()],
+        )
+        .await
+        .unwrap();
+
+    let owner = test_f.payer_keypair();
+    let owner_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&owner, max_single_deposit + 1)
+        .await;
+    let owner_collateral_ata = TokenAccountFixture::new(
+        Rc::clone(&test_f.context),
+        &reserve_pdas.collateral_ctoken_mint,
+        &owner.pubkey(),
+    )
+    .await;
+    let user_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&user, max_single_deposit + 1)
+        .await;
+
+    let err = test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                reserve_f.deposit_liquidity_ix(
+                    max_single_deposit + 1,
+                    owner_liquidity_ata.key,
+                    owner_collateral_ata.key,
+                ),
+            ],
+            &[&owner],
+        )
+        .await
+        .unwrap_err();
+    assert_custom_error!(err, LendingError::DepositLimitExceeded);
+
+    // obligation deposits go through the same check
+    let err = test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.deposit_liquidity_collateral_ix(
+                    max_single_deposit + 1,
+                    &reserve_f,
+                    user_liquidity_ata.key,
+                ),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap_err();
+    assert_custom_error!(err, LendingError::DepositLimitExceeded);
+}
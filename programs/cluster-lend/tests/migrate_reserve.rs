@@ -0,0 +1,102 @@
+#[cfg(test)]
+mod helpers;
+
+use cluster_lend::{constants::PROGRAM_VERSION, Reserve, RewardState};
+use solana_program_test::*;
+
+use helpers::*;
+use solana_sdk::{pubkey::Pubkey, signature::Keypair};
+use test::{TestFixture, PYTH_USDC_FEED};
+
+#[tokio::test]
+async fn success_migrate_old_reserve() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (_market_f, reserve_f, _obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+    let owner = test_f.payer_keypair();
+
+    // an up to date reserve has nothing to migrate
+    let r = test_f
+        .send_transaction(&[reserve_f.migrate_reserve_ix()], &[&owner])
+        .await;
+    assert!(r.is_err());
+
+    // a reserve written by an older program, with leftovers in its reserved space
+    test_f
+        .update_reserve_account(reserve_f.key, |reserve| {
+            reserve.version = PROGRAM_VERSION as u64 - 1;
+            reserve.reward_state.deposited_collateral = 7;
+            reserve.reserved = [7; 1];
+            reserve.config.insurance_fee_bps = 7;
+            reserve.config.min_borrow_rate_bps = 7;
+            reserve.config.reserved = [7; 15];
+            reserve.liquidity.insurance_vault = Pubkey::new_unique();
+            // leftovers that would fail the config validation if kept
+            reserve.config.debt_tranche = 7;
+            reserve.config.is_rebasing = 7;
+            reserve.config.repay_fee_sf = u64::MAX;
+            reserve.config.token_info.price_pin_mode = 7;
+            reserve.config.token_info.price_authority = Pubkey::new_unique();
+        })
+        .await;
+
+    let r = test_f
+        .send_transaction(&[reserve_f.refresh_ix(Some(PYTH_USDC_FEED))], &[&owner])
+        .await;
+    assert!(r.is_err());
+
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.migrate_reserve_ix(),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+            ],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+
+    let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+    assert_eq!(reserve.version, PROGRAM_VERSION as u64);
+    assert_eq!(reserve.reward_state, RewardState::default());
+    assert_eq!(reserve.reserved, [0; 1]);
+    assert_eq!(reserve.config.insurance_fee_bps, 0);
+    assert_eq!(reserve.config.min_borrow_rate_bps, 0);
+    assert_eq!(reserve.config.reserved, [0; 15]);
+    assert_eq!(reserve.liquidity.insurance_vault, Pubkey::default());
+    assert_eq!(reserve.config.debt_tranche, 0);
+    assert_eq!(reserve.config.is_rebasing, 0);
+    assert_eq!(reserve.config.repay_fee_sf, 0);
+    assert_eq!(reserve.config.token_info.price_pin_mode, 0);
+    assert_eq!(reserve.config.token_info.price_authority, Pubkey::default());
+}
+
+#[tokio::test]
+async fn fail_migrate_reserve_with_invalid_config() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (_market_f, reserve_f, _obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+
+    // the liquidation threshold must not be below the LTV under the current rules
+    test_f
+        .update_reserve_account(reserve_f.key, |reserve| {
+            reserve.version = PROGRAM_VERSION as u64 - 1;
+            reserve.config.liquidation_threshold_pct = reserve.config.loan_to_value_pct - 1;
+        })
+        .await;
+
+    let r = test_f
+        .send_transaction(
+            &[reserve_f.migrate_reserve_ix()],
+            &[&test_f.payer_keypair()],
+        )
+        .await;
+    assert!(r.is_err());
+
+    let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+    assert_eq!(reserve.version, PROGRAM_VERSION as u64 - 1);
+}
@@ -0,0 +1,214 @@
+#[cfg(test)]
+mod helpers;
+use std::rc::Rc;
+
+use cluster_lend::{errors::LendingError, utils::pda, Reserve, UpdateConfigMode};
+use solana_program_test::*;
+
+use helpers::*;
+use solana_sdk::{signature::Keypair, signer::Signer};
+use spl::TokenAccountFixture;
+use test::{TestFixture, PYTH_USDC_FEED};
+
+fn min_available_liquidity_value(amount: u64) -> [u8; 32] {
+    let mut value = [0; 32];
+    value[..8].copy_from_slice(&amount.to_le_bytes());
+    value
+}
+
+#[test]
+fn check_min_available_liquidity_boundary() {
+    let mut reserve = Reserve::default();
+    reserve.liquidity.available_amount = 200_000;
+    assert!(reserve.check_min_available_liquidity().is_ok());
+
+    reserve.config.min_available_liquidity = 200_000;
+    assert!(reserve.check_min_available_liquidity().is_ok());
+
+    reserve.config.min_available_liquidity = 200_001;
+    let err = reserve.check_min_available_liquidity().unwrap_err();
+    assert_eq!(err, LendingError::InsufficientLiquidity.into());
+}
+
+#[tokio::test]
+async fn success_redeem_down_to_min_available_liquidity() {
+    let user = Keypair::new();
+
+    // setup market & reserve
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (market_f, reserve_f, _obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+    let reserve_pdas = pda::init_reserve_pdas(&market_f.key, &usdc_mint_f.key);
+
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_time(120);
+
+    // deposit liquidity to the reserve as the reserve owner
+    let owner = test_f.payer_keypair();
+    let deposit_amount = 1_000_000;
+    let owner_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&owner, deposit_amount)
+        .await;
+    let owner_collateral_ata_f = TokenAccountFixture::new(
+        Rc::clone(&test_f.context),
+        &reserve_pdas.collateral_ctoken_mint,
+        &owner.pubkey(),
+    )
+    .await;
+
+    let min_available_liquidity = 200_000;
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                reserve_f.deposit_liquidity_ix(
+                    deposit_amount,
+                    owner_liquidity_ata.key,
+                    owner_collateral_ata_f.key,
+                ),
+                reserve_f.update_reserve_mode_ix(
+                    UpdateConfigMode::UpdateMinAvailableLiquidity as u64,
+                    min_available_liquidity_value(min_available_liquidity),
+                ),
+            ],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+
+    // one past the minimum is rejected
+    let redeem_amount = deposit_amount - min_available_liquidity;
+    let r = test_f
+        .send_transaction(
+            &[reserve_f.redeem_collateral_ix(
+                redeem_amount + 1,
+                owner_collateral_ata_f.key,
+                owner_liquidity_ata.key,
+            )],
+            &[&owner],
+        )
+        .await;
+    assert!(r.is_err());
+
+    // redeeming down to exactly the minimum goes through
+    test_f
+        .send_transaction(
+            &[reserve_f.redeem_collateral_ix(
+                redeem_amount,
+                owner_collateral_ata_f.key,
+                owner_liquidity_ata.key,
+            )],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+
+    let r = test_f
+        .send_transaction(
+            &[reserve_f.redeem_collateral_ix(
+                1,
+                owner_collateral_ata_f.key,
+                owner_liquidity_ata.key,
+            )],
+            &[&owner],
+        )
+        .await;
+    assert!(r.is_err());
+
+    // redeeming the last of the collateral closes the position
+    test_f
+        .send_transaction(
+            &[reserve_f.redeem_collateral_ix(
+                min_available_liquidity,
+                owner_collateral_ata_f.key,
+                owner_liquidity_ata.key,
+            )],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(owner_collateral_ata_f.balance().await, 0);
+    assert_eq!(owner_liquidity_ata.balance().await, deposit_amount);
+}
+
+#[tokio::test]
+async fn success_borrow_down_to_min_available_liquidity() {
+    let user = Keypair::new();
+
+    // setup market & reserve/obligation
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (_market_f, reserve_f, obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_time(120);
+
+    let min_available_liquidity = 400_000;
+    test_f
+        .send_transaction(
+            &[reserve_f.update_reserve_mode_ix(
+                UpdateConfigMode::UpdateMinAvailableLiquidity as u64,
+                min_available_liquidity_value(min_available_liquidity),
+            )],
+            &[&test_f.payer_keypair()],
+        )
+        .await
+        .unwrap();
+
+    let deposit_amount = 1_000_000;
+    let user_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&user, deposit_amount)
+        .await;
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.deposit_liquidity_collateral_ix(
+                    deposit_amount,
+                    &reserve_f,
+                    user_liquidity_ata.key,
+                ),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key]),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    // one past the minimum is rejected
+    let borrow_amount = deposit_amount - min_available_liquidity;
+    let r = test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key]),
+                obligation_f.borrow_liquidity_ix(
+                    borrow_amount + 1,
+                    &reserve_f,
+                    user_liquidity_ata.key,
+                ),
+            ],
+            &[&user],
+        )
+        .await;
+    assert!(r.is_err());
+
+    // borrowing down to exactly the minimum goes through
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key]),
+                obligation_f.borrow_liquidity_ix(borrow_amount, &reserve_f, user_liquidity_ata.key),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+    assert_eq!(reserve.liquidity.available_amount, min_available_liquidity);
+    assert_eq!(user_liquidity_ata.balance().await, borrow_amount);
+}
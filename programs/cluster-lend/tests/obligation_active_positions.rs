@@ -0,0 +1,54 @@
+use anchor_lang::prelude::*;
+use cluster_lend::{utils::Fraction, Obligation, ObligationCollateral, ObligationLiquidity};
+
+#[test]
+fn active_positions_skip_empty_slots() {
+    let deposit_reserves = [Pubkey::new_unique(), Pubkey::new_unique()];
+    let borrow_reserves = [Pubkey::new_unique(), Pubkey::new_unique()];
+
+    // filled slots with empty ones before, between and after them
+    let mut obligation = Obligation::default();
+    obligation.deposits[1] = ObligationCollateral::new(deposit_reserves[0]);
+    obligation.deposits[5] = ObligationCollateral::new(deposit_reserves[1]);
+    obligation.borrows[0] = ObligationLiquidity::new(borrow_reserves[0], Fraction::ONE.into());
+    obligation.borrows[3] = ObligationLiquidity::new(borrow_reserves[1], Fraction::ONE.into());
+
+    assert_eq!(
+        obligation
+            .active_deposits()
+            .map(|c| c.deposit_reserve)
+            .collect::<Vec<_>>(),
+        deposit_reserves
+    );
+    assert_eq!(
+        obligation
+            .active_borrows()
+            .map(|l| l.borrow_reserve)
+            .collect::<Vec<_>>(),
+        borrow_reserves
+    );
+    assert_eq!(obligation.deposits_count(), 2);
+    assert_eq!(obligation.borrows_count(), 2);
+    assert!(!obligation.deposits_empty());
+    assert!(!obligation.borrows_empty());
+
+    // clearing the first filled slot leaves only the later one
+    obligation.deposits[1] = ObligationCollateral::default();
+    assert_eq!(
+        obligation
+            .active_deposits()
+            .map(|c| c.deposit_reserve)
+            .collect::<Vec<_>>(),
+        [deposit_reserves[1]]
+    );
+}
+
+#[test]
+fn active_positions_of_empty_obligation() {
+    let obligation = Obligation::default();
+
+    assert_eq!(obligation.active_deposits().count(), 0);
+    assert_eq!(obligation.active_borrows().count(), 0);
+    assert!(obligation.deposits_empty());
+    assert!(obligation.borrows_empty());
+}
@@ -0,0 +1,164 @@
+#[cfg(test)]
+mod helpers;
+
+use anchor_lang::prelude::*;
+use cluster_lend::{
+    lending_market::lending_operations::refresh_obligation,
+    utils::{FatAccountLoader, Fraction},
+    AssetTier, DebtTranche, LendingMarket, Obligation, Reserve,
+};
+
+use helpers::*;
+use reserve::{refreshed_reserve, reserve_account_data};
+
+const SLOT: u64 = 100;
+
+#[test]
+fn closing_middle_positions_compacts_obligation() {
+    let program_id = cluster_lend::ID;
+    let keys = [
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    ];
+    let mut lamports = [0; 3];
+    // the middle reserve has a different tier, refreshing stamps it on its positions
+    let mut data = [
+        (1, AssetTier::Regular),
+        (2, AssetTier::IsolatedDebt),
+        (3, AssetTier::Regular),
+    ]
+    .map(|(market_price, asset_tier)| {
+        let mut reserve = refreshed_reserve(market_price, 2_000_000, SLOT);
+        reserve.config.asset_tier = asset_tier.into();
+        reserve_account_data(&reserve)
+    });
+    let infos: Vec<AccountInfo> = keys
+        .iter()
+        .zip(lamports.iter_mut())
+        .zip(data.iter_mut())
+        .map(|((key, lamports), data)| {
+            AccountInfo::new(key, false, true, lamports, data, &program_id, false, 0)
+        })
+        .collect();
+    let reserves = |indexes: &[usize]| {
+        indexes
+            .iter()
+            .map(|index| FatAccountLoader::<Reserve>::try_from(&infos[*index]).unwrap())
+            .collect::<Vec<_>>()
+    };
+
+    // three deposits and three borrows, one per reserve
+    let mut obligation = Obligation::default();
+    let deposit_tiers = [
+        AssetTier::Regular,
+        AssetTier::IsolatedDebt,
+        AssetTier::Regular,
+    ];
+    let borrow_tiers = [
+        AssetTier::Regular,
+        AssetTier::IsolatedDebt,
+        AssetTier::Regular,
+    ];
+    let tiers = deposit_tiers.into_iter().zip(borrow_tiers);
+    for (index, (key, (deposit_tier, borrow_tier))) in keys.iter().zip(tiers).enumerate() {
+        let (collateral, _) = obligation
+            .find_or_add_collateral_to_deposits(*key, deposit_tier, 0)
+            .unwrap();
+        collateral.deposited_amount = 100_000 * (index as u64 + 1);
+
+        let (liquidity, _) = obligation
+            .find_or_add_liquidity_to_borrows(
+                *key,
+                Fraction::ONE.into(),
+                borrow_tier,
+                DebtTranche::Senior,
+                0,
+            )
+            .unwrap();
+        liquidity.borrowed_amount_sf = Fraction::from(10_000 * (index as u64 + 1)).to_bits();
+    }
+
+    let lending_market = LendingMarket::default();
+    refresh_obligation(
+        &mut obligation,
+        &lending_market,
+        SLOT,
+        reserves(&[0, 1, 2, 0, 1, 2]).into_iter(),
+    )
+    .unwrap();
+    let before = obligation;
+
+    // close the middle deposit and borrow
+    let middle_borrowed_amount = Fraction::from_bits(obligation.borrows[1].borrowed_amount_sf);
+    obligation.repay(middle_borrowed_amount, 1).unwrap();
+    obligation
+        .withdraw(obligation.deposits[1].deposited_amount, 1)
+        .unwrap();
+    obligation.compact_obligation();
+
+    assert_eq!(obligation.deposits[0], before.deposits[0]);
+    assert_eq!(obligation.deposits[1], before.deposits[2]);
+    assert!(obligation.deposits[2..]
+        .iter()
+        .all(|c| c.deposit_reserve == Pubkey::default()));
+    assert_eq!(
+        obligation.deposits_asset_tiers,
+        [
+            u8::from(AssetTier::Regular),
+            u8::from(AssetTier::Regular),
+            u8::MAX,
+            u8::MAX,
+            u8::MAX,
+            u8::MAX,
+            u8::MAX,
+            u8::MAX,
+        ]
+    );
+
+    assert_eq!(obligation.borrows[0], before.borrows[0]);
+    assert_eq!(obligation.borrows[1], before.borrows[2]);
+    assert!(obligation.borrows[2..]
+        .iter()
+        .all(|l| l.borrow_reserve == Pubkey::default()));
+    assert_eq!(
+        obligation.borrows_asset_tiers,
+        [
+            u8::from(AssetTier::Regular),
+            u8::from(AssetTier::Regular),
+            u8::MAX,
+            u8::MAX,
+            u8::MAX,
+        ]
+    );
+
+    // the remaining positions refresh to the same market values as before
+    refresh_obligation(
+        &mut obligation,
+        &lending_market,
+        SLOT,
+        reserves(&[0, 2, 0, 2]).into_iter(),
+    )
+    .unwrap();
+
+    assert_eq!(
+        obligation.deposits[0].market_value_sf,
+        before.deposits[0].market_value_sf
+    );
+    assert_eq!(
+        obligation.deposits[1].market_value_sf,
+        before.deposits[2].market_value_sf
+    );
+    assert_eq!(
+        obligation.borrows[1].market_value_sf,
+        before.borrows[2].market_value_sf
+    );
+    assert_eq!(
+        Fraction::from_bits(obligation.deposited_value_sf).to_num::<u64>(),
+        100_000 + 300_000 * 3
+    );
+    assert_eq!(
+        Fraction::from_bits(obligation.borrowed_assets_market_value_sf).to_num::<u64>(),
+        10_000 + 30_000 * 3
+    );
+}
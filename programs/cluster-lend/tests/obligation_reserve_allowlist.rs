@@ -0,0 +1,197 @@
+#[cfg(test)]
+mod helpers;
+
+use anchor_lang::prelude::Pubkey;
+use cluster_lend::{InitObligationArgs, Obligation};
+use solana_program_test::*;
+
+use helpers::*;
+use obligation::ObligationFixture;
+use solana_sdk::signature::Keypair;
+use test::{TestFixture, PYTH_USDC_FEED};
+
+#[tokio::test]
+async fn success_allowlisted_reserves() {
+    let user = Keypair::new();
+    let owner = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (market_f, reserve_f, obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+
+    // default obligations are unrestricted
+    let obligation: Obligation = test_f.load_and_deserialize(&obligation_f.key).await;
+    assert!(obligation.is_deposit_reserve_allowed(&reserve_f.key));
+    assert!(obligation.is_borrow_reserve_allowed(&reserve_f.key));
+
+    let mut allowed_deposit_reserves = [Pubkey::default(); 4];
+    allowed_deposit_reserves[0] = reserve_f.key;
+    let mut allowed_borrow_reserves = [Pubkey::default(); 2];
+    allowed_borrow_reserves[0] = reserve_f.key;
+
+    let obligation_f =
+        ObligationFixture::new(&test_f, &owner, market_f.key, InitObligationArgs::default()).await;
+    test_f
+        .send_transaction(
+            &[obligation_f
+                .set_reserve_allowlist_ix(allowed_deposit_reserves, allowed_borrow_reserves)],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+
+    let obligation: Obligation = test_f.load_and_deserialize(&obligation_f.key).await;
+    assert_eq!(
+        obligation.allowed_deposit_reserves,
+        allowed_deposit_reserves
+    );
+    assert_eq!(obligation.allowed_borrow_reserves, allowed_borrow_reserves);
+
+    let deposit_amount = 1_000_000;
+    let owner_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&owner, deposit_amount)
+        .await;
+
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_time(120);
+
+    test_f
+        .send_transaction(
+            &[
+                obligation_f.deposit_liquidity_collateral_ix(
+                    deposit_amount,
+                    &reserve_f,
+                    owner_liquidity_ata.key,
+                ),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key]),
+            ],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+
+    // the allowlists can't be lifted once the obligation holds a deposit
+    let r = test_f
+        .send_transaction(
+            &[obligation_f
+                .set_reserve_allowlist_ix([Pubkey::default(); 4], [Pubkey::default(); 2])],
+            &[&owner],
+        )
+        .await;
+    assert!(r.is_err());
+
+    test_f
+        .send_transaction(
+            &[
+                obligation_f.borrow_liquidity_ix(100_000, &reserve_f, owner_liquidity_ata.key),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+            ],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn failure_reserve_not_allowlisted() {
+    let user = Keypair::new();
+    let owner = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (market_f, reserve_f, _) = test_f.setup(&user, &usdc_mint_f.key).await;
+
+    let mut allowed_deposit_reserves = [Pubkey::default(); 4];
+    allowed_deposit_reserves[0] = reserve_f.key;
+    let mut allowed_borrow_reserves = [Pubkey::default(); 2];
+    allowed_borrow_reserves[0] = Pubkey::new_unique();
+
+    let obligation_f =
+        ObligationFixture::new(&test_f, &owner, market_f.key, InitObligationArgs::default()).await;
+    test_f
+        .send_transaction(
+            &[obligation_f
+                .set_reserve_allowlist_ix(allowed_deposit_reserves, allowed_borrow_reserves)],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+
+    let deposit_amount = 1_000_000;
+    let owner_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&owner, deposit_amount)
+        .await;
+
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_time(120);
+
+    test_f
+        .send_transaction(
+            &[
+                obligation_f.deposit_liquidity_collateral_ix(
+                    deposit_amount,
+                    &reserve_f,
+                    owner_liquidity_ata.key,
+                ),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key]),
+            ],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+
+    // the reserve is not on the borrow allowlist
+    let r = test_f
+        .send_transaction(
+            &[
+                obligation_f.borrow_liquidity_ix(100_000, &reserve_f, owner_liquidity_ata.key),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+            ],
+            &[&owner],
+        )
+        .await;
+    assert!(r.is_err());
+
+    // an obligation allowlisting another reserve cannot deposit here
+    let other_owner = Keypair::new();
+    let mut allowed_deposit_reserves = [Pubkey::default(); 4];
+    allowed_deposit_reserves[0] = Pubkey::new_unique();
+    let other_obligation_f = ObligationFixture::new(
+        &test_f,
+        &other_owner,
+        market_f.key,
+        InitObligationArgs::default(),
+    )
+    .await;
+    test_f
+        .send_transaction(
+            &[other_obligation_f
+                .set_reserve_allowlist_ix(allowed_deposit_reserves, [Pubkey::default(); 2])],
+            &[&other_owner],
+        )
+        .await
+        .unwrap();
+    let other_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&other_owner, deposit_amount)
+        .await;
+
+    let r = test_f
+        .send_transaction(
+            &[
+                other_obligation_f.deposit_liquidity_collateral_ix(
+                    deposit_amount,
+                    &reserve_f,
+                    other_liquidity_ata.key,
+                ),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                other_obligation_f.refresh_ix(vec![reserve_f.key]),
+            ],
+            &[&other_owner],
+        )
+        .await;
+    assert!(r.is_err());
+}
@@ -0,0 +1,63 @@
+use anchor_lang::prelude::*;
+use cluster_lend::{
+    constants::MAX_OBLIGATION_RESERVES, errors::LendingError, utils::Fraction, AssetTier,
+    DebtTranche, LendingMarket, Obligation,
+};
+
+const MAX_RESERVES: u8 = 2;
+
+fn deposit(obligation: &mut Obligation, reserve: Pubkey) -> Result<()> {
+    obligation
+        .find_or_add_collateral_to_deposits(reserve, AssetTier::Regular, MAX_RESERVES)
+        .map(|_| ())
+}
+
+fn borrow(obligation: &mut Obligation, reserve: Pubkey) -> Result<()> {
+    obligation
+        .find_or_add_liquidity_to_borrows(
+            reserve,
+            Fraction::ONE.into(),
+            AssetTier::Regular,
+            DebtTranche::Senior,
+            MAX_RESERVES,
+        )
+        .map(|_| ())
+}
+
+#[test]
+fn adding_reserve_beyond_market_limit_fails() {
+    let reserve_a = Pubkey::new_unique();
+    let reserve_b = Pubkey::new_unique();
+    let reserve_c = Pubkey::new_unique();
+    let mut obligation = Obligation::default();
+
+    deposit(&mut obligation, reserve_a).unwrap();
+    // borrowing from a reserve already deposited into is not a new reserve
+    borrow(&mut obligation, reserve_a).unwrap();
+    borrow(&mut obligation, reserve_b).unwrap();
+    deposit(&mut obligation, reserve_b).unwrap();
+
+    assert_eq!(
+        deposit(&mut obligation, reserve_c).unwrap_err(),
+        LendingError::ObligationReserveLimit.into()
+    );
+    assert_eq!(
+        borrow(&mut obligation, reserve_c).unwrap_err(),
+        LendingError::ObligationReserveLimit.into()
+    );
+    assert_eq!(obligation.deposits_count(), 2);
+    assert_eq!(obligation.borrows_count(), 2);
+
+    // the limit is off at 0
+    obligation
+        .find_or_add_collateral_to_deposits(reserve_c, AssetTier::Regular, 0)
+        .unwrap();
+}
+
+#[test]
+fn new_markets_default_to_max_obligation_reserves() {
+    assert_eq!(
+        LendingMarket::default().max_obligation_reserves as u64,
+        MAX_OBLIGATION_RESERVES
+    );
+}
@@ -0,0 +1,84 @@
+#[cfg(test)]
+mod helpers;
+
+use anchor_lang::prelude::*;
+use cluster_lend::{
+    errors::LendingError,
+    lending_market::lending_operations::{refresh_obligation, refresh_obligation_compact},
+    utils::{FatAccountLoader, Fraction},
+    LendingMarket, Obligation, ObligationCollateral, Reserve,
+};
+
+use helpers::*;
+use reserve::{refreshed_reserve, reserve_account_data};
+
+const SLOT: u64 = 100;
+const DEPOSIT_LIMIT: u64 = u64::MAX / 2;
+
+/// Eight reserves filled up to their deposit limit, with the obligation holding all of it
+fn refresh_full_portfolio(market_price: u64) -> [Result<Obligation>; 2] {
+    let program_id = cluster_lend::ID;
+    let keys: [Pubkey; 8] = std::array::from_fn(|_| Pubkey::new_unique());
+    let mut lamports = [0; 8];
+    let mut reserve = refreshed_reserve(market_price, DEPOSIT_LIMIT, SLOT);
+    reserve.config.deposit_limit = DEPOSIT_LIMIT;
+    let mut data: [Vec<u8>; 8] = std::array::from_fn(|_| reserve_account_data(&reserve));
+    let infos: Vec<AccountInfo> = keys
+        .iter()
+        .zip(lamports.iter_mut())
+        .zip(data.iter_mut())
+        .map(|((key, lamports), data)| {
+            AccountInfo::new(key, false, true, lamports, data, &program_id, false, 0)
+        })
+        .collect();
+    let reserves = || {
+        infos
+            .iter()
+            .map(|info| FatAccountLoader::<Reserve>::try_from(info).unwrap())
+    };
+
+    let mut obligation = Obligation::default();
+    for (deposit, key) in obligation.deposits.iter_mut().zip(keys) {
+        *deposit = ObligationCollateral::new(key);
+        deposit.deposited_amount = DEPOSIT_LIMIT;
+    }
+
+    let lending_market = LendingMarket::default();
+    let mut verbose = obligation;
+    let mut compact = obligation;
+    [
+        refresh_obligation(&mut verbose, &lending_market, SLOT, reserves()).map(|_| verbose),
+        refresh_obligation_compact(&mut compact, &lending_market, SLOT, reserves())
+            .map(|_| compact),
+    ]
+}
+
+#[test]
+fn refresh_obligation_aggregates_large_deposits() {
+    // 8 * 2^63 * 2 = 2^67, within the 68 integer bits of a `Fraction`
+    for obligation in refresh_full_portfolio(2) {
+        let obligation = obligation.unwrap();
+        let deposit_value = Fraction::from_num(u128::from(DEPOSIT_LIMIT) * 2);
+        assert!(obligation
+            .deposits
+            .iter()
+            .all(|deposit| Fraction::from_bits(deposit.market_value_sf) == deposit_value));
+        assert_eq!(
+            Fraction::from_bits(obligation.deposited_value_sf),
+            deposit_value * 8
+        );
+        // the borrow values are still clamped to the market globals
+        assert_eq!(
+            Fraction::from_bits(obligation.allowed_borrow_value_sf),
+            Fraction::from(LendingMarket::default().global_allowed_borrow_value)
+        );
+    }
+}
+
+#[test]
+fn refresh_obligation_fails_on_deposited_value_overflow() {
+    // each deposit is worth just under 2^66, the total crosses 2^68 on the fifth one
+    for obligation in refresh_full_portfolio(8) {
+        assert_eq!(obligation.unwrap_err(), LendingError::MathOverflow.into());
+    }
+}
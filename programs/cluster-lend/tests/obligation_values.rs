@@ -0,0 +1,39 @@
+use cluster_lend::{
+    lending_market::lending_operations::get_obligation_values,
+    utils::{Fraction, FractionExtra, FRACTION_ONE_SCALED},
+    Obligation,
+};
+
+#[test]
+fn get_obligation_values_scaled() {
+    let values = get_obligation_values(&Obligation::default());
+    assert_eq!(values.scale, FRACTION_ONE_SCALED);
+    assert_eq!(values.deposited_value_sf, 0);
+    assert_eq!(values.loan_to_value_sf, 0);
+    assert_eq!(values.unhealthy_loan_to_value_sf, 0);
+
+    let mut obligation = Obligation::default();
+    obligation.deposited_value_sf = Fraction::from_num(200).to_sf();
+    obligation.borrow_factor_adjusted_debt_value_sf = Fraction::from_num(50).to_sf();
+    obligation.borrowed_assets_market_value_sf = Fraction::from_num(40).to_sf();
+    obligation.allowed_borrow_value_sf = Fraction::from_num(150).to_sf();
+    obligation.unhealthy_borrow_value_sf = Fraction::from_num(160).to_sf();
+
+    let values = get_obligation_values(&obligation);
+    assert_eq!(values.deposited_value_sf, 200 * values.scale);
+    assert_eq!(
+        values.borrow_factor_adjusted_debt_value_sf,
+        50 * values.scale
+    );
+    assert_eq!(values.borrowed_assets_market_value_sf, 40 * values.scale);
+    assert_eq!(values.allowed_borrow_value_sf, 150 * values.scale);
+    assert_eq!(values.unhealthy_borrow_value_sf, 160 * values.scale);
+    assert_eq!(
+        Fraction::from_sf(values.loan_to_value_sf).to_decimal_string(4),
+        "0.2500"
+    );
+    assert_eq!(
+        Fraction::from_sf(values.unhealthy_loan_to_value_sf).to_decimal_string(4),
+        "0.8000"
+    );
+}
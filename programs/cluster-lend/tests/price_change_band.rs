@@ -0,0 +1,159 @@
+#[cfg(test)]
+mod helpers;
+
+use cluster_lend::{
+    utils::{Fraction, FractionExtra},
+    PriceStatusFlags, Reserve, UpdateConfigMode,
+};
+use solana_program_test::*;
+
+use helpers::*;
+use solana_sdk::{compute_budget::ComputeBudgetInstruction, signature::Keypair};
+use test::{TestFixture, PYTH_USDC_FEED, TEST_RESERVE_CONFIG};
+
+const USDC_NATIVE_ONE: i64 = 1_000_000;
+
+fn max_price_change_bps_value(bps: u64) -> [u8; 32] {
+    let mut value = [0; 32];
+    value[..8].copy_from_slice(&bps.to_le_bytes());
+    value
+}
+
+#[tokio::test]
+async fn success_small_price_move_within_band_is_accepted() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (_market_f, reserve_f, _obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+    let owner = test_f.payer_keypair();
+
+    test_f
+        .send_transaction(
+            &[reserve_f.update_reserve_mode_ix(
+                UpdateConfigMode::UpdateMaxPriceChangeBpsPerRefresh as u64,
+                max_price_change_bps_value(500),
+            )],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_time(120);
+
+    test_f
+        .send_transaction(&[reserve_f.refresh_ix(Some(PYTH_USDC_FEED))], &[])
+        .await
+        .unwrap();
+
+    let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+    assert_eq!(reserve.config.max_price_change_bps_per_refresh, 500);
+    assert_eq!(reserve.liquidity.get_market_price_f(), Fraction::ONE);
+
+    // a 3% move stays within the 5% band
+    test_f
+        .set_pyth_oracle_price(PYTH_USDC_FEED, USDC_NATIVE_ONE * 103 / 100)
+        .await;
+    test_f
+        .send_transaction(
+            &[
+                ComputeBudgetInstruction::set_compute_unit_limit(400_000),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+            ],
+            &[],
+        )
+        .await
+        .unwrap();
+
+    let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+    assert_eq!(
+        reserve.liquidity.get_market_price_f().to_bps::<u64>(),
+        Some(10_300)
+    );
+    assert_eq!(
+        reserve.last_update.get_price_status(),
+        PriceStatusFlags::ALL_CHECKS
+    );
+}
+
+#[tokio::test]
+async fn success_price_spike_is_rejected_while_saved_price_is_fresh() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (_market_f, reserve_f, _obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+    let owner = test_f.payer_keypair();
+
+    let mut config = TEST_RESERVE_CONFIG;
+    config.token_info.max_age_price_seconds = 60;
+    config.max_price_change_bps_per_refresh = 500;
+    test_f
+        .send_transaction(&[reserve_f.update_reserve_ix(config)], &[&owner])
+        .await
+        .unwrap();
+
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_time(120);
+
+    test_f
+        .send_transaction(&[reserve_f.refresh_ix(Some(PYTH_USDC_FEED))], &[])
+        .await
+        .unwrap();
+
+    // a doubling within the refresh interval keeps the saved price
+    test_f
+        .set_pyth_oracle_price(PYTH_USDC_FEED, 2 * USDC_NATIVE_ONE)
+        .await;
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 130).await;
+    test_f.set_time(130);
+
+    test_f
+        .send_transaction(
+            &[
+                ComputeBudgetInstruction::set_compute_unit_limit(400_000),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+            ],
+            &[],
+        )
+        .await
+        .unwrap();
+
+    let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+    assert_eq!(reserve.liquidity.get_market_price_f(), Fraction::ONE);
+    assert_eq!(reserve.liquidity.market_price_last_updated_ts, 120);
+    assert_eq!(
+        reserve.last_update.get_price_status(),
+        (PriceStatusFlags::ALL_CHECKS | PriceStatusFlags::PRICE_CHANGE_REJECTED)
+            - PriceStatusFlags::PRICE_AGE_CHECKED
+    );
+
+    // borrows and withdrawals can't go ahead on the kept price, liquidations still can
+    let price_status = reserve.last_update.get_price_status();
+    assert!(!price_status.contains(PriceStatusFlags::ALL_CHECKS));
+    assert!(price_status.contains(PriceStatusFlags::LIQUIDATION_CHECKS));
+
+    // once the saved price has aged out the new price is taken
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 200).await;
+    test_f.set_time(200);
+
+    test_f
+        .send_transaction(
+            &[
+                ComputeBudgetInstruction::set_compute_unit_limit(300_000),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+            ],
+            &[],
+        )
+        .await
+        .unwrap();
+
+    let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+    assert_eq!(reserve.liquidity.get_market_price_f(), Fraction::from(2u64));
+    assert_eq!(reserve.liquidity.market_price_last_updated_ts, 200);
+    assert_eq!(
+        reserve.last_update.get_price_status(),
+        PriceStatusFlags::ALL_CHECKS
+    );
+}
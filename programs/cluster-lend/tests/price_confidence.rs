@@ -0,0 +1,83 @@
+#[cfg(test)]
+mod helpers;
+use cluster_lend::{Reserve, UpdateConfigMode};
+use solana_program_test::*;
+
+use helpers::*;
+use solana_sdk::signature::Keypair;
+use test::{TestFixture, PYTH_USDC_FEED};
+
+fn max_confidence_pct_value(max_confidence_pct: u64) -> [u8; 32] {
+    let mut value = [0; 32];
+    value[..8].copy_from_slice(&max_confidence_pct.to_le_bytes());
+    value
+}
+
+#[tokio::test]
+async fn success_refresh_with_wide_confidence_under_loose_config() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (_market_f, reserve_f, _obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+    let mode = UpdateConfigMode::UpdateTokenInfoMaxConfidencePct as u64;
+
+    // 5% confidence on a price of 1
+    test_f
+        .set_pyth_oracle_confidence(PYTH_USDC_FEED, 50_000)
+        .await;
+
+    // the 2% default rejects the price
+    let r = test_f
+        .send_transaction(
+            &[reserve_f.refresh_ix(Some(PYTH_USDC_FEED))],
+            &[&test_f.payer_keypair()],
+        )
+        .await;
+    assert!(r.is_err());
+
+    // a strict 3% bound rejects the price
+    let r = test_f
+        .send_transaction(
+            &[
+                reserve_f.update_reserve_mode_ix(mode, max_confidence_pct_value(3)),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+            ],
+            &[&test_f.payer_keypair()],
+        )
+        .await;
+    assert!(r.is_err());
+
+    // a loose 10% bound accepts the price
+    let r = test_f
+        .send_transaction(
+            &[
+                reserve_f.update_reserve_mode_ix(mode, max_confidence_pct_value(10)),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+            ],
+            &[&test_f.payer_keypair()],
+        )
+        .await;
+    assert!(r.is_ok());
+
+    let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+    assert_eq!(reserve.config.token_info.max_confidence_pct, 10);
+}
+
+#[tokio::test]
+async fn failure_max_confidence_pct_out_of_range() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (_market_f, reserve_f, _obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+    let mode = UpdateConfigMode::UpdateTokenInfoMaxConfidencePct as u64;
+
+    let r = test_f
+        .send_transaction(
+            &[reserve_f.update_reserve_mode_ix(mode, max_confidence_pct_value(51))],
+            &[&test_f.payer_keypair()],
+        )
+        .await;
+    assert!(r.is_err());
+}
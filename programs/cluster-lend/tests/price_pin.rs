@@ -0,0 +1,105 @@
+#[cfg(test)]
+mod helpers;
+
+use cluster_lend::{
+    utils::{Fraction, FractionExtra},
+    PricePinMode, Reserve, UpdateConfigMode,
+};
+use solana_program_test::*;
+
+use helpers::*;
+use reserve::u64_value;
+use solana_sdk::signature::Keypair;
+use test::{TestFixture, PYTH_USDC_FEED, USDC_MINT_DECIMALS};
+
+#[tokio::test]
+async fn success_depegged_price_is_clamped_or_pinned() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (_market_f, reserve_f, _obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+
+    // USDC depegs to 0.90
+    let one = 10_i64.pow(USDC_MINT_DECIMALS.into());
+    test_f
+        .set_pyth_oracle_price(PYTH_USDC_FEED, one * 90 / 100)
+        .await;
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_time(120);
+
+    let owner = test_f.payer_keypair();
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.update_reserve_mode_ix(
+                    UpdateConfigMode::UpdateTokenInfoPricePinMode as u64,
+                    u64_value(PricePinMode::Clamped.into()),
+                ),
+                reserve_f.update_reserve_mode_ix(
+                    UpdateConfigMode::UpdateTokenInfoPricePinBandBps as u64,
+                    u64_value(100),
+                ),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+            ],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+
+    // clamped to 1% under the peg
+    let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+    assert_eq!(
+        reserve.liquidity.get_market_price_f().to_bps::<u64>(),
+        Some(9_900)
+    );
+
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.update_reserve_mode_ix(
+                    UpdateConfigMode::UpdateTokenInfoPricePinMode as u64,
+                    u64_value(PricePinMode::Pinned.into()),
+                ),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+            ],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+
+    let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+    assert_eq!(reserve.liquidity.get_market_price_f(), Fraction::ONE);
+
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.update_reserve_mode_ix(
+                    UpdateConfigMode::UpdateTokenInfoPricePinMode as u64,
+                    u64_value(PricePinMode::Disabled.into()),
+                ),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+            ],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+
+    let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+    assert_eq!(
+        reserve.liquidity.get_market_price_f().to_bps::<u64>(),
+        Some(9_000)
+    );
+
+    // unknown pin modes are rejected
+    let r = test_f
+        .send_transaction(
+            &[reserve_f.update_reserve_mode_ix(
+                UpdateConfigMode::UpdateTokenInfoPricePinMode as u64,
+                u64_value(3),
+            )],
+            &[&owner],
+        )
+        .await;
+    assert!(r.is_err());
+}
@@ -0,0 +1,80 @@
+#[cfg(test)]
+mod helpers;
+
+use cluster_lend::{errors::LendingError, PriceStatusFlags, Reserve, UpdateLendingMarketMode};
+use solana_program_test::*;
+
+use helpers::*;
+use solana_sdk::{compute_budget::ComputeBudgetInstruction, signature::Keypair};
+use test::{TestFixture, PYTH_USDC_FEED};
+
+fn price_refresh_trigger_value(pct: u8) -> [u8; 72] {
+    let mut value = [0; 72];
+    value[0] = pct;
+    value
+}
+
+#[tokio::test]
+async fn success_refresh_without_oracle_while_price_fresh() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (market_f, reserve_f, _obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_slot_and_time(1, 120);
+
+    // without a refresh trigger every refresh loads the oracle
+    let err = test_f
+        .send_transaction(&[reserve_f.refresh_ix(None)], &[])
+        .await
+        .unwrap_err();
+    assert_custom_error!(err, LendingError::InvalidPythPriceAccount);
+
+    test_f
+        .send_transaction(
+            &[
+                market_f.update_market_ix(
+                    UpdateLendingMarketMode::UpdatePriceRefreshTriggerToMaxAgePct as u64,
+                    price_refresh_trigger_value(50),
+                ),
+                reserve_f.force_refresh_ix(Some(PYTH_USDC_FEED)),
+            ],
+            &[&test_f.payer_keypair()],
+        )
+        .await
+        .unwrap();
+
+    let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+    assert_eq!(reserve.liquidity.market_price_last_updated_ts, 120);
+
+    // the saved price is well within half of the max age, only interest is accrued
+    // the compute limit keeps the signature apart from the rejected refresh above
+    test_f.set_slot_and_time(2, 200);
+    test_f
+        .send_transaction(
+            &[
+                ComputeBudgetInstruction::set_compute_unit_limit(400_000),
+                reserve_f.refresh_ix(None),
+            ],
+            &[],
+        )
+        .await
+        .unwrap();
+
+    let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+    assert_eq!(reserve.last_update.slots_elapsed(2).unwrap(), 0);
+    assert_eq!(reserve.liquidity.market_price_last_updated_ts, 120);
+    assert!(!reserve
+        .last_update
+        .is_stale(2, PriceStatusFlags::ALL_CHECKS)
+        .unwrap());
+
+    // a forced refresh always needs the oracle
+    let err = test_f
+        .send_transaction(&[reserve_f.force_refresh_ix(None)], &[])
+        .await
+        .unwrap_err();
+    assert_custom_error!(err, LendingError::InvalidPythPriceAccount);
+}
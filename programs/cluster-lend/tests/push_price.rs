@@ -0,0 +1,134 @@
+#[cfg(test)]
+mod helpers;
+
+use cluster_lend::{
+    errors::LendingError, utils::Fraction, Obligation, PriceStatusFlags, Reserve, UpdateConfigMode,
+};
+use solana_program::pubkey::Pubkey;
+use solana_program_test::*;
+
+use helpers::*;
+use solana_sdk::{signature::Keypair, signer::Signer};
+use test::{TestFixture, PYTH_USDC_FEED};
+
+#[tokio::test]
+async fn success_push_price_and_refresh_obligation() {
+    let user = Keypair::new();
+    let price_authority = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (_market_f, reserve_f, obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+
+    test_f
+        .send_transaction(
+            &[reserve_f.update_reserve_mode_ix(
+                UpdateConfigMode::UpdateTokenInfoPriceAuthority as u64,
+                price_authority.pubkey().to_bytes(),
+            )],
+            &[&test_f.payer_keypair()],
+        )
+        .await
+        .unwrap();
+
+    let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+    assert_eq!(
+        reserve.config.token_info.pyth_configuration.price,
+        Pubkey::default()
+    );
+    assert!(reserve.config.token_info.is_pushed_price());
+
+    test_f.set_time(120);
+    let price = Fraction::from(2u64);
+    test_f
+        .send_transaction(
+            &[reserve_f.push_price_ix(price_authority.pubkey(), price.to_bits())],
+            &[&price_authority],
+        )
+        .await
+        .unwrap();
+
+    let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+    assert_eq!(reserve.liquidity.get_market_price_f(), price);
+    assert_eq!(reserve.liquidity.market_price_last_updated_ts, 120);
+    assert_eq!(
+        reserve.last_update.get_price_status(),
+        PriceStatusFlags::ALL_CHECKS
+    );
+
+    // the pushed price is kept by refreshes, which take no oracle account
+    let deposit_amount = 1_000_000;
+    let user_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&user, deposit_amount)
+        .await;
+    test_f
+        .send_transaction(
+            &[
+                obligation_f.deposit_liquidity_collateral_ix(
+                    deposit_amount,
+                    &reserve_f,
+                    user_liquidity_ata.key,
+                ),
+                reserve_f.refresh_ix(None),
+                obligation_f.refresh_ix(vec![reserve_f.key]),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    let obligation: Obligation = test_f.load_and_deserialize(&obligation_f.key).await;
+    assert_eq!(
+        Fraction::from_bits(obligation.deposited_value_sf),
+        Fraction::from(2u64)
+    );
+}
+
+#[tokio::test]
+async fn failure_push_price() {
+    let user = Keypair::new();
+    let price_authority = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (_market_f, reserve_f, _obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+
+    // a reserve priced by pyth rejects pushed prices
+    let err = test_f
+        .send_transaction(
+            &[reserve_f.push_price_ix(price_authority.pubkey(), Fraction::ONE.to_bits())],
+            &[&price_authority],
+        )
+        .await
+        .unwrap_err();
+    assert_custom_error!(err, LendingError::PricePushNotEnabled);
+
+    test_f
+        .send_transaction(
+            &[reserve_f.update_reserve_mode_ix(
+                UpdateConfigMode::UpdateTokenInfoPriceAuthority as u64,
+                price_authority.pubkey().to_bytes(),
+            )],
+            &[&test_f.payer_keypair()],
+        )
+        .await
+        .unwrap();
+
+    // only the configured authority can push
+    let other_authority = Keypair::new();
+    let err = test_f
+        .send_transaction(
+            &[reserve_f.push_price_ix(other_authority.pubkey(), Fraction::ONE.to_bits())],
+            &[&other_authority],
+        )
+        .await
+        .unwrap_err();
+    assert_custom_error!(err, LendingError::InvalidPriceAuthority);
+
+    // and a pyth account is not expected anymore
+    let err = test_f
+        .send_transaction(&[reserve_f.refresh_ix(Some(PYTH_USDC_FEED))], &[])
+        .await
+        .unwrap_err();
+    assert_custom_error!(err, LendingError::InvalidPythPriceAccount);
+}
@@ -0,0 +1,81 @@
+#[cfg(test)]
+mod helpers;
+
+use cluster_lend::{errors::LendingError, Reserve, UpdateConfigMode};
+use solana_program_test::*;
+
+use helpers::*;
+use solana_sdk::signature::Keypair;
+use test::{TestFixture, PYTH_SOL_FEED, PYTH_USDC_FEED};
+
+#[tokio::test]
+async fn success_update_pyth_price_to_valid_feed() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (_market_f, reserve_f, _obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+    let owner = test_f.payer_keypair();
+
+    test_f
+        .send_transaction(
+            &[reserve_f.update_reserve_pyth_price_ix(PYTH_SOL_FEED)],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+
+    let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+    assert_eq!(
+        reserve.config.token_info.pyth_configuration.price,
+        PYTH_SOL_FEED
+    );
+}
+
+#[tokio::test]
+async fn failure_update_pyth_price_to_zero_price_feed() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (_market_f, reserve_f, _obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+    let owner = test_f.payer_keypair();
+
+    test_f.set_pyth_oracle_price(PYTH_SOL_FEED, 0).await;
+
+    let err = test_f
+        .send_transaction(
+            &[reserve_f.update_reserve_pyth_price_ix(PYTH_SOL_FEED)],
+            &[&owner],
+        )
+        .await
+        .unwrap_err();
+    assert_custom_error!(err, LendingError::PriceIsZero);
+
+    let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+    assert_eq!(
+        reserve.config.token_info.pyth_configuration.price,
+        PYTH_USDC_FEED
+    );
+}
+
+#[tokio::test]
+async fn failure_update_pyth_price_without_price_account() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (_market_f, reserve_f, _obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+
+    let err = test_f
+        .send_transaction(
+            &[reserve_f.update_reserve_mode_ix(
+                UpdateConfigMode::UpdatePythPrice as u64,
+                PYTH_SOL_FEED.to_bytes(),
+            )],
+            &[&test_f.payer_keypair()],
+        )
+        .await
+        .unwrap_err();
+    assert_custom_error!(err, LendingError::InvalidPythPriceAccount);
+}
@@ -0,0 +1,50 @@
+use cluster_lend::{utils::Fraction, Reserve, RoundingDirection};
+
+fn rebasing_reserve(deposit_amount: u64) -> Reserve {
+    let mut reserve = Reserve::default();
+    reserve.config.is_rebasing = true as u8;
+    reserve.deposit_liquidity(deposit_amount).unwrap();
+    reserve
+}
+
+fn collateral_value(reserve: &Reserve, collateral_amount: u64) -> u64 {
+    reserve
+        .collateral_exchange_rate()
+        .unwrap()
+        .collateral_to_liquidity(collateral_amount, RoundingDirection::Down)
+        .unwrap()
+}
+
+#[test]
+fn positive_rebase_accrues_to_exchange_rate() {
+    let mut reserve = rebasing_reserve(1_000_000);
+    assert_eq!(collateral_value(&reserve, 1_000_000), 1_000_000);
+
+    reserve.reconcile_rebasing_vault_balance(1_100_000).unwrap();
+    assert_eq!(reserve.liquidity.available_amount, 1_100_000);
+    assert_eq!(collateral_value(&reserve, 1_000_000), 1_100_000);
+    assert_eq!(reserve.liquidity.accumulated_bad_debt_sf, 0);
+}
+
+#[test]
+fn negative_rebase_is_recorded_as_bad_debt() {
+    let mut reserve = rebasing_reserve(1_000_000);
+
+    reserve.reconcile_rebasing_vault_balance(900_000).unwrap();
+    assert_eq!(reserve.liquidity.available_amount, 900_000);
+    assert_eq!(collateral_value(&reserve, 1_000_000), 900_000);
+    assert_eq!(
+        reserve.liquidity.accumulated_bad_debt(),
+        Fraction::from(100_000u64)
+    );
+}
+
+#[test]
+fn non_rebasing_reserve_ignores_vault_balance() {
+    let mut reserve = rebasing_reserve(1_000_000);
+    reserve.config.is_rebasing = false as u8;
+
+    reserve.reconcile_rebasing_vault_balance(1_100_000).unwrap();
+    assert_eq!(reserve.liquidity.available_amount, 1_000_000);
+    assert_eq!(collateral_value(&reserve, 1_000_000), 1_000_000);
+}
@@ -0,0 +1,87 @@
+#[cfg(test)]
+mod helpers;
+
+use anchor_lang::AnchorDeserialize;
+use anchor_spl::token::spl_token;
+use cluster_lend::{utils::pda, ReconcileReserveResult};
+use solana_program_test::*;
+
+use helpers::*;
+use solana_sdk::{signature::Keypair, signer::Signer};
+use test::{TestFixture, PYTH_USDC_FEED};
+
+#[tokio::test]
+async fn success_reconcile_reserve_reports_vault_drift() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (market_f, reserve_f, obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+    let reserve_pdas = pda::init_reserve_pdas(&market_f.key, &usdc_mint_f.key);
+
+    let deposit_amount = 1_000_000;
+    let donation_amount = 12_345;
+    let user_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&user, deposit_amount + donation_amount)
+        .await;
+
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_time(120);
+
+    test_f
+        .send_transaction(
+            &[
+                obligation_f.deposit_liquidity_collateral_ix(
+                    deposit_amount,
+                    &reserve_f,
+                    user_liquidity_ata.key,
+                ),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key]),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    let return_data = test_f
+        .send_transaction_with_return_data(&[reserve_f.reconcile_ix()], &[])
+        .await
+        .unwrap();
+    let result = ReconcileReserveResult::try_from_slice(&return_data).unwrap();
+    assert_eq!(result.vault_balance, deposit_amount);
+    assert_eq!(result.available_amount, deposit_amount);
+    assert_eq!(result.drift, 0);
+
+    // tokens sent straight to the vault are not tracked by the reserve
+    test_f
+        .send_transaction(
+            &[spl_token::instruction::transfer(
+                &spl_token::id(),
+                &user_liquidity_ata.key,
+                &reserve_pdas.liquidity_supply_vault,
+                &user.pubkey(),
+                &[],
+                donation_amount,
+            )
+            .unwrap()],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    let return_data = test_f
+        .send_transaction_with_return_data(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                reserve_f.reconcile_ix(),
+            ],
+            &[],
+        )
+        .await
+        .unwrap();
+    let result = ReconcileReserveResult::try_from_slice(&return_data).unwrap();
+    assert_eq!(result.vault_balance, deposit_amount + donation_amount);
+    assert_eq!(result.available_amount, deposit_amount);
+    assert_eq!(result.drift, donation_amount as i128);
+}
@@ -0,0 +1,105 @@
+#[cfg(test)]
+mod helpers;
+use std::rc::Rc;
+
+use cluster_lend::{errors::LendingError, utils::pda, Reserve};
+use solana_program_test::*;
+
+use helpers::*;
+use solana_sdk::{signature::Keypair, signer::Signer};
+use spl::TokenAccountFixture;
+use test::{TestFixture, PYTH_USDC_FEED};
+
+#[tokio::test]
+async fn success_redeem_collateral_for_exact_liquidity() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (market_f, reserve_f, _obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+    let reserve_pdas = pda::init_reserve_pdas(&market_f.key, &usdc_mint_f.key);
+    let owner = test_f.payer_keypair();
+
+    let deposit_amount = 1_000_000;
+    let donation_amount = 333_333;
+    let owner_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&owner, deposit_amount + donation_amount)
+        .await;
+    let owner_collateral_ata = TokenAccountFixture::new(
+        Rc::clone(&test_f.context),
+        &reserve_pdas.collateral_ctoken_mint,
+        &owner.pubkey(),
+    )
+    .await;
+
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_time(120);
+
+    // 1_000_000 collateral backed by 1_333_333 liquidity after the donation
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                reserve_f.deposit_liquidity_ix(
+                    deposit_amount,
+                    owner_liquidity_ata.key,
+                    owner_collateral_ata.key,
+                ),
+                reserve_f.donate_liquidity_ix(donation_amount, owner_liquidity_ata.key),
+            ],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+    assert_eq!(owner_liquidity_ata.balance().await, 0);
+    assert_eq!(owner_collateral_ata.balance().await, deposit_amount);
+
+    // 100_000 liquidity is worth 75_000.01875 collateral, rounded up in the reserve's favor
+    let liquidity_amount = 100_000;
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                reserve_f.redeem_collateral_exact_liquidity_ix(
+                    liquidity_amount,
+                    owner_collateral_ata.key,
+                    owner_liquidity_ata.key,
+                ),
+            ],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(owner_liquidity_ata.balance().await, liquidity_amount);
+    assert_eq!(
+        owner_collateral_ata.balance().await,
+        deposit_amount - 75_001
+    );
+    let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+    assert_eq!(
+        reserve.liquidity.available_amount,
+        deposit_amount + donation_amount - liquidity_amount
+    );
+    assert_eq!(
+        reserve.collateral.mint_total_supply,
+        deposit_amount - 75_001
+    );
+
+    // more than the reserve holds cannot be redeemed
+    let err = test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                reserve_f.redeem_collateral_exact_liquidity_ix(
+                    reserve.liquidity.available_amount + 1,
+                    owner_collateral_ata.key,
+                    owner_liquidity_ata.key,
+                ),
+            ],
+            &[&owner],
+        )
+        .await
+        .unwrap_err();
+    assert_custom_error!(err, LendingError::InsufficientLiquidity);
+}
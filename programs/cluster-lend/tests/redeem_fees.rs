@@ -0,0 +1,117 @@
+#[cfg(test)]
+mod helpers;
+use cluster_lend::{
+    utils::{pda, Fraction, FractionExtra},
+    Reserve, UpdateConfigMode,
+};
+use solana_program_test::*;
+
+use helpers::*;
+use solana_sdk::signature::Keypair;
+use spl::balance_of;
+use test::{TestFixture, PYTH_USDC_FEED};
+
+#[tokio::test]
+async fn success_redeem_fees_preserves_liquidity_buffer() {
+    let user = Keypair::new();
+
+    // setup market & reserve/obligation
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (market_f, reserve_f, obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+    let reserve_pdas = pda::init_reserve_pdas(&market_f.key, &usdc_mint_f.key);
+
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_time(120);
+
+    // all interest goes to the protocol
+    let mut value = [0; 32];
+    value[0] = 100;
+    test_f
+        .send_transaction(
+            &[reserve_f
+                .update_reserve_mode_ix(UpdateConfigMode::UpdateProtocolTakeRate as u64, value)],
+            &[&test_f.payer_keypair()],
+        )
+        .await
+        .unwrap();
+
+    // deposit and borrow up to 70% utilization
+    let deposit_amount = 1_000_000;
+    let borrow_amount = 700_000;
+    let user_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&user, deposit_amount)
+        .await;
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.deposit_liquidity_collateral_ix(
+                    deposit_amount,
+                    &reserve_f,
+                    user_liquidity_ata.key,
+                ),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key]),
+                obligation_f.borrow_liquidity_ix(borrow_amount, &reserve_f, user_liquidity_ata.key),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    // accrue interest into protocol fees
+    test_f.set_slot_and_time(1_000_000, 120);
+
+    // a 50% buffer is more than the reserve has available
+    let mut value = [0; 32];
+    value[..2].copy_from_slice(&5_000u16.to_le_bytes());
+    let r = test_f
+        .send_transaction(
+            &[
+                reserve_f.update_reserve_mode_ix(
+                    UpdateConfigMode::UpdateRedeemFeesLiquidityBufferBps as u64,
+                    value,
+                ),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                reserve_f.redeem_fees_ix(),
+            ],
+            &[&test_f.payer_keypair()],
+        )
+        .await;
+    assert!(r.is_err());
+    assert_eq!(
+        balance_of(test_f.context.clone(), reserve_pdas.fee_vault).await,
+        0
+    );
+
+    // a 10% buffer leaves room for the fees to be redeemed
+    let mut value = [0; 32];
+    value[..2].copy_from_slice(&1_000u16.to_le_bytes());
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.update_reserve_mode_ix(
+                    UpdateConfigMode::UpdateRedeemFeesLiquidityBufferBps as u64,
+                    value,
+                ),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                reserve_f.redeem_fees_ix(),
+            ],
+            &[&test_f.payer_keypair()],
+        )
+        .await
+        .unwrap();
+
+    let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+    let redeemed_fees = balance_of(test_f.context.clone(), reserve_pdas.fee_vault).await;
+    assert!(redeemed_fees > 0);
+    assert_eq!(
+        reserve.liquidity.available_amount,
+        deposit_amount - borrow_amount - redeemed_fees
+    );
+
+    let total_supply = reserve.liquidity.total_supply().unwrap();
+    let liquidity_buffer = (total_supply * Fraction::from_percent(10)).to_ceil::<u64>();
+    assert!(reserve.liquidity.available_amount >= liquidity_buffer);
+}
@@ -0,0 +1,119 @@
+#[cfg(test)]
+mod helpers;
+use std::rc::Rc;
+
+use anchor_spl::token::spl_token;
+use cluster_lend::{errors::LendingError, utils::pda};
+use solana_program_test::*;
+
+use helpers::*;
+use solana_sdk::{signature::Keypair, signer::Signer};
+use spl::TokenAccountFixture;
+use test::{TestFixture, PYTH_USDC_FEED};
+
+#[tokio::test]
+async fn success_redeem_collateral_to_recipient_with_transfer_authority() {
+    let user = Keypair::new();
+    let vault_authority = Keypair::new();
+    let recipient = Keypair::new();
+
+    // setup market & reserve
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (market_f, reserve_f, _obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+    let reserve_pdas = pda::init_reserve_pdas(&market_f.key, &usdc_mint_f.key);
+
+    let owner = test_f.payer_keypair();
+    let deposit_amount = 1_000_000;
+    let owner_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&owner, deposit_amount)
+        .await;
+    let owner_collateral_ata_f = TokenAccountFixture::new(
+        Rc::clone(&test_f.context),
+        &reserve_pdas.collateral_ctoken_mint,
+        &owner.pubkey(),
+    )
+    .await;
+    let vault_collateral_ata_f = TokenAccountFixture::new(
+        Rc::clone(&test_f.context),
+        &reserve_pdas.collateral_ctoken_mint,
+        &vault_authority.pubkey(),
+    )
+    .await;
+    let recipient_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&recipient, 0)
+        .await;
+
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_slot_and_time(10, 120);
+
+    // the collateral ends up held by the vault authority
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                reserve_f.deposit_liquidity_ix(
+                    deposit_amount,
+                    owner_liquidity_ata.key,
+                    owner_collateral_ata_f.key,
+                ),
+                spl_token::instruction::transfer(
+                    &spl_token::id(),
+                    &owner_collateral_ata_f.key,
+                    &vault_collateral_ata_f.key,
+                    &owner.pubkey(),
+                    &[],
+                    deposit_amount,
+                )
+                .unwrap(),
+            ],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+
+    let redeem_amount = 400_000;
+
+    // the reserve vault is still refused as the destination
+    let err = test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                reserve_f.redeem_collateral_with_authority_ix(
+                    owner.pubkey(),
+                    vault_authority.pubkey(),
+                    redeem_amount,
+                    vault_collateral_ata_f.key,
+                    reserve_pdas.liquidity_supply_vault,
+                ),
+            ],
+            &[&owner, &vault_authority],
+        )
+        .await
+        .unwrap_err();
+    assert_custom_error!(err, LendingError::InvalidAccountInput);
+
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                reserve_f.redeem_collateral_with_authority_ix(
+                    owner.pubkey(),
+                    vault_authority.pubkey(),
+                    redeem_amount,
+                    vault_collateral_ata_f.key,
+                    recipient_liquidity_ata.key,
+                ),
+            ],
+            &[&owner, &vault_authority],
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(
+        vault_collateral_ata_f.balance().await,
+        deposit_amount - redeem_amount
+    );
+    assert_eq!(recipient_liquidity_ata.balance().await, redeem_amount);
+    assert_eq!(owner_collateral_ata_f.balance().await, 0);
+}
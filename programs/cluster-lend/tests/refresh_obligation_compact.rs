@@ -0,0 +1,95 @@
+#[cfg(test)]
+mod helpers;
+
+use anchor_lang::prelude::*;
+use cluster_lend::{
+    lending_market::lending_operations::{refresh_obligation, refresh_obligation_compact},
+    utils::{BigFraction, FatAccountLoader, Fraction, FractionExtra},
+    LendingMarket, Obligation, ObligationCollateral, ObligationLiquidity, Reserve,
+};
+
+use helpers::*;
+use reserve::{refreshed_reserve, reserve_account_data};
+
+const SLOT: u64 = 100;
+
+#[test]
+fn compact_refresh_matches_verbose_refresh() {
+    let program_id = cluster_lend::ID;
+    let (key_a, key_b) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (mut lamports_a, mut lamports_b) = (0, 0);
+    let [mut data_a, mut data_b] = [(1, Fraction::from_percent(110)), (3, Fraction::ONE)].map(
+        |(market_price, cumulative_borrow_rate)| {
+            let mut reserve = refreshed_reserve(market_price, 2_000_000, SLOT);
+            reserve.config.borrow_factor_pct = 120;
+            reserve.liquidity.cumulative_borrow_rate_bsf =
+                BigFraction::from(cumulative_borrow_rate).into();
+            reserve_account_data(&reserve)
+        },
+    );
+    let info_a = AccountInfo::new(
+        &key_a,
+        false,
+        true,
+        &mut lamports_a,
+        &mut data_a,
+        &program_id,
+        false,
+        0,
+    );
+    let info_b = AccountInfo::new(
+        &key_b,
+        false,
+        true,
+        &mut lamports_b,
+        &mut data_b,
+        &program_id,
+        false,
+        0,
+    );
+
+    // deposits in both reserves, with an empty slot in between, and interest due on the borrow
+    let mut obligation = Obligation::default();
+    let mut collateral_a = ObligationCollateral::new(key_a);
+    collateral_a.deposited_amount = 1_000_000;
+    obligation.deposits[0] = collateral_a;
+    let mut collateral_b = ObligationCollateral::new(key_b);
+    collateral_b.deposited_amount = 500_000;
+    obligation.deposits[2] = collateral_b;
+    let mut liquidity = ObligationLiquidity::new(key_a, Fraction::ONE.into());
+    liquidity.borrowed_amount_sf = Fraction::from(300_000u64).to_bits();
+    obligation.borrows[1] = liquidity;
+
+    let lending_market = LendingMarket::default();
+    let reserves = || {
+        [&info_a, &info_b, &info_a]
+            .into_iter()
+            .map(|info| FatAccountLoader::<Reserve>::try_from(info).unwrap())
+    };
+
+    let mut verbose = obligation;
+    refresh_obligation(&mut verbose, &lending_market, SLOT, reserves()).unwrap();
+    let mut compact = obligation;
+    refresh_obligation_compact(&mut compact, &lending_market, SLOT, reserves()).unwrap();
+
+    assert_eq!(
+        Fraction::from_bits(verbose.deposited_value_sf).to_num::<u64>(),
+        2_500_000
+    );
+    assert_eq!(compact.deposited_value_sf, verbose.deposited_value_sf);
+    assert_eq!(
+        compact.borrow_factor_adjusted_debt_value_sf,
+        verbose.borrow_factor_adjusted_debt_value_sf
+    );
+    assert_eq!(compact.last_update, verbose.last_update);
+    assert_eq!(compact, verbose);
+
+    // a missing reserve is rejected the same way
+    let err =
+        refresh_obligation_compact(&mut obligation, &lending_market, SLOT, reserves().take(2))
+            .unwrap_err();
+    assert_eq!(
+        err,
+        cluster_lend::errors::LendingError::InvalidAccountInput.into()
+    );
+}
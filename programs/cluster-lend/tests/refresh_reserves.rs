@@ -0,0 +1,73 @@
+#[cfg(test)]
+mod helpers;
+use std::rc::Rc;
+
+use cluster_lend::Reserve;
+use solana_program_test::*;
+
+use helpers::*;
+use solana_sdk::signature::Keypair;
+use spl::MintFixture;
+use test::{TestFixture, PYTH_SOL_FEED, PYTH_USDC_FEED, USDC_MINT_DECIMALS};
+
+#[tokio::test]
+async fn success_refresh_reserves() {
+    let user = Keypair::new();
+
+    // setup market with three reserves
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (market_f, usdc_reserve_f, _obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+    let sol_reserve_f = test_f.add_reserve(&market_f, &test_f.sol_mint.key).await;
+    let other_mint_f =
+        MintFixture::new(Rc::clone(&test_f.context), None, Some(USDC_MINT_DECIMALS)).await;
+    let other_reserve_f = test_f.add_reserve(&market_f, &other_mint_f.key).await;
+    let reserves = [&usdc_reserve_f, &sol_reserve_f, &other_reserve_f];
+
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_time(120);
+
+    // a price account that does not match its reserve config is rejected
+    let r = test_f
+        .send_transaction(
+            &[market_f.refresh_reserves_ix(vec![
+                (usdc_reserve_f.key, Some(PYTH_USDC_FEED)),
+                (sol_reserve_f.key, Some(PYTH_SOL_FEED)),
+                (other_reserve_f.key, Some(PYTH_USDC_FEED)),
+            ])],
+            &[],
+        )
+        .await;
+    assert!(r.is_err());
+
+    let batched_compute_units = test_f
+        .send_transaction_with_compute_units(
+            &[market_f.refresh_reserves_ix(
+                reserves
+                    .iter()
+                    .map(|reserve_f| (reserve_f.key, Some(PYTH_USDC_FEED)))
+                    .collect(),
+            )],
+            &[],
+        )
+        .await
+        .unwrap();
+
+    for reserve_f in reserves {
+        let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+        assert_eq!(reserve.liquidity.market_price_last_updated_ts, 120);
+    }
+
+    let separate_compute_units = test_f
+        .send_transaction_with_compute_units(
+            &reserves
+                .iter()
+                .map(|reserve_f| reserve_f.refresh_ix(Some(PYTH_USDC_FEED)))
+                .collect::<Vec<_>>(),
+            &[],
+        )
+        .await
+        .unwrap();
+
+    assert!(batched_compute_units <= separate_compute_units);
+}
@@ -0,0 +1,172 @@
+#[cfg(test)]
+mod helpers;
+use std::rc::Rc;
+
+use cluster_lend::{errors::LendingError, utils::pda, Obligation};
+use solana_program_test::*;
+
+use helpers::*;
+use reserve::ReserveFixture;
+use solana_sdk::{signature::Keypair, signer::Signer};
+use spl::TokenAccountFixture;
+use test::{TestFixture, PYTH_SOL_FEED, PYTH_USDC_FEED, TEST_RESERVE_CONFIG};
+
+#[tokio::test]
+async fn success_reorder_deposits_then_withdraw_ltv_zero_first() {
+    let user = Keypair::new();
+
+    // setup market & regular reserve/obligation
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let sol_mint_f = test_f.sol_mint.clone();
+    let (market_f, usdc_reserve_f, obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+    let usdc_reserve_pdas = pda::init_reserve_pdas(&market_f.key, &usdc_mint_f.key);
+    let sol_reserve_pdas = pda::init_reserve_pdas(&market_f.key, &sol_mint_f.key);
+
+    // setup a second reserve with LTV 0
+    let payer = test_f.payer_keypair();
+    let sol_reserve_key = Keypair::new();
+    let sol_reserve_f = ReserveFixture {
+        key: sol_reserve_key.pubkey(),
+        owner: payer.pubkey(),
+        payer: payer.pubkey(),
+        lending_market: market_f.key,
+        liquidity_mint: sol_mint_f.key,
+    };
+    let mut ltv_zero_config = TEST_RESERVE_CONFIG;
+    ltv_zero_config.loan_to_value_pct = 0;
+    ltv_zero_config.token_info.pyth_configuration.price = PYTH_SOL_FEED;
+    test_f
+        .send_transaction(
+            &[
+                sol_reserve_f.initialize_reserve_ix(),
+                sol_reserve_f.update_reserve_ix(ltv_zero_config),
+            ],
+            &[&payer, &sol_reserve_key],
+        )
+        .await
+        .unwrap();
+
+    // the regular collateral takes the first deposit slot
+    let deposit_amount = 1_000_000;
+    let user_usdc_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&user, deposit_amount)
+        .await;
+    let user_sol_ata = sol_mint_f
+        .create_token_account_and_mint_to(&user, deposit_amount)
+        .await;
+
+    test_f
+        .send_transaction(
+            &[
+                usdc_reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                sol_reserve_f.refresh_ix(Some(PYTH_SOL_FEED)),
+                obligation_f.deposit_liquidity_collateral_ix(
+                    deposit_amount,
+                    &usdc_reserve_f,
+                    user_usdc_ata.key,
+                ),
+                obligation_f.deposit_liquidity_collateral_ix(
+                    deposit_amount,
+                    &sol_reserve_f,
+                    user_sol_ata.key,
+                ),
+                usdc_reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                sol_reserve_f.refresh_ix(Some(PYTH_SOL_FEED)),
+                obligation_f.refresh_ix(vec![usdc_reserve_f.key, sol_reserve_f.key]),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    let before: Obligation = test_f.load_and_deserialize(&obligation_f.key).await;
+    assert_eq!(before.deposits[0].deposit_reserve, usdc_reserve_f.key);
+    assert_eq!(before.deposits[1].deposit_reserve, sol_reserve_f.key);
+
+    // move the LTV 0 collateral to the front
+    test_f
+        .send_transaction(
+            &[obligation_f.reorder_deposits_ix([1, 0, 2, 3, 4, 5, 6, 7])],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    let obligation: Obligation = test_f.load_and_deserialize(&obligation_f.key).await;
+    assert_eq!(obligation.deposits[0], before.deposits[1]);
+    assert_eq!(obligation.deposits[1], before.deposits[0]);
+    assert_eq!(
+        obligation.deposits_asset_tiers[..2],
+        [
+            before.deposits_asset_tiers[1],
+            before.deposits_asset_tiers[0]
+        ]
+    );
+
+    let user_usdc_collateral_f = TokenAccountFixture::new(
+        Rc::clone(&test_f.context),
+        &usdc_reserve_pdas.collateral_ctoken_mint,
+        &user.pubkey(),
+    )
+    .await;
+    let user_sol_collateral_f = TokenAccountFixture::new(
+        Rc::clone(&test_f.context),
+        &sol_reserve_pdas.collateral_ctoken_mint,
+        &user.pubkey(),
+    )
+    .await;
+
+    // refreshes now list the reserves in the new order
+    test_f
+        .send_transaction(
+            &[
+                usdc_reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                sol_reserve_f.refresh_ix(Some(PYTH_SOL_FEED)),
+                obligation_f.refresh_ix(vec![sol_reserve_f.key, usdc_reserve_f.key]),
+                obligation_f.withdraw_collateral_ix(
+                    u64::MAX,
+                    &sol_reserve_f,
+                    user_sol_collateral_f.key,
+                ),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+    assert_eq!(user_sol_collateral_f.balance().await, deposit_amount);
+
+    test_f
+        .send_transaction(
+            &[
+                usdc_reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![usdc_reserve_f.key]),
+                obligation_f.withdraw_collateral_ix(
+                    1_000,
+                    &usdc_reserve_f,
+                    user_usdc_collateral_f.key,
+                ),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+    assert_eq!(user_usdc_collateral_f.balance().await, 1_000);
+}
+
+#[tokio::test]
+async fn failure_reorder_deposits_not_a_permutation() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (_market_f, _reserve_f, obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+
+    for order in [[0, 0, 2, 3, 4, 5, 6, 7], [8, 1, 2, 3, 4, 5, 6, 7]] {
+        let err = test_f
+            .send_transaction(&[obligation_f.reorder_deposits_ix(order)], &[&user])
+            .await
+            .unwrap_err();
+        assert_custom_error!(err, LendingError::InvalidDepositsOrder);
+    }
+}
@@ -0,0 +1,177 @@
+#[cfg(test)]
+mod helpers;
+use std::rc::Rc;
+
+use cluster_lend::{
+    utils::{pda, Fraction, FractionExtra},
+    Obligation, ObligationCollateral,
+};
+use solana_program_test::*;
+
+use helpers::*;
+use solana_sdk::signature::Keypair;
+use test::{TestFixture, PYTH_USDC_FEED};
+
+#[tokio::test]
+async fn success_repay_with_collateral() {
+    let user = Keypair::new();
+
+    // setup market & reserve/obligation
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (market_f, reserve_f, obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+    let reserve_pdas = pda::init_reserve_pdas(&market_f.key, &usdc_mint_f.key);
+
+    let deposit_amount = 1_000_000;
+    let user_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&user, deposit_amount)
+        .await;
+
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_time(120);
+
+    test_f
+        .send_transaction(
+            &[
+                obligation_f.deposit_liquidity_collateral_ix(
+                    deposit_amount,
+                    &reserve_f,
+                    user_liquidity_ata.key,
+                ),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key]),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    // borrow against the deposit of the same reserve
+    let borrow_amount = 700_000;
+    test_f
+        .send_transaction(
+            &[
+                obligation_f.borrow_liquidity_ix(borrow_amount, &reserve_f, user_liquidity_ata.key),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+    assert_eq!(user_liquidity_ata.balance().await, borrow_amount);
+
+    // deleverage by repaying part of the debt with the deposited collateral
+    let repay_amount = 300_000;
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+                obligation_f.repay_with_collateral_ix(repay_amount, &reserve_f),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    let obligation: Obligation = test_f.load_and_deserialize(&obligation_f.key).await;
+    assert_eq!(
+        obligation.deposits[0].deposited_amount,
+        deposit_amount - repay_amount
+    );
+    let borrowed_amount: u64 =
+        Fraction::from_bits(obligation.borrows[0].borrowed_amount_sf).to_round();
+    assert_eq!(borrowed_amount, borrow_amount - repay_amount);
+
+    // no tokens leave the reserve or reach the user, only the collateral is burnt
+    assert_eq!(user_liquidity_ata.balance().await, borrow_amount);
+    assert_eq!(
+        spl::balance_of(
+            Rc::clone(&test_f.context),
+            reserve_pdas.collateral_supply_vault
+        )
+        .await,
+        deposit_amount - repay_amount
+    );
+    assert_eq!(
+        spl::balance_of(
+            Rc::clone(&test_f.context),
+            reserve_pdas.liquidity_supply_vault
+        )
+        .await,
+        deposit_amount - borrow_amount
+    );
+}
+
+#[tokio::test]
+async fn success_repay_with_collateral_behind_a_deposit_gap() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (_market_f, reserve_f, obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+
+    let deposit_amount = 1_000_000;
+    let user_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&user, deposit_amount)
+        .await;
+
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_time(120);
+
+    let borrow_amount = 700_000;
+    test_f
+        .send_transaction(
+            &[
+                obligation_f.deposit_liquidity_collateral_ix(
+                    deposit_amount,
+                    &reserve_f,
+                    user_liquidity_ata.key,
+                ),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key]),
+                obligation_f.borrow_liquidity_ix(borrow_amount, &reserve_f, user_liquidity_ata.key),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    // withdrawals used to leave empty slots ahead of the remaining deposits
+    test_f
+        .update_obligation_account(obligation_f.key, |obligation| {
+            obligation.deposits[1] = obligation.deposits[0];
+            obligation.deposits[0] = ObligationCollateral::default();
+            obligation.deposits_asset_tiers.swap(0, 1);
+            obligation.deposits_reward_tracked.swap(0, 1);
+        })
+        .await;
+
+    let repay_amount = 300_000;
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+                obligation_f.repay_with_collateral_ix(repay_amount, &reserve_f),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    // the repay compacts the deposits, the collateral is still taken from this reserve
+    let obligation: Obligation = test_f.load_and_deserialize(&obligation_f.key).await;
+    assert_eq!(obligation.deposits[0].deposit_reserve, reserve_f.key);
+    assert_eq!(
+        obligation.deposits[0].deposited_amount,
+        deposit_amount - repay_amount
+    );
+    assert!(!obligation.deposits[1].is_active());
+    let borrowed_amount: u64 =
+        Fraction::from_bits(obligation.borrows[0].borrowed_amount_sf).to_round();
+    assert_eq!(borrowed_amount, borrow_amount - repay_amount);
+}
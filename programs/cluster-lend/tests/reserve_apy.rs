@@ -0,0 +1,82 @@
+#[cfg(test)]
+mod helpers;
+
+use cluster_lend::{
+    constants::SLOTS_PER_YEAR,
+    lending_market::lending_operations::get_reserve_apy,
+    utils::{Fraction, FractionExtra},
+};
+
+use helpers::*;
+use reserve::reserve_with_utilization;
+use test::TEST_RESERVE_CONFIG;
+
+// (1 + rate / slots)^slots - 1, computed through ln_1p/exp_m1 to keep f64 precision
+fn expected_apy(rate: Fraction) -> f64 {
+    let slots_per_year = SLOTS_PER_YEAR as f64;
+    ((rate.to_num::<f64>() / slots_per_year).ln_1p() * slots_per_year).exp_m1()
+}
+
+fn assert_apy_eq(apy: Fraction, expected: f64) {
+    let apy = apy.to_num::<f64>();
+    assert!(
+        (apy - expected).abs() <= expected * 1e-5,
+        "apy {apy} expected {expected}"
+    );
+}
+
+#[test]
+fn apy_at_zero_utilization() {
+    let reserve = reserve_with_utilization(TEST_RESERVE_CONFIG, 1_000_000, 0);
+
+    // the curve starts at a 0.01% borrow rate, nothing is paid to suppliers
+    assert_eq!(
+        reserve.current_borrow_rate().unwrap(),
+        Fraction::from_bps(1)
+    );
+    assert_apy_eq(
+        reserve.current_borrow_apy().unwrap(),
+        expected_apy(Fraction::from_bps(1)),
+    );
+    assert_eq!(reserve.current_supply_apy().unwrap(), Fraction::ZERO);
+}
+
+#[test]
+fn apy_at_half_utilization() {
+    let mut reserve = reserve_with_utilization(TEST_RESERVE_CONFIG, 1_000_000, 500_000);
+    let borrow_rate = reserve.current_borrow_rate().unwrap();
+    assert_eq!(borrow_rate.to_bps::<u64>(), Some(49_545));
+
+    let borrow_apy = reserve.current_borrow_apy().unwrap();
+    assert_apy_eq(borrow_apy, expected_apy(borrow_rate));
+    // compounding makes the APY larger than the rate
+    assert!(borrow_apy > borrow_rate);
+
+    // suppliers earn half of the borrow rate
+    assert_apy_eq(
+        reserve.current_supply_apy().unwrap(),
+        expected_apy(borrow_rate / 2),
+    );
+
+    // and less once the protocol takes its share
+    reserve.config.protocol_take_rate_pct = 20;
+    assert_apy_eq(
+        reserve.current_supply_apy().unwrap(),
+        expected_apy(borrow_rate * Fraction::from_percent(40)),
+    );
+}
+
+#[test]
+fn apy_at_full_utilization() {
+    let reserve = reserve_with_utilization(TEST_RESERVE_CONFIG, 1_000_000, 1_000_000);
+    let borrow_rate = reserve.current_borrow_rate().unwrap();
+    assert_eq!(borrow_rate, Fraction::from_num(10));
+
+    // at full utilization suppliers earn the whole borrow rate
+    let result = get_reserve_apy(&reserve).unwrap();
+    assert_apy_eq(
+        Fraction::from_sf(result.borrow_apy_sf),
+        expected_apy(borrow_rate),
+    );
+    assert_eq!(result.supply_apy_sf, result.borrow_apy_sf);
+}
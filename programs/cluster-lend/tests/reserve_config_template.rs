@@ -0,0 +1,125 @@
+#[cfg(test)]
+mod helpers;
+
+use cluster_lend::{
+    utils::pda, Reserve, ReserveConfigTemplate, ReserveConfigTemplateKind, ReserveStatus,
+};
+use lending_market::LendingMarketFixture;
+
+use reserve::ReserveFixture;
+use solana_program_test::*;
+
+use helpers::*;
+use solana_sdk::{signature::Keypair, signer::Signer};
+use test::{TestFixture, PYTH_USDC_FEED, USDC_QUOTE_CURRENCY};
+
+#[tokio::test]
+async fn success_init_reserve_from_template() {
+    let test_f = TestFixture::new().await;
+
+    let payer = test_f.payer_keypair();
+
+    let lending_market_key = Keypair::new();
+    let lending_market_f = LendingMarketFixture {
+        key: lending_market_key.pubkey(),
+        owner: payer.pubkey(),
+    };
+
+    let reserve_key = Keypair::new();
+    let reserve_f = ReserveFixture {
+        key: reserve_key.pubkey(),
+        owner: payer.pubkey(),
+        payer: payer.pubkey(),
+        lending_market: lending_market_f.key,
+        liquidity_mint: test_f.usdc_mint.key,
+    };
+
+    let kind = ReserveConfigTemplateKind::Stablecoin;
+    let template_key = pda::reserve_config_template(&lending_market_f.key, kind.into());
+    test_f
+        .send_transaction(
+            &[
+                lending_market_f.init_market_ix(USDC_QUOTE_CURRENCY),
+                lending_market_f.init_reserve_config_template_ix(kind.into()),
+                reserve_f.initialize_reserve_with_template_ix(Some(template_key)),
+            ],
+            &[&payer, &lending_market_key, &reserve_key],
+        )
+        .await
+        .unwrap();
+
+    let template: ReserveConfigTemplate = test_f.load_and_deserialize(&template_key).await;
+    assert_eq!(template.lending_market, lending_market_f.key);
+    assert_eq!(template.kind, u8::from(kind));
+    assert_eq!(template.config, kind.default_config());
+
+    // the reserve gets the template defaults but stays hidden
+    let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+    let mut expected_config = kind.default_config();
+    expected_config.status = ReserveStatus::Hidden.into();
+    assert_eq!(reserve.config, expected_config);
+
+    // only the oracle is left to configure
+    let r = test_f
+        .send_transaction(
+            &[reserve_f.update_reserve_pyth_price_ix(PYTH_USDC_FEED)],
+            &[&payer],
+        )
+        .await;
+    assert!(r.is_ok());
+}
+
+#[tokio::test]
+async fn success_apply_updated_template_to_reserve() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (market_f, reserve_f, _obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+    let reserve_before: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+
+    let kind = ReserveConfigTemplateKind::Longtail;
+    let mut template_config = kind.default_config();
+    template_config.loan_to_value_pct = 30;
+    let template_key = pda::reserve_config_template(&market_f.key, kind.into());
+
+    let payer = test_f.payer_keypair();
+    test_f
+        .send_transaction(
+            &[
+                market_f.init_reserve_config_template_ix(kind.into()),
+                market_f.update_reserve_config_template_ix(kind.into(), template_config),
+                reserve_f.apply_reserve_config_template_ix(template_key),
+            ],
+            &[&payer],
+        )
+        .await
+        .unwrap();
+
+    let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+    assert_eq!(reserve.config.loan_to_value_pct, 30);
+    assert_eq!(
+        reserve.config.liquidation_threshold_pct,
+        template_config.liquidation_threshold_pct
+    );
+    assert_eq!(
+        reserve.config.borrow_rate_curve,
+        template_config.borrow_rate_curve
+    );
+    assert_eq!(
+        reserve.config.borrow_factor_pct,
+        template_config.borrow_factor_pct
+    );
+
+    // asset specific settings are kept
+    assert_eq!(reserve.config.status, reserve_before.config.status);
+    assert_eq!(reserve.config.token_info, reserve_before.config.token_info);
+    assert_eq!(
+        reserve.config.deposit_limit,
+        reserve_before.config.deposit_limit
+    );
+    assert_eq!(
+        reserve.config.borrow_limit,
+        reserve_before.config.borrow_limit
+    );
+}
@@ -0,0 +1,114 @@
+#[cfg(test)]
+mod helpers;
+
+use anchor_lang::AnchorDeserialize;
+use cluster_lend::{
+    utils::Fraction, BorrowObligationLiquidityResult, RepayObligationLiquidityResult,
+};
+use solana_program::pubkey::Pubkey;
+use solana_program_test::*;
+
+use cpi_caller::{cpi_caller_entry, via_cpi_caller};
+use helpers::*;
+use solana_sdk::signature::Keypair;
+use test::{TestFixture, PYTH_USDC_FEED};
+
+#[tokio::test]
+async fn success_borrow_repay_return_data_via_cpi() {
+    let user = Keypair::new();
+    let cpi_caller_id = Pubkey::new_unique();
+
+    let test_f = TestFixture::new_with_programs(|program| {
+        program.add_program("cpi_caller", cpi_caller_id, processor!(cpi_caller_entry));
+    })
+    .await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (_, reserve_f, obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+
+    let deposit_amount = 1_000_000;
+    let user_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&user, deposit_amount)
+        .await;
+
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_time(120);
+
+    test_f
+        .send_transaction(
+            &[
+                obligation_f.deposit_liquidity_collateral_ix(
+                    deposit_amount,
+                    &reserve_f,
+                    user_liquidity_ata.key,
+                ),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key]),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    // the transaction return data is the last instruction's, the CPI goes last
+    let borrow_amount = 300_000;
+    let return_data = test_f
+        .send_transaction_with_return_data(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key]),
+                via_cpi_caller(
+                    cpi_caller_id,
+                    obligation_f.borrow_liquidity_ix(
+                        borrow_amount,
+                        &reserve_f,
+                        user_liquidity_ata.key,
+                    ),
+                ),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    // u128 borrow_amount_sf, u64 receive_amount, u64 borrow_fee
+    assert_eq!(return_data.len(), 32);
+    let result = BorrowObligationLiquidityResult::try_from_slice(&return_data).unwrap();
+    assert_eq!(
+        result,
+        BorrowObligationLiquidityResult {
+            borrow_amount_sf: Fraction::from(borrow_amount).to_bits(),
+            receive_amount: borrow_amount,
+            borrow_fee: 0,
+        }
+    );
+    assert_eq!(user_liquidity_ata.balance().await, borrow_amount);
+
+    let repay_amount = 100_000;
+    let return_data = test_f
+        .send_transaction_with_return_data(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+                via_cpi_caller(
+                    cpi_caller_id,
+                    obligation_f.repay_liquidity_ix(
+                        repay_amount,
+                        &reserve_f,
+                        user_liquidity_ata.key,
+                    ),
+                ),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    // u64 repay_amount
+    assert_eq!(return_data.len(), 8);
+    let result = RepayObligationLiquidityResult::try_from_slice(&return_data).unwrap();
+    assert_eq!(result.repay_amount, repay_amount);
+    assert_eq!(
+        user_liquidity_ata.balance().await,
+        borrow_amount - repay_amount
+    );
+}
@@ -0,0 +1,367 @@
+#[cfg(test)]
+mod helpers;
+
+use cluster_lend::{
+    utils::{pda::lending_market_auth, Fraction},
+    InitObligationArgs, LendingMarket, Obligation, Reserve, RewardState, UpdateConfigMode,
+    UpdateLendingMarketMode,
+};
+use solana_program_test::*;
+
+use helpers::*;
+use lending_market::LendingMarketFixture;
+use obligation::ObligationFixture;
+use reserve::ReserveFixture;
+use solana_sdk::{compute_budget::ComputeBudgetInstruction, signature::Keypair};
+use spl::TokenAccountFixture;
+use test::{TestFixture, PYTH_USDC_FEED};
+
+fn reward_emission_per_slot_value(emission_per_slot: u64) -> [u8; 32] {
+    let mut value = [0; 32];
+    value[..8].copy_from_slice(&emission_per_slot.to_le_bytes());
+    value
+}
+
+fn reward_vault_value(reward_vault: &TokenAccountFixture) -> [u8; 72] {
+    let mut value = [0; 72];
+    value[..32].copy_from_slice(reward_vault.key.as_ref());
+    value
+}
+
+/// Funds a reward vault with 100_000_000 tokens and emits 1_000_000 of them per slot
+async fn setup_reward_vault(
+    test_f: &TestFixture,
+    market_f: &LendingMarketFixture,
+    reserve_f: &ReserveFixture,
+) -> TokenAccountFixture {
+    let reward_mint_f = test_f.sol_mint.clone();
+    let reward_vault_f = TokenAccountFixture::new(
+        test_f.context.clone(),
+        &reward_mint_f.key,
+        &lending_market_auth(&market_f.key),
+    )
+    .await;
+    test_f
+        .send_transaction(
+            &[
+                reward_mint_f.make_mint_to_ix(&reward_vault_f.key, 100_000_000),
+                market_f.update_market_ix(
+                    UpdateLendingMarketMode::UpdateRewardVault as u64,
+                    reward_vault_value(&reward_vault_f),
+                ),
+                reserve_f.update_reserve_mode_ix(
+                    UpdateConfigMode::UpdateRewardEmissionPerSlot as u64,
+                    reward_emission_per_slot_value(1_000_000),
+                ),
+            ],
+            &[&test_f.payer_keypair()],
+        )
+        .await
+        .unwrap();
+
+    reward_vault_f
+}
+
+#[test]
+fn reward_state_accrues_emission_per_deposited_collateral() {
+    let mut reward_state = RewardState::default();
+
+    // nothing is emitted while no collateral is deposited
+    reward_state.accrue(1_000, 10).unwrap();
+    assert_eq!(reward_state.reward_per_collateral_token(), Fraction::ZERO);
+
+    reward_state.deposit(2_000).unwrap();
+    reward_state.accrue(1_000, 10).unwrap();
+    assert_eq!(
+        reward_state.reward_per_collateral_token(),
+        Fraction::from(5u64)
+    );
+
+    reward_state.deposit(3_000).unwrap();
+    reward_state.accrue(1_000, 10).unwrap();
+    assert_eq!(
+        reward_state.reward_per_collateral_token(),
+        Fraction::from(7u64)
+    );
+
+    reward_state.withdraw(5_000).unwrap();
+    reward_state.accrue(1_000, 10).unwrap();
+    assert_eq!(
+        reward_state.reward_per_collateral_token(),
+        Fraction::from(7u64)
+    );
+}
+
+#[test]
+fn obligation_keeps_pending_rewards_across_withdrawals() {
+    let mut obligation = Obligation::default();
+
+    obligation
+        .add_reward_debt(2_000, Fraction::from(5u64))
+        .unwrap();
+    assert_eq!(
+        obligation
+            .pending_rewards(Fraction::from(2_000u64 * 7))
+            .unwrap(),
+        Fraction::from(4_000u64)
+    );
+
+    // withdrawing everything leaves the rewards earned until then to claim
+    obligation
+        .remove_reward_debt(2_000, Fraction::from(7u64))
+        .unwrap();
+    assert!(obligation.reward_debt_sf < 0);
+    assert_eq!(
+        obligation.pending_rewards(Fraction::ZERO).unwrap(),
+        Fraction::from(4_000u64)
+    );
+}
+
+#[tokio::test]
+async fn success_claim_rewards_accrued_over_slots() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let reward_mint_f = test_f.sol_mint.clone();
+    let (market_f, reserve_f, obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+
+    let reward_vault_f = setup_reward_vault(&test_f, &market_f, &reserve_f).await;
+    let market: LendingMarket = test_f.load_and_deserialize(&market_f.key).await;
+    assert_eq!(market.reward_vault, reward_vault_f.key);
+
+    let deposit_amount = 1_000_000;
+    let user_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&user, deposit_amount)
+        .await;
+    let user_reward_ata = reward_mint_f
+        .create_token_account_and_mint_to(&user, 0)
+        .await;
+
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_slot_and_time(100, 120);
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.deposit_liquidity_collateral_ix(
+                    deposit_amount,
+                    &reserve_f,
+                    user_liquidity_ata.key,
+                ),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+    let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+    assert_eq!(reserve.reward_state.deposited_collateral, deposit_amount);
+
+    let claim_ixs = || {
+        vec![
+            reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+            obligation_f.claim_rewards_ix(
+                vec![reserve_f.key],
+                reward_vault_f.key,
+                user_reward_ata.key,
+            ),
+        ]
+    };
+
+    // the only depositor earns the whole emission of the 10 slots
+    test_f.set_slot_and_time(110, 120);
+    test_f
+        .send_transaction(&claim_ixs(), &[&user])
+        .await
+        .unwrap();
+    assert_eq!(user_reward_ata.balance().await, 10_000_000);
+
+    // a second claim in the same slot has nothing left to pay
+    test_f
+        .send_transaction(
+            &[obligation_f.claim_rewards_ix(
+                vec![reserve_f.key],
+                reward_vault_f.key,
+                user_reward_ata.key,
+            )],
+            &[&user],
+        )
+        .await
+        .unwrap();
+    assert_eq!(user_reward_ata.balance().await, 10_000_000);
+
+    // withdrawing half the collateral keeps it earning the whole emission
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key]),
+                obligation_f.withdraw_collateral_and_redeem_ix(
+                    deposit_amount / 2,
+                    &reserve_f,
+                    user_liquidity_ata.key,
+                ),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+    let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+    assert_eq!(
+        reserve.reward_state.deposited_collateral,
+        deposit_amount / 2
+    );
+
+    // the compute limit keeps the signature apart from the first claim
+    test_f.set_slot_and_time(120, 120);
+    let mut ixs = vec![ComputeBudgetInstruction::set_compute_unit_limit(400_000)];
+    ixs.extend(claim_ixs());
+    test_f.send_transaction(&ixs, &[&user]).await.unwrap();
+    assert_eq!(user_reward_ata.balance().await, 20_000_000);
+    assert_eq!(reward_vault_f.balance().await, 80_000_000);
+}
+
+#[tokio::test]
+async fn legacy_deposit_earns_rewards_only_once_tracked() {
+    let legacy_user = Keypair::new();
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let reward_mint_f = test_f.sol_mint.clone();
+    let (market_f, reserve_f, legacy_obligation_f) =
+        test_f.setup(&legacy_user, &usdc_mint_f.key).await;
+    let obligation_f =
+        ObligationFixture::new(&test_f, &user, market_f.key, InitObligationArgs::default()).await;
+    let reward_vault_f = setup_reward_vault(&test_f, &market_f, &reserve_f).await;
+
+    let deposit_amount = 1_000_000;
+    let legacy_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&legacy_user, 2 * deposit_amount)
+        .await;
+    let user_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&user, deposit_amount)
+        .await;
+    let legacy_reward_ata = reward_mint_f
+        .create_token_account_and_mint_to(&legacy_user, 0)
+        .await;
+    let user_reward_ata = reward_mint_f
+        .create_token_account_and_mint_to(&user, 0)
+        .await;
+
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_slot_and_time(100, 120);
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                legacy_obligation_f.deposit_liquidity_collateral_ix(
+                    deposit_amount,
+                    &reserve_f,
+                    legacy_liquidity_ata.key,
+                ),
+            ],
+            &[&legacy_user],
+        )
+        .await
+        .unwrap();
+
+    // the deposit was made before rewards were tracked
+    test_f
+        .update_obligation_account(legacy_obligation_f.key, |obligation| {
+            obligation.deposits_reward_tracked = [0; 8];
+            obligation.reward_debt_sf = 0;
+        })
+        .await;
+    test_f
+        .update_reserve_account(reserve_f.key, |reserve| {
+            reserve.reward_state.deposited_collateral = 0;
+        })
+        .await;
+
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.deposit_liquidity_collateral_ix(
+                    deposit_amount,
+                    &reserve_f,
+                    user_liquidity_ata.key,
+                ),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+    let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+    assert_eq!(reserve.reward_state.deposited_collateral, deposit_amount);
+
+    // the untracked deposit earns nothing, the tracked one the whole emission
+    test_f.set_slot_and_time(110, 120);
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                legacy_obligation_f.claim_rewards_ix(
+                    vec![reserve_f.key],
+                    reward_vault_f.key,
+                    legacy_reward_ata.key,
+                ),
+                obligation_f.claim_rewards_ix(
+                    vec![reserve_f.key],
+                    reward_vault_f.key,
+                    user_reward_ata.key,
+                ),
+            ],
+            &[&legacy_user, &user],
+        )
+        .await
+        .unwrap();
+    assert_eq!(legacy_reward_ata.balance().await, 0);
+    assert_eq!(user_reward_ata.balance().await, 10_000_000);
+
+    // depositing again starts tracking the whole deposit, the compute limit keeps the
+    // signature apart from the first deposit
+    test_f
+        .send_transaction(
+            &[
+                ComputeBudgetInstruction::set_compute_unit_limit(300_000),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                legacy_obligation_f.deposit_liquidity_collateral_ix(
+                    deposit_amount,
+                    &reserve_f,
+                    legacy_liquidity_ata.key,
+                ),
+            ],
+            &[&legacy_user],
+        )
+        .await
+        .unwrap();
+    let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+    assert_eq!(
+        reserve.reward_state.deposited_collateral,
+        3 * deposit_amount
+    );
+    let obligation: Obligation = test_f.load_and_deserialize(&legacy_obligation_f.key).await;
+    assert!(obligation.is_deposit_reward_tracked(0));
+
+    // from then on it earns its share of the emission
+    test_f.set_slot_and_time(120, 120);
+    test_f
+        .send_transaction(
+            &[
+                ComputeBudgetInstruction::set_compute_unit_limit(400_000),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                legacy_obligation_f.claim_rewards_ix(
+                    vec![reserve_f.key],
+                    reward_vault_f.key,
+                    legacy_reward_ata.key,
+                ),
+            ],
+            &[&legacy_user],
+        )
+        .await
+        .unwrap();
+    let legacy_rewards = legacy_reward_ata.balance().await;
+    assert!((6_666_665..=6_666_666).contains(&legacy_rewards));
+}
@@ -0,0 +1,167 @@
+#[cfg(test)]
+mod helpers;
+
+use cluster_lend::{
+    errors::LendingError, LendingMarket, Reserve, ReserveStatus, UpdateConfigMode,
+    UpdateLendingMarketMode,
+};
+use solana_program_test::*;
+
+use helpers::*;
+use reserve::u64_value;
+use solana_sdk::{signature::Keypair, signer::Signer};
+use test::{TestFixture, TEST_RESERVE_CONFIG};
+
+fn emergency_mode_value(emergency_mode: u8) -> [u8; 72] {
+    let mut value = [0; 72];
+    value[0] = emergency_mode;
+    value
+}
+
+#[tokio::test]
+async fn success_risk_authority_tightens_reserve_and_market() {
+    let user = Keypair::new();
+    let risk_authority = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (market_f, reserve_f, _obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+
+    test_f
+        .send_transaction(
+            &[market_f.update_risk_authority_ix(risk_authority.pubkey())],
+            &[&test_f.payer_keypair()],
+        )
+        .await
+        .unwrap();
+
+    let market: LendingMarket = test_f.load_and_deserialize(&market_f.key).await;
+    assert_eq!(market.risk_authority, risk_authority.pubkey());
+
+    let deposit_limit = TEST_RESERVE_CONFIG.deposit_limit / 2;
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.update_reserve_mode_with_signer_ix(
+                    risk_authority.pubkey(),
+                    UpdateConfigMode::UpdateDepositLimit as u64,
+                    u64_value(deposit_limit),
+                ),
+                reserve_f.update_reserve_mode_with_signer_ix(
+                    risk_authority.pubkey(),
+                    UpdateConfigMode::UpdateDepositsDisabled as u64,
+                    u64_value(1),
+                ),
+                reserve_f.update_reserve_mode_with_signer_ix(
+                    risk_authority.pubkey(),
+                    UpdateConfigMode::UpdateReserveStatus as u64,
+                    u64_value(ReserveStatus::Obsolete as u64),
+                ),
+                market_f.update_market_with_signer_ix(
+                    risk_authority.pubkey(),
+                    UpdateLendingMarketMode::UpdateEmergencyMode as u64,
+                    emergency_mode_value(1),
+                ),
+            ],
+            &[&risk_authority],
+        )
+        .await
+        .unwrap();
+
+    let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+    assert_eq!(reserve.config.deposit_limit, deposit_limit);
+    assert!(reserve.config.is_deposits_disabled());
+    assert_eq!(reserve.config.status(), ReserveStatus::Obsolete);
+    let market: LendingMarket = test_f.load_and_deserialize(&market_f.key).await;
+    assert_eq!(market.emergency_mode, 1);
+
+    // the owner keeps full control
+    test_f
+        .send_transaction(
+            &[market_f.update_market_ix(
+                UpdateLendingMarketMode::UpdateEmergencyMode as u64,
+                emergency_mode_value(0),
+            )],
+            &[&test_f.payer_keypair()],
+        )
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn failure_risk_authority_loosens_config() {
+    let user = Keypair::new();
+    let risk_authority = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (market_f, reserve_f, _obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+
+    test_f
+        .send_transaction(
+            &[market_f.update_risk_authority_ix(risk_authority.pubkey())],
+            &[&test_f.payer_keypair()],
+        )
+        .await
+        .unwrap();
+
+    let err = test_f
+        .send_transaction(
+            &[reserve_f.update_reserve_mode_with_signer_ix(
+                risk_authority.pubkey(),
+                UpdateConfigMode::UpdateDepositLimit as u64,
+                u64_value(TEST_RESERVE_CONFIG.deposit_limit + 1),
+            )],
+            &[&risk_authority],
+        )
+        .await
+        .unwrap_err();
+    assert_custom_error!(err, LendingError::RiskAuthorityUpdateNotAllowed);
+
+    // modes outside the tightening subset are owner only
+    let err = test_f
+        .send_transaction(
+            &[reserve_f.update_reserve_mode_with_signer_ix(
+                risk_authority.pubkey(),
+                UpdateConfigMode::UpdateLoanToValuePct as u64,
+                u64_value(1),
+            )],
+            &[&risk_authority],
+        )
+        .await
+        .unwrap_err();
+    assert_custom_error!(err, LendingError::RiskAuthorityUpdateNotAllowed);
+
+    let err = test_f
+        .send_transaction(
+            &[market_f.update_market_with_signer_ix(
+                risk_authority.pubkey(),
+                UpdateLendingMarketMode::UpdateEmergencyMode as u64,
+                emergency_mode_value(0),
+            )],
+            &[&risk_authority],
+        )
+        .await
+        .unwrap_err();
+    assert_custom_error!(err, LendingError::RiskAuthorityUpdateNotAllowed);
+
+    let other = Keypair::new();
+    let err = test_f
+        .send_transaction(
+            &[reserve_f.update_reserve_mode_with_signer_ix(
+                other.pubkey(),
+                UpdateConfigMode::UpdateDepositLimit as u64,
+                u64_value(0),
+            )],
+            &[&other],
+        )
+        .await
+        .unwrap_err();
+    assert_custom_error!(err, LendingError::InvalidMarketOwner);
+
+    let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+    assert_eq!(
+        reserve.config.deposit_limit,
+        TEST_RESERVE_CONFIG.deposit_limit
+    );
+}
@@ -0,0 +1,206 @@
+#[cfg(test)]
+mod helpers;
+use std::rc::Rc;
+
+use cluster_lend::{errors::LendingError, utils::pda, UpdateConfigMode};
+use solana_program_test::*;
+
+use helpers::*;
+use solana_sdk::{signature::Keypair, signer::Signer};
+use spl::TokenAccountFixture;
+use test::{TestFixture, PYTH_USDC_FEED};
+
+#[cfg(feature = "staging")]
+#[tokio::test]
+async fn failure_self_liquidate_healthy_obligation_with_ltv_override() {
+    let user = Keypair::new();
+
+    // setup market & reserve/obligation
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (market_f, reserve_f, obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+    let reserve_pdas = pda::init_reserve_pdas(&market_f.key, &usdc_mint_f.key);
+
+    let deposit_amount = 1_000_000;
+    let user_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&user, deposit_amount)
+        .await;
+
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_time(120);
+
+    // 70% LTV against the 85% liquidation threshold
+    let borrow_amount = 700_000;
+    test_f
+        .send_transaction(
+            &[
+                obligation_f.deposit_liquidity_collateral_ix(
+                    deposit_amount,
+                    &reserve_f,
+                    user_liquidity_ata.key,
+                ),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key]),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+    test_f
+        .send_transaction(
+            &[
+                obligation_f.borrow_liquidity_ix(borrow_amount, &reserve_f, user_liquidity_ata.key),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    let user_destination_collateral = TokenAccountFixture::new(
+        Rc::clone(&test_f.context),
+        &reserve_pdas.collateral_ctoken_mint,
+        &user.pubkey(),
+    )
+    .await;
+    let user_destination_liquidity =
+        TokenAccountFixture::new(Rc::clone(&test_f.context), &usdc_mint_f.key, &user.pubkey())
+            .await;
+
+    // the owner overrides the max allowed LTV below its current LTV
+    let liquidity_amount = 100_000;
+    let r = test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+                obligation_f.liquidate_ix(
+                    liquidity_amount,
+                    50,
+                    0,
+                    0,
+                    user.pubkey(),
+                    reserve_f.key,
+                    reserve_pdas.liquidity_supply_vault,
+                    reserve_f.key,
+                    usdc_mint_f.key,
+                    user_destination_collateral.key,
+                    user_liquidity_ata.key,
+                    user_destination_liquidity.key,
+                ),
+            ],
+            &[&user],
+        )
+        .await;
+    assert!(r.is_err());
+    assert_eq!(user_liquidity_ata.balance().await, borrow_amount);
+}
+
+#[cfg(not(feature = "staging"))]
+#[tokio::test]
+async fn failure_self_liquidate_unhealthy_obligation() {
+    let user = Keypair::new();
+
+    // setup market & reserve/obligation
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (market_f, reserve_f, obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+    let reserve_pdas = pda::init_reserve_pdas(&market_f.key, &usdc_mint_f.key);
+
+    let deposit_amount = 1_000_000;
+    let user_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&user, deposit_amount)
+        .await;
+
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_time(120);
+
+    let borrow_amount = 700_000;
+    test_f
+        .send_transaction(
+            &[
+                obligation_f.deposit_liquidity_collateral_ix(
+                    deposit_amount,
+                    &reserve_f,
+                    user_liquidity_ata.key,
+                ),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key]),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+    test_f
+        .send_transaction(
+            &[
+                obligation_f.borrow_liquidity_ix(borrow_amount, &reserve_f, user_liquidity_ata.key),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    // make the obligation unhealthy
+    let mut loan_to_value_pct = [0; 32];
+    loan_to_value_pct[0] = 50;
+    let mut liquidation_threshold_pct = [0; 32];
+    liquidation_threshold_pct[0] = 60;
+    test_f
+        .send_transaction(
+            &[
+                reserve_f.update_reserve_mode_ix(
+                    UpdateConfigMode::UpdateLoanToValuePct as u64,
+                    loan_to_value_pct,
+                ),
+                reserve_f.update_reserve_mode_ix(
+                    UpdateConfigMode::UpdateLiquidationThresholdPct as u64,
+                    liquidation_threshold_pct,
+                ),
+            ],
+            &[&test_f.payer_keypair()],
+        )
+        .await
+        .unwrap();
+
+    let user_destination_collateral = TokenAccountFixture::new(
+        Rc::clone(&test_f.context),
+        &reserve_pdas.collateral_ctoken_mint,
+        &user.pubkey(),
+    )
+    .await;
+    let user_destination_liquidity =
+        TokenAccountFixture::new(Rc::clone(&test_f.context), &usdc_mint_f.key, &user.pubkey())
+            .await;
+
+    // the owner cannot collect the liquidation bonus on their own obligation
+    let err = test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+                obligation_f.liquidate_ix(
+                    100_000,
+                    0,
+                    0,
+                    0,
+                    user.pubkey(),
+                    reserve_f.key,
+                    reserve_pdas.liquidity_supply_vault,
+                    reserve_f.key,
+                    usdc_mint_f.key,
+                    user_destination_collateral.key,
+                    user_liquidity_ata.key,
+                    user_destination_liquidity.key,
+                ),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap_err();
+    assert_custom_error!(err, LendingError::SelfLiquidationForbidden);
+    assert_eq!(user_liquidity_ata.balance().await, borrow_amount);
+}
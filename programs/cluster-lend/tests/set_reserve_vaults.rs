@@ -0,0 +1,98 @@
+#[cfg(test)]
+mod helpers;
+use std::rc::Rc;
+
+use cluster_lend::{
+    errors::LendingError,
+    utils::pda::{self, lending_market_auth},
+    Reserve, ReserveStatus, UpdateConfigMode,
+};
+use solana_program_test::*;
+
+use helpers::*;
+use reserve::reserve_status_value;
+use solana_sdk::{compute_budget::ComputeBudgetInstruction, signature::Keypair, signer::Signer};
+use spl::TokenAccountFixture;
+use test::TestFixture;
+
+#[tokio::test]
+async fn success_set_reserve_vaults_on_hidden_reserve() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (market_f, reserve_f, _obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+    let reserve_pdas = pda::init_reserve_pdas(&market_f.key, &usdc_mint_f.key);
+    let owner = test_f.payer_keypair();
+    let lending_market_authority = lending_market_auth(&market_f.key);
+
+    let new_token_account =
+        |mint, authority| TokenAccountFixture::new(Rc::clone(&test_f.context), mint, authority);
+    let liquidity_supply_vault =
+        new_token_account(&usdc_mint_f.key, &lending_market_authority).await;
+    let fee_vault = new_token_account(&usdc_mint_f.key, &lending_market_authority).await;
+    let collateral_supply_vault = new_token_account(
+        &reserve_pdas.collateral_ctoken_mint,
+        &lending_market_authority,
+    )
+    .await;
+
+    // an active reserve keeps its vaults
+    let err = test_f
+        .send_transaction(
+            &[reserve_f.set_reserve_vaults_ix(
+                liquidity_supply_vault.key,
+                fee_vault.key,
+                collateral_supply_vault.key,
+            )],
+            &[&owner],
+        )
+        .await
+        .unwrap_err();
+    assert_custom_error!(err, LendingError::ReserveNotHidden);
+
+    test_f
+        .send_transaction(
+            &[reserve_f.update_reserve_mode_ix(
+                UpdateConfigMode::UpdateReserveStatus as u64,
+                reserve_status_value(ReserveStatus::Hidden),
+            )],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+
+    // vaults must be owned by the market authority
+    let user_liquidity_ata = new_token_account(&usdc_mint_f.key, &user.pubkey()).await;
+    let r = test_f
+        .send_transaction(
+            &[reserve_f.set_reserve_vaults_ix(
+                user_liquidity_ata.key,
+                fee_vault.key,
+                collateral_supply_vault.key,
+            )],
+            &[&owner],
+        )
+        .await;
+    assert!(r.is_err());
+
+    test_f
+        .send_transaction(
+            &[
+                ComputeBudgetInstruction::set_compute_unit_limit(400_000),
+                reserve_f.set_reserve_vaults_ix(
+                    liquidity_supply_vault.key,
+                    fee_vault.key,
+                    collateral_supply_vault.key,
+                ),
+            ],
+            &[&owner],
+        )
+        .await
+        .unwrap();
+
+    let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+    assert_eq!(reserve.liquidity.supply_vault, liquidity_supply_vault.key);
+    assert_eq!(reserve.liquidity.fee_vault, fee_vault.key);
+    assert_eq!(reserve.collateral.supply_vault, collateral_supply_vault.key);
+}
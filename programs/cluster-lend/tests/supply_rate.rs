@@ -0,0 +1,110 @@
+#[cfg(test)]
+mod helpers;
+
+use cluster_lend::{
+    constants::SLOTS_PER_YEAR,
+    errors::LendingError,
+    utils::{BorrowRateCurve, Fraction, FractionExtra},
+    ReserveConfig,
+};
+
+use helpers::*;
+use reserve::reserve_with_utilization;
+
+const BORROW_RATE_BPS: u32 = 1_000;
+
+fn flat_rate_config() -> ReserveConfig {
+    ReserveConfig {
+        borrow_rate_curve: BorrowRateCurve::new_flat(BORROW_RATE_BPS),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn supply_rate_uses_configured_take_rate() {
+    let mut reserve = reserve_with_utilization(flat_rate_config(), 1_000_000, 500_000);
+    reserve.config.protocol_take_rate_pct = 10;
+    reserve.config.min_supply_borrow_spread_bps = 500;
+
+    // 10% * 50% utilization * 90% left to suppliers
+    assert_eq!(
+        reserve.protocol_take_rate().unwrap(),
+        Fraction::from_percent(10)
+    );
+    let supply_rate = reserve.current_supply_rate().unwrap();
+    assert_eq!(supply_rate.to_bps::<u64>().unwrap(), 450);
+}
+
+#[test]
+fn supply_rate_is_clamped_below_borrow_rate() {
+    // protocol fees count towards the debt but not the supply, pushing utilization to 125%
+    let mut reserve = reserve_with_utilization(flat_rate_config(), 1_000_000, 1_000_000);
+    reserve.liquidity.accumulated_protocol_fees_sf = Fraction::from(200_000u64).to_bits();
+    assert_eq!(
+        reserve.liquidity.utilization_rate().unwrap(),
+        Fraction::from_percent(125)
+    );
+
+    for (protocol_take_rate_pct, min_supply_borrow_spread_bps) in
+        [(0, 0), (0, 500), (15, 500), (100, 500), (0, 10_000)]
+    {
+        let mut reserve = reserve;
+        reserve.config.protocol_take_rate_pct = protocol_take_rate_pct;
+        reserve.config.min_supply_borrow_spread_bps = min_supply_borrow_spread_bps;
+
+        let borrow_rate = reserve.current_borrow_rate().unwrap();
+        let supply_rate = reserve.current_supply_rate().unwrap();
+        let max_supply_rate =
+            borrow_rate * (Fraction::ONE - Fraction::from_bps(min_supply_borrow_spread_bps));
+        assert!(supply_rate <= max_supply_rate);
+
+        // the take rate is never lowered, only raised
+        assert!(
+            reserve.protocol_take_rate().unwrap() >= Fraction::from_percent(protocol_take_rate_pct)
+        );
+    }
+
+    // without a take rate suppliers would earn 12.5% on a 10% borrow rate
+    let mut reserve = reserve;
+    reserve.config.min_supply_borrow_spread_bps = 500;
+    assert_eq!(
+        reserve
+            .current_supply_rate()
+            .unwrap()
+            .to_bps::<u64>()
+            .unwrap(),
+        950
+    );
+}
+
+#[test]
+fn total_supply_rejects_fees_above_liquidity() {
+    let mut reserve = reserve_with_utilization(flat_rate_config(), 1_000_000, 500_000);
+    reserve.liquidity.accumulated_protocol_fees_sf = Fraction::from(1_000_001u64).to_bits();
+    assert!(matches!(
+        reserve.liquidity.total_supply(),
+        Err(LendingError::MathOverflow)
+    ));
+}
+
+#[test]
+fn full_take_rate_accrual_does_not_underflow() {
+    // fully utilized, every bit of interest goes to the protocol
+    let mut reserve = reserve_with_utilization(flat_rate_config(), 1_000_000, 1_000_000);
+    reserve.config.protocol_take_rate_pct = 100;
+
+    let slots_per_accrual = SLOTS_PER_YEAR / 12;
+    for step in 1..=120 {
+        let slot = step * slots_per_accrual;
+        reserve.accrue_interest(slot).unwrap();
+        reserve.last_update.update_slot(slot, None);
+
+        let liquidity = &reserve.liquidity;
+        assert!(
+            Fraction::from_bits(liquidity.accumulated_protocol_fees_sf)
+                <= Fraction::from(liquidity.available_amount) + liquidity.total_borrow()
+        );
+        assert!(liquidity.total_supply().unwrap() >= Fraction::from(999_999u64));
+    }
+    assert!(reserve.liquidity.total_borrow() > Fraction::from(2_000_000u64));
+}
@@ -0,0 +1,148 @@
+#[cfg(test)]
+mod helpers;
+
+use cluster_lend::{
+    errors::LendingError, utils::Fraction, InitObligationArgs, Obligation, PriceStatusFlags,
+};
+use obligation::ObligationFixture;
+use solana_program_test::*;
+
+use helpers::*;
+use solana_sdk::signature::Keypair;
+use test::{TestFixture, PYTH_USDC_FEED};
+
+#[tokio::test]
+async fn success_transfer_collateral_between_obligations() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (market_f, reserve_f, source_obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+    // a second obligation of the same owner, scoped to the usdc mint
+    let destination_obligation_f = ObligationFixture::new_with_seeds(
+        &test_f,
+        &user,
+        market_f.key,
+        InitObligationArgs {
+            tag: 2,
+            ..InitObligationArgs::default()
+        },
+        usdc_mint_f.key,
+        usdc_mint_f.key,
+    )
+    .await;
+
+    let deposit_amount = 1_000_000;
+    let user_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&user, deposit_amount)
+        .await;
+
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_slot_and_time(1, 120);
+
+    test_f
+        .send_transaction(
+            &[
+                source_obligation_f.deposit_liquidity_collateral_ix(
+                    deposit_amount,
+                    &reserve_f,
+                    user_liquidity_ata.key,
+                ),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                source_obligation_f.refresh_ix(vec![reserve_f.key]),
+                source_obligation_f.borrow_liquidity_ix(
+                    300_000,
+                    &reserve_f,
+                    user_liquidity_ata.key,
+                ),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    let transfer_ixs = |collateral_amount: u64| {
+        vec![
+            reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+            source_obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+            source_obligation_f.transfer_collateral_ix(
+                collateral_amount,
+                &reserve_f,
+                destination_obligation_f.key,
+            ),
+            reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+            source_obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+            destination_obligation_f.refresh_ix(vec![reserve_f.key]),
+        ]
+    };
+
+    // the source keeps enough collateral for its debt, no token leaves the reserve
+    test_f
+        .send_transaction(&transfer_ixs(400_000), &[&user])
+        .await
+        .unwrap();
+    assert_eq!(user_liquidity_ata.balance().await, 300_000);
+
+    let source: Obligation = test_f.load_and_deserialize(&source_obligation_f.key).await;
+    let destination: Obligation = test_f
+        .load_and_deserialize(&destination_obligation_f.key)
+        .await;
+    assert_eq!(source.deposits[0].deposited_amount, 600_000);
+    assert_eq!(destination.deposits[0].deposited_amount, 400_000);
+    assert_eq!(destination.deposits[0].deposit_reserve, reserve_f.key);
+    assert_eq!(
+        Fraction::from_bits(source.deposited_value_sf),
+        Fraction::from(600_000u64) / 1_000_000u128
+    );
+    assert_eq!(
+        Fraction::from_bits(destination.deposited_value_sf),
+        Fraction::from(400_000u64) / 1_000_000u128
+    );
+    assert!(!source
+        .last_update
+        .is_stale(1, PriceStatusFlags::ALL_CHECKS)
+        .unwrap());
+    assert!(!destination
+        .last_update
+        .is_stale(1, PriceStatusFlags::ALL_CHECKS)
+        .unwrap());
+
+    // moving more would leave the source debt undercollateralized
+    let err = test_f
+        .send_transaction(&transfer_ixs(300_000), &[&user])
+        .await
+        .unwrap_err();
+    assert_custom_error!(err, LendingError::WithdrawTooLarge);
+}
+
+#[tokio::test]
+async fn failure_transfer_collateral_to_same_obligation() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (_market_f, reserve_f, obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+
+    let deposit_amount = 1_000_000;
+    let user_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&user, deposit_amount)
+        .await;
+
+    let err = test_f
+        .send_transaction(
+            &[
+                obligation_f.deposit_liquidity_collateral_ix(
+                    deposit_amount,
+                    &reserve_f,
+                    user_liquidity_ata.key,
+                ),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key]),
+                obligation_f.transfer_collateral_ix(100_000, &reserve_f, obligation_f.key),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap_err();
+    assert_custom_error!(err, LendingError::InvalidAccountInput);
+}
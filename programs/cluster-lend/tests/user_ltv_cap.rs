@@ -0,0 +1,98 @@
+#[cfg(test)]
+mod helpers;
+
+use cluster_lend::{errors::LendingError, Obligation};
+use solana_program_test::*;
+
+use helpers::*;
+use solana_sdk::{compute_budget::ComputeBudgetInstruction, signature::Keypair};
+use test::{TestFixture, PYTH_USDC_FEED};
+
+#[tokio::test]
+async fn success_user_ltv_cap_blocks_borrow_within_protocol_ltv() {
+    let user = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (_market_f, reserve_f, obligation_f) = test_f.setup(&user, &usdc_mint_f.key).await;
+
+    let deposit_amount = 10_000_000;
+    let user_liquidity_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&user, deposit_amount)
+        .await;
+
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.set_time(120);
+
+    let err = test_f
+        .send_transaction(&[obligation_f.set_user_ltv_cap_ix(100)], &[&user])
+        .await
+        .unwrap_err();
+    assert_custom_error!(err, LendingError::InvalidUserLtvCap);
+
+    test_f
+        .send_transaction(
+            &[
+                obligation_f.set_user_ltv_cap_ix(50),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.deposit_liquidity_collateral_ix(
+                    deposit_amount,
+                    &reserve_f,
+                    user_liquidity_ata.key,
+                ),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    let obligation: Obligation = test_f.load_and_deserialize(&obligation_f.key).await;
+    assert_eq!(obligation.user_ltv_cap_pct, 50);
+
+    // 60% is within the reserve's 75% LTV but past the owner's 50% cap
+    let err = test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key]),
+                obligation_f.borrow_liquidity_ix(6_000_000, &reserve_f, user_liquidity_ata.key),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap_err();
+    assert_custom_error!(err, LendingError::BorrowTooLarge);
+    assert_eq!(user_liquidity_ata.balance().await, 0);
+
+    test_f
+        .send_transaction(
+            &[
+                ComputeBudgetInstruction::set_compute_unit_limit(400_000),
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key]),
+                obligation_f.borrow_liquidity_ix(5_000_000, &reserve_f, user_liquidity_ata.key),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap();
+    assert_eq!(user_liquidity_ata.balance().await, 5_000_000);
+
+    // with the cap reached no collateral can be withdrawn, though the reserve LTV would allow it
+    let err = test_f
+        .send_transaction(
+            &[
+                reserve_f.refresh_ix(Some(PYTH_USDC_FEED)),
+                obligation_f.refresh_ix(vec![reserve_f.key, reserve_f.key]),
+                obligation_f.withdraw_collateral_and_redeem_ix(
+                    1_000_000,
+                    &reserve_f,
+                    user_liquidity_ata.key,
+                ),
+            ],
+            &[&user],
+        )
+        .await
+        .unwrap_err();
+    assert_custom_error!(err, LendingError::WithdrawTooLarge);
+}
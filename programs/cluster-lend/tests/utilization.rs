@@ -0,0 +1,82 @@
+#[cfg(test)]
+mod helpers;
+
+use cluster_lend::{
+    constants::{UTILIZATION_SNAPSHOTS_LEN, UTILIZATION_SNAPSHOT_INTERVAL_SLOTS},
+    lending_market::lending_operations::get_utilization,
+    utils::Fraction,
+    Reserve,
+};
+
+use helpers::*;
+use reserve::reserve_with_debt;
+
+#[test]
+fn get_utilization_current_rate() {
+    let reserve = Reserve::default();
+    let utilization = get_utilization(&reserve).unwrap();
+    assert_eq!(utilization.utilization_rate_bps, 0);
+    assert!(utilization.snapshots.is_empty());
+
+    let reserve = reserve_with_debt(1_000_000, 0);
+    assert_eq!(get_utilization(&reserve).unwrap().utilization_rate_bps, 0);
+
+    let reserve = reserve_with_debt(1_000_000, 500_000);
+    assert_eq!(
+        get_utilization(&reserve).unwrap().utilization_rate_bps,
+        5_000
+    );
+
+    let reserve = reserve_with_debt(1_000_000, 999_900);
+    assert_eq!(
+        get_utilization(&reserve).unwrap().utilization_rate_bps,
+        9_999
+    );
+}
+
+#[test]
+fn get_utilization_snapshots() {
+    let mut reserve = reserve_with_debt(1_000_000, 500_000);
+    let first_slot = 1_000;
+
+    reserve.record_utilization_snapshot(first_slot).unwrap();
+    // at most one sample is taken per interval
+    reserve.record_utilization_snapshot(first_slot + 1).unwrap();
+
+    reserve
+        .liquidity
+        .borrow(Fraction::from(499_900u64))
+        .unwrap();
+    reserve
+        .record_utilization_snapshot(first_slot + UTILIZATION_SNAPSHOT_INTERVAL_SLOTS)
+        .unwrap();
+
+    let utilization = get_utilization(&reserve).unwrap();
+    assert_eq!(utilization.utilization_rate_bps, 9_999);
+    assert_eq!(utilization.snapshots.len(), 2);
+    assert_eq!(utilization.snapshots[0].slot, first_slot);
+    assert_eq!(utilization.snapshots[0].utilization_rate_bps, 5_000);
+    assert_eq!(
+        utilization.snapshots[1].slot,
+        first_slot + UTILIZATION_SNAPSHOT_INTERVAL_SLOTS
+    );
+    assert_eq!(utilization.snapshots[1].utilization_rate_bps, 9_999);
+
+    // the oldest samples are overwritten once the buffer is full
+    for i in 2..=UTILIZATION_SNAPSHOTS_LEN as u64 {
+        reserve
+            .record_utilization_snapshot(first_slot + i * UTILIZATION_SNAPSHOT_INTERVAL_SLOTS)
+            .unwrap();
+    }
+
+    let snapshots = get_utilization(&reserve).unwrap().snapshots;
+    assert_eq!(snapshots.len(), UTILIZATION_SNAPSHOTS_LEN);
+    assert_eq!(
+        snapshots[0].slot,
+        first_slot + UTILIZATION_SNAPSHOT_INTERVAL_SLOTS
+    );
+    assert_eq!(
+        snapshots[UTILIZATION_SNAPSHOTS_LEN - 1].slot,
+        first_slot + UTILIZATION_SNAPSHOTS_LEN as u64 * UTILIZATION_SNAPSHOT_INTERVAL_SLOTS
+    );
+}
@@ -0,0 +1,121 @@
+#[cfg(test)]
+mod helpers;
+
+use cluster_lend::{utils::pda, Reserve, UpdateConfigMode};
+use solana_program_test::*;
+
+use helpers::*;
+use solana_sdk::{signature::Keypair, signer::Signer};
+use spl::balance_of;
+use test::TestFixture;
+
+#[tokio::test]
+async fn success_withdraw_protocol_fees_to_treasury() {
+    let user = Keypair::new();
+    let treasury = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (market_f, reserve_f, _) = test_f.setup(&user, &usdc_mint_f.key).await;
+    let reserve_pdas = pda::init_reserve_pdas(&market_f.key, &usdc_mint_f.key);
+
+    let fees = 1_000;
+    test_f
+        .send_transaction(
+            &[usdc_mint_f.make_mint_to_ix(&reserve_pdas.fee_vault, fees)],
+            &[&test_f.payer_keypair()],
+        )
+        .await
+        .unwrap();
+
+    test_f
+        .send_transaction(
+            &[reserve_f.update_reserve_mode_ix(
+                UpdateConfigMode::UpdateProtocolFeeReceiver as u64,
+                treasury.pubkey().to_bytes(),
+            )],
+            &[&test_f.payer_keypair()],
+        )
+        .await
+        .unwrap();
+
+    let reserve: Reserve = test_f.load_and_deserialize(&reserve_f.key).await;
+    assert_eq!(reserve.config.protocol_fee_receiver, treasury.pubkey());
+
+    let treasury_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&treasury, 0)
+        .await;
+
+    test_f
+        .send_transaction(
+            &[reserve_f.withdraw_protocol_fees_ix(treasury_ata.key, fees)],
+            &[&test_f.payer_keypair()],
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(treasury_ata.balance().await, fees);
+    assert_eq!(
+        balance_of(test_f.context.clone(), reserve_pdas.fee_vault).await,
+        0
+    );
+}
+
+#[tokio::test]
+async fn failure_withdraw_protocol_fees_wrong_destination() {
+    let user = Keypair::new();
+    let treasury = Keypair::new();
+
+    let test_f = TestFixture::new().await;
+    let usdc_mint_f = test_f.usdc_mint.clone();
+    let (market_f, reserve_f, _) = test_f.setup(&user, &usdc_mint_f.key).await;
+    let reserve_pdas = pda::init_reserve_pdas(&market_f.key, &usdc_mint_f.key);
+
+    let fees = 1_000;
+    test_f
+        .send_transaction(
+            &[usdc_mint_f.make_mint_to_ix(&reserve_pdas.fee_vault, fees)],
+            &[&test_f.payer_keypair()],
+        )
+        .await
+        .unwrap();
+
+    let owner_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&test_f.payer_keypair(), 0)
+        .await;
+    let treasury_ata = usdc_mint_f
+        .create_token_account_and_mint_to(&treasury, 0)
+        .await;
+
+    // without a receiver configured fees only go to the market owner
+    let r = test_f
+        .send_transaction(
+            &[reserve_f.withdraw_protocol_fees_ix(treasury_ata.key, fees)],
+            &[&test_f.payer_keypair()],
+        )
+        .await;
+    assert!(r.is_err());
+
+    test_f
+        .send_transaction(
+            &[reserve_f.update_reserve_mode_ix(
+                UpdateConfigMode::UpdateProtocolFeeReceiver as u64,
+                treasury.pubkey().to_bytes(),
+            )],
+            &[&test_f.payer_keypair()],
+        )
+        .await
+        .unwrap();
+
+    // once set, the owner can no longer route fees to itself
+    let r = test_f
+        .send_transaction(
+            &[reserve_f.withdraw_protocol_fees_ix(owner_ata.key, fees)],
+            &[&test_f.payer_keypair()],
+        )
+        .await;
+    assert!(r.is_err());
+
+    assert_eq!(owner_ata.balance().await, 0);
+    assert_eq!(treasury_ata.balance().await, 0);
+}
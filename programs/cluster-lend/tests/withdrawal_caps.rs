@@ -0,0 +1,54 @@
+use cluster_lend::{
+    errors::LendingError,
+    lending_market::withdrawal_operations::utils::{
+        add_to_withdrawal_accum, sub_from_withdrawal_accum,
+    },
+    WithdrawalCaps,
+};
+
+const CAPACITY: i64 = 1_000;
+const INTERVAL_LENGTH_SECONDS: u64 = 100;
+
+fn caps() -> WithdrawalCaps {
+    WithdrawalCaps {
+        config_capacity: CAPACITY,
+        current_total: 0,
+        last_interval_start_timestamp: 1_000,
+        config_interval_length_seconds: INTERVAL_LENGTH_SECONDS,
+    }
+}
+
+#[test]
+fn withdrawal_cap_is_not_replenished_mid_interval() {
+    let mut caps = caps();
+    add_to_withdrawal_accum(&mut caps, CAPACITY as u64, 1_010).unwrap();
+
+    let err = add_to_withdrawal_accum(&mut caps, 1, 1_099).unwrap_err();
+    assert!(matches!(err, LendingError::WithdrawalCapReached));
+    assert_eq!(caps.current_total, CAPACITY);
+    assert_eq!(caps.last_interval_start_timestamp, 1_000);
+
+    // deposits only free up what they put back
+    sub_from_withdrawal_accum(&mut caps, 300, 1_099).unwrap();
+    assert_eq!(caps.current_total, CAPACITY - 300);
+}
+
+#[test]
+fn withdrawal_cap_is_replenished_after_elapsed_intervals() {
+    let mut caps = caps();
+    add_to_withdrawal_accum(&mut caps, CAPACITY as u64, 1_010).unwrap();
+
+    // two and a half intervals later a deposit finds a fresh window
+    sub_from_withdrawal_accum(&mut caps, 300, 1_250).unwrap();
+    assert_eq!(caps.current_total, -300);
+    assert_eq!(caps.last_interval_start_timestamp, 1_200);
+
+    // the window stays aligned to whole intervals
+    add_to_withdrawal_accum(&mut caps, CAPACITY as u64, 1_299).unwrap();
+    let err = add_to_withdrawal_accum(&mut caps, 301, 1_299).unwrap_err();
+    assert!(matches!(err, LendingError::WithdrawalCapReached));
+
+    add_to_withdrawal_accum(&mut caps, CAPACITY as u64, 1_300).unwrap();
+    assert_eq!(caps.current_total, CAPACITY);
+    assert_eq!(caps.last_interval_start_timestamp, 1_300);
+}